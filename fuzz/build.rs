@@ -0,0 +1,52 @@
+//! Collects the keywords and punctuation of the lexer's `Token` set into
+//! the `FRAGMENTS` list of the fuzz target, so that the two never drift
+//! apart
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Source file holding the `Token` enum
+const LEXER: &str = "../src/lib.rs";
+
+/// Fragments matched by a regex rather than a fixed token, added by hand
+const EXTRA: &[&str] = &[" ", "\t", "$display", "$finish"];
+
+fn main() {
+    println!("cargo:rerun-if-changed={LEXER}");
+
+    let source = fs::read_to_string(LEXER).expect("lexer source is readable");
+    let mut fragments: Vec<String> = source
+        .lines()
+        .filter_map(|line| {
+            let text = line.trim().strip_prefix("#[token(\"")?;
+            Some(unescape(text.strip_suffix("\")]")?))
+        })
+        .collect();
+    fragments.extend(EXTRA.iter().map(|fragment| fragment.to_string()));
+
+    let out = Path::new(&env::var("OUT_DIR").expect("OUT_DIR is set")).join("fragments.rs");
+    fs::write(out, format!("const FRAGMENTS: &[&str] = &{fragments:?};\n"))
+        .expect("fragment list is writable");
+}
+
+/// Resolves the escape sequences of a string literal
+fn unescape(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+
+    out
+}
@@ -0,0 +1,13 @@
+#!/bin/sh
+# Writes the 1000 seeds the parse_sv fuzz target starts from to
+# fuzz/corpus/parse_sv. Seeds are pseudo-random byte strings of 32 to 512
+# bytes, the same on every run, and seeds already in the corpus are kept
+set -e
+cd "$(dirname "$0")"
+mkdir -p corpus/parse_sv
+
+for seed in $(seq 1 1000); do
+    for block in $(seq 1 $((seed % 16 + 1))); do
+        printf '%s-%s' "$seed" "$block" | sha256sum | cut -c1-64
+    done | xxd -r -p > "corpus/parse_sv/seed-$seed"
+done
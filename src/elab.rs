@@ -0,0 +1,1022 @@
+use crate::eval::{
+    collect_reads, eval_expr, expr_reads, expr_width, package_item, ConstLookup, ValueLookup,
+};
+use crate::expr::Expr;
+use crate::function::FunctionDef;
+use crate::generate::{GenBlock, GenBody};
+use crate::interface::InterfacePort;
+use crate::module::{
+    check_import_conflicts, local_names, Module, ModuleInst, ParamOverride, PortConn, PortDirection,
+};
+use crate::param::{Param, ParamType};
+use crate::preprocess::Preprocessor;
+use crate::sim::stmt_reads;
+use crate::sim_time::SimTime;
+use crate::value::LogicValue;
+use crate::var_types::Var;
+use crate::{parse_sv_path, ElabError, SimConfig, SimError, SimObject};
+use log::{debug, error, trace, warn};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Deepest a module may be nested below another instance of itself, which
+/// stops a module instantiating itself with ever different parameters
+pub const MAX_RECURSION_DEPTH: usize = 64;
+
+/// Design hierarchy resolved from a top-level module
+#[derive(Debug, Clone)]
+pub struct ElaboratedDesign {
+    /// Top-level module instance, the root of the hierarchy
+    pub top: ModuleInstance,
+
+    /// Modules instantiated in the hierarchy, by name
+    pub mods: HashMap<String, Module>,
+
+    /// Simulation timing information
+    pub sim_time: SimTime,
+
+    /// Configuration the design was elaborated with, which it is also
+    /// simulated with
+    pub config: SimConfig,
+}
+
+/// Designs are equal when their hierarchies are, as the modules are those
+/// the hierarchy instantiates
+impl PartialEq for ElaboratedDesign {
+    fn eq(&self, other: &Self) -> bool {
+        self.top == other.top
+    }
+}
+
+/// Instance of a module within an elaborated design
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleInstance {
+    /// Name of the instantiated module
+    pub module_name: String,
+
+    /// Instance name, which is the module name for the top-level module
+    pub inst_name: String,
+
+    /// Hierarchical instance path, as in `top.u_cpu.u_alu`
+    pub path: String,
+
+    /// Values of the module's parameters, with the overrides the instance
+    /// gives applied
+    pub params: HashMap<String, LogicValue>,
+
+    /// Signals of the parent connected to the instance's ports, as
+    /// `(port, signal)` in connection order, leaving out unconnected ports
+    /// and ports typed as interfaces
+    pub connections: Vec<(String, Expr)>,
+
+    /// Sub-module instances, in source order, leaving out instances of
+    /// interfaces
+    pub children: Vec<ModuleInstance>,
+}
+
+impl ModuleInstance {
+    /// Iterates over this instance and every instance below it, parents
+    /// before their children
+    pub fn iter(&self) -> impl Iterator<Item = &ModuleInstance> {
+        let mut stack = vec![self];
+
+        std::iter::from_fn(move || {
+            let inst = stack.pop()?;
+            stack.extend(inst.children.iter().rev());
+            Some(inst)
+        })
+    }
+
+    /// Number of levels in the hierarchy below and including this instance,
+    /// which is 1 for an instance with no children
+    pub fn depth(&self) -> u64 {
+        1 + self
+            .children
+            .iter()
+            .map(ModuleInstance::depth)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+impl SimObject {
+    /// Picks the top-level module of the design
+    ///
+    /// The module named `top_module` is used if one is given. Otherwise the
+    /// only module not instantiated by another is used, failing with
+    /// `ElabError::AmbiguousTopModule` if there are several
+    pub fn find_top_module(&self, top_module: Option<&str>) -> Result<&Module, ElabError> {
+        let Some(name) = top_module else {
+            let tops = self.top_modules();
+
+            return match tops.as_slice() {
+                [] => Err(ElabError::NoTopModule),
+                [top] => Ok(top),
+                _ => Err(ElabError::AmbiguousTopModule(
+                    tops.iter().map(|top| top.name.to_owned()).collect(),
+                )),
+            };
+        };
+
+        self.mods.get(name).ok_or_else(|| {
+            error!("top-level module '{}' is not defined", name);
+            ElabError::UndefinedModule(name.to_owned())
+        })
+    }
+
+    /// Resolves the design hierarchy below the module named `top_module`,
+    /// using the default configuration
+    ///
+    /// See `elaborate_with_config` for the checks made
+    pub fn elaborate(&self, top_module: &str) -> Result<ElaboratedDesign, ElabError> {
+        self.elaborate_with_config(top_module, &SimConfig::default())
+    }
+
+    /// Resolves the design hierarchy below the module named `top_module`
+    ///
+    /// Every instantiated module must be defined in the object, otherwise
+    /// `ElabError::UndefinedModule` is returned. A signal connected to a port
+    /// of a different width returns `ElabError::PortWidthMismatch`, or only
+    /// warns if `config.allow_implicit_truncation` is set. A port left
+    /// unconnected, by omitting it or with an empty connection such as
+    /// `.clk()`, warns, or returns `ElabError::UnconnectedPort` if
+    /// `config.deny_unconnected_ports` is set or the port is an interface
+    /// port. A port typed as an interface
+    /// must be connected to an instance or port of that interface, otherwise
+    /// `ElabError::InterfaceMismatch` is returned, and any modport it names
+    /// must be declared by the interface, otherwise
+    /// `ElabError::UndefinedModport`. A module that instantiates itself,
+    /// directly or through other modules, returns
+    /// `ElabError::CircularDependency`, unless it does so with different
+    /// parameter values, as a generate block ending the recursion would.
+    /// Such recursion may only be `MAX_RECURSION_DEPTH` instances deep.
+    ///
+    /// Package imports of every instantiated module are resolved, as by
+    /// `resolve_imports`
+    pub fn elaborate_with_config(
+        &self,
+        top_module: &str,
+        config: &SimConfig,
+    ) -> Result<ElaboratedDesign, ElabError> {
+        trace!("elaborating design from top module '{}'", top_module);
+
+        let resolved: HashMap<String, Result<Module, ElabError>> = self
+            .mods
+            .iter()
+            .map(|(name, module)| (name.to_owned(), self.resolve_imports(module)))
+            .collect();
+
+        let mut elaborator = Elaborator {
+            object: self,
+            mods: &resolved,
+            config,
+            stack: Vec::new(),
+        };
+        let module = elaborator.lookup_module(top_module)?;
+        let top = elaborator.elaborate_instance(module, top_module, top_module, &HashMap::new())?;
+        let mods = top
+            .iter()
+            .filter_map(|inst| match resolved.get_key_value(&inst.module_name) {
+                Some((name, Ok(module))) => Some((name.to_owned(), module.clone())),
+                _ => None,
+            })
+            .collect();
+
+        Ok(ElaboratedDesign {
+            top,
+            mods,
+            sim_time: self.sim_time,
+            config: config.clone(),
+        })
+    }
+
+    /// Checks the width of each signal connected to the ports of an
+    /// elaborated instance of `module`, where `scope` is the module that
+    /// instantiates it
+    ///
+    /// The widths of the ports are those `module` has with the parameter
+    /// values of `inst`, and the signals are those `scope` declares, with
+    /// its generate constructs already unrolled. Each connection of a
+    /// different width returns a `SimError::PortWidthMismatch`, and
+    /// connections whose width is not known are skipped, as during
+    /// elaboration
+    pub fn check_port_compatibility(
+        inst: &ModuleInstance,
+        module: &Module,
+        scope: &Module,
+    ) -> Vec<SimError> {
+        let module = match module.generate(&inst.params) {
+            Ok(module) => module,
+            Err(err) => return vec![err.into()],
+        };
+        let connections = inst
+            .connections
+            .iter()
+            .map(|(port, signal)| (port.to_owned(), Some(signal)));
+
+        width_mismatches(connections, &module, &inst.path, &SignalWidths::new(scope))
+            .into_iter()
+            .map(SimError::from)
+            .collect()
+    }
+
+    /// Copy of a module with the parameters and functions it imports from
+    /// packages added to it
+    ///
+    /// An item imported by name, as in `import pkg::WIDTH;`, must be declared
+    /// by the package, otherwise `ElabError::UndefinedImport` is returned,
+    /// and a module may not declare an item of the same name, otherwise
+    /// `ElabError::ImportConflict` is returned. A wildcard import, as in
+    /// `import pkg::*;`, adds every item of the package not declared by the
+    /// module or imported before. Importing a package that is not defined
+    /// returns `ElabError::UndefinedPackage`. Imported parameters become
+    /// localparams ahead of the module's own parameters, holding the values
+    /// they have in the package
+    ///
+    /// Items named with their package, as in `pkg::WIDTH`, are added in the
+    /// same way under their scoped name, whether or not they are imported.
+    /// The package must be defined and declare the item, and the same errors
+    /// are returned if not
+    pub fn resolve_imports(&self, module: &Module) -> Result<Module, ElabError> {
+        if let Some(conflict) = check_import_conflicts(module).into_iter().next() {
+            return Err(conflict);
+        }
+
+        let locals = local_names(module);
+        let mut resolved = module.clone();
+        let mut params: Vec<Param> = Vec::new();
+
+        for import in &module.imports {
+            let Some(package) = self.package(&import.package) else {
+                error!(
+                    "module '{}' imports from undefined package '{}'",
+                    module.name, import.package
+                );
+                return Err(ElabError::UndefinedPackage(import.package.to_owned()));
+            };
+
+            let names: Vec<&str> = match &import.item {
+                Some(item) if package.declares(item) => vec![item.as_str()],
+                Some(item) => {
+                    error!("package '{}' does not declare '{}'", package.name, item);
+                    return Err(ElabError::UndefinedImport {
+                        package: package.name.to_owned(),
+                        item: item.to_owned(),
+                    });
+                }
+                None => package
+                    .params
+                    .iter()
+                    .map(|param| param.name.as_str())
+                    .chain(
+                        package
+                            .functions
+                            .iter()
+                            .map(|function| function.name.as_str()),
+                    )
+                    .filter(|name| !locals.contains(name))
+                    .collect(),
+            };
+
+            let values = package.param_values();
+            for name in names {
+                let imported = params.iter().any(|param| param.name == name)
+                    || resolved
+                        .functions
+                        .iter()
+                        .any(|function| function.name == name);
+                if imported {
+                    debug!(
+                        "'{}' from package '{}' is already imported",
+                        name, package.name
+                    );
+                    continue;
+                }
+
+                match (
+                    values.get(name),
+                    package.functions.iter().find(|f| f.name == name),
+                ) {
+                    (Some(value), _) => params.push(Param {
+                        name: name.to_owned(),
+                        value: Expr::Literal(value.clone(), false),
+                        param_type: ParamType::Localparam,
+                        data_type: None,
+                    }),
+                    (None, Some(function)) => resolved.functions.push(function.clone()),
+                    (None, None) => (),
+                }
+            }
+        }
+
+        for (scope, name) in scoped_items(module) {
+            let Some(package) = self.package(&scope) else {
+                error!(
+                    "module '{}' refers to undefined package '{}'",
+                    module.name, scope
+                );
+                return Err(ElabError::UndefinedPackage(scope));
+            };
+
+            let values = package.param_values();
+            match (
+                values.get(&name),
+                package.functions.iter().find(|f| f.name == name),
+            ) {
+                (Some(value), _) => params.push(Param {
+                    name: package_item(&scope, &name),
+                    value: Expr::Literal(value.clone(), false),
+                    param_type: ParamType::Localparam,
+                    data_type: None,
+                }),
+                (None, Some(function)) => resolved.functions.push(FunctionDef {
+                    name: package_item(&scope, &name),
+                    ..function.clone()
+                }),
+                (None, None) => {
+                    error!("package '{}' does not declare '{}'", scope, name);
+                    return Err(ElabError::UndefinedImport {
+                        package: scope,
+                        item: name,
+                    });
+                }
+            }
+        }
+
+        params.append(&mut resolved.io.params);
+        resolved.io.params = params;
+
+        Ok(resolved)
+    }
+
+    /// Counts the module instances in the hierarchy below the module named
+    /// `top`, including `top` itself
+    ///
+    /// Every instance counts separately, so a module instantiated twice
+    /// counts twice. Interface instances are left out. The hierarchy is
+    /// elaborated as by `elaborate`, and any error doing so is returned
+    pub fn count_instances(&self, top: &str) -> Result<u64, SimError> {
+        Ok(self.elaborate(top)?.top.iter().count() as u64)
+    }
+
+    /// Number of distinct modules defined in the object
+    pub fn count_unique_modules(&self) -> u64 {
+        self.mods.len() as u64
+    }
+
+    /// Number of levels in the hierarchy below the module named `top`,
+    /// which is 1 for a module that instantiates nothing
+    ///
+    /// The hierarchy is elaborated as by `elaborate`, and any error doing so
+    /// is returned
+    pub fn deepest_hierarchy_depth(&self, top: &str) -> Result<u64, SimError> {
+        Ok(self.elaborate(top)?.top.depth())
+    }
+
+    /// Reads, preprocesses and parses `files`, then resolves the design
+    /// hierarchy below the module named `top_module`
+    ///
+    /// See `elaborate_with_top_with` for how the files are combined and the
+    /// top-level module is picked
+    pub fn elaborate_with_top(
+        files: &[PathBuf],
+        top_module: Option<&str>,
+        config: &SimConfig,
+    ) -> Result<ElaboratedDesign, Vec<SimError>> {
+        Self::elaborate_with_top_with(files, top_module, config, &mut Preprocessor::new())
+    }
+
+    /// Reads, preprocesses and parses `files`, then resolves the design
+    /// hierarchy below the module named `top_module`
+    ///
+    /// The files are combined as by `parse_sv_files_with`, but every file is
+    /// parsed even if an earlier one fails, so the errors of all of them are
+    /// returned together. The design is only elaborated once every file
+    /// parses, as by `elaborate_with_config`. Without `top_module`, the top
+    /// is picked as by `find_top_module`
+    pub fn elaborate_with_top_with(
+        files: &[PathBuf],
+        top_module: Option<&str>,
+        config: &SimConfig,
+        preprocessor: &mut Preprocessor,
+    ) -> Result<ElaboratedDesign, Vec<SimError>> {
+        let mut object = SimObject::default();
+        let mut errors: Vec<SimError> = Vec::new();
+
+        for path in files {
+            if let Err(e) = parse_sv_path(path, preprocessor, &mut object) {
+                error!("encountered an error in {:?}: '{}'", path, e);
+                errors.push(e);
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        object
+            .find_top_module(top_module)
+            .and_then(|top| object.elaborate_with_config(&top.name, config))
+            .map_err(|e| vec![e.into()])
+    }
+}
+
+/// Design being elaborated
+struct Elaborator<'a> {
+    /// Interfaces available for instantiation
+    object: &'a SimObject,
+
+    /// Modules available for instantiation, with their imports resolved or
+    /// the error resolving them
+    mods: &'a HashMap<String, Result<Module, ElabError>>,
+
+    /// Configuration deciding which problems are errors
+    config: &'a SimConfig,
+
+    /// Names of the modules being elaborated, from the top down, with the
+    /// values of their parameters
+    stack: Vec<(String, HashMap<String, LogicValue>)>,
+}
+
+impl<'a> Elaborator<'a> {
+    /// Elaborates an instance of `module` and every instance below it
+    ///
+    /// `overrides` holds the parameter values given by the instance, which
+    /// decide the blocks its generate constructs produce
+    fn elaborate_instance(
+        &mut self,
+        module: &'a Module,
+        inst_name: &str,
+        path: &str,
+        overrides: &HashMap<String, LogicValue>,
+    ) -> Result<ModuleInstance, ElabError> {
+        trace!("elaborating instance '{}' of '{}'", path, module.name);
+
+        let params = module.param_values(overrides);
+        let on_stack = |same_params: bool| {
+            self.stack.iter().position(|(name, values)| {
+                *name == module.name && (!same_params || *values == params)
+            })
+        };
+        let repeated = on_stack(true).or_else(|| {
+            on_stack(false).filter(|start| self.stack.len() - start >= MAX_RECURSION_DEPTH)
+        });
+        if let Some(start) = repeated {
+            let mut cycle: Vec<String> = self.stack[start..]
+                .iter()
+                .map(|(name, _)| name.to_owned())
+                .collect();
+            cycle.push(module.name.to_owned());
+
+            error!(
+                "module '{}' instantiates itself: {}",
+                module.name,
+                cycle.join(" -> ")
+            );
+            return Err(ElabError::CircularDependency(cycle));
+        }
+
+        self.stack.push((module.name.to_owned(), params.clone()));
+        let children = module.generate(&params).and_then(|generated| {
+            self.elaborate_children(&generated, path, &generated.param_values(overrides))
+        });
+        self.stack.pop();
+
+        Ok(ModuleInstance {
+            module_name: module.name.to_owned(),
+            inst_name: inst_name.to_owned(),
+            path: path.to_owned(),
+            params,
+            connections: Vec::new(),
+            children: children?,
+        })
+    }
+
+    /// Elaborates the sub-module instances of `module`, a module with its
+    /// generate constructs already unrolled and `params` as the values of
+    /// its parameters
+    fn elaborate_children(
+        &mut self,
+        module: &Module,
+        path: &str,
+        params: &HashMap<String, LogicValue>,
+    ) -> Result<Vec<ModuleInstance>, ElabError> {
+        let widths = SignalWidths::new(module);
+        let interfaces = self.interface_names(module);
+
+        let mut children = Vec::new();
+        for inst in &module.instances {
+            if self.object.interface(&inst.module_name).is_some() {
+                trace!("'{}' is an instance of an interface", inst.inst_name);
+                continue;
+            }
+
+            let child_path = format!("{}.{}", path, inst.inst_name);
+            let child = match self.mods.get(&inst.module_name) {
+                Some(child) => child.as_ref().map_err(|err| err.clone())?,
+                None => {
+                    error!(
+                        "module '{}' of instance '{}' is not defined",
+                        inst.module_name, child_path
+                    );
+                    return Err(ElabError::UndefinedModule(inst.module_name.to_owned()));
+                }
+            };
+            let inst = &expand_wildcard(inst, child, |name| {
+                widths.declares(name) || interfaces.contains_key(name)
+            });
+
+            let lookup = ConstLookup {
+                values: params,
+                functions: &module.functions,
+            };
+            let overrides = override_values(inst, child, &lookup);
+            let resolved = child.generate(&child.param_values(&overrides))?;
+            self.check_connections(inst, &resolved, &child_path, &widths, &interfaces)?;
+            let mut child_inst =
+                self.elaborate_instance(child, &inst.inst_name, &child_path, &overrides)?;
+            child_inst.connections = port_connections(inst, child)
+                .into_iter()
+                .filter(|(port, _)| child.io.interface(port).is_none())
+                .filter_map(|(port, signal)| Some((port, signal?.clone())))
+                .collect();
+            children.push(child_inst);
+        }
+
+        Ok(children)
+    }
+
+    /// Checks the signals connected to an instance's ports against the
+    /// ports of the instantiated module
+    ///
+    /// Widths are compared as described for `width_mismatches`.
+    /// `interfaces` gives the interface of each interface instance and port
+    /// of the parent
+    fn check_connections(
+        &self,
+        inst: &ModuleInst,
+        child: &Module,
+        path: &str,
+        widths: &SignalWidths,
+        interfaces: &HashMap<&str, &str>,
+    ) -> Result<(), ElabError> {
+        let connections = port_connections(inst, child);
+
+        for port in &child.io.port_order {
+            let connected = connections
+                .iter()
+                .any(|(name, signal)| name == port && signal.is_some());
+            if !connected {
+                self.unconnected_port(child, port, path)?;
+            }
+        }
+
+        for (port, signal) in &connections {
+            let (Some(expected), Some(signal)) = (child.io.interface(port), signal) else {
+                continue;
+            };
+            self.check_interface_connection(expected, signal, path, interfaces)?;
+        }
+
+        for err in width_mismatches(connections, child, path, widths) {
+            if !self.config.allow_implicit_truncation {
+                error!("{}", err);
+                return Err(err);
+            }
+            warn!("{}", err);
+        }
+
+        Ok(())
+    }
+
+    /// Checks the signal connected to the interface port `port` of the
+    /// instance at `path` is an instance or port of the port's interface, and
+    /// that the interface declares the port's modport
+    fn check_interface_connection(
+        &self,
+        port: &InterfacePort,
+        signal: &Expr,
+        path: &str,
+        interfaces: &HashMap<&str, &str>,
+    ) -> Result<(), ElabError> {
+        let got = match signal {
+            Expr::Ident(name) => interfaces.get(name.as_str()).copied(),
+            _ => None,
+        };
+        if got != Some(port.interface.as_str()) {
+            let err = ElabError::InterfaceMismatch {
+                inst: path.to_owned(),
+                port: port.name.to_owned(),
+                interface: port.interface.to_owned(),
+            };
+            error!("{}", err);
+            return Err(err);
+        }
+
+        let Some(modport) = &port.modport else {
+            return Ok(());
+        };
+        let declared = self
+            .object
+            .interface(&port.interface)
+            .is_some_and(|interface| interface.modport(modport).is_some());
+        if !declared {
+            let err = ElabError::UndefinedModport {
+                interface: port.interface.to_owned(),
+                modport: modport.to_owned(),
+            };
+            error!("{}", err);
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Names of the interface instances and interface ports of `module`,
+    /// each with the name of its interface
+    fn interface_names<'m>(&self, module: &'m Module) -> HashMap<&'m str, &'m str> {
+        module
+            .instances
+            .iter()
+            .filter(|inst| self.object.interface(&inst.module_name).is_some())
+            .map(|inst| (inst.inst_name.as_str(), inst.module_name.as_str()))
+            .chain(
+                module
+                    .io
+                    .interfaces
+                    .iter()
+                    .map(|port| (port.name.as_str(), port.interface.as_str())),
+            )
+            .collect()
+    }
+
+    /// Reports a port of `child` left unconnected by the instance at `path`
+    ///
+    /// An interface port has nothing to read or drive without an interface
+    /// instance, so leaving one unconnected always fails
+    fn unconnected_port(&self, child: &Module, port: &str, path: &str) -> Result<(), ElabError> {
+        let err = ElabError::UnconnectedPort {
+            inst: path.to_owned(),
+            port: port.to_owned(),
+        };
+
+        if self.config.deny_unconnected_ports || child.io.interface(port).is_some() {
+            error!("{}", err);
+            return Err(err);
+        }
+
+        match child.io.port(port) {
+            Some((PortDirection::Input, _)) => warn!("{}, so it floats at Z", err),
+            _ => warn!("{}", err),
+        }
+
+        Ok(())
+    }
+
+    /// Finds a module by name, failing if it is not defined
+    fn lookup_module(&self, name: &str) -> Result<&'a Module, ElabError> {
+        match self.mods.get(name) {
+            Some(module) => module.as_ref().map_err(|err| err.clone()),
+            None => {
+                error!("module '{}' is not defined", name);
+                Err(ElabError::UndefinedModule(name.to_owned()))
+            }
+        }
+    }
+}
+
+/// Package items a module names with their package, as in `pkg::WIDTH`,
+/// as pairs of package and item names in order of first use
+fn scoped_items(module: &Module) -> Vec<(String, String)> {
+    let mut names: Vec<String> = Vec::new();
+    collect_module_reads(module, &mut names);
+
+    names
+        .iter()
+        .filter_map(|name| name.split_once("::"))
+        .map(|(scope, name)| (scope.to_owned(), name.to_owned()))
+        .collect()
+}
+
+/// Adds the names read anywhere in a module, including its ports, generate
+/// blocks, functions and tasks, to `names`
+fn collect_module_reads(module: &Module, names: &mut Vec<String>) {
+    let io = &module.io;
+    let ports = io
+        .inputs
+        .iter()
+        .map(|input| &input.var)
+        .chain(io.outputs.iter().map(|output| &output.var))
+        .chain(io.inouts.iter().map(|inout| &inout.var));
+    let bounds = ports
+        .chain(&module.vars)
+        .flat_map(|var| var.bounds.iter().chain(&var.array_bounds))
+        .flat_map(|bounds| [&bounds.left, &bounds.right]);
+    let params = io
+        .params
+        .iter()
+        .chain(&module.params)
+        .map(|param| &param.value);
+    let assigns = module
+        .assigns
+        .iter()
+        .flat_map(|assign| [&assign.lhs, &assign.rhs]);
+    let overrides = module
+        .instances
+        .iter()
+        .flat_map(|inst| &inst.param_overrides)
+        .filter_map(|param| match param {
+            ParamOverride::Named { value, .. } => value.as_ref(),
+            ParamOverride::Positional(value) => Some(value),
+        });
+    let connections = module
+        .instances
+        .iter()
+        .flat_map(|inst| &inst.port_connections)
+        .filter_map(|conn| match conn {
+            PortConn::Named { signal, .. } | PortConn::Positional(signal) => signal.as_ref(),
+            PortConn::Wildcard => None,
+        });
+    bounds
+        .chain(params)
+        .chain(assigns)
+        .chain(overrides)
+        .chain(connections)
+        .for_each(|expr| collect_reads(expr, names));
+
+    let bodies = module
+        .comb_blocks
+        .iter()
+        .map(|block| &block.body)
+        .chain(module.ff_blocks.iter().map(|block| &block.body))
+        .chain(module.latch_blocks.iter().map(|block| &block.body))
+        .chain(module.initial_blocks.iter().map(|block| &block.body))
+        .chain(module.final_blocks.iter().map(|block| &block.body))
+        .chain(module.functions.iter().map(|function| &function.body))
+        .chain(module.tasks.iter().map(|task| &task.body));
+    for name in bodies.flat_map(|body| stmt_reads(body)) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    let body = |body: &GenBody, names: &mut Vec<String>| collect_module_reads(&body.items, names);
+    for block in &module.generates {
+        match block {
+            GenBlock::For {
+                init,
+                cond,
+                step,
+                body: items,
+                ..
+            } => {
+                [init, cond, step]
+                    .into_iter()
+                    .for_each(|expr| collect_reads(expr, names));
+                body(items, names);
+            }
+            GenBlock::If {
+                cond,
+                then,
+                else_if,
+                else_,
+            } => {
+                collect_reads(cond, names);
+                body(then, names);
+                for (cond, items) in else_if {
+                    collect_reads(cond, names);
+                    body(items, names);
+                }
+                if let Some(items) = else_ {
+                    body(items, names);
+                }
+            }
+            GenBlock::Case {
+                expr,
+                items,
+                default,
+            } => {
+                collect_reads(expr, names);
+                for (labels, items) in items {
+                    labels.iter().for_each(|label| collect_reads(label, names));
+                    body(items, names);
+                }
+                if let Some(items) = default {
+                    body(items, names);
+                }
+            }
+        }
+    }
+}
+
+/// Copy of an instance with a `.*` connection replaced by a named connection
+/// for each port of `child` not already connected by name
+///
+/// Each port is connected to the signal of the same name, if `declared`
+/// holds for that name, and is otherwise left unconnected
+fn expand_wildcard(
+    inst: &ModuleInst,
+    child: &Module,
+    declared: impl Fn(&str) -> bool,
+) -> ModuleInst {
+    if !inst.port_connections.contains(&PortConn::Wildcard) {
+        return inst.clone();
+    }
+
+    let named: Vec<&str> = inst
+        .port_connections
+        .iter()
+        .filter_map(|conn| match conn {
+            PortConn::Named { port, .. } => Some(port.as_str()),
+            _ => None,
+        })
+        .collect();
+    let implicit = child
+        .io
+        .port_order
+        .iter()
+        .filter(|port| !named.contains(&port.as_str()) && declared(port))
+        .map(|port| PortConn::Named {
+            port: port.to_owned(),
+            signal: Some(Expr::Ident(port.to_owned())),
+        });
+
+    ModuleInst {
+        port_connections: inst
+            .port_connections
+            .iter()
+            .filter(|conn| **conn != PortConn::Wildcard)
+            .cloned()
+            .chain(implicit)
+            .collect(),
+        ..inst.clone()
+    }
+}
+
+/// Port connections of an instance as `(port, signal)` pairs, matching
+/// positional connections to the ports of `child` in header order
+fn port_connections<'a>(inst: &'a ModuleInst, child: &Module) -> Vec<(String, Option<&'a Expr>)> {
+    inst.port_connections
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, conn)| match conn {
+            PortConn::Named { port, signal } => Some((port.to_owned(), signal.as_ref())),
+            PortConn::Positional(signal) => match child.io.port_order.get(idx) {
+                Some(port) => Some((port.to_owned(), signal.as_ref())),
+                None => {
+                    warn!(
+                        "'{}' has more connections than '{}' has ports",
+                        inst.inst_name, child.name
+                    );
+                    None
+                }
+            },
+            PortConn::Wildcard => None,
+        })
+        .collect()
+}
+
+/// Port width mismatches between the `(port, signal)` connections of the
+/// instance at `path` and the ports of `child`
+///
+/// Only connections made of signals declared in the parent are checked.
+/// Constants such as `.cin(0)` take the width of the port, and names the
+/// parent does not declare have no known width. Interface ports and
+/// unconnected ports are left out
+fn width_mismatches<'e>(
+    connections: impl IntoIterator<Item = (String, Option<&'e Expr>)>,
+    child: &Module,
+    path: &str,
+    widths: &SignalWidths,
+) -> Vec<ElabError> {
+    let mut mismatches = Vec::new();
+
+    for (port, signal) in connections {
+        let Some(signal) = signal else {
+            continue;
+        };
+        if child.io.interface(&port).is_some() {
+            continue;
+        }
+        let Some((_, var)) = child.io.port(&port) else {
+            warn!("'{}' has no port '{}' for '{}'", child.name, port, path);
+            continue;
+        };
+
+        let reads = expr_reads(signal);
+        if reads.is_empty() || reads.iter().any(|name| !widths.declares(name)) {
+            continue;
+        }
+
+        let got = expr_width(signal, widths);
+        if got != var.width {
+            mismatches.push(ElabError::PortWidthMismatch {
+                inst: path.to_owned(),
+                port,
+                expected: var.width,
+                got,
+            });
+        }
+    }
+
+    mismatches
+}
+
+/// Values an instance gives to the parameters of `child`, evaluated using
+/// the parameters and functions of the instantiating module
+///
+/// Positional overrides follow the order `child` declares its overridable
+/// parameters in. Named overrides of parameters `child` does not declare, or
+/// declares as a localparam, are left out
+fn override_values(
+    inst: &ModuleInst,
+    child: &Module,
+    params: &ConstLookup,
+) -> HashMap<String, LogicValue> {
+    let positional: Vec<&str> = child
+        .io
+        .params
+        .iter()
+        .chain(&child.params)
+        .filter(|param| param.param_type != ParamType::Localparam)
+        .map(|param| param.name.as_str())
+        .collect();
+
+    inst.param_overrides
+        .iter()
+        .enumerate()
+        .filter_map(|(index, param)| match param {
+            ParamOverride::Named { param, .. } if !positional.contains(&param.as_str()) => {
+                warn!(
+                    "'{}' has no overridable parameter '{}' for instance '{}'",
+                    child.name, param, inst.inst_name
+                );
+                None
+            }
+            ParamOverride::Named { param, value } => {
+                Some((param.to_owned(), eval_expr(value.as_ref()?, params)))
+            }
+            ParamOverride::Positional(value) => match positional.get(index) {
+                Some(name) => Some((name.to_string(), eval_expr(value, params))),
+                None => {
+                    warn!(
+                        "instance '{}' overrides more parameters than '{}' declares",
+                        inst.inst_name, child.name
+                    );
+                    None
+                }
+            },
+        })
+        .collect()
+}
+
+/// Ports and variables of a module, by name, giving their widths for
+/// `expr_width`
+///
+/// Each reads as an unknown value of its width. As in simulation, a memory
+/// has no value of its own, while each of its elements does, so that
+/// connecting `mem[3]` takes the width of an element
+struct SignalWidths<'m> {
+    vars: HashMap<&'m str, &'m Var>,
+}
+
+impl<'m> SignalWidths<'m> {
+    /// Collects the ports and variables of `module`
+    fn new(module: &'m Module) -> Self {
+        let io = &module.io;
+        let vars = io
+            .inputs
+            .iter()
+            .map(|input| &input.var)
+            .chain(io.outputs.iter().map(|output| &output.var))
+            .chain(io.inouts.iter().map(|inout| &inout.var))
+            .chain(module.vars.iter())
+            .map(|var| (var.name.as_str(), var))
+            .collect();
+
+        Self { vars }
+    }
+
+    /// Checks if the module declares a port or variable named `name`
+    fn declares(&self, name: &str) -> bool {
+        self.vars.contains_key(name)
+    }
+}
+
+impl ValueLookup for SignalWidths<'_> {
+    fn value(&self, name: &str) -> Option<LogicValue> {
+        if let Some(var) = self.vars.get(name) {
+            return var.array.is_none().then(|| LogicValue::new(var.width));
+        }
+
+        let (memory, address) = name.strip_suffix(']')?.split_once('[')?;
+        let var = self.vars.get(memory)?;
+        var.addresses()?
+            .contains(&address.parse().ok()?)
+            .then(|| LogicValue::new(var.width))
+    }
+
+    fn memory_bounds(&self, name: &str) -> Option<(u64, u64)> {
+        let addresses = self.vars.get(name)?.addresses()?;
+        Some((*addresses.start(), *addresses.end()))
+    }
+}
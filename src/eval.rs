@@ -0,0 +1,790 @@
+use crate::expr::{BinOp, CastKind, Expr, UnaryOp};
+use crate::function::FunctionDef;
+use crate::sys_task::{system_command, system_time};
+use crate::value::{sv_pow, LogicState, LogicValue, MAX_WIDTH};
+use log::warn;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Source of the values read while evaluating an expression
+pub trait ValueLookup {
+    /// Returns the current value of a signal or parameter, or `None` if
+    /// nothing of that name exists
+    fn value(&self, name: &str) -> Option<LogicValue>;
+
+    /// Current simulation time in precision steps
+    ///
+    /// Defaults to zero, for values evaluated outside of a simulation
+    fn time(&self) -> u64 {
+        0
+    }
+
+    /// Number of precision steps in one time unit, the unit `$time` counts
+    /// in
+    ///
+    /// Defaults to one, as with a timescale whose unit is its precision
+    fn time_unit(&self) -> u64 {
+        1
+    }
+
+    /// Lowest and highest addresses of a memory, or `None` if `name` is not
+    /// a memory
+    fn memory_bounds(&self, _name: &str) -> Option<(u64, u64)> {
+        None
+    }
+
+    /// Checks if a signal or parameter is declared `signed`
+    ///
+    /// Defaults to false, so every name reads as unsigned
+    fn is_signed(&self, _name: &str) -> bool {
+        false
+    }
+
+    /// Functions that may be called, those defined by the module being
+    /// evaluated
+    ///
+    /// Defaults to none, so any call reads as X
+    fn functions(&self) -> &[FunctionDef] {
+        &[]
+    }
+
+    /// Runs a shell command for `$system`, returning its exit code, or
+    /// `None` if the command was not run
+    ///
+    /// Defaults to never running the command, so the call reads as X
+    fn system(&self, _cmd: &str) -> Option<i32> {
+        None
+    }
+}
+
+impl ValueLookup for HashMap<String, LogicValue> {
+    fn value(&self, name: &str) -> Option<LogicValue> {
+        self.get(name).cloned()
+    }
+
+    fn memory_bounds(&self, name: &str) -> Option<(u64, u64)> {
+        element_bounds(self.keys(), name)
+    }
+}
+
+/// Values known while elaborating a module, along with the functions its
+/// constant expressions may call, as in `localparam AW = log2(DEPTH)`
+pub struct ConstLookup<'a> {
+    /// Parameters and other values, by name
+    pub values: &'a HashMap<String, LogicValue>,
+
+    /// Functions defined by the module
+    pub functions: &'a [FunctionDef],
+}
+
+impl ValueLookup for ConstLookup<'_> {
+    fn value(&self, name: &str) -> Option<LogicValue> {
+        self.values.value(name)
+    }
+
+    fn memory_bounds(&self, name: &str) -> Option<(u64, u64)> {
+        self.values.memory_bounds(name)
+    }
+
+    fn functions(&self) -> &[FunctionDef] {
+        self.functions
+    }
+}
+
+/// Lowest and highest addresses among the elements of the memory `name`,
+/// named `name[address]`, found in `names`
+pub fn element_bounds<'a>(
+    names: impl Iterator<Item = &'a String>,
+    name: &str,
+) -> Option<(u64, u64)> {
+    names
+        .filter_map(|element| {
+            element
+                .strip_prefix(name)?
+                .strip_prefix('[')?
+                .strip_suffix(']')?
+                .parse::<u64>()
+                .ok()
+        })
+        .fold(None, |bounds, address| match bounds {
+            Some((low, high)) => Some((address.min(low), address.max(high))),
+            None => Some((address, address)),
+        })
+}
+
+/// Evaluates an expression using its own width
+///
+/// Signals declared `signed`, unbased decimal literals such as `7`, based
+/// literals written with `s` such as `4'sd3`, and `$signed` are signed. An
+/// expression is only signed if all of its operands are, so `a + 8'hFF` is
+/// unsigned even if `a` is signed. A signed expression sign-extends its
+/// operands, and compares, divides and shifts with `>>>` as signed. Names
+/// that cannot be found read as a single X bit.
+///
+/// A name in a package scope, as in `pkg::WIDTH`, reads the item of that
+/// package, whether or not it is imported. A member of an instance, as in
+/// `u_sub.count`, reads the signal by its hierarchical name below the
+/// current scope. Method calls are not supported and read as a single X bit
+pub fn eval_expr(expr: &Expr, lookup: &impl ValueLookup) -> LogicValue {
+    eval(
+        expr,
+        expr_width(expr, lookup),
+        is_signed(expr, lookup),
+        lookup,
+    )
+}
+
+/// Evaluates an expression assigned to a target `width` bits wide
+///
+/// As in SystemVerilog, the operands of arithmetic and bitwise operators are
+/// extended to the wider of the expression and the target before the
+/// operation, so `sum = a + b` keeps the carry if `sum` is wider than `a`
+/// and `b`. The result is truncated or zero-extended to `width`
+pub fn eval_expr_sized(expr: &Expr, width: u64, lookup: &impl ValueLookup) -> LogicValue {
+    eval(
+        expr,
+        expr_width(expr, lookup).max(width),
+        is_signed(expr, lookup),
+        lookup,
+    )
+    .resize(width)
+}
+
+/// Self-determined width of an expression in bits
+pub fn expr_width(expr: &Expr, lookup: &impl ValueLookup) -> u64 {
+    match expr {
+        Expr::Ident(name) => lookup.value(name).map_or(1, |val| val.width()),
+        Expr::Literal(val, _) => val.width(),
+        Expr::Str(val) => 8 * val.len().max(1) as u64,
+        Expr::Fill(_) | Expr::DefaultLiteral(_) => 1,
+        Expr::UnaryOp(UnaryOp::Plus | UnaryOp::Negate | UnaryOp::BitwiseNot, operand) => {
+            expr_width(operand, lookup)
+        }
+        Expr::UnaryOp(..) => 1,
+        Expr::BinOp(op, lhs, rhs) => match op {
+            BinOp::Pow | BinOp::Shl | BinOp::Shr | BinOp::AShl | BinOp::AShr => {
+                expr_width(lhs, lookup)
+            }
+            BinOp::Lt
+            | BinOp::Lte
+            | BinOp::Gt
+            | BinOp::Gte
+            | BinOp::Eq
+            | BinOp::Neq
+            | BinOp::CaseEq
+            | BinOp::CaseNeq
+            | BinOp::LogAnd
+            | BinOp::LogOr => 1,
+            _ => expr_width(lhs, lookup).max(expr_width(rhs, lookup)),
+        },
+        Expr::Ternary { then, else_, .. } => {
+            expr_width(then, lookup).max(expr_width(else_, lookup))
+        }
+        Expr::BitSelect(base, index) => match memory_element(base, index, lookup) {
+            Some(element) => lookup.value(&element).map_or(1, |val| val.width()),
+            None => 1,
+        },
+        Expr::PartSelect(_, msb, lsb) => {
+            match (const_index(msb, lookup), const_index(lsb, lookup)) {
+                (Some(msb), Some(lsb)) => msb.abs_diff(lsb) + 1,
+                _ => 1,
+            }
+        }
+        Expr::PartSelectUp(_, _, width) | Expr::PartSelectDown(_, _, width) => {
+            const_index(width, lookup).unwrap_or(1).max(1)
+        }
+        Expr::Concat(values) | Expr::ArrayLiteral(values) => {
+            values.iter().map(|val| expr_width(val, lookup)).sum()
+        }
+        Expr::StructLiteral(fields) => fields.iter().map(|(_, val)| expr_width(val, lookup)).sum(),
+        Expr::Replicate { count, value } => {
+            const_index(count, lookup).unwrap_or(0) * expr_width(value, lookup)
+        }
+        Expr::Cast(_, operand) => expr_width(operand, lookup),
+        Expr::SysFunc(name, _) => match name.as_str() {
+            "time" | "realtime" | "system_time" => 64,
+            "stime" | "clog2" | "bits" | "size" | "high" | "low" | "system" => 32,
+            _ => 1,
+        },
+        Expr::FuncCall(name, _) => find_function(name, lookup).map_or(1, |func| func.ret.width),
+        Expr::Scoped(scope, item) => match &**item {
+            Expr::Ident(name) => lookup
+                .value(&package_item(scope, name))
+                .map_or(1, |val| val.width()),
+            Expr::FuncCall(name, _) => {
+                find_function(&package_item(scope, name), lookup).map_or(1, |func| func.ret.width)
+            }
+            _ => 1,
+        },
+        Expr::Member(..) => member_name(expr)
+            .and_then(|name| lookup.value(&name))
+            .map_or(1, |val| val.width()),
+        Expr::MethodCall(..) => 1,
+    }
+}
+
+/// Name under which a module holds the item `name` of the package `scope`
+/// it refers to, as `pkg::WIDTH` for `pkg::WIDTH`
+///
+/// Elaboration adds every package item a module refers to by a scoped name
+/// to the module under this name, so that it cannot clash with a local or
+/// imported item of the same name
+pub(crate) fn package_item(scope: &str, name: &str) -> String {
+    format!("{}::{}", scope, name)
+}
+
+/// Hierarchical name a name or member access reads, as `u_sub.count` for
+/// `u_sub.count`, or `None` if it is not made up only of names
+pub(crate) fn member_name(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Ident(name) => Some(name.to_owned()),
+        Expr::Member(base, name) => Some(format!("{}.{}", member_name(base)?, name)),
+        _ => None,
+    }
+}
+
+/// Names of every signal or parameter an expression reads, in order of
+/// first use
+pub fn expr_reads(expr: &Expr) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    collect_reads(expr, &mut names);
+    names
+}
+
+/// Checks if an expression calls a function defined in a module, or a
+/// method
+pub fn calls_function(expr: &Expr) -> bool {
+    match expr {
+        Expr::FuncCall(..) | Expr::MethodCall(..) => true,
+        Expr::Scoped(_, item) => calls_function(item),
+        Expr::Member(base, _) => calls_function(base),
+        Expr::Ident(_) | Expr::Literal(..) | Expr::Str(_) | Expr::Fill(_) => false,
+        Expr::UnaryOp(_, operand) | Expr::Cast(_, operand) | Expr::DefaultLiteral(operand) => {
+            calls_function(operand)
+        }
+        Expr::BinOp(_, lhs, rhs)
+        | Expr::BitSelect(lhs, rhs)
+        | Expr::Replicate {
+            count: lhs,
+            value: rhs,
+        } => calls_function(lhs) || calls_function(rhs),
+        Expr::Ternary { cond, then, else_ } => {
+            [cond, then, else_].into_iter().any(|e| calls_function(e))
+        }
+        Expr::PartSelect(base, a, b)
+        | Expr::PartSelectUp(base, a, b)
+        | Expr::PartSelectDown(base, a, b) => [base, a, b].into_iter().any(|e| calls_function(e)),
+        Expr::Concat(values) | Expr::ArrayLiteral(values) | Expr::SysFunc(_, values) => {
+            values.iter().any(calls_function)
+        }
+        Expr::StructLiteral(fields) => fields.iter().any(|(_, val)| calls_function(val)),
+    }
+}
+
+/// Adds the names read by `expr` to `names`, skipping any already present
+pub fn collect_reads(expr: &Expr, names: &mut Vec<String>) {
+    match expr {
+        Expr::Ident(name) => {
+            if !names.contains(name) {
+                names.push(name.to_owned());
+            }
+        }
+        Expr::Literal(..) | Expr::Str(_) | Expr::Fill(_) => (),
+        Expr::UnaryOp(_, operand) | Expr::Cast(_, operand) | Expr::DefaultLiteral(operand) => {
+            collect_reads(operand, names)
+        }
+        Expr::BinOp(_, lhs, rhs) | Expr::BitSelect(lhs, rhs) => {
+            collect_reads(lhs, names);
+            collect_reads(rhs, names);
+        }
+        Expr::Ternary { cond, then, else_ } => {
+            collect_reads(cond, names);
+            collect_reads(then, names);
+            collect_reads(else_, names);
+        }
+        Expr::PartSelect(base, a, b)
+        | Expr::PartSelectUp(base, a, b)
+        | Expr::PartSelectDown(base, a, b) => {
+            collect_reads(base, names);
+            collect_reads(a, names);
+            collect_reads(b, names);
+        }
+        Expr::Concat(values) | Expr::ArrayLiteral(values) => {
+            values.iter().for_each(|val| collect_reads(val, names))
+        }
+        Expr::StructLiteral(fields) => fields.iter().for_each(|(_, val)| collect_reads(val, names)),
+        Expr::Replicate { count, value } => {
+            collect_reads(count, names);
+            collect_reads(value, names);
+        }
+        Expr::SysFunc(_, args) | Expr::FuncCall(_, args) => {
+            args.iter().for_each(|arg| collect_reads(arg, names))
+        }
+        Expr::Scoped(scope, item) => match &**item {
+            Expr::Ident(name) | Expr::FuncCall(name, _) => {
+                let name = package_item(scope, name);
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+                if let Expr::FuncCall(_, args) = &**item {
+                    args.iter().for_each(|arg| collect_reads(arg, names))
+                }
+            }
+            // Names in nested scopes, as in `pkg::Class::f()`, are not
+            // package items
+            _ => (),
+        },
+        Expr::Member(base, _) => match member_name(expr) {
+            Some(name) if !names.contains(&name) => names.push(name),
+            Some(_) => (),
+            None => collect_reads(base, names),
+        },
+        Expr::MethodCall(base, _, args) => {
+            collect_reads(base, names);
+            args.iter().for_each(|arg| collect_reads(arg, names))
+        }
+    }
+}
+
+/// Name of the memory element selected by `base[index]`, as in `mem[3]`, or
+/// `None` if `base` is not a memory or the element does not exist
+///
+/// Each element of a memory is looked up as a value of its own, while the
+/// memory itself has no value
+pub fn memory_element(base: &Expr, index: &Expr, lookup: &impl ValueLookup) -> Option<String> {
+    let Expr::Ident(name) = base else {
+        return None;
+    };
+    if lookup.value(name).is_some() {
+        return None;
+    }
+
+    let element = format!("{}[{}]", name, const_index(index, lookup)?);
+    lookup.value(&element).map(|_| element)
+}
+
+/// Checks if an expression is signed, as described in `eval_expr`
+pub fn is_signed(expr: &Expr, lookup: &impl ValueLookup) -> bool {
+    match expr {
+        Expr::Ident(name) => lookup.is_signed(name),
+        Expr::BitSelect(base, index) => {
+            memory_element(base, index, lookup).is_some_and(|element| lookup.is_signed(&element))
+        }
+        Expr::FuncCall(name, _) => find_function(name, lookup).is_some_and(|func| func.ret.signed),
+        Expr::Scoped(scope, item) => match &**item {
+            Expr::Ident(name) => lookup.is_signed(&package_item(scope, name)),
+            Expr::FuncCall(name, _) => find_function(&package_item(scope, name), lookup)
+                .is_some_and(|func| func.ret.signed),
+            _ => false,
+        },
+        Expr::Member(..) => member_name(expr).is_some_and(|name| lookup.is_signed(&name)),
+        Expr::Literal(_, signed) => *signed,
+        Expr::Cast(kind, _) => *kind == CastKind::Signed,
+        Expr::UnaryOp(UnaryOp::Plus | UnaryOp::Negate | UnaryOp::BitwiseNot, operand) => {
+            is_signed(operand, lookup)
+        }
+        Expr::BinOp(op, lhs, rhs) => match op {
+            BinOp::Pow | BinOp::Shl | BinOp::Shr | BinOp::AShl | BinOp::AShr => {
+                is_signed(lhs, lookup)
+            }
+            BinOp::Lt
+            | BinOp::Lte
+            | BinOp::Gt
+            | BinOp::Gte
+            | BinOp::Eq
+            | BinOp::Neq
+            | BinOp::CaseEq
+            | BinOp::CaseNeq
+            | BinOp::LogAnd
+            | BinOp::LogOr => false,
+            _ => is_signed(lhs, lookup) && is_signed(rhs, lookup),
+        },
+        Expr::Ternary { then, else_, .. } => is_signed(then, lookup) && is_signed(else_, lookup),
+        _ => false,
+    }
+}
+
+/// Evaluates an index or width, returning `None` if it is unknown or does
+/// not fit in 64 bits
+pub fn const_index(expr: &Expr, lookup: &impl ValueLookup) -> Option<u64> {
+    eval_expr(expr, lookup)
+        .to_u128()
+        .and_then(|val| u64::try_from(val).ok())
+}
+
+/// Evaluates an expression in a context `width` bits wide, which is `signed`
+/// or not
+///
+/// Operands of context-determined operators are evaluated at `width`, while
+/// self-determined operands, such as those of comparisons, use their own
+/// width. The result is always `width` bits wide, sign-extended if the
+/// context is signed
+fn eval(expr: &Expr, width: u64, signed: bool, lookup: &impl ValueLookup) -> LogicValue {
+    let val = match expr {
+        Expr::Ident(name) => lookup.value(name).unwrap_or_else(|| LogicValue::new(1)),
+        Expr::Literal(val, _) => val.clone(),
+        Expr::Str(val) => string_value(val),
+        Expr::Fill(state) => LogicValue::filled(width, *state),
+        Expr::DefaultLiteral(value) => {
+            LogicValue::filled(width, eval_expr(value, lookup).get_bit(0))
+        }
+        Expr::UnaryOp(op, operand) => eval_unary(*op, operand, width, signed, lookup),
+        Expr::BinOp(op, lhs, rhs) => eval_binary(*op, lhs, rhs, width, signed, lookup),
+        Expr::Ternary { cond, then, else_ } => match eval_expr(cond, lookup).truth() {
+            LogicState::One => eval(then, width, signed, lookup),
+            LogicState::Zero => eval(else_, width, signed, lookup),
+            _ => merge_unknown(
+                &eval(then, width, signed, lookup),
+                &eval(else_, width, signed, lookup),
+            ),
+        },
+        Expr::BitSelect(base, index) => match memory_element(base, index, lookup) {
+            Some(element) => lookup.value(&element).unwrap_or_else(|| LogicValue::new(1)),
+            None => {
+                let base = eval_expr(base, lookup);
+                match const_index(index, lookup) {
+                    Some(index) => LogicValue::from_bits(vec![base.get_bit(index)]),
+                    None => LogicValue::new(1),
+                }
+            }
+        },
+        Expr::PartSelect(base, msb, lsb) => {
+            match (const_index(msb, lookup), const_index(lsb, lookup)) {
+                (Some(msb), Some(lsb)) => {
+                    eval_expr(base, lookup).get_range(msb.max(lsb), msb.min(lsb))
+                }
+                _ => LogicValue::new(expr_width(expr, lookup)),
+            }
+        }
+        Expr::PartSelectUp(base, start, _) | Expr::PartSelectDown(base, start, _) => {
+            let select_width = expr_width(expr, lookup);
+            let low = match (expr, const_index(start, lookup)) {
+                (Expr::PartSelectUp(..), Some(start)) => Some(start),
+                (_, Some(start)) => (start + 1).checked_sub(select_width),
+                _ => None,
+            };
+
+            match low {
+                Some(low) => eval_expr(base, lookup).get_range(low + select_width - 1, low),
+                None => LogicValue::new(select_width),
+            }
+        }
+        Expr::Concat(values) | Expr::ArrayLiteral(values) => LogicValue::from_bits(
+            values
+                .iter()
+                .flat_map(|val| eval_expr(val, lookup).bits().to_vec())
+                .collect(),
+        ),
+        // Without struct types to lay them out, fields are packed in the
+        // order they are given, the first most significant
+        Expr::StructLiteral(fields) => LogicValue::from_bits(
+            fields
+                .iter()
+                .flat_map(|(_, val)| eval_expr(val, lookup).bits().to_vec())
+                .collect(),
+        ),
+        Expr::Replicate { count, value } => {
+            let value = eval_expr(value, lookup);
+            let mut count = const_index(count, lookup).unwrap_or(0);
+            let limit = MAX_WIDTH / value.width().max(1);
+            if count > limit {
+                warn!(
+                    "replication is wider than {} bits and is truncated",
+                    MAX_WIDTH
+                );
+                count = limit;
+            }
+            LogicValue::from_bits(value.bits().repeat(count as usize))
+        }
+        Expr::Cast(_, operand) => eval_expr(operand, lookup),
+        Expr::SysFunc(name, args) => eval_sys_func(name, args, lookup),
+        Expr::FuncCall(name, args) => eval_func_call(name, args, lookup),
+        Expr::Scoped(scope, item) => match &**item {
+            Expr::Ident(name) => lookup
+                .value(&package_item(scope, name))
+                .unwrap_or_else(|| LogicValue::new(1)),
+            Expr::FuncCall(name, args) => eval_func_call(&package_item(scope, name), args, lookup),
+            _ => {
+                warn!("names in nested scopes are not supported and read as X");
+                LogicValue::new(1)
+            }
+        },
+        Expr::Member(..) => member_name(expr)
+            .and_then(|name| lookup.value(&name))
+            .unwrap_or_else(|| LogicValue::new(1)),
+        Expr::MethodCall(_, name, _) => {
+            warn!("method call '{}' is not supported and reads as X", name);
+            LogicValue::new(1)
+        }
+    };
+
+    match signed {
+        true => val.sign_extend(width),
+        false => val.resize(width),
+    }
+}
+
+/// Function named `name` among those `lookup` may call
+fn find_function<'a>(name: &str, lookup: &'a impl ValueLookup) -> Option<&'a FunctionDef> {
+    lookup.functions().iter().find(|func| func.name == name)
+}
+
+/// Evaluates a call to a function defined in the module
+///
+/// Each argument is evaluated at the width of the input it is given to.
+/// Calls to undefined functions read as a single X bit, and calls with the
+/// wrong number of arguments as an X return value
+fn eval_func_call(name: &str, args: &[Expr], lookup: &impl ValueLookup) -> LogicValue {
+    let Some(func) = find_function(name, lookup) else {
+        warn!("function '{}' is not defined", name);
+        return LogicValue::new(1);
+    };
+
+    if args.len() != func.args.len() {
+        warn!(
+            "function '{}' takes {} arguments, but is given {}",
+            name,
+            func.args.len(),
+            args.len()
+        );
+        return LogicValue::new(func.ret.width);
+    }
+
+    let values: Vec<LogicValue> = args
+        .iter()
+        .zip(&func.args)
+        .map(|(arg, input)| eval_expr_sized(arg, input.width, lookup))
+        .collect();
+    func.call(&values, lookup.functions())
+}
+
+/// Current simulation time in whole time units, rounding half a unit up
+fn time_units(lookup: &impl ValueLookup) -> u64 {
+    let unit = lookup.time_unit().max(1);
+    lookup.time() / unit + u64::from(lookup.time() % unit >= unit.div_ceil(2))
+}
+
+/// Evaluates a call to a system function
+///
+/// `$time` and `$realtime` give the current time in time units as 64 bits,
+/// rounded to a whole number of units, and `$stime` gives its low 32 bits.
+/// Real values are not yet supported, so `$realtime` is rounded the same way
+/// as `$time`.
+///
+/// `$clog2`, `$bits`, `$size`, `$high` and `$low` give 32-bit integers.
+/// `$bits` of a type is worked out as it is parsed, so here it takes an
+/// expression. For a memory, `$size`, `$high` and `$low` describe its
+/// addresses, and for any other value its bits, numbered from 0. Any of them
+/// given a name that cannot be found reads as X, as do `$clog2` of a value
+/// that is not constant.
+///
+/// `$system` runs its command through the lookup and gives the 32-bit exit
+/// code, or X if the command did not run, and `$system_time` gives the
+/// host's time as by `system_time`. Unsupported functions read as a single
+/// X bit
+fn eval_sys_func(name: &str, args: &[Expr], lookup: &impl ValueLookup) -> LogicValue {
+    let integer = |val: Option<u64>| match val {
+        Some(val) => LogicValue::from_u128(val as u128, 32),
+        None => LogicValue::new(32),
+    };
+    let bounds = || match args.first() {
+        Some(Expr::Ident(name)) if lookup.value(name).is_none() => lookup.memory_bounds(name),
+        Some(arg) => Some((0, expr_width(arg, lookup).saturating_sub(1))),
+        None => None,
+    };
+
+    match name {
+        "time" | "realtime" => LogicValue::from_u128(time_units(lookup) as u128, 64),
+        "stime" => LogicValue::from_u128(time_units(lookup) as u128, 32),
+        "clog2" => integer(
+            args.first()
+                .and_then(|arg| const_index(arg, lookup))
+                .map(clog2),
+        ),
+        "bits" => integer(args.first().and_then(|arg| match arg {
+            Expr::Ident(name) if lookup.value(name).is_none() => {
+                let (low, high) = lookup.memory_bounds(name)?;
+                let element = lookup.value(&format!("{}[{}]", name, low))?;
+                Some((high - low + 1) * element.width())
+            }
+            _ => Some(expr_width(arg, lookup)),
+        })),
+        "size" => integer(bounds().map(|(low, high)| high - low + 1)),
+        "high" => integer(bounds().map(|(_, high)| high)),
+        "low" => integer(bounds().map(|(low, _)| low)),
+        "system" => match args.first() {
+            Some(arg) => match lookup.system(&system_command(arg, lookup)) {
+                Some(code) => LogicValue::from_u128(code as u32 as u128, 32),
+                None => LogicValue::new(32),
+            },
+            None => {
+                warn!("$system expects a command");
+                LogicValue::new(32)
+            }
+        },
+        "system_time" => LogicValue::from_u128(system_time() as u128, 64),
+        _ => LogicValue::new(1),
+    }
+}
+
+/// Ceiling of the base 2 logarithm of `val`, with `$clog2(0)` being 0
+fn clog2(val: u64) -> u64 {
+    match val {
+        0 | 1 => 0,
+        _ => u64::from(u64::BITS - (val - 1).leading_zeros()),
+    }
+}
+
+/// Evaluates a prefix operation in a context `width` bits wide
+fn eval_unary(
+    op: UnaryOp,
+    operand: &Expr,
+    width: u64,
+    signed: bool,
+    lookup: &impl ValueLookup,
+) -> LogicValue {
+    let reduce = |init: LogicState, op: fn(LogicState, LogicState) -> LogicState| {
+        let bits = eval_expr(operand, lookup);
+        bits.bits().iter().fold(init, |acc, bit| op(acc, *bit))
+    };
+
+    let bit = match op {
+        UnaryOp::Plus => return eval(operand, width, signed, lookup),
+        UnaryOp::Negate => {
+            return &LogicValue::from_u128(0, width) - &eval(operand, width, signed, lookup)
+        }
+        UnaryOp::BitwiseNot => return !&eval(operand, width, signed, lookup),
+        UnaryOp::LogicalNot => !eval_expr(operand, lookup).truth(),
+        UnaryOp::ReduceAnd => reduce(LogicState::One, |a, b| a & b),
+        UnaryOp::ReduceOr => reduce(LogicState::Zero, |a, b| a | b),
+        UnaryOp::ReduceXor => reduce(LogicState::Zero, |a, b| a ^ b),
+        UnaryOp::ReduceXnor => !reduce(LogicState::Zero, |a, b| a ^ b),
+        UnaryOp::ReduceNand => !reduce(LogicState::One, |a, b| a & b),
+        UnaryOp::ReduceNor => !reduce(LogicState::Zero, |a, b| a | b),
+    };
+
+    LogicValue::from_bits(vec![bit])
+}
+
+/// Evaluates an infix operation in a context `width` bits wide
+fn eval_binary(
+    op: BinOp,
+    lhs: &Expr,
+    rhs: &Expr,
+    width: u64,
+    signed: bool,
+    lookup: &impl ValueLookup,
+) -> LogicValue {
+    let context = || {
+        (
+            eval(lhs, width, signed, lookup),
+            eval(rhs, width, signed, lookup),
+        )
+    };
+    // Comparison operands are sized and signed by each other alone
+    let compared_signed = is_signed(lhs, lookup) && is_signed(rhs, lookup);
+    let compared = || {
+        let operand_width = expr_width(lhs, lookup).max(expr_width(rhs, lookup));
+        (
+            eval(lhs, operand_width, compared_signed, lookup),
+            eval(rhs, operand_width, compared_signed, lookup),
+        )
+    };
+    let ordering = |accept: fn(Ordering) -> bool| {
+        let (lhs, rhs) = compared();
+        let ord = match compared_signed {
+            true => lhs.signed_compare(&rhs),
+            false => lhs.compare(&rhs),
+        };
+        match ord {
+            Some(ord) => LogicState::from(accept(ord)),
+            None => LogicState::X,
+        }
+    };
+
+    let bit = match op {
+        BinOp::Add => return context_op(context(), |a, b| a + b),
+        BinOp::Sub => return context_op(context(), |a, b| a - b),
+        BinOp::Mul => return context_op(context(), |a, b| a * b),
+        BinOp::Div if signed => return context_op(context(), LogicValue::signed_div),
+        BinOp::Div => return context_op(context(), LogicValue::div),
+        BinOp::Mod if signed => return context_op(context(), LogicValue::signed_rem),
+        BinOp::Mod => return context_op(context(), LogicValue::rem),
+        BinOp::BitAnd => return context_op(context(), |a, b| a & b),
+        BinOp::BitOr => return context_op(context(), |a, b| a | b),
+        BinOp::BitXor => return context_op(context(), |a, b| a ^ b),
+        BinOp::BitXnor => return context_op(context(), |a, b| !&(a ^ b)),
+        BinOp::Pow => {
+            let base = eval(lhs, width, signed, lookup);
+            let exp = eval_expr(rhs, lookup);
+            return LogicValue::from_bits(sv_pow(base.bits(), exp.bits()));
+        }
+        BinOp::Shl | BinOp::AShl => {
+            return eval(lhs, width, signed, lookup).shl(&eval_expr(rhs, lookup))
+        }
+        BinOp::AShr if signed => {
+            return eval(lhs, width, signed, lookup).ashr(&eval_expr(rhs, lookup))
+        }
+        BinOp::Shr | BinOp::AShr => {
+            return eval(lhs, width, signed, lookup).shr(&eval_expr(rhs, lookup))
+        }
+        BinOp::Lt => ordering(Ordering::is_lt),
+        BinOp::Lte => ordering(Ordering::is_le),
+        BinOp::Gt => ordering(Ordering::is_gt),
+        BinOp::Gte => ordering(Ordering::is_ge),
+        BinOp::Eq => {
+            let (lhs, rhs) = compared();
+            lhs.logic_eq(&rhs)
+        }
+        BinOp::Neq => {
+            let (lhs, rhs) = compared();
+            !lhs.logic_eq(&rhs)
+        }
+        BinOp::CaseEq => {
+            let (lhs, rhs) = compared();
+            LogicState::from(lhs.case_eq(&rhs))
+        }
+        BinOp::CaseNeq => {
+            let (lhs, rhs) = compared();
+            LogicState::from(!lhs.case_eq(&rhs))
+        }
+        BinOp::LogAnd => eval_expr(lhs, lookup).truth() & eval_expr(rhs, lookup).truth(),
+        BinOp::LogOr => eval_expr(lhs, lookup).truth() | eval_expr(rhs, lookup).truth(),
+    };
+
+    LogicValue::from_bits(vec![bit])
+}
+
+/// Applies a context-determined operator to its evaluated operands
+fn context_op(
+    (lhs, rhs): (LogicValue, LogicValue),
+    op: fn(&LogicValue, &LogicValue) -> LogicValue,
+) -> LogicValue {
+    op(&lhs, &rhs)
+}
+
+/// Combines the two branches of a conditional with an unknown condition
+///
+/// Bits on which both branches agree keep their value, all others are X
+fn merge_unknown(then: &LogicValue, else_: &LogicValue) -> LogicValue {
+    LogicValue::from_bits(
+        then.bits()
+            .iter()
+            .zip(else_.bits())
+            .map(|(a, b)| if a == b { *a } else { LogicState::X })
+            .collect(),
+    )
+}
+
+/// Converts a string literal to its value, eight bits per character
+fn string_value(val: &str) -> LogicValue {
+    LogicValue::from_bits(
+        val.bytes()
+            .flat_map(|byte| {
+                (0..8)
+                    .rev()
+                    .map(move |idx| LogicState::from((byte >> idx) & 1 == 1))
+            })
+            .collect(),
+    )
+}
@@ -0,0 +1,1030 @@
+use crate::value::{LogicState, LogicValue};
+use crate::var_types::{parse_width, sv_identifier, VarType};
+use crate::{next_token, peek_token, LexingError, Token};
+use log::{error, trace, warn};
+use logos::Lexer;
+
+/// Width of an unsized integer literal
+const UNSIZED_WIDTH: u64 = 32;
+
+/// SystemVerilog expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// Reference to a signal or parameter by name
+    Ident(String),
+
+    /// Constant value, and whether it is signed
+    ///
+    /// Unbased decimal literals such as `7` are signed, while based literals
+    /// are unsigned unless written with `s`, as in `4'sd3`
+    Literal(LogicValue, bool),
+
+    /// String literal, with escape sequences kept as written
+    Str(String),
+
+    /// Unbased, unsized literal, `'0`, `'1`, `'x` or `'z`, setting every bit
+    /// of the expression around it to the same state
+    Fill(LogicState),
+
+    /// Operator applied to a single operand
+    UnaryOp(UnaryOp, Box<Expr>),
+
+    /// Operator applied to a left and right operand
+    BinOp(BinOp, Box<Expr>, Box<Expr>),
+
+    /// Conditional operator, `cond ? then : else_`
+    Ternary {
+        cond: Box<Expr>,
+        then: Box<Expr>,
+        else_: Box<Expr>,
+    },
+
+    /// Single bit of a value, `a[index]`
+    BitSelect(Box<Expr>, Box<Expr>),
+
+    /// Range of bits of a value, `a[msb:lsb]`
+    PartSelect(Box<Expr>, Box<Expr>, Box<Expr>),
+
+    /// Range of `width` bits starting at `base` and counting up,
+    /// `a[base +: width]`
+    PartSelectUp(Box<Expr>, Box<Expr>, Box<Expr>),
+
+    /// Range of `width` bits starting at `base` and counting down,
+    /// `a[base -: width]`
+    PartSelectDown(Box<Expr>, Box<Expr>, Box<Expr>),
+
+    /// Values joined most significant first, `{a, b}`
+    Concat(Vec<Expr>),
+
+    /// Value repeated a number of times, `{count{value}}`
+    Replicate { count: Box<Expr>, value: Box<Expr> },
+
+    /// Array literal, with the value of each element in order,
+    /// `'{val0, val1}`
+    ArrayLiteral(Vec<Expr>),
+
+    /// Structure literal, with the value of each field in order,
+    /// `'{field0: val0, field1: val1}`
+    StructLiteral(Vec<(String, Expr)>),
+
+    /// Array or structure literal giving every element the same value,
+    /// `'{default: val}`
+    DefaultLiteral(Box<Expr>),
+
+    /// Call to a system function, as in `$time`, by name without the leading
+    /// `$`
+    SysFunc(String, Vec<Expr>),
+
+    /// Value reinterpreted as signed or unsigned, `$signed(a)` or
+    /// `$unsigned(a)`
+    Cast(CastKind, Box<Expr>),
+
+    /// Call to a function defined in the module, as in `parity(data)`
+    FuncCall(String, Vec<Expr>),
+
+    /// Name or call resolved in the scope of a package or class, as the
+    /// scope and the name or call, as in `pkg::WIDTH` or `Class::f()`
+    Scoped(String, Box<Expr>),
+
+    /// Member of a structure, instance or class handle, as in `h.field`
+    Member(Box<Expr>, String),
+
+    /// Call to a method of a class handle, as in `h.f(a)`
+    MethodCall(Box<Expr>, String, Vec<Expr>),
+}
+
+/// Signedness a value is cast to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastKind {
+    /// `$signed(a)`
+    Signed,
+
+    /// `$unsigned(a)`
+    Unsigned,
+}
+
+impl From<&CastKind> for &str {
+    fn from(val: &CastKind) -> Self {
+        match val {
+            CastKind::Signed => "signed",
+            CastKind::Unsigned => "unsigned",
+        }
+    }
+}
+
+/// Prefix operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// `+a`
+    Plus,
+
+    /// `-a`
+    Negate,
+
+    /// `!a`
+    LogicalNot,
+
+    /// `~a`
+    BitwiseNot,
+
+    /// `&a`
+    ReduceAnd,
+
+    /// `|a`
+    ReduceOr,
+
+    /// `^a`
+    ReduceXor,
+
+    /// `~^a` or `^~a`
+    ReduceXnor,
+
+    /// `~&a`
+    ReduceNand,
+
+    /// `~|a`
+    ReduceNor,
+}
+
+/// Infix operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    /// `a ** b`
+    Pow,
+
+    /// `a * b`
+    Mul,
+
+    /// `a / b`
+    Div,
+
+    /// `a % b`
+    Mod,
+
+    /// `a + b`
+    Add,
+
+    /// `a - b`
+    Sub,
+
+    /// `a << b`
+    Shl,
+
+    /// `a >> b`
+    Shr,
+
+    /// `a <<< b`
+    AShl,
+
+    /// `a >>> b`
+    AShr,
+
+    /// `a < b`
+    Lt,
+
+    /// `a <= b`
+    Lte,
+
+    /// `a > b`
+    Gt,
+
+    /// `a >= b`
+    Gte,
+
+    /// `a == b`
+    Eq,
+
+    /// `a != b`
+    Neq,
+
+    /// `a === b`
+    CaseEq,
+
+    /// `a !== b`
+    CaseNeq,
+
+    /// `a & b`
+    BitAnd,
+
+    /// `a ^ b`
+    BitXor,
+
+    /// `a ~^ b` or `a ^~ b`
+    BitXnor,
+
+    /// `a | b`
+    BitOr,
+
+    /// `a && b`
+    LogAnd,
+
+    /// `a || b`
+    LogOr,
+}
+
+impl BinOp {
+    /// Binding strength of the operator, higher binds tighter
+    fn precedence(&self) -> u8 {
+        match self {
+            BinOp::Pow => 11,
+            BinOp::Mul | BinOp::Div | BinOp::Mod => 10,
+            BinOp::Add | BinOp::Sub => 9,
+            BinOp::Shl | BinOp::Shr | BinOp::AShl | BinOp::AShr => 8,
+            BinOp::Lt | BinOp::Lte | BinOp::Gt | BinOp::Gte => 7,
+            BinOp::Eq | BinOp::Neq | BinOp::CaseEq | BinOp::CaseNeq => 6,
+            BinOp::BitAnd => 5,
+            BinOp::BitXor | BinOp::BitXnor => 4,
+            BinOp::BitOr => 3,
+            BinOp::LogAnd => 2,
+            BinOp::LogOr => 1,
+        }
+    }
+}
+
+impl From<&UnaryOp> for &str {
+    fn from(val: &UnaryOp) -> Self {
+        match val {
+            UnaryOp::Plus => "+",
+            UnaryOp::Negate => "-",
+            UnaryOp::LogicalNot => "!",
+            UnaryOp::BitwiseNot => "~",
+            UnaryOp::ReduceAnd => "&",
+            UnaryOp::ReduceOr => "|",
+            UnaryOp::ReduceXor => "^",
+            UnaryOp::ReduceXnor => "~^",
+            UnaryOp::ReduceNand => "~&",
+            UnaryOp::ReduceNor => "~|",
+        }
+    }
+}
+
+impl From<&BinOp> for &str {
+    fn from(val: &BinOp) -> Self {
+        match val {
+            BinOp::Pow => "**",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Mod => "%",
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Shl => "<<",
+            BinOp::Shr => ">>",
+            BinOp::AShl => "<<<",
+            BinOp::AShr => ">>>",
+            BinOp::Lt => "<",
+            BinOp::Lte => "<=",
+            BinOp::Gt => ">",
+            BinOp::Gte => ">=",
+            BinOp::Eq => "==",
+            BinOp::Neq => "!=",
+            BinOp::CaseEq => "===",
+            BinOp::CaseNeq => "!==",
+            BinOp::BitAnd => "&",
+            BinOp::BitXor => "^",
+            BinOp::BitXnor => "~^",
+            BinOp::BitOr => "|",
+            BinOp::LogAnd => "&&",
+            BinOp::LogOr => "||",
+        }
+    }
+}
+
+/// Maps a token to the infix operator it represents, if any
+fn binary_op(token: &Token) -> Option<BinOp> {
+    match token {
+        Token::Power => Some(BinOp::Pow),
+        Token::Multiply => Some(BinOp::Mul),
+        Token::Divide => Some(BinOp::Div),
+        Token::Modulo => Some(BinOp::Mod),
+        Token::Add => Some(BinOp::Add),
+        Token::Subtract => Some(BinOp::Sub),
+        Token::SHL => Some(BinOp::Shl),
+        Token::SHR => Some(BinOp::Shr),
+        Token::ASHL => Some(BinOp::AShl),
+        Token::ASHR => Some(BinOp::AShr),
+        Token::BLT => Some(BinOp::Lt),
+        Token::BLTE => Some(BinOp::Lte),
+        Token::BGT => Some(BinOp::Gt),
+        Token::BGTE => Some(BinOp::Gte),
+        Token::BEQ => Some(BinOp::Eq),
+        Token::BNEQ => Some(BinOp::Neq),
+        Token::CaseEq => Some(BinOp::CaseEq),
+        Token::CaseNeq => Some(BinOp::CaseNeq),
+        Token::BitAnd => Some(BinOp::BitAnd),
+        Token::BitXor => Some(BinOp::BitXor),
+        Token::BitXnor => Some(BinOp::BitXnor),
+        Token::BitOr => Some(BinOp::BitOr),
+        Token::LogAnd => Some(BinOp::LogAnd),
+        Token::LogOr => Some(BinOp::LogOr),
+        _ => None,
+    }
+}
+
+/// Maps a token to the prefix operator it represents, if any
+fn unary_op(token: &Token) -> Option<UnaryOp> {
+    match token {
+        Token::Add => Some(UnaryOp::Plus),
+        Token::Subtract => Some(UnaryOp::Negate),
+        Token::LogicalNot => Some(UnaryOp::LogicalNot),
+        Token::BitNot => Some(UnaryOp::BitwiseNot),
+        Token::BitAnd => Some(UnaryOp::ReduceAnd),
+        Token::BitOr => Some(UnaryOp::ReduceOr),
+        Token::BitXor => Some(UnaryOp::ReduceXor),
+        Token::BitXnor => Some(UnaryOp::ReduceXnor),
+        Token::ReductionNand => Some(UnaryOp::ReduceNand),
+        Token::ReductionNor => Some(UnaryOp::ReduceNor),
+        _ => None,
+    }
+}
+
+/// Parses an expression to completion
+///
+/// Parsing stops before the first token that cannot continue the expression,
+/// such as `;` or `)`, leaving it for the caller. Operators bind following
+/// the SystemVerilog precedence rules, with `**` and `?:` grouping right to
+/// left and all other binary operators left to right. The conditional
+/// operator binds least tightly of all
+pub fn parse_expr<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Expr, LexingError> {
+    trace!("parsing expression");
+
+    let cond = parse_binary(lexer, 0)?;
+    if peek_token(lexer) != Some(Ok(Token::QMark)) {
+        return Ok(cond);
+    }
+    next_token(lexer);
+
+    let then = parse_expr(lexer)?;
+    expect_close(lexer, Token::Colon, ":")?;
+    let else_ = parse_expr(lexer)?;
+
+    Ok(Expr::Ternary {
+        cond: Box::new(cond),
+        then: Box::new(then),
+        else_: Box::new(else_),
+    })
+}
+
+/// Parses binary operations binding at least as tightly as `min_precedence`
+fn parse_binary<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    min_precedence: u8,
+) -> Result<Expr, LexingError> {
+    let mut lhs = parse_unary(lexer)?;
+
+    while let Some(Ok(token)) = peek_token(lexer) {
+        let op = match binary_op(&token) {
+            Some(op) if op.precedence() >= min_precedence => op,
+            _ => break,
+        };
+        next_token(lexer);
+
+        let rhs = parse_binary(lexer, op.precedence() + 1)?;
+        lhs = Expr::BinOp(op, Box::new(lhs), Box::new(rhs));
+    }
+
+    Ok(lhs)
+}
+
+/// Parses an operand along with any prefix operators applied to it
+fn parse_unary<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Expr, LexingError> {
+    match next_token(lexer) {
+        Some(Ok(token)) => match unary_op(&token) {
+            Some(op) => Ok(Expr::UnaryOp(op, Box::new(parse_unary(lexer)?))),
+            None => parse_primary(lexer, token),
+        },
+        Some(Err(e)) => {
+            error!(
+                "unexpected error occurred parsing expression: '{}'",
+                lexer.slice()
+            );
+            Err(e)
+        }
+        None => {
+            error!("expression not completed before end of file");
+            Err(LexingError::UnexpectedToken)
+        }
+    }
+}
+
+/// Parses an identifier, literal or parenthesized expression starting with
+/// the already consumed `token`
+fn parse_primary<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    token: Token,
+) -> Result<Expr, LexingError> {
+    match token {
+        Token::Word | Token::Underscore | Token::EscapedIdentifier(_) => {
+            let name = parse_ident(lexer);
+            let expr = match peek_token(lexer) {
+                Some(Ok(Token::DoubleColon)) => parse_scoped(lexer, name)?,
+                Some(Ok(Token::OpenParen)) => {
+                    trace!("parsing function call {}", name);
+
+                    let args = parse_call_args(lexer, &name)?;
+                    return Ok(Expr::FuncCall(name, args));
+                }
+                _ => Expr::Ident(name),
+            };
+            let expr = parse_selects(lexer, expr)?;
+            parse_members(lexer, expr)
+        }
+        Token::OpenBrace => parse_concat(lexer),
+        Token::TickBrace => parse_assignment_pattern(lexer),
+        Token::Signed | Token::Unsigned => parse_cast(lexer, token),
+        Token::SystemIdent(name) => parse_sys_func(lexer, name),
+        Token::Integer(val) => Ok(Expr::Literal(integer_literal(val), true)),
+        Token::StringLiteral(val) => Ok(Expr::Str(val)),
+        Token::UnbasedValue(state) => Ok(Expr::Fill(state)),
+        Token::BinaryValue(val)
+        | Token::OctalValue(val)
+        | Token::DecimalValue(val)
+        | Token::HexValue(val) => Ok(Expr::Literal(LogicValue::from(&val), val.signed)),
+        Token::OpenParen => {
+            let expr = parse_expr(lexer)?;
+            expect_close(lexer, Token::CloseParen, ")")?;
+            Ok(expr)
+        }
+        _ => {
+            error!("unexpected token parsing expression: '{}'", lexer.slice());
+            Err(LexingError::UnexpectedToken)
+        }
+    }
+}
+
+/// Parses the rest of a name or call in the scope `scope`, whose name has
+/// already been consumed, as in `pkg::WIDTH` or `Class::f()`
+///
+/// Scopes may be nested, as in `pkg::Class::f()`, and the innermost name is
+/// read or called as in any other expression
+fn parse_scoped<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    scope: String,
+) -> Result<Expr, LexingError> {
+    trace!("parsing name in scope {}", scope);
+
+    next_token(lexer);
+    let name = match next_token(lexer) {
+        Some(Ok(Token::Word)) | Some(Ok(Token::EscapedIdentifier(_))) => parse_ident(lexer),
+        _ => {
+            error!("expected name after '{}::': '{}'", scope, lexer.slice());
+            return Err(LexingError::UnexpectedToken);
+        }
+    };
+
+    let item = match peek_token(lexer) {
+        Some(Ok(Token::DoubleColon)) => parse_scoped(lexer, name)?,
+        Some(Ok(Token::OpenParen)) => {
+            let args = parse_call_args(lexer, &format!("{}::{}", scope, name))?;
+            Expr::FuncCall(name, args)
+        }
+        _ => Expr::Ident(name),
+    };
+
+    Ok(Expr::Scoped(scope, Box::new(item)))
+}
+
+/// Parses any member accesses and method calls applied to `base`, each
+/// followed by its own selects, as in `h.data[3:0]` or `h.next.f(a)`
+fn parse_members<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    base: Expr,
+) -> Result<Expr, LexingError> {
+    let mut expr = base;
+
+    while peek_token(lexer) == Some(Ok(Token::Dot)) {
+        next_token(lexer);
+        let name = match next_token(lexer) {
+            Some(Ok(Token::Word)) | Some(Ok(Token::EscapedIdentifier(_))) => parse_ident(lexer),
+            _ => {
+                error!("expected member name after '.': '{}'", lexer.slice());
+                return Err(LexingError::UnexpectedToken);
+            }
+        };
+        trace!("parsing member {}", name);
+
+        expr = match peek_token(lexer) {
+            Some(Ok(Token::OpenParen)) => {
+                let args = parse_call_args(lexer, &name)?;
+                Expr::MethodCall(Box::new(expr), name, args)
+            }
+            _ => Expr::Member(Box::new(expr), name),
+        };
+        expr = parse_selects(lexer, expr)?;
+    }
+
+    Ok(expr)
+}
+
+/// Parses a cast to the signedness named by the already consumed `token`, as
+/// in `signed'(a)`, to completion
+///
+/// The cast is the same as a call to `$signed` or `$unsigned`
+fn parse_cast<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    token: Token,
+) -> Result<Expr, LexingError> {
+    let kind = match token {
+        Token::Signed => CastKind::Signed,
+        _ => CastKind::Unsigned,
+    };
+    let name = <&str>::from(&kind);
+    trace!("parsing cast to {}", name);
+
+    if next_token(lexer) != Some(Ok(Token::TickParen)) {
+        error!("expected \"'(\" after '{}', got '{}'", name, lexer.slice());
+        return Err(LexingError::UnexpectedToken);
+    }
+    let expr = parse_expr(lexer)?;
+    expect_close(lexer, Token::CloseParen, ")")?;
+
+    Ok(Expr::Cast(kind, Box::new(expr)))
+}
+
+/// Parses a call to the system function `name`, whose name has already been
+/// consumed
+///
+/// The argument list is optional, so `$time` and `$time()` are the same call.
+/// `$signed` and `$unsigned` take a single argument and are parsed as casts,
+/// and `$bits` of a type is parsed as the width of the type
+fn parse_sys_func<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    name: String,
+) -> Result<Expr, LexingError> {
+    trace!("parsing system function call ${}", name);
+
+    if name == "bits" {
+        let mut ahead = lexer.clone();
+        if next_token(&mut ahead) == Some(Ok(Token::OpenParen)) && starts_type(&mut ahead) {
+            next_token(lexer);
+            return parse_type_width(lexer);
+        }
+    }
+
+    let args = match peek_token(lexer) {
+        Some(Ok(Token::OpenParen)) => parse_call_args(lexer, &format!("${}", name))?,
+        _ => Vec::new(),
+    };
+
+    let kind = match name.as_str() {
+        "signed" => CastKind::Signed,
+        "unsigned" => CastKind::Unsigned,
+        _ => return Ok(Expr::SysFunc(name, args)),
+    };
+    match <[Expr; 1]>::try_from(args) {
+        Ok([arg]) => Ok(Expr::Cast(kind, Box::new(arg))),
+        Err(args) => {
+            error!("${} takes 1 argument, but is given {}", name, args.len());
+            Err(LexingError::UnexpectedToken)
+        }
+    }
+}
+
+/// Checks if the next token is a variable type keyword, as in `logic`
+fn starts_type(lexer: &mut Lexer<Token>) -> bool {
+    matches!(
+        next_token(lexer),
+        Some(Ok(Token::Logic
+            | Token::Reg
+            | Token::Byte
+            | Token::ShortInt
+            | Token::Int
+            | Token::LongInt
+            | Token::IntegerType))
+    )
+}
+
+/// Parses a type argument of `$bits`, as in `$bits(logic [WIDTH-1:0])`, to
+/// completion, giving the width of the type
+///
+/// The opening parenthesis has already been consumed. A range depending on
+/// parameters gives an expression working out its width from its bounds
+fn parse_type_width<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Expr, LexingError> {
+    next_token(lexer);
+    let mut width = VarType::from(lexer.slice()).width().unwrap_or(1);
+    let mut bounds = None;
+
+    loop {
+        match next_token(lexer) {
+            Some(Ok(Token::Signed)) | Some(Ok(Token::Unsigned)) => (),
+            Some(Ok(Token::OpenBracket)) => (width, bounds) = parse_width(lexer)?,
+            Some(Ok(Token::CloseParen)) => break,
+            _ => {
+                error!("expected ')' after type in $bits, got '{}'", lexer.slice());
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    }
+
+    let literal = |value: u64| Expr::Literal(integer_literal(value), true);
+    let Some(bounds) = bounds else {
+        return Ok(literal(width));
+    };
+    let span = Box::new(Expr::BinOp(
+        BinOp::Sub,
+        Box::new(bounds.left),
+        Box::new(bounds.right),
+    ));
+    Ok(Expr::BinOp(BinOp::Add, span, Box::new(literal(1))))
+}
+
+/// Parses the parenthesized arguments of a call to `name`, which start at
+/// the next token
+pub(crate) fn parse_call_args<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    name: &str,
+) -> Result<Vec<Expr>, LexingError> {
+    let mut args: Vec<Expr> = Vec::new();
+
+    next_token(lexer);
+    if peek_token(lexer) == Some(Ok(Token::CloseParen)) {
+        next_token(lexer);
+        return Ok(args);
+    }
+
+    loop {
+        args.push(parse_expr(lexer)?);
+        match next_token(lexer) {
+            Some(Ok(Token::Comma)) => (),
+            Some(Ok(Token::CloseParen)) => return Ok(args),
+            _ => {
+                error!(
+                    "expected ',' or ')' in arguments to {}: '{}'",
+                    name,
+                    lexer.slice()
+                );
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    }
+}
+
+/// Parses any bit and part selects applied to `base`, as in `mem[i][7:0]` or
+/// `data[i * 8 +: 8]`
+pub fn parse_selects<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    base: Expr,
+) -> Result<Expr, LexingError> {
+    let mut expr = base;
+
+    while peek_token(lexer) == Some(Ok(Token::OpenBracket)) {
+        next_token(lexer);
+        trace!("parsing select");
+
+        let index = parse_expr(lexer)?;
+        expr = match next_token(lexer) {
+            Some(Ok(Token::CloseBracket)) => Expr::BitSelect(Box::new(expr), Box::new(index)),
+            Some(Ok(Token::Colon)) => {
+                let lsb = parse_expr(lexer)?;
+                expect_close(lexer, Token::CloseBracket, "]")?;
+                Expr::PartSelect(Box::new(expr), Box::new(index), Box::new(lsb))
+            }
+            Some(Ok(Token::PlusColon)) => {
+                let width = parse_expr(lexer)?;
+                expect_close(lexer, Token::CloseBracket, "]")?;
+                Expr::PartSelectUp(Box::new(expr), Box::new(index), Box::new(width))
+            }
+            Some(Ok(Token::MinusColon)) => {
+                let width = parse_expr(lexer)?;
+                expect_close(lexer, Token::CloseBracket, "]")?;
+                Expr::PartSelectDown(Box::new(expr), Box::new(index), Box::new(width))
+            }
+            _ => {
+                error!(
+                    "expected ']', ':', '+:' or '-:' parsing select: '{}'",
+                    lexer.slice()
+                );
+                return Err(LexingError::IncompleteWidth);
+            }
+        };
+    }
+
+    Ok(expr)
+}
+
+/// Parses a concatenation or replication to completion
+///
+/// The opening `{` has already been consumed. A replication such as
+/// `{4{a, b}}` holds the concatenation `{a, b}` as its value, while a
+/// single replicated value is held directly
+pub fn parse_concat<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Expr, LexingError> {
+    trace!("parsing concatenation");
+
+    let first = parse_expr(lexer)?;
+
+    if peek_token(lexer) == Some(Ok(Token::OpenBrace)) {
+        next_token(lexer);
+        let mut values = parse_expr_list(lexer)?;
+        expect_close(lexer, Token::CloseBrace, "}")?;
+
+        let value = if values.len() == 1 {
+            values.remove(0)
+        } else {
+            Expr::Concat(values)
+        };
+        return Ok(Expr::Replicate {
+            count: Box::new(first),
+            value: Box::new(value),
+        });
+    }
+
+    let mut values = vec![first];
+    if peek_token(lexer) == Some(Ok(Token::Comma)) {
+        next_token(lexer);
+        values.append(&mut parse_expr_list(lexer)?);
+    } else {
+        expect_close(lexer, Token::CloseBrace, "}")?;
+    }
+
+    Ok(Expr::Concat(values))
+}
+
+/// Parses an array or structure literal to completion
+///
+/// The opening `'{` has already been consumed. Values given as
+/// `name: value` make a structure literal, and values given on their own
+/// an array literal. The two cannot be mixed. `'{default: value}` gives every
+/// element the same value, and cannot be mixed with either
+///
+/// Without struct types to lay them out, the fields of a structure literal
+/// are packed in the order they are written, so a warning is logged for
+/// each one parsed
+pub fn parse_assignment_pattern<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<Expr, LexingError> {
+    trace!("parsing assignment pattern");
+
+    let mut ahead = lexer.clone();
+    if next_token(&mut ahead) == Some(Ok(Token::Default)) {
+        *lexer = ahead;
+        expect_close(lexer, Token::Colon, ":")?;
+        let value = parse_expr(lexer)?;
+        expect_close(lexer, Token::CloseBrace, "}")?;
+        return Ok(Expr::DefaultLiteral(Box::new(value)));
+    }
+
+    if field_name(&mut lexer.clone()).is_none() {
+        return Ok(Expr::ArrayLiteral(parse_expr_list(lexer)?));
+    }
+
+    let mut fields: Vec<(String, Expr)> = Vec::new();
+    loop {
+        let Some(name) = field_name(lexer) else {
+            error!(
+                "expected field name parsing structure literal: '{}'",
+                lexer.slice()
+            );
+            return Err(LexingError::UnexpectedToken);
+        };
+        fields.push((name, parse_expr(lexer)?));
+
+        match next_token(lexer) {
+            Some(Ok(Token::Comma)) => (),
+            Some(Ok(Token::CloseBrace)) => {
+                let names: Vec<&str> = fields.iter().map(|(name, _)| name.as_str()).collect();
+                warn!(
+                    "fields '{}' are packed in the order written, as struct types are not supported",
+                    names.join("', '")
+                );
+                return Ok(Expr::StructLiteral(fields));
+            }
+            _ => {
+                error!(
+                    "expected ',' or '}}' parsing structure literal: '{}'",
+                    lexer.slice()
+                );
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    }
+}
+
+/// Consumes a `name:` structure literal field name if one is next,
+/// returning the name
+fn field_name<'source>(lexer: &mut Lexer<'source, Token>) -> Option<String> {
+    let mut ahead = lexer.clone();
+
+    let name = match next_token(&mut ahead) {
+        Some(Ok(Token::Word)) | Some(Ok(Token::EscapedIdentifier(_))) => parse_ident(&mut ahead),
+        _ => return None,
+    };
+    if next_token(&mut ahead) != Some(Ok(Token::Colon)) {
+        return None;
+    }
+
+    *lexer = ahead;
+    Some(name)
+}
+
+/// Parses comma separated expressions up to and including a closing `}`
+fn parse_expr_list<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Vec<Expr>, LexingError> {
+    let mut values = vec![parse_expr(lexer)?];
+
+    loop {
+        match next_token(lexer) {
+            Some(Ok(Token::Comma)) => values.push(parse_expr(lexer)?),
+            Some(Ok(Token::CloseBrace)) => return Ok(values),
+            _ => {
+                error!(
+                    "expected ',' or '}}' parsing concatenation: '{}'",
+                    lexer.slice()
+                );
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    }
+}
+
+/// Consumes the closing token `expected`, written as `symbol` in errors
+fn expect_close<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    expected: Token,
+    symbol: &str,
+) -> Result<(), LexingError> {
+    match next_token(lexer) {
+        Some(Ok(token)) if token == expected => Ok(()),
+        _ => {
+            error!(
+                "expected '{}' parsing expression: '{}'",
+                symbol,
+                lexer.slice()
+            );
+            Err(LexingError::UnexpectedToken)
+        }
+    }
+}
+
+/// Formats an expression as SystemVerilog, e.g. `a & (b | c)`
+///
+/// Operands are parenthesized only where precedence requires it, so parsing
+/// the result gives back the same expression
+pub fn expr_sv_string(expr: &Expr) -> String {
+    match expr {
+        Expr::Ident(name) => sv_identifier(name),
+        Expr::Literal(val, false) => format!("{}'b{}", val.width(), val),
+        Expr::Literal(val, true) => format!("{}'sb{}", val.width(), val),
+        Expr::Str(val) => format!("\"{}\"", val),
+        Expr::Fill(state) => format!("'{}", state),
+        Expr::UnaryOp(op, operand) => {
+            let op: &str = op.into();
+            match **operand {
+                Expr::UnaryOp(..) | Expr::BinOp(..) | Expr::Ternary { .. } => {
+                    format!("{}({})", op, expr_sv_string(operand))
+                }
+                _ => format!("{}{}", op, expr_sv_string(operand)),
+            }
+        }
+        Expr::BinOp(op, lhs, rhs) => {
+            // Operators group left to right, so a right operand of equal
+            // precedence needs parentheses
+            let symbol: &str = op.into();
+
+            format!(
+                "{} {} {}",
+                operand_sv_string(lhs, op.precedence()),
+                symbol,
+                operand_sv_string(rhs, op.precedence() + 1)
+            )
+        }
+        Expr::Ternary { cond, then, else_ } => format!(
+            "{} ? {} : {}",
+            operand_sv_string(cond, 0),
+            expr_sv_string(then),
+            expr_sv_string(else_)
+        ),
+        Expr::BitSelect(base, index) => {
+            format!("{}[{}]", expr_sv_string(base), expr_sv_string(index))
+        }
+        Expr::PartSelect(base, msb, lsb) => format!(
+            "{}[{}:{}]",
+            expr_sv_string(base),
+            expr_sv_string(msb),
+            expr_sv_string(lsb)
+        ),
+        Expr::PartSelectUp(base, start, width) => format!(
+            "{}[{} +: {}]",
+            expr_sv_string(base),
+            expr_sv_string(start),
+            expr_sv_string(width)
+        ),
+        Expr::PartSelectDown(base, start, width) => format!(
+            "{}[{} -: {}]",
+            expr_sv_string(base),
+            expr_sv_string(start),
+            expr_sv_string(width)
+        ),
+        Expr::Concat(values) => format!(
+            "{{{}}}",
+            values
+                .iter()
+                .map(expr_sv_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Expr::Replicate { count, value } => match **value {
+            Expr::Concat(_) => format!("{{{}{}}}", expr_sv_string(count), expr_sv_string(value)),
+            _ => format!("{{{}{{{}}}}}", expr_sv_string(count), expr_sv_string(value)),
+        },
+        Expr::ArrayLiteral(values) => format!(
+            "'{{{}}}",
+            values
+                .iter()
+                .map(expr_sv_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Expr::StructLiteral(fields) => format!(
+            "'{{{}}}",
+            fields
+                .iter()
+                .map(|(name, value)| format!("{}: {}", sv_identifier(name), expr_sv_string(value)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Expr::DefaultLiteral(value) => format!("'{{default: {}}}", expr_sv_string(value)),
+        Expr::Cast(kind, operand) => {
+            let kind: &str = kind.into();
+            format!("${}({})", kind, expr_sv_string(operand))
+        }
+        Expr::SysFunc(name, args) if args.is_empty() => format!("${}", name),
+        Expr::SysFunc(name, args) => format!(
+            "${}({})",
+            name,
+            args.iter()
+                .map(expr_sv_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Expr::FuncCall(name, args) => format!(
+            "{}({})",
+            sv_identifier(name),
+            args.iter()
+                .map(expr_sv_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Expr::Scoped(scope, item) => format!("{}::{}", sv_identifier(scope), expr_sv_string(item)),
+        Expr::Member(base, name) => format!("{}.{}", expr_sv_string(base), sv_identifier(name)),
+        Expr::MethodCall(base, name, args) => format!(
+            "{}.{}({})",
+            expr_sv_string(base),
+            sv_identifier(name),
+            args.iter()
+                .map(expr_sv_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Formats an operand, parenthesizing binary operations binding less
+/// tightly than `min_precedence`
+fn operand_sv_string(expr: &Expr, min_precedence: u8) -> String {
+    match expr {
+        Expr::BinOp(op, ..) if op.precedence() < min_precedence => {
+            format!("({})", expr_sv_string(expr))
+        }
+        Expr::Ternary { .. } => format!("({})", expr_sv_string(expr)),
+        _ => expr_sv_string(expr),
+    }
+}
+
+/// Parses an identifier starting with the token last consumed by `lexer`
+///
+/// The lexer splits simple identifiers at underscores, digits and keywords,
+/// so adjacent pieces are joined back together. Escaped identifiers are
+/// returned without their leading backslash
+pub fn parse_ident<'source>(lexer: &mut Lexer<'source, Token>) -> String {
+    if let Some(name) = lexer.slice().strip_prefix('\\') {
+        return name.to_owned();
+    }
+
+    let mut name = lexer.slice().to_owned();
+    loop {
+        let mut ahead = lexer.clone();
+        match ahead.next() {
+            Some(Ok(_)) if is_ident_piece(ahead.slice()) => {
+                name += ahead.slice();
+                *lexer = ahead;
+            }
+            _ => break,
+        }
+    }
+
+    name
+}
+
+/// Checks if a slice may continue a simple identifier
+fn is_ident_piece(slice: &str) -> bool {
+    !slice.is_empty()
+        && slice
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Converts an unsized integer literal to its value
+///
+/// Unsized literals are 32 bits wide, or wider if the value needs more bits
+pub fn integer_literal(val: u64) -> LogicValue {
+    let width = UNSIZED_WIDTH.max(u64::from(u64::BITS - val.leading_zeros()));
+    LogicValue::from_u128(val as u128, width)
+}
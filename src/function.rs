@@ -0,0 +1,294 @@
+use crate::expr::{parse_expr, parse_ident, Expr};
+use crate::logic::{parse_statement_block, LogicStmt};
+use crate::module::PortDirection;
+use crate::value::LogicValue;
+use crate::var_types::{parse_width, Var, VarType};
+use crate::{expect_token, next_token, peek_token, LexingError, Token};
+use log::{error, trace};
+use logos::Lexer;
+
+/// Words of a declaration that are skipped rather than taken as its name
+const SKIPPED_WORDS: [&str; 4] = ["automatic", "static", "bit", "void"];
+
+/// Function defined in a module, as in
+/// `function [7:0] add(input [7:0] a, input [7:0] b); ... endfunction`
+#[derive(Debug, Clone)]
+pub struct FunctionDef {
+    /// Function name
+    pub name: String,
+
+    /// Value returned, held in a variable named after the function
+    pub ret: Var,
+
+    /// Input arguments, in order
+    pub args: Vec<Var>,
+
+    /// Variables declared in the function body
+    pub vars: Vec<Var>,
+
+    /// Statements run by each call
+    pub body: Vec<LogicStmt>,
+}
+
+/// Task defined in a module, as in
+/// `task send(input [7:0] data); ... endtask`
+#[derive(Debug, Clone)]
+pub struct TaskDef {
+    /// Task name
+    pub name: String,
+
+    /// Arguments, in order
+    pub args: Vec<Arg>,
+
+    /// Variables declared in the task body
+    pub vars: Vec<Var>,
+
+    /// Statements run by each call, which may include delays and event
+    /// controls
+    pub body: Vec<LogicStmt>,
+}
+
+/// Argument of a task or function
+#[derive(Debug, Clone)]
+pub struct Arg {
+    /// Whether the value is passed in, out or both
+    pub direction: PortDirection,
+
+    /// Argument name, type and width
+    pub var: Var,
+
+    /// Value used when a call leaves the argument out, as the `4` of
+    /// `input int count = 4`
+    pub default: Option<Expr>,
+}
+
+/// Arguments, variables and statements of a function or task
+#[derive(Default)]
+struct Body {
+    args: Vec<Arg>,
+    vars: Vec<Var>,
+    stmts: Vec<LogicStmt>,
+}
+
+/// Parses a function definition to completion
+///
+/// The `function` keyword has already been consumed. Arguments may be
+/// declared in a list after the name, as in `add(input a, input b);`, or as
+/// `input` declarations before the body. The value returned is the one given
+/// by a `return` statement, or otherwise the last one assigned to the
+/// function name
+pub fn parse_function<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<FunctionDef, LexingError> {
+    trace!("parsing function");
+
+    let ret = parse_typed_name(lexer)?;
+    let name = ret.name.to_owned();
+    let body = parse_body(lexer, &name, Token::EndFunction)?;
+
+    if body
+        .args
+        .iter()
+        .any(|arg| arg.direction != PortDirection::Input)
+    {
+        error!("arguments of function '{}' must be inputs", name);
+        return Err(LexingError::UnexpectedToken);
+    }
+    if body.args.iter().any(|arg| arg.default.is_some()) {
+        error!("function '{}' gives its arguments default values", name);
+        return Err(LexingError::UnexpectedToken);
+    }
+
+    Ok(FunctionDef {
+        name,
+        ret,
+        args: body.args.into_iter().map(|arg| arg.var).collect(),
+        vars: body.vars,
+        body: body.stmts,
+    })
+}
+
+/// Parses a task definition to completion
+///
+/// The `task` keyword has already been consumed. Arguments are declared as
+/// for `parse_function`, and may also be `output` or `inout`. An argument
+/// in the list after the name may have a default value, as in
+/// `input int count = 4`, used by calls that leave it out. The body may
+/// contain any procedural statement, including delays, event controls and
+/// a `return;` ending the call early
+pub fn parse_task<'source>(lexer: &mut Lexer<'source, Token>) -> Result<TaskDef, LexingError> {
+    trace!("parsing task");
+
+    let name = parse_typed_name(lexer)?.name;
+    let body = parse_body(lexer, &name, Token::EndTask)?;
+
+    Ok(TaskDef {
+        name,
+        args: body.args,
+        vars: body.vars,
+        body: body.stmts,
+    })
+}
+
+/// Parses the argument list, declarations and statements following the name
+/// of the function or task `name`, up to and including the `end` keyword and
+/// any label after it
+fn parse_body<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    name: &str,
+    end: Token,
+) -> Result<Body, LexingError> {
+    let mut body = Body::default();
+
+    if peek_token(lexer) == Some(Ok(Token::OpenParen)) {
+        next_token(lexer);
+        while peek_token(lexer) != Some(Ok(Token::CloseParen)) {
+            body.args.push(parse_arg(lexer, body.args.last())?);
+            if peek_token(lexer) == Some(Ok(Token::Comma)) {
+                next_token(lexer);
+            }
+        }
+        next_token(lexer);
+    }
+    expect_token(lexer, Token::Semicolon, "function or task header")?;
+
+    loop {
+        match peek_token(lexer) {
+            Some(Ok(token)) if token == end => break,
+            Some(Ok(Token::Input | Token::Output | Token::Inout)) => {
+                body.args.push(parse_arg(lexer, None)?);
+                expect_token(lexer, Token::Semicolon, "function or task argument")?;
+            }
+            Some(Ok(
+                Token::Reg
+                | Token::Logic
+                | Token::Var
+                | Token::Byte
+                | Token::ShortInt
+                | Token::Int
+                | Token::LongInt
+                | Token::IntegerType,
+            )) => {
+                body.vars.push(parse_typed_name(lexer)?);
+                expect_token(lexer, Token::Semicolon, "function or task variable")?;
+            }
+            Some(_) => body.stmts.append(&mut parse_statement_block(lexer)?),
+            None => {
+                error!("'{}' not closed with '{:?}'", name, end);
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    }
+
+    next_token(lexer);
+    if peek_token(lexer) == Some(Ok(Token::Colon)) {
+        next_token(lexer);
+        next_token(lexer);
+        parse_ident(lexer);
+    }
+
+    Ok(body)
+}
+
+/// Parses an argument, as in `input [7:0] a`, along with any default value
+/// after it, as in `input [7:0] a = 8'hff`
+///
+/// An argument given only by name, as the `b` of `input [7:0] a, b`, has the
+/// same direction and type as the `previous` one, but not its default.
+/// Otherwise the direction defaults to input
+fn parse_arg<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    previous: Option<&Arg>,
+) -> Result<Arg, LexingError> {
+    let (direction, var) = match (peek_token(lexer), previous) {
+        (Some(Ok(Token::Word | Token::EscapedIdentifier(_))), Some(previous)) => {
+            next_token(lexer);
+            let var = Var {
+                name: parse_ident(lexer),
+                ..previous.var.clone()
+            };
+            (previous.direction, var)
+        }
+        (Some(Ok(token @ (Token::Input | Token::Output | Token::Inout))), _) => {
+            next_token(lexer);
+            let direction = match token {
+                Token::Input => PortDirection::Input,
+                Token::Output => PortDirection::Output,
+                _ => PortDirection::Inout,
+            };
+            (direction, parse_typed_name(lexer)?)
+        }
+        _ => (PortDirection::Input, parse_typed_name(lexer)?),
+    };
+
+    let default = match peek_token(lexer) {
+        Some(Ok(Token::Equals)) => {
+            next_token(lexer);
+            Some(parse_expr(lexer)?)
+        }
+        _ => None,
+    };
+
+    Ok(Arg {
+        direction,
+        var,
+        default,
+    })
+}
+
+/// Parses the optional type and range of a function, argument or variable
+/// followed by its name, as in `logic signed [7:0] data`
+///
+/// The token after the name is left to the caller. Without a type the value
+/// is a `logic`. Any of `SKIPPED_WORDS`, such as the lifetime qualifier
+/// `automatic`, are skipped
+fn parse_typed_name<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Var, LexingError> {
+    let mut var_type = VarType::Logic;
+    let mut width = None;
+    let mut bounds = None;
+    let mut signed = None;
+
+    loop {
+        match next_token(lexer) {
+            Some(Ok(
+                Token::Wire
+                | Token::Reg
+                | Token::Logic
+                | Token::Byte
+                | Token::ShortInt
+                | Token::Int
+                | Token::LongInt
+                | Token::IntegerType,
+            )) => var_type = VarType::from(lexer.slice()),
+            Some(Ok(Token::Var)) => (),
+            Some(Ok(Token::Signed)) => signed = Some(true),
+            Some(Ok(Token::Unsigned)) => signed = Some(false),
+            Some(Ok(Token::OpenBracket)) => {
+                let (val, val_bounds) = parse_width(lexer)?;
+                width = Some(val);
+                bounds = val_bounds;
+            }
+            Some(Ok(Token::Word)) if SKIPPED_WORDS.contains(&lexer.slice()) => (),
+            Some(Ok(Token::Word | Token::EscapedIdentifier(_))) => {
+                let width = width.or(var_type.width()).unwrap_or(1);
+
+                return Ok(Var {
+                    name: parse_ident(lexer),
+                    width,
+                    signed: signed.unwrap_or(var_type.signed()),
+                    var_type,
+                    state: LogicValue::new(width),
+                    bounds,
+                    ..Default::default()
+                });
+            }
+            _ => {
+                error!(
+                    "unexpected token parsing function or task declaration: '{}'",
+                    lexer.slice()
+                );
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    }
+}
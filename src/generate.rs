@@ -0,0 +1,1168 @@
+use crate::eval::{const_index, eval_expr, ConstLookup};
+use crate::expr::{integer_literal, parse_expr, parse_ident, BinOp, Expr};
+use crate::logic::{
+    parse_assignment_body, parse_operator_assignment, skip_loop_var_type, AlwaysComb, AlwaysFF,
+    AlwaysLatch, AssignStmt, CaseItem, Delay, EdgeEvent, EventTrigger, FinalBlock, ForLoop, IfStmt,
+    InitialBlock, LogicStmt, LoopStmt,
+};
+use crate::module::{parse_module_item, Module, ModuleInst, ParamOverride, PortConn};
+use crate::param::Param;
+use crate::value::{LogicState, LogicValue};
+use crate::var_types::{Bounds, TypeExpr, Var, VarType};
+use crate::{expect_token, next_token, peek_token, ElabError, LexingError, Token};
+use log::{error, trace};
+use logos::Lexer;
+use std::collections::{HashMap, HashSet};
+
+/// Most iterations a single generate loop may unroll before it is assumed
+/// never to finish
+pub const MAX_GENERATE_ITERATIONS: usize = 65_536;
+
+/// Generate construct, repeating or selecting module items during
+/// elaboration
+#[derive(Debug, Clone)]
+pub enum GenBlock {
+    /// Loop generate, as in
+    /// `for (genvar i = 0; i < N; i++) begin : gen ... end`
+    For {
+        /// Loop variable
+        genvar: String,
+
+        /// Whether the loop header declares the genvar, as in
+        /// `for (genvar i = 0; ...)`, rather than it being declared before
+        declared: bool,
+
+        /// Value of the loop variable in the first iteration
+        init: Expr,
+
+        /// Condition checked before each iteration
+        cond: Expr,
+
+        /// Value of the loop variable in the next iteration. Increments such
+        /// as `i++` are stored as the equivalent `i + 1`
+        step: Expr,
+
+        /// Items repeated for each iteration
+        body: GenBody,
+    },
+
+    /// Conditional generate, along with any `else if` and `else` branches
+    If {
+        /// Condition of the leading `if`
+        cond: Expr,
+
+        /// Items kept when `cond` is true
+        then: GenBody,
+
+        /// `else if` branches, as condition and items, in source order
+        else_if: Vec<(Expr, GenBody)>,
+
+        /// Items kept when no condition is true
+        else_: Option<GenBody>,
+    },
+
+    /// Case generate, as in `case (MODE) 0: ... default: ... endcase`
+    Case {
+        /// Value compared against the labels of each item
+        expr: Expr,
+
+        /// Labels and items of each case item, in source order
+        items: Vec<(Vec<Expr>, GenBody)>,
+
+        /// Items kept when no label matches
+        default: Option<GenBody>,
+    },
+}
+
+/// Items of a generate block, as in `begin : gen ... end`
+#[derive(Debug, Clone, Default)]
+pub struct GenBody {
+    /// Block name, `None` for an unnamed block
+    pub label: Option<String>,
+
+    /// Items of the block, held as a module without a name or ports
+    pub items: Box<Module>,
+}
+
+/// Parses a `generate`/`endgenerate` region to completion, adding its items
+/// to `module`
+///
+/// The `generate` keyword has already been consumed. A region only groups
+/// items, so they are added as if written directly in the module
+pub fn parse_generate_region<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    module: &mut Module,
+) -> Result<(), LexingError> {
+    trace!("parsing generate region");
+
+    while let Some(token) = lexer.next() {
+        match token {
+            Ok(Token::EndGenerate) => return Ok(()),
+            Ok(Token::EndModule) => break,
+            token => parse_module_item(lexer, token, module)?,
+        }
+    }
+
+    error!("generate region not closed with 'endgenerate'");
+    Err(LexingError::UnexpectedToken)
+}
+
+/// Parses a loop generate construct to completion
+///
+/// The `for` keyword has already been consumed. The genvar may be declared
+/// in the header, as in `for (genvar i = 0; ...)`, and the step accepts the
+/// same increments as a procedural for loop
+pub fn parse_generate_for<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<GenBlock, LexingError> {
+    trace!("parsing generate for loop");
+
+    expect_token(lexer, Token::OpenParen, "generate for loop")?;
+    let declared = peek_token(lexer) == Some(Ok(Token::Genvar));
+    skip_loop_var_type(lexer);
+    let (genvar, init) = genvar_assignment(parse_assignment_body(lexer)?)?;
+    expect_token(lexer, Token::Semicolon, "generate for loop")?;
+    let cond = parse_expr(lexer)?;
+    expect_token(lexer, Token::Semicolon, "generate for loop")?;
+    let (stepped, step) = genvar_assignment(parse_operator_assignment(lexer)?)?;
+    expect_token(lexer, Token::CloseParen, "generate for loop")?;
+
+    if stepped != genvar {
+        error!(
+            "generate for loop steps '{}' rather than its genvar '{}'",
+            stepped, genvar
+        );
+        return Err(LexingError::UnexpectedToken);
+    }
+
+    Ok(GenBlock::For {
+        genvar,
+        declared,
+        init,
+        cond,
+        step,
+        body: parse_generate_body(lexer)?,
+    })
+}
+
+/// Parses a conditional generate construct to completion
+///
+/// The `if` keyword has already been consumed. Any `else if` branches that
+/// follow are collected in order, up to an optional final `else`
+pub fn parse_generate_if<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<GenBlock, LexingError> {
+    trace!("parsing generate if");
+
+    let (cond, then) = parse_generate_branch(lexer)?;
+    let mut else_if: Vec<(Expr, GenBody)> = Vec::new();
+    let mut else_: Option<GenBody> = None;
+
+    while peek_token(lexer) == Some(Ok(Token::Else)) {
+        next_token(lexer);
+
+        if peek_token(lexer) == Some(Ok(Token::If)) {
+            next_token(lexer);
+            else_if.push(parse_generate_branch(lexer)?);
+        } else {
+            else_ = Some(parse_generate_body(lexer)?);
+            break;
+        }
+    }
+
+    Ok(GenBlock::If {
+        cond,
+        then,
+        else_if,
+        else_,
+    })
+}
+
+/// Parses a case generate construct to completion
+///
+/// The `case` keyword has already been consumed. As in a case statement,
+/// each item lists one or more comma separated labels, and at most one
+/// `default` item may appear, with or without a colon
+pub fn parse_generate_case<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<GenBlock, LexingError> {
+    trace!("parsing generate case");
+
+    let mut items: Vec<(Vec<Expr>, GenBody)> = Vec::new();
+    let mut default: Option<GenBody> = None;
+
+    expect_token(lexer, Token::OpenParen, "generate case")?;
+    let expr = parse_expr(lexer)?;
+    expect_token(lexer, Token::CloseParen, "generate case")?;
+
+    loop {
+        match peek_token(lexer) {
+            Some(Ok(Token::EndCase)) => {
+                next_token(lexer);
+                break;
+            }
+            Some(Ok(Token::Default)) => {
+                next_token(lexer);
+                if peek_token(lexer) == Some(Ok(Token::Colon)) {
+                    next_token(lexer);
+                }
+                if default.is_some() {
+                    error!("generate case has more than one default item");
+                    return Err(LexingError::UnexpectedToken);
+                }
+                default = Some(parse_generate_body(lexer)?);
+            }
+            Some(Ok(_)) => {
+                let mut labels = vec![parse_expr(lexer)?];
+                while peek_token(lexer) == Some(Ok(Token::Comma)) {
+                    next_token(lexer);
+                    labels.push(parse_expr(lexer)?);
+                }
+                expect_token(lexer, Token::Colon, "generate case item")?;
+                items.push((labels, parse_generate_body(lexer)?));
+            }
+            Some(Err(e)) => {
+                next_token(lexer);
+                error!(
+                    "unexpected error occurred parsing generate case: '{}'",
+                    lexer.slice()
+                );
+                return Err(e);
+            }
+            None => {
+                error!("generate case not closed before end of file");
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    }
+
+    Ok(GenBlock::Case {
+        expr,
+        items,
+        default,
+    })
+}
+
+/// Splits the assignment in a generate loop header into the genvar and the
+/// value assigned to it
+fn genvar_assignment(stmt: LogicStmt) -> Result<(String, Expr), LexingError> {
+    match stmt {
+        LogicStmt::BlockingAssign {
+            lhs: Expr::Ident(genvar),
+            rhs,
+        } => Ok((genvar, rhs)),
+        _ => {
+            error!("generate for loop must assign its genvar, got {:?}", stmt);
+            Err(LexingError::UnexpectedToken)
+        }
+    }
+}
+
+/// Parses the parenthesized condition and items of an `if` or `else if`
+/// generate branch
+fn parse_generate_branch<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<(Expr, GenBody), LexingError> {
+    expect_token(lexer, Token::OpenParen, "generate if")?;
+    let cond = parse_expr(lexer)?;
+    expect_token(lexer, Token::CloseParen, "generate if")?;
+
+    Ok((cond, parse_generate_body(lexer)?))
+}
+
+/// Parses the items of a generate block, either a `begin`/`end` block or a
+/// single item
+fn parse_generate_body<'source>(lexer: &mut Lexer<'source, Token>) -> Result<GenBody, LexingError> {
+    let mut body = GenBody::default();
+
+    if peek_token(lexer) != Some(Ok(Token::Begin)) {
+        let Some(token) = next_token(lexer) else {
+            error!("generate block not completed before end of file");
+            return Err(LexingError::UnexpectedToken);
+        };
+        parse_module_item(lexer, token, &mut body.items)?;
+        return Ok(body);
+    }
+
+    trace!("parsing generate begin/end block");
+
+    next_token(lexer);
+    body.label = parse_generate_label(lexer)?;
+
+    while let Some(token) = lexer.next() {
+        match token {
+            Ok(Token::End) => {
+                parse_generate_label(lexer)?;
+                return Ok(body);
+            }
+            Ok(Token::EndGenerate) | Ok(Token::EndModule) => break,
+            token => parse_module_item(lexer, token, &mut body.items)?,
+        }
+    }
+
+    error!("generate block not closed with 'end'");
+    Err(LexingError::UnexpectedToken)
+}
+
+/// Parses an optional `: name` label following `begin` or `end`
+fn parse_generate_label<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<Option<String>, LexingError> {
+    if peek_token(lexer) != Some(Ok(Token::Colon)) {
+        return Ok(None);
+    }
+    next_token(lexer);
+
+    match next_token(lexer) {
+        Some(Ok(Token::Word)) | Some(Ok(Token::EscapedIdentifier(_))) => {
+            Ok(Some(parse_ident(lexer)))
+        }
+        _ => {
+            error!("expected generate block label: '{}'", lexer.slice());
+            Err(LexingError::UnexpectedToken)
+        }
+    }
+}
+
+impl Module {
+    /// Values of the module's parameters, with `overrides` replacing the
+    /// defaults of the parameters they name
+    ///
+    /// Parameters are evaluated in declaration order, so each may use those
+    /// before it. Localparams keep their default values
+    pub fn param_values(
+        &self,
+        overrides: &HashMap<String, LogicValue>,
+    ) -> HashMap<String, LogicValue> {
+        let mut values: HashMap<String, LogicValue> = HashMap::new();
+
+        for param in self.io.params.iter().chain(&self.params) {
+            let lookup = ConstLookup {
+                values: &values,
+                functions: &self.functions,
+            };
+            let value = param.resolve(overrides, &lookup);
+            values.insert(param.name.to_owned(), value);
+        }
+
+        values
+    }
+
+    /// Copy of the module with its generate blocks unrolled, using `params`
+    /// as the values of its parameters
+    ///
+    /// A loop generate repeats its items for each value of its genvar, a
+    /// conditional generate keeps the items of the first branch whose
+    /// condition holds, and a case generate those of the first item with a
+    /// label equal to its value. Names declared in a generate block are
+    /// prefixed with the block name, as in `gen[2].carry` for a loop or
+    /// `fast.carry` otherwise, and the genvar is replaced by its value.
+    /// Unnamed blocks are called `genblk1`, `genblk2` and so on, by their
+    /// position in the module.
+    ///
+    /// Fails with `ElabError::UndeclaredGenvar` if a loop steps a variable
+    /// not declared as a genvar, with `ElabError::UnknownGenerateValue` if a
+    /// condition or genvar value is not a known constant, or with
+    /// `ElabError::GenerateLoopLimit` if a loop runs more than
+    /// `MAX_GENERATE_ITERATIONS` times
+    pub fn generate(&self, params: &HashMap<String, LogicValue>) -> Result<Module, ElabError> {
+        trace!("unrolling generate blocks of '{}'", self.name);
+
+        let mut genvars: HashSet<&str> = HashSet::new();
+        collect_genvars(self, &mut genvars);
+        if let Some(name) = undeclared_genvar(self, &genvars) {
+            error!(
+                "'{}' steps a generate loop in '{}' but is not declared as a genvar",
+                name, self.name
+            );
+            return Err(ElabError::UndeclaredGenvar(name.to_owned()));
+        }
+
+        let mut module = Module {
+            generates: Vec::new(),
+            ..self.clone()
+        };
+        let mut values = params.clone();
+
+        for (index, block) in self.generates.iter().enumerate() {
+            unroll(block, "", index, &mut values, &mut module)?;
+        }
+        module.apply_bounds(params)?;
+
+        Ok(module)
+    }
+
+    /// Works out the widths and memory address ranges that depend on
+    /// parameters, using `params` as their values
+    ///
+    /// A range may also use the widths of the values declared before it, as
+    /// in `[$bits(addr)-1:0]`
+    ///
+    /// Fails with `ElabError::InvalidWidth` if a range is not constant, or
+    /// is negative or too wide
+    fn apply_bounds(&mut self, params: &HashMap<String, LogicValue>) -> Result<(), ElabError> {
+        // Function variables are among those being worked out
+        let functions = self.functions.clone();
+        let io = &mut self.io;
+        let vars = io
+            .inputs
+            .iter_mut()
+            .map(|input| &mut input.var)
+            .chain(io.outputs.iter_mut().map(|output| &mut output.var))
+            .chain(io.inouts.iter_mut().map(|inout| &mut inout.var))
+            .chain(self.vars.iter_mut())
+            .chain(self.functions.iter_mut().flat_map(|func| {
+                std::iter::once(&mut func.ret)
+                    .chain(func.args.iter_mut())
+                    .chain(func.vars.iter_mut())
+            }))
+            .chain(self.tasks.iter_mut().flat_map(|task| {
+                task.args
+                    .iter_mut()
+                    .map(|arg| &mut arg.var)
+                    .chain(task.vars.iter_mut())
+            }));
+
+        let mut values = params.clone();
+        for var in vars {
+            let lookup = ConstLookup {
+                values: &values,
+                functions: &functions,
+            };
+            if (var.bounds.is_some() || var.array_bounds.is_some())
+                && var.apply_bounds(&lookup) != Ok(true)
+            {
+                error!(
+                    "range of '{}' in '{}' is not a valid constant range",
+                    var.name, self.name
+                );
+                return Err(ElabError::InvalidWidth(var.name.to_owned()));
+            }
+
+            // The first and last elements are enough to give the size of a
+            // memory
+            let names = match var.array {
+                Some((first, last)) => vec![
+                    format!("{}[{}]", var.name, first),
+                    format!("{}[{}]", var.name, last),
+                ],
+                None => vec![var.name.to_owned()],
+            };
+            for name in names {
+                values.entry(name).or_insert_with(|| var.state.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks if the module instantiates the module `name`, directly or
+    /// within any branch of a generate block
+    pub fn instantiates(&self, name: &str) -> bool {
+        self.instances.iter().any(|inst| inst.module_name == name)
+            || self
+                .generates
+                .iter()
+                .flat_map(|block| block.bodies())
+                .any(|body| body.items.instantiates(name))
+    }
+}
+
+impl GenBlock {
+    /// Every block of items the construct may generate
+    fn bodies(&self) -> Vec<&GenBody> {
+        match self {
+            GenBlock::For { body, .. } => vec![body],
+            GenBlock::If {
+                then,
+                else_if,
+                else_,
+                ..
+            } => std::iter::once(then)
+                .chain(else_if.iter().map(|(_, body)| body))
+                .chain(else_)
+                .collect(),
+            GenBlock::Case { items, default, .. } => {
+                items.iter().map(|(_, body)| body).chain(default).collect()
+            }
+        }
+    }
+}
+
+/// Adds the names of the genvars declared by `items`, and by the generate
+/// blocks within them, to `genvars`
+fn collect_genvars<'a>(items: &'a Module, genvars: &mut HashSet<&'a str>) {
+    let declared = items
+        .vars
+        .iter()
+        .filter(|var| matches!(var.var_type, VarType::Genvar));
+    genvars.extend(declared.map(|var| var.name.as_str()));
+
+    for body in items.generates.iter().flat_map(|block| block.bodies()) {
+        collect_genvars(&body.items, genvars);
+    }
+}
+
+/// First generate loop variable in `items`, or the generate blocks within
+/// them, that is neither declared by its loop header nor in `genvars`
+fn undeclared_genvar<'a>(items: &'a Module, genvars: &HashSet<&str>) -> Option<&'a str> {
+    items.generates.iter().find_map(|block| match block {
+        GenBlock::For {
+            genvar,
+            declared: false,
+            ..
+        } if !genvars.contains(genvar.as_str()) => Some(genvar.as_str()),
+        _ => block
+            .bodies()
+            .into_iter()
+            .find_map(|body| undeclared_genvar(&body.items, genvars)),
+    })
+}
+
+/// Unrolls a generate construct into `out`
+///
+/// `prefix` comes before the names of the blocks generated, and `index` is
+/// the position of the construct in its scope, naming unnamed blocks.
+/// `values` holds the parameters in scope, and gains those of the generated
+/// blocks
+fn unroll(
+    block: &GenBlock,
+    prefix: &str,
+    index: usize,
+    values: &mut HashMap<String, LogicValue>,
+    out: &mut Module,
+) -> Result<(), ElabError> {
+    let block_name = |body: &GenBody| match &body.label {
+        Some(label) => format!("{}{}", prefix, label),
+        None => format!("{}genblk{}", prefix, index + 1),
+    };
+
+    match block {
+        GenBlock::For {
+            genvar,
+            init,
+            cond,
+            step,
+            body,
+            ..
+        } => {
+            let name = block_name(body);
+            let mut value = genvar_value(init, &consts(values, out), &name)?;
+
+            for _ in 0..MAX_GENERATE_ITERATIONS {
+                values.insert(genvar.to_owned(), integer_literal(value));
+                match eval_expr(cond, &consts(values, out)).truth() {
+                    LogicState::One => (),
+                    LogicState::Zero => {
+                        values.remove(genvar);
+                        return Ok(());
+                    }
+                    _ => return Err(unknown_value(&name)),
+                }
+
+                let iteration = format!("{}[{}]", name, value);
+                add_items(body, &iteration, Some((genvar, value)), values, out)?;
+
+                values.insert(genvar.to_owned(), integer_literal(value));
+                value = genvar_value(step, &consts(values, out), &name)?;
+            }
+
+            error!(
+                "generate loop '{}' did not finish within {} iterations",
+                name, MAX_GENERATE_ITERATIONS
+            );
+            Err(ElabError::GenerateLoopLimit(name))
+        }
+        GenBlock::If {
+            cond,
+            then,
+            else_if,
+            else_,
+        } => {
+            let branches = std::iter::once((cond, then))
+                .chain(else_if.iter().map(|(cond, body)| (cond, body)));
+
+            for (cond, body) in branches {
+                match eval_expr(cond, &consts(values, out)).truth() {
+                    LogicState::One => {
+                        return add_items(body, &block_name(body), None, values, out)
+                    }
+                    LogicState::Zero => (),
+                    _ => return Err(unknown_value(&block_name(body))),
+                }
+            }
+
+            match else_ {
+                Some(body) => add_items(body, &block_name(body), None, values, out),
+                None => Ok(()),
+            }
+        }
+        GenBlock::Case {
+            expr,
+            items,
+            default,
+        } => {
+            for (labels, body) in items {
+                for label in labels {
+                    let eq =
+                        Expr::BinOp(BinOp::Eq, Box::new(expr.clone()), Box::new(label.clone()));
+                    match eval_expr(&eq, &consts(values, out)).truth() {
+                        LogicState::One => {
+                            return add_items(body, &block_name(body), None, values, out)
+                        }
+                        LogicState::Zero => (),
+                        _ => return Err(unknown_value(&block_name(body))),
+                    }
+                }
+            }
+
+            match default {
+                Some(body) => add_items(body, &block_name(body), None, values, out),
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+/// Values known while unrolling into `out`, along with the functions `out`
+/// defines
+fn consts<'a>(values: &'a HashMap<String, LogicValue>, out: &'a Module) -> ConstLookup<'a> {
+    ConstLookup {
+        values,
+        functions: &out.functions,
+    }
+}
+
+/// Evaluates a value given to a genvar
+fn genvar_value(expr: &Expr, lookup: &ConstLookup, name: &str) -> Result<u64, ElabError> {
+    const_index(expr, lookup).ok_or_else(|| unknown_value(name))
+}
+
+/// Error for the generate block `name` depending on an unknown value
+fn unknown_value(name: &str) -> ElabError {
+    error!(
+        "generate block '{}' depends on a value that is not constant",
+        name
+    );
+    ElabError::UnknownGenerateValue(name.to_owned())
+}
+
+/// Adds the items of a generated block named `block_name` to `out`
+///
+/// The names the block declares are prefixed with `block_name`, and the
+/// genvar of a loop, given with its value, is replaced by that value.
+/// Nested generate constructs are unrolled in turn
+fn add_items(
+    body: &GenBody,
+    block_name: &str,
+    genvar: Option<(&str, u64)>,
+    values: &mut HashMap<String, LogicValue>,
+    out: &mut Module,
+) -> Result<(), ElabError> {
+    trace!("generating block '{}'", block_name);
+
+    let mut names: HashMap<String, Expr> = declared_names(&body.items)
+        .into_iter()
+        .map(|name| {
+            let renamed = Expr::Ident(format!("{}.{}", block_name, name));
+            (name, renamed)
+        })
+        .collect();
+    if let Some((genvar, value)) = genvar {
+        names.insert(
+            genvar.to_owned(),
+            Expr::Literal(integer_literal(value), true),
+        );
+    }
+    let items = rename_module(&body.items, &names);
+
+    for param in &items.params {
+        let value = eval_expr(&param.value, &consts(values, out));
+        values.insert(param.name.to_owned(), value);
+    }
+
+    out.vars.extend(items.vars);
+    out.params.extend(items.params);
+    out.assigns.extend(items.assigns);
+    out.comb_blocks.extend(items.comb_blocks);
+    out.ff_blocks.extend(items.ff_blocks);
+    out.latch_blocks.extend(items.latch_blocks);
+    out.initial_blocks.extend(items.initial_blocks);
+    out.final_blocks.extend(items.final_blocks);
+    out.instances
+        .extend(items.instances.into_iter().map(|inst| ModuleInst {
+            inst_name: format!("{}.{}", block_name, inst.inst_name),
+            ..inst
+        }));
+
+    let prefix = format!("{}.", block_name);
+    for (index, nested) in items.generates.iter().enumerate() {
+        unroll(nested, &prefix, index, values, out)?;
+    }
+
+    Ok(())
+}
+
+/// Names of the variables and parameters declared directly in a generate
+/// block
+fn declared_names(items: &Module) -> Vec<String> {
+    items
+        .vars
+        .iter()
+        .map(|var| var.name.to_owned())
+        .chain(items.params.iter().map(|param| param.name.to_owned()))
+        .collect()
+}
+
+/// Copy of the items of a generate block with the names in `names`
+/// replaced, both where they are declared and where they are used
+fn rename_module(items: &Module, names: &HashMap<String, Expr>) -> Module {
+    let stmts = |body: &[LogicStmt]| rename_stmts(body, names);
+
+    Module {
+        vars: items
+            .vars
+            .iter()
+            .map(|var| Var {
+                name: rename_signal(&var.name, names),
+                bounds: var
+                    .bounds
+                    .as_ref()
+                    .map(|bounds| rename_bounds(bounds, names)),
+                array_bounds: var
+                    .array_bounds
+                    .as_ref()
+                    .map(|bounds| rename_bounds(bounds, names)),
+                ..var.clone()
+            })
+            .collect(),
+        params: items
+            .params
+            .iter()
+            .map(|param| Param {
+                name: rename_signal(&param.name, names),
+                value: rename_expr(&param.value, names),
+                param_type: param.param_type,
+                data_type: param.data_type.as_ref().map(|data_type| TypeExpr {
+                    bounds: data_type
+                        .bounds
+                        .as_ref()
+                        .map(|bounds| rename_bounds(bounds, names)),
+                    ..data_type.clone()
+                }),
+            })
+            .collect(),
+        assigns: items
+            .assigns
+            .iter()
+            .map(|assign| AssignStmt {
+                lhs: rename_expr(&assign.lhs, names),
+                rhs: rename_expr(&assign.rhs, names),
+                delay: assign
+                    .delay
+                    .as_ref()
+                    .map(|delay| rename_delay(delay, names)),
+                strength: assign.strength,
+            })
+            .collect(),
+        comb_blocks: items
+            .comb_blocks
+            .iter()
+            .map(|block| AlwaysComb {
+                body: stmts(&block.body),
+            })
+            .collect(),
+        ff_blocks: items
+            .ff_blocks
+            .iter()
+            .map(|block| AlwaysFF {
+                clock: rename_edge(&block.clock, names),
+                reset: block.reset.as_ref().map(|reset| rename_edge(reset, names)),
+                body: stmts(&block.body),
+            })
+            .collect(),
+        latch_blocks: items
+            .latch_blocks
+            .iter()
+            .map(|block| AlwaysLatch {
+                body: stmts(&block.body),
+            })
+            .collect(),
+        initial_blocks: items
+            .initial_blocks
+            .iter()
+            .map(|block| InitialBlock {
+                body: stmts(&block.body),
+                testbench: block.testbench.clone(),
+            })
+            .collect(),
+        final_blocks: items
+            .final_blocks
+            .iter()
+            .map(|block| FinalBlock {
+                body: stmts(&block.body),
+            })
+            .collect(),
+        instances: items
+            .instances
+            .iter()
+            .map(|inst| rename_inst(inst, names))
+            .collect(),
+        generates: items
+            .generates
+            .iter()
+            .map(|block| rename_generate(block, names))
+            .collect(),
+        ..items.clone()
+    }
+}
+
+/// Copy of a module instance with the names in `names` replaced in its
+/// parameter overrides and port connections
+fn rename_inst(inst: &ModuleInst, names: &HashMap<String, Expr>) -> ModuleInst {
+    let rename = |expr: &Option<Expr>| expr.as_ref().map(|expr| rename_expr(expr, names));
+
+    ModuleInst {
+        param_overrides: inst
+            .param_overrides
+            .iter()
+            .map(|param| match param {
+                ParamOverride::Named { param, value } => ParamOverride::Named {
+                    param: param.to_owned(),
+                    value: rename(value),
+                },
+                ParamOverride::Positional(value) => {
+                    ParamOverride::Positional(rename_expr(value, names))
+                }
+            })
+            .collect(),
+        port_connections: inst
+            .port_connections
+            .iter()
+            .map(|conn| match conn {
+                PortConn::Named { port, signal } => PortConn::Named {
+                    port: port.to_owned(),
+                    signal: rename(signal),
+                },
+                PortConn::Positional(signal) => PortConn::Positional(rename(signal)),
+                PortConn::Wildcard => PortConn::Wildcard,
+            })
+            .collect(),
+        ..inst.clone()
+    }
+}
+
+/// Copy of a nested generate construct with the names in `names` replaced
+///
+/// Names the nested blocks declare themselves, and the genvar of a nested
+/// loop, hide the outer names and are left alone
+fn rename_generate(block: &GenBlock, names: &HashMap<String, Expr>) -> GenBlock {
+    let body = |body: &GenBody, names: &HashMap<String, Expr>| {
+        let mut names = names.clone();
+        for name in declared_names(&body.items) {
+            names.remove(&name);
+        }
+
+        GenBody {
+            label: body.label.clone(),
+            items: Box::new(rename_module(&body.items, &names)),
+        }
+    };
+
+    match block {
+        GenBlock::For {
+            genvar,
+            declared,
+            init,
+            cond,
+            step,
+            body: items,
+        } => {
+            let mut names = names.clone();
+            names.remove(genvar);
+
+            GenBlock::For {
+                genvar: genvar.to_owned(),
+                declared: *declared,
+                init: rename_expr(init, &names),
+                cond: rename_expr(cond, &names),
+                step: rename_expr(step, &names),
+                body: body(items, &names),
+            }
+        }
+        GenBlock::If {
+            cond,
+            then,
+            else_if,
+            else_,
+        } => GenBlock::If {
+            cond: rename_expr(cond, names),
+            then: body(then, names),
+            else_if: else_if
+                .iter()
+                .map(|(cond, items)| (rename_expr(cond, names), body(items, names)))
+                .collect(),
+            else_: else_.as_ref().map(|items| body(items, names)),
+        },
+        GenBlock::Case {
+            expr,
+            items,
+            default,
+        } => GenBlock::Case {
+            expr: rename_expr(expr, names),
+            items: items
+                .iter()
+                .map(|(labels, items)| {
+                    let labels = labels.iter().map(|label| rename_expr(label, names));
+                    (labels.collect(), body(items, names))
+                })
+                .collect(),
+            default: default.as_ref().map(|items| body(items, names)),
+        },
+    }
+}
+
+/// New name of a declared or watched signal, which is left as it is unless
+/// `names` renames it
+fn rename_signal(name: &str, names: &HashMap<String, Expr>) -> String {
+    match names.get(name) {
+        Some(Expr::Ident(renamed)) => renamed.to_owned(),
+        _ => name.to_owned(),
+    }
+}
+
+/// Copy of an edge event watching the renamed signal
+fn rename_edge(event: &EdgeEvent, names: &HashMap<String, Expr>) -> EdgeEvent {
+    EdgeEvent {
+        edge: event.edge,
+        signal: rename_signal(&event.signal, names),
+    }
+}
+
+/// Copy of the bounds of a range with every name in `names` replaced
+fn rename_bounds(bounds: &Bounds, names: &HashMap<String, Expr>) -> Bounds {
+    Bounds {
+        left: rename_expr(&bounds.left, names),
+        right: rename_expr(&bounds.right, names),
+    }
+}
+
+/// Copy of a delay with every name in `names` replaced
+fn rename_delay(delay: &Delay, names: &HashMap<String, Expr>) -> Delay {
+    match delay {
+        Delay::Units(units) => Delay::Units(rename_expr(units, names)),
+        Delay::RealUnits(units) => Delay::RealUnits(*units),
+        Delay::Time(time) => Delay::Time(*time),
+    }
+}
+
+/// Copy of an expression with every name in `names` replaced
+fn rename_expr(expr: &Expr, names: &HashMap<String, Expr>) -> Expr {
+    let rename = |expr: &Expr| Box::new(rename_expr(expr, names));
+
+    match expr {
+        Expr::Ident(name) => names.get(name).cloned().unwrap_or_else(|| expr.clone()),
+        Expr::Literal(..) | Expr::Str(_) | Expr::Fill(_) => expr.clone(),
+        Expr::UnaryOp(op, operand) => Expr::UnaryOp(*op, rename(operand)),
+        Expr::BinOp(op, lhs, rhs) => Expr::BinOp(*op, rename(lhs), rename(rhs)),
+        Expr::Ternary { cond, then, else_ } => Expr::Ternary {
+            cond: rename(cond),
+            then: rename(then),
+            else_: rename(else_),
+        },
+        Expr::BitSelect(base, index) => Expr::BitSelect(rename(base), rename(index)),
+        Expr::PartSelect(base, msb, lsb) => {
+            Expr::PartSelect(rename(base), rename(msb), rename(lsb))
+        }
+        Expr::PartSelectUp(base, start, width) => {
+            Expr::PartSelectUp(rename(base), rename(start), rename(width))
+        }
+        Expr::PartSelectDown(base, start, width) => {
+            Expr::PartSelectDown(rename(base), rename(start), rename(width))
+        }
+        Expr::Concat(values) => Expr::Concat(
+            values
+                .iter()
+                .map(|value| rename_expr(value, names))
+                .collect(),
+        ),
+        Expr::Replicate { count, value } => Expr::Replicate {
+            count: rename(count),
+            value: rename(value),
+        },
+        Expr::ArrayLiteral(values) => Expr::ArrayLiteral(
+            values
+                .iter()
+                .map(|value| rename_expr(value, names))
+                .collect(),
+        ),
+        Expr::StructLiteral(fields) => Expr::StructLiteral(
+            fields
+                .iter()
+                .map(|(name, value)| (name.to_owned(), rename_expr(value, names)))
+                .collect(),
+        ),
+        Expr::DefaultLiteral(value) => Expr::DefaultLiteral(rename(value)),
+        Expr::SysFunc(name, args) => Expr::SysFunc(
+            name.to_owned(),
+            args.iter().map(|arg| rename_expr(arg, names)).collect(),
+        ),
+        Expr::Cast(kind, operand) => Expr::Cast(*kind, rename(operand)),
+        Expr::FuncCall(name, args) => Expr::FuncCall(
+            name.to_owned(),
+            args.iter().map(|arg| rename_expr(arg, names)).collect(),
+        ),
+        // Names in a package or class belong to it, so only call arguments
+        // are renamed
+        Expr::Scoped(_, item) if matches!(**item, Expr::Ident(_)) => expr.clone(),
+        Expr::Scoped(scope, item) => Expr::Scoped(scope.to_owned(), rename(item)),
+        Expr::Member(base, name) => Expr::Member(rename(base), name.to_owned()),
+        Expr::MethodCall(base, name, args) => Expr::MethodCall(
+            rename(base),
+            name.to_owned(),
+            args.iter().map(|arg| rename_expr(arg, names)).collect(),
+        ),
+    }
+}
+
+/// Copy of a list of statements with every name in `names` replaced
+pub(crate) fn rename_stmts(stmts: &[LogicStmt], names: &HashMap<String, Expr>) -> Vec<LogicStmt> {
+    stmts.iter().map(|stmt| rename_stmt(stmt, names)).collect()
+}
+
+/// Copy of a statement with every name in `names` replaced
+fn rename_stmt(stmt: &LogicStmt, names: &HashMap<String, Expr>) -> LogicStmt {
+    let expr = |expr: &Expr| rename_expr(expr, names);
+    let stmts = |stmts: &[LogicStmt]| rename_stmts(stmts, names);
+
+    match stmt {
+        LogicStmt::BlockingAssign { lhs, rhs } => LogicStmt::BlockingAssign {
+            lhs: expr(lhs),
+            rhs: expr(rhs),
+        },
+        LogicStmt::NonBlockingAssign { lhs, rhs } => LogicStmt::NonBlockingAssign {
+            lhs: expr(lhs),
+            rhs: expr(rhs),
+        },
+        LogicStmt::DelayedAssign {
+            lhs,
+            rhs,
+            delay,
+            blocking,
+        } => LogicStmt::DelayedAssign {
+            lhs: expr(lhs),
+            rhs: expr(rhs),
+            delay: rename_delay(delay, names),
+            blocking: *blocking,
+        },
+        LogicStmt::If(IfStmt {
+            cond,
+            then,
+            else_if,
+            else_,
+        }) => LogicStmt::If(IfStmt {
+            cond: expr(cond),
+            then: stmts(then),
+            else_if: else_if
+                .iter()
+                .map(|(cond, body)| (expr(cond), stmts(body)))
+                .collect(),
+            else_: else_.as_ref().map(|body| stmts(body)),
+        }),
+        LogicStmt::Case {
+            kind,
+            expr: value,
+            items,
+            default,
+        } => LogicStmt::Case {
+            kind: *kind,
+            expr: expr(value),
+            items: items
+                .iter()
+                .map(|item| CaseItem {
+                    labels: item.labels.iter().map(expr).collect(),
+                    body: stmts(&item.body),
+                })
+                .collect(),
+            default: default.as_ref().map(|body| stmts(body)),
+        },
+        LogicStmt::For(ForLoop {
+            var,
+            init,
+            cond,
+            step,
+            body,
+        }) => LogicStmt::For(ForLoop {
+            var: var.as_ref().map(|var| {
+                Box::new(Var {
+                    name: rename_signal(&var.name, names),
+                    bounds: var
+                        .bounds
+                        .as_ref()
+                        .map(|bounds| rename_bounds(bounds, names)),
+                    ..(**var).clone()
+                })
+            }),
+            init: Box::new(rename_stmt(init, names)),
+            cond: expr(cond),
+            step: Box::new(rename_stmt(step, names)),
+            body: stmts(body),
+        }),
+        LogicStmt::Loop(LoopStmt::While { cond, body }) => LogicStmt::Loop(LoopStmt::While {
+            cond: expr(cond),
+            body: stmts(body),
+        }),
+        LogicStmt::Loop(LoopStmt::DoWhile { body, cond }) => LogicStmt::Loop(LoopStmt::DoWhile {
+            body: stmts(body),
+            cond: expr(cond),
+        }),
+        LogicStmt::Loop(LoopStmt::Repeat { count, body }) => LogicStmt::Loop(LoopStmt::Repeat {
+            count: expr(count),
+            body: stmts(body),
+        }),
+        LogicStmt::Loop(LoopStmt::Forever { body }) => {
+            LogicStmt::Loop(LoopStmt::Forever { body: stmts(body) })
+        }
+        LogicStmt::ForkJoin { branches, join } => LogicStmt::ForkJoin {
+            branches: branches.iter().map(|branch| stmts(branch)).collect(),
+            join: *join,
+        },
+        LogicStmt::Delay { delay, body } => LogicStmt::Delay {
+            delay: rename_delay(delay, names),
+            body: stmts(body),
+        },
+        LogicStmt::Wait { cond, body } => LogicStmt::Wait {
+            cond: expr(cond),
+            body: stmts(body),
+        },
+        LogicStmt::EventControl { events, body } => LogicStmt::EventControl {
+            events: events.as_ref().map(|events| {
+                events
+                    .iter()
+                    .map(|event| match event {
+                        EventTrigger::Edge(edge) => EventTrigger::Edge(rename_edge(edge, names)),
+                        EventTrigger::Change(signal) => {
+                            EventTrigger::Change(rename_signal(signal, names))
+                        }
+                    })
+                    .collect()
+            }),
+            body: stmts(body),
+        },
+        LogicStmt::Display { task, radix, args } => LogicStmt::Display {
+            task: *task,
+            radix: *radix,
+            args: args.iter().map(expr).collect(),
+        },
+        LogicStmt::SysTaskCall { name, args } => LogicStmt::SysTaskCall {
+            name: name.to_owned(),
+            args: args.iter().map(expr).collect(),
+        },
+        LogicStmt::TaskCall { name, args } => LogicStmt::TaskCall {
+            name: name.to_owned(),
+            args: args.iter().map(expr).collect(),
+        },
+        LogicStmt::Return(value) => LogicStmt::Return(value.as_ref().map(expr)),
+        LogicStmt::Break | LogicStmt::Continue => stmt.clone(),
+    }
+}
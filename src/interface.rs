@@ -0,0 +1,324 @@
+use crate::expr::parse_ident;
+use crate::module::{
+    parse_module_io, parse_module_item, ImportDecl, Module, ModuleIO, PortDirection,
+};
+use crate::param::Param;
+use crate::var_types::{parse_name, Var};
+use crate::{expect_token, next_token, peek_token, LexingError, Token};
+use log::{debug, error, trace, warn};
+use logos::Lexer;
+use std::fmt;
+
+/// SystemVerilog interface, bundling signals into a connection type that
+/// modules take as a single port
+#[derive(Default, Clone)]
+pub struct Interface {
+    /// Interface name
+    pub name: String,
+
+    /// Ports and parameters of the interface header
+    pub io: ModuleIO,
+
+    /// Signals bundled by the interface
+    pub vars: Vec<Var>,
+
+    /// Parameters declared in the interface body
+    pub params: Vec<Param>,
+
+    /// Modport declarations
+    pub modports: Vec<Modport>,
+}
+
+impl fmt::Debug for Interface {
+    fn fmt(&self, _: &mut std::fmt::Formatter) -> fmt::Result {
+        debug!("INTERFACE: {:?}", self.name);
+        let _ = format!("{0:?}", self.io);
+        for param in &self.params {
+            debug!("PARAM: {:?}", param);
+        }
+        for var in &self.vars {
+            debug!("VAR: {:?}", var);
+        }
+        for modport in &self.modports {
+            debug!("MODPORT: {:?}", modport);
+        }
+        Ok(())
+    }
+}
+
+impl Interface {
+    /// Finds a modport by name
+    pub fn modport(&self, name: &str) -> Option<&Modport> {
+        self.modports.iter().find(|modport| modport.name == name)
+    }
+
+    /// Checks if the interface declares a signal or port named `name`
+    fn declares(&self, name: &str) -> bool {
+        self.vars.iter().any(|var| var.name == name) || self.io.port(name).is_some()
+    }
+}
+
+/// Modport declared in an interface, as in
+/// `modport Master (output psel, input prdata);`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Modport {
+    /// Modport name
+    pub name: String,
+
+    /// Interface signals the modport exposes, in order
+    pub ports: Vec<ModportPort>,
+}
+
+/// Signal exposed by a modport, with the direction a module using the
+/// modport sees it in
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModportPort {
+    /// Name of the interface signal
+    pub name: String,
+
+    /// Direction of the signal at the module boundary
+    pub direction: PortDirection,
+}
+
+/// Module port typed as an interface, as in `APB_if.Master bus`
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterfacePort {
+    /// Port name
+    pub name: String,
+
+    /// Name of the interface connected to the port
+    pub interface: String,
+
+    /// Modport the module sees the interface through, `None` for the whole
+    /// interface
+    pub modport: Option<String>,
+}
+
+/// Parses an interface to completion
+///
+/// The `interface` keyword has already been consumed. The header takes the
+/// same parameter and port lists as a module header, and may be left out
+/// entirely, as in `interface APB_if;`. Interfaces only hold signals,
+/// parameters and modports, so any logic in the body is not simulated.
+/// Modport names must be unique, every modport port must name a signal or
+/// port of the interface, and a label after `endinterface` must repeat the
+/// interface name
+pub fn parse_interface<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<Interface, LexingError> {
+    trace!("parsing interface");
+
+    let mut imports: Vec<ImportDecl> = Vec::new();
+    let mut ahead = lexer.clone();
+    next_token(&mut ahead);
+    let name = parse_ident(&mut ahead);
+    let io = match peek_token(&ahead) {
+        Some(Ok(Token::Semicolon)) => {
+            next_token(&mut ahead);
+            *lexer = ahead;
+            ModuleIO {
+                name,
+                ..Default::default()
+            }
+        }
+        _ => parse_module_io(lexer, &mut imports)?,
+    };
+
+    let mut items = Module {
+        name: io.name.to_owned(),
+        io,
+        imports,
+        ..Default::default()
+    };
+    let mut modports: Vec<Modport> = Vec::new();
+
+    loop {
+        match lexer.next() {
+            Some(Ok(Token::EndInterface)) => {
+                parse_end_label(lexer, "endinterface", &items.name)?;
+                break;
+            }
+            Some(Ok(Token::Modport)) => modports.append(&mut parse_modport(lexer)?),
+            Some(token) => parse_module_item(lexer, token, &mut items)?,
+            None => {
+                error!("interface '{}' not closed with 'endinterface'", items.name);
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    }
+
+    let has_logic = !(items.assigns.is_empty()
+        && items.comb_blocks.is_empty()
+        && items.ff_blocks.is_empty()
+        && items.latch_blocks.is_empty()
+        && items.initial_blocks.is_empty()
+        && items.final_blocks.is_empty()
+        && items.instances.is_empty());
+    if has_logic {
+        warn!("logic in interface '{}' is not simulated", items.name);
+    }
+
+    let interface = Interface {
+        name: items.name,
+        io: items.io,
+        vars: items.vars,
+        params: items.params,
+        modports,
+    };
+
+    for (idx, modport) in interface.modports.iter().enumerate() {
+        if interface.modports[..idx]
+            .iter()
+            .any(|other| other.name == modport.name)
+        {
+            error!(
+                "interface '{}' declares modport '{}' more than once",
+                interface.name, modport.name
+            );
+            return Err(LexingError::DuplicateModport(modport.name.to_owned()));
+        }
+        if let Some(port) = modport
+            .ports
+            .iter()
+            .find(|port| !interface.declares(&port.name))
+        {
+            error!(
+                "modport '{}' exposes '{}', which interface '{}' does not declare",
+                modport.name, port.name, interface.name
+            );
+            return Err(LexingError::UndeclaredPortReference(port.name.to_owned()));
+        }
+    }
+
+    Ok(interface)
+}
+
+/// Parses the label that may follow the `keyword` closing an interface or
+/// package, as in `endinterface : APB_if`, which must repeat its name
+pub(crate) fn parse_end_label<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    keyword: &str,
+    name: &str,
+) -> Result<(), LexingError> {
+    if peek_token(lexer) != Some(Ok(Token::Colon)) {
+        return Ok(());
+    }
+    next_token(lexer);
+
+    let label = match next_token(lexer) {
+        Some(Ok(Token::Word)) | Some(Ok(Token::EscapedIdentifier(_))) => parse_ident(lexer),
+        _ => {
+            error!("expected label after '{}': '{}'", keyword, lexer.slice());
+            return Err(LexingError::UnexpectedToken);
+        }
+    };
+    if label != name {
+        error!("'{}' closed with the label of '{}'", name, label);
+        return Err(LexingError::UnexpectedToken);
+    }
+
+    Ok(())
+}
+
+/// Parses a modport declaration to completion
+///
+/// The `modport` keyword has already been consumed. A single declaration may
+/// hold several comma separated modports, as in
+/// `modport Master (output psel), Slave (input psel);`. A port without a
+/// direction of its own takes that of the port before it, as the `penable`
+/// of `(output psel, penable)`. Each signal may be listed only once in a
+/// modport
+fn parse_modport<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Vec<Modport>, LexingError> {
+    let mut modports: Vec<Modport> = Vec::new();
+
+    trace!("parsing modport");
+
+    loop {
+        let name = match next_token(lexer) {
+            Some(Ok(Token::Word)) | Some(Ok(Token::EscapedIdentifier(_))) => parse_ident(lexer),
+            _ => {
+                error!("expected modport name: '{}'", lexer.slice());
+                return Err(LexingError::UnexpectedToken);
+            }
+        };
+        expect_token(lexer, Token::OpenParen, "modport")?;
+
+        let mut ports: Vec<ModportPort> = Vec::new();
+        let mut direction = None;
+        loop {
+            match next_token(lexer) {
+                Some(Ok(Token::Input)) => direction = Some(PortDirection::Input),
+                Some(Ok(Token::Output)) => direction = Some(PortDirection::Output),
+                Some(Ok(Token::Inout)) => direction = Some(PortDirection::Inout),
+                Some(Ok(Token::Word)) | Some(Ok(Token::EscapedIdentifier(_))) => {
+                    let port = parse_ident(lexer);
+                    let Some(direction) = direction else {
+                        error!("port '{}' of modport '{}' has no direction", port, name);
+                        return Err(LexingError::UnexpectedToken);
+                    };
+                    if ports.iter().any(|other| other.name == port) {
+                        error!("modport '{}' lists '{}' more than once", name, port);
+                        return Err(LexingError::UnexpectedToken);
+                    }
+                    ports.push(ModportPort {
+                        name: port,
+                        direction,
+                    });
+                }
+                Some(Ok(Token::Comma)) => (),
+                Some(Ok(Token::CloseParen)) => break,
+                _ => {
+                    error!(
+                        "unexpected token in modport '{}': '{}'",
+                        name,
+                        lexer.slice()
+                    );
+                    return Err(LexingError::UnexpectedToken);
+                }
+            }
+        }
+        modports.push(Modport { name, ports });
+
+        match next_token(lexer) {
+            Some(Ok(Token::Comma)) => (),
+            Some(Ok(Token::Semicolon)) => return Ok(modports),
+            _ => {
+                error!("expected ',' or ';' after modport: '{}'", lexer.slice());
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    }
+}
+
+/// Parses a module header entry typed as an interface, as in `APB_if bus` or
+/// `APB_if.Master bus`, to completion
+///
+/// The first token of the entry is the current token. Returns `None`,
+/// leaving the lexer where it was, if the entry is a plain port name
+pub(crate) fn parse_interface_port<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<Option<InterfacePort>, LexingError> {
+    let mut ahead = lexer.clone();
+    let interface = parse_ident(&mut ahead);
+    let mut modport = None;
+
+    if peek_token(&ahead) == Some(Ok(Token::Dot)) {
+        next_token(&mut ahead);
+        next_token(&mut ahead);
+        modport = Some(parse_ident(&mut ahead));
+    }
+
+    match next_token(&mut ahead) {
+        Some(Ok(Token::Word)) | Some(Ok(Token::EscapedIdentifier(_))) => (),
+        _ => return Ok(None),
+    }
+    *lexer = ahead;
+
+    trace!("parsing port typed as interface '{}'", interface);
+
+    Ok(Some(InterfacePort {
+        name: parse_name(lexer)?,
+        interface,
+        modport,
+    }))
+}
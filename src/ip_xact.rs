@@ -0,0 +1,339 @@
+use crate::eval::const_index;
+use crate::expr::expr_sv_string;
+use crate::module::Module;
+use crate::param::{Param, ParamType};
+use crate::var_types::Var;
+use crate::SimError;
+use log::{trace, warn};
+use quick_xml::events::{BytesDecl, BytesText, Event};
+use quick_xml::Writer;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// IP-XACT 2014 (IEEE 1685-2014) namespace
+const IPXACT_NS: &str = "http://www.accellera.org/XMLSchema/IPXACT/1685-2014";
+
+/// IP-XACT 2014 schema location
+const IPXACT_SCHEMA: &str = "http://www.accellera.org/XMLSchema/IPXACT/1685-2014 \
+    http://www.accellera.org/XMLSchema/IPXACT/1685-2014/index.xsd";
+
+/// Port name prefix used to infer an AXI bus interface
+const AXI_PREFIX: &str = "axi_";
+
+/// Module port with its IP-XACT direction
+struct Port<'a> {
+    direction: &'static str,
+    var: &'a Var,
+}
+
+/// Writes an IP-XACT 2014 component description of a module to `path`
+///
+/// See `ip_xact_string` for the contents of the description
+pub fn write_ip_xact(
+    module: &Module,
+    vendor: &str,
+    library: &str,
+    version: &str,
+    path: &Path,
+) -> Result<(), SimError> {
+    trace!("writing IP-XACT component for {} to {:?}", module.name, path);
+
+    fs::write(path, ip_xact_string(module, vendor, library, version)?)?;
+
+    Ok(())
+}
+
+/// Produces an IP-XACT 2014 component description of a module
+///
+/// The description contains the module's ports, its overridable parameters
+/// with their default values, a memory map for each memory and any bus
+/// interfaces inferred from port names. Ports prefixed with `axi_` are
+/// grouped into an AXI4 interface, which is a master if it drives
+/// `axi_awvalid` or `axi_arvalid` and a slave otherwise
+pub fn ip_xact_string(
+    module: &Module,
+    vendor: &str,
+    library: &str,
+    version: &str,
+) -> Result<String, SimError> {
+    let io = &module.io;
+    let ports: Vec<Port> = io
+        .inputs
+        .iter()
+        .map(|input| Port {
+            direction: "in",
+            var: &input.var,
+        })
+        .chain(io.outputs.iter().map(|output| Port {
+            direction: "out",
+            var: &output.var,
+        }))
+        .chain(io.inouts.iter().map(|inout| Port {
+            direction: "inout",
+            var: &inout.var,
+        }))
+        .collect();
+
+    for port in &io.ports {
+        warn!("port '{}' has no direction and is left out of IP-XACT", port);
+    }
+
+    let params: Vec<&Param> = io
+        .params
+        .iter()
+        .chain(&module.params)
+        .filter(|param| param.param_type == ParamType::Parameter)
+        .collect();
+
+    let memories: Vec<&Var> = module
+        .vars
+        .iter()
+        .filter(|var| match (var.array, &var.array_bounds) {
+            (Some(_), _) => true,
+            (None, Some(_)) => {
+                warn!(
+                    "memory '{}' has an unknown depth and is left out of IP-XACT",
+                    var.name
+                );
+                false
+            }
+            (None, None) => false,
+        })
+        .collect();
+
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    writer
+        .create_element("ipxact:component")
+        .with_attribute(("xmlns:ipxact", IPXACT_NS))
+        .with_attribute(("xmlns:xsi", "http://www.w3.org/2001/XMLSchema-instance"))
+        .with_attribute(("xsi:schemaLocation", IPXACT_SCHEMA))
+        .write_inner_content(|writer| {
+            write_text_element(writer, "ipxact:vendor", vendor)?;
+            write_text_element(writer, "ipxact:library", library)?;
+            write_text_element(writer, "ipxact:name", &module.name)?;
+            write_text_element(writer, "ipxact:version", version)?;
+
+            let axi_ports: Vec<&Port> = ports
+                .iter()
+                .filter(|port| port.var.name.starts_with(AXI_PREFIX))
+                .collect();
+
+            if !axi_ports.is_empty() {
+                writer
+                    .create_element("ipxact:busInterfaces")
+                    .write_inner_content(|writer| write_axi_interface(writer, &axi_ports))?;
+            }
+
+            if !memories.is_empty() {
+                writer
+                    .create_element("ipxact:memoryMaps")
+                    .write_inner_content(|writer| {
+                        for memory in &memories {
+                            write_memory_map(writer, memory)?;
+                        }
+                        Ok(())
+                    })?;
+            }
+
+            writer
+                .create_element("ipxact:model")
+                .write_inner_content(|writer| {
+                    writer
+                        .create_element("ipxact:ports")
+                        .write_inner_content(|writer| {
+                            for port in &ports {
+                                write_port(writer, port)?;
+                            }
+                            Ok(())
+                        })?;
+                    Ok(())
+                })?;
+
+            if !params.is_empty() {
+                writer
+                    .create_element("ipxact:parameters")
+                    .write_inner_content(|writer| {
+                        for param in &params {
+                            write_parameter(writer, param)?;
+                        }
+                        Ok(())
+                    })?;
+            }
+
+            Ok(())
+        })?;
+
+    Ok(String::from_utf8_lossy(&writer.into_inner()).into_owned())
+}
+
+/// Writes an element containing only text
+fn write_text_element<W: io::Write>(
+    writer: &mut Writer<W>,
+    name: &str,
+    text: &str,
+) -> io::Result<()> {
+    writer
+        .create_element(name)
+        .write_text_content(BytesText::new(text))?;
+    Ok(())
+}
+
+/// Writes an AXI4 bus interface mapping each `axi_` port to its signal
+fn write_axi_interface<W: io::Write>(writer: &mut Writer<W>, ports: &[&Port]) -> io::Result<()> {
+    let master = ports.iter().any(|port| {
+        port.direction == "out" && matches!(port.var.name.as_str(), "axi_awvalid" | "axi_arvalid")
+    });
+
+    writer
+        .create_element("ipxact:busInterface")
+        .write_inner_content(|writer| {
+            write_text_element(writer, "ipxact:name", "axi")?;
+            writer
+                .create_element("ipxact:busType")
+                .with_attribute(("vendor", "amba.com"))
+                .with_attribute(("library", "AMBA4"))
+                .with_attribute(("name", "AXI4"))
+                .with_attribute(("version", "r0p0_0"))
+                .write_empty()?;
+            writer
+                .create_element("ipxact:abstractionTypes")
+                .write_inner_content(|writer| {
+                    writer
+                        .create_element("ipxact:abstractionType")
+                        .write_inner_content(|writer| {
+                            writer
+                                .create_element("ipxact:abstractionRef")
+                                .with_attribute(("vendor", "amba.com"))
+                                .with_attribute(("library", "AMBA4"))
+                                .with_attribute(("name", "AXI4_rtl"))
+                                .with_attribute(("version", "r0p0_0"))
+                                .write_empty()?;
+                            writer
+                                .create_element("ipxact:portMaps")
+                                .write_inner_content(|writer| {
+                                    for port in ports {
+                                        write_port_map(writer, port)?;
+                                    }
+                                    Ok(())
+                                })?;
+                            Ok(())
+                        })?;
+                    Ok(())
+                })?;
+            writer
+                .create_element(if master {
+                    "ipxact:master"
+                } else {
+                    "ipxact:slave"
+                })
+                .write_empty()?;
+            Ok(())
+        })?;
+
+    Ok(())
+}
+
+/// Writes a port map from an `axi_` port to its upper case logical signal
+fn write_port_map<W: io::Write>(writer: &mut Writer<W>, port: &Port) -> io::Result<()> {
+    let logical = port.var.name[AXI_PREFIX.len()..].to_uppercase();
+
+    writer
+        .create_element("ipxact:portMap")
+        .write_inner_content(|writer| {
+            writer
+                .create_element("ipxact:logicalPort")
+                .write_inner_content(|writer| write_text_element(writer, "ipxact:name", &logical))?;
+            writer
+                .create_element("ipxact:physicalPort")
+                .write_inner_content(|writer| {
+                    write_text_element(writer, "ipxact:name", &port.var.name)
+                })?;
+            Ok(())
+        })?;
+
+    Ok(())
+}
+
+/// Writes a memory map holding one address block for a memory, addressed
+/// by element from its lowest address
+fn write_memory_map<W: io::Write>(writer: &mut Writer<W>, memory: &Var) -> io::Result<()> {
+    let (first, last) = memory.array.unwrap_or_default();
+
+    writer
+        .create_element("ipxact:memoryMap")
+        .write_inner_content(|writer| {
+            write_text_element(writer, "ipxact:name", &memory.name)?;
+            writer
+                .create_element("ipxact:addressBlock")
+                .write_inner_content(|writer| {
+                    write_text_element(writer, "ipxact:name", &memory.name)?;
+                    let base = first.min(last).to_string();
+                    write_text_element(writer, "ipxact:baseAddress", &base)?;
+                    let range = (first.abs_diff(last) + 1).to_string();
+                    write_text_element(writer, "ipxact:range", &range)?;
+                    write_text_element(writer, "ipxact:width", &memory.width.to_string())?;
+                    write_text_element(writer, "ipxact:usage", "memory")
+                })?;
+            write_text_element(writer, "ipxact:addressUnitBits", &memory.width.to_string())
+        })?;
+
+    Ok(())
+}
+
+/// Writes a parameter with its default value, in decimal if it is a known
+/// constant
+fn write_parameter<W: io::Write>(writer: &mut Writer<W>, param: &Param) -> io::Result<()> {
+    writer
+        .create_element("ipxact:parameter")
+        .with_attribute(("parameterId", param.name.as_str()))
+        .with_attribute(("resolve", "user"))
+        .write_inner_content(|writer| {
+            write_text_element(writer, "ipxact:name", &param.name)?;
+            let value = match const_index(&param.value, &HashMap::new()) {
+                Some(value) => value.to_string(),
+                None => expr_sv_string(&param.value),
+            };
+            write_text_element(writer, "ipxact:value", &value)
+        })?;
+
+    Ok(())
+}
+
+/// Writes a wire port with its direction and, for vectors, its declared
+/// range
+fn write_port<W: io::Write>(writer: &mut Writer<W>, port: &Port) -> io::Result<()> {
+    writer
+        .create_element("ipxact:port")
+        .write_inner_content(|writer| {
+            write_text_element(writer, "ipxact:name", &port.var.name)?;
+            writer
+                .create_element("ipxact:wire")
+                .write_inner_content(|writer| {
+                    write_text_element(writer, "ipxact:direction", port.direction)?;
+
+                    if port.var.width > 1 {
+                        let left = (port.var.lsb + port.var.width - 1).to_string();
+                        let right = port.var.lsb.to_string();
+                        writer
+                            .create_element("ipxact:vectors")
+                            .write_inner_content(|writer| {
+                                writer
+                                    .create_element("ipxact:vector")
+                                    .write_inner_content(|writer| {
+                                        write_text_element(writer, "ipxact:left", &left)?;
+                                        write_text_element(writer, "ipxact:right", &right)
+                                    })?;
+                                Ok(())
+                            })?;
+                    }
+                    Ok(())
+                })?;
+            Ok(())
+        })?;
+
+    Ok(())
+}
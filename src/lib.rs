@@ -5,9 +5,14 @@
 use log::{debug, error, trace, warn};
 use logos::{Lexer, Logos};
 use std::num::ParseIntError;
+use std::ops::Range;
 
 /// File reading/writing
 use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Module lookup
+use std::collections::HashMap;
 
 /// Debug
 use std::fmt;
@@ -20,16 +25,71 @@ pub mod var_types;
 pub mod sim_time;
 use sim_time::*;
 
+/// Macro expansion and other preprocessor directives
+pub mod preprocess;
+use preprocess::Preprocessor;
+
 /// Module type and parsing
 pub mod module;
 use module::*;
 
+/// Expressions and expression parsing
+pub mod expr;
+
+/// Parameter declarations and parsing
+pub mod param;
+
+/// Procedural blocks and statement parsing
+pub mod logic;
+
+/// Generate constructs and their unrolling
+pub mod generate;
+
+/// Function and task definitions and parsing
+pub mod function;
+
+/// Interface declarations and parsing
+pub mod interface;
+use interface::{parse_interface, Interface};
+
+/// Package declarations and parsing
+pub mod package;
+use package::{parse_package, Package};
+
+/// Four-state signal values and formatting
+pub mod value;
+use value::{sized_literal, unbased_literal, LogicState, SizedLiteral};
+
+/// IP-XACT component generation
+pub mod ip_xact;
+
+/// System task and function evaluation
+pub mod sys_task;
+
+/// Expression evaluation
+pub mod eval;
+
+/// Design hierarchy elaboration
+pub mod elab;
+
+/// Event-driven simulation
+pub mod sim;
+
+/// Design checks, such as combinational loop detection
+pub mod lint;
+
+/// Simulation output formats
+pub mod output;
+
 /// Errors occurring due to incorrect character sequences
 #[derive(Default, Debug, Clone, PartialEq)]
 pub enum LexingError {
     /// Invalid integer found
     InvalidInteger(String),
 
+    /// Integer that could not be parsed, with the underlying error
+    ParseInteger(ParseIntError),
+
     /// Unexpected token or generic error
     #[default]
     UnexpectedToken,
@@ -52,19 +112,169 @@ pub enum LexingError {
     /// Bit width determined to be negative
     NegativeBitWidth,
 
+    /// Bit width wider than `value::MAX_WIDTH`
+    WidthTooLarge(u64),
+
+    /// Memory with more elements than `var_types::MAX_MEMORY_DEPTH`
+    MemoryTooLarge(u64),
+
     /// Module wire parsing failed
     ModuleWireNotFound,
+
+    /// Port declared in a module body but not listed in the module header
+    UndeclaredPortReference(String),
+
+    /// Identifier that is not legal unless escaped, e.g. one starting with a
+    /// digit
+    InvalidIdentifier(String),
+
+    /// Port or parameter given more than once in a module instance, by name
+    /// or, for `.*`, as `*`
+    DuplicateConnection(String),
+
+    /// Modport declared more than once in the same interface
+    DuplicateModport(String),
+
+    /// Error found at a location in the source
+    Spanned {
+        /// Error found
+        error: Box<LexingError>,
+
+        /// Byte range of the token the error was found at
+        span: Range<usize>,
+
+        /// Line of the token, starting from 1
+        line: usize,
+
+        /// Column of the token in characters, starting from 1
+        column: usize,
+
+        /// File the token is in, if known and not the source being parsed
+        /// itself
+        file: Option<PathBuf>,
+    },
+}
+
+impl LexingError {
+    /// Byte range of the source the error was found at, if known
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            LexingError::Spanned { span, .. } => Some(span.clone()),
+            _ => None,
+        }
+    }
+
+    /// Line and column the error was found at, if known
+    pub fn location(&self) -> Option<(usize, usize)> {
+        match self {
+            LexingError::Spanned { line, column, .. } => Some((*line, *column)),
+            _ => None,
+        }
+    }
+
+    /// File the error was found in, if known
+    pub fn file(&self) -> Option<&Path> {
+        match self {
+            LexingError::Spanned { file, .. } => file.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Error without its location
+    pub fn kind(&self) -> &LexingError {
+        match self {
+            LexingError::Spanned { error, .. } => error.kind(),
+            _ => self,
+        }
+    }
 }
 
-impl Into<String> for LexingError {
-    fn into(self) -> String {
+/// Attaches the location of the lexer's current token to an error
+///
+/// Errors that already have a location keep it
+pub fn err_with_span(e: LexingError, lexer: &Lexer<Token>) -> LexingError {
+    if e.span().is_some() {
+        return e;
+    }
+
+    let span = lexer.span();
+    let before = &lexer.source()[..span.start];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+
+    LexingError::Spanned {
+        error: Box::new(e),
+        span,
+        line,
+        column,
+        file: None,
+    }
+}
+
+impl fmt::Display for LexingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Self::InvalidInteger(error) => format!("invalid integer encountered: {error:}"),
-            Self::UnexpectedToken => "unexpected token encountered".to_owned(),
-            Self::ImproperTimeFormatting => "improper time format encountered".to_owned(),
-            Self::IncompleteWidth => "incomplete width encountered".to_owned(),
-            Self::NegativeBitWidth => "negative bit width encountered".to_owned(),
-            _ => "generic/unknown error encountered".to_owned(),
+            LexingError::InvalidInteger(error) => {
+                write!(f, "invalid integer encountered: {error:}")
+            }
+            LexingError::ParseInteger(error) => {
+                write!(f, "invalid integer encountered: {error:}")
+            }
+            LexingError::UnexpectedToken => write!(f, "unexpected token encountered"),
+            LexingError::ExpectedSemi => write!(f, "expected ';'"),
+            LexingError::ImproperTimeFormatting => write!(f, "improper time format encountered"),
+            LexingError::ImproperCommentFormatting => {
+                write!(f, "improper comment format encountered")
+            }
+            LexingError::NonAsciiCharacter => write!(f, "non-ASCII character encountered"),
+            LexingError::IncompleteWidth => write!(f, "incomplete width encountered"),
+            LexingError::NegativeBitWidth => write!(f, "negative bit width encountered"),
+            LexingError::WidthTooLarge(width) => write!(
+                f,
+                "bit width {width:} exceeds the maximum of {} bits",
+                value::MAX_WIDTH
+            ),
+            LexingError::MemoryTooLarge(depth) => write!(
+                f,
+                "memory of {depth:} elements exceeds the maximum of {}",
+                var_types::MAX_MEMORY_DEPTH
+            ),
+            LexingError::ModuleWireNotFound => write!(f, "module wire declaration not found"),
+            LexingError::UndeclaredPortReference(name) => {
+                write!(f, "port '{name:}' is not listed in the module header")
+            }
+            LexingError::InvalidIdentifier(name) => write!(f, "invalid identifier '{name:}'"),
+            LexingError::DuplicateConnection(name) => {
+                write!(f, "'{name:}' is connected more than once")
+            }
+            LexingError::DuplicateModport(name) => {
+                write!(f, "modport '{name:}' is declared more than once")
+            }
+            LexingError::Spanned {
+                error,
+                line,
+                column,
+                file: Some(file),
+                ..
+            } => write!(f, "{}:{line:}:{column:}: {error:}", file.display()),
+            LexingError::Spanned {
+                error,
+                line,
+                column,
+                ..
+            } => write!(f, "{line:}:{column:}: {error:}"),
+        }
+    }
+}
+
+/// Only `ParseInteger` has an underlying error, which a location attached
+/// to it is passed through to
+impl std::error::Error for LexingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LexingError::ParseInteger(err) => Some(err),
+            LexingError::Spanned { error, .. } => error.source(),
+            _ => None,
         }
     }
 }
@@ -72,17 +282,490 @@ impl Into<String> for LexingError {
 /// Error type returned by calling lex.slice().parse() to u8
 impl From<ParseIntError> for LexingError {
     fn from(err: ParseIntError) -> Self {
-        use std::num::IntErrorKind::*;
+        LexingError::ParseInteger(err)
+    }
+}
+
+/// Errors occurring while resolving an included file
+#[derive(Debug, Clone, PartialEq)]
+pub enum IncludeError {
+    /// File with the given name is in none of the include directories
+    NotFound(String),
+
+    /// `` `include `` that isn't followed by a file name in quotes or angle
+    /// brackets, or by a macro expanding to one
+    MissingFileName,
+
+    /// Included file at the given path that could not be read
+    Unreadable(String),
+
+    /// File that includes itself, with the chain of paths from the file back
+    /// to itself
+    Circular(Vec<String>),
+
+    /// File with the given name included at more than
+    /// `preprocess::MAX_INCLUDE_DEPTH` levels of nesting
+    TooDeep(String),
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IncludeError::NotFound(name) => {
+                write!(
+                    f,
+                    "included file '{name:}' not found in any include directory"
+                )
+            }
+            IncludeError::MissingFileName => {
+                write!(
+                    f,
+                    "'`include' is missing a file name in quotes or angle brackets"
+                )
+            }
+            IncludeError::Unreadable(path) => {
+                write!(f, "included file '{path:}' could not be read")
+            }
+            IncludeError::Circular(chain) => write!(f, "circular include: {}", chain.join(" -> ")),
+            IncludeError::TooDeep(name) => write!(
+                f,
+                "including '{name:}' exceeds the maximum include depth of {}",
+                preprocess::MAX_INCLUDE_DEPTH
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IncludeError {}
+
+/// Errors occurring while preprocessing a source
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreprocessError {
+    /// Macro with the given name is used but never defined
+    UndefinedMacro(String),
+
+    /// Command line macro definition that is not `NAME` or `NAME=VALUE`,
+    /// or `` `define `` without a valid macro name and argument list
+    InvalidDefine(String),
+
+    /// Macro that uses itself, with the chain of macro names from the macro
+    /// back to itself, as in `[A, B, A]`
+    RecursiveMacro(Vec<String>),
+
+    /// Conditional directive such as `` `ifdef `` that is missing the macro
+    /// name to check
+    MissingMacroName(String),
+
+    /// Parenthesised condition of a conditional directive that is not an
+    /// expression of macro names, as in `` `ifdef (A &&) ``
+    InvalidMacroExpression(String),
+
+    /// `` `elsif ``, `` `else `` or `` `endif `` without an open `` `ifdef ``
+    /// or `` `ifndef `` block, or following the block's `` `else ``
+    UnmatchedConditional(String),
+
+    /// `` `ifdef `` or `` `ifndef `` block never closed by `` `endif ``
+    UnterminatedConditional,
+
+    /// Macro used with the wrong number of arguments
+    MacroArguments {
+        /// Macro name
+        name: String,
+
+        /// Number of arguments the macro takes
+        expected: usize,
+
+        /// Number of arguments given
+        got: usize,
+    },
+
+    /// Resolving an included file failed
+    Include(IncludeError),
+}
 
-        match err.kind() {
-            PosOverflow | NegOverflow => LexingError::InvalidInteger("overflow error".to_owned()),
-            _ => LexingError::InvalidInteger("unknown error".to_owned()),
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PreprocessError::UndefinedMacro(name) => write!(f, "macro '{name:}' is not defined"),
+            PreprocessError::InvalidDefine(arg) => {
+                write!(f, "'{arg:}' is not a valid macro definition")
+            }
+            PreprocessError::RecursiveMacro(chain) => {
+                write!(f, "recursive macro expansion: {}", chain.join(" -> "))
+            }
+            PreprocessError::MissingMacroName(directive) => {
+                write!(f, "'`{directive:}' is missing a macro name")
+            }
+            PreprocessError::InvalidMacroExpression(expr) => {
+                write!(f, "'{expr:}' is not a valid macro expression")
+            }
+            PreprocessError::UnmatchedConditional(directive) => {
+                write!(f, "'`{directive:}' without a matching '`ifdef'")
+            }
+            PreprocessError::UnterminatedConditional => {
+                write!(f, "'`ifdef' is never closed by '`endif'")
+            }
+            PreprocessError::MacroArguments {
+                name,
+                expected,
+                got,
+            } => write!(
+                f,
+                "macro '{name:}' takes {expected:} arguments, but is given {got:}"
+            ),
+            PreprocessError::Include(err) => write!(f, "{err:}"),
+        }
+    }
+}
+
+impl std::error::Error for PreprocessError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PreprocessError::Include(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<IncludeError> for PreprocessError {
+    fn from(err: IncludeError) -> Self {
+        PreprocessError::Include(err)
+    }
+}
+
+/// Errors occurring while elaborating a design hierarchy
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElabError {
+    /// Module with the given name is instantiated but never defined
+    UndefinedModule(String),
+
+    /// No top-level module was given, and every module is instantiated by
+    /// another
+    NoTopModule,
+
+    /// No top-level module was given, and several modules with the given
+    /// names could be the top
+    AmbiguousTopModule(Vec<String>),
+
+    /// Signal connected to a port of a different width, which becomes
+    /// `SimError::PortWidthMismatch`
+    PortWidthMismatch {
+        /// Hierarchical path of the instance
+        inst: String,
+
+        /// Port name
+        port: String,
+
+        /// Width of the port
+        expected: u64,
+
+        /// Width of the connected signal
+        got: u64,
+    },
+
+    /// Module that instantiates itself, with the chain of module names from
+    /// the module back to itself, as in `[a, b, a]`
+    CircularDependency(Vec<String>),
+
+    /// Port left unconnected by an instance
+    UnconnectedPort {
+        /// Hierarchical path of the instance
+        inst: String,
+
+        /// Port name
+        port: String,
+    },
+
+    /// Condition or loop bound of the named generate block is not a known
+    /// constant
+    UnknownGenerateValue(String),
+
+    /// Generate loop with the given block name that ran more than
+    /// `generate::MAX_GENERATE_ITERATIONS` times
+    GenerateLoopLimit(String),
+
+    /// Variable stepped by a generate loop that is not declared as a genvar
+    UndeclaredGenvar(String),
+
+    /// Port typed as an interface connected to something other than an
+    /// instance of that interface
+    InterfaceMismatch {
+        /// Hierarchical path of the instance
+        inst: String,
+
+        /// Port name
+        port: String,
+
+        /// Name of the interface the port takes
+        interface: String,
+    },
+
+    /// Modport named by a port that the interface does not declare
+    UndefinedModport {
+        /// Interface name
+        interface: String,
+
+        /// Modport name
+        modport: String,
+    },
+
+    /// Range of the named signal that is not constant once the parameters
+    /// are known, or is negative or too wide
+    InvalidWidth(String),
+
+    /// Package with the given name is imported, or names an item as in
+    /// `pkg::WIDTH`, but is never defined
+    UndefinedPackage(String),
+
+    /// Item imported by name, or named with its package as in `pkg::WIDTH`,
+    /// that the package does not declare
+    UndefinedImport {
+        /// Package name
+        package: String,
+
+        /// Item name
+        item: String,
+    },
+
+    /// Item imported from a package (name, package) conflicts with a local
+    /// declaration, which becomes `SimError::ImportConflict`
+    ImportConflict(String, String),
+}
+
+impl fmt::Display for ElabError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ElabError::UndefinedModule(name) => write!(f, "module '{name:}' is not defined"),
+            ElabError::NoTopModule => write!(f, "no module could be the top-level module"),
+            ElabError::AmbiguousTopModule(names) => write!(
+                f,
+                "several modules could be the top-level module, choose one of: {}",
+                names.join(", ")
+            ),
+            ElabError::PortWidthMismatch {
+                inst,
+                port,
+                expected,
+                got,
+            } => write!(
+                f,
+                "port '{port:}' of '{inst:}' is {expected:} bits wide, but is connected to {got:} bits"
+            ),
+            ElabError::CircularDependency(cycle) => {
+                write!(f, "circular module instantiation: {}", cycle.join(" -> "))
+            }
+            ElabError::UnconnectedPort { inst, port } => {
+                write!(f, "port '{port:}' of '{inst:}' is not connected")
+            }
+            ElabError::UnknownGenerateValue(name) => {
+                write!(f, "generate block '{name:}' depends on a value that is not constant")
+            }
+            ElabError::GenerateLoopLimit(name) => write!(
+                f,
+                "generate loop '{name:}' did not finish within {} iterations",
+                generate::MAX_GENERATE_ITERATIONS
+            ),
+            ElabError::UndeclaredGenvar(name) => {
+                write!(f, "generate loop variable '{name:}' is not declared as a genvar")
+            }
+            ElabError::InterfaceMismatch {
+                inst,
+                port,
+                interface,
+            } => write!(
+                f,
+                "port '{port:}' of '{inst:}' must be connected to an instance of interface '{interface:}'"
+            ),
+            ElabError::UndefinedModport { interface, modport } => {
+                write!(f, "interface '{interface:}' has no modport '{modport:}'")
+            }
+            ElabError::InvalidWidth(name) => {
+                write!(f, "range of '{name:}' is not a valid constant range")
+            }
+            ElabError::UndefinedPackage(name) => write!(f, "package '{name:}' is not defined"),
+            ElabError::UndefinedImport { package, item } => {
+                write!(f, "package '{package:}' does not declare '{item:}'")
+            }
+            ElabError::ImportConflict(name, package) => write!(
+                f,
+                "'{name:}' imported from package '{package:}' conflicts with a local declaration"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ElabError {}
+
+/// Errors occurring while reading, parsing, evaluating or simulating a
+/// design
+#[derive(Debug)]
+pub enum SimError {
+    /// Value contains X or Z bits where a known value is required
+    UnknownValue,
+
+    /// Reading or writing a file failed
+    Io(std::io::Error),
+
+    /// Preprocessing a source file failed
+    Preprocess(PreprocessError),
+
+    /// Parsing a source file failed
+    Lex(LexingError),
+
+    /// Module with the given name is defined more than once
+    DuplicateModule(String),
+
+    /// Interface with the given name is defined more than once
+    DuplicateInterface(String),
+
+    /// Package with the given name is defined more than once
+    DuplicatePackage(String),
+
+    /// Elaborating the design hierarchy failed
+    Elaboration(ElabError),
+
+    /// Item imported from a package (name, package) conflicts with a local
+    /// declaration
+    ImportConflict(String, String),
+
+    /// `$system` called with the given command while system calls are
+    /// disabled
+    SystemCallForbidden(String),
+
+    /// Signal connected to a port of a different width
+    PortWidthMismatch {
+        /// Hierarchical path of the instance
+        inst: String,
+
+        /// Port name
+        port: String,
+
+        /// Width of the port
+        port_width: u64,
+
+        /// Width of the connected signal
+        connection_width: u64,
+    },
+
+    /// Signals, in sorted order, that depend on each other through
+    /// combinational logic without a register to break the loop
+    CombinationalLoop(Vec<String>),
+
+    /// Test run that ended for the given reason rather than by `$finish`
+    Unfinished(sim::SimStatus),
+
+    /// Signal or memory with the given hierarchical name does not exist
+    UndefinedSignal(String),
+
+    /// Address outside the range of a memory
+    AddressOutOfRange {
+        /// Hierarchical name of the memory
+        memory: String,
+
+        /// Address accessed
+        addr: u64,
+    },
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SimError::UnknownValue => write!(f, "value contains X or Z bits"),
+            SimError::Io(err) => write!(f, "{err:}"),
+            SimError::Preprocess(err) => write!(f, "{err:}"),
+            SimError::Lex(err) => write!(f, "{err:}"),
+            SimError::DuplicateModule(name) => {
+                write!(f, "module '{name:}' is defined more than once")
+            }
+            SimError::DuplicateInterface(name) => {
+                write!(f, "interface '{name:}' is defined more than once")
+            }
+            SimError::DuplicatePackage(name) => {
+                write!(f, "package '{name:}' is defined more than once")
+            }
+            SimError::Elaboration(err) => write!(f, "{err:}"),
+            SimError::ImportConflict(name, package) => write!(
+                f,
+                "'{name:}' imported from package '{package:}' conflicts with a local declaration"
+            ),
+            SimError::SystemCallForbidden(cmd) => {
+                write!(f, "system call '{cmd:}' is not allowed")
+            }
+            SimError::PortWidthMismatch {
+                inst,
+                port,
+                port_width,
+                connection_width,
+            } => write!(
+                f,
+                "port '{port:}' of '{inst:}' is {port_width:} bits wide, but is connected to {connection_width:} bits"
+            ),
+            SimError::CombinationalLoop(signals) => {
+                write!(f, "combinational loop through {}", signals.join(", "))
+            }
+            SimError::Unfinished(status) => {
+                write!(f, "test ended because {status:} rather than by $finish")
+            }
+            SimError::UndefinedSignal(name) => write!(f, "signal '{name:}' does not exist"),
+            SimError::AddressOutOfRange { memory, addr } => {
+                write!(f, "address {addr:} is outside memory '{memory:}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SimError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SimError::Io(err) => Some(err),
+            SimError::Preprocess(err) => Some(err),
+            SimError::Lex(err) => Some(err),
+            SimError::Elaboration(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SimError {
+    fn from(err: std::io::Error) -> Self {
+        SimError::Io(err)
+    }
+}
+
+impl From<PreprocessError> for SimError {
+    fn from(err: PreprocessError) -> Self {
+        SimError::Preprocess(err)
+    }
+}
+
+impl From<LexingError> for SimError {
+    fn from(err: LexingError) -> Self {
+        SimError::Lex(err)
+    }
+}
+
+impl From<ElabError> for SimError {
+    fn from(err: ElabError) -> Self {
+        match err {
+            ElabError::ImportConflict(name, package) => SimError::ImportConflict(name, package),
+            ElabError::PortWidthMismatch {
+                inst,
+                port,
+                expected,
+                got,
+            } => SimError::PortWidthMismatch {
+                inst,
+                port,
+                port_width: expected,
+                connection_width: got,
+            },
+            err => SimError::Elaboration(err),
         }
     }
 }
 
 /// Lexer token output
-#[derive(Logos, Debug, PartialEq)]
+#[derive(Logos, Debug, Clone, PartialEq)]
 #[logos(error = LexingError)]
 #[logos(skip r"[\r\f]+")]
 pub enum Token {
@@ -94,10 +777,78 @@ pub enum Token {
     #[token("endmodule")]
     EndModule,
 
+    /// Interface start
+    #[token("interface")]
+    Interface,
+
+    /// Interface end
+    #[token("endinterface")]
+    EndInterface,
+
+    /// Package start
+    #[token("package")]
+    Package,
+
+    /// Package end
+    #[token("endpackage")]
+    EndPackage,
+
+    /// Modport declaration start
+    #[token("modport")]
+    Modport,
+
+    /// Generate region start
+    #[token("generate")]
+    Generate,
+
+    /// Generate region end
+    #[token("endgenerate")]
+    EndGenerate,
+
+    /// Function definition start
+    #[token("function")]
+    Function,
+
+    /// Function definition end
+    #[token("endfunction")]
+    EndFunction,
+
+    /// Task definition start
+    #[token("task")]
+    Task,
+
+    /// Task definition end
+    #[token("endtask")]
+    EndTask,
+
+    /// Return statement start
+    #[token("return")]
+    Return,
+
+    /// Statement leaving the innermost loop
+    #[token("break")]
+    Break,
+
+    /// Statement starting the next iteration of the innermost loop
+    #[token("continue")]
+    Continue,
+
+    /// Level-sensitive event control start
+    #[token("wait")]
+    Wait,
+
     /// Parameter start
     #[token("parameter")]
     Parameter,
 
+    /// Local parameter start
+    #[token("localparam")]
+    Localparam,
+
+    /// Type parameter keyword, as in `localparam type T = logic [7:0]`
+    #[token("type")]
+    Type,
+
     /// Inout start
     #[token("inout")]
     Inout,
@@ -118,6 +869,52 @@ pub enum Token {
     #[token("wire")]
     Wire,
 
+    /// Tri-state net start
+    #[token("tri")]
+    Tri,
+
+    /// Logic variable start
+    #[token("logic")]
+    Logic,
+
+    /// Variable keyword, as in `var logic v`, or alone for a `logic`, as in
+    /// `var [3:0] v`
+    #[token("var")]
+    Var,
+
+    /// 8 bit integer variable start
+    #[token("byte")]
+    Byte,
+
+    /// 16 bit integer variable start
+    #[token("shortint")]
+    ShortInt,
+
+    /// 32 bit integer variable start
+    #[token("int")]
+    Int,
+
+    /// 64 bit integer variable start
+    #[token("longint")]
+    LongInt,
+
+    /// 32 bit four-state integer variable start, not to be confused with
+    /// `Token::Integer` literals
+    #[token("integer")]
+    IntegerType,
+
+    /// Generate loop variable start
+    #[token("genvar")]
+    Genvar,
+
+    /// Signed qualifier
+    #[token("signed")]
+    Signed,
+
+    /// Unsigned qualifier
+    #[token("unsigned")]
+    Unsigned,
+
     /// Assign start
     #[token("assign")]
     Assign,
@@ -126,6 +923,30 @@ pub enum Token {
     #[token("always_comb")]
     Comb,
 
+    /// Sequential logic start
+    #[token("always_ff")]
+    AlwaysFF,
+
+    /// Latched logic start
+    #[token("always_latch")]
+    AlwaysLatch,
+
+    /// Initial block start
+    #[token("initial")]
+    Initial,
+
+    /// Final block start
+    #[token("final")]
+    Final,
+
+    /// General purpose procedural block start
+    #[token("always")]
+    Always,
+
+    /// Event list separator, as in `@(posedge clk or negedge rst)`
+    #[token("or")]
+    Or,
+
     /// If statement start
     #[token("if")]
     If,
@@ -134,6 +955,56 @@ pub enum Token {
     #[token("else")]
     Else,
 
+    /// Qualifier asking for exactly one branch of an if or case statement to
+    /// match, or at most one for `unique0`
+    #[token("unique")]
+    #[token("unique0")]
+    Unique,
+
+    /// Qualifier asking for some branch of an if or case statement to match
+    #[token("priority")]
+    Priority,
+
+    /// For loop start
+    #[token("for")]
+    For,
+
+    /// While loop start, or end of a do-while loop
+    #[token("while")]
+    While,
+
+    /// Do-while loop start
+    #[token("do")]
+    Do,
+
+    /// Repeat loop start
+    #[token("repeat")]
+    Repeat,
+
+    /// Forever loop start
+    #[token("forever")]
+    Forever,
+
+    /// Case statement start
+    #[token("case")]
+    Case,
+
+    /// Case statement start, with `z` bits as don't-cares
+    #[token("casez")]
+    Casez,
+
+    /// Case statement start, with `x` and `z` bits as don't-cares
+    #[token("casex")]
+    Casex,
+
+    /// Case statement end
+    #[token("endcase")]
+    EndCase,
+
+    /// Default case item
+    #[token("default")]
+    Default,
+
     /// Begin statement
     #[token("begin")]
     Begin,
@@ -142,6 +1013,22 @@ pub enum Token {
     #[token("end")]
     End,
 
+    /// Parallel block start
+    #[token("fork")]
+    Fork,
+
+    /// Parallel block end, waiting for every branch
+    #[token("join")]
+    Join,
+
+    /// Parallel block end, waiting for any one branch
+    #[token("join_any")]
+    JoinAny,
+
+    /// Parallel block end, waiting for no branch
+    #[token("join_none")]
+    JoinNone,
+
     /// Rising edge trigger
     #[token("posedge")]
     Posedge,
@@ -154,11 +1041,21 @@ pub enum Token {
     #[token("timescale")]
     Timescale,
 
+    /// Package import start
+    #[token("import")]
+    Import,
+
     /// Simulation time
     ///
-    /// Accepts times in ns or ps
-    #[regex(r"\d+ns", nanosecond)]
-    #[regex(r"\d+ps", picosecond)]
+    /// Accepts times in s, ms, us, ns, ps or fs, stored in seconds. Digits
+    /// may be separated by underscores, as in `1_000ns`, and times may be
+    /// fractional, as in `2.5ns`
+    #[regex(r"\d[\d_]*(\.\d[\d_]*)?s", second)]
+    #[regex(r"\d[\d_]*(\.\d[\d_]*)?ms", millisecond)]
+    #[regex(r"\d[\d_]*(\.\d[\d_]*)?us", microsecond)]
+    #[regex(r"\d[\d_]*(\.\d[\d_]*)?ns", nanosecond)]
+    #[regex(r"\d[\d_]*(\.\d[\d_]*)?ps", picosecond)]
+    #[regex(r"\d[\d_]*(\.\d[\d_]*)?fs", femtosecond)]
     Time(f64),
 
     /// Pound symbol
@@ -189,10 +1086,30 @@ pub enum Token {
     #[token("}")]
     CloseBrace,
 
+    /// Assignment pattern start, as in `'{a, b}` or `'{x: a, y: b}`
+    #[token("'{")]
+    TickBrace,
+
+    /// Cast start, as in `signed'(a)`
+    #[token("'(")]
+    TickParen,
+
     /// Equivalent comparison
     #[token("==")]
     BEQ,
 
+    /// Not equivalent comparison
+    #[token("!=")]
+    BNEQ,
+
+    /// Case equality, comparing X and Z bits as values
+    #[token("===")]
+    CaseEq,
+
+    /// Case inequality, comparing X and Z bits as values
+    #[token("!==")]
+    CaseNeq,
+
     /// Less than comparison
     #[token("<")]
     BLT,
@@ -221,6 +1138,12 @@ pub enum Token {
     #[token("+")]
     Add,
 
+    /// Power
+    ///
+    /// Binds tighter than any other binary operator
+    #[token("**")]
+    Power,
+
     /// Multiply
     #[token("*")]
     Multiply,
@@ -229,18 +1152,100 @@ pub enum Token {
     #[token("/")]
     Divide,
 
+    /// Modulus
+    #[token("%")]
+    Modulo,
+
     /// Question mark
     #[token("?")]
     QMark,
 
-    /// Exclamation point
+    /// Logical NOT, producing a single bit result
     #[token("!")]
-    EMark,
+    LogicalNot,
+
+    /// Bitwise NOT, producing a result as wide as its operand
+    #[token("~")]
+    BitNot,
+
+    /// Bitwise AND, or AND reduction when used as a prefix
+    #[token("&")]
+    BitAnd,
+
+    /// Bitwise OR, or OR reduction when used as a prefix
+    #[token("|")]
+    BitOr,
+
+    /// Bitwise XOR, or XOR reduction when used as a prefix
+    #[token("^")]
+    BitXor,
+
+    /// Bitwise XNOR, or XNOR reduction when used as a prefix
+    ///
+    /// Accepts both `~^` and `^~`. Lexing prefers the longest match, so
+    /// `~^` is never split into `~` and `^`
+    #[token("~^")]
+    #[token("^~")]
+    BitXnor,
+
+    /// Logical AND, producing a single bit result
+    ///
+    /// Binds tighter than `||` and looser than every bitwise operator
+    #[token("&&")]
+    LogAnd,
+
+    /// Logical OR, producing a single bit result
+    ///
+    /// Binds looser than every other binary operator
+    #[token("||")]
+    LogOr,
+
+    /// Logical shift left
+    ///
+    /// Shifts bind looser than `+` and `-` and tighter than comparisons
+    #[token("<<")]
+    SHL,
+
+    /// Logical shift right, filling with zeros
+    #[token(">>")]
+    SHR,
+
+    /// Arithmetic shift left, identical to `<<`
+    #[token("<<<")]
+    ASHL,
+
+    /// Arithmetic shift right, filling with the sign bit of signed operands
+    #[token(">>>")]
+    ASHR,
+
+    /// NAND reduction, only valid as a prefix
+    #[token("~&")]
+    ReductionNand,
+
+    /// NOR reduction, only valid as a prefix
+    #[token("~|")]
+    ReductionNor,
 
     /// Colon
     #[token(":")]
     Colon,
 
+    /// Ascending indexed part-select, as in `a[base +: width]`
+    #[token("+:")]
+    PlusColon,
+
+    /// Descending indexed part-select, as in `a[base -: width]`
+    #[token("-:")]
+    MinusColon,
+
+    /// Scope resolution, as in `pkg::CONST` or `MyClass::static_method()`
+    #[token("::")]
+    DoubleColon,
+
+    /// Member access, as in `inst_handle.field`, or named port connection
+    #[token(".")]
+    Dot,
+
     /// Semicolon
     #[token(";")]
     Semicolon,
@@ -272,104 +1277,489 @@ pub enum Token {
 
     /// Binary value
     ///
-    /// Takes form `X'bY` where `X` is the bit width and `Y` is the desired value
-    #[regex(r"\d+'b\d+")]
-    BinaryValue,
+    /// Takes form `X'bY` where `X` is the optional bit width and `Y` is the
+    /// value in binary. Digits of `Y` may be separated by underscores, as in
+    /// `8'b1010_0101`, and may be `x`, `z` or `?` for unknown and high
+    /// impedance bits, as in `4'b10xz`. An `s` before the base marks the
+    /// value as signed, as in `4'sb1010`, for this and every other base
+    #[regex(r"(\d[\d_]*)?'[sS]?[bB][01xXzZ?][01xXzZ?_]*", sized_literal)]
+    BinaryValue(SizedLiteral),
+
+    /// Octal value
+    ///
+    /// Takes form `X'oY` where `X` is the optional bit width and `Y` is the
+    /// value in octal, as in `12'o7_7`
+    #[regex(r"(\d[\d_]*)?'[sS]?[oO][0-7xXzZ?][0-7xXzZ?_]*", sized_literal)]
+    OctalValue(SizedLiteral),
 
-    /// Hi-Z value
+    /// Decimal value
     ///
-    /// Takes form `X'bz` where `X` is the bit width
-    #[regex(r"\d+'bz")]
-    HiZValue,
+    /// Takes form `X'dY` where `X` is the optional bit width and `Y` is the
+    /// value in decimal, as in `16'd65_535`. A single `x`, `z` or `?` digit
+    /// makes every bit unknown or high impedance
+    #[regex(r"(\d[\d_]*)?'[sS]?[dD]([0-9][0-9_]*|[xXzZ?]_*)", sized_literal)]
+    DecimalValue(SizedLiteral),
 
-    /// Comment start
-    #[regex(r"//")]
-    Comment,
+    /// Hexadecimal value
+    ///
+    /// Takes form `X'hY` where `X` is the optional bit width and `Y` is the
+    /// value in hexadecimal, as in `32'hDEAD_BEEF`
+    #[regex(r"(\d[\d_]*)?'[sS]?[hH][0-9a-fA-FxXzZ?][0-9a-fA-FxXzZ?_]*", sized_literal)]
+    HexValue(SizedLiteral),
+
+    /// Unbased, unsized value
+    ///
+    /// Takes form `'Y` where `Y` is one of `0`, `1`, `x` or `z`, and sets
+    /// every bit of the expression it is in, as in `q <= '1`
+    #[regex(r"'[01xXzZ]", unbased_literal)]
+    UnbasedValue(LogicState),
+
+    /// Line comment start, running to the end of the line
+    #[token("//")]
+    LineComment,
+
+    /// Block comment start
+    #[token("/*")]
+    BlockCommentStart,
+
+    /// Block comment end
+    #[token("*/")]
+    BlockCommentEnd,
 
-    /// Generic text
-    #[regex(r"[a-zA-Z]+")]
+    /// Generic text, such as an identifier
+    ///
+    /// Takes the longest run of identifier characters, so names that start
+    /// with a keyword, as in `byte_en` or `logic_out`, lex as a single word.
+    /// Names may start with an underscore, as in `_8b`, though a lone `_` is
+    /// `Underscore`
+    #[regex(r"[a-zA-Z][a-zA-Z0-9_$]*|_[a-zA-Z0-9_$]+")]
     Word,
 
+    /// System task or function name
+    ///
+    /// Takes form `$X`, as in `$display`. The value excludes the leading `$`
+    #[regex(r"\$[a-zA-Z_][a-zA-Z0-9_$]*", |lex| lex.slice()[1..].to_owned())]
+    SystemIdent(String),
+
+    /// String literal
+    ///
+    /// The value excludes the surrounding quotes and keeps escape sequences
+    /// as written
+    #[regex(r#""([^"\\\n]|\\.)*""#, string_literal)]
+    StringLiteral(String),
+
+    /// Escaped identifier
+    ///
+    /// Takes form `\X` where `X` is any sequence of non-whitespace characters.
+    /// The identifier ends at the next whitespace and its value excludes the
+    /// leading backslash
+    #[regex(r"\\[^ \t\r\n]+", |lex| lex.slice()[1..].to_owned())]
+    EscapedIdentifier(String),
+
     /// Integer value
-    #[regex(r"[0-9]+", |lex| lex.slice().parse())]
+    ///
+    /// Digits may be separated by underscores, as in `1_000_000`
+    #[regex(r"[0-9][0-9_]*", |lex| lex.slice().replace('_', "").parse())]
     Integer(u64),
+
+    /// Fixed point real value, as in `2.5`
+    ///
+    /// Digits may be separated by underscores, as in `1_000.5`
+    #[regex(r"[0-9][0-9_]*\.[0-9][0-9_]*", |lex| lex.slice().replace('_', "").parse().ok())]
+    Real(f64),
 }
 
 /// Reads a SystemVerilog file to string for parsing
-pub fn read_sv_file(path: &std::path::PathBuf) -> Result<String, std::io::Error> {
+///
+/// A file that cannot be read returns `SimError::Io`, whose message names
+/// the file
+pub fn read_sv_file(path: &std::path::PathBuf) -> Result<String, SimError> {
     trace!("reading sv file {:?}", path);
 
-    Ok(fs::read_to_string(path)?)
+    fs::read_to_string(path).map_err(|e| {
+        let message = format!("failed to read {}: {}", path.display(), e);
+        SimError::Io(std::io::Error::new(e.kind(), message))
+    })
 }
 
 /// Simulation object
 ///
-/// Contains file metadata and modules
+/// Contains file metadata, modules, interfaces and packages
 #[derive(Default)]
 pub struct SimObject {
     /// Simulation timing information
     pub sim_time: SimTime,
 
-    /// Object modules
-    pub mods: Vec<Module>,
+    /// Object modules by name
+    pub mods: HashMap<String, Module>,
+
+    /// Object interfaces, in source order
+    pub interfaces: Vec<Interface>,
+
+    /// Object packages, in source order
+    pub packages: Vec<Package>,
+}
+
+impl SimObject {
+    /// Adds a module to the object, failing if one with the same name is
+    /// already present
+    fn add_module(&mut self, module: Module) -> Result<(), SimError> {
+        if self.mods.contains_key(&module.name) {
+            error!("module '{}' is defined more than once", module.name);
+            return Err(SimError::DuplicateModule(module.name));
+        }
+
+        self.mods.insert(module.name.to_owned(), module);
+        Ok(())
+    }
+
+    /// Adds an interface to the object, failing if one with the same name is
+    /// already present
+    fn add_interface(&mut self, interface: Interface) -> Result<(), SimError> {
+        if self.interface(&interface.name).is_some() {
+            error!("interface '{}' is defined more than once", interface.name);
+            return Err(SimError::DuplicateInterface(interface.name));
+        }
+
+        self.interfaces.push(interface);
+        Ok(())
+    }
+
+    /// Adds a package to the object, failing if one with the same name is
+    /// already present
+    fn add_package(&mut self, package: Package) -> Result<(), SimError> {
+        if self.package(&package.name).is_some() {
+            error!("package '{}' is defined more than once", package.name);
+            return Err(SimError::DuplicatePackage(package.name));
+        }
+
+        self.packages.push(package);
+        Ok(())
+    }
+
+    /// Object modules sorted by name
+    pub fn sorted_mods(&self) -> Vec<&Module> {
+        let mut mods: Vec<&Module> = self.mods.values().collect();
+        mods.sort_by(|a, b| a.name.cmp(&b.name));
+        mods
+    }
+
+    /// Finds an interface by name
+    pub fn interface(&self, name: &str) -> Option<&Interface> {
+        self.interfaces
+            .iter()
+            .find(|interface| interface.name == name)
+    }
+
+    /// Finds a package by name
+    pub fn package(&self, name: &str) -> Option<&Package> {
+        self.packages.iter().find(|package| package.name == name)
+    }
 }
 
 impl fmt::Debug for SimObject {
     fn fmt(&self, _: &mut std::fmt::Formatter) -> fmt::Result {
         debug!("{:?}", self.sim_time);
 
-        for module in &self.mods {
-            format!("{module:?}");
+        for module in self.sorted_mods() {
+            let _ = format!("{module:?}");
+        }
+        for interface in &self.interfaces {
+            let _ = format!("{interface:?}");
+        }
+        for package in &self.packages {
+            debug!("{package:?}");
         }
         Ok(())
     }
 }
 
-/// Parses a read SystemVerilog file
+/// Simulation configuration
+#[derive(Default, Debug, Clone)]
+pub struct SimConfig {
+    /// Allows `$system` to run shell commands on the host
+    ///
+    /// Disabled by default. Enabling this lets any simulated design run
+    /// arbitrary commands with the permissions of the simulator, so it should
+    /// only be enabled for trusted sources
+    pub allow_system_calls: bool,
+
+    /// Reports port connections whose width differs from the port's as
+    /// warnings during elaboration, rather than failing
+    pub allow_implicit_truncation: bool,
+
+    /// Fails elaboration when an instance leaves a port unconnected, rather
+    /// than warning. Unconnected interface ports always fail
+    pub deny_unconnected_ports: bool,
+
+    /// Most simulated time, in seconds, each test run by
+    /// `ElaboratedDesign::run_testbench` may take before it fails, or
+    /// `DEFAULT_TEST_TIMEOUT` if not given
+    pub test_timeout: Option<f64>,
+}
+
+/// Simulated time, in seconds, a test may take when `SimConfig` gives no
+/// timeout, so that a test of a design with a free-running clock still ends
+pub const DEFAULT_TEST_TIMEOUT: f64 = 1e-3;
+
+/// Preprocesses and parses a read SystemVerilog file
+///
+/// Preprocessing errors are returned as `SimError::Preprocess`, and parse
+/// errors as `SimError::Lex` carrying the line and column of the token they
+/// were found at
+pub fn parse_sv_file(file_contents: String) -> Result<SimObject, SimError> {
+    let mut object = SimObject::default();
+    let source = Preprocessor::new().preprocess(&file_contents)?;
+
+    parse_sv_source(&source, &mut object)?;
+
+    Ok(object)
+}
+
+/// Reads and parses several SystemVerilog files into a single object
+///
+/// See `parse_sv_files_with` for how the files are combined
+pub fn parse_sv_files(paths: &[PathBuf]) -> Result<SimObject, SimError> {
+    parse_sv_files_with(paths, &mut Preprocessor::new())
+}
+
+/// Reads, preprocesses and parses several SystemVerilog files into a
+/// single object
+///
+/// Files are parsed in order, and a `timescale` directive carries over into
+/// the files after it, as do macros defined in `preprocessor`. Included
+/// files are searched for next to the file including them, then in the
+/// include directories. Defining a module in more than one file returns
+/// `SimError::DuplicateModule`, and an interface `SimError::DuplicateInterface`
+pub fn parse_sv_files_with(
+    paths: &[PathBuf],
+    preprocessor: &mut Preprocessor,
+) -> Result<SimObject, SimError> {
+    let mut object = SimObject::default();
+
+    for path in paths {
+        parse_sv_path(path, preprocessor, &mut object)
+            .inspect_err(|e| error!("encountered an error in {:?}: '{}'", path, e))?;
+    }
+
+    Ok(object)
+}
+
+/// Reads, preprocesses and parses a single file, adding its modules to
+/// `object`
 ///
-/// At this time, `parse_sv_file` can only return a single error
-pub fn parse_sv_file(file_contents: String) -> Result<SimObject, LexingError> {
-    let mut lexer = Token::lexer(file_contents.as_str());
-    let mut sim_time = SimTime::default();
-    let mut mods: Vec<Module> = Vec::new();
+/// Parse errors are located at the line they are found at in the file, or
+/// in the file it includes, rather than in the preprocessed source
+pub(crate) fn parse_sv_path(
+    path: &PathBuf,
+    preprocessor: &mut Preprocessor,
+    object: &mut SimObject,
+) -> Result<(), SimError> {
+    let source = preprocessor.preprocess_file(path, &read_sv_file(path)?)?;
+
+    parse_sv_source(&source, object).map_err(|e| match e {
+        SimError::Lex(LexingError::Spanned {
+            error,
+            span,
+            line,
+            column,
+            ..
+        }) => {
+            let (file, line) = preprocessor.source_line(line);
+            SimError::Lex(LexingError::Spanned {
+                error,
+                span,
+                line,
+                column,
+                file: file.map(Path::to_path_buf),
+            })
+        }
+        e => e,
+    })
+}
+
+/// Parses a single source, adding its modules to `object`
+pub(crate) fn parse_sv_source(file_contents: &str, object: &mut SimObject) -> Result<(), SimError> {
+    let mut lexer = Token::lexer(file_contents);
 
     trace!("parsing sv file");
 
     while let Some(token) = lexer.next() {
         match token {
-            Ok(Token::Module) => mods.push(parse_module(&mut lexer)?),
-            Ok(Token::BTick) => sim_time = parse_sim_time(&mut lexer)?,
-            Ok(Token::Comment) => parse_comment(&mut lexer)?,
+            Ok(Token::Module) => {
+                let module = parse_module(&mut lexer).map_err(|e| err_with_span(e, &lexer))?;
+                object.add_module(module)?
+            }
+            Ok(Token::Interface) => {
+                let interface =
+                    parse_interface(&mut lexer).map_err(|e| err_with_span(e, &lexer))?;
+                object.add_interface(interface)?
+            }
+            Ok(Token::Package) => {
+                let package = parse_package(&mut lexer).map_err(|e| err_with_span(e, &lexer))?;
+                object.add_package(package)?
+            }
+            Ok(Token::BTick) => {
+                object.sim_time =
+                    parse_sim_time(&mut lexer).map_err(|e| err_with_span(e, &lexer))?
+            }
+            Ok(Token::LineComment) => {
+                parse_comment(&mut lexer).map_err(|e| err_with_span(e, &lexer))?
+            }
+            Ok(Token::BlockCommentStart) => {
+                parse_block_comment(&mut lexer).map_err(|e| err_with_span(e, &lexer))?
+            }
             Ok(Token::Newline) | Ok(Token::WhiteSpace) => (),
             Err(e) => {
                 error!(
                     "unexpected error occurred parsing sv file: '{}'",
                     lexer.slice()
                 );
-                return Err(e);
+                return Err(err_with_span(e, &lexer).into());
             }
             _ => warn!("{:?} not implemented", token.unwrap()),
         }
     }
 
-    Ok(SimObject { sim_time, mods })
+    Ok(())
+}
+
+/// Strips the quotes from a string literal
+fn string_literal(lex: &mut Lexer<Token>) -> String {
+    let slice = lex.slice();
+    slice[1..slice.len() - 1].to_owned()
 }
 
+/// Parses a line comment to completion
+///
+/// The comment ends at the next newline or the end of the file, and may
+/// contain any text, including text that does not lex
 fn parse_comment<'source>(lexer: &mut Lexer<'source, Token>) -> Result<(), LexingError> {
     trace!("parsing comment");
 
+    for token in lexer.by_ref() {
+        if token == Ok(Token::Newline) {
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+/// Parses a block comment to completion
+///
+/// Block comments cannot be nested, so a `/*` inside a block comment is
+/// ignored and the comment ends at the first `*/`. If the comment did contain
+/// a `/*` and a stray `*/` follows on the same line, the comment was most
+/// likely written as if it nested and `LexingError::ImproperCommentFormatting`
+/// is returned
+fn parse_block_comment<'source>(lexer: &mut Lexer<'source, Token>) -> Result<(), LexingError> {
+    let mut nested_start = false;
+
+    trace!("parsing block comment");
+
     while let Some(token) = lexer.next() {
         match token {
-            Ok(Token::Newline) => return Ok(()),
-            Err(_) => {
-                error!(
-                    "unexpected error occurred parsing comment: '{}'",
-                    lexer.slice()
-                );
-                return Err(LexingError::ImproperCommentFormatting);
+            Ok(Token::BlockCommentEnd) => {
+                if nested_start && has_stray_comment_end(lexer) {
+                    error!("nested block comments are not supported, comment ended at first '*/'");
+                    return Err(LexingError::ImproperCommentFormatting);
+                }
+
+                return Ok(());
+            }
+            Ok(Token::BlockCommentStart) => {
+                warn!("'/*' inside block comment is ignored, block comments do not nest");
+                nested_start = true;
             }
+            // Comments may contain any text, including text that does not lex
             _ => (),
         };
     }
-    Ok(())
+
+    error!("block comment not closed before end of file");
+    Err(LexingError::ImproperCommentFormatting)
+}
+
+/// Returns the next token that is not whitespace, a newline or a comment
+///
+/// Comments are parsed to completion and any error doing so is returned in
+/// place of a token
+pub fn next_token<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Option<Result<Token, LexingError>> {
+    while let Some(token) = lexer.next() {
+        match token {
+            Ok(Token::WhiteSpace) | Ok(Token::Newline) => (),
+            Ok(Token::LineComment) => {
+                if let Err(e) = parse_comment(lexer) {
+                    return Some(Err(e));
+                }
+            }
+            Ok(Token::BlockCommentStart) => {
+                if let Err(e) = parse_block_comment(lexer) {
+                    return Some(Err(e));
+                }
+            }
+            _ => return Some(token),
+        }
+    }
+
+    None
+}
+
+/// Returns the token `next_token` would return without consuming it
+pub fn peek_token(lexer: &Lexer<Token>) -> Option<Result<Token, LexingError>> {
+    next_token(&mut lexer.clone())
+}
+
+/// Consumes the next token, failing if it is not `expected`
+pub fn expect_token<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    expected: Token,
+    context: &str,
+) -> Result<(), LexingError> {
+    match next_token(lexer) {
+        Some(Ok(token)) if token == expected => Ok(()),
+        Some(Err(e)) => {
+            error!(
+                "unexpected error occurred parsing {}: '{}'",
+                context,
+                lexer.slice()
+            );
+            Err(e)
+        }
+        _ => {
+            error!(
+                "expected {:?} parsing {}: '{}'",
+                expected,
+                context,
+                lexer.slice()
+            );
+            if expected == Token::Semicolon {
+                Err(LexingError::ExpectedSemi)
+            } else {
+                Err(LexingError::UnexpectedToken)
+            }
+        }
+    }
+}
+
+/// Checks for a `*/` ahead of the lexer that is not preceded by a `/*`
+///
+/// Only the rest of the current line up to the next `;` is searched, and a
+/// `*/` inside a line comment does not count, so that a comment end written
+/// much later in the file is not mistaken for the end of a nested comment
+fn has_stray_comment_end(lexer: &Lexer<Token>) -> bool {
+    for token in lexer.clone() {
+        match token {
+            Ok(Token::BlockCommentEnd) => return true,
+            Ok(Token::BlockCommentStart)
+            | Ok(Token::LineComment)
+            | Ok(Token::Newline)
+            | Ok(Token::Semicolon) => return false,
+            _ => (),
+        }
+    }
+
+    false
 }
@@ -0,0 +1,427 @@
+use crate::elab::ElaboratedDesign;
+use crate::eval::collect_reads;
+use crate::expr::Expr;
+use crate::logic::{JoinKind, LogicStmt, LoopStmt};
+use crate::module::Module;
+use crate::sim::{scoped_name, stmt_reads, stmt_writes};
+use crate::SimError;
+use log::{error, trace, warn};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Finds the combinational loops in a module's continuous assignments and
+/// `always_comb` blocks
+///
+/// Each assignment makes the signals it writes depend on the signals it
+/// reads, including those read by the conditions it is nested in. Every
+/// strongly connected component of more than one signal in the resulting
+/// graph is a loop, returned as its signal names in sorted order. Loops are
+/// sorted by their first name
+///
+/// Generate blocks are not unrolled, so a module should be generated with
+/// its parameter values first to check the logic they produce
+pub fn detect_combinational_loops(module: &Module) -> Vec<Vec<String>> {
+    trace!("checking '{}' for combinational loops", module.name);
+
+    let mut graph: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for (read, written) in comb_dependencies(module) {
+        graph.entry(written.to_owned()).or_default();
+        graph.entry(read).or_default().insert(written);
+    }
+
+    let mut loops: Vec<Vec<String>> = strongly_connected(&graph)
+        .into_iter()
+        .filter(|component| component.len() > 1)
+        .map(|mut component| {
+            component.sort();
+            component
+        })
+        .collect();
+    loops.sort();
+
+    loops
+}
+
+/// Counts the logic blocks reading each signal of a module
+///
+/// Every continuous assignment and `always_comb`, `always_ff` and
+/// `always_latch` block counts once towards the fanout of each signal it
+/// reads, however many times it reads it. The clock and reset of an
+/// `always_ff` block count as read by it. Signals no block reads are left
+/// out, as are parameters
+pub fn compute_fanout(module: &Module) -> HashMap<String, u64> {
+    trace!("computing fanout of '{}'", module.name);
+
+    let io = &module.io;
+    let signals: BTreeSet<&str> = io
+        .inputs
+        .iter()
+        .map(|input| input.name.as_str())
+        .chain(io.outputs.iter().map(|output| output.name.as_str()))
+        .chain(io.inouts.iter().map(|inout| inout.name.as_str()))
+        .chain(module.vars.iter().map(|var| var.name.as_str()))
+        .collect();
+
+    let assigns = module.assigns.iter().map(|assign| {
+        stmt_reads(&[LogicStmt::BlockingAssign {
+            lhs: assign.lhs.clone(),
+            rhs: assign.rhs.clone(),
+        }])
+    });
+    let comb = module.comb_blocks.iter().map(|block| stmt_reads(&block.body));
+    let latch = module.latch_blocks.iter().map(|block| stmt_reads(&block.body));
+    let ff = module.ff_blocks.iter().map(|block| {
+        let mut reads = stmt_reads(&block.body);
+        reads.push(block.clock.signal.to_owned());
+        reads.extend(block.reset.iter().map(|reset| reset.signal.to_owned()));
+        reads
+    });
+
+    let mut fanout: HashMap<String, u64> = HashMap::new();
+    for reads in assigns.chain(comb).chain(latch).chain(ff) {
+        let reads: BTreeSet<String> = reads.into_iter().collect();
+        for name in reads {
+            if signals.contains(name.as_str()) {
+                *fanout.entry(name).or_default() += 1;
+            }
+        }
+    }
+
+    fanout
+}
+
+/// Names of the signals assigned on some paths through `body` but not
+/// others, in sorted order
+///
+/// A path that skips a signal's assignment leaves it holding its value,
+/// which is what an `always_latch` block is for, but infers a latch in an
+/// `always_comb` block. An `if` without an `else`, a `case` without a
+/// `default`, and any loop that may run no iterations are taken to be such
+/// paths, as constant conditions are not evaluated
+pub fn partially_assigned(body: &[LogicStmt]) -> Vec<String> {
+    let always = assigned_on_every_path(body);
+    let some: BTreeSet<String> = stmt_writes(body).into_iter().collect();
+
+    some.difference(&always).cloned().collect()
+}
+
+/// Finds the signals of a module's `always_comb` blocks that are not
+/// assigned on every path through their block, inferring latches
+///
+/// Signals are returned in the order of their blocks, as found by
+/// `partially_assigned`
+pub fn detect_inferred_latches(module: &Module) -> Vec<String> {
+    trace!("checking '{}' for inferred latches", module.name);
+
+    module
+        .comb_blocks
+        .iter()
+        .flat_map(|block| partially_assigned(&block.body))
+        .collect()
+}
+
+/// Names of the signals assigned on every path through `stmts`
+fn assigned_on_every_path(stmts: &[LogicStmt]) -> BTreeSet<String> {
+    /// Names assigned on every path through all of `bodies`
+    fn on_every_branch<'a>(bodies: impl IntoIterator<Item = &'a [LogicStmt]>) -> BTreeSet<String> {
+        bodies
+            .into_iter()
+            .map(assigned_on_every_path)
+            .reduce(|all, names| all.intersection(&names).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    let mut names: BTreeSet<String> = BTreeSet::new();
+
+    for stmt in stmts {
+        match stmt {
+            LogicStmt::BlockingAssign { .. }
+            | LogicStmt::NonBlockingAssign { .. }
+            | LogicStmt::DelayedAssign { .. } => {
+                names.extend(stmt_writes(std::slice::from_ref(stmt)))
+            }
+            LogicStmt::If(stmt) => {
+                if let Some(else_) = &stmt.else_ {
+                    let branches = std::iter::once(&stmt.then)
+                        .chain(stmt.else_if.iter().map(|(_, body)| body))
+                        .chain(std::iter::once(else_));
+                    names.extend(on_every_branch(branches.map(Vec::as_slice)));
+                }
+            }
+            LogicStmt::Case { items, default, .. } => {
+                if let Some(default) = default {
+                    let branches = items.iter().map(|item| &item.body).chain([default]);
+                    names.extend(on_every_branch(branches.map(Vec::as_slice)));
+                }
+            }
+            LogicStmt::For(stmt) => names.extend(stmt_writes(std::slice::from_ref(&*stmt.init))),
+            LogicStmt::Loop(LoopStmt::DoWhile { body, .. } | LoopStmt::Forever { body })
+            | LogicStmt::Delay { body, .. }
+            | LogicStmt::EventControl { body, .. }
+            | LogicStmt::Wait { body, .. } => names.extend(assigned_on_every_path(body)),
+            LogicStmt::ForkJoin {
+                branches,
+                join: JoinKind::Join,
+            } => {
+                for body in branches {
+                    names.extend(assigned_on_every_path(body));
+                }
+            }
+            LogicStmt::Loop(_)
+            | LogicStmt::ForkJoin { .. }
+            | LogicStmt::Display { .. }
+            | LogicStmt::SysTaskCall { .. }
+            | LogicStmt::TaskCall { .. }
+            | LogicStmt::Return(_)
+            | LogicStmt::Break
+            | LogicStmt::Continue => (),
+        }
+    }
+
+    names
+}
+
+impl ElaboratedDesign {
+    /// Finds the nets of every instance of the design read by more than
+    /// `threshold` logic blocks, as hierarchical names with their fanout
+    ///
+    /// Fanout is counted within each instance, as by `compute_fanout`, with
+    /// the instance's generate blocks unrolled. Nets are sorted by fanout,
+    /// highest first, then by name
+    pub fn high_fanout_nets(&self, threshold: u64) -> Vec<(String, u64)> {
+        let mut nets: Vec<(String, u64)> = Vec::new();
+
+        for inst in self.top.iter() {
+            let Some(module) = self.mods.get(&inst.module_name) else {
+                continue;
+            };
+            let generated = module.generate(&inst.params).unwrap_or_else(|e| {
+                warn!("'{}' is only partly checked: {}", inst.path, e);
+                module.clone()
+            });
+
+            nets.extend(
+                compute_fanout(&generated)
+                    .into_iter()
+                    .filter(|(_, fanout)| *fanout > threshold)
+                    .map(|(name, fanout)| (scoped_name(&inst.path, &name), fanout)),
+            );
+        }
+
+        nets.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        nets
+    }
+
+    /// Finds the combinational loops in every instance of the design, as
+    /// `SimError::CombinationalLoop` holding hierarchical signal names
+    ///
+    /// Each instance is checked with its generate blocks unrolled using the
+    /// parameter values it was elaborated with, as by
+    /// `detect_combinational_loops`. Loops running through the ports of an
+    /// instance are not found
+    pub fn combinational_loops(&self) -> Vec<SimError> {
+        let mut errors: Vec<SimError> = Vec::new();
+
+        for inst in self.top.iter() {
+            let Some(module) = self.mods.get(&inst.module_name) else {
+                continue;
+            };
+            let generated = module.generate(&inst.params).unwrap_or_else(|e| {
+                warn!("'{}' is only partly checked: {}", inst.path, e);
+                module.clone()
+            });
+
+            for signals in detect_combinational_loops(&generated) {
+                let signals: Vec<String> = signals
+                    .iter()
+                    .map(|name| scoped_name(&inst.path, name))
+                    .collect();
+                let err = SimError::CombinationalLoop(signals);
+                error!("{}", err);
+                errors.push(err);
+            }
+        }
+
+        errors
+    }
+}
+
+/// Signal dependencies made by a module's continuous assignments and
+/// `always_comb` blocks, as `(read, written)` pairs
+fn comb_dependencies(module: &Module) -> BTreeSet<(String, String)> {
+    let mut deps: BTreeSet<(String, String)> = BTreeSet::new();
+
+    for assign in &module.assigns {
+        let stmt = LogicStmt::BlockingAssign {
+            lhs: assign.lhs.clone(),
+            rhs: assign.rhs.clone(),
+        };
+        stmt_dependencies(std::slice::from_ref(&stmt), &[], &mut deps);
+    }
+    for block in &module.comb_blocks {
+        stmt_dependencies(&block.body, &[], &mut deps);
+    }
+
+    deps
+}
+
+/// Adds the dependencies made by the assignments in `stmts` to `deps`
+///
+/// `control` holds the names read by the conditions the statements are
+/// nested in, which every assignment among them depends on as well
+fn stmt_dependencies(
+    stmts: &[LogicStmt],
+    control: &[String],
+    deps: &mut BTreeSet<(String, String)>,
+) {
+    for stmt in stmts {
+        match stmt {
+            LogicStmt::BlockingAssign { .. }
+            | LogicStmt::NonBlockingAssign { .. }
+            | LogicStmt::DelayedAssign { .. } => {
+                let stmt = std::slice::from_ref(stmt);
+                let mut reads = control.to_vec();
+                reads.extend(stmt_reads(stmt));
+
+                for written in stmt_writes(stmt) {
+                    for read in &reads {
+                        deps.insert((read.to_owned(), written.to_owned()));
+                    }
+                }
+            }
+            LogicStmt::If(stmt) => {
+                let conds = std::iter::once(&stmt.cond).chain(stmt.else_if.iter().map(|(c, _)| c));
+                let control = with_reads(control, conds);
+                stmt_dependencies(&stmt.then, &control, deps);
+                for (_, body) in &stmt.else_if {
+                    stmt_dependencies(body, &control, deps);
+                }
+                if let Some(body) = &stmt.else_ {
+                    stmt_dependencies(body, &control, deps);
+                }
+            }
+            LogicStmt::Case {
+                expr,
+                items,
+                default,
+                ..
+            } => {
+                let labels = items.iter().flat_map(|item| &item.labels);
+                let control = with_reads(control, std::iter::once(expr).chain(labels));
+                for item in items {
+                    stmt_dependencies(&item.body, &control, deps);
+                }
+                if let Some(body) = default {
+                    stmt_dependencies(body, &control, deps);
+                }
+            }
+            LogicStmt::For(stmt) => {
+                let control = with_reads(control, [&stmt.cond]);
+                stmt_dependencies(std::slice::from_ref(&*stmt.init), &control, deps);
+                stmt_dependencies(std::slice::from_ref(&*stmt.step), &control, deps);
+                stmt_dependencies(&stmt.body, &control, deps);
+            }
+            LogicStmt::Loop(
+                LoopStmt::While { cond, body }
+                | LoopStmt::DoWhile { body, cond }
+                | LoopStmt::Repeat { count: cond, body },
+            )
+            | LogicStmt::Wait { cond, body } => {
+                let control = with_reads(control, [cond]);
+                stmt_dependencies(body, &control, deps);
+            }
+            LogicStmt::Loop(LoopStmt::Forever { body })
+            | LogicStmt::Delay { body, .. }
+            | LogicStmt::EventControl { body, .. } => stmt_dependencies(body, control, deps),
+            LogicStmt::ForkJoin { branches, .. } => {
+                for body in branches {
+                    stmt_dependencies(body, control, deps);
+                }
+            }
+            LogicStmt::Display { .. }
+            | LogicStmt::SysTaskCall { .. }
+            | LogicStmt::TaskCall { .. }
+            | LogicStmt::Return(_)
+            | LogicStmt::Break
+            | LogicStmt::Continue => (),
+        }
+    }
+}
+
+/// `names` along with every name read by `exprs`
+fn with_reads<'a>(names: &[String], exprs: impl IntoIterator<Item = &'a Expr>) -> Vec<String> {
+    let mut names = names.to_vec();
+    exprs
+        .into_iter()
+        .for_each(|expr| collect_reads(expr, &mut names));
+    names
+}
+
+/// Strongly connected components of a directed graph, using Tarjan's
+/// algorithm
+fn strongly_connected(graph: &BTreeMap<String, BTreeSet<String>>) -> Vec<Vec<String>> {
+    /// Search state shared across the nodes visited
+    #[derive(Default)]
+    struct Tarjan<'a> {
+        /// Next visit order to hand out
+        next: usize,
+
+        /// Visit order and lowest visit order reachable, by node
+        order: BTreeMap<&'a str, (usize, usize)>,
+
+        /// Visited nodes not yet assigned a component
+        stack: Vec<&'a str>,
+
+        /// Nodes in `stack`
+        on_stack: BTreeSet<&'a str>,
+
+        /// Components found so far
+        components: Vec<Vec<String>>,
+    }
+
+    impl<'a> Tarjan<'a> {
+        /// Visits `node` and every node reachable from it that has not been
+        /// visited yet, adding each component they complete
+        fn visit(&mut self, graph: &'a BTreeMap<String, BTreeSet<String>>, node: &'a str) {
+            let index = self.next;
+            self.next += 1;
+            self.order.insert(node, (index, index));
+            self.stack.push(node);
+            self.on_stack.insert(node);
+
+            for next in graph.get(node).into_iter().flatten() {
+                let low = match self.order.get(next.as_str()) {
+                    None => {
+                        self.visit(graph, next);
+                        self.order[next.as_str()].1
+                    }
+                    Some(&(index, _)) if self.on_stack.contains(next.as_str()) => index,
+                    Some(_) => continue,
+                };
+                let entry = self.order.get_mut(node).expect("node was visited");
+                entry.1 = entry.1.min(low);
+            }
+
+            if self.order[node].0 == self.order[node].1 {
+                let mut component = Vec::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack.remove(member);
+                    component.push(member.to_owned());
+                    if member == node {
+                        break;
+                    }
+                }
+                self.components.push(component);
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan::default();
+    for node in graph.keys() {
+        if !tarjan.order.contains_key(node.as_str()) {
+            tarjan.visit(graph, node);
+        }
+    }
+
+    tarjan.components
+}
@@ -1,9 +1,1690 @@
-use crate::var_types::{self, *};
-use crate::{parse_comment, LexingError, Token};
+use crate::expr::{
+    expr_sv_string, integer_literal, parse_call_args, parse_concat, parse_expr, parse_ident,
+    parse_selects, BinOp, Expr,
+};
+use crate::sim_time::TimeUnit;
+use crate::value::DriveStrength;
+use crate::var_types::{parse_var, parse_width, Var};
+use crate::{expect_token, next_token, peek_token, LexingError, Token};
 use log::{debug, error, trace};
 use logos::Lexer;
 use std::fmt;
 
-pub fn parse_if_statement<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Logic, LexingError> {
+/// Procedural statement
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogicStmt {
+    /// Blocking assignment, `lhs = rhs;`
+    BlockingAssign { lhs: Expr, rhs: Expr },
 
+    /// Non-blocking assignment, `lhs <= rhs;`
+    NonBlockingAssign { lhs: Expr, rhs: Expr },
+
+    /// Assignment with an intra-assignment delay, as in `q = #5 d;` or
+    /// `q <= #5 d;`. The value is taken straight away but only written once
+    /// the delay has passed
+    DelayedAssign {
+        lhs: Expr,
+        rhs: Expr,
+        delay: Delay,
+        blocking: bool,
+    },
+
+    /// Conditional statement, along with any `else if` and `else` branches
+    If(IfStmt),
+
+    /// Case statement, with the `default` item kept apart from the others
+    Case {
+        kind: CaseKind,
+        expr: Expr,
+        items: Vec<CaseItem>,
+        default: Option<Vec<LogicStmt>>,
+    },
+
+    /// For loop
+    For(ForLoop),
+
+    /// While, do-while, repeat or forever loop
+    Loop(LoopStmt),
+
+    /// Branches run in parallel, as in `fork a = 1; b = 2; join`. Each
+    /// branch is a single statement or `begin`/`end` block
+    ForkJoin {
+        branches: Vec<Vec<LogicStmt>>,
+        join: JoinKind,
+    },
+
+    /// Delay before running `body`, as in `#10 a = 1;`, with an empty body
+    /// for `#10;`
+    Delay { delay: Delay, body: Vec<LogicStmt> },
+
+    /// Wait for an event before running `body`, as in `@(posedge clk) a = 1;`,
+    /// with an empty body for `@(posedge clk);`
+    ///
+    /// The events are `None` for `@*`, which waits for a change on anything
+    /// `body` reads
+    EventControl {
+        events: Option<Vec<EventTrigger>>,
+        body: Vec<LogicStmt>,
+    },
+
+    /// Wait until a condition is true before running `body`, as in
+    /// `wait (done) a = 1;`, with an empty body for `wait (done);`. A
+    /// condition that is already true does not wait at all
+    Wait { cond: Expr, body: Vec<LogicStmt> },
+
+    /// Output task call, as in `$display("%d", a);`
+    ///
+    /// `radix` is the format specifier of arguments printed outside of a
+    /// format string: `'d'`, or `'b'`, `'o'` or `'h'` for tasks such as
+    /// `$displayh`
+    Display {
+        task: DisplayTask,
+        radix: char,
+        args: Vec<Expr>,
+    },
+
+    /// Call to any other system task, as in `$finish;`
+    ///
+    /// The name excludes the leading `$`
+    SysTaskCall { name: String, args: Vec<Expr> },
+
+    /// Call to a task defined in the module, as in `send(8'hA5);`
+    TaskCall { name: String, args: Vec<Expr> },
+
+    /// Return from a function or task, as in `return a + b;`, with no value for
+    /// `return;`
+    Return(Option<Expr>),
+
+    /// Leave the innermost loop, `break;`
+    Break,
+
+    /// Start the next iteration of the innermost loop, `continue;`
+    Continue,
+}
+
+/// Way a fork/join block waits for its branches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    /// `join`, waiting for every branch to finish
+    Join,
+
+    /// `join_any`, waiting for the first branch to finish
+    JoinAny,
+
+    /// `join_none`, continuing without waiting
+    JoinNone,
+}
+
+/// Output system task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayTask {
+    /// `$display`, printing its arguments followed by a newline
+    Display,
+
+    /// `$write`, printing its arguments without a newline
+    Write,
+
+    /// `$strobe`, printing its arguments at the end of the time step
+    Strobe,
+
+    /// `$monitor`, printing its arguments at the end of every time step in
+    /// which one of them changed
+    Monitor,
+}
+
+impl From<&DisplayTask> for &str {
+    fn from(val: &DisplayTask) -> Self {
+        match val {
+            DisplayTask::Display => "display",
+            DisplayTask::Write => "write",
+            DisplayTask::Strobe => "strobe",
+            DisplayTask::Monitor => "monitor",
+        }
+    }
+}
+
+/// Conditional statement, as in
+/// `if (a) x = 1; else if (b) x = 2; else x = 3;`
+#[derive(Debug, Clone, PartialEq)]
+pub struct IfStmt {
+    /// Condition of the leading `if`
+    pub cond: Expr,
+
+    /// Statements run when `cond` is true
+    pub then: Vec<LogicStmt>,
+
+    /// `else if` branches, as condition and statements, in source order
+    pub else_if: Vec<(Expr, Vec<LogicStmt>)>,
+
+    /// Statements run when no condition is true
+    pub else_: Option<Vec<LogicStmt>>,
+}
+
+/// For loop, as in `for (int i = 0; i < 8; i++) begin ... end`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForLoop {
+    /// Variable declared by the loop header, as the `int i` of
+    /// `for (int i = 0; ...)`
+    pub var: Option<Box<Var>>,
+
+    /// Assignment run once before the loop, with any loop variable type
+    /// dropped
+    pub init: Box<LogicStmt>,
+
+    /// Condition checked before each iteration
+    pub cond: Expr,
+
+    /// Assignment run after each iteration. Increments such as `i++` and
+    /// `i += 2` are stored as the equivalent blocking assignment
+    pub step: Box<LogicStmt>,
+
+    /// Loop statements
+    pub body: Vec<LogicStmt>,
+}
+
+/// Loop statement other than a for loop
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoopStmt {
+    /// `while (cond) body`, checking `cond` before each iteration
+    While { cond: Expr, body: Vec<LogicStmt> },
+
+    /// `do body while (cond);`, checking `cond` after each iteration
+    DoWhile { body: Vec<LogicStmt>, cond: Expr },
+
+    /// `repeat (count) body`, running `body` a fixed number of times
+    Repeat { count: Expr, body: Vec<LogicStmt> },
+
+    /// `forever body`, running `body` until the simulation ends
+    Forever { body: Vec<LogicStmt> },
+}
+
+/// Procedural delay amount
+#[derive(Debug, Clone, PartialEq)]
+pub enum Delay {
+    /// Delay in units of the timescale, as in `#10` or `#(PERIOD / 2)`
+    Units(Expr),
+
+    /// Fractional delay in units of the timescale, as in `#2.5`
+    RealUnits(f64),
+
+    /// Delay with an explicit time unit, as in `#5ns`, in seconds
+    Time(f64),
+}
+
+/// Case statement kind
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseKind {
+    /// `case`, matching every bit exactly
+    #[default]
+    Case,
+
+    /// `casez`, treating `z` bits as don't-cares
+    Casez,
+
+    /// `casex`, treating `x` and `z` bits as don't-cares
+    Casex,
+}
+
+impl From<&CaseKind> for &str {
+    fn from(val: &CaseKind) -> Self {
+        match val {
+            CaseKind::Case => "case",
+            CaseKind::Casez => "casez",
+            CaseKind::Casex => "casex",
+        }
+    }
+}
+
+/// Case statement item, as in `2'b01, 2'b10: out = a;`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseItem {
+    /// Values matched against the case expression
+    pub labels: Vec<Expr>,
+
+    /// Statements run on a match
+    pub body: Vec<LogicStmt>,
+}
+
+/// Signal edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    /// Rising edge, `posedge`
+    Posedge,
+
+    /// Falling edge, `negedge`
+    Negedge,
+}
+
+/// Edge event in a sensitivity list, as in `posedge clk`
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdgeEvent {
+    /// Edge triggering the event
+    pub edge: Edge,
+
+    /// Signal watched for the edge
+    pub signal: String,
+}
+
+/// Event in an event control or sensitivity list
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventTrigger {
+    /// Edge of a signal, as in `posedge clk`
+    Edge(EdgeEvent),
+
+    /// Any change of a signal, as in `@(a or b)`
+    Change(String),
+}
+
+/// Sequential logic block
+///
+/// Produced by `always_ff`, or by `always` with a sensitivity list made up
+/// only of edge events
+#[derive(Clone, PartialEq)]
+pub struct AlwaysFF {
+    /// Clock edge triggering the block
+    pub clock: EdgeEvent,
+
+    /// Asynchronous reset edge, if the block has one
+    pub reset: Option<EdgeEvent>,
+
+    /// Block statements
+    pub body: Vec<LogicStmt>,
+}
+
+impl fmt::Debug for AlwaysFF {
+    fn fmt(&self, _: &mut std::fmt::Formatter) -> fmt::Result {
+        debug!("ALWAYS_FF: {:?} RESET: {:?}", self.clock, self.reset);
+        for stmt in &self.body {
+            debug!("STMT: {:?}", stmt);
+        }
+        Ok(())
+    }
+}
+
+/// Combinational logic block
+///
+/// Produced by `always_comb`, or by `always` with `@*` or a sensitivity list
+/// that is not made up only of edge events
+#[derive(Clone, PartialEq)]
+pub struct AlwaysComb {
+    /// Block statements
+    pub body: Vec<LogicStmt>,
+}
+
+impl fmt::Debug for AlwaysComb {
+    fn fmt(&self, _: &mut std::fmt::Formatter) -> fmt::Result {
+        debug!("ALWAYS_COMB:");
+        for stmt in &self.body {
+            debug!("STMT: {:?}", stmt);
+        }
+        Ok(())
+    }
+}
+
+/// Parses an `always_comb` block to completion
+pub fn parse_always_comb<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<AlwaysComb, LexingError> {
+    trace!("parsing always_comb");
+
+    let body = parse_statement_block(lexer)?;
+    if has_timing_control(&body) {
+        error!("always_comb blocks may not contain delays, event controls or fork/join");
+        return Err(LexingError::UnexpectedToken);
+    }
+
+    Ok(AlwaysComb { body })
+}
+
+/// Variables declared by the headers of every for loop in `stmts`, however
+/// deeply nested
+pub fn loop_vars(stmts: &[LogicStmt]) -> Vec<&Var> {
+    stmts
+        .iter()
+        .flat_map(|stmt| match stmt {
+            LogicStmt::If(stmt) => {
+                let mut vars = loop_vars(&stmt.then);
+                for (_, body) in &stmt.else_if {
+                    vars.append(&mut loop_vars(body));
+                }
+                vars.append(&mut loop_vars(stmt.else_.as_deref().unwrap_or_default()));
+                vars
+            }
+            LogicStmt::Case { items, default, .. } => {
+                let mut vars: Vec<&Var> = items
+                    .iter()
+                    .flat_map(|item| loop_vars(&item.body))
+                    .collect();
+                vars.append(&mut loop_vars(default.as_deref().unwrap_or_default()));
+                vars
+            }
+            LogicStmt::For(stmt) => stmt
+                .var
+                .as_deref()
+                .into_iter()
+                .chain(loop_vars(&stmt.body))
+                .collect(),
+            LogicStmt::Loop(
+                LoopStmt::While { body, .. }
+                | LoopStmt::DoWhile { body, .. }
+                | LoopStmt::Repeat { body, .. }
+                | LoopStmt::Forever { body },
+            )
+            | LogicStmt::Delay { body, .. }
+            | LogicStmt::EventControl { body, .. }
+            | LogicStmt::Wait { body, .. } => loop_vars(body),
+            LogicStmt::ForkJoin { branches, .. } => {
+                branches.iter().flat_map(|body| loop_vars(body)).collect()
+            }
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// Checks if any of `stmts` waits, through a delay, an event control or a
+/// fork/join, which `always_comb` and `always_latch` blocks may not do and
+/// `always` blocks without a sensitivity list must
+fn has_timing_control(stmts: &[LogicStmt]) -> bool {
+    stmts.iter().any(|stmt| match stmt {
+        LogicStmt::Delay { .. }
+        | LogicStmt::DelayedAssign { .. }
+        | LogicStmt::EventControl { .. }
+        | LogicStmt::Wait { .. }
+        | LogicStmt::ForkJoin { .. } => true,
+        LogicStmt::If(stmt) => {
+            has_timing_control(&stmt.then)
+                || stmt
+                    .else_if
+                    .iter()
+                    .any(|(_, body)| has_timing_control(body))
+                || stmt.else_.as_deref().is_some_and(has_timing_control)
+        }
+        LogicStmt::Case { items, default, .. } => {
+            items.iter().any(|item| has_timing_control(&item.body))
+                || default.as_deref().is_some_and(has_timing_control)
+        }
+        LogicStmt::For(stmt) => has_timing_control(&stmt.body),
+        LogicStmt::Loop(
+            LoopStmt::While { body, .. }
+            | LoopStmt::DoWhile { body, .. }
+            | LoopStmt::Repeat { body, .. }
+            | LoopStmt::Forever { body },
+        ) => has_timing_control(body),
+        _ => false,
+    })
+}
+
+/// Level sensitive latch block, `always_latch`
+///
+/// Signals assigned on only some paths through the body hold their value
+/// on the others, which is what infers the latch
+#[derive(Clone, PartialEq)]
+pub struct AlwaysLatch {
+    /// Block statements
+    pub body: Vec<LogicStmt>,
+}
+
+impl fmt::Debug for AlwaysLatch {
+    fn fmt(&self, _: &mut std::fmt::Formatter) -> fmt::Result {
+        debug!("ALWAYS_LATCH:");
+        for stmt in &self.body {
+            debug!("STMT: {:?}", stmt);
+        }
+        Ok(())
+    }
+}
+
+/// Parses an `always_latch` block to completion
+pub fn parse_always_latch<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<AlwaysLatch, LexingError> {
+    trace!("parsing always_latch");
+
+    let body = parse_statement_block(lexer)?;
+    if has_timing_control(&body) {
+        error!("always_latch blocks may not contain delays, event controls or fork/join");
+        return Err(LexingError::UnexpectedToken);
+    }
+
+    Ok(AlwaysLatch { body })
+}
+
+/// Continuous assignment, as in `assign out = a & b;`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssignStmt {
+    /// Driven net
+    pub lhs: Expr,
+
+    /// Driving expression
+    pub rhs: Expr,
+
+    /// Delay before the net follows a change of the expression, as in
+    /// `assign #5 out = a;`
+    ///
+    /// The net takes the value the expression has once the delay ends, so
+    /// pulses shorter than the delay are filtered out
+    pub delay: Option<Delay>,
+
+    /// Strengths the net is driven with to 0 and to 1, as in
+    /// `assign (weak0, strong1) out = a;`, strong unless given
+    pub strength: (DriveStrength, DriveStrength),
+}
+
+/// Formats a continuous assignment as SystemVerilog, e.g. `assign out = a & b;`
+pub fn assign_sv_string(assign: &AssignStmt) -> String {
+    let strength = match assign.strength {
+        (DriveStrength::Strong, DriveStrength::Strong) => String::new(),
+        (strength0, strength1) => format!(
+            "({}0, {}1) ",
+            strength_sv_string(strength0),
+            strength_sv_string(strength1)
+        ),
+    };
+    let delay = match &assign.delay {
+        Some(delay) => format!("{} ", delay_sv_string(delay)),
+        None => String::new(),
+    };
+
+    format!(
+        "assign {}{}{} = {};",
+        strength,
+        delay,
+        expr_sv_string(&assign.lhs),
+        expr_sv_string(&assign.rhs)
+    )
+}
+
+/// Name of a drive strength without its value, as the `weak` of `weak0`
+fn strength_sv_string(strength: DriveStrength) -> &'static str {
+    match strength {
+        DriveStrength::HighZ => "highz",
+        DriveStrength::Weak => "weak",
+        DriveStrength::Pull => "pull",
+        DriveStrength::Strong => "strong",
+        DriveStrength::Supply => "supply",
+    }
+}
+
+/// Formats a delay as SystemVerilog, e.g. `#(PERIOD / 2)`, `#2.5` or `#5ns`
+///
+/// Times are given in the largest unit they have at least one of
+pub fn delay_sv_string(delay: &Delay) -> String {
+    match delay {
+        Delay::Units(units) => format!("#({})", expr_sv_string(units)),
+        Delay::RealUnits(units) => format!("#{:?}", units),
+        Delay::Time(seconds) => {
+            let unit = [
+                TimeUnit::Seconds,
+                TimeUnit::Milliseconds,
+                TimeUnit::Microseconds,
+                TimeUnit::Nanoseconds,
+                TimeUnit::Picoseconds,
+            ]
+            .into_iter()
+            .find(|unit| *seconds >= unit.multiplier())
+            .unwrap_or(TimeUnit::Femtoseconds);
+
+            // Rounded to drop the error left by dividing by the multiplier
+            let value = (seconds / unit.multiplier() * 1e6).round() / 1e6;
+            format!("#{}{}", value, unit.suffix())
+        }
+    }
+}
+
+/// Parses a continuous assignment to completion
+///
+/// A single statement may hold several comma separated assignments, as in
+/// `assign a = x, b = y;`, and a drive strength and delay applying to each
+/// of them, as in `assign (weak0, weak1) #5 a = x, b = y;`
+pub fn parse_assign<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<Vec<AssignStmt>, LexingError> {
+    let mut assigns: Vec<AssignStmt> = Vec::new();
+
+    trace!("parsing assign");
+
+    let strength = match peek_token(lexer) {
+        Some(Ok(Token::OpenParen)) => {
+            next_token(lexer);
+            parse_drive_strength(lexer)?
+        }
+        _ => (DriveStrength::Strong, DriveStrength::Strong),
+    };
+
+    let delay = match peek_token(lexer) {
+        Some(Ok(Token::Pound)) => {
+            next_token(lexer);
+            Some(parse_delay_amount(lexer)?)
+        }
+        _ => None,
+    };
+
+    loop {
+        let lhs = parse_lvalue(lexer)?;
+        expect_token(lexer, Token::Equals, "assign")?;
+        let rhs = parse_expr(lexer)?;
+        assigns.push(AssignStmt {
+            lhs,
+            rhs,
+            delay: delay.clone(),
+            strength,
+        });
+
+        match next_token(lexer) {
+            Some(Ok(Token::Comma)) => (),
+            Some(Ok(Token::Semicolon)) => return Ok(assigns),
+            _ => {
+                error!("expected ';', got '{}'", lexer.slice());
+                return Err(LexingError::ExpectedSemi);
+            }
+        }
+    }
+}
+
+/// Parses a drive strength to completion, as the `weak0, strong1)` of
+/// `assign (weak0, strong1) out = a;`
+///
+/// The opening parenthesis has already been consumed. The strengths may be
+/// given in either order, and are returned as the strength of 0 then of 1.
+/// Giving both as `highz` is an error
+fn parse_drive_strength<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<(DriveStrength, DriveStrength), LexingError> {
+    let mut strengths = [None, None];
+
+    for separator in [Token::Comma, Token::CloseParen] {
+        expect_token(lexer, Token::Word, "drive strength")?;
+        let word = lexer.slice();
+        let (name, value) = word.split_at(word.len().saturating_sub(1));
+        let strength = match name {
+            "highz" => DriveStrength::HighZ,
+            "weak" => DriveStrength::Weak,
+            "pull" => DriveStrength::Pull,
+            "strong" => DriveStrength::Strong,
+            "supply" => DriveStrength::Supply,
+            _ => {
+                error!("expected a drive strength, got '{}'", word);
+                return Err(LexingError::UnexpectedToken);
+            }
+        };
+        let slot = match value {
+            "0" => &mut strengths[0],
+            "1" => &mut strengths[1],
+            _ => {
+                error!("expected a drive strength, got '{}'", word);
+                return Err(LexingError::UnexpectedToken);
+            }
+        };
+        if slot.replace(strength).is_some() {
+            error!("drive strength for {} is given twice", value);
+            return Err(LexingError::UnexpectedToken);
+        }
+        expect_token(lexer, separator, "drive strength")?;
+    }
+
+    match strengths {
+        [Some(DriveStrength::HighZ), Some(DriveStrength::HighZ)] => {
+            error!("a net cannot be driven with highz0 and highz1");
+            Err(LexingError::UnexpectedToken)
+        }
+        [Some(strength0), Some(strength1)] => Ok((strength0, strength1)),
+        _ => unreachable!("each strength is given once"),
+    }
+}
+
+/// Parses the target of an assignment
+///
+/// Targets are identifiers, optionally with bit or part selects, or
+/// concatenations of targets
+fn parse_lvalue<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Expr, LexingError> {
+    match next_token(lexer) {
+        Some(Ok(Token::Word))
+        | Some(Ok(Token::Underscore))
+        | Some(Ok(Token::EscapedIdentifier(_))) => {
+            let name = Expr::Ident(parse_ident(lexer));
+            parse_selects(lexer, name)
+        }
+        Some(Ok(Token::OpenBrace)) => {
+            let target = parse_concat(lexer)?;
+            if !is_lvalue(&target) {
+                error!("cannot assign to '{}'", expr_sv_string(&target));
+                return Err(LexingError::UnexpectedToken);
+            }
+            Ok(target)
+        }
+        _ => {
+            error!("expected assignment target: '{}'", lexer.slice());
+            Err(LexingError::UnexpectedToken)
+        }
+    }
+}
+
+/// Whether an expression can be assigned to, being a signal, a select of
+/// one, or a concatenation of such targets
+fn is_lvalue(expr: &Expr) -> bool {
+    match expr {
+        Expr::Ident(_) => true,
+        Expr::BitSelect(base, _)
+        | Expr::PartSelect(base, _, _)
+        | Expr::PartSelectUp(base, _, _)
+        | Expr::PartSelectDown(base, _, _) => is_lvalue(base),
+        Expr::Concat(parts) => parts.iter().all(is_lvalue),
+        _ => false,
+    }
+}
+
+/// Initial block, run once at the start of simulation
+#[derive(Clone, PartialEq)]
+pub struct InitialBlock {
+    /// Block statements
+    pub body: Vec<LogicStmt>,
+
+    /// Test name given by a `// @testbench` annotation in the block's first
+    /// comment, which is empty if the annotation names no test, or `None`
+    /// if the block is not annotated
+    pub testbench: Option<String>,
+}
+
+impl fmt::Debug for InitialBlock {
+    fn fmt(&self, _: &mut std::fmt::Formatter) -> fmt::Result {
+        debug!("INITIAL:");
+        for stmt in &self.body {
+            debug!("STMT: {:?}", stmt);
+        }
+        Ok(())
+    }
+}
+
+/// Parses an `initial` block to completion
+pub fn parse_initial<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<InitialBlock, LexingError> {
+    trace!("parsing initial");
+
+    let testbench = testbench_annotation(lexer);
+    Ok(InitialBlock {
+        body: parse_statement_block(lexer)?,
+        testbench,
+    })
+}
+
+/// Final block, run once when the simulation ends
+#[derive(Clone, PartialEq)]
+pub struct FinalBlock {
+    /// Block statements
+    pub body: Vec<LogicStmt>,
+}
+
+impl fmt::Debug for FinalBlock {
+    fn fmt(&self, _: &mut std::fmt::Formatter) -> fmt::Result {
+        debug!("FINAL:");
+        for stmt in &self.body {
+            debug!("STMT: {:?}", stmt);
+        }
+        Ok(())
+    }
+}
+
+/// Parses a `final` block to completion
+pub fn parse_final<'source>(lexer: &mut Lexer<'source, Token>) -> Result<FinalBlock, LexingError> {
+    trace!("parsing final");
+
+    let body = parse_statement_block(lexer)?;
+    if has_timing_control(&body) {
+        error!("final blocks may not contain delays, event controls or fork/join");
+        return Err(LexingError::UnexpectedToken);
+    }
+
+    Ok(FinalBlock { body })
+}
+
+/// Annotation marking an `initial` block as a test case
+const TESTBENCH_ANNOTATION: &str = "@testbench";
+
+/// Finds a `@testbench` annotation in the first comment of the block ahead
+/// of the lexer, returning the test name following it
+///
+/// The comment may come before the block's `begin`, or after the `begin`
+/// and any block label, as in `initial begin : reset // @testbench reset`
+fn testbench_annotation(lexer: &Lexer<Token>) -> Option<String> {
+    let mut ahead = lexer.clone();
+    let mut in_block = false;
+
+    while let Some(token) = ahead.next() {
+        let end = match token {
+            Ok(Token::WhiteSpace) | Ok(Token::Newline) => continue,
+            Ok(Token::Begin) if !in_block => {
+                in_block = true;
+                continue;
+            }
+            Ok(Token::Colon) | Ok(Token::Word) | Ok(Token::Underscore) if in_block => continue,
+            Ok(Token::LineComment) => "\n",
+            Ok(Token::BlockCommentStart) => "*/",
+            _ => return None,
+        };
+
+        let remainder = ahead.remainder();
+        let comment = &remainder[..remainder.find(end).unwrap_or(remainder.len())];
+        let (_, name) = comment.split_once(TESTBENCH_ANNOTATION)?;
+        return Some(name.split_whitespace().next().unwrap_or_default().to_owned());
+    }
+
+    None
+}
+
+/// Parses an `always_ff` block to completion
+///
+/// The first edge of the sensitivity list is taken as the clock and a second
+/// edge, if present, as the asynchronous reset
+pub fn parse_always_ff<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<AlwaysFF, LexingError> {
+    trace!("parsing always_ff");
+
+    match parse_sensitivity_list(lexer)? {
+        Some(events) => build_always_ff(lexer, events),
+        None => {
+            error!("always_ff sensitivity list may only contain edge events");
+            Err(LexingError::UnexpectedToken)
+        }
+    }
+}
+
+/// Parses an `always` block to completion
+///
+/// Blocks triggered only by edge events are sequential and pushed to
+/// `ff_blocks`, all other blocks are treated as combinational and pushed to
+/// `comb_blocks`. Incomplete sensitivity lists are not checked, so such a
+/// block is modeled as if it listed every signal it reads
+///
+/// A block without a sensitivity list, as in `always #5 clk = ~clk;`, runs
+/// its body over and over and is pushed to `initial_blocks` as a `forever`
+/// loop. Its body must wait somewhere, or it would never let time advance
+pub fn parse_always<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    ff_blocks: &mut Vec<AlwaysFF>,
+    comb_blocks: &mut Vec<AlwaysComb>,
+    initial_blocks: &mut Vec<InitialBlock>,
+) -> Result<(), LexingError> {
+    trace!("parsing always");
+
+    if peek_token(lexer) != Some(Ok(Token::At)) {
+        let body = parse_statement_block(lexer)?;
+        if !has_timing_control(&body) {
+            error!(
+                "always blocks without a sensitivity list must contain a delay or event control"
+            );
+            return Err(LexingError::UnexpectedToken);
+        }
+
+        initial_blocks.push(InitialBlock {
+            body: vec![LogicStmt::Loop(LoopStmt::Forever { body })],
+            testbench: None,
+        });
+        return Ok(());
+    }
+
+    match parse_sensitivity_list(lexer)? {
+        Some(events) => ff_blocks.push(build_always_ff(lexer, events)?),
+        None => {
+            comb_blocks.push(AlwaysComb {
+                body: parse_statement_block(lexer)?,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses the body of a sequential block triggered by `events`
+fn build_always_ff<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    events: Vec<EdgeEvent>,
+) -> Result<AlwaysFF, LexingError> {
+    let mut events = events.into_iter();
+    let clock = events.next().ok_or(LexingError::UnexpectedToken)?;
+    let reset = events.next();
+
+    if events.next().is_some() {
+        error!("sequential blocks may have at most a clock and a reset edge");
+        return Err(LexingError::UnexpectedToken);
+    }
+
+    Ok(AlwaysFF {
+        clock,
+        reset,
+        body: parse_statement_block(lexer)?,
+    })
+}
+
+/// Parses a sensitivity list such as `@(posedge clk or negedge rst_n)`
+///
+/// Returns `None` if any entry is not an edge event, including for `@*` and
+/// `@(*)`
+fn parse_sensitivity_list<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<Option<Vec<EdgeEvent>>, LexingError> {
+    trace!("parsing sensitivity list");
+
+    expect_token(lexer, Token::At, "sensitivity list")?;
+
+    let mut edges: Vec<EdgeEvent> = Vec::new();
+    for event in parse_event_list(lexer)?.unwrap_or_default() {
+        match event {
+            EventTrigger::Edge(edge) => edges.push(edge),
+            EventTrigger::Change(_) => return Ok(None),
+        }
+    }
+
+    Ok((!edges.is_empty()).then_some(edges))
+}
+
+/// Parses the events following `@`, as in `(posedge clk or negedge rst_n)`
+/// or `clk`
+///
+/// Entries may be separated by `or` or `,`. Returns `None` for `*` and `(*)`
+fn parse_event_list<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<Option<Vec<EventTrigger>>, LexingError> {
+    let mut events: Vec<EventTrigger> = Vec::new();
+
+    match next_token(lexer) {
+        Some(Ok(Token::Multiply)) => return Ok(None),
+        Some(Ok(Token::Word)) | Some(Ok(Token::EscapedIdentifier(_))) => {
+            return Ok(Some(vec![EventTrigger::Change(parse_ident(lexer))]));
+        }
+        Some(Ok(Token::OpenParen)) => (),
+        _ => {
+            error!("expected '(' in event list: '{}'", lexer.slice());
+            return Err(LexingError::UnexpectedToken);
+        }
+    }
+
+    if peek_token(lexer) == Some(Ok(Token::Multiply)) {
+        next_token(lexer);
+        expect_token(lexer, Token::CloseParen, "event list")?;
+        return Ok(None);
+    }
+
+    loop {
+        let edge = match next_token(lexer) {
+            Some(Ok(Token::Posedge)) => Some(Edge::Posedge),
+            Some(Ok(Token::Negedge)) => Some(Edge::Negedge),
+            Some(Ok(Token::Word)) | Some(Ok(Token::EscapedIdentifier(_))) => {
+                events.push(EventTrigger::Change(parse_ident(lexer)));
+                None
+            }
+            Some(Err(e)) => {
+                error!(
+                    "unexpected error occurred parsing event list: '{}'",
+                    lexer.slice()
+                );
+                return Err(e);
+            }
+            _ => {
+                error!("unexpected token parsing event list: '{}'", lexer.slice());
+                return Err(LexingError::UnexpectedToken);
+            }
+        };
+
+        if let Some(edge) = edge {
+            match next_token(lexer) {
+                Some(Ok(Token::Word)) | Some(Ok(Token::EscapedIdentifier(_))) => {
+                    let signal = parse_ident(lexer);
+                    events.push(EventTrigger::Edge(EdgeEvent { edge, signal }));
+                }
+                _ => {
+                    error!("expected signal after edge: '{}'", lexer.slice());
+                    return Err(LexingError::UnexpectedToken);
+                }
+            }
+        }
+
+        match next_token(lexer) {
+            Some(Ok(Token::Comma)) | Some(Ok(Token::Or)) => (),
+            Some(Ok(Token::CloseParen)) => return Ok(Some(events)),
+            _ => {
+                error!("unexpected token parsing event list: '{}'", lexer.slice());
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    }
+}
+
+/// Parses a `begin`/`end` block or a single statement
+///
+/// Block labels, as in `begin : name`, are accepted and discarded. Nested
+/// blocks run in order, so their statements are flattened into the
+/// enclosing block
+pub fn parse_statement_block<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<Vec<LogicStmt>, LexingError> {
+    let mut stmts: Vec<LogicStmt> = Vec::new();
+
+    if peek_token(lexer) != Some(Ok(Token::Begin)) {
+        return Ok(vec![parse_statement(lexer)?]);
+    }
+
+    trace!("parsing begin/end block");
+
+    next_token(lexer);
+    parse_block_label(lexer)?;
+
+    loop {
+        match peek_token(lexer) {
+            Some(Ok(Token::End)) => {
+                next_token(lexer);
+                parse_block_label(lexer)?;
+                return Ok(stmts);
+            }
+            Some(Ok(Token::Begin)) => stmts.append(&mut parse_statement_block(lexer)?),
+            Some(_) => stmts.push(parse_statement(lexer)?),
+            None => {
+                error!("begin/end block not closed before end of file");
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    }
+}
+
+/// Consumes an optional `: name` label following `begin` or `end`
+fn parse_block_label<'source>(lexer: &mut Lexer<'source, Token>) -> Result<(), LexingError> {
+    if peek_token(lexer) == Some(Ok(Token::Colon)) {
+        next_token(lexer);
+        match next_token(lexer) {
+            Some(Ok(Token::Word)) | Some(Ok(Token::EscapedIdentifier(_))) => {
+                parse_ident(lexer);
+            }
+            _ => {
+                error!("expected block label: '{}'", lexer.slice());
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a single procedural statement to completion
+///
+/// An if or case statement may start with a `unique`, `unique0` or
+/// `priority` qualifier. The qualifier is accepted, but the checks it asks
+/// for are not made, so the statement runs as if it had none
+pub fn parse_statement<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<LogicStmt, LexingError> {
+    trace!("parsing statement");
+
+    if let Some(Ok(Token::Word | Token::EscapedIdentifier(_))) = peek_token(lexer) {
+        let mut ahead = lexer.clone();
+        next_token(&mut ahead);
+        let name = parse_ident(&mut ahead);
+        if let Some(Ok(Token::OpenParen | Token::Semicolon)) = peek_token(&ahead) {
+            *lexer = ahead;
+            return parse_task_call(lexer, name);
+        }
+    }
+
+    if let Some(Ok(Token::Word))
+    | Some(Ok(Token::Underscore))
+    | Some(Ok(Token::EscapedIdentifier(_)))
+    | Some(Ok(Token::OpenBrace)) = peek_token(lexer)
+    {
+        return parse_assignment(lexer);
+    }
+
+    match next_token(lexer) {
+        Some(Ok(Token::If)) => parse_if_statement(lexer),
+        Some(Ok(Token::Unique | Token::Priority)) => match next_token(lexer) {
+            Some(Ok(Token::If)) => parse_if_statement(lexer),
+            Some(Ok(Token::Case)) => parse_case_statement(lexer, CaseKind::Case),
+            Some(Ok(Token::Casez)) => parse_case_statement(lexer, CaseKind::Casez),
+            Some(Ok(Token::Casex)) => parse_case_statement(lexer, CaseKind::Casex),
+            _ => {
+                error!(
+                    "expected 'if' or 'case' after qualifier, got '{}'",
+                    lexer.slice()
+                );
+                Err(LexingError::UnexpectedToken)
+            }
+        },
+        Some(Ok(Token::Case)) => parse_case_statement(lexer, CaseKind::Case),
+        Some(Ok(Token::Casez)) => parse_case_statement(lexer, CaseKind::Casez),
+        Some(Ok(Token::Casex)) => parse_case_statement(lexer, CaseKind::Casex),
+        Some(Ok(Token::For)) => parse_for_loop(lexer),
+        Some(Ok(Token::While)) => parse_while_loop(lexer),
+        Some(Ok(Token::Do)) => parse_do_while_loop(lexer),
+        Some(Ok(Token::Repeat)) => parse_repeat_loop(lexer),
+        Some(Ok(Token::Forever)) => parse_forever_loop(lexer),
+        Some(Ok(Token::Fork)) => parse_fork_join(lexer),
+        Some(Ok(Token::Pound)) => parse_delay(lexer),
+        Some(Ok(Token::At)) => parse_event_control(lexer),
+        Some(Ok(Token::Wait)) => parse_wait(lexer),
+        Some(Ok(Token::SystemIdent(name))) => parse_sys_task_call(lexer, name),
+        Some(Ok(Token::Return)) => parse_return(lexer),
+        Some(Ok(Token::Break)) => {
+            expect_token(lexer, Token::Semicolon, "break statement")?;
+            Ok(LogicStmt::Break)
+        }
+        Some(Ok(Token::Continue)) => {
+            expect_token(lexer, Token::Semicolon, "continue statement")?;
+            Ok(LogicStmt::Continue)
+        }
+        Some(Err(e)) => {
+            error!(
+                "unexpected error occurred parsing statement: '{}'",
+                lexer.slice()
+            );
+            Err(e)
+        }
+        _ => {
+            error!("unexpected token parsing statement: '{}'", lexer.slice());
+            Err(LexingError::UnexpectedToken)
+        }
+    }
+}
+
+/// Parses a call to the task `name` to completion
+///
+/// The task name has already been consumed. As for system tasks, the
+/// arguments are optional
+fn parse_task_call<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    name: String,
+) -> Result<LogicStmt, LexingError> {
+    trace!("parsing task call {}", name);
+
+    let args = match peek_token(lexer) {
+        Some(Ok(Token::OpenParen)) => parse_call_args(lexer, &name)?,
+        _ => Vec::new(),
+    };
+    expect_token(lexer, Token::Semicolon, "task call")?;
+
+    Ok(LogicStmt::TaskCall { name, args })
+}
+
+/// Parses a return statement to completion
+///
+/// The `return` keyword has already been consumed
+fn parse_return<'source>(lexer: &mut Lexer<'source, Token>) -> Result<LogicStmt, LexingError> {
+    trace!("parsing return statement");
+
+    if peek_token(lexer) == Some(Ok(Token::Semicolon)) {
+        next_token(lexer);
+        return Ok(LogicStmt::Return(None));
+    }
+
+    let value = parse_expr(lexer)?;
+    expect_token(lexer, Token::Semicolon, "return statement")?;
+
+    Ok(LogicStmt::Return(Some(value)))
+}
+
+/// Parses a delay and the statement it applies to
+///
+/// The `#` has already been consumed
+fn parse_delay<'source>(lexer: &mut Lexer<'source, Token>) -> Result<LogicStmt, LexingError> {
+    trace!("parsing delay");
+
+    let delay = parse_delay_amount(lexer)?;
+    let body = if peek_token(lexer) == Some(Ok(Token::Semicolon)) {
+        next_token(lexer);
+        Vec::new()
+    } else {
+        parse_statement_block(lexer)?
+    };
+
+    Ok(LogicStmt::Delay { delay, body })
+}
+
+/// Parses the amount of a delay, as the `10` of `#10`
+///
+/// The `#` has already been consumed
+fn parse_delay_amount<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Delay, LexingError> {
+    let delay = match peek_token(lexer) {
+        Some(Ok(Token::Time(val))) => {
+            next_token(lexer);
+            Delay::Time(val)
+        }
+        Some(Ok(Token::Real(units))) => {
+            next_token(lexer);
+            Delay::RealUnits(units)
+        }
+        Some(Ok(Token::Integer(_)))
+        | Some(Ok(Token::Word))
+        | Some(Ok(Token::EscapedIdentifier(_)))
+        | Some(Ok(Token::OpenParen)) => Delay::Units(parse_delay_value(lexer)?),
+        _ => {
+            next_token(lexer);
+            error!("expected delay value after '#': '{}'", lexer.slice());
+            return Err(LexingError::ImproperTimeFormatting);
+        }
+    };
+
+    Ok(delay)
+}
+
+/// Parses an event control and the statement it applies to
+///
+/// The `@` has already been consumed
+fn parse_event_control<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<LogicStmt, LexingError> {
+    trace!("parsing event control");
+
+    let events = parse_event_list(lexer)?;
+    let body = if peek_token(lexer) == Some(Ok(Token::Semicolon)) {
+        next_token(lexer);
+        Vec::new()
+    } else {
+        parse_statement_block(lexer)?
+    };
+
+    Ok(LogicStmt::EventControl { events, body })
+}
+
+/// Parses a wait statement and the statement it applies to
+///
+/// The `wait` keyword has already been consumed
+fn parse_wait<'source>(lexer: &mut Lexer<'source, Token>) -> Result<LogicStmt, LexingError> {
+    trace!("parsing wait statement");
+
+    let cond = parse_loop_header(lexer, "wait statement")?;
+    let body = if peek_token(lexer) == Some(Ok(Token::Semicolon)) {
+        next_token(lexer);
+        Vec::new()
+    } else {
+        parse_statement_block(lexer)?
+    };
+
+    Ok(LogicStmt::Wait { cond, body })
+}
+
+/// Parses a fork/join block to completion
+///
+/// The `fork` keyword has already been consumed. A label, as in
+/// `fork : name`, is accepted and discarded
+fn parse_fork_join<'source>(lexer: &mut Lexer<'source, Token>) -> Result<LogicStmt, LexingError> {
+    let mut branches: Vec<Vec<LogicStmt>> = Vec::new();
+
+    trace!("parsing fork/join block");
+
+    parse_block_label(lexer)?;
+
+    let join = loop {
+        match peek_token(lexer) {
+            Some(Ok(Token::Join)) => break JoinKind::Join,
+            Some(Ok(Token::JoinAny)) => break JoinKind::JoinAny,
+            Some(Ok(Token::JoinNone)) => break JoinKind::JoinNone,
+            Some(_) => branches.push(parse_statement_block(lexer)?),
+            None => {
+                error!("fork/join block not closed before end of file");
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    };
+    next_token(lexer);
+    parse_block_label(lexer)?;
+
+    Ok(LogicStmt::ForkJoin { branches, join })
+}
+
+/// Parses a delay value without letting it run on into the delayed
+/// statement, so `#10 -a` is not read as `#(10 - a)`
+fn parse_delay_value<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Expr, LexingError> {
+    if peek_token(lexer) == Some(Ok(Token::OpenParen)) {
+        next_token(lexer);
+        let expr = parse_expr(lexer)?;
+        expect_token(lexer, Token::CloseParen, "delay")?;
+        return Ok(expr);
+    }
+
+    match next_token(lexer) {
+        Some(Ok(Token::Integer(val))) => Ok(Expr::Literal(integer_literal(val), true)),
+        _ => Ok(Expr::Ident(parse_ident(lexer))),
+    }
+}
+
+/// Parses a system task call to completion
+///
+/// The task name has already been consumed. Arguments are optional, so both
+/// `$finish;` and `$finish(0);` are accepted. Output tasks such as `$display`
+/// produce `LogicStmt::Display`
+fn parse_sys_task_call<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    name: String,
+) -> Result<LogicStmt, LexingError> {
+    let mut args: Vec<Expr> = Vec::new();
+
+    trace!("parsing system task call ${}", name);
+
+    if peek_token(lexer) == Some(Ok(Token::OpenParen)) {
+        next_token(lexer);
+        if peek_token(lexer) == Some(Ok(Token::CloseParen)) {
+            next_token(lexer);
+        } else {
+            loop {
+                args.push(parse_expr(lexer)?);
+                match next_token(lexer) {
+                    Some(Ok(Token::Comma)) => (),
+                    Some(Ok(Token::CloseParen)) => break,
+                    _ => {
+                        error!(
+                            "expected ',' or ')' in arguments to ${}: '{}'",
+                            name,
+                            lexer.slice()
+                        );
+                        return Err(LexingError::UnexpectedToken);
+                    }
+                }
+            }
+        }
+    }
+
+    expect_token(lexer, Token::Semicolon, "system task call")?;
+
+    let (task, radix) = match name.strip_suffix(['b', 'o', 'h']) {
+        Some(task) => (task, name.chars().last().unwrap_or('d')),
+        None => (name.as_str(), 'd'),
+    };
+    let task = match task {
+        "display" => DisplayTask::Display,
+        "write" => DisplayTask::Write,
+        "strobe" => DisplayTask::Strobe,
+        "monitor" => DisplayTask::Monitor,
+        _ => return Ok(LogicStmt::SysTaskCall { name, args }),
+    };
+
+    Ok(LogicStmt::Display { task, radix, args })
+}
+
+/// Parses a blocking or non-blocking assignment to completion
+fn parse_assignment<'source>(lexer: &mut Lexer<'source, Token>) -> Result<LogicStmt, LexingError> {
+    let stmt = parse_operator_assignment(lexer)?;
+    expect_token(lexer, Token::Semicolon, "assignment")?;
+
+    Ok(stmt)
+}
+
+/// Parses a blocking or non-blocking assignment, leaving any closing `;`
+/// for the caller
+///
+/// Either may have an intra-assignment delay, as in `q <= #1 d`
+pub(crate) fn parse_assignment_body<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<LogicStmt, LexingError> {
+    let lhs = parse_lvalue(lexer)?;
+
+    trace!("parsing assignment");
+
+    let blocking = match next_token(lexer) {
+        Some(Ok(Token::Equals)) => true,
+        Some(Ok(Token::BLTE)) => false,
+        _ => {
+            error!("expected '=' or '<=' in assignment: '{}'", lexer.slice());
+            return Err(LexingError::UnexpectedToken);
+        }
+    };
+
+    if peek_token(lexer) == Some(Ok(Token::Pound)) {
+        next_token(lexer);
+        let delay = parse_delay_amount(lexer)?;
+        let rhs = parse_expr(lexer)?;
+
+        return Ok(LogicStmt::DelayedAssign {
+            lhs,
+            rhs,
+            delay,
+            blocking,
+        });
+    }
+
+    let rhs = parse_expr(lexer)?;
+
+    if blocking {
+        Ok(LogicStmt::BlockingAssign { lhs, rhs })
+    } else {
+        Ok(LogicStmt::NonBlockingAssign { lhs, rhs })
+    }
+}
+
+/// Parses a for loop to completion
+///
+/// The `for` keyword has already been consumed. The loop variable may be
+/// declared in the header, as in `for (int i = 0; ...)`
+pub fn parse_for_loop<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<LogicStmt, LexingError> {
+    trace!("parsing for loop");
+
+    expect_token(lexer, Token::OpenParen, "for loop")?;
+    let var = parse_loop_var(lexer)?;
+    let init = parse_assignment_body(lexer)?;
+    expect_token(lexer, Token::Semicolon, "for loop")?;
+    let cond = parse_expr(lexer)?;
+    expect_token(lexer, Token::Semicolon, "for loop")?;
+    let step = parse_operator_assignment(lexer)?;
+    expect_token(lexer, Token::CloseParen, "for loop")?;
+
+    Ok(LogicStmt::For(ForLoop {
+        var,
+        init: Box::new(init),
+        cond,
+        step: Box::new(step),
+        body: parse_statement_block(lexer)?,
+    }))
+}
+
+/// Parses a while loop to completion
+///
+/// The `while` keyword has already been consumed
+pub fn parse_while_loop<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<LogicStmt, LexingError> {
+    trace!("parsing while loop");
+
+    let cond = parse_loop_header(lexer, "while loop")?;
+
+    Ok(LogicStmt::Loop(LoopStmt::While {
+        cond,
+        body: parse_statement_block(lexer)?,
+    }))
+}
+
+/// Parses a do-while loop to completion
+///
+/// The `do` keyword has already been consumed
+pub fn parse_do_while_loop<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<LogicStmt, LexingError> {
+    trace!("parsing do-while loop");
+
+    let body = parse_statement_block(lexer)?;
+    expect_token(lexer, Token::While, "do-while loop")?;
+    let cond = parse_loop_header(lexer, "do-while loop")?;
+    expect_token(lexer, Token::Semicolon, "do-while loop")?;
+
+    Ok(LogicStmt::Loop(LoopStmt::DoWhile { body, cond }))
+}
+
+/// Parses a repeat loop to completion
+///
+/// The `repeat` keyword has already been consumed. The repeat count should
+/// be a constant expression
+pub fn parse_repeat_loop<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<LogicStmt, LexingError> {
+    trace!("parsing repeat loop");
+
+    let count = parse_loop_header(lexer, "repeat loop")?;
+
+    Ok(LogicStmt::Loop(LoopStmt::Repeat {
+        count,
+        body: parse_statement_block(lexer)?,
+    }))
+}
+
+/// Parses a forever loop to completion
+///
+/// The `forever` keyword has already been consumed
+pub fn parse_forever_loop<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<LogicStmt, LexingError> {
+    trace!("parsing forever loop");
+
+    Ok(LogicStmt::Loop(LoopStmt::Forever {
+        body: parse_statement_block(lexer)?,
+    }))
+}
+
+/// Parses the parenthesized expression after a loop or `wait` keyword
+fn parse_loop_header<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    context: &str,
+) -> Result<Expr, LexingError> {
+    expect_token(lexer, Token::OpenParen, context)?;
+    let expr = parse_expr(lexer)?;
+    expect_token(lexer, Token::CloseParen, context)?;
+
+    Ok(expr)
+}
+
+/// Parses the data type of a loop variable declared in a for loop header,
+/// such as the `int` of `int i = 0`, leaving the lexer at the variable name
+///
+/// Returns the declared variable, or `None` if the header assigns an
+/// existing variable or declares one of a user-defined type, whose width is
+/// not known here
+fn parse_loop_var<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<Option<Box<Var>>, LexingError> {
+    let mut ahead = lexer.clone();
+    skip_loop_var_type(&mut ahead);
+    if ahead.span() == lexer.span() || peek_token(lexer) == Some(Ok(Token::Word)) {
+        *lexer = ahead;
+        return Ok(None);
+    }
+
+    let var = parse_var(&mut lexer.clone())?;
+    *lexer = ahead;
+
+    Ok(Some(Box::new(var)))
+}
+
+/// Skips the data type of a loop variable declared in a for loop header,
+/// such as the `int` of `int i = 0` or the `logic [3:0]` of
+/// `logic [3:0] i = 0`
+pub(crate) fn skip_loop_var_type<'source>(lexer: &mut Lexer<'source, Token>) {
+    let mut typed = false;
+    loop {
+        let mut ahead = lexer.clone();
+        match next_token(&mut ahead) {
+            Some(Ok(Token::OpenBracket)) if typed => {
+                if parse_width(&mut ahead).is_err() {
+                    return;
+                }
+            }
+            Some(Ok(Token::Reg))
+            | Some(Ok(Token::Logic))
+            | Some(Ok(Token::Byte))
+            | Some(Ok(Token::ShortInt))
+            | Some(Ok(Token::Int))
+            | Some(Ok(Token::LongInt))
+            | Some(Ok(Token::IntegerType))
+            | Some(Ok(Token::Genvar))
+            | Some(Ok(Token::Signed))
+            | Some(Ok(Token::Unsigned)) => (),
+            Some(Ok(Token::Word)) => {
+                parse_ident(&mut ahead);
+                let mut name = ahead.clone();
+                if !matches!(
+                    next_token(&mut name),
+                    Some(Ok(Token::Word))
+                        | Some(Ok(Token::Underscore))
+                        | Some(Ok(Token::EscapedIdentifier(_)))
+                ) {
+                    return;
+                }
+            }
+            _ => return,
+        }
+        *lexer = ahead;
+        typed = true;
+    }
+}
+
+/// Parses an assignment that may use an assignment operator, leaving any
+/// closing `;` for the caller
+///
+/// Besides plain assignments, the increments `i++` and `i--` and assignment
+/// operators such as `i += n` or `mask <<= 1` are accepted and stored as the
+/// equivalent blocking assignment
+pub(crate) fn parse_operator_assignment<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<LogicStmt, LexingError> {
+    let mut ahead = lexer.clone();
+    let lhs = parse_lvalue(&mut ahead)?;
+
+    let op = match next_token(&mut ahead) {
+        Some(Ok(token)) => match assignment_op(&token) {
+            Some(op) => op,
+            None => return parse_assignment_body(lexer),
+        },
+        _ => return parse_assignment_body(lexer),
+    };
+    *lexer = ahead;
+
+    let rhs = match next_token(lexer) {
+        Some(Ok(Token::Add)) if op == BinOp::Add => Expr::Literal(integer_literal(1), true),
+        Some(Ok(Token::Subtract)) if op == BinOp::Sub => Expr::Literal(integer_literal(1), true),
+        Some(Ok(Token::Equals)) => parse_expr(lexer)?,
+        _ => {
+            error!(
+                "expected '=' after assignment operator, got '{}'",
+                lexer.slice()
+            );
+            return Err(LexingError::UnexpectedToken);
+        }
+    };
+
+    Ok(LogicStmt::BlockingAssign {
+        rhs: Expr::BinOp(op, Box::new(lhs.clone()), Box::new(rhs)),
+        lhs,
+    })
+}
+
+/// Maps a token to the operator of the assignment operator it starts, as
+/// `+` does for `+=`
+fn assignment_op(token: &Token) -> Option<BinOp> {
+    match token {
+        Token::Add => Some(BinOp::Add),
+        Token::Subtract => Some(BinOp::Sub),
+        Token::Multiply => Some(BinOp::Mul),
+        Token::Divide => Some(BinOp::Div),
+        Token::Modulo => Some(BinOp::Mod),
+        Token::BitAnd => Some(BinOp::BitAnd),
+        Token::BitOr => Some(BinOp::BitOr),
+        Token::BitXor => Some(BinOp::BitXor),
+        Token::SHL => Some(BinOp::Shl),
+        Token::SHR => Some(BinOp::Shr),
+        Token::ASHL => Some(BinOp::AShl),
+        Token::ASHR => Some(BinOp::AShr),
+        _ => None,
+    }
+}
+
+/// Parses an if statement to completion
+///
+/// The `if` keyword has already been consumed. Any `else if` branches that
+/// follow are collected in order, up to an optional final `else`
+pub fn parse_if_statement<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<LogicStmt, LexingError> {
+    trace!("parsing if statement");
+
+    let (cond, then) = parse_if_branch(lexer)?;
+    let mut else_if: Vec<(Expr, Vec<LogicStmt>)> = Vec::new();
+    let mut else_: Option<Vec<LogicStmt>> = None;
+
+    while peek_token(lexer) == Some(Ok(Token::Else)) {
+        next_token(lexer);
+
+        if peek_token(lexer) == Some(Ok(Token::If)) {
+            next_token(lexer);
+            else_if.push(parse_if_branch(lexer)?);
+        } else {
+            else_ = Some(parse_statement_block(lexer)?);
+            break;
+        }
+    }
+
+    Ok(LogicStmt::If(IfStmt {
+        cond,
+        then,
+        else_if,
+        else_,
+    }))
+}
+
+/// Parses the parenthesized condition and statements of an `if` or
+/// `else if` branch
+fn parse_if_branch<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<(Expr, Vec<LogicStmt>), LexingError> {
+    expect_token(lexer, Token::OpenParen, "if statement")?;
+    let cond = parse_expr(lexer)?;
+    expect_token(lexer, Token::CloseParen, "if statement")?;
+
+    Ok((cond, parse_statement_block(lexer)?))
+}
+
+/// Parses a case statement to completion
+///
+/// The `case`, `casez` or `casex` keyword has already been consumed, with
+/// `kind` telling which. Each item lists one or more comma separated labels
+/// before its statement, and at most one `default` item may appear, with or
+/// without a colon
+pub fn parse_case_statement<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    kind: CaseKind,
+) -> Result<LogicStmt, LexingError> {
+    let mut items: Vec<CaseItem> = Vec::new();
+    let mut default: Option<Vec<LogicStmt>> = None;
+
+    trace!("parsing {} statement", <&str>::from(&kind));
+
+    expect_token(lexer, Token::OpenParen, "case statement")?;
+    let expr = parse_expr(lexer)?;
+    expect_token(lexer, Token::CloseParen, "case statement")?;
+
+    loop {
+        match peek_token(lexer) {
+            Some(Ok(Token::EndCase)) => {
+                next_token(lexer);
+                break;
+            }
+            Some(Ok(Token::Default)) => {
+                next_token(lexer);
+                if peek_token(lexer) == Some(Ok(Token::Colon)) {
+                    next_token(lexer);
+                }
+                if default.is_some() {
+                    error!("case statement has more than one default item");
+                    return Err(LexingError::UnexpectedToken);
+                }
+                default = Some(parse_statement_block(lexer)?);
+            }
+            Some(Ok(_)) => {
+                let mut labels = vec![parse_expr(lexer)?];
+                while peek_token(lexer) == Some(Ok(Token::Comma)) {
+                    next_token(lexer);
+                    labels.push(parse_expr(lexer)?);
+                }
+                expect_token(lexer, Token::Colon, "case item")?;
+                items.push(CaseItem {
+                    labels,
+                    body: parse_statement_block(lexer)?,
+                });
+            }
+            Some(Err(e)) => {
+                next_token(lexer);
+                error!(
+                    "unexpected error occurred parsing case statement: '{}'",
+                    lexer.slice()
+                );
+                return Err(e);
+            }
+            None => {
+                error!("case statement not closed before end of file");
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    }
+
+    Ok(LogicStmt::Case {
+        kind,
+        expr,
+        items,
+        default,
+    })
 }
@@ -1,34 +1,143 @@
 // Logging
 use chrono::Local;
 use env_logger::Builder;
-use log::{error, info, LevelFilter};
+use log::{error, info, warn, LevelFilter};
 use std::io::Write;
+use std::process::ExitCode;
 
 // Argument parsing
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 
-/// SystemVerilog simulation tool. Takes a single file as an output and produces
-/// an object file in the same directory by default
+// Simulation
+use sv_sim::sim_time::{sim_time_display, TimeUnit};
+
+/// SystemVerilog simulation tool. Takes one or more files as an input and
+/// produces an object file in the same directory by default
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Cli {
-    /// File input path
-    input_path: std::path::PathBuf,
+    /// File input paths, parsed in order
+    #[arg(required = true)]
+    input_paths: Vec<std::path::PathBuf>,
 
-    /// File output path
+    /// Waveform output path. Defaults to the first input path, with the
+    /// extension of the output format
+    #[arg(short, long)]
     output_path: Option<std::path::PathBuf>,
 
+    /// Waveform output format. Defaults to the format named by the
+    /// extension of the output path, or VCD
+    #[arg(short, long, value_enum)]
+    format: Option<OutputFormat>,
+
     /// Sets logging level (0 = off ... 4 = trace)
     #[arg(short, long, default_value_t = LevelFilter::Error)]
     log_level: LevelFilter,
 
-    /// Enables verbose file output
+    /// Logs progress messages, raising the logging level to at least 3
+    /// (info)
     #[arg(short, long, default_value_t = false)]
     verbose: bool,
+
+    /// Defines a preprocessor macro, as in `-D NAME` to define it as 1,
+    /// `-D NAME=VALUE` or `-D 'NAME(a, b)=VALUE'`. May be given more than
+    /// once
+    #[arg(short = 'D', value_name = "NAME[=VALUE]")]
+    define: Vec<String>,
+
+    /// Adds a directory to search for included files. May be given more
+    /// than once, with directories searched in the order given
+    #[arg(short = 'I', value_name = "PATH")]
+    include_dir: Vec<std::path::PathBuf>,
+
+    /// Top-level module to elaborate and simulate. Without it, the only
+    /// module not instantiated by another is used
+    #[arg(long)]
+    top: Option<String>,
+
+    /// Stops the simulation after this much simulated time, as in `1000ns`.
+    /// Without it, the simulation runs until nothing is left to simulate
+    #[arg(long, value_parser = parse_end_time)]
+    end_time: Option<f64>,
+
+    /// Writes an IP-XACT component description of each module to this path
+    #[arg(long)]
+    ip_xact_output: Option<std::path::PathBuf>,
+
+    /// Runs each initial block annotated with `// @testbench` as a separate
+    /// test, stopping each after --end-time if given, and writes a JUnit XML
+    /// report to this path
+    #[arg(long)]
+    junit_output: Option<std::path::PathBuf>,
+
+    /// Checks the design for combinational loops, inferred latches and
+    /// high-fanout nets before simulating it, logging what it finds
+    #[arg(long, default_value_t = false)]
+    lint: bool,
+
+    /// Number of logic blocks a net may be read by before --lint reports it
+    #[arg(long, default_value_t = 16)]
+    fanout_threshold: u64,
+
+    /// Warns about port connections of the wrong width instead of failing
+    #[arg(long, default_value_t = false)]
+    allow_implicit_truncation: bool,
+
+    /// Fails instead of warning when an instance leaves a port unconnected
+    #[arg(long, default_value_t = false)]
+    deny_unconnected_ports: bool,
+
+    /// Lets `$system` run shell commands on the host. Only use this with
+    /// trusted designs
+    #[arg(long, default_value_t = false)]
+    allow_system_calls: bool,
 }
 
-fn main() {
+/// Waveform output format
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    /// Value Change Dump
+    Vcd,
+
+    /// Fast Signal Trace, which cannot be written yet and fails the run
+    Fst,
+
+    /// JSON object holding every signal and change
+    Json,
+}
+
+impl OutputFormat {
+    /// File extension of the format
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Vcd => "vcd",
+            OutputFormat::Fst => "fst",
+            OutputFormat::Json => "json",
+        }
+    }
+
+    /// Format selected by the arguments, as described for `Cli::format`
+    fn selected(args: &Cli) -> Self {
+        let from_path = || {
+            let ext = args.output_path.as_ref()?.extension()?;
+            OutputFormat::value_variants()
+                .iter()
+                .find(|format| ext.eq_ignore_ascii_case(format.extension()))
+                .copied()
+        };
+
+        args.format.or_else(from_path).unwrap_or(OutputFormat::Vcd)
+    }
+}
+
+/// Exits with a failure status if the design could not be read, parsed or
+/// elaborated, or if it called `$system` without --allow-system-calls
+fn main() -> ExitCode {
     let args = Cli::parse();
+    let log_level = match args.verbose {
+        true => args.log_level.max(LevelFilter::Info),
+        false => args.log_level,
+    };
 
     Builder::new()
         .format(|buf, record| {
@@ -40,27 +149,203 @@ fn main() {
                 record.args()
             )
         })
-        .filter(None, args.log_level)
+        .filter(None, log_level)
         .init();
 
-    let ret = sv_sim::read_sv_file(&args.input_path);
+    if OutputFormat::selected(&args) == OutputFormat::Fst {
+        error!("FST output is not supported, use --format vcd or --format json");
+        return ExitCode::FAILURE;
+    }
+
+    let mut preprocessor = sv_sim::preprocess::Preprocessor::new();
+    for define in &args.define {
+        if let Err(e) = preprocessor.define_arg(define) {
+            error!("encountered an error reading -D {}: '{}'", define, e);
+            return ExitCode::FAILURE;
+        }
+    }
+    for dir in &args.include_dir {
+        preprocessor.add_include_dir(dir);
+    }
+
+    let config = sv_sim::SimConfig {
+        allow_system_calls: args.allow_system_calls,
+        allow_implicit_truncation: args.allow_implicit_truncation,
+        deny_unconnected_ports: args.deny_unconnected_ports,
+        test_timeout: args.end_time,
+    };
+    let ret = sv_sim::SimObject::elaborate_with_top_with(
+        &args.input_paths,
+        args.top.as_deref(),
+        &config,
+        &mut preprocessor,
+    );
 
     match ret {
-        Ok(input) => {
-            match sv_sim::parse_sv_file(input) {
-                Ok(object) => {
-                    info!(
-                        "succesfully parsed input file {}",
-                        &args.input_path.display()
-                    );
-                    format!("{object:?}");
+        Ok(design) => {
+            info!(
+                "elaborated top-level module '{}' from {} input file(s)",
+                design.top.module_name,
+                args.input_paths.len()
+            );
+
+            if args.lint {
+                lint(&design, &args);
+            }
+
+            let result = simulate(&design, &args);
+            write_waveform(&result, &args);
+            let status = match result.check_system_calls() {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    error!("encountered an error simulating the design: '{}'", e);
+                    ExitCode::FAILURE
                 }
-                Err(_) => (),
             };
+
+            if let Some(dump) = &result.dump {
+                match sv_sim::output::vcd::write_dump(&result, dump) {
+                    Ok(()) => info!("wrote requested dump to {}", dump.file.display()),
+                    Err(e) => error!("encountered an error writing {:?}: '{}'", dump.file, e),
+                }
+            }
+
+            if let Some(path) = &args.ip_xact_output {
+                write_ip_xact(&design, path);
+            }
+
+            if let Some(path) = &args.junit_output {
+                run_testbench(&design, &config, path);
+            }
+
+            status
+        }
+        Err(errors) => {
+            for e in errors {
+                error!("encountered an error elaborating the design: '{}'", e);
+            }
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Reads an `--end-time` duration, in seconds
+fn parse_end_time(text: &str) -> Result<f64, String> {
+    sv_sim::sim_time::parse_duration(text).map_err(|e| e.to_string())
+}
+
+/// Reports combinational loops, inferred latches and high-fanout nets in an
+/// elaborated design
+fn lint(design: &sv_sim::elab::ElaboratedDesign, args: &Cli) {
+    let loops = design.combinational_loops();
+
+    let nets = design.high_fanout_nets(args.fanout_threshold);
+    for (net, fanout) in &nets {
+        warn!("net '{}' is read by {} logic blocks", net, fanout);
+    }
+
+    let mut mods: Vec<&sv_sim::module::Module> = design.mods.values().collect();
+    mods.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut latches = 0;
+    for module in mods {
+        for name in sv_sim::lint::detect_inferred_latches(module) {
+            warn!(
+                "'{}' is not assigned in every branch of an always_comb block in '{}', inferring a latch",
+                name, module.name
+            );
+            latches += 1;
         }
-        Err(e) => error!(
-            "encountered an error reading {:?}: '{}'",
-            args.input_path, e
-        ),
+    }
+
+    info!(
+        "found {} combinational loop(s), {} inferred latch(es) and {} net(s) with a fanout above {}",
+        loops.len(),
+        latches,
+        nets.len(),
+        args.fanout_threshold
+    );
+}
+
+/// Simulates an elaborated design
+fn simulate(design: &sv_sim::elab::ElaboratedDesign, args: &Cli) -> sv_sim::sim::SimResult {
+    let result = design.run(args.end_time.unwrap_or(f64::INFINITY));
+    info!(
+        "simulated '{}' until {}, as {}",
+        design.top.module_name,
+        sim_time_display(result.end_time, TimeUnit::Nanoseconds),
+        result.status
+    );
+
+    result
+}
+
+/// Writes a simulation result in the selected output format
+fn write_waveform(result: &sv_sim::sim::SimResult, args: &Cli) {
+    let format = OutputFormat::selected(args);
+    let path = match &args.output_path {
+        Some(path) => path.to_path_buf(),
+        None => args.input_paths[0].with_extension(format.extension()),
+    };
+
+    let ret = match format {
+        OutputFormat::Vcd => sv_sim::output::vcd::write_vcd(result, &path),
+        OutputFormat::Fst => Err(sv_sim::SimError::Io(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "FST output is not supported",
+        ))),
+        OutputFormat::Json => sv_sim::output::json::write_json(result, &path),
     };
+
+    match ret {
+        Ok(()) => info!("wrote waveform to {}", path.display()),
+        Err(e) => error!("encountered an error writing {:?}: '{}'", path, e),
+    }
+}
+
+/// Runs the annotated tests of an elaborated design, writing a JUnit XML
+/// report to `path`
+fn run_testbench(
+    design: &sv_sim::elab::ElaboratedDesign,
+    config: &sv_sim::SimConfig,
+    path: &std::path::Path,
+) {
+    let results = design.run_testbench(config);
+    info!(
+        "{} of {} test(s) passed",
+        results.iter().filter(|result| result.passed).count(),
+        results.len()
+    );
+
+    match sv_sim::output::junit::write_junit(&results, &design.top.module_name, path) {
+        Ok(()) => info!("wrote test report to {}", path.display()),
+        Err(e) => error!("encountered an error writing {:?}: '{}'", path, e),
+    }
+}
+
+/// Writes IP-XACT descriptions for every module of an elaborated design
+///
+/// A design with several modules gets one file per module, named by appending
+/// the module name to the file stem of `path`
+fn write_ip_xact(design: &sv_sim::elab::ElaboratedDesign, path: &std::path::Path) {
+    let mut mods: Vec<&sv_sim::module::Module> = design.mods.values().collect();
+    mods.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for module in mods {
+        let module_path = if design.mods.len() > 1 {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let name = match path.extension() {
+                Some(ext) => format!("{}_{}.{}", stem, module.name, ext.to_string_lossy()),
+                None => format!("{}_{}", stem, module.name),
+            };
+            path.with_file_name(name)
+        } else {
+            path.to_path_buf()
+        };
+
+        match sv_sim::ip_xact::write_ip_xact(module, "sv-sim", "work", "1.0", &module_path) {
+            Ok(()) => info!("wrote IP-XACT description to {}", module_path.display()),
+            Err(e) => error!("encountered an error writing {:?}: '{}'", module_path, e),
+        }
+    }
 }
@@ -1,14 +1,34 @@
+use crate::eval::ConstLookup;
+use crate::expr::{parse_expr, parse_ident, Expr};
+use crate::function::{parse_function, parse_task, FunctionDef, TaskDef};
+use crate::generate::{
+    parse_generate_case, parse_generate_for, parse_generate_if, parse_generate_region, GenBlock,
+};
+use crate::interface::{parse_interface_port, InterfacePort};
+use crate::logic::{
+    parse_always, parse_always_comb, parse_always_ff, parse_always_latch, parse_assign,
+    parse_final, parse_initial, AlwaysComb, AlwaysFF, AlwaysLatch, AssignStmt, FinalBlock,
+    InitialBlock, LogicStmt,
+};
+use crate::param::{
+    parse_parameter, parse_parameter_ports, parse_type_parameter, LocalparamType, Param, ParamType,
+};
+use crate::value::LogicValue;
 use crate::var_types::{self, *};
-use crate::{parse_comment, LexingError, Token};
-use log::{debug, error, trace};
+use crate::{
+    expect_token, next_token, parse_block_comment, parse_comment, peek_token, ElabError,
+    LexingError, Token,
+};
+use log::{debug, error, trace, warn};
 use logos::Lexer;
+use std::collections::HashMap;
 use std::fmt;
 
 /// SystemVerilog module representation
 ///
 /// Contains a module I/O header, variable assignments, combinational
 /// and sequential logic, as well as any constants
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Module {
     /// Module friendly name
     pub name: String,
@@ -18,72 +38,703 @@ pub struct Module {
 
     /// Module "variables" (wire, reg, etc.)
     pub vars: Vec<Var>,
+
+    /// Parameters declared in the module body
+    pub params: Vec<Param>,
+
+    /// Type parameters declared in the module body
+    pub type_params: Vec<LocalparamType>,
+
+    /// Package imports, from both the module header and body
+    pub imports: Vec<ImportDecl>,
+
+    /// Continuous assignments
+    pub assigns: Vec<AssignStmt>,
+
+    /// Combinational logic blocks
+    pub comb_blocks: Vec<AlwaysComb>,
+
+    /// Sequential logic blocks
+    pub ff_blocks: Vec<AlwaysFF>,
+
+    /// Latched logic blocks
+    pub latch_blocks: Vec<AlwaysLatch>,
+
+    /// Initial blocks
+    pub initial_blocks: Vec<InitialBlock>,
+
+    /// Final blocks
+    pub final_blocks: Vec<FinalBlock>,
+
+    /// Sub-module instances
+    pub instances: Vec<ModuleInst>,
+
+    /// Generate constructs, unrolled during elaboration
+    pub generates: Vec<GenBlock>,
+
+    /// Function definitions
+    pub functions: Vec<FunctionDef>,
+
+    /// Task definitions
+    pub tasks: Vec<TaskDef>,
 }
 
 impl fmt::Debug for Module {
     fn fmt(&self, _: &mut std::fmt::Formatter) -> fmt::Result {
         debug!("MODULE: {:?}", self.name);
-        format!("{0:?}", self.io);
+        for import in self.imports.clone() {
+            debug!("IMPORT: {:?}", import);
+        }
+        let _ = format!("{0:?}", self.io);
+        for param in &self.params {
+            debug!("PARAM: {:?}", param);
+        }
+        for param in &self.type_params {
+            debug!("TYPE PARAM: {:?}", param);
+        }
         for var in self.vars.clone() {
             debug!("VAR: {:?}", var);
         }
+        for assign in &self.assigns {
+            debug!("ASSIGN: {:?}", assign);
+        }
+        for block in &self.comb_blocks {
+            let _ = format!("{block:?}");
+        }
+        for block in &self.ff_blocks {
+            let _ = format!("{block:?}");
+        }
+        for block in &self.latch_blocks {
+            let _ = format!("{block:?}");
+        }
+        for block in &self.initial_blocks {
+            let _ = format!("{block:?}");
+        }
+        for block in &self.final_blocks {
+            let _ = format!("{block:?}");
+        }
+        for inst in &self.instances {
+            debug!("INSTANCE: {:?}", inst);
+        }
+        for generate in &self.generates {
+            debug!("GENERATE: {:?}", generate);
+        }
+        for function in &self.functions {
+            debug!("FUNCTION: {:?}", function);
+        }
+        for task in &self.tasks {
+            debug!("TASK: {:?}", task);
+        }
         Ok(())
     }
 }
 
 /// Parses a module to completion
 pub fn parse_module<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Module, LexingError> {
-    let mut vars: Vec<Var> = Vec::new();
-
-    let io = parse_module_io(lexer)?;
+    let mut imports: Vec<ImportDecl> = Vec::new();
+    let io = parse_module_io(lexer, &mut imports)?;
+    let mut module = Module {
+        name: io.name.to_owned(),
+        io,
+        imports,
+        ..Default::default()
+    };
 
     trace!("parsing module");
 
     while let Some(token) = lexer.next() {
         match token {
-            Ok(Token::Wire) => vars.push(parse_module_var(lexer, VarType::Wire)?),
-            Ok(Token::Reg) => vars.push(parse_module_var(lexer, VarType::Reg)?),
-            Ok(Token::Comment) => parse_comment(lexer)?,
-            Ok(Token::WhiteSpace) => (),
-            Ok(Token::EndModule) => break,
+            Ok(Token::EndModule) => return Ok(module),
+            token => parse_module_item(lexer, token, &mut module)?,
+        }
+    }
+
+    error!("module '{}' is missing 'endmodule'", module.name);
+    Err(LexingError::UnexpectedToken)
+}
+
+/// Parses a single module item starting with the already consumed `token`,
+/// adding it to `module`
+///
+/// Whitespace and comments are accepted as items, so the caller may pass
+/// every token it reads
+pub(crate) fn parse_module_item<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    token: Result<Token, LexingError>,
+    module: &mut Module,
+) -> Result<(), LexingError> {
+    match token {
+        Ok(Token::Wire) => parse_module_var(lexer, VarType::Wire, module)?,
+        Ok(Token::Reg) => parse_module_var(lexer, VarType::Reg, module)?,
+        Ok(Token::Tri) => parse_module_var(lexer, VarType::Tri, module)?,
+        Ok(Token::Logic) => parse_module_var(lexer, VarType::Logic, module)?,
+        // A type following `var` starts the declaration itself, as in
+        // `var int v;`, while without one the variable is a `logic`
+        Ok(Token::Var)
+            if matches!(
+                peek_token(lexer),
+                Some(Ok(Token::OpenBracket
+                    | Token::Signed
+                    | Token::Unsigned
+                    | Token::Word
+                    | Token::EscapedIdentifier(_)))
+            ) =>
+        {
+            parse_module_var(lexer, VarType::Logic, module)?
+        }
+        Ok(Token::Var) => (),
+        Ok(Token::Byte) => parse_module_var(lexer, VarType::Byte, module)?,
+        Ok(Token::ShortInt) => parse_module_var(lexer, VarType::ShortInt, module)?,
+        Ok(Token::Int) => parse_module_var(lexer, VarType::Int, module)?,
+        Ok(Token::LongInt) => parse_module_var(lexer, VarType::LongInt, module)?,
+        Ok(Token::IntegerType) => parse_module_var(lexer, VarType::Integer, module)?,
+        Ok(Token::Genvar) => parse_module_var(lexer, VarType::Genvar, module)?,
+        Ok(Token::Input) => parse_port_declaration(lexer, &mut module.io, PortDirection::Input)?,
+        Ok(Token::Output) => {
+            parse_port_declaration(lexer, &mut module.io, PortDirection::Output)?
+        }
+        Ok(Token::Inout) => parse_port_declaration(lexer, &mut module.io, PortDirection::Inout)?,
+        Ok(Token::Import) => module.imports.append(&mut parse_import(lexer)?),
+        Ok(Token::Parameter) if peek_token(lexer) == Some(Ok(Token::Type)) => {
+            parse_type_parameter(lexer, ParamType::Parameter, module)?
+        }
+        Ok(Token::Localparam) if peek_token(lexer) == Some(Ok(Token::Type)) => {
+            parse_type_parameter(lexer, ParamType::Localparam, module)?
+        }
+        Ok(Token::Parameter) => module
+            .params
+            .append(&mut parse_parameter(lexer, ParamType::Parameter)?),
+        Ok(Token::Localparam) => module
+            .params
+            .append(&mut parse_parameter(lexer, ParamType::Localparam)?),
+        Ok(Token::Assign) => module.assigns.append(&mut parse_assign(lexer)?),
+        Ok(Token::Comb) => module.comb_blocks.push(parse_always_comb(lexer)?),
+        Ok(Token::AlwaysFF) => module.ff_blocks.push(parse_always_ff(lexer)?),
+        Ok(Token::AlwaysLatch) => module.latch_blocks.push(parse_always_latch(lexer)?),
+        Ok(Token::Initial) => module.initial_blocks.push(parse_initial(lexer)?),
+        Ok(Token::Final) => module.final_blocks.push(parse_final(lexer)?),
+        Ok(Token::Always) => parse_always(
+            lexer,
+            &mut module.ff_blocks,
+            &mut module.comb_blocks,
+            &mut module.initial_blocks,
+        )?,
+        Ok(Token::Generate) => parse_generate_region(lexer, module)?,
+        Ok(Token::Function) => module.functions.push(parse_function(lexer)?),
+        Ok(Token::Task) => module.tasks.push(parse_task(lexer)?),
+        Ok(Token::For) => module.generates.push(parse_generate_for(lexer)?),
+        Ok(Token::If) => module.generates.push(parse_generate_if(lexer)?),
+        Ok(Token::Case) => module.generates.push(parse_generate_case(lexer)?),
+        Ok(Token::Word) | Ok(Token::Underscore) | Ok(Token::EscapedIdentifier(_))
+            if is_type_name(lexer, module) =>
+        {
+            let name = parse_ident(lexer);
+            let type_expr = module.type_parameter(&name).cloned();
+            parse_module_var_of_type(lexer, type_expr.expect("name is a type"), module)?
+        }
+        Ok(Token::Word) | Ok(Token::EscapedIdentifier(_)) if is_module_inst(lexer) => {
+            module.instances.append(&mut parse_module_inst(lexer)?)
+        }
+        Ok(Token::LineComment) => parse_comment(lexer)?,
+        Ok(Token::BlockCommentStart) => parse_block_comment(lexer)?,
+        Ok(Token::WhiteSpace) | Ok(Token::Newline) => (),
+        Err(e) => {
+            error!(
+                "unexpected error occurred parsing sv file: '{}'",
+                lexer.slice()
+            );
+            return Err(e);
+        }
+        _ => warn!("ignoring unsupported module item '{}'", lexer.slice()),
+    }
+
+    Ok(())
+}
+
+/// Package import declaration
+///
+/// Produced by `import pkg::item;` or, for a wildcard import, `import pkg::*;`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportDecl {
+    /// Imported package name
+    pub package: String,
+
+    /// Imported item name, `None` for wildcard imports
+    pub item: Option<String>,
+}
+
+/// Parses an import declaration to completion
+///
+/// A single declaration may import several items, as in `import a::x, b::*;`
+fn parse_import<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<Vec<ImportDecl>, LexingError> {
+    let mut imports: Vec<ImportDecl> = Vec::new();
+    let mut package = String::new();
+    let mut item = String::new();
+    let mut wildcard = false;
+    let mut scoped = false;
+
+    trace!("parsing import");
+
+    while let Some(token) = lexer.next() {
+        match token {
+            Ok(Token::Word) | Ok(Token::Underscore) | Ok(Token::Integer(_)) => {
+                let name = if scoped { &mut item } else { &mut package };
+                *name += lexer.slice();
+            }
+            Ok(Token::EscapedIdentifier(val)) => {
+                let name = if scoped { &mut item } else { &mut package };
+                *name += &val;
+            }
+            Ok(Token::DoubleColon) if !scoped && !package.is_empty() => scoped = true,
+            Ok(Token::Multiply) if scoped && item.is_empty() => wildcard = true,
+            Ok(Token::Comma) | Ok(Token::Semicolon) => {
+                if !scoped || (item.is_empty() && !wildcard) {
+                    error!("expected 'package::item' or 'package::*' in import");
+                    return Err(LexingError::UnexpectedToken);
+                }
+
+                imports.push(ImportDecl {
+                    package: std::mem::take(&mut package),
+                    item: (!wildcard).then(|| std::mem::take(&mut item)),
+                });
+                wildcard = false;
+                scoped = false;
+
+                if lexer.slice() == ";" {
+                    return Ok(imports);
+                }
+            }
+            Ok(Token::WhiteSpace) | Ok(Token::Newline) => (),
+            Ok(Token::LineComment) => parse_comment(lexer)?,
+            Ok(Token::BlockCommentStart) => parse_block_comment(lexer)?,
             Err(e) => {
                 error!(
-                    "unexpected error occurred parsing sv file: '{}'",
+                    "unexpected error occurred parsing import: '{}'",
                     lexer.slice()
                 );
                 return Err(e);
             }
-            // _ => warn!("{:?} not implemented", token.unwrap()),
-            _ => (),
+            _ => {
+                error!("unexpected value in import, got {:?}", token.unwrap());
+                return Err(LexingError::UnexpectedToken);
+            }
         }
     }
 
-    Ok(Module {
-        name: io.name.to_owned(),
-        io,
-        vars,
-    })
+    Err(LexingError::ExpectedSemi)
+}
+
+/// Module instance, as in `adder u_add (.a(x), .b(y), .sum(z));`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleInst {
+    /// Name of the instantiated module
+    pub module_name: String,
+
+    /// Parameter overrides, as in `adder #(.WIDTH(8)) u_add (...)`
+    pub param_overrides: Vec<ParamOverride>,
+
+    /// Instance name
+    pub inst_name: String,
+
+    /// Port connections, in source order
+    pub port_connections: Vec<PortConn>,
+}
+
+/// Module instance port connection
+#[derive(Debug, Clone, PartialEq)]
+pub enum PortConn {
+    /// Connection by port name, as in `.clk(sys_clk)`. The signal is `None`
+    /// for a port left explicitly unconnected, as in `.clk()`
+    Named { port: String, signal: Option<Expr> },
+
+    /// Connection by port position. The signal is `None` for an empty entry,
+    /// as in `(a, , b)`
+    Positional(Option<Expr>),
+
+    /// Connection of every port not connected by name to the signal of the
+    /// same name, as in `.*`
+    Wildcard,
+}
+
+/// Module instance parameter override
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamOverride {
+    /// Override by parameter name, as in `#(.WIDTH(8))`. The value is `None`
+    /// for a parameter explicitly left at its default, as in `#(.WIDTH())`
+    Named { param: String, value: Option<Expr> },
+
+    /// Override by parameter position, as in `#(8)` or `#8`
+    Positional(Expr),
+}
+
+/// Checks if a module body identifier names a type parameter of `module`,
+/// starting a variable declaration
+fn is_type_name(lexer: &Lexer<Token>, module: &Module) -> bool {
+    module
+        .type_parameter(&parse_ident(&mut lexer.clone()))
+        .is_some()
+}
+
+/// Checks if the tokens following a module body identifier start a module
+/// instantiation, that is `module_name #` or `module_name inst_name (`
+fn is_module_inst(lexer: &Lexer<Token>) -> bool {
+    let mut ahead = lexer.clone();
+    parse_ident(&mut ahead);
+
+    match next_token(&mut ahead) {
+        Some(Ok(Token::Pound)) => true,
+        Some(Ok(Token::Word))
+        | Some(Ok(Token::Underscore))
+        | Some(Ok(Token::EscapedIdentifier(_))) => {
+            parse_ident(&mut ahead);
+            next_token(&mut ahead) == Some(Ok(Token::OpenParen))
+        }
+        _ => false,
+    }
+}
+
+/// Parses a module instantiation to completion
+///
+/// The first token of the module name has already been consumed. A single
+/// statement may hold several comma separated instances of the same module,
+/// as in `adder u0 (a, b, s0), u1 (c, d, s1);`, which share any parameter
+/// overrides
+pub fn parse_module_inst<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<Vec<ModuleInst>, LexingError> {
+    let mut instances: Vec<ModuleInst> = Vec::new();
+    let mut param_overrides: Vec<ParamOverride> = Vec::new();
+    let module_name = parse_ident(lexer);
+
+    trace!("parsing instance of module '{}'", module_name);
+
+    if peek_token(lexer) == Some(Ok(Token::Pound)) {
+        next_token(lexer);
+        param_overrides = parse_param_overrides(lexer)?;
+    }
+
+    loop {
+        let inst_name = match next_token(lexer) {
+            Some(Ok(Token::Word))
+            | Some(Ok(Token::Underscore))
+            | Some(Ok(Token::EscapedIdentifier(_))) => parse_ident(lexer),
+            _ => {
+                error!("expected instance name, got '{}'", lexer.slice());
+                return Err(LexingError::UnexpectedToken);
+            }
+        };
+
+        expect_token(lexer, Token::OpenParen, "module instance")?;
+
+        instances.push(ModuleInst {
+            module_name: module_name.to_owned(),
+            param_overrides: param_overrides.clone(),
+            inst_name,
+            port_connections: parse_port_connections(lexer)?,
+        });
+
+        match next_token(lexer) {
+            Some(Ok(Token::Comma)) => (),
+            Some(Ok(Token::Semicolon)) => return Ok(instances),
+            _ => {
+                error!("expected ';', got '{}'", lexer.slice());
+                return Err(LexingError::ExpectedSemi);
+            }
+        }
+    }
+}
+
+/// Parses a module instance parameter override list to completion
+///
+/// The `#` has already been consumed. Overrides follow the same rules as port
+/// connections, except that a positional override cannot be empty. A single
+/// positional override may also be given without parentheses, as in `#8`
+fn parse_param_overrides<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<Vec<ParamOverride>, LexingError> {
+    trace!("parsing parameter overrides");
+
+    if peek_token(lexer) != Some(Ok(Token::OpenParen)) {
+        return Ok(vec![ParamOverride::Positional(parse_expr(lexer)?)]);
+    }
+
+    next_token(lexer);
+    parse_port_connections(lexer)?
+        .into_iter()
+        .map(|conn| match conn {
+            PortConn::Named { port, signal } => Ok(ParamOverride::Named {
+                param: port,
+                value: signal,
+            }),
+            PortConn::Positional(Some(value)) => Ok(ParamOverride::Positional(value)),
+            PortConn::Positional(None) => {
+                error!("empty positional parameter override");
+                Err(LexingError::UnexpectedToken)
+            }
+            PortConn::Wildcard => {
+                error!("parameters cannot be overridden by '.*'");
+                Err(LexingError::UnexpectedToken)
+            }
+        })
+        .collect()
+}
+
+/// Parses a module instance port connection list up to and including its
+/// closing parenthesis
+///
+/// The list uses named connections if its first entry starts with `.`, and
+/// positional connections otherwise. The two styles cannot be mixed, and a
+/// name, or `.*`, may only be given once
+fn parse_port_connections<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<Vec<PortConn>, LexingError> {
+    let mut connections: Vec<PortConn> = Vec::new();
+
+    let named = match peek_token(lexer) {
+        Some(Ok(Token::CloseParen)) => {
+            next_token(lexer);
+            return Ok(connections);
+        }
+        Some(Ok(Token::Dot)) => true,
+        _ => false,
+    };
+
+    loop {
+        match peek_token(lexer) {
+            Some(Ok(Token::Dot)) if named => {
+                next_token(lexer);
+                let conn = parse_named_connection(lexer)?;
+                let name = connection_name(&conn);
+                if connections.iter().any(|prev| connection_name(prev) == name) {
+                    let name = name.unwrap_or_default().to_owned();
+                    error!("'{}' is connected more than once", name);
+                    return Err(LexingError::DuplicateConnection(name));
+                }
+                connections.push(conn);
+            }
+            Some(Ok(Token::Dot)) => {
+                error!("named port connection in a positional connection list");
+                return Err(LexingError::UnexpectedToken);
+            }
+            _ if named => {
+                next_token(lexer);
+                error!("expected named port connection, got '{}'", lexer.slice());
+                return Err(LexingError::UnexpectedToken);
+            }
+            Some(Ok(Token::Comma)) | Some(Ok(Token::CloseParen)) => {
+                connections.push(PortConn::Positional(None))
+            }
+            _ => connections.push(PortConn::Positional(Some(parse_expr(lexer)?))),
+        }
+
+        match next_token(lexer) {
+            Some(Ok(Token::Comma)) => (),
+            Some(Ok(Token::CloseParen)) => return Ok(connections),
+            _ => {
+                error!(
+                    "expected ',' or ')' in port connection list, got '{}'",
+                    lexer.slice()
+                );
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    }
+}
+
+/// Name a connection is made by, `*` for `.*`, or `None` for a positional
+/// connection
+fn connection_name(conn: &PortConn) -> Option<&str> {
+    match conn {
+        PortConn::Named { port, .. } => Some(port),
+        PortConn::Positional(_) => None,
+        PortConn::Wildcard => Some("*"),
+    }
+}
+
+/// Parses a named port connection such as `.clk(sys_clk)` or `.clk`, or a
+/// wildcard connection, `.*`
+///
+/// The `.` has already been consumed
+fn parse_named_connection<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<PortConn, LexingError> {
+    let port = match next_token(lexer) {
+        Some(Ok(Token::Multiply)) => return Ok(PortConn::Wildcard),
+        Some(Ok(Token::Word))
+        | Some(Ok(Token::Underscore))
+        | Some(Ok(Token::EscapedIdentifier(_))) => parse_ident(lexer),
+        _ => {
+            error!("expected port name, got '{}'", lexer.slice());
+            return Err(LexingError::UnexpectedToken);
+        }
+    };
+
+    // `.clk` is shorthand for `.clk(clk)`
+    if peek_token(lexer) != Some(Ok(Token::OpenParen)) {
+        return Ok(PortConn::Named {
+            signal: Some(Expr::Ident(port.to_owned())),
+            port,
+        });
+    }
+
+    expect_token(lexer, Token::OpenParen, "named port connection")?;
+
+    let signal = match peek_token(lexer) {
+        Some(Ok(Token::CloseParen)) => None,
+        _ => Some(parse_expr(lexer)?),
+    };
+
+    expect_token(lexer, Token::CloseParen, "named port connection")?;
+
+    Ok(PortConn::Named { port, signal })
+}
+
+/// Names a module declares itself, as its ports, variables, parameters and
+/// functions
+pub fn local_names(module: &Module) -> Vec<&str> {
+    let io = &module.io;
+
+    io.ports
+        .iter()
+        .map(|port| port.as_str())
+        .chain(io.inputs.iter().map(|input| input.name.as_str()))
+        .chain(io.outputs.iter().map(|output| output.name.as_str()))
+        .chain(io.inouts.iter().map(|inout| inout.name.as_str()))
+        .chain(module.vars.iter().map(|var| var.name.as_str()))
+        .chain(
+            io.params
+                .iter()
+                .chain(&module.params)
+                .map(|param| param.name.as_str()),
+        )
+        .chain(
+            module
+                .functions
+                .iter()
+                .map(|function| function.name.as_str()),
+        )
+        .collect()
+}
+
+/// Checks explicit package imports against a module's own declarations
+///
+/// An item imported by name cannot share its name with a port, variable,
+/// parameter or function of the importing module. Wildcard imports never
+/// conflict, as local declarations take precedence over wildcard imported
+/// names
+pub fn check_import_conflicts(module: &Module) -> Vec<ElabError> {
+    let locals = local_names(module);
+
+    module
+        .imports
+        .iter()
+        .filter_map(|import| match &import.item {
+            Some(item) if locals.contains(&item.as_str()) => {
+                error!(
+                    "'{}' imported from package '{}' conflicts with a declaration in module '{}'",
+                    item, import.package, module.name
+                );
+                Some(ElabError::ImportConflict(
+                    item.to_owned(),
+                    import.package.to_owned(),
+                ))
+            }
+            _ => None,
+        })
+        .collect()
 }
 
+/// Parses a module variable declaration to completion, adding every declared
+/// variable to `module`
+///
+/// Comma separated names share the type and width, as in `wire [7:0] a, b;`.
+/// A net initializer, as in `wire a = b;`, drives the net as a continuous
+/// assignment, while a variable initializer, as in `reg r = 1'b0;`, is
+/// assigned once at the start of simulation
 fn parse_module_var<'source>(
     lexer: &mut Lexer<'source, Token>,
     var_type: VarType,
-) -> Result<Var, LexingError> {
-    let mut width = 1;
+    module: &mut Module,
+) -> Result<(), LexingError> {
+    parse_module_var_of_type(lexer, TypeExpr::from(var_type), module)
+}
+
+/// Parses a module variable declaration to completion, starting from the
+/// width and signedness of `type_expr`, as for a type parameter
+fn parse_module_var_of_type<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    type_expr: TypeExpr,
+    module: &mut Module,
+) -> Result<(), LexingError> {
+    let TypeExpr {
+        var_type,
+        mut signed,
+        mut width,
+        mut bounds,
+    } = type_expr;
+    let mut lsb = 0;
 
     trace!("parsing module variable of type {:?}", var_type);
 
     while let Some(token) = lexer.next() {
         match token {
-            Ok(Token::Word) => return Ok(Var {
-                name: parse_name(lexer)?,
-                var_type,
-                width,
-                ..Default::default()
-            }),
-            Ok(Token::OpenBracket) => width = var_types::parse_width(lexer)?,
-            Ok(Token::Comment) => crate::parse_comment(lexer)?,
-            Ok(Token::WhiteSpace) => (),
+            Ok(Token::Word) | Ok(Token::EscapedIdentifier(_)) => {
+                let (name, array_bounds) = var_types::parse_array_name(lexer)?;
+
+                if lexer.slice() == "=" {
+                    let lhs = Expr::Ident(name.to_owned());
+                    let rhs = parse_expr(lexer)?;
+                    match var_type {
+                        VarType::Wire | VarType::Tri => {
+                            module.assigns.push(AssignStmt {
+                                lhs,
+                                rhs,
+                                delay: None,
+                                strength: Default::default(),
+                            })
+                        }
+                        _ => module.initial_blocks.push(InitialBlock {
+                            body: vec![LogicStmt::BlockingAssign { lhs, rhs }],
+                            testbench: None,
+                        }),
+                    }
+                    next_token(lexer);
+                }
+
+                let mut var = Var {
+                    name,
+                    var_type: var_type.clone(),
+                    width,
+                    lsb,
+                    signed,
+                    state: LogicValue::new(width),
+                    array: None,
+                    bounds: bounds.clone(),
+                    array_bounds,
+                };
+                var.apply_bounds(&ConstLookup {
+                    values: &HashMap::new(),
+                    functions: &module.functions,
+                })?;
+                module.vars.push(var);
+
+                match lexer.slice() {
+                    "," => (),
+                    ";" => return Ok(()),
+                    _ => {
+                        error!("expected ',' or ';' in declaration, got '{}'", lexer.slice());
+                        return Err(LexingError::ExpectedSemi);
+                    }
+                }
+            }
+            Ok(Token::Signed) => signed = true,
+            Ok(Token::Unsigned) => signed = false,
+            Ok(Token::OpenBracket) => (width, lsb, bounds) = var_types::parse_range(lexer)?,
+            Ok(Token::LineComment) => crate::parse_comment(lexer)?,
+            Ok(Token::BlockCommentStart) => parse_block_comment(lexer)?,
+            Ok(Token::WhiteSpace) | Ok(Token::Newline) => (),
             Err(e) => {
                 error!(
                     "unexpected error occurred parsing module wire: '{}'",
@@ -98,14 +749,128 @@ fn parse_module_var<'source>(
     Err(LexingError::ModuleWireNotFound)
 }
 
+/// Parses a non-ANSI port declaration in a module body to completion
+///
+/// Every declared name must already be listed in the module header
+fn parse_port_declaration<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    io: &mut ModuleIO,
+    direction: PortDirection,
+) -> Result<(), LexingError> {
+    let var = var_types::parse_var(lexer)?;
+    let mut names = vec![var.name.to_owned()];
+
+    trace!("parsing port declaration");
+
+    // Additional names share the type and width of the first, e.g. `input [7:0] a, b;`
+    while lexer.slice() == "," {
+        match lexer.next() {
+            Some(Ok(Token::Word)) | Some(Ok(Token::EscapedIdentifier(_))) => {
+                names.push(parse_name(lexer)?)
+            }
+            Some(Ok(Token::WhiteSpace)) | Some(Ok(Token::Newline)) => (),
+            Some(Ok(Token::LineComment)) => parse_comment(lexer)?,
+            Some(Ok(Token::BlockCommentStart)) => parse_block_comment(lexer)?,
+            Some(Err(e)) => {
+                error!(
+                    "unexpected error occurred parsing port declaration: '{}'",
+                    lexer.slice()
+                );
+                return Err(e);
+            }
+            Some(Ok(token)) => {
+                error!("expected port name, got {:?}", token);
+                return Err(LexingError::UnexpectedToken);
+            }
+            None => return Err(LexingError::UnexpectedToken),
+        }
+    }
+
+    for name in names {
+        io.declare_port(
+            direction,
+            Var {
+                name,
+                ..var.clone()
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Produces a standalone SystemVerilog module header for a module's ports
+///
+/// The header lists inputs, then outputs, then inouts, each sorted by name,
+/// for example:
+///
+/// ```text
+/// module modname (
+///   input wire [7:0] data_in,
+///   output reg [7:0] data_out
+/// );
+/// ```
+///
+/// Ports named in a non-ANSI header but never declared have no direction
+/// and are left out
+pub fn extract_io_sv_string(module: &Module) -> String {
+    let mut inputs: Vec<&Var> = module.io.inputs.iter().map(|input| &input.var).collect();
+    let mut outputs: Vec<&Var> = module.io.outputs.iter().map(|output| &output.var).collect();
+    let mut inouts: Vec<&Var> = module.io.inouts.iter().map(|inout| &inout.var).collect();
+
+    inputs.sort_by(|a, b| a.name.cmp(&b.name));
+    outputs.sort_by(|a, b| a.name.cmp(&b.name));
+    inouts.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for port in &module.io.ports {
+        warn!("port '{}' has no direction and is left out of the header", port);
+    }
+
+    let ports: Vec<String> = [("input", inputs), ("output", outputs), ("inout", inouts)]
+        .into_iter()
+        .flat_map(|(direction, vars)| {
+            vars.into_iter()
+                .map(move |var| format!("  {} {}", direction, var_types::var_sv_string(var)))
+        })
+        .collect();
+
+    if ports.is_empty() {
+        return format!("module {} ();", var_types::sv_identifier(&module.name));
+    }
+
+    format!(
+        "module {} (\n{}\n);",
+        var_types::sv_identifier(&module.name),
+        ports.join(",\n")
+    )
+}
+
+/// Direction of a module port
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PortDirection {
+    Input,
+    Output,
+    Inout,
+}
+
 /// Module I/O information
 ///
 /// Stores all inputs, outputs, and inouts for a given module
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct ModuleIO {
     /// Module name
     pub name: String,
 
+    /// Parameters declared in the `#(...)` list of the module header
+    pub params: Vec<Param>,
+
+    /// Port names listed in a non-ANSI style header that have not yet been
+    /// declared in the module body
+    pub ports: Vec<String>,
+
+    /// Names of every port listed in the header, in header order
+    pub port_order: Vec<String>,
+
     /// Module inputs
     pub inputs: Vec<Input>,
 
@@ -114,11 +879,83 @@ pub struct ModuleIO {
 
     // Module combination input/outputs
     pub inouts: Vec<Inout>,
+
+    /// Ports typed as interfaces
+    pub interfaces: Vec<InterfacePort>,
+}
+
+impl ModuleIO {
+    /// Finds a declared port by name, returning its direction and variable
+    pub fn port(&self, name: &str) -> Option<(PortDirection, &Var)> {
+        let inputs = self.inputs.iter().map(|p| (PortDirection::Input, &p.var));
+        let outputs = self.outputs.iter().map(|p| (PortDirection::Output, &p.var));
+        let inouts = self.inouts.iter().map(|p| (PortDirection::Inout, &p.var));
+
+        inputs
+            .chain(outputs)
+            .chain(inouts)
+            .find(|(_, var)| var.name == name)
+    }
+
+    /// Finds a port typed as an interface by name
+    pub fn interface(&self, name: &str) -> Option<&InterfacePort> {
+        self.interfaces
+            .iter()
+            .find(|interface| interface.name == name)
+    }
+
+    /// Applies a port declaration from the module body
+    ///
+    /// A port only named in the header is added with the given direction,
+    /// while an already declared port has its type and width updated
+    fn declare_port(&mut self, direction: PortDirection, var: Var) -> Result<(), LexingError> {
+        if let Some(idx) = self.ports.iter().position(|port| *port == var.name) {
+            self.ports.remove(idx);
+
+            let name = var.name.to_owned();
+            match direction {
+                PortDirection::Input => self.inputs.push(Input { name, var }),
+                PortDirection::Output => self.outputs.push(Output { name, var }),
+                PortDirection::Inout => self.inouts.push(Inout { name, var }),
+            }
+
+            return Ok(());
+        }
+
+        let existing = self
+            .inputs
+            .iter_mut()
+            .map(|input| &mut input.var)
+            .chain(self.outputs.iter_mut().map(|output| &mut output.var))
+            .chain(self.inouts.iter_mut().map(|inout| &mut inout.var))
+            .find(|port| port.name == var.name);
+
+        match existing {
+            Some(port) => {
+                port.var_type = var.var_type;
+                port.width = var.width;
+                port.lsb = var.lsb;
+                port.signed = var.signed;
+                port.state = var.state;
+                Ok(())
+            }
+            None => {
+                error!("port '{}' is not listed in the module header", var.name);
+                Err(LexingError::UndeclaredPortReference(var.name))
+            }
+        }
+    }
 }
 
 impl fmt::Debug for ModuleIO {
     fn fmt(&self, _: &mut std::fmt::Formatter) -> fmt::Result {
         debug!("MODULE I/O: {:?}", self.name);
+        for param in self.params.clone() {
+            debug!("PARAM: {:?}", param);
+        }
+        for port in self.ports.clone() {
+            debug!("UNDECLARED PORT: {:?}", port);
+        }
         for input in self.inputs.clone() {
             debug!("IO: {:?}", input);
         }
@@ -128,12 +965,21 @@ impl fmt::Debug for ModuleIO {
         for inout in self.inouts.clone() {
             debug!("IO: {:?}", inout);
         }
+        for interface in &self.interfaces {
+            debug!("IO: {:?}", interface);
+        }
         Ok(())
     }
 }
 
 /// Parses a module I/O block to completion
-fn parse_module_io<'source>(lexer: &mut Lexer<'source, Token>) -> Result<ModuleIO, LexingError> {
+///
+/// Package imports between the module name and the port list are added to
+/// `imports`
+pub(crate) fn parse_module_io<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    imports: &mut Vec<ImportDecl>,
+) -> Result<ModuleIO, LexingError> {
     #[derive(Default)]
     enum State {
         #[default]
@@ -148,6 +994,11 @@ fn parse_module_io<'source>(lexer: &mut Lexer<'source, Token>) -> Result<ModuleI
     let mut inputs: Vec<Input> = Vec::new();
     let mut outputs: Vec<Output> = Vec::new();
     let mut inouts: Vec<Inout> = Vec::new();
+    let mut interfaces: Vec<InterfacePort> = Vec::new();
+    let mut ports: Vec<String> = Vec::new();
+    let mut port_order: Vec<String> = Vec::new();
+    let mut params: Vec<Param> = Vec::new();
+    let mut complete = false;
 
     trace!("parsing module I/O");
 
@@ -155,11 +1006,17 @@ fn parse_module_io<'source>(lexer: &mut Lexer<'source, Token>) -> Result<ModuleI
         match state {
             State::Name => match token {
                 Ok(Token::Word) => {
-                    name = lexer.slice().to_owned();
+                    name = parse_ident(lexer);
+                    state = State::Paren;
+                }
+                Ok(Token::EscapedIdentifier(val)) => {
+                    name = val;
                     state = State::Paren;
                 }
                 Ok(Token::WhiteSpace) => (),
                 Ok(Token::Newline) => (),
+                Ok(Token::LineComment) => parse_comment(lexer)?,
+                Ok(Token::BlockCommentStart) => parse_block_comment(lexer)?,
                 Err(e) => {
                     error!(
                         "unexpected error occurred parsing module name: '{}'",
@@ -167,12 +1024,24 @@ fn parse_module_io<'source>(lexer: &mut Lexer<'source, Token>) -> Result<ModuleI
                     );
                     return Err(e);
                 }
-                _ => error!("expected module name, got {:?}", token.unwrap()),
+                _ => {
+                    error!("expected module name, got {:?}", token.unwrap());
+                    return Err(LexingError::UnexpectedToken);
+                }
             },
             State::Paren => match token {
                 Ok(Token::OpenParen) => state = State::IO,
+                Ok(Token::Pound) => params.append(&mut parse_parameter_ports(lexer)?),
+                Ok(Token::Import) => imports.append(&mut parse_import(lexer)?),
+                // Modules without ports may leave out the port list
+                Ok(Token::Semicolon) => {
+                    complete = true;
+                    break;
+                }
                 Ok(Token::WhiteSpace) => (),
                 Ok(Token::Newline) => (),
+                Ok(Token::LineComment) => parse_comment(lexer)?,
+                Ok(Token::BlockCommentStart) => parse_block_comment(lexer)?,
                 Err(e) => {
                     error!(
                         "unexpected error occurred parsing module open paren: '{}'",
@@ -180,30 +1049,70 @@ fn parse_module_io<'source>(lexer: &mut Lexer<'source, Token>) -> Result<ModuleI
                     );
                     return Err(e);
                 }
-                _ => error!("expected '(', got {:?}", token.unwrap()),
-            },
-            State::IO => match token {
-                Ok(Token::Input) => inputs.push(parse_input(lexer)?),
-                Ok(Token::Output) => outputs.push(parse_output(lexer)?),
-                Ok(Token::Inout) => inouts.push(parse_inout(lexer)?),
-                Ok(Token::Comment) => parse_comment(lexer)?,
-                Ok(Token::CloseParen) => state = State::Semi,
-                Ok(Token::WhiteSpace) => (),
-                Ok(Token::Newline) => (),
-                Err(e) => {
-                    error!(
-                        "unexpected error occurred parsing module: '{}'",
-                        lexer.slice()
-                    );
-                    return Err(e);
+                _ => {
+                    error!("expected '(' or ';', got {:?}", token.unwrap());
+                    return Err(LexingError::UnexpectedToken);
                 }
-                _ => error!("expected I/O declaration or ')', got {:?}", token.unwrap()),
             },
+            State::IO => {
+                match token {
+                    Ok(Token::Input) => {
+                        let input = parse_input(lexer)?;
+                        port_order.push(input.name.to_owned());
+                        inputs.push(input);
+                    }
+                    Ok(Token::Output) => {
+                        let output = parse_output(lexer)?;
+                        port_order.push(output.name.to_owned());
+                        outputs.push(output);
+                    }
+                    Ok(Token::Inout) => {
+                        let inout = parse_inout(lexer)?;
+                        port_order.push(inout.name.to_owned());
+                        inouts.push(inout);
+                    }
+                    Ok(Token::Word) | Ok(Token::EscapedIdentifier(_)) => {
+                        match parse_interface_port(lexer)? {
+                            Some(interface) => {
+                                port_order.push(interface.name.to_owned());
+                                interfaces.push(interface);
+                            }
+                            None => {
+                                let name = parse_name(lexer)?;
+                                port_order.push(name.to_owned());
+                                ports.push(name);
+                            }
+                        }
+                    }
+                    Ok(Token::LineComment) => parse_comment(lexer)?,
+                    Ok(Token::BlockCommentStart) => parse_block_comment(lexer)?,
+                    Ok(Token::CloseParen) => (),
+                    Ok(Token::WhiteSpace) => (),
+                    Ok(Token::Newline) => (),
+                    Err(e) => {
+                        error!(
+                            "unexpected error occurred parsing module: '{}'",
+                            lexer.slice()
+                        );
+                        return Err(e);
+                    }
+                    _ => error!("expected I/O declaration or ')', got {:?}", token.unwrap()),
+                }
+
+                // The last port name in the list is terminated by the closing paren
+                if lexer.slice() == ")" {
+                    state = State::Semi;
+                }
+            }
             State::Semi => match token {
-                Ok(Token::Semicolon) => break,
+                Ok(Token::Semicolon) => {
+                    complete = true;
+                    break;
+                }
                 Ok(Token::WhiteSpace) => (),
                 Ok(Token::Newline) => (),
-                Ok(Token::Comment) => parse_comment(lexer)?,
+                Ok(Token::LineComment) => parse_comment(lexer)?,
+                Ok(Token::BlockCommentStart) => parse_block_comment(lexer)?,
                 Err(e) => {
                     error!(
                         "unexpected error occurred parsing module semicolon: '{}'",
@@ -211,15 +1120,27 @@ fn parse_module_io<'source>(lexer: &mut Lexer<'source, Token>) -> Result<ModuleI
                     );
                     return Err(e);
                 }
-                _ => error!("expected ';', got {:?}", token.unwrap()),
+                _ => {
+                    error!("expected ';', got {:?}", token.unwrap());
+                    return Err(LexingError::ExpectedSemi);
+                }
             },
         };
     }
 
+    if !complete {
+        error!("module '{}' ends before its header is complete", name);
+        return Err(LexingError::UnexpectedToken);
+    }
+
     Ok(ModuleIO {
         name,
+        params,
+        ports,
+        port_order,
         inputs,
         outputs,
         inouts,
+        interfaces,
     })
 }
@@ -0,0 +1,8 @@
+/// Value Change Dump waveform output
+pub mod vcd;
+
+/// JSON waveform output
+pub mod json;
+
+/// JUnit XML test reports
+pub mod junit;
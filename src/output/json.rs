@@ -0,0 +1,139 @@
+use crate::sim::{SignalChange, SimResult};
+use crate::value::{to_binary_string, LogicValue};
+use crate::SimError;
+use log::trace;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Writes a simulation result as a JSON file at `path`
+///
+/// See `JsonWriter::write_result` for the contents of the file
+pub fn write_json(result: &SimResult, path: &Path) -> Result<(), SimError> {
+    trace!("writing JSON to {:?}", path);
+
+    let mut writer = JsonWriter::new(BufWriter::new(File::create(path)?));
+    writer.write_result(result)?;
+    writer.into_inner().flush()?;
+
+    Ok(())
+}
+
+/// JSON waveform writer
+///
+/// Writes a single object holding the timescale, end time, final value of
+/// every signal and every value change. Times are given in seconds and
+/// values as binary strings, most significant bit first, as in
+///
+/// ```text
+/// {
+///   "timescale": {"unit": 1e-9, "precision": 1e-12},
+///   "end_time": 1e-8,
+///   "signals": {
+///     "top.count": {"width": 4, "value": "0011"}
+///   },
+///   "changes": [
+///     {"time": 1e-9, "signal": "top.count", "value": "0000"}
+///   ]
+/// }
+/// ```
+pub struct JsonWriter<W: Write> {
+    /// Output the result is written to
+    out: W,
+}
+
+impl<W: Write> JsonWriter<W> {
+    /// Creates a writer
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+
+    /// Writes a complete simulation result
+    pub fn write_result(&mut self, result: &SimResult) -> Result<(), SimError> {
+        writeln!(self.out, "{{")?;
+        writeln!(
+            self.out,
+            "  \"timescale\": {{\"unit\": {:e}, \"precision\": {:e}}},",
+            result.sim_time.n_time, result.sim_time.d_time
+        )?;
+        writeln!(self.out, "  \"end_time\": {:e},", result.end_time)?;
+
+        writeln!(self.out, "  \"signals\": {{")?;
+        for (idx, (name, value)) in result.signals.iter().enumerate() {
+            let sep = separator(idx, result.signals.len());
+            self.write_signal(name, value, sep)?;
+        }
+        writeln!(self.out, "  }},")?;
+
+        writeln!(self.out, "  \"changes\": [")?;
+        for (idx, change) in result.changes.iter().enumerate() {
+            let sep = separator(idx, result.changes.len());
+            self.write_change(change, sep)?;
+        }
+        writeln!(self.out, "  ]")?;
+        writeln!(self.out, "}}")?;
+
+        Ok(())
+    }
+
+    /// Returns the output the result was written to
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Writes the final value of a signal, followed by `sep`
+    fn write_signal(&mut self, name: &str, value: &LogicValue, sep: &str) -> Result<(), SimError> {
+        writeln!(
+            self.out,
+            "    {}: {{\"width\": {}, \"value\": \"{}\"}}{}",
+            json_string(name),
+            value.width(),
+            to_binary_string(value.bits()),
+            sep
+        )?;
+
+        Ok(())
+    }
+
+    /// Writes a value change, followed by `sep`
+    fn write_change(&mut self, change: &SignalChange, sep: &str) -> Result<(), SimError> {
+        writeln!(
+            self.out,
+            "    {{\"time\": {:e}, \"signal\": {}, \"value\": \"{}\"}}{}",
+            change.time,
+            json_string(&change.signal),
+            to_binary_string(change.value.bits()),
+            sep
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Separator following the `idx`th of `len` items
+fn separator(idx: usize, len: usize) -> &'static str {
+    match idx + 1 < len {
+        true => ",",
+        false => "",
+    }
+}
+
+/// Formats a string as a quoted JSON string
+///
+/// Escaped SystemVerilog identifiers may hold quotes, backslashes and
+/// control characters, which are escaped
+fn json_string(val: &str) -> String {
+    let mut out = String::from("\"");
+
+    for c in val.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+
+    out
+}
@@ -0,0 +1,99 @@
+use crate::sim::TestResult;
+use crate::SimError;
+use log::trace;
+use quick_xml::events::{BytesDecl, Event};
+use quick_xml::Writer;
+use std::fs;
+use std::path::Path;
+
+/// Writes the results of a testbench run as a JUnit XML file at `path`
+///
+/// See `junit_string` for the contents of the file
+pub fn write_junit(results: &[TestResult], suite: &str, path: &Path) -> Result<(), SimError> {
+    trace!("writing JUnit XML to {:?}", path);
+
+    fs::write(path, junit_string(results, suite)?)?;
+
+    Ok(())
+}
+
+/// Produces a JUnit XML report of the results of a testbench run
+///
+/// The report holds a single test suite named `suite`, with a test case for
+/// each result. Times are given in seconds, and a failed test case holds a
+/// failure describing why, as in
+///
+/// ```text
+/// <testsuites tests="2" failures="1" time="0.002000">
+///   <testsuite name="tb" tests="2" failures="1" time="0.002000">
+///     <testcase name="tb.reset" classname="tb" time="0.001000"/>
+///     <testcase name="tb.overflow" classname="tb" time="0.001000">
+///       <failure message="test ended because $stop was called rather than by $finish"/>
+///     </testcase>
+///   </testsuite>
+/// </testsuites>
+/// ```
+pub fn junit_string(results: &[TestResult], suite: &str) -> Result<String, SimError> {
+    let tests = results.len().to_string();
+    let failures = results
+        .iter()
+        .filter(|result| !result.passed)
+        .count()
+        .to_string();
+    let time = seconds(results.iter().map(|result| result.duration_ns).sum());
+
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    writer
+        .create_element("testsuites")
+        .with_attribute(("tests", tests.as_str()))
+        .with_attribute(("failures", failures.as_str()))
+        .with_attribute(("time", time.as_str()))
+        .write_inner_content(|writer| {
+            writer
+                .create_element("testsuite")
+                .with_attribute(("name", suite))
+                .with_attribute(("tests", tests.as_str()))
+                .with_attribute(("failures", failures.as_str()))
+                .with_attribute(("time", time.as_str()))
+                .write_inner_content(|writer| {
+                    for result in results {
+                        let classname = match result.name.rsplit_once('.') {
+                            Some((module, _)) => module,
+                            None => suite,
+                        };
+                        let case = writer
+                            .create_element("testcase")
+                            .with_attribute(("name", result.name.as_str()))
+                            .with_attribute(("classname", classname))
+                            .with_attribute(("time", seconds(result.duration_ns).as_str()));
+
+                        match &result.failure {
+                            Some(failure) => {
+                                let message = failure.to_string();
+                                case.write_inner_content(|writer| {
+                                    writer
+                                        .create_element("failure")
+                                        .with_attribute(("message", message.as_str()))
+                                        .write_empty()?;
+                                    Ok(())
+                                })?;
+                            }
+                            None => {
+                                case.write_empty()?;
+                            }
+                        }
+                    }
+                    Ok(())
+                })?;
+            Ok(())
+        })?;
+
+    Ok(String::from_utf8_lossy(&writer.into_inner()).into_owned())
+}
+
+/// Formats a duration in nanoseconds as seconds
+fn seconds(duration_ns: f64) -> String {
+    format!("{:.6}", duration_ns / 1e9)
+}
@@ -0,0 +1,350 @@
+use crate::sim::{Dump, SignalChange, SimResult};
+use crate::sim_time::{SimTime, TimeUnit};
+use crate::value::{to_binary_string, LogicValue};
+use crate::SimError;
+use log::{trace, warn};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Time units from largest to smallest, for picking the `$timescale` unit
+const UNITS: [TimeUnit; 6] = [
+    TimeUnit::Seconds,
+    TimeUnit::Milliseconds,
+    TimeUnit::Microseconds,
+    TimeUnit::Nanoseconds,
+    TimeUnit::Picoseconds,
+    TimeUnit::Femtoseconds,
+];
+
+/// Writes a simulation result as a VCD file at `path`
+///
+/// See `VcdWriter::write_result` for the contents of the file
+pub fn write_vcd(result: &SimResult, path: &Path) -> Result<(), SimError> {
+    trace!("writing VCD to {:?}", path);
+
+    let mut writer = VcdWriter::new(BufWriter::new(File::create(path)?), &result.sim_time);
+    writer.write_result(result)?;
+    writer.into_inner().flush()?;
+
+    Ok(())
+}
+
+/// Writes the waveform dump a design requested with `$dumpfile` and
+/// `$dumpvars` to the file it named
+///
+/// Only the signals within the dumped scopes are written. Changes made
+/// before the dump started are left out, and the values they left behind
+/// are dumped at the start time instead. Every signal is dumped as X while
+/// the dump is paused by `$dumpoff`, and goes back to its value when
+/// `$dumpon` resumes it
+pub fn write_dump(result: &SimResult, dump: &Dump) -> Result<(), SimError> {
+    trace!("writing the requested dump to {:?}", dump.file);
+
+    let signals: BTreeMap<String, LogicValue> = result
+        .signals
+        .iter()
+        .filter(|(name, _)| dump.includes(name))
+        .map(|(name, value)| (name.to_owned(), value.clone()))
+        .collect();
+    let toggles = std::iter::once((dump.start, true)).chain(
+        dump.pauses
+            .iter()
+            .flat_map(|(off, on)| [(*off, false), (*on, true)])
+            .filter(|(time, _)| time.is_finite()),
+    );
+
+    let mut events = result
+        .changes
+        .iter()
+        .filter(|change| signals.contains_key(&change.signal))
+        .peekable();
+    let mut values: BTreeMap<&str, &LogicValue> = BTreeMap::new();
+    let mut changes: Vec<SignalChange> = Vec::new();
+    let mut on = false;
+    for (index, (time, turn_on)) in toggles.enumerate() {
+        while let Some(change) = events.next_if(|change| change.time < time) {
+            values.insert(&change.signal, &change.value);
+            if on {
+                changes.push(change.clone());
+            }
+        }
+
+        // Signals that never changed already start on their value
+        let dumped: Vec<(&str, LogicValue)> = match (index, turn_on) {
+            (0, _) => values
+                .iter()
+                .map(|(name, value)| (*name, (*value).clone()))
+                .collect(),
+            (_, true) => signals
+                .iter()
+                .map(|(name, value)| {
+                    let value = values.get(name.as_str()).copied().unwrap_or(value);
+                    (name.as_str(), value.clone())
+                })
+                .collect(),
+            (_, false) => signals
+                .iter()
+                .map(|(name, value)| (name.as_str(), LogicValue::new(value.width())))
+                .collect(),
+        };
+        changes.extend(dumped.into_iter().map(|(signal, value)| SignalChange {
+            time,
+            signal: signal.to_owned(),
+            value,
+        }));
+        on = turn_on;
+    }
+    if on {
+        changes.extend(events.cloned());
+    }
+
+    let dumped = SimResult {
+        signals,
+        changes,
+        ..result.clone()
+    };
+
+    write_vcd(&dumped, &dump.file)
+}
+
+/// Value Change Dump writer
+///
+/// Writes a header declaring every signal, followed by the value changes
+/// made during simulation. Hierarchical signal names such as `top.u_add.sum`
+/// are declared as `sum` within nested `top` and `u_add` scopes
+pub struct VcdWriter<W: Write> {
+    /// Output the dump is written to
+    out: W,
+
+    /// Length of one VCD time step, in seconds
+    precision: f64,
+
+    /// `$timescale` of the dump, as in `1ps`
+    timescale: String,
+
+    /// Identifier code of every declared signal
+    ids: HashMap<String, String>,
+
+    /// Last time written, in time steps
+    time: Option<u64>,
+}
+
+/// Signals declared within a single scope
+#[derive(Default)]
+struct Scope<'a> {
+    /// Signals declared directly in the scope, as `(name, hierarchical name,
+    /// width)`
+    vars: Vec<(&'a str, &'a str, u64)>,
+
+    /// Nested scopes by name
+    scopes: BTreeMap<&'a str, Scope<'a>>,
+}
+
+impl<W: Write> VcdWriter<W> {
+    /// Creates a writer using the precision of `sim_time` as its time step
+    pub fn new(out: W, sim_time: &SimTime) -> Self {
+        Self {
+            out,
+            precision: sim_time.d_time,
+            timescale: timescale_string(sim_time.d_time),
+            ids: HashMap::new(),
+            time: None,
+        }
+    }
+
+    /// Writes a complete dump of a simulation result
+    ///
+    /// Signals that never changed are dumped with their final value, and
+    /// every other signal starts out as X. Only the value a signal settles
+    /// on in each time step is dumped, so a glitch that comes and goes
+    /// within one step leaves no trace. The dump ends at the time the run
+    /// stopped
+    pub fn write_result(&mut self, result: &SimResult) -> Result<(), SimError> {
+        let initial: BTreeMap<String, LogicValue> = result
+            .signals
+            .iter()
+            .map(|(name, value)| {
+                let changed = result.changes.iter().any(|change| change.signal == *name);
+                let value = match changed {
+                    true => LogicValue::new(value.width()),
+                    false => value.clone(),
+                };
+
+                (name.to_owned(), value)
+            })
+            .collect();
+
+        self.write_header(&initial)?;
+        for change in self.settled_changes(&result.changes, &initial) {
+            self.write_change(change)?;
+        }
+        self.write_time(result.end_time)
+    }
+
+    /// Last change to each signal in each time step, in order, leaving out
+    /// those that end the step on the value the signal started it with
+    fn settled_changes<'a>(
+        &self,
+        changes: &'a [SignalChange],
+        initial: &'a BTreeMap<String, LogicValue>,
+    ) -> Vec<&'a SignalChange> {
+        let mut values: HashMap<&str, &LogicValue> = initial
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+            .collect();
+        let mut settled: Vec<&SignalChange> = Vec::new();
+
+        for step in changes.chunk_by(|a, b| self.steps(a.time) == self.steps(b.time)) {
+            let last: HashMap<&str, usize> = step
+                .iter()
+                .enumerate()
+                .map(|(index, change)| (change.signal.as_str(), index))
+                .collect();
+
+            for (index, change) in step.iter().enumerate() {
+                if last[change.signal.as_str()] == index
+                    && values.insert(&change.signal, &change.value) != Some(&change.value)
+                {
+                    settled.push(change);
+                }
+            }
+        }
+
+        settled
+    }
+
+    /// Writes the header, declaring every signal and dumping its initial
+    /// value at time zero
+    pub fn write_header(&mut self, signals: &BTreeMap<String, LogicValue>) -> Result<(), SimError> {
+        writeln!(
+            self.out,
+            "$version sv-sim {} $end",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        writeln!(self.out, "$timescale {} $end", self.timescale)?;
+
+        let mut root = Scope::default();
+        for (index, (name, value)) in signals.iter().enumerate() {
+            self.ids.insert(name.to_owned(), id_code(index));
+
+            let mut parts: Vec<&str> = name.split('.').collect();
+            let var = parts.pop().unwrap_or_default();
+            let scope = parts.into_iter().fold(&mut root, |scope, part| {
+                scope.scopes.entry(part).or_default()
+            });
+            scope.vars.push((var, name, value.width()));
+        }
+        self.write_scope(&root)?;
+        writeln!(self.out, "$enddefinitions $end")?;
+
+        writeln!(self.out, "#0")?;
+        self.time = Some(0);
+        writeln!(self.out, "$dumpvars")?;
+        for (name, value) in signals {
+            self.write_value(name, value)?;
+        }
+        writeln!(self.out, "$end")?;
+
+        Ok(())
+    }
+
+    /// Writes a value change, preceded by its time if it differs from the
+    /// time of the last change
+    ///
+    /// Changes must be written in time order, after the header
+    pub fn write_change(&mut self, change: &SignalChange) -> Result<(), SimError> {
+        self.write_time(change.time)?;
+        self.write_value(&change.signal, &change.value)
+    }
+
+    /// Returns the output the dump was written to
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Writes a `#time` line, unless the time was the last one written
+    fn write_time(&mut self, time: f64) -> Result<(), SimError> {
+        let steps = self.steps(time);
+
+        if self.time.is_some_and(|last| last >= steps) {
+            return Ok(());
+        }
+
+        writeln!(self.out, "#{}", steps)?;
+        self.time = Some(steps);
+
+        Ok(())
+    }
+
+    /// Time in VCD time steps
+    fn steps(&self, time: f64) -> u64 {
+        (time / self.precision).round() as u64
+    }
+
+    /// Writes a single value, as `1!` for one bit or `b1010 !` for several
+    fn write_value(&mut self, name: &str, value: &LogicValue) -> Result<(), SimError> {
+        let Some(id) = self.ids.get(name) else {
+            warn!("'{}' was not declared in the VCD header", name);
+            return Ok(());
+        };
+
+        match value.width() {
+            1 => writeln!(self.out, "{}{}", value.get_bit(0), id)?,
+            _ => writeln!(self.out, "b{} {}", to_binary_string(value.bits()), id)?,
+        }
+
+        Ok(())
+    }
+
+    /// Writes the declarations of a scope's signals, then its nested scopes
+    fn write_scope(&mut self, scope: &Scope) -> Result<(), SimError> {
+        for (var, name, width) in &scope.vars {
+            writeln!(
+                self.out,
+                "$var wire {} {} {} $end",
+                width, self.ids[*name], var
+            )?;
+        }
+
+        for (name, nested) in &scope.scopes {
+            writeln!(self.out, "$scope module {} $end", name)?;
+            self.write_scope(nested)?;
+            writeln!(self.out, "$upscope $end")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Formats a time step length as a `$timescale`, as in `10ps`
+fn timescale_string(precision: f64) -> String {
+    let unit = UNITS
+        .iter()
+        .find(|unit| precision >= unit.multiplier() * (1. - 1e-9))
+        .unwrap_or(&TimeUnit::Femtoseconds);
+
+    format!(
+        "{}{}",
+        (precision / unit.multiplier()).round(),
+        unit.suffix()
+    )
+}
+
+/// Identifier code of the signal declared `index`th, using the printable
+/// ASCII characters `!` to `~`
+fn id_code(index: usize) -> String {
+    let mut code = String::new();
+    let mut index = index;
+
+    loop {
+        code.push(char::from(b'!' + (index % 94) as u8));
+        index /= 94;
+
+        if index == 0 {
+            return code;
+        }
+        index -= 1;
+    }
+}
@@ -0,0 +1,111 @@
+use crate::eval::ConstLookup;
+use crate::expr::parse_ident;
+use crate::function::FunctionDef;
+use crate::interface::parse_end_label;
+use crate::module::{parse_module_item, Module};
+use crate::param::Param;
+use crate::value::LogicValue;
+use crate::{expect_token, next_token, LexingError, Token};
+use log::{error, trace, warn};
+use logos::Lexer;
+use std::collections::HashMap;
+
+/// SystemVerilog package, holding parameters and functions that modules
+/// import by name or all at once
+#[derive(Debug, Default, Clone)]
+pub struct Package {
+    /// Package name
+    pub name: String,
+
+    /// Parameters declared in the package, which are constants whether they
+    /// are declared as a `parameter` or a `localparam`
+    pub params: Vec<Param>,
+
+    /// Function definitions
+    pub functions: Vec<FunctionDef>,
+}
+
+impl Package {
+    /// Values of the package's parameters, by name
+    ///
+    /// Each parameter may use the parameters declared before it and the
+    /// package's functions
+    pub fn param_values(&self) -> HashMap<String, LogicValue> {
+        let mut values: HashMap<String, LogicValue> = HashMap::new();
+
+        for param in &self.params {
+            let lookup = ConstLookup {
+                values: &values,
+                functions: &self.functions,
+            };
+            let value = param.resolve(&HashMap::new(), &lookup);
+            values.insert(param.name.to_owned(), value);
+        }
+
+        values
+    }
+
+    /// Checks if the package declares a parameter or function named `name`
+    pub fn declares(&self, name: &str) -> bool {
+        self.params.iter().any(|param| param.name == name)
+            || self.functions.iter().any(|function| function.name == name)
+    }
+}
+
+/// Parses a package to completion
+///
+/// The `package` keyword has already been consumed. Packages only export
+/// parameters and functions, so any other item in the body is left out.
+/// A label after `endpackage` must repeat the package name
+pub fn parse_package<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Package, LexingError> {
+    trace!("parsing package");
+
+    let name = match next_token(lexer) {
+        Some(Ok(Token::Word)) | Some(Ok(Token::EscapedIdentifier(_))) => parse_ident(lexer),
+        _ => {
+            error!("expected package name: '{}'", lexer.slice());
+            return Err(LexingError::UnexpectedToken);
+        }
+    };
+    expect_token(lexer, Token::Semicolon, "package")?;
+
+    let mut items = Module {
+        name,
+        ..Default::default()
+    };
+
+    loop {
+        match lexer.next() {
+            Some(Ok(Token::EndPackage)) => {
+                parse_end_label(lexer, "endpackage", &items.name)?;
+                break;
+            }
+            Some(token) => parse_module_item(lexer, token, &mut items)?,
+            None => {
+                error!("package '{}' not closed with 'endpackage'", items.name);
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    }
+
+    let has_other_items = !(items.vars.is_empty()
+        && items.assigns.is_empty()
+        && items.comb_blocks.is_empty()
+        && items.ff_blocks.is_empty()
+        && items.latch_blocks.is_empty()
+        && items.initial_blocks.is_empty()
+        && items.final_blocks.is_empty()
+        && items.instances.is_empty());
+    if has_other_items {
+        warn!(
+            "only parameters and functions of package '{}' can be imported",
+            items.name
+        );
+    }
+
+    Ok(Package {
+        name: items.name,
+        params: items.params,
+        functions: items.functions,
+    })
+}
@@ -0,0 +1,418 @@
+use crate::eval::{const_index, eval_expr, eval_expr_sized, ValueLookup};
+use crate::expr::{parse_expr, parse_ident, Expr};
+use crate::module::Module;
+use crate::value::{LogicValue, MAX_WIDTH};
+use crate::var_types::{parse_width, TypeExpr, VarType};
+use crate::{expect_token, next_token, peek_token, LexingError, Token};
+use log::{error, trace};
+use logos::Lexer;
+use std::collections::HashMap;
+
+/// Parameter kind
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamType {
+    /// `parameter`, which may be overridden when the module is instantiated
+    #[default]
+    Parameter,
+
+    /// `localparam`, which may not be overridden
+    Localparam,
+}
+
+impl From<&ParamType> for &str {
+    fn from(val: &ParamType) -> Self {
+        match val {
+            ParamType::Parameter => "parameter",
+            ParamType::Localparam => "localparam",
+        }
+    }
+}
+
+/// Parameter declaration, as in `parameter WIDTH = 8`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    /// Parameter name
+    pub name: String,
+
+    /// Default value, which may be any constant expression
+    pub value: Expr,
+
+    /// Parameter kind
+    pub param_type: ParamType,
+
+    /// Declared data type, as the `int` of `parameter int N = 4` or the
+    /// `[3:0]` of `parameter [3:0] MASK = 15`, if any
+    pub data_type: Option<TypeExpr>,
+}
+
+impl Param {
+    /// Value of the parameter, taken from `overrides` unless it is a
+    /// `localparam` and otherwise from its default value
+    ///
+    /// A parameter with a data type takes the width of the type, with its
+    /// value truncated or extended to fit. Without one, or if the bounds of
+    /// its range are not known, it takes the width of its value
+    pub fn resolve(
+        &self,
+        overrides: &HashMap<String, LogicValue>,
+        lookup: &impl ValueLookup,
+    ) -> LogicValue {
+        let width = self.data_type.as_ref().and_then(|data_type| {
+            let Some(bounds) = &data_type.bounds else {
+                return Some(data_type.width);
+            };
+            let (left, right) = (
+                const_index(&bounds.left, lookup)?,
+                const_index(&bounds.right, lookup)?,
+            );
+            Some(left.abs_diff(right).saturating_add(1).min(MAX_WIDTH))
+        });
+
+        match (overrides.get(&self.name), width) {
+            (Some(value), Some(width)) if self.param_type != ParamType::Localparam => {
+                value.resize(width)
+            }
+            (Some(value), None) if self.param_type != ParamType::Localparam => value.clone(),
+            (_, Some(width)) => eval_expr_sized(&self.value, width, lookup),
+            (_, None) => eval_expr(&self.value, lookup),
+        }
+    }
+}
+
+/// Type parameter declaration, as in `localparam type T = logic [7:0]`
+///
+/// Variables declared with the type take its width and signedness. A
+/// `parameter type` keeps its default type, as instances cannot override it
+#[derive(Debug, Clone)]
+pub struct LocalparamType {
+    /// Type name
+    pub name: String,
+
+    /// Type the name stands for
+    pub type_expr: TypeExpr,
+
+    /// Parameter kind
+    pub param_type: ParamType,
+}
+
+impl Module {
+    /// Type given to the type parameter `name`, of either kind, if the
+    /// module declares one
+    pub fn type_parameter(&self, name: &str) -> Option<&TypeExpr> {
+        self.type_params
+            .iter()
+            .find(|param| param.name == name)
+            .map(|param| &param.type_expr)
+    }
+
+    /// Parameters that may be overridden, from the module header and body,
+    /// in declaration order
+    pub fn parameters(&self) -> Vec<&Param> {
+        self.declared_params(ParamType::Parameter).collect()
+    }
+
+    /// Checks if the module declares a parameter called `name` that may be
+    /// overridden
+    pub fn has_parameter(&self, name: &str) -> bool {
+        self.parameter_default(name).is_some()
+    }
+
+    /// Default value of the parameter `name`, if the module declares one
+    /// that may be overridden
+    pub fn parameter_default(&self, name: &str) -> Option<&Expr> {
+        self.find_param(ParamType::Parameter, name)
+    }
+
+    /// Localparams, from the module header and body, in declaration order
+    pub fn localparams(&self) -> Vec<&Param> {
+        self.declared_params(ParamType::Localparam).collect()
+    }
+
+    /// Checks if the module declares a localparam called `name`
+    pub fn has_localparam(&self, name: &str) -> bool {
+        self.localparam_default(name).is_some()
+    }
+
+    /// Value of the localparam `name`, if the module declares one
+    pub fn localparam_default(&self, name: &str) -> Option<&Expr> {
+        self.find_param(ParamType::Localparam, name)
+    }
+
+    /// Parameters of the given kind, in declaration order
+    fn declared_params(&self, param_type: ParamType) -> impl Iterator<Item = &Param> {
+        self.io
+            .params
+            .iter()
+            .chain(&self.params)
+            .filter(move |param| param.param_type == param_type)
+    }
+
+    /// Value of the parameter of the given kind called `name`
+    fn find_param(&self, param_type: ParamType, name: &str) -> Option<&Expr> {
+        self.declared_params(param_type)
+            .find(|param| param.name == name)
+            .map(|param| &param.value)
+    }
+}
+
+/// Parses a parameter declaration in a module body to completion
+///
+/// The `parameter` or `localparam` keyword has already been consumed. A
+/// single declaration may hold several comma separated parameters, as in
+/// `localparam A = 1, B = A + 1;`
+pub fn parse_parameter<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    param_type: ParamType,
+) -> Result<Vec<Param>, LexingError> {
+    let mut params: Vec<Param> = Vec::new();
+
+    trace!("parsing {}", <&str>::from(&param_type));
+
+    loop {
+        params.push(parse_param_assignment(lexer, param_type)?);
+
+        match next_token(lexer) {
+            Some(Ok(Token::Comma)) => (),
+            Some(Ok(Token::Semicolon)) => return Ok(params),
+            _ => {
+                error!("expected ';', got '{}'", lexer.slice());
+                return Err(LexingError::ExpectedSemi);
+            }
+        }
+    }
+}
+
+/// Parses a type parameter declaration in a module body to completion,
+/// adding every declared type to `module`
+///
+/// The `parameter` or `localparam` keyword has already been consumed. A
+/// single declaration may hold several comma separated types, as in
+/// `localparam type A = logic [3:0], B = A;`
+pub fn parse_type_parameter<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    param_type: ParamType,
+    module: &mut Module,
+) -> Result<(), LexingError> {
+    trace!("parsing {} type", <&str>::from(&param_type));
+
+    expect_token(lexer, Token::Type, "type parameter")?;
+
+    loop {
+        let name = match next_token(lexer) {
+            Some(Ok(Token::Word))
+            | Some(Ok(Token::Underscore))
+            | Some(Ok(Token::EscapedIdentifier(_))) => parse_ident(lexer),
+            _ => {
+                error!("expected type parameter name, got '{}'", lexer.slice());
+                return Err(LexingError::UnexpectedToken);
+            }
+        };
+        expect_token(lexer, Token::Equals, "type parameter")?;
+        let type_expr = parse_type_expr(lexer, module)?;
+
+        module.type_params.push(LocalparamType {
+            name,
+            type_expr,
+            param_type,
+        });
+
+        match lexer.slice() {
+            "," => (),
+            _ => return Ok(()),
+        }
+    }
+}
+
+/// Parses the type of a type parameter, up to and including the `,` or `;`
+/// following it
+///
+/// A type is a variable type, optionally signed or unsigned and with a
+/// range, as in `logic signed [7:0]`, or the name of a type parameter
+/// declared before it
+fn parse_type_expr<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    module: &Module,
+) -> Result<TypeExpr, LexingError> {
+    let mut type_expr: Option<TypeExpr> = None;
+    let mut signed: Option<bool> = None;
+    let mut range = None;
+
+    loop {
+        match next_token(lexer) {
+            Some(Ok(Token::Wire))
+            | Some(Ok(Token::Reg))
+            | Some(Ok(Token::Tri))
+            | Some(Ok(Token::Logic))
+            | Some(Ok(Token::Byte))
+            | Some(Ok(Token::ShortInt))
+            | Some(Ok(Token::Int))
+            | Some(Ok(Token::LongInt))
+            | Some(Ok(Token::IntegerType))
+                if type_expr.is_none() =>
+            {
+                type_expr = Some(TypeExpr::from(VarType::from(lexer.slice())))
+            }
+            Some(Ok(Token::Word))
+            | Some(Ok(Token::Underscore))
+            | Some(Ok(Token::EscapedIdentifier(_)))
+                if type_expr.is_none() =>
+            {
+                let name = parse_ident(lexer);
+                let Some(named) = module.type_parameter(&name) else {
+                    error!("type '{}' is not declared", name);
+                    return Err(LexingError::InvalidIdentifier(name));
+                };
+                type_expr = Some(named.clone());
+            }
+            Some(Ok(Token::Signed)) => signed = Some(true),
+            Some(Ok(Token::Unsigned)) => signed = Some(false),
+            Some(Ok(Token::OpenBracket)) => range = Some(parse_width(lexer)?),
+            Some(Ok(Token::Comma)) | Some(Ok(Token::Semicolon)) => break,
+            Some(Err(e)) => {
+                error!(
+                    "unexpected error occurred parsing type parameter: '{}'",
+                    lexer.slice()
+                );
+                return Err(e);
+            }
+            _ => {
+                error!(
+                    "expected ',' or ';' in type parameter, got '{}'",
+                    lexer.slice()
+                );
+                return Err(LexingError::ExpectedSemi);
+            }
+        }
+    }
+
+    let Some(mut type_expr) = type_expr else {
+        error!("expected a type in type parameter, got '{}'", lexer.slice());
+        return Err(LexingError::UnexpectedToken);
+    };
+    if let Some(signed) = signed {
+        type_expr.signed = signed;
+    }
+    if let Some((width, bounds)) = range {
+        type_expr.width = width;
+        type_expr.bounds = bounds;
+    }
+
+    Ok(type_expr)
+}
+
+/// Parses a module header parameter list, as in `#(parameter A = 1, B = 2)`,
+/// to completion
+///
+/// The `#` has already been consumed. Parameters without a keyword take the
+/// kind of the one before them, or `parameter` if they come first
+pub fn parse_parameter_ports<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<Vec<Param>, LexingError> {
+    let mut params: Vec<Param> = Vec::new();
+    let mut param_type = ParamType::Parameter;
+
+    trace!("parsing parameter port list");
+
+    expect_token(lexer, Token::OpenParen, "parameter port list")?;
+    if peek_token(lexer) == Some(Ok(Token::CloseParen)) {
+        next_token(lexer);
+        return Ok(params);
+    }
+
+    loop {
+        match peek_token(lexer) {
+            Some(Ok(Token::Parameter)) => {
+                next_token(lexer);
+                param_type = ParamType::Parameter;
+            }
+            Some(Ok(Token::Localparam)) => {
+                next_token(lexer);
+                param_type = ParamType::Localparam;
+            }
+            _ => (),
+        }
+
+        params.push(parse_param_assignment(lexer, param_type)?);
+
+        match next_token(lexer) {
+            Some(Ok(Token::Comma)) => (),
+            Some(Ok(Token::CloseParen)) => return Ok(params),
+            _ => {
+                error!(
+                    "expected ',' or ')' in parameter port list, got '{}'",
+                    lexer.slice()
+                );
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    }
+}
+
+/// Parses a single `name = value` parameter assignment
+///
+/// A data type, signing or range before the name, as in `int N = 4`,
+/// `signed [7:0] MASK = 8'hFF` or `logic [3:0] SEL = 2`, becomes the data
+/// type of the parameter. A signing or range without a type is of type
+/// `logic`
+fn parse_param_assignment<'source>(
+    lexer: &mut Lexer<'source, Token>,
+    param_type: ParamType,
+) -> Result<Param, LexingError> {
+    let mut name = String::new();
+    let mut var_type: Option<VarType> = None;
+    let mut signed: Option<bool> = None;
+    let mut range = None;
+
+    loop {
+        match next_token(lexer) {
+            Some(Ok(Token::Word))
+            | Some(Ok(Token::Underscore))
+            | Some(Ok(Token::EscapedIdentifier(_))) => name = parse_ident(lexer),
+            Some(Ok(Token::OpenBracket)) => range = Some(parse_width(lexer)?),
+            Some(Ok(Token::Reg))
+            | Some(Ok(Token::Wire))
+            | Some(Ok(Token::Logic))
+            | Some(Ok(Token::Byte))
+            | Some(Ok(Token::ShortInt))
+            | Some(Ok(Token::Int))
+            | Some(Ok(Token::LongInt))
+            | Some(Ok(Token::IntegerType)) => var_type = Some(VarType::from(lexer.slice())),
+            Some(Ok(Token::Signed)) => signed = Some(true),
+            Some(Ok(Token::Unsigned)) => signed = Some(false),
+            Some(Ok(Token::Equals)) if !name.is_empty() => break,
+            Some(Err(e)) => {
+                error!(
+                    "unexpected error occurred parsing parameter: '{}'",
+                    lexer.slice()
+                );
+                return Err(e);
+            }
+            _ => {
+                error!("expected parameter name, got '{}'", lexer.slice());
+                return Err(LexingError::UnexpectedToken);
+            }
+        }
+    }
+
+    let data_type = match (var_type, signed, range) {
+        (None, None, None) => None,
+        (var_type, signed, range) => {
+            let mut data_type = TypeExpr::from(var_type.unwrap_or(VarType::Logic));
+            if let Some(signed) = signed {
+                data_type.signed = signed;
+            }
+            if let Some((width, bounds)) = range {
+                data_type.width = width;
+                data_type.bounds = bounds;
+            }
+            Some(data_type)
+        }
+    };
+
+    Ok(Param {
+        name,
+        value: parse_expr(lexer)?,
+        param_type,
+        data_type,
+    })
+}
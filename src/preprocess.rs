@@ -0,0 +1,897 @@
+use crate::{IncludeError, PreprocessError};
+use log::{error, trace};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Maximum number of nested `` `include `` directives
+pub const MAX_INCLUDE_DEPTH: usize = 32;
+
+/// Compiler directives left in place for the parser, rather than expanded
+/// as macros
+const DIRECTIVES: [&str; 13] = [
+    "timescale",
+    "resetall",
+    "default_nettype",
+    "celldefine",
+    "endcelldefine",
+    "unconnected_drive",
+    "nounconnected_drive",
+    "line",
+    "pragma",
+    "begin_keywords",
+    "end_keywords",
+    "__FILE__",
+    "__LINE__",
+];
+
+/// Operators of the macro expressions of conditional directives, from the
+/// loosest binding to the tightest
+const MACRO_OPERATORS: [&str; 4] = ["<->", "->", "||", "&&"];
+
+/// Text macro
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Macro {
+    /// Names of the arguments, or `None` for a macro used without an
+    /// argument list
+    pub params: Option<Vec<String>>,
+
+    /// Default value of each argument, as in `b` of `` `define F(a, b=1) ``,
+    /// used when a use of the macro leaves it out or empty
+    pub defaults: Vec<Option<String>>,
+
+    /// Replacement text
+    pub body: String,
+}
+
+/// Conditionally compiled block, opened by `` `ifdef `` or `` `ifndef ``
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Condition {
+    /// Whether the enclosing text is being compiled
+    parent: bool,
+
+    /// Whether the current branch is being compiled
+    active: bool,
+
+    /// Whether any branch so far has been compiled
+    taken: bool,
+
+    /// Whether the `` `else `` branch has been reached
+    in_else: bool,
+}
+
+/// Point in preprocessed text from which lines no longer match the lines of
+/// the source, as after an included file or a macro spanning several lines
+#[derive(Debug, Clone, PartialEq)]
+struct LineMark {
+    /// Line of the preprocessed text, starting from 1
+    line: usize,
+
+    /// File the line comes from, `None` for the source being preprocessed
+    file: Option<PathBuf>,
+
+    /// Line within that file, starting from 1
+    source_line: usize,
+}
+
+/// SystemVerilog preprocessor
+///
+/// Holds the macros defined so far, which carry over from one source to the
+/// next as they would within a single compilation unit
+#[derive(Debug, Clone, Default)]
+pub struct Preprocessor {
+    /// Defined macros by name
+    pub defines: HashMap<String, Macro>,
+
+    /// Directories searched for included files, in order
+    pub include_dirs: Vec<PathBuf>,
+
+    /// Conditional blocks open at the current point, outermost first
+    conditions: Vec<Condition>,
+
+    /// Files being preprocessed, from the outermost to the one currently
+    /// included
+    files: Vec<PathBuf>,
+
+    /// Number of `` `include `` directives currently being expanded
+    depth: usize,
+
+    /// Points in the text last preprocessed where its lines move to another
+    /// place in the sources, in order
+    marks: Vec<LineMark>,
+}
+
+impl Preprocessor {
+    /// Creates a preprocessor with no macros defined
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defines a macro, replacing any earlier definition of the same name
+    pub fn define(&mut self, name: &str, body: &str) {
+        trace!("defining macro '{}'", name);
+
+        self.defines.insert(
+            name.to_owned(),
+            Macro {
+                body: body.to_owned(),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Defines a macro given on the command line, as in `NAME`,
+    /// `NAME=VALUE` or `NAME(a, b=1)=VALUE`
+    ///
+    /// A macro without a value is defined as `1`
+    pub fn define_arg(&mut self, arg: &str) -> Result<(), PreprocessError> {
+        let (name, after) = split_identifier(arg);
+        let head = match after.starts_with('(') {
+            true => macro_params(after),
+            false => Some((Macro::default(), after)),
+        };
+        let mac = head.and_then(|(mac, rest)| {
+            let body = match rest {
+                "" => "1",
+                rest => rest.strip_prefix('=')?,
+            };
+            Some(Macro {
+                body: body.to_owned(),
+                ..mac
+            })
+        });
+
+        match mac {
+            Some(mac) if is_identifier(name) => {
+                trace!("defining macro '{}'", name);
+                self.defines.insert(name.to_owned(), mac);
+                Ok(())
+            }
+            _ => {
+                error!("'{}' is not a valid macro definition", arg);
+                Err(PreprocessError::InvalidDefine(arg.to_owned()))
+            }
+        }
+    }
+
+    /// Adds a directory to the end of the include search path
+    pub fn add_include_dir(&mut self, dir: &Path) {
+        trace!("adding include directory {:?}", dir);
+
+        self.include_dirs.push(dir.to_path_buf());
+    }
+
+    /// Finds an included file, as named in `` `include "name" ``
+    ///
+    /// The directory of the file being preprocessed is searched first, if
+    /// known, then each include directory in order. The first holding the
+    /// file is used, and a file found in none of them returns
+    /// `IncludeError::NotFound`. A file named in angle brackets, as in
+    /// `` `include <name> ``, is only searched for in the include directories
+    pub fn find_include(&self, name: &str) -> Result<PathBuf, IncludeError> {
+        self.search_include(name, true)
+    }
+
+    /// Finds an included file as described for `find_include`, searching
+    /// the directory of the file being preprocessed only if `local` is set
+    fn search_include(&self, name: &str, local: bool) -> Result<PathBuf, IncludeError> {
+        let current = self
+            .files
+            .last()
+            .and_then(|file| file.parent())
+            .filter(|_| local);
+
+        current
+            .into_iter()
+            .chain(self.include_dirs.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(name))
+            .chain(Path::new(name).is_absolute().then(|| PathBuf::from(name)))
+            .find(|path| path.is_file())
+            .ok_or_else(|| {
+                error!(
+                    "included file '{}' not found in {:?}",
+                    name, self.include_dirs
+                );
+                IncludeError::NotFound(name.to_owned())
+            })
+    }
+
+    /// Expands the macros used in a source, returning the text to lex
+    ///
+    /// `` `define `` and `` `undef `` update the macros defined, and are
+    /// replaced by blank lines so the remaining source keeps its line numbers,
+    /// while lines moved by included files and macros spanning several lines
+    /// can be traced back with `source_line`. Macros are not expanded within
+    /// comments or string literals, and the text a macro expands to is scanned
+    /// again for further macros. Compiler directives such as `` `timescale ``
+    /// are left for the parser.
+    ///
+    /// Blocks within `` `ifdef ``, `` `ifndef ``, `` `elsif ``, `` `else ``
+    /// and `` `endif `` are kept or skipped depending on the macros defined
+    /// at that point, checked by name or by an expression of names as in
+    /// `` `ifdef (A && !B) ``. Skipped blocks are also replaced by blank
+    /// lines, and every block must be closed within the same source.
+    ///
+    /// Using a macro that was never defined returns
+    /// `PreprocessError::UndefinedMacro`, and a macro that ends up using
+    /// itself returns `PreprocessError::RecursiveMacro`
+    pub fn preprocess(&mut self, source: &str) -> Result<String, PreprocessError> {
+        trace!("preprocessing source");
+
+        let mut out = String::with_capacity(source.len());
+        self.marks.clear();
+        let outer = std::mem::take(&mut self.conditions);
+        let result = self.scan(source, &mut out, &mut Vec::new());
+        let open = std::mem::replace(&mut self.conditions, outer);
+        result?;
+
+        if !open.is_empty() {
+            error!("'`ifdef' is never closed by '`endif'");
+            return Err(PreprocessError::UnterminatedConditional);
+        }
+
+        Ok(out)
+    }
+
+    /// Expands the macros used in the source read from `path`
+    ///
+    /// As `preprocess`, except that files the source includes are also
+    /// searched for in the directory of `path`
+    pub fn preprocess_file(
+        &mut self,
+        path: &Path,
+        source: &str,
+    ) -> Result<String, PreprocessError> {
+        trace!("preprocessing {:?}", path);
+
+        self.files.push(path.to_path_buf());
+        let result = self.preprocess(source);
+        self.files.pop();
+
+        result
+    }
+
+    /// Finds where a line of the text last preprocessed comes from, as the
+    /// included file it is in, or `None` for the source itself, and the line
+    /// within that file
+    ///
+    /// Lines start from 1. Lines produced by a macro are placed at the line
+    /// the macro is used on
+    pub fn source_line(&self, line: usize) -> (Option<&Path>, usize) {
+        match self.marks.iter().rfind(|mark| mark.line <= line) {
+            Some(mark) => (mark.file.as_deref(), mark.source_line + (line - mark.line)),
+            None => (None, line),
+        }
+    }
+
+    /// Mark for `line` of the text being preprocessed, at the place in the
+    /// sources it comes from
+    fn mark_at(&self, line: usize) -> LineMark {
+        let (file, source_line) = self.source_line(line);
+
+        LineMark {
+            line,
+            file: file.map(Path::to_path_buf),
+            source_line,
+        }
+    }
+
+    /// Pushes `text` to `out`, handling every directive and macro it uses
+    ///
+    /// `stack` holds the names of the macros being expanded, outermost
+    /// first
+    fn scan(
+        &mut self,
+        text: &str,
+        out: &mut String,
+        stack: &mut Vec<String>,
+    ) -> Result<(), PreprocessError> {
+        let mut rest = text;
+
+        while let Some(idx) = rest.find(['`', '/', '"']) {
+            self.emit(&rest[..idx], out);
+            rest = &rest[idx..];
+
+            let end = match rest.as_bytes() {
+                [b'/', b'/', ..] => rest.find('\n').unwrap_or(rest.len()),
+                [b'/', b'*', ..] => rest[2..].find("*/").map_or(rest.len(), |end| end + 4),
+                [b'"', ..] => string_end(rest),
+                [b'`', ..] => {
+                    rest = self.directive(rest, out, stack)?;
+                    continue;
+                }
+                _ => 1,
+            };
+
+            self.emit(&rest[..end], out);
+            rest = &rest[end..];
+        }
+        self.emit(rest, out);
+
+        Ok(())
+    }
+
+    /// Checks if text at the current point is being compiled, rather than
+    /// skipped by a conditional block
+    fn active(&self) -> bool {
+        self.conditions.last().is_none_or(|cond| cond.active)
+    }
+
+    /// Pushes `text` to `out`, or only its line breaks if it is being
+    /// skipped
+    fn emit(&self, text: &str, out: &mut String) {
+        match self.active() {
+            true => out.push_str(text),
+            false => text.matches('\n').for_each(|_| out.push('\n')),
+        }
+    }
+
+    /// Handles the directive or macro used at the start of `text`, pushing
+    /// the result to `out` and returning the text after it
+    fn directive<'a>(
+        &mut self,
+        text: &'a str,
+        out: &mut String,
+        stack: &mut Vec<String>,
+    ) -> Result<&'a str, PreprocessError> {
+        let (name, rest) = split_identifier(&text[1..]);
+
+        match name {
+            "ifdef" | "ifndef" | "elsif" | "else" | "endif" => self.conditional(name, rest),
+            _ if !self.active() => Ok(rest),
+            "define" => self.parse_define(rest, out),
+            "include" => self.include(rest, out),
+            "undef" => {
+                let (name, rest) = split_identifier(rest.trim_start_matches([' ', '\t']));
+                trace!("undefining macro '{}'", name);
+                self.defines.remove(name);
+                Ok(rest)
+            }
+            _ if name.is_empty() || DIRECTIVES.contains(&name) => {
+                out.push('`');
+                out.push_str(name);
+                Ok(rest)
+            }
+            _ => self.expand(name, rest, out, stack),
+        }
+    }
+
+    /// Replaces the `` `include `` directive followed by `text` with the
+    /// preprocessed contents of the file it names, returning the text after
+    /// the file name
+    ///
+    /// The file name may also come from a macro, as in `` `include `DEFS ``
+    /// after `` `define DEFS "defs.svh" ``.
+    ///
+    /// The included file sees the macros defined so far, and the macros it
+    /// defines carry over to the rest of the source. Including a file that
+    /// is already being included returns `IncludeError::Circular`
+    fn include<'a>(&mut self, text: &'a str, out: &mut String) -> Result<&'a str, PreprocessError> {
+        let text = text.trim_start_matches([' ', '\t']);
+        let spec = match text.strip_prefix('`') {
+            Some(after) => {
+                let (mac, args) = split_identifier(after);
+                let mut spec = String::new();
+                let rest = self.expand(mac, args, &mut spec, &mut Vec::new())?;
+
+                include_name(spec.trim())
+                    .filter(|(_, _, after)| after.is_empty())
+                    .map(|(name, local, _)| (name.to_owned(), local, rest))
+            }
+            None => include_name(text).map(|(name, local, rest)| (name.to_owned(), local, rest)),
+        };
+
+        let Some((name, local, rest)) = spec else {
+            error!("'`include' is missing a file name in quotes or angle brackets");
+            return Err(IncludeError::MissingFileName.into());
+        };
+        let name = name.as_str();
+
+        if self.depth >= MAX_INCLUDE_DEPTH {
+            error!("including '{}' exceeds the maximum include depth", name);
+            return Err(IncludeError::TooDeep(name.to_owned()).into());
+        }
+
+        let path = match local {
+            true => self.find_include(name)?,
+            false => self.search_include(name, false)?,
+        };
+        if let Some(start) = self.files.iter().position(|file| same_file(file, &path)) {
+            let chain: Vec<String> = self.files[start..]
+                .iter()
+                .chain([&path])
+                .map(|file| file.display().to_string())
+                .collect();
+
+            error!("circular include: {}", chain.join(" -> "));
+            return Err(IncludeError::Circular(chain).into());
+        }
+
+        let source = fs::read_to_string(&path).map_err(|e| {
+            error!("failed to read included file {:?}: {}", path, e);
+            IncludeError::Unreadable(path.display().to_string())
+        })?;
+
+        trace!("including {:?}", path);
+        let outer = std::mem::take(&mut self.marks);
+        self.depth += 1;
+        let result = self.preprocess_file(&path, &source);
+        self.depth -= 1;
+        let inner = std::mem::replace(&mut self.marks, outer);
+        let included = result?;
+
+        // Lines of the included file, then the rest of the line including it
+        let line = out.matches('\n').count() + 1;
+        let back = self.mark_at(line);
+        self.marks.push(LineMark {
+            line,
+            file: Some(path.clone()),
+            source_line: 1,
+        });
+        self.marks.extend(inner.into_iter().map(|mark| LineMark {
+            line: line + mark.line - 1,
+            file: mark.file.or_else(|| Some(path.clone())),
+            source_line: mark.source_line,
+        }));
+        self.marks.push(LineMark {
+            line: line + included.matches('\n').count(),
+            ..back
+        });
+
+        out.push_str(&included);
+        Ok(rest)
+    }
+
+    /// Handles the conditional directive `name`, followed by `text`,
+    /// returning the text after it
+    ///
+    /// The macro name checked may instead be a parenthesised expression of
+    /// macro names, as in `` `ifdef (A && !(B || C)) ``
+    fn conditional<'a>(&mut self, name: &str, text: &'a str) -> Result<&'a str, PreprocessError> {
+        let (defined, rest) = match name {
+            "ifdef" | "ifndef" | "elsif" => {
+                let text = text.trim_start_matches([' ', '\t']);
+                if text.starts_with('(') {
+                    let Some((defined, rest)) = self.macro_expression(text, 0) else {
+                        let line = text.lines().next().unwrap_or_default().trim();
+                        error!("'{}' is not a valid macro expression", line);
+                        return Err(PreprocessError::InvalidMacroExpression(line.to_owned()));
+                    };
+                    (defined, rest)
+                } else {
+                    let (macro_name, rest) = split_identifier(text);
+                    if !is_identifier(macro_name) {
+                        error!("'`{}' is missing a macro name", name);
+                        return Err(PreprocessError::MissingMacroName(name.to_owned()));
+                    }
+                    (self.defines.contains_key(macro_name), rest)
+                }
+            }
+            _ => (false, text),
+        };
+
+        if name == "ifdef" || name == "ifndef" {
+            let parent = self.active();
+            let taken = defined == (name == "ifdef");
+
+            self.conditions.push(Condition {
+                parent,
+                active: parent && taken,
+                taken,
+                in_else: false,
+            });
+            return Ok(rest);
+        }
+
+        let cond = match self.conditions.last_mut() {
+            Some(cond) if !cond.in_else || name == "endif" => cond,
+            _ => {
+                error!("'`{}' without a matching '`ifdef'", name);
+                return Err(PreprocessError::UnmatchedConditional(name.to_owned()));
+            }
+        };
+
+        match name {
+            "elsif" => {
+                cond.active = cond.parent && !cond.taken && defined;
+                cond.taken |= defined;
+            }
+            "else" => {
+                cond.active = cond.parent && !cond.taken;
+                cond.taken = true;
+                cond.in_else = true;
+            }
+            _ => {
+                self.conditions.pop();
+            }
+        }
+
+        Ok(rest)
+    }
+
+    /// Evaluates the macro expression starting `text`, returning whether it
+    /// holds and the text after it, or `None` if it isn't valid
+    ///
+    /// Operators bind more loosely the lower their index in
+    /// `MACRO_OPERATORS`, from `level` on, and `!` binds tightest of all.
+    /// `->` groups from the right, the others from the left
+    fn macro_expression<'a>(&self, text: &'a str, level: usize) -> Option<(bool, &'a str)> {
+        let text = text.trim_start_matches([' ', '\t']);
+        let Some(op) = MACRO_OPERATORS.get(level) else {
+            if let Some(rest) = text.strip_prefix('!') {
+                let (value, rest) = self.macro_expression(rest, level)?;
+                return Some((!value, rest));
+            }
+            if let Some(rest) = text.strip_prefix('(') {
+                let (value, rest) = self.macro_expression(rest, 0)?;
+                let rest = rest.trim_start_matches([' ', '\t']).strip_prefix(')')?;
+                return Some((value, rest));
+            }
+
+            let (name, rest) = split_identifier(text);
+            return is_identifier(name).then(|| (self.defines.contains_key(name), rest));
+        };
+
+        let (mut value, mut rest) = self.macro_expression(text, level + 1)?;
+        while let Some(after) = rest.trim_start_matches([' ', '\t']).strip_prefix(op) {
+            let (rhs, after) = match *op {
+                "->" => self.macro_expression(after, level)?,
+                _ => self.macro_expression(after, level + 1)?,
+            };
+            value = match *op {
+                "<->" => value == rhs,
+                "->" => !value || rhs,
+                "||" => value || rhs,
+                _ => value && rhs,
+            };
+            rest = after;
+        }
+
+        Some((value, rest))
+    }
+
+    /// Parses the `` `define `` directive at the start of `text`, following
+    /// the keyword, returning the text after the macro body
+    ///
+    /// The body runs to the end of the line, or further when a line ends
+    /// with `\`. Each line the definition continues onto is replaced by a
+    /// blank line
+    fn parse_define<'a>(
+        &mut self,
+        text: &'a str,
+        out: &mut String,
+    ) -> Result<&'a str, PreprocessError> {
+        let mut end = 0;
+        while let Some(line_end) = text[end..].find('\n').map(|idx| end + idx) {
+            if !text[..line_end].trim_end_matches('\r').ends_with('\\') {
+                end = line_end;
+                break;
+            }
+            end = line_end + 1;
+        }
+        if !text[end..].starts_with('\n') {
+            end = text.len();
+        }
+
+        let (definition, rest) = text.split_at(end);
+        for _ in definition.matches('\n') {
+            out.push('\n');
+        }
+
+        let definition = definition.trim_start_matches([' ', '\t']);
+        let (name, after) = split_identifier(definition);
+        if !is_identifier(name) {
+            error!("'{}' is not a valid macro definition", definition.trim());
+            return Err(PreprocessError::InvalidDefine(definition.trim().to_owned()));
+        }
+
+        let (mac, body) = match after.starts_with('(') {
+            true => {
+                let Some(head) = macro_params(after) else {
+                    error!("'{}' is not a valid macro definition", definition.trim());
+                    return Err(PreprocessError::InvalidDefine(definition.trim().to_owned()));
+                };
+                head
+            }
+            false => (Macro::default(), after),
+        };
+
+        let body: Vec<&str> = body
+            .lines()
+            .map(|line| strip_comment(line).trim_end().trim_end_matches('\\'))
+            .collect();
+
+        trace!("defining macro '{}'", name);
+        self.defines.insert(
+            name.to_owned(),
+            Macro {
+                body: body.join("\n").trim().to_owned(),
+                ..mac
+            },
+        );
+
+        Ok(rest)
+    }
+
+    /// Expands a use of the macro `name`, whose arguments (if any) start
+    /// `text`, returning the text after the use
+    fn expand<'a>(
+        &mut self,
+        name: &str,
+        text: &'a str,
+        out: &mut String,
+        stack: &mut Vec<String>,
+    ) -> Result<&'a str, PreprocessError> {
+        let Some(mac) = self.defines.get(name).cloned() else {
+            error!("macro '{}' is not defined", name);
+            return Err(PreprocessError::UndefinedMacro(name.to_owned()));
+        };
+
+        if let Some(start) = stack.iter().position(|used| used == name) {
+            let mut chain = stack[start..].to_vec();
+            chain.push(name.to_owned());
+
+            error!("recursive macro expansion: {}", chain.join(" -> "));
+            return Err(PreprocessError::RecursiveMacro(chain));
+        }
+
+        let (body, rest) = match &mac.params {
+            Some(params) => {
+                let (args, rest) = split_arguments(text);
+                let mut args = match args {
+                    Some(args) if args == [""] && params.is_empty() => Vec::new(),
+                    Some(args) => args,
+                    None => Vec::new(),
+                };
+
+                // Arguments left out or empty take their defaults
+                for (idx, default) in mac.defaults.iter().enumerate() {
+                    let Some(default) = default else { continue };
+                    if idx == args.len() {
+                        args.push(default.clone());
+                    } else if args.get(idx).is_some_and(|arg| arg.is_empty()) {
+                        args[idx] = default.clone();
+                    }
+                }
+
+                if args.len() != params.len() {
+                    error!(
+                        "macro '{}' takes {} arguments, but is given {}",
+                        name,
+                        params.len(),
+                        args.len()
+                    );
+                    return Err(PreprocessError::MacroArguments {
+                        name: name.to_owned(),
+                        expected: params.len(),
+                        got: args.len(),
+                    });
+                }
+                (substitute(&mac.body, params, &args), rest)
+            }
+            None => (substitute(&mac.body, &[], &[]), text),
+        };
+
+        trace!("expanding macro '{}'", name);
+        let start = out.len();
+        stack.push(name.to_owned());
+        self.scan(&body, out, stack)?;
+        stack.pop();
+
+        // Lines after a use of a macro spanning a different number of lines
+        // than the use itself continue from the lines after the use
+        let used = text[..text.len() - rest.len()].matches('\n').count();
+        let expanded = out[start..].matches('\n').count();
+        if stack.is_empty() && used != expanded {
+            let mark = self.mark_at(out[..start].matches('\n').count() + 1);
+            self.marks.push(LineMark {
+                line: mark.line + expanded,
+                source_line: mark.source_line + used,
+                ..mark
+            });
+        }
+
+        Ok(rest)
+    }
+}
+
+/// Checks if two paths refer to the same file
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Checks if a name is a simple identifier, as in `WIDTH` or `debug_2`
+fn is_identifier(name: &str) -> bool {
+    let (ident, rest) = split_identifier(name);
+
+    !ident.is_empty() && rest.is_empty() && !ident.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Macro without a body taking the parameters of the parenthesised list
+/// starting `text`, as in `(a, b=1)`, along with the text after the list
+///
+/// Returns `None` if the list is never closed, or any name is not an
+/// identifier
+fn macro_params(text: &str) -> Option<(Macro, &str)> {
+    let (list, rest) = match split_arguments(text) {
+        (Some(list), rest) if list == [""] => (Vec::new(), rest),
+        (Some(list), rest) => (list, rest),
+        (None, _) => return None,
+    };
+
+    let (params, defaults) = list
+        .iter()
+        .map(|param| match param.split_once('=') {
+            Some((name, default)) => (name.trim(), Some(default.trim().to_owned())),
+            None => (param.as_str(), None),
+        })
+        .map(|(name, default)| is_identifier(name).then(|| (name.to_owned(), default)))
+        .collect::<Option<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+
+    let mac = Macro {
+        params: Some(params),
+        defaults,
+        body: String::new(),
+    };
+
+    Some((mac, rest))
+}
+
+/// Splits the identifier characters at the start of `text` from the rest
+fn split_identifier(text: &str) -> (&str, &str) {
+    let end = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$'))
+        .unwrap_or(text.len());
+
+    text.split_at(end)
+}
+
+/// File name of the `` `include `` directive starting `text`, in quotes or
+/// angle brackets, along with whether it was quoted and the text after it
+fn include_name(text: &str) -> Option<(&str, bool, &str)> {
+    let (end, close) = match text.chars().next()? {
+        '"' => (string_end(text), '"'),
+        '<' => {
+            let line = text.find('\n').unwrap_or(text.len());
+            (text[..line].find('>')? + 1, '>')
+        }
+        _ => return None,
+    };
+
+    (end >= 2 && text[..end].ends_with(close))
+        .then(|| (&text[1..end - 1], close == '"', &text[end..]))
+}
+
+/// Length of the string literal at the start of `text`, including both
+/// quotes
+///
+/// An unterminated literal runs to the end of the line
+fn string_end(text: &str) -> usize {
+    let mut escaped = false;
+
+    for (idx, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return idx + 1,
+            '\n' => return idx,
+            _ => (),
+        }
+    }
+
+    text.len()
+}
+
+/// Cuts a `//` comment from the end of a line of macro text
+fn strip_comment(line: &str) -> &str {
+    let mut idx = 0;
+
+    while let Some(found) = line[idx..].find(['/', '"']).map(|found| idx + found) {
+        if line[found..].starts_with("//") {
+            return &line[..found];
+        }
+
+        idx = match line.as_bytes()[found] {
+            b'"' => found + string_end(&line[found..]),
+            _ => found + 1,
+        };
+    }
+
+    line
+}
+
+/// Splits the argument list of a macro use, as in `(a, f(b, c))`, from the
+/// text following it
+///
+/// Arguments are split on commas outside of any brackets or string
+/// literals. Text that doesn't start with an argument list, or whose list
+/// is never closed, returns `None`
+fn split_arguments(text: &str) -> (Option<Vec<String>>, &str) {
+    let list = text.trim_start_matches([' ', '\t']);
+    if !list.starts_with('(') {
+        return (None, text);
+    }
+
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut start = 1;
+    let mut idx = 1;
+
+    while idx < list.len() {
+        match list.as_bytes()[idx] {
+            b'"' => {
+                idx += string_end(&list[idx..]);
+                continue;
+            }
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' if depth == 0 => {
+                args.push(list[start..idx].trim().to_owned());
+                return (Some(args), &list[idx + 1..]);
+            }
+            b')' | b']' | b'}' => depth -= 1,
+            b',' if depth == 0 => {
+                args.push(list[start..idx].trim().to_owned());
+                start = idx + 1;
+            }
+            _ => (),
+        }
+        idx += 1;
+    }
+
+    (None, text)
+}
+
+/// Replaces each use of a macro argument within `body` by its value
+///
+/// Names within string literals, and macro names following a back-tick, are
+/// left alone. A ` `` ` joins the text either side of it, and `` `" `` and
+/// `` `\`" `` give a quote and an escaped quote in which arguments are still
+/// replaced
+fn substitute(body: &str, params: &[String], args: &[String]) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut rest = body;
+
+    while let Some(idx) = rest.find(|c: char| c.is_ascii_alphanumeric() || "_$\"`".contains(c)) {
+        out.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        match rest.as_bytes()[0] {
+            b'"' => {
+                let end = string_end(rest);
+                out.push_str(&rest[..end]);
+                rest = &rest[end..];
+            }
+            b'`' if rest.starts_with("``") => rest = &rest[2..],
+            b'`' if rest.starts_with("`\"") => {
+                out.push('"');
+                rest = &rest[2..];
+            }
+            b'`' if rest.starts_with("`\\`\"") => {
+                out.push_str("\\\"");
+                rest = &rest[4..];
+            }
+            b'`' => {
+                let (name, after) = split_identifier(&rest[1..]);
+                out.push('`');
+                out.push_str(name);
+                rest = after;
+            }
+            _ => {
+                let (word, after) = split_identifier(rest);
+                match params.iter().position(|param| param == word) {
+                    Some(idx) => out.push_str(&args[idx]),
+                    None => out.push_str(word),
+                }
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+
+    out
+}
@@ -0,0 +1,3096 @@
+use crate::elab::{ElaboratedDesign, ModuleInstance};
+use crate::eval::{
+    collect_reads, const_index, element_bounds, eval_expr, eval_expr_sized, expr_reads,
+    expr_width, member_name, memory_element, ValueLookup,
+};
+use crate::expr::Expr;
+use crate::function::{FunctionDef, TaskDef};
+use crate::generate::rename_stmts;
+use crate::logic::{
+    loop_vars, CaseKind, Delay, DisplayTask, Edge, EdgeEvent, EventTrigger, JoinKind, LogicStmt,
+    LoopStmt,
+};
+use crate::module::{Module, PortDirection};
+use crate::sim_time::SimTime;
+use crate::sys_task::{
+    self, assert_action, format_display, is_assert_task, parse_mem_file, system_command,
+    AssertState,
+};
+use crate::value::{DriveStrength, LogicState, LogicValue, Net};
+use crate::var_types::VarType;
+use crate::{SimConfig, SimError, SimObject, DEFAULT_TEST_TIMEOUT};
+use log::{debug, error, info, trace, warn};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// Most instructions a process may run without waiting before it is assumed
+/// to be stuck in a zero-delay loop and stopped
+const MAX_STEPS_PER_ACTIVATION: usize = 1_000_000;
+
+/// Most delta cycles a single time step may take before it is assumed to
+/// never settle
+const MAX_DELTA_CYCLES: usize = 10_000;
+
+/// System tasks controlling the waveform dump, handled by `dump_task`
+const DUMP_TASKS: [&str; 4] = ["dumpfile", "dumpvars", "dumpoff", "dumpon"];
+
+/// Signal value change recorded during simulation
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalChange {
+    /// Simulation time of the change, in seconds
+    pub time: f64,
+
+    /// Hierarchical signal name, as in `top.count`
+    pub signal: String,
+
+    /// Value after the change
+    pub value: LogicValue,
+}
+
+/// Reason a simulation run stopped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SimStatus {
+    /// Nothing was left to simulate
+    #[default]
+    Completed,
+
+    /// The end time of the run was reached
+    EndTime,
+
+    /// `$finish` was called
+    Finished,
+
+    /// `$stop` was called
+    Stopped,
+
+    /// A time step never settled, as with combinational feedback that
+    /// oscillates
+    Unsettled,
+}
+
+impl fmt::Display for SimStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SimStatus::Completed => write!(f, "nothing was left to simulate"),
+            SimStatus::EndTime => write!(f, "the end time was reached"),
+            SimStatus::Finished => write!(f, "$finish was called"),
+            SimStatus::Stopped => write!(f, "$stop was called"),
+            SimStatus::Unsettled => write!(f, "a time step did not settle"),
+        }
+    }
+}
+
+/// Waveform dump requested by `$dumpfile` and `$dumpvars`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dump {
+    /// File named by `$dumpfile`, or `dump.vcd` if none was given
+    pub file: PathBuf,
+
+    /// Scopes to dump, as a hierarchical instance or signal name and the
+    /// number of levels below it to include, with 0 for every level
+    ///
+    /// An empty list dumps every signal
+    pub scopes: Vec<(String, u64)>,
+
+    /// Simulation time of the first `$dumpvars` call, in seconds
+    pub start: f64,
+
+    /// Times `$dumpoff` paused the dump and `$dumpon` resumed it, in
+    /// seconds, with an infinite end for a dump that was never resumed
+    pub pauses: Vec<(f64, f64)>,
+}
+
+impl Dump {
+    /// Checks if a signal, by hierarchical name, is included in the dump
+    pub fn includes(&self, signal: &str) -> bool {
+        if self.scopes.is_empty() {
+            return true;
+        }
+
+        self.scopes.iter().any(|(scope, levels)| {
+            if signal == scope {
+                return true;
+            }
+
+            match signal
+                .strip_prefix(scope.as_str())
+                .and_then(|rest| rest.strip_prefix('.'))
+            {
+                Some(rest) => *levels == 0 || rest.split('.').count() as u64 <= *levels,
+                None => false,
+            }
+        })
+    }
+}
+
+/// Outcome of a simulation run
+#[derive(Debug, Clone, Default)]
+pub struct SimResult {
+    /// Reason the run stopped
+    pub status: SimStatus,
+
+    /// Simulation time when the run stopped, in seconds
+    pub end_time: f64,
+
+    /// Final value of every signal, by hierarchical name
+    pub signals: BTreeMap<String, LogicValue>,
+
+    /// Every signal change, in the order it happened
+    pub changes: Vec<SignalChange>,
+
+    /// Time unit and precision the run was simulated with
+    pub sim_time: SimTime,
+
+    /// Text printed by output tasks such as `$display`, in order
+    pub output: String,
+
+    /// Waveform dump requested by the design, if any
+    pub dump: Option<Dump>,
+
+    /// Commands `$system` was called with but not allowed to run, as
+    /// `SimConfig::allow_system_calls` was not set, in call order. A run
+    /// with any fails, as reported by `check_system_calls`
+    pub forbidden_calls: Vec<String>,
+
+    /// Assertion checking state of every instance when the run stopped, by
+    /// hierarchical name
+    pub assertions: BTreeMap<String, AssertState>,
+}
+
+impl SimResult {
+    /// Final contents of a memory, by hierarchical name, as in `top.mem`
+    ///
+    /// Returns one word per address, from the lowest address up, with the
+    /// bits of each word most significant first. Names that are not a
+    /// memory return `SimError::UndefinedSignal`
+    pub fn get_memory(&self, signal_path: &str) -> Result<Vec<Vec<LogicState>>, SimError> {
+        let (low, high) = self.memory_bounds(signal_path)?;
+
+        (low..=high)
+            .map(|addr| self.get_memory_word(signal_path, addr))
+            .collect()
+    }
+
+    /// Final value of the word at `addr` in a memory, by hierarchical name,
+    /// with its bits most significant first
+    ///
+    /// Addresses outside the memory return `SimError::AddressOutOfRange`
+    pub fn get_memory_word(
+        &self,
+        signal_path: &str,
+        addr: u64,
+    ) -> Result<Vec<LogicState>, SimError> {
+        let (low, high) = self.memory_bounds(signal_path)?;
+        if !(low..=high).contains(&addr) {
+            return Err(SimError::AddressOutOfRange {
+                memory: signal_path.to_owned(),
+                addr,
+            });
+        }
+
+        self.signals
+            .get(&format!("{}[{}]", signal_path, addr))
+            .map(|word| word.bits().to_vec())
+            .ok_or_else(|| SimError::UndefinedSignal(signal_path.to_owned()))
+    }
+
+    /// Fails with `SimError::SystemCallForbidden` for the first command
+    /// `$system` was called with but not allowed to run, if any
+    pub fn check_system_calls(&self) -> Result<(), SimError> {
+        match self.forbidden_calls.first() {
+            Some(cmd) => Err(SimError::SystemCallForbidden(cmd.to_owned())),
+            None => Ok(()),
+        }
+    }
+
+    /// Lowest and highest addresses of a memory, by hierarchical name
+    fn memory_bounds(&self, signal_path: &str) -> Result<(u64, u64), SimError> {
+        element_bounds(self.signals.keys(), signal_path)
+            .ok_or_else(|| SimError::UndefinedSignal(signal_path.to_owned()))
+    }
+}
+
+impl SimObject {
+    /// Simulates every top-level module until `end_time`, in seconds, or
+    /// until nothing is left to simulate
+    ///
+    /// The run also stops early if a time step never settles, as with
+    /// combinational feedback that oscillates
+    ///
+    /// Top-level modules are those not instantiated by another module in the
+    /// object
+    pub fn run(&self, end_time: f64) -> SimResult {
+        let mut sim = Simulator::new(self.sim_time.n_time, self.sim_time.d_time);
+
+        for module in self.top_modules() {
+            warn_unelaborated(module);
+            sim.add_module(module, &module.name, &HashMap::new());
+        }
+
+        sim.run(end_time)
+    }
+
+    /// Simulates a single top-level module until `end_time`, in seconds, or
+    /// until nothing is left to simulate
+    ///
+    /// An `end_time` of `f64::INFINITY` runs until nothing is left to
+    /// simulate
+    pub fn run_module(&self, top: &Module, end_time: f64) -> SimResult {
+        let mut sim = Simulator::new(self.sim_time.n_time, self.sim_time.d_time);
+        warn_unelaborated(top);
+        sim.add_module(top, &top.name, &HashMap::new());
+
+        sim.run(end_time)
+    }
+
+    /// Modules not instantiated by any other module in the object, sorted
+    /// by name
+    pub fn top_modules(&self) -> Vec<&Module> {
+        self.sorted_mods()
+            .into_iter()
+            .filter(|module| {
+                !self
+                    .mods
+                    .values()
+                    .any(|parent| parent.instantiates(&module.name))
+            })
+            .collect()
+    }
+}
+
+/// Warns that the instances of a module simulated without elaborating the
+/// design are left out
+fn warn_unelaborated(module: &Module) {
+    if !module.instances.is_empty() {
+        warn!(
+            "instances in module '{}' are only simulated in an elaborated design",
+            module.name
+        );
+    }
+}
+
+impl ElaboratedDesign {
+    /// Simulates the design until `end_time`, in seconds, or until
+    /// nothing is left to simulate
+    ///
+    /// Every instance in the hierarchy is simulated with the parameter
+    /// values it was elaborated with, named by its hierarchical path, as in
+    /// `top.u_fa.sum`. Input ports follow the parent signals connected to
+    /// them, and output ports drive theirs. Inputs left unconnected float
+    /// at Z. Inout ports are not connected. `$system` commands run only if
+    /// the configuration the design was elaborated with allows them
+    pub fn run(&self, end_time: f64) -> SimResult {
+        self.simulate(end_time, None, &self.config)
+    }
+
+    /// Simulates the design as `run` with the given configuration, leaving
+    /// out every `initial` block annotated as a test except, if `test` is
+    /// given, the block at its index in the instance at its hierarchical path
+    fn simulate(
+        &self,
+        end_time: f64,
+        test: Option<(&str, usize)>,
+        config: &SimConfig,
+    ) -> SimResult {
+        let mut sim = Simulator::new(self.sim_time.n_time, self.sim_time.d_time);
+        sim.system.config = config.clone();
+
+        for inst in self.top.iter() {
+            let Some(module) = self.mods.get(&inst.module_name) else {
+                error!("module '{}' is not defined", inst.module_name);
+                continue;
+            };
+            match test {
+                Some((path, index)) => {
+                    let mut module = module.clone();
+                    module.initial_blocks = std::mem::take(&mut module.initial_blocks)
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(other, block)| {
+                            block.testbench.is_none() || (inst.path == path && *other == index)
+                        })
+                        .map(|(_, block)| block)
+                        .collect();
+                    sim.add_module(&module, &inst.path, &inst.params);
+                }
+                None => sim.add_module(module, &inst.path, &inst.params),
+            }
+        }
+        for inst in self.top.iter() {
+            for child in &inst.children {
+                if let Some(module) = self.mods.get(&child.module_name) {
+                    sim.bind_ports(module, inst, child);
+                }
+            }
+        }
+
+        sim.run(end_time)
+    }
+}
+
+/// Outcome of a test run by `ElaboratedDesign::run_testbench`
+#[derive(Debug)]
+pub struct TestResult {
+    /// Test name, as the module name and the name given by the annotation,
+    /// as in `tb.reset`
+    pub name: String,
+
+    /// Whether the test ended by calling `$finish`
+    pub passed: bool,
+
+    /// Reason the test failed, if it did
+    pub failure: Option<SimError>,
+
+    /// Wall clock time the test took to run, in nanoseconds
+    pub duration_ns: f64,
+}
+
+impl ElaboratedDesign {
+    /// Runs every `initial` block annotated with `// @testbench` in its
+    /// first comment as a separate test, once for each instance of the
+    /// module holding it
+    ///
+    /// Each test simulates the design from the start with its own block
+    /// and every block that is not a test, as by `run` with `config`, and
+    /// passes if it ends by calling `$finish`. A test calling `$system` when
+    /// `config` does not allow it fails with `SimError::SystemCallForbidden`,
+    /// and one ending any other way with `SimError::Unfinished`. Tests are
+    /// named by the instance path and the annotation, as in `tb.u_dut.reset`
+    /// for `// @testbench reset`, or `test1`, `test2` and so on by their
+    /// position in the module, and stop after `config.test_timeout`, or
+    /// `DEFAULT_TEST_TIMEOUT` if it is not given
+    pub fn run_testbench(&self, config: &SimConfig) -> Vec<TestResult> {
+        let timeout = config.test_timeout.unwrap_or(DEFAULT_TEST_TIMEOUT);
+
+        let tests = self.top.iter().flat_map(|inst| {
+            self.mods
+                .get(&inst.module_name)
+                .into_iter()
+                .flat_map(|module| &module.initial_blocks)
+                .enumerate()
+                .filter_map(|(index, block)| Some((index, block.testbench.as_ref()?)))
+                .enumerate()
+                .map(move |(number, (index, name))| {
+                    let name = match name.is_empty() {
+                        true => format!("test{}", number + 1),
+                        false => name.to_owned(),
+                    };
+                    (inst.path.as_str(), index, format!("{}.{}", inst.path, name))
+                })
+        });
+
+        tests
+            .map(|(path, index, name)| {
+                info!("running test '{}'", name);
+
+                let start = std::time::Instant::now();
+                let result = self.simulate(timeout, Some((path, index)), config);
+                let duration_ns = start.elapsed().as_secs_f64() * 1e9;
+
+                let failure = match result.check_system_calls() {
+                    Err(e) => Some(e),
+                    Ok(()) => (result.status != SimStatus::Finished)
+                        .then_some(SimError::Unfinished(result.status)),
+                };
+                match &failure {
+                    Some(e) => error!("test '{}' failed: {}", name, e),
+                    None => info!("test '{}' passed", name),
+                }
+
+                TestResult {
+                    name,
+                    passed: failure.is_none(),
+                    failure,
+                    duration_ns,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Module {
+    /// Evaluates the module's combinational logic for the given input
+    /// values, returning the value of every output
+    ///
+    /// Continuous assignments and `always_comb` blocks each run once, after
+    /// the logic driving the signals they read. Inputs missing from `inputs`
+    /// are X. Logic in a combinational loop runs repeatedly until its values
+    /// settle
+    ///
+    /// Parameters take their default values, and generate blocks are
+    /// unrolled with them before anything is evaluated
+    pub fn evaluate_comb(
+        &self,
+        inputs: &HashMap<String, LogicValue>,
+    ) -> HashMap<String, LogicValue> {
+        let module = self.with_default_params();
+        let mut signals: HashMap<String, LogicValue> = HashMap::new();
+        let mut signed: HashSet<String> = HashSet::new();
+        let mut descending: HashSet<String> = HashSet::new();
+        declare_signals(
+            &module,
+            "",
+            &mut signals,
+            &mut signed,
+            &mut descending,
+            &HashMap::new(),
+        );
+
+        let ports = &module.io.inputs;
+        // Inputs are unknown rather than floating until they are given
+        for input in ports {
+            signals.insert(input.var.name.to_owned(), LogicValue::new(input.var.width));
+        }
+        for (name, value) in inputs {
+            match ports.iter().find(|input| input.var.name == *name) {
+                Some(input) => {
+                    signals.insert(name.to_owned(), value.resize(input.var.width));
+                }
+                None => warn!("'{}' is not an input of module '{}'", name, self.name),
+            }
+        }
+
+        module.settle_comb(&mut signals, &signed, &descending);
+
+        module
+            .io
+            .outputs
+            .iter()
+            .map(|output| &output.var)
+            .chain(self.io.inouts.iter().map(|inout| &inout.var))
+            .map(|var| (var.name.to_owned(), signals[&var.name].clone()))
+            .collect()
+    }
+
+    /// Applies a clock edge to a module simulated on its own, returning the
+    /// names of the signals that changed
+    ///
+    /// `signal` is set to the value after the edge, and every `always_ff`
+    /// block triggered by the edge runs. Their non-blocking assignments are
+    /// all evaluated before any is applied, so blocks see each other's values
+    /// from before the edge. Combinational logic is settled before the edge
+    /// and after the updates
+    ///
+    /// As with `evaluate_comb`, parameters take their default values
+    pub fn clock_edge(
+        &self,
+        signal: &str,
+        edge: Edge,
+        state: &mut ModuleState,
+    ) -> BTreeSet<String> {
+        let module = self.with_default_params();
+        let before = state.signals.clone();
+        module.settle_comb(&mut state.signals, &state.signed, &state.descending);
+
+        if let Some(value) = state.signals.get_mut(signal) {
+            let new_state = match edge {
+                Edge::Posedge => LogicState::One,
+                Edge::Negedge => LogicState::Zero,
+            };
+            value.set_bit(0, new_state);
+        }
+
+        let triggered = |event: &EdgeEvent| event.signal == signal && event.edge == edge;
+        let mut nba: Vec<Update> = Vec::new();
+
+        for block in &module.ff_blocks {
+            if triggered(&block.clock) || block.reset.as_ref().is_some_and(triggered) {
+                let mut code = Vec::new();
+                compile(&block.body, &module.tasks, &mut code);
+                run_untimed(
+                    &code,
+                    "",
+                    &mut state.signals,
+                    &mut nba,
+                    &module.functions,
+                    &state.signed,
+                    &state.descending,
+                );
+            }
+        }
+
+        for update in &nba {
+            apply_update(&mut state.signals, update);
+        }
+        module.settle_comb(&mut state.signals, &state.signed, &state.descending);
+
+        state
+            .signals
+            .iter()
+            .filter(|(name, value)| before.get(*name) != Some(value))
+            .map(|(name, _)| name.to_owned())
+            .collect()
+    }
+
+    /// Copy of the module with its parameters at their default values and
+    /// its generate blocks unrolled, for evaluating it on its own
+    fn with_default_params(&self) -> Module {
+        self.generate(&self.param_values(&HashMap::new()))
+            .unwrap_or_else(|e| {
+                warn!("'{}' is only partly evaluated: {}", self.name, e);
+                self.clone()
+            })
+    }
+
+    /// Runs the module's continuous assignments and `always_comb` blocks
+    /// until their values settle
+    fn settle_comb(
+        &self,
+        signals: &mut HashMap<String, LogicValue>,
+        signed: &HashSet<String>,
+        descending: &HashSet<String>,
+    ) {
+        let blocks: Vec<Vec<LogicStmt>> = self
+            .assigns
+            .iter()
+            .map(|assign| {
+                vec![LogicStmt::BlockingAssign {
+                    lhs: assign.lhs.clone(),
+                    rhs: assign.rhs.clone(),
+                }]
+            })
+            .chain(self.comb_blocks.iter().map(|block| block.body.clone()))
+            .collect();
+        let code: Vec<Vec<Instr>> = blocks
+            .iter()
+            .map(|block| {
+                let mut code = Vec::new();
+                compile(block, &self.tasks, &mut code);
+                code
+            })
+            .collect();
+        let (order, cyclic) = comb_order(&blocks);
+
+        let evaluate = |signals: &mut HashMap<String, LogicValue>| {
+            for &index in &order {
+                let mut nba: Vec<Update> = Vec::new();
+                run_untimed(
+                    &code[index],
+                    "",
+                    signals,
+                    &mut nba,
+                    &self.functions,
+                    signed,
+                    descending,
+                );
+                nba.iter().for_each(|update| {
+                    apply_update(signals, update);
+                });
+            }
+        };
+
+        evaluate(signals);
+        if cyclic {
+            warn!("module '{}' has a combinational loop", self.name);
+
+            let settled = (0..MAX_DELTA_CYCLES).any(|_| {
+                let before = signals.clone();
+                evaluate(signals);
+                before == *signals
+            });
+            if !settled {
+                error!(
+                    "combinational loop in module '{}' did not settle",
+                    self.name
+                );
+            }
+        }
+    }
+}
+
+impl FunctionDef {
+    /// Runs the function with the given argument values, returning its value
+    ///
+    /// The body sees only the arguments and its own variables, which start
+    /// out as X or zero for two-state types, and may call any of `functions`
+    /// in turn. Timing controls are ignored
+    pub fn call(&self, args: &[LogicValue], functions: &[FunctionDef]) -> LogicValue {
+        trace!("calling function '{}'", self.name);
+
+        let mut signals: HashMap<String, LogicValue> = std::iter::once(&self.ret)
+            .chain(&self.vars)
+            .map(|var| (var.name.to_owned(), var.initial_value()))
+            .collect();
+        for (arg, value) in self.args.iter().zip(args) {
+            signals.insert(arg.name.to_owned(), value.resize(arg.width));
+        }
+        let signed: HashSet<String> = std::iter::once(&self.ret)
+            .chain(&self.vars)
+            .chain(&self.args)
+            .filter(|var| var.signed)
+            .map(|var| var.name.to_owned())
+            .collect();
+
+        let mut code = Vec::new();
+        compile(&self.body, &[], &mut code);
+        let mut nba: Vec<Update> = Vec::new();
+
+        let descending = HashSet::new();
+        match run_untimed(
+            &code,
+            "",
+            &mut signals,
+            &mut nba,
+            functions,
+            &signed,
+            &descending,
+        ) {
+            Some(value) => {
+                let lookup = ScopedLookup {
+                    signals: &signals,
+                    scope: "",
+                    time: 0,
+                    time_unit: 1,
+                    functions,
+                    signed: &signed,
+                    descending: &descending,
+                    system: None,
+                };
+                assigned_value(&Expr::Ident(self.ret.name.to_owned()), value, &lookup)
+            }
+            None => signals
+                .remove(&self.ret.name)
+                .unwrap_or_else(|| LogicValue::new(self.ret.width)),
+        }
+    }
+}
+
+/// Signal values of a module simulated on its own, one clock edge at a time
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModuleState {
+    /// Current value of every port, variable and parameter, by name
+    pub signals: HashMap<String, LogicValue>,
+
+    /// Names of the signals and parameters declared `signed`
+    pub signed: HashSet<String>,
+
+    /// Names of the memories whose address range counts down, as in
+    /// `mem [3:0]`
+    pub descending: HashSet<String>,
+}
+
+impl ModuleState {
+    /// Creates the state of a module before simulation starts, with every
+    /// port and variable X, or zero for two-state types, and parameters at
+    /// their default values
+    pub fn new(module: &Module) -> Self {
+        let module = module.with_default_params();
+        let mut signals: HashMap<String, LogicValue> = HashMap::new();
+        let mut signed: HashSet<String> = HashSet::new();
+        let mut descending: HashSet<String> = HashSet::new();
+        declare_signals(
+            &module,
+            "",
+            &mut signals,
+            &mut signed,
+            &mut descending,
+            &HashMap::new(),
+        );
+
+        Self {
+            signals,
+            signed,
+            descending,
+        }
+    }
+
+    /// Sets a signal, resizing the value to the signal's width
+    pub fn set(&mut self, name: &str, value: &LogicValue) {
+        match self.signals.get_mut(name) {
+            Some(current) => *current = value.resize(current.width()),
+            None => warn!("'{}' is not a signal of the module", name),
+        }
+    }
+
+    /// Current value of a signal
+    pub fn get(&self, name: &str) -> Option<&LogicValue> {
+        self.signals.get(name)
+    }
+}
+
+/// Partial or full update of a signal's value
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Update {
+    /// Hierarchical signal name
+    pub signal: String,
+
+    /// Bits written, as `(high, low)`, or `None` for the whole signal
+    pub range: Option<(u64, u64)>,
+
+    /// Value written to the range
+    pub value: LogicValue,
+}
+
+/// Values visible from a module instance, looked up by local name
+pub(crate) struct ScopedLookup<'a> {
+    /// Every signal in the simulation, by hierarchical name
+    pub signals: &'a HashMap<String, LogicValue>,
+
+    /// Hierarchical name of the instance, as in `top.u_add`
+    pub scope: &'a str,
+
+    /// Current simulation time in precision steps
+    pub time: u64,
+
+    /// Number of precision steps in one time unit
+    pub time_unit: u64,
+
+    /// Functions defined by the instance
+    pub functions: &'a [FunctionDef],
+
+    /// Hierarchical names of the signals and parameters declared `signed`
+    pub signed: &'a HashSet<String>,
+
+    /// Hierarchical names of the memories whose address range counts down,
+    /// as in `mem [3:0]`
+    pub descending: &'a HashSet<String>,
+
+    /// Runs `$system` commands, or `None` outside of a simulation, where
+    /// they are never run
+    pub system: Option<&'a SystemCalls>,
+}
+
+impl ValueLookup for ScopedLookup<'_> {
+    fn value(&self, name: &str) -> Option<LogicValue> {
+        self.signals.get(&scoped_name(self.scope, name)).cloned()
+    }
+
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn time_unit(&self) -> u64 {
+        self.time_unit
+    }
+
+    fn memory_bounds(&self, name: &str) -> Option<(u64, u64)> {
+        element_bounds(self.signals.keys(), &scoped_name(self.scope, name))
+    }
+
+    fn functions(&self) -> &[FunctionDef] {
+        self.functions
+    }
+
+    fn is_signed(&self, name: &str) -> bool {
+        self.signed.contains(&scoped_name(self.scope, name))
+    }
+
+    fn system(&self, cmd: &str) -> Option<i32> {
+        self.system?.run(cmd)
+    }
+}
+
+/// Runs `$system` commands for a simulation, as its configuration allows
+#[derive(Debug, Default)]
+pub(crate) struct SystemCalls {
+    /// Configuration of the run, which may allow system calls
+    config: SimConfig,
+
+    /// Commands that were not allowed to run, in call order
+    forbidden: RefCell<Vec<String>>,
+}
+
+impl SystemCalls {
+    /// Runs a command as by `sys_task::system`, returning its exit code, or
+    /// `None` if it failed to run or was not allowed to
+    fn run(&self, cmd: &str) -> Option<i32> {
+        match sys_task::system(&self.config, cmd) {
+            Ok(code) => Some(code),
+            Err(SimError::SystemCallForbidden(cmd)) => {
+                self.forbidden.borrow_mut().push(cmd);
+                None
+            }
+            Err(e) => {
+                error!("$system(\"{}\") failed: {}", cmd, e);
+                None
+            }
+        }
+    }
+}
+
+/// Checks if changing the signal `changed` changes `signal`, as it does when
+/// they are the same signal or `changed` is an element of the memory
+/// `signal`
+fn changes_signal(changed: &str, signal: &str) -> bool {
+    changed
+        .strip_prefix(signal)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('['))
+}
+
+/// Functions defined by the instance `scope`
+fn scope_functions<'a>(
+    functions: &'a HashMap<String, Vec<FunctionDef>>,
+    scope: &str,
+) -> &'a [FunctionDef] {
+    functions.get(scope).map_or(&[], Vec::as_slice)
+}
+
+/// Hierarchical name of `name` declared in the instance `scope`
+///
+/// An empty scope leaves the name as it is, for evaluating a module on its
+/// own
+pub(crate) fn scoped_name(scope: &str, name: &str) -> String {
+    match scope.is_empty() {
+        true => name.to_owned(),
+        false => format!("{}.{}", scope, name),
+    }
+}
+
+/// Works out the signal updates made by assigning `value` to `lhs`
+///
+/// Bit and part selects with unknown indices write nothing, as in
+/// SystemVerilog. A concatenation is split between its parts, most
+/// significant first
+pub(crate) fn lvalue_updates(lhs: &Expr, value: &LogicValue, lookup: &ScopedLookup) -> Vec<Update> {
+    let update = |name: &String, range: Option<(u64, u64)>| Update {
+        signal: scoped_name(lookup.scope, name),
+        range,
+        value: value.clone(),
+    };
+
+    match lhs {
+        Expr::Ident(name) => vec![update(name, None)],
+        Expr::BitSelect(base, index) => {
+            let element = memory_element(base, index, lookup);
+            match (element, &**base, const_index(index, lookup)) {
+                (Some(element), ..) => vec![update(&element, None)],
+                (None, Expr::Ident(name), Some(index)) => {
+                    vec![update(name, Some((index, index)))]
+                }
+                _ => Vec::new(),
+            }
+        }
+        Expr::PartSelect(base, msb, lsb) => {
+            match (&**base, const_index(msb, lookup), const_index(lsb, lookup)) {
+                (Expr::Ident(name), Some(msb), Some(lsb)) => {
+                    vec![update(name, Some((msb.max(lsb), msb.min(lsb))))]
+                }
+                _ => Vec::new(),
+            }
+        }
+        Expr::PartSelectUp(base, start, _) | Expr::PartSelectDown(base, start, _) => {
+            let width = expr_width(lhs, lookup);
+            let low = match (lhs, const_index(start, lookup)) {
+                (Expr::PartSelectUp(..), Some(start)) => Some(start),
+                (_, Some(start)) => (start + 1).checked_sub(width),
+                _ => None,
+            };
+
+            match (&**base, low) {
+                (Expr::Ident(name), Some(low)) => vec![update(name, Some((low + width - 1, low)))],
+                _ => Vec::new(),
+            }
+        }
+        Expr::Concat(parts) => {
+            let mut low = value.width();
+            parts
+                .iter()
+                .flat_map(|part| {
+                    let width = expr_width(part, lookup);
+                    low = low.saturating_sub(width);
+                    let part_value = value.get_range(low + width - 1, low);
+                    lvalue_updates(part, &part_value, lookup)
+                })
+                .collect()
+        }
+        _ => {
+            warn!("cannot assign to '{}'", crate::expr::expr_sv_string(lhs));
+            Vec::new()
+        }
+    }
+}
+
+/// Works out the signal updates made by the assignment `lhs = rhs`
+///
+/// An array literal assigned to a whole memory, as in `mem = '{1, 2, 3}`,
+/// writes its values to the memory's elements from the left bound of its
+/// address range to the right, each at the width of the element.
+/// `mem = '{default: 0}` writes the same value to every element
+fn assign_updates(lhs: &Expr, rhs: &Expr, lookup: &ScopedLookup) -> Vec<Update> {
+    let Expr::Ident(name) = lhs else {
+        return lvalue_updates(lhs, &assigned_value(lhs, rhs, lookup), lookup);
+    };
+    let (Some((low, high)), Expr::ArrayLiteral(_) | Expr::DefaultLiteral(_)) =
+        (lookup.memory_bounds(name), rhs)
+    else {
+        return lvalue_updates(lhs, &assigned_value(lhs, rhs, lookup), lookup);
+    };
+
+    let mut addresses: Vec<u64> = (low..=high).collect();
+    if lookup.descending.contains(&scoped_name(lookup.scope, name)) {
+        addresses.reverse();
+    }
+    let values: Vec<&Expr> = match rhs {
+        Expr::ArrayLiteral(values) => values.iter().collect(),
+        Expr::DefaultLiteral(value) => vec![value; addresses.len()],
+        _ => Vec::new(),
+    };
+
+    if values.len() != addresses.len() {
+        warn!(
+            "'{}' has {} elements, but is assigned {} values",
+            name,
+            addresses.len(),
+            values.len()
+        );
+    }
+
+    addresses
+        .into_iter()
+        .zip(values)
+        .map(|(address, value)| {
+            let element = format!("{}[{}]", name, address);
+            let width = lookup.value(&element).map_or(1, |current| current.width());
+            Update {
+                signal: scoped_name(lookup.scope, &element),
+                range: None,
+                value: eval_expr_sized(value, width, lookup),
+            }
+        })
+        .collect()
+}
+
+/// Evaluates the right-hand side of an assignment at the width of its target
+///
+/// An undeclared target, such as a loop variable declared in a `for`
+/// header, takes the width of the expression instead
+pub(crate) fn assigned_value(lhs: &Expr, rhs: &Expr, lookup: &ScopedLookup) -> LogicValue {
+    match lhs {
+        Expr::Ident(name) if lookup.value(name).is_none() => eval_expr(rhs, lookup),
+        _ => eval_expr_sized(rhs, expr_width(lhs, lookup), lookup),
+    }
+}
+
+/// Names of the signals written by an assignment target, as the `a` and `b`
+/// of `{a[3:0], b}`
+fn lvalue_signals(lhs: &Expr) -> Vec<&str> {
+    match lhs {
+        Expr::Ident(name) => vec![name],
+        Expr::BitSelect(base, _)
+        | Expr::PartSelect(base, _, _)
+        | Expr::PartSelectUp(base, _, _)
+        | Expr::PartSelectDown(base, _, _) => lvalue_signals(base),
+        Expr::Concat(targets) => targets.iter().flat_map(lvalue_signals).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Applies an update to a set of signals, returning the signal's previous
+/// value if it changed
+///
+/// Signals written before being declared, such as loop variables declared in
+/// a `for` header, are created with the width of the value written
+pub(crate) fn apply_update(
+    signals: &mut HashMap<String, LogicValue>,
+    update: &Update,
+) -> Option<LogicValue> {
+    let current = signals
+        .entry(update.signal.to_owned())
+        .or_insert_with(|| LogicValue::new(update.value.width()));
+    let old = current.clone();
+
+    match update.range {
+        Some((high, low)) => current.set_range(high, low, &update.value),
+        None => *current = update.value.resize(current.width()),
+    }
+
+    (*current != old).then_some(old)
+}
+
+/// Checks if a change of a signal from `old` to `new` is the given edge
+///
+/// Only the least significant bit is considered. A rising edge is any change
+/// from 0 or towards 1, as in `0 -> x` or `z -> 1`, and a falling edge the
+/// reverse
+pub(crate) fn is_edge(edge: Edge, old: &LogicValue, new: &LogicValue) -> bool {
+    let (old, new) = (old.get_bit(0), new.get_bit(0));
+
+    old != new
+        && match edge {
+            Edge::Posedge => old == LogicState::Zero || new == LogicState::One,
+            Edge::Negedge => old == LogicState::One || new == LogicState::Zero,
+        }
+}
+
+/// Checks if the labels of a case item match the case expression
+///
+/// `casez` treats Z and `?` bits on either side as matching anything, and
+/// `casex` does the same for X bits as well
+fn case_matches(kind: CaseKind, value: &LogicValue, label: &LogicValue) -> bool {
+    let width = value.width().max(label.width());
+    let (value, label) = (value.resize(width), label.resize(width));
+
+    value.bits().iter().zip(label.bits()).all(|(a, b)| {
+        let wildcard = |bit: &LogicState| match kind {
+            CaseKind::Case => false,
+            CaseKind::Casez => *bit == LogicState::Z,
+            CaseKind::Casex => bit.is_unknown(),
+        };
+
+        a == b || wildcard(a) || wildcard(b)
+    })
+}
+
+/// Single step of a compiled process
+#[derive(Debug, Clone)]
+enum Instr {
+    /// Blocking or non-blocking assignment
+    Assign {
+        lhs: Expr,
+        rhs: Expr,
+        blocking: bool,
+    },
+
+    /// Evaluate an assignment, holding its updates until they are released
+    Hold { lhs: Expr, rhs: Expr },
+
+    /// Apply the held updates, as a blocking assignment
+    Release,
+
+    /// Apply the held updates as a non-blocking assignment once the delay
+    /// has passed, without waiting for it
+    ReleaseAfter(Delay),
+
+    /// Continue at the given instruction
+    Jump(usize),
+
+    /// Continue at the given instruction if the condition is not true
+    JumpUnless(Expr, usize),
+
+    /// Continue at the given instruction if no label of a case item matches
+    JumpUnlessCase {
+        kind: CaseKind,
+        expr: Expr,
+        labels: Vec<Expr>,
+        target: usize,
+    },
+
+    /// Push a repeat count
+    PushCount(Expr),
+
+    /// Pop the repeat count and continue at the given instruction if it is
+    /// zero, otherwise decrement it
+    LoopCount(usize),
+
+    /// Pop the repeat count of a loop left by `break`
+    PopCount,
+
+    /// `break`, until the loop around it replaces it with a jump. Outside of
+    /// any loop it does nothing
+    Break,
+
+    /// `continue`, until the loop around it replaces it with a jump. Outside
+    /// of any loop it does nothing
+    Continue,
+
+    /// Suspend for a delay
+    Delay(Delay),
+
+    /// Suspend until one of the events happens
+    Wait(Vec<EventTrigger>),
+
+    /// Start each branch as a new process, waiting for them as given
+    Fork(Vec<Rc<Vec<Instr>>>, JoinKind),
+
+    /// Output task call, with the radix of arguments printed outside of a
+    /// format string
+    Display(DisplayTask, char, Vec<Expr>),
+
+    /// Call to any other system task
+    SysTask(String, Vec<Expr>),
+
+    /// Return from a function or task, with the value returned if one is
+    /// given
+    Return(Option<Expr>),
+}
+
+/// Compiles statements to instructions, appending them to `code`
+///
+/// Calls to any of `tasks` are compiled in place of the call
+fn compile(stmts: &[LogicStmt], tasks: &[TaskDef], code: &mut Vec<Instr>) {
+    for stmt in stmts {
+        compile_stmt(stmt, tasks, code);
+    }
+}
+
+/// Compiles a single statement to instructions, appending them to `code`
+fn compile_stmt(stmt: &LogicStmt, tasks: &[TaskDef], code: &mut Vec<Instr>) {
+    match stmt {
+        LogicStmt::BlockingAssign { lhs, rhs } => code.push(Instr::Assign {
+            lhs: lhs.clone(),
+            rhs: rhs.clone(),
+            blocking: true,
+        }),
+        LogicStmt::NonBlockingAssign { lhs, rhs } => code.push(Instr::Assign {
+            lhs: lhs.clone(),
+            rhs: rhs.clone(),
+            blocking: false,
+        }),
+        LogicStmt::DelayedAssign {
+            lhs,
+            rhs,
+            delay,
+            blocking,
+        } => {
+            code.push(Instr::Hold {
+                lhs: lhs.clone(),
+                rhs: rhs.clone(),
+            });
+            match blocking {
+                true => code.extend([Instr::Delay(delay.clone()), Instr::Release]),
+                false => code.push(Instr::ReleaseAfter(delay.clone())),
+            }
+        }
+        LogicStmt::If(stmt) => {
+            let mut exits: Vec<usize> = Vec::new();
+            let branches = std::iter::once((&stmt.cond, &stmt.then))
+                .chain(stmt.else_if.iter().map(|(cond, body)| (cond, body)));
+
+            for (cond, body) in branches {
+                let check = code.len();
+                code.push(Instr::JumpUnless(cond.clone(), 0));
+                compile(body, tasks, code);
+                exits.push(code.len());
+                code.push(Instr::Jump(0));
+                patch(code, check);
+            }
+            if let Some(body) = &stmt.else_ {
+                compile(body, tasks, code);
+            }
+            exits.into_iter().for_each(|exit| patch(code, exit));
+        }
+        LogicStmt::Case {
+            kind,
+            expr,
+            items,
+            default,
+        } => {
+            let mut exits: Vec<usize> = Vec::new();
+
+            for item in items {
+                let check = code.len();
+                code.push(Instr::JumpUnlessCase {
+                    kind: *kind,
+                    expr: expr.clone(),
+                    labels: item.labels.clone(),
+                    target: 0,
+                });
+                compile(&item.body, tasks, code);
+                exits.push(code.len());
+                code.push(Instr::Jump(0));
+                patch(code, check);
+            }
+            if let Some(body) = default {
+                compile(body, tasks, code);
+            }
+            exits.into_iter().for_each(|exit| patch(code, exit));
+        }
+        LogicStmt::For(stmt) => {
+            compile_stmt(&stmt.init, tasks, code);
+            let start = code.len();
+            code.push(Instr::JumpUnless(stmt.cond.clone(), 0));
+            compile(&stmt.body, tasks, code);
+            let step = code.len();
+            compile_stmt(&stmt.step, tasks, code);
+            code.push(Instr::Jump(start));
+            patch(code, start);
+            patch_loop(code, start, step);
+        }
+        LogicStmt::Loop(LoopStmt::While { cond, body }) => {
+            let start = code.len();
+            code.push(Instr::JumpUnless(cond.clone(), 0));
+            compile(body, tasks, code);
+            code.push(Instr::Jump(start));
+            patch(code, start);
+            patch_loop(code, start, start);
+        }
+        LogicStmt::Loop(LoopStmt::DoWhile { body, cond }) => {
+            let start = code.len();
+            compile(body, tasks, code);
+            let check = code.len();
+            code.push(Instr::JumpUnless(cond.clone(), 0));
+            code.push(Instr::Jump(start));
+            patch(code, check);
+            patch_loop(code, start, check);
+        }
+        LogicStmt::Loop(LoopStmt::Repeat { count, body }) => {
+            code.push(Instr::PushCount(count.clone()));
+            let start = code.len();
+            code.push(Instr::LoopCount(0));
+            compile(body, tasks, code);
+            code.push(Instr::Jump(start));
+            // Only reached by `break`, as the count is popped when it runs out
+            patch_loop(code, start, start);
+            code.push(Instr::PopCount);
+            patch(code, start);
+        }
+        LogicStmt::Loop(LoopStmt::Forever { body }) => {
+            let start = code.len();
+            compile(body, tasks, code);
+            code.push(Instr::Jump(start));
+            patch_loop(code, start, start);
+        }
+        LogicStmt::ForkJoin { branches, join } => {
+            let branches = branches
+                .iter()
+                .map(|body| {
+                    let mut branch = Vec::new();
+                    compile(body, tasks, &mut branch);
+                    Rc::new(branch)
+                })
+                .collect();
+            code.push(Instr::Fork(branches, *join));
+        }
+        LogicStmt::Delay { delay, body } => {
+            code.push(Instr::Delay(delay.clone()));
+            compile(body, tasks, code);
+        }
+        LogicStmt::EventControl { events, body } => {
+            let events = match events {
+                Some(events) => events.clone(),
+                None => stmt_reads(body)
+                    .into_iter()
+                    .map(EventTrigger::Change)
+                    .collect(),
+            };
+            code.push(Instr::Wait(events));
+            compile(body, tasks, code);
+        }
+        LogicStmt::Wait { cond, body } => {
+            // Check the condition again after every change to what it reads
+            let check = code.len();
+            code.push(Instr::JumpUnless(cond.clone(), check + 2));
+            code.push(Instr::Jump(check + 4));
+            let events = expr_reads(cond)
+                .into_iter()
+                .map(EventTrigger::Change)
+                .collect();
+            code.push(Instr::Wait(events));
+            code.push(Instr::Jump(check));
+            compile(body, tasks, code);
+        }
+        LogicStmt::Display { task, radix, args } => {
+            code.push(Instr::Display(*task, *radix, args.clone()))
+        }
+        LogicStmt::SysTaskCall { name, args } => {
+            code.push(Instr::SysTask(name.to_owned(), args.clone()))
+        }
+        LogicStmt::TaskCall { name, args } => match tasks.iter().find(|task| task.name == *name) {
+            Some(task) => compile_task_call(task, args, tasks, code),
+            None => warn!("task '{}' is not defined, so its call is skipped", name),
+        },
+        LogicStmt::Return(value) => code.push(Instr::Return(value.clone())),
+        LogicStmt::Break => code.push(Instr::Break),
+        LogicStmt::Continue => code.push(Instr::Continue),
+    }
+}
+
+/// Compiles a call to `task` with the arguments `args` in place of the call
+///
+/// The task's arguments and variables are signals of the calling scope, as
+/// declared by `declare_signals`. Inputs are copied in before the body runs,
+/// and outputs are copied out once it ends, including when a `return` ends
+/// it early. Arguments left out of the call take their default values. A
+/// task calling itself would never finish compiling, so such calls are
+/// skipped
+fn compile_task_call(task: &TaskDef, args: &[Expr], tasks: &[TaskDef], code: &mut Vec<Instr>) {
+    trace!("compiling call to task '{}'", task.name);
+
+    let actuals: Vec<Option<&Expr>> = task
+        .args
+        .iter()
+        .enumerate()
+        .map(|(idx, arg)| args.get(idx).or(arg.default.as_ref()))
+        .collect();
+    if args.len() > task.args.len() || actuals.contains(&None) {
+        warn!(
+            "task '{}' takes {} arguments, but is given {}",
+            task.name,
+            task.args.len(),
+            args.len()
+        );
+    }
+
+    let names: HashMap<String, Expr> = task
+        .args
+        .iter()
+        .map(|arg| &arg.var)
+        .chain(&task.vars)
+        .map(|var| {
+            (
+                var.name.to_owned(),
+                Expr::Ident(task_local(task, &var.name)),
+            )
+        })
+        .collect();
+
+    let given = || {
+        task.args
+            .iter()
+            .zip(&actuals)
+            .filter_map(|(arg, actual)| Some((arg, (*actual)?)))
+    };
+
+    for (arg, actual) in given() {
+        if arg.direction != PortDirection::Output {
+            code.push(Instr::Assign {
+                lhs: names[&arg.var.name].clone(),
+                rhs: actual.clone(),
+                blocking: true,
+            });
+        }
+    }
+
+    let start = code.len();
+    let others: Vec<TaskDef> = tasks
+        .iter()
+        .filter(|other| other.name != task.name)
+        .cloned()
+        .collect();
+    compile(&rename_stmts(&task.body, &names), &others, code);
+
+    let end = code.len();
+    for instr in &mut code[start..] {
+        if let Instr::Return(_) = instr {
+            *instr = Instr::Jump(end);
+        }
+    }
+
+    for (arg, actual) in given() {
+        if arg.direction != PortDirection::Input {
+            code.push(Instr::Assign {
+                lhs: actual.clone(),
+                rhs: names[&arg.var.name].clone(),
+                blocking: true,
+            });
+        }
+    }
+}
+
+/// Name of the signal holding the argument or variable `name` of `task`
+fn task_local(task: &TaskDef, name: &str) -> String {
+    format!("{}.{}", task.name, name)
+}
+
+/// Points the jump at `at` to the end of `code`
+fn patch(code: &mut [Instr], at: usize) {
+    let end = code.len();
+
+    match &mut code[at] {
+        Instr::Jump(target)
+        | Instr::JumpUnless(_, target)
+        | Instr::JumpUnlessCase { target, .. }
+        | Instr::LoopCount(target) => *target = end,
+        _ => unreachable!("only jumps are patched"),
+    }
+}
+
+/// Replaces the `break` and `continue` instructions of a loop compiled from
+/// `start` with jumps to the end of the code and to `next`
+///
+/// Those of loops nested inside have already been replaced, so every one
+/// left belongs to this loop
+fn patch_loop(code: &mut [Instr], start: usize, next: usize) {
+    let exit = code.len();
+
+    for instr in &mut code[start..] {
+        match instr {
+            Instr::Break => *instr = Instr::Jump(exit),
+            Instr::Continue => *instr = Instr::Jump(next),
+            _ => (),
+        }
+    }
+}
+
+/// Runs a control flow instruction, returning the instruction to continue
+/// at if it jumps
+fn control_flow(instr: &Instr, counts: &mut Vec<u128>, lookup: &ScopedLookup) -> Option<usize> {
+    match instr {
+        Instr::Jump(target) => Some(*target),
+        Instr::JumpUnless(cond, target) => {
+            (eval_expr(cond, lookup).truth() != LogicState::One).then_some(*target)
+        }
+        Instr::JumpUnlessCase {
+            kind,
+            expr,
+            labels,
+            target,
+        } => {
+            let value = eval_expr(expr, lookup);
+            let matched = labels
+                .iter()
+                .any(|label| case_matches(*kind, &value, &eval_expr(label, lookup)));
+
+            (!matched).then_some(*target)
+        }
+        Instr::PushCount(count) => {
+            counts.push(eval_expr(count, lookup).to_u128().unwrap_or(0));
+            None
+        }
+        Instr::LoopCount(target) => match counts.last_mut() {
+            Some(0) | None => {
+                counts.pop();
+                Some(*target)
+            }
+            Some(count) => {
+                *count -= 1;
+                None
+            }
+        },
+        Instr::PopCount => {
+            counts.pop();
+            None
+        }
+        _ => unreachable!("only control flow instructions are run here"),
+    }
+}
+
+/// Runs compiled statements to completion, ignoring delays and event
+/// controls
+///
+/// Blocking assignments update `signals` straight away, while non-blocking
+/// assignments are added to `nba`, as are those with an intra-assignment
+/// delay. Forked branches run one after another.
+/// Returns the value of the `return` statement that ended the statements, if
+/// it gave one
+fn run_untimed<'c>(
+    code: &'c [Instr],
+    scope: &str,
+    signals: &mut HashMap<String, LogicValue>,
+    nba: &mut Vec<Update>,
+    functions: &[FunctionDef],
+    signed: &HashSet<String>,
+    descending: &HashSet<String>,
+) -> Option<&'c Expr> {
+    let mut pc = 0;
+    let mut counts: Vec<u128> = Vec::new();
+    let mut held: Vec<Update> = Vec::new();
+
+    for _ in 0..MAX_STEPS_PER_ACTIVATION {
+        let instr = code.get(pc)?;
+        pc += 1;
+
+        let lookup = ScopedLookup {
+            signals,
+            scope,
+            time: 0,
+            time_unit: 1,
+            functions,
+            signed,
+            descending,
+            system: None,
+        };
+
+        match instr {
+            Instr::Assign { lhs, rhs, blocking } => {
+                let updates = assign_updates(lhs, rhs, &lookup);
+
+                match blocking {
+                    true => updates.iter().for_each(|update| {
+                        apply_update(signals, update);
+                    }),
+                    false => nba.extend(updates),
+                }
+            }
+            Instr::Hold { lhs, rhs } => held = assign_updates(lhs, rhs, &lookup),
+            Instr::Release => std::mem::take(&mut held).iter().for_each(|update| {
+                apply_update(signals, update);
+            }),
+            Instr::ReleaseAfter(_) => nba.append(&mut held),
+            Instr::Jump(_)
+            | Instr::JumpUnless(..)
+            | Instr::JumpUnlessCase { .. }
+            | Instr::PushCount(_)
+            | Instr::LoopCount(_)
+            | Instr::PopCount => {
+                if let Some(target) = control_flow(instr, &mut counts, &lookup) {
+                    pc = target;
+                }
+            }
+            Instr::Break | Instr::Continue => {
+                warn!("break or continue outside of a loop is ignored")
+            }
+            Instr::Fork(branches, _) => {
+                for branch in branches {
+                    run_untimed(branch, scope, signals, nba, functions, signed, descending);
+                }
+            }
+            Instr::Delay(_) | Instr::Wait(_) => trace!("ignoring timing control"),
+            Instr::Display(..) | Instr::SysTask(..) => (),
+            Instr::Return(value) => return value.as_ref(),
+        }
+    }
+
+    error!(
+        "statements ran {} steps without finishing and were stopped",
+        MAX_STEPS_PER_ACTIVATION
+    );
+    None
+}
+
+/// Names of every signal assigned by a list of statements
+pub(crate) fn stmt_writes(stmts: &[LogicStmt]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+
+    fn targets(lhs: &Expr, names: &mut Vec<String>) {
+        match lhs {
+            Expr::Ident(name) => names.push(name.to_owned()),
+            Expr::BitSelect(base, _)
+            | Expr::PartSelect(base, ..)
+            | Expr::PartSelectUp(base, ..)
+            | Expr::PartSelectDown(base, ..) => targets(base, names),
+            Expr::Concat(parts) => parts.iter().for_each(|part| targets(part, names)),
+            _ => (),
+        }
+    }
+
+    fn writes(stmt: &LogicStmt, names: &mut Vec<String>) {
+        let body = |stmts: &[LogicStmt], names: &mut Vec<String>| {
+            stmts.iter().for_each(|stmt| writes(stmt, names))
+        };
+
+        match stmt {
+            LogicStmt::BlockingAssign { lhs, .. }
+            | LogicStmt::NonBlockingAssign { lhs, .. }
+            | LogicStmt::DelayedAssign { lhs, .. } => targets(lhs, names),
+            LogicStmt::If(stmt) => {
+                body(&stmt.then, names);
+                stmt.else_if
+                    .iter()
+                    .for_each(|(_, stmts)| body(stmts, names));
+                if let Some(stmts) = &stmt.else_ {
+                    body(stmts, names);
+                }
+            }
+            LogicStmt::Case { items, default, .. } => {
+                items.iter().for_each(|item| body(&item.body, names));
+                if let Some(stmts) = default {
+                    body(stmts, names);
+                }
+            }
+            LogicStmt::For(stmt) => {
+                writes(&stmt.init, names);
+                writes(&stmt.step, names);
+                body(&stmt.body, names);
+            }
+            LogicStmt::Loop(
+                LoopStmt::While { body: stmts, .. }
+                | LoopStmt::DoWhile { body: stmts, .. }
+                | LoopStmt::Repeat { body: stmts, .. }
+                | LoopStmt::Forever { body: stmts },
+            )
+            | LogicStmt::Delay { body: stmts, .. }
+            | LogicStmt::EventControl { body: stmts, .. }
+            | LogicStmt::Wait { body: stmts, .. } => body(stmts, names),
+            LogicStmt::ForkJoin { branches, .. } => {
+                branches.iter().for_each(|stmts| body(stmts, names))
+            }
+            LogicStmt::Display { .. }
+            | LogicStmt::SysTaskCall { .. }
+            | LogicStmt::TaskCall { .. }
+            | LogicStmt::Return(_)
+            | LogicStmt::Break
+            | LogicStmt::Continue => {}
+        }
+    }
+
+    stmts.iter().for_each(|stmt| writes(stmt, &mut names));
+    names
+}
+
+/// Orders blocks of combinational logic so that each runs after the blocks
+/// driving the signals it reads
+///
+/// Blocks in a combinational loop cannot be ordered, and are put last in
+/// source order. The returned flag is set if there are any
+fn comb_order(blocks: &[Vec<LogicStmt>]) -> (Vec<usize>, bool) {
+    let reads: Vec<Vec<String>> = blocks.iter().map(|block| stmt_reads(block)).collect();
+    let writes: Vec<Vec<String>> = blocks.iter().map(|block| stmt_writes(block)).collect();
+    let drives = |from: usize, to: usize| {
+        from != to && writes[from].iter().any(|name| reads[to].contains(name))
+    };
+
+    let mut order: Vec<usize> = Vec::new();
+    let mut placed = vec![false; blocks.len()];
+
+    while let Some(next) = (0..blocks.len())
+        .find(|&to| !placed[to] && (0..blocks.len()).all(|from| placed[from] || !drives(from, to)))
+    {
+        placed[next] = true;
+        order.push(next);
+    }
+
+    let cyclic = order.len() < blocks.len();
+    order.extend((0..blocks.len()).filter(|&index| !placed[index]));
+
+    (order, cyclic)
+}
+
+/// Adds every port, variable and parameter of a module instance named
+/// `scope` to a set of signals
+///
+/// Ports and variables start out as X, or zero for two-state types such as
+/// `int`, and parameters take their default values. Genvars only exist while
+/// generate loops are unrolled, so they are left out. The arguments and
+/// variables of each task are added as `task.name`, and variables declared by
+/// for loop headers are added unless the module declares a variable of the same
+/// name. Those declared `signed` are also added to `signed`, and memories whose
+/// address range counts down to `descending`
+fn declare_signals(
+    module: &Module,
+    scope: &str,
+    signals: &mut HashMap<String, LogicValue>,
+    signed: &mut HashSet<String>,
+    descending: &mut HashSet<String>,
+    overrides: &HashMap<String, LogicValue>,
+) {
+    let io = &module.io;
+    let declared = io
+        .inputs
+        .iter()
+        .map(|input| &input.var)
+        .chain(io.outputs.iter().map(|output| &output.var))
+        .chain(io.inouts.iter().map(|inout| &inout.var))
+        .chain(
+            module
+                .vars
+                .iter()
+                .filter(|var| !matches!(var.var_type, VarType::Genvar)),
+        )
+        .map(|var| (var.name.to_owned(), var))
+        .chain(module.tasks.iter().flat_map(|task| {
+            task.args
+                .iter()
+                .map(|arg| &arg.var)
+                .chain(&task.vars)
+                .map(move |var| (task_local(task, &var.name), var))
+        }));
+    for (name, var) in declared {
+        if var.array.is_some_and(|(left, right)| left > right) {
+            descending.insert(scoped_name(scope, &name));
+        }
+        let names: Vec<String> = match var.addresses() {
+            Some(addresses) => addresses
+                .map(|address| scoped_name(scope, &format!("{}[{}]", name, address)))
+                .collect(),
+            None => vec![scoped_name(scope, &name)],
+        };
+        for name in names {
+            if var.signed {
+                signed.insert(name.to_owned());
+            }
+            signals.insert(name, var.initial_value());
+        }
+    }
+
+    let bodies = module
+        .comb_blocks
+        .iter()
+        .map(|block| &block.body)
+        .chain(module.ff_blocks.iter().map(|block| &block.body))
+        .chain(module.latch_blocks.iter().map(|block| &block.body))
+        .chain(module.initial_blocks.iter().map(|block| &block.body))
+        .chain(module.final_blocks.iter().map(|block| &block.body));
+    for var in bodies.flat_map(|body| loop_vars(body)) {
+        if let Entry::Vacant(entry) = signals.entry(scoped_name(scope, &var.name)) {
+            if var.signed {
+                signed.insert(entry.key().to_owned());
+            }
+            entry.insert(var.initial_value());
+        }
+    }
+
+    for param in io.params.iter().chain(&module.params) {
+        let lookup = ScopedLookup {
+            signals,
+            scope,
+            time: 0,
+            time_unit: 1,
+            functions: &module.functions,
+            signed,
+            descending,
+            system: None,
+        };
+        let value = param.resolve(overrides, &lookup);
+        let name = scoped_name(scope, &param.name);
+        if param.data_type.as_ref().is_some_and(|ty| ty.signed) {
+            signed.insert(name.to_owned());
+        }
+        signals.insert(name, value);
+    }
+}
+
+/// Names of every signal read by a list of statements, which an `@*` event
+/// control or combinational block is sensitive to
+pub(crate) fn stmt_reads(stmts: &[LogicStmt]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+
+    fn lvalue_reads(lhs: &Expr, names: &mut Vec<String>) {
+        match lhs {
+            Expr::Ident(_) => (),
+            Expr::BitSelect(_, index) => collect_reads(index, names),
+            Expr::PartSelect(_, a, b)
+            | Expr::PartSelectUp(_, a, b)
+            | Expr::PartSelectDown(_, a, b) => {
+                collect_reads(a, names);
+                collect_reads(b, names);
+            }
+            Expr::Concat(parts) => parts.iter().for_each(|part| lvalue_reads(part, names)),
+            _ => collect_reads(lhs, names),
+        }
+    }
+
+    fn reads(stmt: &LogicStmt, names: &mut Vec<String>) {
+        let body = |stmts: &[LogicStmt], names: &mut Vec<String>| {
+            stmts.iter().for_each(|stmt| reads(stmt, names))
+        };
+
+        match stmt {
+            LogicStmt::BlockingAssign { lhs, rhs }
+            | LogicStmt::NonBlockingAssign { lhs, rhs }
+            | LogicStmt::DelayedAssign { lhs, rhs, .. } => {
+                lvalue_reads(lhs, names);
+                collect_reads(rhs, names);
+            }
+            LogicStmt::If(stmt) => {
+                collect_reads(&stmt.cond, names);
+                body(&stmt.then, names);
+                for (cond, stmts) in &stmt.else_if {
+                    collect_reads(cond, names);
+                    body(stmts, names);
+                }
+                if let Some(stmts) = &stmt.else_ {
+                    body(stmts, names);
+                }
+            }
+            LogicStmt::Case {
+                expr,
+                items,
+                default,
+                ..
+            } => {
+                collect_reads(expr, names);
+                for item in items {
+                    item.labels
+                        .iter()
+                        .for_each(|label| collect_reads(label, names));
+                    body(&item.body, names);
+                }
+                if let Some(stmts) = default {
+                    body(stmts, names);
+                }
+            }
+            LogicStmt::For(stmt) => {
+                reads(&stmt.init, names);
+                collect_reads(&stmt.cond, names);
+                reads(&stmt.step, names);
+                body(&stmt.body, names);
+            }
+            LogicStmt::Loop(LoopStmt::While { cond, body: stmts })
+            | LogicStmt::Loop(LoopStmt::DoWhile { body: stmts, cond })
+            | LogicStmt::Loop(LoopStmt::Repeat {
+                count: cond,
+                body: stmts,
+            })
+            | LogicStmt::Wait { cond, body: stmts } => {
+                collect_reads(cond, names);
+                body(stmts, names);
+            }
+            LogicStmt::Loop(LoopStmt::Forever { body: stmts })
+            | LogicStmt::Delay { body: stmts, .. }
+            | LogicStmt::EventControl { body: stmts, .. } => body(stmts, names),
+            LogicStmt::ForkJoin { branches, .. } => {
+                branches.iter().for_each(|stmts| body(stmts, names))
+            }
+            LogicStmt::Display { args, .. }
+            | LogicStmt::SysTaskCall { args, .. }
+            | LogicStmt::TaskCall { args, .. } => {
+                args.iter().for_each(|arg| collect_reads(arg, names))
+            }
+            LogicStmt::Return(value) => {
+                if let Some(value) = value {
+                    collect_reads(value, names);
+                }
+            }
+            LogicStmt::Break | LogicStmt::Continue => (),
+        }
+    }
+
+    stmts.iter().for_each(|stmt| reads(stmt, &mut names));
+    names
+}
+
+/// Output registered by `$monitor`
+#[derive(Debug)]
+struct Monitor {
+    /// Hierarchical name of the calling instance
+    scope: Rc<str>,
+
+    /// Arguments, printed as for `$display`
+    args: Vec<Expr>,
+
+    /// Hierarchical names of the signals the arguments read
+    reads: Vec<String>,
+
+    /// Radix of arguments printed outside of a format string
+    radix: char,
+
+    /// Values of the arguments when last printed, or `None` if the monitor
+    /// has not printed since it was set or turned back on
+    last: Option<Vec<LogicValue>>,
+
+    /// Whether the monitor is on, as set by `$monitoron` and `$monitoroff`
+    enabled: bool,
+}
+
+/// Scheduling state of a process
+#[derive(Debug, Clone, PartialEq)]
+enum ProcState {
+    /// Scheduled to run
+    Ready,
+
+    /// Waiting for a delay to pass
+    Sleeping,
+
+    /// Waiting for one of the events, by hierarchical signal name
+    Waiting(Vec<EventTrigger>),
+
+    /// Waiting for this many more forked branches to finish
+    Joining(usize),
+
+    /// Finished
+    Done,
+}
+
+/// Thread of execution, such as an `initial` block or continuous assignment
+#[derive(Debug)]
+struct Process {
+    /// Hierarchical name of the module instance the process belongs to
+    scope: Rc<str>,
+
+    /// Compiled statements
+    code: Rc<Vec<Instr>>,
+
+    /// Next instruction to run
+    pc: usize,
+
+    /// Active repeat loop counts, innermost last
+    counts: Vec<u128>,
+
+    /// Updates of an assignment with an intra-assignment delay, waiting for
+    /// the delay to pass
+    held: Vec<Update>,
+
+    /// Scheduling state
+    state: ProcState,
+
+    /// Process that forked this one, if any
+    parent: Option<usize>,
+
+    /// Strengths with which the process drives nets to 0 and to 1, if it is
+    /// a continuous assignment or port connection
+    driver: Option<(DriveStrength, DriveStrength)>,
+}
+
+/// Process resumption scheduled on the time queue
+#[derive(Debug)]
+struct Event {
+    /// Time in precision steps
+    time: u64,
+
+    /// Scheduling order, so events at the same time run first come first
+    /// served
+    seq: u64,
+
+    /// Process to resume
+    pid: usize,
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Self) -> bool {
+        (self.time, self.seq) == (other.time, other.seq)
+    }
+}
+
+impl Eq for Event {}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Ordered earliest first, so the max-heap `BinaryHeap` pops the next event
+impl Ord for Event {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.time, other.seq).cmp(&(self.time, self.seq))
+    }
+}
+
+/// Event-driven simulator
+///
+/// Every always block, initial block and continuous assignment runs as a
+/// process. Processes run until they wait for a delay or an event, and
+/// signal changes wake the processes waiting on them
+///
+/// Each time step is settled in delta cycles before time advances. A delta
+/// cycle runs every active process, and processes woken along the way run in
+/// the next one. Once no process is active, the non-blocking assignments made
+/// so far are applied together as one more delta cycle
+struct Simulator {
+    /// Length of a delay of one, in seconds
+    unit: f64,
+
+    /// Length of one time step, in seconds
+    precision: f64,
+
+    /// Current time in precision steps
+    now: u64,
+
+    /// Scheduling order of the next event
+    seq: u64,
+
+    /// Processes resuming at a later time
+    queue: BinaryHeap<Event>,
+
+    /// Processes to run in the next delta cycle of the current time step
+    active: VecDeque<usize>,
+
+    /// Non-blocking assignments waiting for the active processes to finish
+    nba: Vec<Update>,
+
+    /// Every process, running or not
+    processes: Vec<Process>,
+
+    /// Current value of every signal, by hierarchical name
+    signals: HashMap<String, LogicValue>,
+
+    /// Every change made so far
+    changes: Vec<SignalChange>,
+
+    /// Number of changes made before the current time step
+    step_start: usize,
+
+    /// `$strobe` calls waiting for the current time step to settle, with
+    /// the scope they were called from
+    strobes: Vec<(Rc<str>, char, Vec<Expr>)>,
+
+    /// Active `$monitor`, replaced by each new call
+    monitor: Option<Monitor>,
+
+    /// Text printed so far
+    output: String,
+
+    /// Set by `$finish` or `$stop` to stop the run
+    halted: Option<SimStatus>,
+
+    /// Code of the `final` blocks, with the scope each runs in
+    finals: Vec<(Rc<str>, Vec<Instr>)>,
+
+    /// File named by the last `$dumpfile` call
+    dump_file: Option<PathBuf>,
+
+    /// Waveform dump started by `$dumpvars`
+    dump: Option<Dump>,
+
+    /// Functions defined by each instance, by hierarchical instance name
+    functions: HashMap<String, Vec<FunctionDef>>,
+
+    /// Hierarchical names of the signals and parameters declared `signed`
+    signed: HashSet<String>,
+
+    /// Hierarchical names of the memories whose address range counts down
+    descending: HashSet<String>,
+
+    /// Drivers of every net, by hierarchical name
+    nets: HashMap<String, Net>,
+
+    /// Runs `$system` commands as the run's configuration allows
+    system: SystemCalls,
+
+    /// Assertion checking state of every instance, by hierarchical name
+    assertions: BTreeMap<String, AssertState>,
+}
+
+impl Simulator {
+    /// Creates a simulator with the given time unit and precision, in seconds
+    fn new(unit: f64, precision: f64) -> Self {
+        Self {
+            unit,
+            precision,
+            now: 0,
+            seq: 0,
+            queue: BinaryHeap::new(),
+            active: VecDeque::new(),
+            nba: Vec::new(),
+            processes: Vec::new(),
+            signals: HashMap::new(),
+            changes: Vec::new(),
+            step_start: 0,
+            strobes: Vec::new(),
+            monitor: None,
+            output: String::new(),
+            halted: None,
+            finals: Vec::new(),
+            dump_file: None,
+            dump: None,
+            functions: HashMap::new(),
+            signed: HashSet::new(),
+            descending: HashSet::new(),
+            nets: HashMap::new(),
+            system: SystemCalls::default(),
+            assertions: BTreeMap::new(),
+        }
+    }
+
+    /// Adds the signals and processes of a module instance named `scope`,
+    /// whose parameters take the values in `overrides` over their defaults
+    fn add_module(
+        &mut self,
+        module: &Module,
+        scope: &str,
+        overrides: &HashMap<String, LogicValue>,
+    ) {
+        trace!("adding module '{}' as '{}'", module.name, scope);
+
+        let generated;
+        let module = match module.generate(&module.param_values(overrides)) {
+            Ok(unrolled) => {
+                generated = unrolled;
+                &generated
+            }
+            Err(e) => {
+                error!("'{}' is not fully simulated: {}", module.name, e);
+                module
+            }
+        };
+
+        declare_signals(
+            module,
+            scope,
+            &mut self.signals,
+            &mut self.signed,
+            &mut self.descending,
+            overrides,
+        );
+        self.functions
+            .insert(scope.to_owned(), module.functions.clone());
+        self.assertions.entry(scope.to_owned()).or_default();
+
+        let io = &module.io;
+        let nets = io
+            .inputs
+            .iter()
+            .map(|input| &input.var)
+            .chain(io.outputs.iter().map(|output| &output.var))
+            .chain(io.inouts.iter().map(|inout| &inout.var))
+            .chain(&module.vars)
+            .filter(|var| var.var_type.net() && var.array.is_none());
+        for var in nets {
+            let name = scoped_name(scope, &var.name);
+            if let Some(value) = self.signals.get(&name) {
+                let net = Net::new(value.width());
+                self.nets.insert(name, net);
+            }
+        }
+
+        let scope: Rc<str> = Rc::from(scope);
+
+        for assign in &module.assigns {
+            let mut stmt = LogicStmt::BlockingAssign {
+                lhs: assign.lhs.clone(),
+                rhs: assign.rhs.clone(),
+            };
+            // The expression is read again once the delay ends, so changes
+            // that don't last as long as the delay never reach the net
+            if let Some(delay) = &assign.delay {
+                stmt = LogicStmt::Delay {
+                    delay: delay.clone(),
+                    body: vec![stmt],
+                };
+            }
+            let pid = self.add_comb_process(&scope, std::slice::from_ref(&stmt), &module.tasks);
+            self.add_driver(pid, &assign.lhs, assign.strength);
+        }
+        for block in &module.comb_blocks {
+            self.add_comb_process(&scope, &block.body, &module.tasks);
+        }
+        for block in &module.latch_blocks {
+            self.add_comb_process(&scope, &block.body, &module.tasks);
+        }
+        for block in &module.ff_blocks {
+            let events = std::iter::once(&block.clock)
+                .chain(&block.reset)
+                .cloned()
+                .map(EventTrigger::Edge)
+                .collect();
+            let mut code = vec![Instr::Wait(events)];
+            compile(&block.body, &module.tasks, &mut code);
+            code.push(Instr::Jump(0));
+            self.spawn(&scope, code, None);
+        }
+        for block in &module.initial_blocks {
+            let mut code = Vec::new();
+            compile(&block.body, &module.tasks, &mut code);
+            self.spawn(&scope, code, None);
+        }
+        for block in &module.final_blocks {
+            let mut code = Vec::new();
+            compile(&block.body, &module.tasks, &mut code);
+            self.finals.push((scope.clone(), code));
+        }
+    }
+
+    /// Adds a process running `body` once, then again whenever a signal it
+    /// reads changes, with calls to any of `tasks` compiled in place,
+    /// returning its id
+    fn add_comb_process(
+        &mut self,
+        scope: &Rc<str>,
+        body: &[LogicStmt],
+        tasks: &[TaskDef],
+    ) -> usize {
+        let events = stmt_reads(body)
+            .into_iter()
+            .map(EventTrigger::Change)
+            .collect();
+
+        let mut code = Vec::new();
+        compile(body, tasks, &mut code);
+        code.push(Instr::Wait(events));
+        code.push(Instr::Jump(0));
+        self.spawn(scope, code, None)
+    }
+
+    /// Makes process `pid` a driver of the nets written by `lhs`, driving
+    /// them to 0 and to 1 with the given strengths
+    ///
+    /// The nets are unknown until their drivers first run, after which
+    /// bits no driver drives float at Z
+    fn add_driver(&mut self, pid: usize, lhs: &Expr, strength: (DriveStrength, DriveStrength)) {
+        let scope = self.processes[pid].scope.clone();
+        self.processes[pid].driver = Some(strength);
+
+        for name in lvalue_signals(lhs) {
+            let name = scoped_name(&scope, name);
+            if let Some(net) = self.nets.get(&name) {
+                self.signals.insert(name, LogicValue::new(net.width));
+            }
+        }
+    }
+
+    /// Connects the ports of the instance `child` of `module` to the
+    /// signals of its parent instance `parent`
+    ///
+    /// Each connection becomes a continuous assignment in the parent's scope,
+    /// from the parent signal to an input or from an output to the parent
+    /// signal
+    fn bind_ports(&mut self, module: &Module, parent: &ModuleInstance, child: &ModuleInstance) {
+        let scope: Rc<str> = Rc::from(parent.path.as_str());
+
+        for (port, signal) in &child.connections {
+            let local = Expr::Ident(format!("{}.{}", child.inst_name, port));
+            let stmt = match module.io.port(port) {
+                Some((PortDirection::Input, _)) => LogicStmt::BlockingAssign {
+                    lhs: local,
+                    rhs: signal.clone(),
+                },
+                Some((PortDirection::Output, _)) => LogicStmt::BlockingAssign {
+                    lhs: signal.clone(),
+                    rhs: local,
+                },
+                Some((PortDirection::Inout, _)) => {
+                    warn!("inout port '{}' of '{}' is not connected", port, child.path);
+                    continue;
+                }
+                None => {
+                    warn!("'{}' has no port '{}'", module.name, port);
+                    continue;
+                }
+            };
+            let pid = self.add_comb_process(&scope, std::slice::from_ref(&stmt), &[]);
+            if let LogicStmt::BlockingAssign { lhs, .. } = &stmt {
+                self.add_driver(pid, lhs, Default::default());
+            }
+        }
+
+        for input in &module.io.inputs {
+            if !child
+                .connections
+                .iter()
+                .any(|(port, _)| *port == input.name)
+            {
+                let name = scoped_name(&child.path, &input.name);
+                if let Some(value) = self.signals.get_mut(&name) {
+                    *value = LogicValue::filled(value.width(), LogicState::Z);
+                }
+            }
+        }
+    }
+
+    /// Adds a process and schedules it to run now
+    fn spawn(&mut self, scope: &Rc<str>, code: Vec<Instr>, parent: Option<usize>) -> usize {
+        self.spawn_shared(scope, Rc::new(code), parent)
+    }
+
+    /// Adds a process running shared code and schedules it to run now
+    fn spawn_shared(
+        &mut self,
+        scope: &Rc<str>,
+        code: Rc<Vec<Instr>>,
+        parent: Option<usize>,
+    ) -> usize {
+        let pid = self.processes.len();
+        self.processes.push(Process {
+            scope: scope.clone(),
+            code,
+            pc: 0,
+            counts: Vec::new(),
+            held: Vec::new(),
+            state: ProcState::Ready,
+            parent,
+            driver: None,
+        });
+        self.schedule(0, pid);
+        pid
+    }
+
+    /// Adds a process making non-blocking `updates` `delay` precision steps
+    /// from now
+    fn spawn_release(&mut self, scope: &Rc<str>, updates: Vec<Update>, delay: u64) {
+        // Releasing them after no delay hands them to the non-blocking
+        // assignments of the time step the process wakes in
+        let pid = self.processes.len();
+        self.processes.push(Process {
+            scope: scope.clone(),
+            code: Rc::new(vec![Instr::ReleaseAfter(Delay::Time(0.0))]),
+            pc: 0,
+            counts: Vec::new(),
+            held: updates,
+            state: ProcState::Sleeping,
+            parent: None,
+            driver: None,
+        });
+        self.schedule(delay, pid);
+    }
+
+    /// Schedules a process to resume `delay` precision steps from now
+    ///
+    /// With no delay, the process runs in the next delta cycle
+    fn schedule(&mut self, delay: u64, pid: usize) {
+        if delay == 0 {
+            self.active.push_back(pid);
+            return;
+        }
+
+        self.queue.push(Event {
+            time: self.now.saturating_add(delay),
+            seq: self.seq,
+            pid,
+        });
+        self.seq += 1;
+    }
+
+    /// Runs the simulation until `end_time`, in seconds, until no events
+    /// remain, until a time step fails to settle, or until `$finish` or
+    /// `$stop` is called
+    ///
+    /// `final` blocks run once the simulation ends, unless it ends by
+    /// `$stop` or by failing to settle
+    fn run(mut self, end_time: f64) -> SimResult {
+        let end = (end_time / self.precision).round() as u64;
+
+        debug!("simulating {} processes", self.processes.len());
+
+        let status = loop {
+            if !self.settle() {
+                break SimStatus::Unsettled;
+            }
+            if let Some(status) = self.halted {
+                break status;
+            }
+            self.strobe();
+            self.monitor();
+            self.step_start = self.changes.len();
+
+            let Some(event) = self.queue.peek() else {
+                break SimStatus::Completed;
+            };
+            if event.time > end {
+                self.now = end;
+                break SimStatus::EndTime;
+            }
+
+            self.now = event.time;
+            while self
+                .queue
+                .peek()
+                .is_some_and(|event| event.time == self.now)
+            {
+                let event = self.queue.pop().expect("event was peeked");
+                self.active.push_back(event.pid);
+            }
+        };
+
+        if matches!(
+            status,
+            SimStatus::Completed | SimStatus::EndTime | SimStatus::Finished
+        ) {
+            self.run_final();
+        }
+
+        SimResult {
+            status,
+            end_time: self.now as f64 * self.precision,
+            signals: self.signals.into_iter().collect(),
+            changes: self.changes,
+            sim_time: SimTime {
+                n_time: self.unit,
+                d_time: self.precision,
+            },
+            output: self.output,
+            dump: self.dump,
+            forbidden_calls: self.system.forbidden.into_inner(),
+            assertions: self.assertions,
+        }
+    }
+
+    /// Runs each `final` block to completion, in the order they were added
+    ///
+    /// Processes and non-blocking assignments still pending are dropped, and
+    /// nothing wakes on the changes the blocks make
+    fn run_final(&mut self) {
+        self.active.clear();
+        self.nba.clear();
+
+        for (scope, code) in std::mem::take(&mut self.finals) {
+            let pid = self.spawn(&scope, code, None);
+            self.active.clear();
+            self.execute(pid);
+        }
+    }
+
+    /// Runs delta cycles until the current time step settles
+    ///
+    /// Returns `false` if the time step is still changing after
+    /// `MAX_DELTA_CYCLES`, as with combinational feedback that oscillates
+    fn settle(&mut self) -> bool {
+        let mut deltas = 0;
+
+        while !self.active.is_empty() || !self.nba.is_empty() {
+            if deltas == MAX_DELTA_CYCLES {
+                error!(
+                    "time {} did not settle within {} delta cycles",
+                    self.now as f64 * self.precision,
+                    MAX_DELTA_CYCLES
+                );
+                return false;
+            }
+            deltas += 1;
+
+            if self.active.is_empty() {
+                for update in std::mem::take(&mut self.nba) {
+                    self.apply(&update);
+                }
+                continue;
+            }
+
+            for pid in std::mem::take(&mut self.active) {
+                self.execute(pid);
+
+                if self.halted.is_some() {
+                    return true;
+                }
+            }
+        }
+
+        trace!(
+            "time {} settled after {} delta cycles",
+            self.now as f64 * self.precision,
+            deltas
+        );
+        true
+    }
+
+    /// Prints the `$strobe` calls made during the time step that just
+    /// settled
+    fn strobe(&mut self) {
+        for (scope, radix, args) in std::mem::take(&mut self.strobes) {
+            let lookup = ScopedLookup {
+                signals: &self.signals,
+                scope: &scope,
+                time: self.now,
+                time_unit: self.time_unit(),
+                functions: scope_functions(&self.functions, &scope),
+                signed: &self.signed,
+                descending: &self.descending,
+                system: Some(&self.system),
+            };
+            let text = format_display(&args, radix, &scope, &lookup) + "\n";
+            self.print(&text);
+        }
+    }
+
+    /// Prints the active `$monitor` if any of its arguments changed since it
+    /// last printed
+    ///
+    /// Arguments are only evaluated again in time steps that changed a
+    /// signal they read. Format strings are not compared, so only the values
+    /// printed can set the monitor off. As in SystemVerilog, neither can
+    /// `$time`, `$stime` or `$realtime`
+    fn monitor(&mut self) {
+        let Some(monitor) = &self.monitor else {
+            return;
+        };
+        if !monitor.enabled {
+            return;
+        }
+
+        let changed = self.changes[self.step_start..].iter().any(|change| {
+            monitor
+                .reads
+                .iter()
+                .any(|signal| changes_signal(&change.signal, signal))
+        });
+        if monitor.last.is_some() && !changed {
+            return;
+        }
+
+        let lookup = ScopedLookup {
+            signals: &self.signals,
+            scope: &monitor.scope,
+            time: self.now,
+            time_unit: self.time_unit(),
+            functions: scope_functions(&self.functions, &monitor.scope),
+            signed: &self.signed,
+            descending: &self.descending,
+            system: Some(&self.system),
+        };
+        let values: Vec<LogicValue> = monitor
+            .args
+            .iter()
+            .filter(|arg| match arg {
+                Expr::Str(_) => false,
+                Expr::SysFunc(name, _) => {
+                    !matches!(name.as_str(), "time" | "stime" | "realtime" | "system_time")
+                }
+                _ => true,
+            })
+            .map(|arg| eval_expr(arg, &lookup))
+            .collect();
+
+        if monitor.last.as_ref() == Some(&values) {
+            return;
+        }
+
+        let text = format_display(&monitor.args, monitor.radix, &monitor.scope, &lookup) + "\n";
+        self.print(&text);
+        if let Some(monitor) = &mut self.monitor {
+            monitor.last = Some(values);
+        }
+    }
+
+    /// Prints text from an output task to stdout, keeping a copy for the
+    /// result
+    fn print(&mut self, text: &str) {
+        print!("{}", text);
+        self.output.push_str(text);
+    }
+
+    /// Applies an update, recording the change and waking any processes
+    /// waiting on it
+    fn apply(&mut self, update: &Update) {
+        let Some(old) = apply_update(&mut self.signals, update) else {
+            return;
+        };
+        let new = self.signals[&update.signal].clone();
+
+        trace!("{} changed from {} to {}", update.signal, old, new);
+
+        self.changes.push(SignalChange {
+            time: self.now as f64 * self.precision,
+            signal: update.signal.to_owned(),
+            value: new.clone(),
+        });
+
+        let woken: Vec<usize> = self
+            .processes
+            .iter()
+            .enumerate()
+            .filter(|(_, process)| match &process.state {
+                ProcState::Waiting(events) => events.iter().any(|event| match event {
+                    EventTrigger::Edge(edge) => {
+                        edge.signal == update.signal && is_edge(edge.edge, &old, &new)
+                    }
+                    EventTrigger::Change(signal) => changes_signal(&update.signal, signal),
+                }),
+                _ => false,
+            })
+            .map(|(pid, _)| pid)
+            .collect();
+
+        for pid in woken {
+            self.processes[pid].state = ProcState::Ready;
+            self.schedule(0, pid);
+        }
+    }
+
+    /// Makes an update as driver `pid` of a net, resolving the net from its
+    /// drivers, or as `apply` for any other signal
+    ///
+    /// Each driver is kept as two drivers of the net, one driving the 0 bits
+    /// with `strength0` and the other the 1 bits with `strength1`, with any
+    /// X bits given to the stronger of the two
+    fn drive(
+        &mut self,
+        pid: usize,
+        (strength0, strength1): (DriveStrength, DriveStrength),
+        update: &Update,
+    ) {
+        let Some(net) = self.nets.get_mut(&update.signal) else {
+            self.apply(update);
+            return;
+        };
+        let (zeros_id, ones_id) = (2 * pid, 2 * pid + 1);
+
+        // Bits the driver has never written are not driven
+        let part = |id| {
+            net.drivers
+                .iter()
+                .find(|(driver, _, _)| *driver == id)
+                .map(|(_, _, bits)| bits.clone())
+        };
+        let mut value = match (part(zeros_id), part(ones_id)) {
+            (Some(zeros), Some(ones)) => LogicValue::from_bits(
+                zeros
+                    .into_iter()
+                    .zip(ones)
+                    .map(|(zero, one)| match zero {
+                        LogicState::Z => one,
+                        _ => zero,
+                    })
+                    .collect(),
+            ),
+            _ => LogicValue::filled(net.width, LogicState::Z),
+        };
+        match update.range {
+            Some((high, low)) => value.set_range(high, low, &update.value),
+            None => value = update.value.resize(net.width),
+        }
+
+        let x_with_zeros = strength0 >= strength1;
+        let split = |kept: LogicState, with_x: bool| {
+            value
+                .bits()
+                .iter()
+                .map(|&bit| match bit {
+                    LogicState::X if with_x => bit,
+                    _ if bit == kept => bit,
+                    _ => LogicState::Z,
+                })
+                .collect()
+        };
+        net.drive(zeros_id, strength0, split(LogicState::Zero, x_with_zeros));
+        net.drive(ones_id, strength1, split(LogicState::One, !x_with_zeros));
+
+        let resolved = Update {
+            signal: update.signal.to_owned(),
+            range: None,
+            value: LogicValue::from_bits(net.resolve()),
+        };
+        self.apply(&resolved);
+    }
+
+    /// Runs a process until it waits or finishes
+    fn execute(&mut self, pid: usize) {
+        match self.processes[pid].state {
+            ProcState::Ready | ProcState::Sleeping => self.processes[pid].state = ProcState::Ready,
+            _ => return,
+        }
+
+        let code = self.processes[pid].code.clone();
+        let scope = self.processes[pid].scope.clone();
+
+        for _ in 0..MAX_STEPS_PER_ACTIVATION {
+            let pc = self.processes[pid].pc;
+            let Some(instr) = code.get(pc) else {
+                self.finish(pid);
+                return;
+            };
+            self.processes[pid].pc += 1;
+
+            let lookup = ScopedLookup {
+                signals: &self.signals,
+                scope: &scope,
+                time: self.now,
+                time_unit: self.time_unit(),
+                functions: scope_functions(&self.functions, &scope),
+                signed: &self.signed,
+                descending: &self.descending,
+                system: Some(&self.system),
+            };
+
+            match instr {
+                Instr::Assign { lhs, rhs, blocking } => {
+                    let updates = assign_updates(lhs, rhs, &lookup);
+                    let driver = self.processes[pid].driver;
+
+                    for update in updates {
+                        match (blocking, driver) {
+                            (true, Some(strength)) => self.drive(pid, strength, &update),
+                            (true, None) => self.apply(&update),
+                            (false, _) => self.nba.push(update),
+                        }
+                    }
+                }
+                Instr::Hold { lhs, rhs } => {
+                    self.processes[pid].held = assign_updates(lhs, rhs, &lookup);
+                }
+                Instr::Release => {
+                    for update in std::mem::take(&mut self.processes[pid].held) {
+                        self.apply(&update);
+                    }
+                }
+                Instr::ReleaseAfter(delay) => {
+                    let delay = self.delay_steps(delay, &lookup);
+                    let updates = std::mem::take(&mut self.processes[pid].held);
+                    match delay {
+                        0 => self.nba.extend(updates),
+                        _ => self.spawn_release(&scope, updates, delay),
+                    }
+                }
+                Instr::Jump(_)
+                | Instr::JumpUnless(..)
+                | Instr::JumpUnlessCase { .. }
+                | Instr::PushCount(_)
+                | Instr::LoopCount(_)
+                | Instr::PopCount => {
+                    let process = &mut self.processes[pid];
+                    if let Some(target) = control_flow(instr, &mut process.counts, &lookup) {
+                        process.pc = target;
+                    }
+                }
+                Instr::Break | Instr::Continue => warn!(
+                    "break or continue outside of a loop in '{}' is ignored",
+                    scope
+                ),
+                Instr::Delay(delay) => {
+                    let delay = self.delay_steps(delay, &lookup);
+                    self.processes[pid].state = ProcState::Sleeping;
+                    self.schedule(delay, pid);
+                    return;
+                }
+                Instr::Wait(events) => {
+                    let events = events
+                        .iter()
+                        .map(|event| match event {
+                            EventTrigger::Edge(edge) => {
+                                let mut edge = edge.clone();
+                                edge.signal = scoped_name(&scope, &edge.signal);
+                                EventTrigger::Edge(edge)
+                            }
+                            EventTrigger::Change(signal) => {
+                                EventTrigger::Change(scoped_name(&scope, signal))
+                            }
+                        })
+                        .collect();
+                    self.processes[pid].state = ProcState::Waiting(events);
+                    return;
+                }
+                Instr::Fork(branches, join) => {
+                    let waiting = match join {
+                        JoinKind::Join => branches.len(),
+                        JoinKind::JoinAny => branches.len().min(1),
+                        JoinKind::JoinNone => 0,
+                    };
+
+                    for branch in branches {
+                        self.spawn_shared(&scope, branch.clone(), Some(pid));
+                    }
+
+                    if waiting > 0 {
+                        self.processes[pid].state = ProcState::Joining(waiting);
+                        return;
+                    }
+                }
+                Instr::Display(DisplayTask::Strobe, radix, args) => {
+                    self.strobes.push((scope.clone(), *radix, args.clone()));
+                }
+                Instr::Display(DisplayTask::Monitor, radix, args) => {
+                    let mut reads = Vec::new();
+                    for arg in args {
+                        collect_reads(arg, &mut reads);
+                    }
+                    self.monitor = Some(Monitor {
+                        scope: scope.clone(),
+                        args: args.clone(),
+                        reads: reads.iter().map(|name| scoped_name(&scope, name)).collect(),
+                        radix: *radix,
+                        last: None,
+                        enabled: true,
+                    });
+                }
+                Instr::Display(task, radix, args) => {
+                    let mut text = format_display(args, *radix, &scope, &lookup);
+                    if *task == DisplayTask::Display {
+                        text.push('\n');
+                    }
+                    self.print(&text);
+                }
+                Instr::SysTask(name, _) if name == "finish" || name == "stop" => {
+                    self.halt(name, &scope);
+                    return;
+                }
+                Instr::SysTask(name, args) if DUMP_TASKS.contains(&name.as_str()) => {
+                    self.dump_task(name, args, &scope)
+                }
+                Instr::SysTask(name, _) if name == "monitoron" || name == "monitoroff" => {
+                    if let Some(monitor) = &mut self.monitor {
+                        monitor.enabled = name == "monitoron";
+                        monitor.last = None;
+                    }
+                }
+                Instr::SysTask(name, args) if name == "readmemh" || name == "readmemb" => {
+                    self.read_mem(name, args, &scope)
+                }
+                Instr::SysTask(name, args) if name == "system" => match args.first() {
+                    Some(arg) => {
+                        lookup.system(&system_command(arg, &lookup));
+                    }
+                    None => warn!("$system in '{}' expects a command", scope),
+                },
+                Instr::SysTask(name, args) if is_assert_task(name) => {
+                    self.assert_control(name, args, &scope)
+                }
+                Instr::SysTask(name, args) => debug!(
+                    "${} with {} arguments is not yet supported",
+                    name,
+                    args.len()
+                ),
+                Instr::Return(_) => {
+                    warn!(
+                        "return outside of a function ends its process in '{}'",
+                        scope
+                    );
+                    self.finish(pid);
+                    return;
+                }
+            }
+        }
+
+        error!(
+            "process in '{}' ran {} steps without waiting and was stopped",
+            scope, MAX_STEPS_PER_ACTIVATION
+        );
+        self.finish(pid);
+    }
+
+    /// Handles a call to `$dumpfile`, `$dumpvars`, `$dumpoff` or `$dumpon`
+    /// from `scope`
+    ///
+    /// `$dumpvars` starts the dump on its first call, and each call adds the
+    /// scopes it names. Its first argument is the number of levels to dump,
+    /// and the others are instance or signal names, looked up from the top
+    /// of the hierarchy first, then from `scope`. `$dumpoff` pauses a dump
+    /// that has started until the next `$dumpon`
+    fn dump_task(&mut self, task: &str, args: &[Expr], scope: &str) {
+        trace!("evaluating ${} in '{}'", task, scope);
+
+        if task == "dumpfile" {
+            match args.first() {
+                Some(Expr::Str(file)) => self.dump_file = Some(PathBuf::from(file)),
+                _ => warn!("$dumpfile in '{}' expects a file name", scope),
+            }
+            return;
+        }
+
+        if task == "dumpoff" || task == "dumpon" {
+            let now = self.now as f64 * self.precision;
+            let Some(dump) = &mut self.dump else {
+                warn!("${} in '{}' has no effect before $dumpvars", task, scope);
+                return;
+            };
+
+            match (task, dump.pauses.last_mut()) {
+                ("dumpon", Some((_, end))) if end.is_infinite() => *end = now,
+                ("dumpoff", Some((_, end))) if end.is_infinite() => (),
+                ("dumpoff", _) => dump.pauses.push((now, f64::INFINITY)),
+                _ => (),
+            }
+            return;
+        }
+
+        let lookup = ScopedLookup {
+            signals: &self.signals,
+            scope,
+            time: self.now,
+            time_unit: self.time_unit(),
+            functions: scope_functions(&self.functions, scope),
+            signed: &self.signed,
+            descending: &self.descending,
+            system: Some(&self.system),
+        };
+        let levels = args
+            .first()
+            .and_then(|levels| const_index(levels, &lookup))
+            .unwrap_or(0);
+
+        let scopes: Vec<(String, u64)> = args
+            .iter()
+            .skip(1)
+            .filter_map(|arg| match arg {
+                Expr::Ident(name) => {
+                    let prefix = format!("{}.", name);
+                    let found = self
+                        .signals
+                        .keys()
+                        .any(|signal| signal == name || signal.starts_with(&prefix));
+
+                    Some(match found {
+                        true => (name.to_owned(), levels),
+                        false => (scoped_name(scope, name), levels),
+                    })
+                }
+                _ => {
+                    warn!("$dumpvars in '{}' expects instance or signal names", scope);
+                    None
+                }
+            })
+            .collect();
+
+        let start = self.now as f64 * self.precision;
+        let file = self
+            .dump_file
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("dump.vcd"));
+        let dump = self.dump.get_or_insert_with(|| Dump {
+            file,
+            scopes: Vec::new(),
+            start,
+            pauses: Vec::new(),
+        });
+
+        match args.len() > 1 {
+            true => dump.scopes.extend(scopes),
+            false => dump.scopes.clear(),
+        }
+    }
+
+    /// Handles a call to an assertion control task from `scope`, changing the
+    /// assertion checking state of the instances it names
+    ///
+    /// `$assertcontrol` takes the action, the assertion and directive types,
+    /// the levels and the scopes, while `$asserton`, `$assertoff` and
+    /// `$assertkill` take only the levels and the scopes. Assertions are not
+    /// told apart by type, so both types are ignored. Each scope is an
+    /// instance name, looked up from the top of the hierarchy first, then
+    /// from `scope`, and the action applies to the instances `levels` deep
+    /// from it, counting it as the first, or every instance below it for 0.
+    /// Without scopes, the action applies from each top-level instance
+    fn assert_control(&mut self, task: &str, args: &[Expr], scope: &str) {
+        let lookup = ScopedLookup {
+            signals: &self.signals,
+            scope,
+            time: self.now,
+            time_unit: self.time_unit(),
+            functions: scope_functions(&self.functions, scope),
+            signed: &self.signed,
+            descending: &self.descending,
+            system: Some(&self.system),
+        };
+        let Some(action) = assert_action(task, args, &lookup) else {
+            return;
+        };
+
+        let args = match task {
+            "assertcontrol" => args.get(3..).unwrap_or_default(),
+            _ => args,
+        };
+        let levels = args
+            .first()
+            .and_then(|levels| const_index(levels, &lookup))
+            .unwrap_or(0);
+
+        let scopes: Vec<String> = match args.get(1..) {
+            Some(names) if !names.is_empty() => names
+                .iter()
+                .filter_map(|arg| {
+                    let Some(name) = member_name(arg) else {
+                        warn!("${} in '{}' expects instance names", task, scope);
+                        return None;
+                    };
+                    let local = scoped_name(scope, &name);
+                    if self.assertions.contains_key(&name) {
+                        Some(name)
+                    } else if self.assertions.contains_key(&local) {
+                        Some(local)
+                    } else {
+                        warn!("${} in '{}' names unknown instance '{}'", task, scope, name);
+                        None
+                    }
+                })
+                .collect(),
+            _ => self
+                .assertions
+                .keys()
+                .filter(|inst| !inst.contains('.'))
+                .cloned()
+                .collect(),
+        };
+        debug!(
+            "${} takes {:?} in {:?}, {} levels deep",
+            task, action, scopes, levels
+        );
+
+        for (inst, state) in &mut self.assertions {
+            let included = scopes.iter().any(|target| {
+                let depth = match inst.strip_prefix(target.as_str()) {
+                    Some("") => 0,
+                    Some(rest) if rest.starts_with('.') => rest.split('.').count() as u64 - 1,
+                    _ => return false,
+                };
+                levels == 0 || depth < levels
+            });
+            if included {
+                state.apply(action);
+            }
+        }
+    }
+
+    /// Handles a call to `$readmemh` or `$readmemb` from `scope`, as in
+    /// `$readmemh("rom.hex", mem)`, loading a file into a memory
+    ///
+    /// Loading starts at the memory's lowest address, or the start address
+    /// given as the third argument, and stops after the end address given
+    /// as the fourth. An end address below the start address loads the
+    /// file downward from the start address. Problems reading the file are
+    /// logged, and leave the rest of the memory as it was
+    fn read_mem(&mut self, task: &str, args: &[Expr], scope: &str) {
+        trace!("evaluating ${} in '{}'", task, scope);
+
+        let (Some(Expr::Str(file)), Some(Expr::Ident(mem))) = (args.first(), args.get(1)) else {
+            warn!("${} in '{}' expects a file name and a memory", task, scope);
+            return;
+        };
+
+        let prefix = scoped_name(scope, &format!("{}[", mem));
+        let lowest = self
+            .signals
+            .iter()
+            .filter_map(|(name, value)| {
+                let address = name.strip_prefix(&prefix)?.strip_suffix(']')?;
+                Some((address.parse::<u64>().ok()?, value.width()))
+            })
+            .min();
+        let Some((lowest, width)) = lowest else {
+            warn!("'{}' read by ${} in '{}' is not a memory", mem, task, scope);
+            return;
+        };
+
+        let lookup = ScopedLookup {
+            signals: &self.signals,
+            scope,
+            time: self.now,
+            time_unit: self.time_unit(),
+            functions: scope_functions(&self.functions, scope),
+            signed: &self.signed,
+            descending: &self.descending,
+            system: Some(&self.system),
+        };
+        let start = args.get(2).and_then(|start| const_index(start, &lookup));
+        let end = args.get(3).and_then(|end| const_index(end, &lookup));
+        let descending = start.zip(end).is_some_and(|(start, end)| start > end);
+
+        let values = std::fs::read_to_string(file)
+            .map_err(|e| error!("failed to read memory file '{}': {}", file, e))
+            .and_then(|text| {
+                let start = start.unwrap_or(lowest);
+                parse_mem_file(&text, task == "readmemh", width, start, descending)
+                    .map_err(|e| error!("failed to parse memory file '{}': {}", file, e))
+            });
+        let Ok(values) = values else {
+            return;
+        };
+
+        for (address, value) in values {
+            let past_end = |end: u64| match descending {
+                true => address < end,
+                false => address > end,
+            };
+            if end.is_some_and(past_end) {
+                break;
+            }
+
+            let signal = format!("{}{}]", prefix, address);
+            if !self.signals.contains_key(&signal) {
+                warn!("address {} in '{}' is outside of '{}'", address, file, mem);
+                break;
+            }
+
+            self.apply(&Update {
+                signal,
+                range: None,
+                value,
+            });
+        }
+    }
+
+    /// Stops the run for a call to `$finish` or `$stop` from `scope`
+    ///
+    /// `$stop` would normally hand control to the user, so the state of
+    /// every signal is logged instead
+    fn halt(&mut self, task: &str, scope: &str) {
+        let time = self.now as f64 * self.precision;
+        info!("${} called in '{}' at {}s", task, scope, time);
+
+        if task == "stop" {
+            self.halted = Some(SimStatus::Stopped);
+
+            let signals: BTreeMap<&String, &LogicValue> = self.signals.iter().collect();
+            for (name, value) in signals {
+                info!("{} = {}", name, value);
+            }
+        } else {
+            self.halted = Some(SimStatus::Finished);
+        }
+    }
+
+    /// Marks a process as finished, resuming a parent waiting on it
+    fn finish(&mut self, pid: usize) {
+        self.processes[pid].state = ProcState::Done;
+
+        let Some(parent) = self.processes[pid].parent else {
+            return;
+        };
+
+        if let ProcState::Joining(waiting) = self.processes[parent].state {
+            if waiting <= 1 {
+                self.processes[parent].state = ProcState::Ready;
+                self.schedule(0, parent);
+            } else {
+                self.processes[parent].state = ProcState::Joining(waiting - 1);
+            }
+        }
+    }
+
+    /// Number of precision steps in one time unit
+    fn time_unit(&self) -> u64 {
+        (self.unit / self.precision).round() as u64
+    }
+
+    /// Length of a delay in precision steps
+    fn delay_steps(&self, delay: &Delay, lookup: &ScopedLookup) -> u64 {
+        let seconds = match delay {
+            Delay::Units(expr) => eval_expr(expr, lookup).to_u128().unwrap_or(0) as f64 * self.unit,
+            Delay::RealUnits(units) => units * self.unit,
+            Delay::Time(seconds) => *seconds,
+        };
+
+        (seconds / self.precision).round() as u64
+    }
+}
@@ -1,6 +1,7 @@
-use crate::{LexingError, Token};
+use crate::{expect_token, next_token, LexingError, Token};
 use log::{error, trace};
-use logos::Lexer;
+use logos::{Lexer, Logos};
+use std::time::Duration;
 
 /// Simulation time command
 ///
@@ -8,10 +9,10 @@ use logos::Lexer;
 /// as `timescale 1ns/1ps
 #[derive(Debug, Clone, Copy)]
 pub struct SimTime {
-    /// Numerator time given in ns or ps
+    /// Numerator time given in seconds
     pub n_time: f64,
 
-    /// Denominator time given in ns or ps
+    /// Denominator time given in seconds
     pub d_time: f64,
 }
 
@@ -22,94 +23,232 @@ pub struct SimTime {
 impl Default for SimTime {
     fn default() -> Self {
         Self {
-            n_time: 0.000_001,
-            d_time: 0.000_000_001,
+            n_time: TimeUnit::Nanoseconds.multiplier(),
+            d_time: TimeUnit::Picoseconds.multiplier(),
         }
     }
 }
 
-/// Parses simulation timing constraints to completion
-pub fn parse_sim_time<'source>(lexer: &mut Lexer<'source, Token>) -> Result<SimTime, LexingError> {
-    let mut n_time = 0.;
-    let mut d_time = 0.;
-    let mut timescale_started = false;
-    let mut n_found = false;
-    let mut n_search = true;
+impl SimTime {
+    /// Ratio of the time unit to the time precision
+    ///
+    /// A timescale of `1ns/1ps` has a ratio of 1000, the number of precision
+    /// steps in a single unit delay
+    pub fn ratio(&self) -> f64 {
+        self.n_time / self.d_time
+    }
+}
 
-    trace!("parsing sim time");
+/// Unit of simulation time
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum TimeUnit {
+    /// Seconds (s)
+    Seconds,
 
-    while let Some(token) = lexer.next() {
-        match token {
-            Ok(Token::Timescale) => {
-                if timescale_started {
-                    trace!("timescale_started check failed");
-                    return Err(LexingError::UnexpectedToken);
-                }
+    /// Milliseconds (ms)
+    Milliseconds,
 
-                timescale_started = true;
-            }
-            Ok(Token::Time(val)) => {
-                if !timescale_started {
-                    trace!("not timescale_started check failed");
-                    return Err(LexingError::UnexpectedToken);
-                }
-
-                if n_search {
-                    if n_found {
-                        trace!("n_found check failed");
-                        return Err(LexingError::ImproperTimeFormatting);
-                    }
-
-                    n_time = val;
-                    n_found = true;
-                } else {
-                    d_time = val;
-                    break;
-                }
-            }
-            Ok(Token::Divide) => n_search = false,
-            Ok(Token::WhiteSpace) => (),
-            Err(e) => {
-                error!(
-                    "unexpected error occurred parsing sim time: '{}'",
-                    lexer.slice()
-                );
-                return Err(e);
-            }
-            _ => error!("Unexpected token in timescale {:?}", token.unwrap()),
+    /// Microseconds (us)
+    Microseconds,
+
+    /// Nanoseconds (ns)
+    #[default]
+    Nanoseconds,
+
+    /// Picoseconds (ps)
+    Picoseconds,
+
+    /// Femtoseconds (fs)
+    Femtoseconds,
+}
+
+impl TimeUnit {
+    /// Number of seconds in one of this unit
+    pub fn multiplier(&self) -> f64 {
+        match self {
+            Self::Seconds => 1.,
+            Self::Milliseconds => 1e-3,
+            Self::Microseconds => 1e-6,
+            Self::Nanoseconds => 1e-9,
+            Self::Picoseconds => 1e-12,
+            Self::Femtoseconds => 1e-15,
+        }
+    }
+
+    /// SystemVerilog suffix for this unit
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Self::Seconds => "s",
+            Self::Milliseconds => "ms",
+            Self::Microseconds => "us",
+            Self::Nanoseconds => "ns",
+            Self::Picoseconds => "ps",
+            Self::Femtoseconds => "fs",
+        }
+    }
+}
+
+/// Converts a simulation time in seconds to a `Duration`
+///
+/// Negative and NaN times are clamped to zero and times too large to
+/// represent saturate to `Duration::MAX`
+pub fn sim_time_to_duration(t: f64) -> Duration {
+    if t.is_nan() || t <= 0. {
+        return Duration::ZERO;
+    }
+
+    Duration::try_from_secs_f64(t).unwrap_or(Duration::MAX)
+}
+
+/// Converts a `Duration` to a simulation time in seconds
+pub fn duration_to_sim_time(d: Duration) -> f64 {
+    d.as_secs_f64()
+}
+
+/// Formats a simulation time in seconds using the given unit
+///
+/// The value is shown with up to three decimal places, so `1.5e-9` in
+/// nanoseconds formats as `1.5ns`
+pub fn sim_time_display(t: f64, unit: TimeUnit) -> String {
+    let val = format!("{:.3}", t / unit.multiplier());
+    let val = val.trim_end_matches('0').trim_end_matches('.');
+
+    format!("{}{}", val, unit.suffix())
+}
+
+/// Parses a duration such as `1000ns`, returning it in seconds
+///
+/// The duration is lexed as a single `Token::Time`, so it takes the same
+/// units as a `timescale` directive. As there, the magnitude and unit may be
+/// apart, as in `10 ns`
+pub fn parse_duration(text: &str) -> Result<f64, LexingError> {
+    let joined;
+    let text = match text.split_whitespace().collect::<Vec<&str>>()[..] {
+        [magnitude, unit] if unit.chars().all(|c| c.is_ascii_alphabetic()) => {
+            joined = format!("{}{}", magnitude, unit);
+            joined.as_str()
+        }
+        _ => text.trim(),
+    };
+    let mut lexer = Token::lexer(text);
+
+    trace!("parsing duration '{}'", text);
+
+    match (lexer.next(), lexer.next()) {
+        (Some(Ok(Token::Time(val))), None) => Ok(val),
+        (Some(Err(e)), _) => Err(e),
+        _ => {
+            error!("expected a duration such as '100ns', got '{}'", text);
+            Err(LexingError::ImproperTimeFormatting)
         }
     }
+}
+
+/// Parses a `timescale` directive to completion, as in `timescale 1ns/1ps
+///
+/// The backtick has already been consumed. The magnitude and unit of each
+/// time may be apart, as in `timescale 1 ns / 1 ps`, and the precision may
+/// not be coarser than the unit
+pub fn parse_sim_time<'source>(lexer: &mut Lexer<'source, Token>) -> Result<SimTime, LexingError> {
+    trace!("parsing sim time");
+
+    expect_token(lexer, Token::Timescale, "compiler directive")?;
+    let n_time = parse_timescale_time(lexer)?;
+    expect_token(lexer, Token::Divide, "timescale")?;
+    let d_time = parse_timescale_time(lexer)?;
+
+    // Allow for rounding in times such as 100us, which is not exactly 1e-4
+    if d_time > n_time * (1. + 1e-9) {
+        error!("timescale precision must not be coarser than its unit");
+        return Err(LexingError::ImproperTimeFormatting);
+    }
 
     Ok(SimTime { n_time, d_time })
 }
 
-/// Parses a time given in picoseconds
-pub fn picosecond(lex: &mut Lexer<Token>) -> Option<f64> {
-    let slice = lex.slice();
-    let n: Result<f64, _> = slice[..slice.len() - 2].parse();
+/// Parses the unit or precision of a timescale, as in `10ns` or `10 ns`,
+/// returning it in seconds
+fn parse_timescale_time<'source>(lexer: &mut Lexer<'source, Token>) -> Result<f64, LexingError> {
+    let time = match next_token(lexer) {
+        Some(Ok(Token::Time(_))) => lexer.slice().to_owned(),
+        Some(Ok(Token::Integer(_))) => {
+            let magnitude = lexer.slice().to_owned();
+            if next_token(lexer) != Some(Ok(Token::Word)) {
+                error!("expected a time unit after '{}' in timescale", magnitude);
+                return Err(LexingError::ImproperTimeFormatting);
+            }
+            magnitude + lexer.slice()
+        }
+        Some(Err(e)) => return Err(e),
+        _ => {
+            error!("expected a time in timescale, got '{}'", lexer.slice());
+            return Err(LexingError::ImproperTimeFormatting);
+        }
+    };
 
-    trace!("parsing picosecond");
+    check_time_multiplier(&time)?;
+    parse_duration(&time)
+}
 
-    match n {
-        Ok(val) => Some(val * 0.000_000_001),
-        Err(e) => {
-            error!("could not read picosecond time: {}", e);
-            None
+/// Checks that a timescale time uses 1, 10 or 100 as its multiplier
+fn check_time_multiplier(time: &str) -> Result<(), LexingError> {
+    let multiplier = time.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+
+    match multiplier {
+        "1" | "10" | "100" => Ok(()),
+        _ => {
+            error!(
+                "timescale multiplier must be 1, 10 or 100, got {}",
+                multiplier
+            );
+            Err(LexingError::ImproperTimeFormatting)
         }
     }
 }
 
+/// Parses a time given in seconds
+pub fn second(lex: &mut Lexer<Token>) -> Option<f64> {
+    parse_time(lex, TimeUnit::Seconds)
+}
+
+/// Parses a time given in milliseconds
+pub fn millisecond(lex: &mut Lexer<Token>) -> Option<f64> {
+    parse_time(lex, TimeUnit::Milliseconds)
+}
+
+/// Parses a time given in microseconds
+pub fn microsecond(lex: &mut Lexer<Token>) -> Option<f64> {
+    parse_time(lex, TimeUnit::Microseconds)
+}
+
 /// Parses a time given in nanoseconds
 pub fn nanosecond(lex: &mut Lexer<Token>) -> Option<f64> {
+    parse_time(lex, TimeUnit::Nanoseconds)
+}
+
+/// Parses a time given in picoseconds
+pub fn picosecond(lex: &mut Lexer<Token>) -> Option<f64> {
+    parse_time(lex, TimeUnit::Picoseconds)
+}
+
+/// Parses a time given in femtoseconds
+pub fn femtosecond(lex: &mut Lexer<Token>) -> Option<f64> {
+    parse_time(lex, TimeUnit::Femtoseconds)
+}
+
+/// Parses a time ending in the suffix of `unit`, converting it to seconds
+fn parse_time(lex: &mut Lexer<Token>, unit: TimeUnit) -> Option<f64> {
     let slice = lex.slice();
-    let n: Result<f64, _> = slice[..slice.len() - 2].parse();
+    let n: Result<f64, _> = slice[..slice.len() - unit.suffix().len()]
+        .replace('_', "")
+        .parse();
 
-    trace!("parsing nanosecond");
+    trace!("parsing time in {}", unit.suffix());
 
     match n {
-        Ok(val) => Some(val * 0.000_001),
+        Ok(val) => Some(val * unit.multiplier()),
         Err(e) => {
-            error!("could not read nanosecond time: {}", e);
+            error!("could not read time in {}: {}", unit.suffix(), e);
             None
         }
     }
@@ -0,0 +1,557 @@
+use crate::eval::{eval_expr, is_signed, ValueLookup};
+use crate::expr::Expr;
+use crate::value::{
+    to_binary_string, to_decimal_string, to_hex_string, to_octal_string, LogicState, LogicValue,
+};
+use crate::{LexingError, SimConfig, SimError};
+use log::{error, info, trace, warn};
+use std::iter::Peekable;
+use std::process::Command;
+use std::slice;
+use std::str::Chars;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Characters a `%t` time is padded to when no width is given, as with the
+/// default `$timeformat`
+const TIME_WIDTH: usize = 20;
+
+/// Runs a shell command for `$system`, returning its exit code
+///
+/// The command only runs if `config.allow_system_calls` is set, otherwise
+/// `SimError::SystemCallForbidden` is returned. Output written by the command
+/// is captured and logged. A command terminated by a signal returns -1.
+///
+/// `$system` is not part of the LRM but is supported by most commercial
+/// simulators. Running it executes arbitrary commands on the host, so it is
+/// disabled by default
+pub fn system(config: &SimConfig, cmd: &str) -> Result<i32, SimError> {
+    trace!("evaluating $system(\"{}\")", cmd);
+
+    if !config.allow_system_calls {
+        warn!("$system(\"{}\") called but system calls are disabled", cmd);
+        return Err(SimError::SystemCallForbidden(cmd.to_owned()));
+    }
+
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/C", cmd]).output()?
+    } else {
+        Command::new("sh").args(["-c", cmd]).output()?
+    };
+
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        info!("$system stdout: {}", line);
+    }
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        warn!("$system stderr: {}", line);
+    }
+
+    Ok(output.status.code().unwrap_or(-1))
+}
+
+/// Command a `$system` call runs, given as its argument
+///
+/// The argument is a string literal or a value holding ASCII text, as with
+/// `%s` in `$display`
+pub fn system_command(arg: &Expr, lookup: &impl ValueLookup) -> String {
+    match arg {
+        Expr::Str(cmd) => cmd.to_owned(),
+        _ => ascii_string(&eval_expr(arg, lookup)),
+    }
+}
+
+/// Host wall clock time for `$system_time`, in whole seconds since the Unix
+/// epoch
+///
+/// Like `$system`, `$system_time` is not part of the LRM. It reads the
+/// host's clock rather than the simulated time, so a design using it does
+/// not simulate the same way twice
+pub fn system_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Assertion control action, as taken by `$assertcontrol` and the tasks
+/// standing for one of its actions, such as `$assertoff`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssertAction {
+    /// Prevents later control actions from changing the assertions, `1`
+    Lock,
+
+    /// Allows control actions to change the assertions again, `2`
+    Unlock,
+
+    /// Re-enables checking, `3`, as by `$asserton`
+    On,
+
+    /// Stops new attempts from starting, `4`, as by `$assertoff`
+    Off,
+
+    /// Stops new attempts and ends those in progress, `5`, as by
+    /// `$assertkill`
+    Kill,
+
+    /// Enables pass actions, `6`, as by `$assertpasson`
+    PassOn,
+
+    /// Disables pass actions, `7`, as by `$assertpassoff`
+    PassOff,
+
+    /// Enables fail actions, `8`, as by `$assertfailon`
+    FailOn,
+
+    /// Disables fail actions, `9`, as by `$assertfailoff`
+    FailOff,
+
+    /// Enables pass actions for non-vacuous successes, `10`, as by
+    /// `$assertnonvacuouson`
+    NonvacuousOn,
+
+    /// Disables pass actions for vacuous successes, `11`, as by
+    /// `$assertvacuousoff`
+    VacuousOff,
+}
+
+impl AssertAction {
+    /// Action given by the `control_type` argument of `$assertcontrol`
+    pub fn from_code(code: u128) -> Option<Self> {
+        match code {
+            1 => Some(AssertAction::Lock),
+            2 => Some(AssertAction::Unlock),
+            3 => Some(AssertAction::On),
+            4 => Some(AssertAction::Off),
+            5 => Some(AssertAction::Kill),
+            6 => Some(AssertAction::PassOn),
+            7 => Some(AssertAction::PassOff),
+            8 => Some(AssertAction::FailOn),
+            9 => Some(AssertAction::FailOff),
+            10 => Some(AssertAction::NonvacuousOn),
+            11 => Some(AssertAction::VacuousOff),
+            _ => None,
+        }
+    }
+
+    /// Action of a system task standing for a single action, as `Off` for
+    /// `assertoff`
+    pub fn from_task(task: &str) -> Option<Self> {
+        match task {
+            "asserton" => Some(AssertAction::On),
+            "assertoff" => Some(AssertAction::Off),
+            "assertkill" => Some(AssertAction::Kill),
+            "assertpasson" => Some(AssertAction::PassOn),
+            "assertpassoff" => Some(AssertAction::PassOff),
+            "assertfailon" => Some(AssertAction::FailOn),
+            "assertfailoff" => Some(AssertAction::FailOff),
+            "assertnonvacuouson" => Some(AssertAction::NonvacuousOn),
+            "assertvacuousoff" => Some(AssertAction::VacuousOff),
+            _ => None,
+        }
+    }
+}
+
+/// Checks if `task` is an assertion control task, which is `assertcontrol`
+/// or a task standing for one of its actions
+pub fn is_assert_task(task: &str) -> bool {
+    task == "assertcontrol" || AssertAction::from_task(task).is_some()
+}
+
+/// Assertion checking state of an instance, as changed by assertion control
+/// actions
+///
+/// Every instance starts with checking and every action enabled and
+/// unlocked. Assertions are not yet simulated, so no attempt is ever in
+/// progress for `AssertAction::Kill` to end, and it stops new attempts as
+/// `AssertAction::Off` does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AssertState {
+    /// Whether new assertion attempts start
+    pub enabled: bool,
+
+    /// Whether control actions other than `AssertAction::Unlock` are ignored
+    pub locked: bool,
+
+    /// Whether non-vacuous successes run their pass actions
+    pub pass_actions: bool,
+
+    /// Whether vacuous successes run their pass actions
+    pub vacuous_pass_actions: bool,
+
+    /// Whether failures run their fail actions
+    pub fail_actions: bool,
+}
+
+impl Default for AssertState {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            locked: false,
+            pass_actions: true,
+            vacuous_pass_actions: true,
+            fail_actions: true,
+        }
+    }
+}
+
+impl AssertState {
+    /// Takes a control action, unless the state is locked
+    pub fn apply(&mut self, action: AssertAction) {
+        match action {
+            AssertAction::Unlock => self.locked = false,
+            _ if self.locked => (),
+            AssertAction::Lock => self.locked = true,
+            AssertAction::On => self.enabled = true,
+            AssertAction::Off | AssertAction::Kill => self.enabled = false,
+            AssertAction::PassOn => {
+                self.pass_actions = true;
+                self.vacuous_pass_actions = true;
+            }
+            AssertAction::PassOff => {
+                self.pass_actions = false;
+                self.vacuous_pass_actions = false;
+            }
+            AssertAction::FailOn => self.fail_actions = true,
+            AssertAction::FailOff => self.fail_actions = false,
+            AssertAction::NonvacuousOn => self.pass_actions = true,
+            AssertAction::VacuousOff => self.vacuous_pass_actions = false,
+        }
+    }
+}
+
+/// Works out the action of a call to the assertion control task `task`,
+/// which is `assertcontrol` or one of the tasks standing for an action,
+/// such as `assertoff`
+///
+/// `$assertcontrol` takes the action as its first argument, followed by
+/// the optional assertion type, directive type, levels and scopes the
+/// action applies to. `None` is returned, and a warning logged, if the
+/// action is not known
+pub fn assert_action(task: &str, args: &[Expr], lookup: &impl ValueLookup) -> Option<AssertAction> {
+    trace!("evaluating ${}", task);
+
+    let action = match task {
+        "assertcontrol" => args
+            .first()
+            .and_then(|arg| eval_expr(arg, lookup).to_u128())
+            .and_then(AssertAction::from_code),
+        _ => AssertAction::from_task(task),
+    };
+
+    if action.is_none() {
+        warn!("${} expects a control type from 1 to 11", task);
+    }
+
+    action
+}
+
+/// Formats the arguments of an output task such as `$display`, as printed
+///
+/// A string literal argument is a format string, as in
+/// `$display("count = %0d", count)`. Each `%b`, `%o`, `%d`, `%h`, `%s` or
+/// `%c` format specifier in it prints the next argument, and `%m` prints
+/// `scope`, the hierarchical name of the calling instance. `%t` prints a time,
+/// such as `$time`, in decimal. Any other argument is printed as for the
+/// format specifier `radix`, which is `'d'` for `$display` and `'h'` for
+/// `$displayh`. Signed values print in decimal with a `-` if they are
+/// negative.
+///
+/// Values are padded to the width of their largest possible value, as in `  5`
+/// for an 8-bit `%d`, and times are padded to 20 characters. A width of zero,
+/// as in `%0d`, prints no padding, and any other width pads to at least that
+/// many characters
+pub fn format_display(
+    args: &[Expr],
+    radix: char,
+    scope: &str,
+    lookup: &impl ValueLookup,
+) -> String {
+    let mut out = String::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg {
+            Expr::Str(format) => format_string(format, &mut args, scope, lookup, &mut out),
+            _ => out.push_str(&format_value(
+                &eval_expr(arg, lookup),
+                radix,
+                None,
+                is_signed(arg, lookup),
+            )),
+        }
+    }
+
+    out
+}
+
+/// Pushes a format string to `out`, replacing escape sequences and format
+/// specifiers, which take their values from `args`
+fn format_string(
+    format: &str,
+    args: &mut slice::Iter<Expr>,
+    scope: &str,
+    lookup: &impl ValueLookup,
+    out: &mut String,
+) {
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('v') => out.push('\u{b}'),
+                Some('f') => out.push('\u{c}'),
+                Some('a') => out.push('\u{7}'),
+                Some(first @ '0'..='7') => out.push(escaped_char(first, &mut chars, 8, 3)),
+                Some('x') => match chars.next_if(char::is_ascii_hexdigit) {
+                    Some(first) => out.push(escaped_char(first, &mut chars, 16, 2)),
+                    None => out.push('x'),
+                },
+                Some(c) => out.push(c),
+                None => out.push('\\'),
+            },
+            '%' => {
+                let mut width: Option<usize> = None;
+                while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                    width = Some(width.unwrap_or(0) * 10 + digit as usize);
+                    chars.next();
+                }
+
+                match chars.next().map(|c| c.to_ascii_lowercase()) {
+                    Some('%') => out.push('%'),
+                    Some('m') => out.push_str(scope),
+                    Some(spec @ ('b' | 'o' | 'd' | 'h' | 'x' | 's' | 'c' | 't')) => {
+                        let value = |arg| match spec {
+                            't' => time_steps(arg, lookup),
+                            _ => eval_expr(arg, lookup),
+                        };
+                        match args.next() {
+                            Some(arg) => out.push_str(&format_value(
+                                &value(arg),
+                                spec,
+                                width,
+                                is_signed(arg, lookup),
+                            )),
+                            None => warn!("no argument left for '%{}' in \"{}\"", spec, format),
+                        }
+                    }
+                    Some(spec) => {
+                        warn!("unsupported format specifier '%{}' in \"{}\"", spec, format);
+                        out.push('%');
+                        out.push(spec);
+                    }
+                    None => out.push('%'),
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Value of a `%t` argument, a time in time units, in precision steps
+///
+/// `$realtime` gives the current time exactly, as it would if real values
+/// were supported
+fn time_steps(arg: &Expr, lookup: &impl ValueLookup) -> LogicValue {
+    if matches!(arg, Expr::SysFunc(name, _) if name == "realtime") {
+        return LogicValue::from_u128(lookup.time() as u128, 64);
+    }
+
+    let value = eval_expr(arg, lookup);
+    match value.to_u128() {
+        Some(units) => LogicValue::from_u128(units.saturating_mul(lookup.time_unit() as u128), 64),
+        None => value,
+    }
+}
+
+/// Character of an octal escape such as `\101`, or a hexadecimal one such as
+/// `\x41`, given its first digit and the characters after it
+///
+/// The escape runs for up to `digits` digits in `radix`, first included, and
+/// is truncated to a byte
+fn escaped_char(first: char, chars: &mut Peekable<Chars>, radix: u32, digits: usize) -> char {
+    let mut code = first.to_digit(radix).unwrap_or(0);
+    for _ in 1..digits {
+        let Some(digit) = chars.peek().and_then(|c| c.to_digit(radix)) else {
+            break;
+        };
+        code = code * radix + digit;
+        chars.next();
+    }
+
+    char::from(code as u8)
+}
+
+/// Formats a value for the format specifier `spec`, padded as described in
+/// `format_display`
+fn format_value(val: &LogicValue, spec: char, width: Option<usize>, signed: bool) -> String {
+    let (text, fill) = match spec {
+        'b' => (to_binary_string(val.bits()), '0'),
+        'o' => (to_octal_string(val.bits()), '0'),
+        'h' | 'x' => (to_hex_string(val.bits()), '0'),
+        's' => (ascii_string(val), ' '),
+        'c' => (ascii_string(&val.resize(8)), ' '),
+        'd' if signed => (signed_decimal_string(val.bits()), ' '),
+        _ => (decimal_string(val.bits()), ' '),
+    };
+
+    let Some(width) = width else {
+        return match spec {
+            'd' => {
+                // The most negative value is the widest of a signed value
+                let mut widest = vec![LogicState::One; val.bits().len()];
+                if let Some(rest) = widest.get_mut(1..).filter(|_| signed) {
+                    rest.fill(LogicState::Zero);
+                }
+                let widest = format_value(&LogicValue::from_bits(widest), 'd', Some(0), signed);
+                format!("{:>1$}", text, widest.len())
+            }
+            't' => format!("{:>1$}", text, TIME_WIDTH),
+            _ => text,
+        };
+    };
+
+    let text = match fill {
+        '0' => match text.trim_start_matches('0') {
+            "" => "0".to_owned(),
+            digits => digits.to_owned(),
+        },
+        _ => text,
+    };
+
+    match fill {
+        '0' => format!("{:0>1$}", text, width),
+        _ => format!("{:>1$}", text, width),
+    }
+}
+
+/// Formats a value as a decimal string, or as `x` or `z` if every bit is X or
+/// Z, and as `X` if only some bits are unknown
+fn decimal_string(val: &[LogicState]) -> String {
+    to_decimal_string(val).unwrap_or_else(|_| {
+        if val.iter().all(|bit| *bit == LogicState::X) {
+            "x".to_owned()
+        } else if val.iter().all(|bit| *bit == LogicState::Z) {
+            "z".to_owned()
+        } else {
+            "X".to_owned()
+        }
+    })
+}
+
+/// Formats a two's complement value as a decimal string, as for
+/// `decimal_string` but with a `-` if it is negative
+fn signed_decimal_string(val: &[LogicState]) -> String {
+    if val.first() != Some(&LogicState::One) || val.iter().any(|bit| bit.is_unknown()) {
+        return decimal_string(val);
+    }
+
+    // Negates the value by inverting its bits and adding one
+    let mut carry = true;
+    let mut magnitude: Vec<LogicState> = val
+        .iter()
+        .rev()
+        .map(|bit| {
+            let inverted = *bit == LogicState::Zero;
+            let sum = inverted != carry;
+            carry = inverted && carry;
+            LogicState::from(sum)
+        })
+        .collect();
+    magnitude.reverse();
+
+    format!("-{}", decimal_string(&magnitude))
+}
+
+/// Formats a value as ASCII text, eight bits to a character, skipping any
+/// null characters
+fn ascii_string(val: &LogicValue) -> String {
+    val.resize(val.width().div_ceil(8) * 8)
+        .bits()
+        .chunks(8)
+        .map(|byte| {
+            byte.iter()
+                .fold(0u8, |acc, bit| (acc << 1) | (*bit == LogicState::One) as u8)
+        })
+        .filter(|byte| *byte != 0)
+        .map(char::from)
+        .collect()
+}
+
+/// Parses the contents of a `$readmemh` or `$readmemb` file into the values
+/// it loads, with their addresses
+///
+/// Values are written in hexadecimal if `hex` is set, or binary otherwise, and
+/// are separated by whitespace or comments. They load at consecutive addresses
+/// from `start`, counting down if `descending` is set, and an `@address`,
+/// always in hexadecimal, moves loading to a new address. Each value is
+/// truncated or zero-extended to `width` bits. A malformed value or address
+/// returns `LexingError::InvalidInteger`
+pub fn parse_mem_file(
+    text: &str,
+    hex: bool,
+    width: u64,
+    start: u64,
+    descending: bool,
+) -> Result<Vec<(u64, LogicValue)>, LexingError> {
+    let mut values = Vec::new();
+    // Values counted down past address 0 are skipped
+    let mut address = Some(start);
+    let mut rest = text;
+
+    loop {
+        rest = rest.trim_start();
+        if let Some(comment) = rest.strip_prefix("//") {
+            rest = comment.find('\n').map_or("", |end| &comment[end..]);
+            continue;
+        }
+        if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.find("*/").map_or("", |end| &comment[end + 2..]);
+            continue;
+        }
+        if rest.is_empty() {
+            return Ok(values);
+        }
+
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(rest.len());
+        let (word, after) = rest.split_at(end);
+        rest = after;
+
+        let invalid = || {
+            error!("invalid value '{}' in memory file", word);
+            LexingError::InvalidInteger(word.to_owned())
+        };
+
+        if let Some(target) = word.strip_prefix('@') {
+            address =
+                Some(u64::from_str_radix(&target.replace('_', ""), 16).map_err(|_| invalid())?);
+            continue;
+        }
+
+        let (radix, digit_bits) = if hex { (16, 4) } else { (2, 1) };
+        let mut bits = Vec::new();
+        for c in word.chars().filter(|c| *c != '_') {
+            match c.to_ascii_lowercase() {
+                'x' => bits.extend(vec![LogicState::X; digit_bits]),
+                'z' | '?' => bits.extend(vec![LogicState::Z; digit_bits]),
+                _ => {
+                    let digit = c.to_digit(radix).ok_or_else(invalid)?;
+                    bits.extend(
+                        (0..digit_bits)
+                            .rev()
+                            .map(|idx| LogicState::from((digit >> idx) & 1 == 1)),
+                    );
+                }
+            }
+        }
+
+        if let Some(address) = address {
+            values.push((address, LogicValue::from_bits(bits).resize(width)));
+        }
+        address = address.and_then(|address| match descending {
+            true => address.checked_sub(1),
+            false => address.checked_add(1),
+        });
+    }
+}
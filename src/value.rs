@@ -0,0 +1,1031 @@
+use crate::{LexingError, SimError, Token};
+use log::{error, warn};
+use logos::Lexer;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, BitAnd, BitOr, BitXor, Mul, Not, Sub};
+
+/// Four-state logic value of a single bit
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogicState {
+    /// Logic low
+    Zero,
+
+    /// Logic high
+    One,
+
+    /// Unknown value
+    #[default]
+    X,
+
+    /// High impedance
+    Z,
+}
+
+impl LogicState {
+    /// Returns `true` for the unknown and high impedance states
+    pub fn is_unknown(&self) -> bool {
+        matches!(self, Self::X | Self::Z)
+    }
+}
+
+impl From<bool> for LogicState {
+    fn from(val: bool) -> Self {
+        match val {
+            true => Self::One,
+            false => Self::Zero,
+        }
+    }
+}
+
+/// Four-state AND
+///
+/// A known 0 on either side forces the result to 0, so `0 & X` is 0
+impl BitAnd for LogicState {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        match (self, rhs) {
+            (Self::Zero, _) | (_, Self::Zero) => Self::Zero,
+            (Self::One, Self::One) => Self::One,
+            _ => Self::X,
+        }
+    }
+}
+
+/// Four-state OR
+///
+/// A known 1 on either side forces the result to 1, so `1 | X` is 1
+impl BitOr for LogicState {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        match (self, rhs) {
+            (Self::One, _) | (_, Self::One) => Self::One,
+            (Self::Zero, Self::Zero) => Self::Zero,
+            _ => Self::X,
+        }
+    }
+}
+
+/// Four-state XOR
+///
+/// Any X or Z input makes the result X
+impl BitXor for LogicState {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self {
+        match (self, rhs) {
+            (Self::Zero, Self::Zero) | (Self::One, Self::One) => Self::Zero,
+            (Self::Zero, Self::One) | (Self::One, Self::Zero) => Self::One,
+            _ => Self::X,
+        }
+    }
+}
+
+/// Four-state NOT
+///
+/// X and Z both invert to X
+impl Not for LogicState {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        match self {
+            Self::Zero => Self::One,
+            Self::One => Self::Zero,
+            _ => Self::X,
+        }
+    }
+}
+
+impl fmt::Display for LogicState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Zero => write!(f, "0"),
+            Self::One => write!(f, "1"),
+            Self::X => write!(f, "x"),
+            Self::Z => write!(f, "z"),
+        }
+    }
+}
+
+/// Multi-bit four-state value
+///
+/// Bits are stored most significant first, while bit indices passed to
+/// accessors count from the least significant bit, so index 0 is the last
+/// stored bit. A bit that is entirely high impedance is represented by `Z`
+/// states rather than a separate flag
+#[derive(Default, Clone, PartialEq, Eq)]
+pub struct LogicValue {
+    bits: Vec<LogicState>,
+}
+
+impl LogicValue {
+    /// Creates a value of the given width with every bit set to X
+    ///
+    /// X is the initial value of four-state variables before they are assigned
+    pub fn new(width: u64) -> Self {
+        Self::filled(width, LogicState::X)
+    }
+
+    /// Creates a value of the given width with every bit set to `state`
+    pub fn filled(width: u64, state: LogicState) -> Self {
+        Self {
+            bits: vec![state; width as usize],
+        }
+    }
+
+    /// Creates a value from bits given most significant first
+    pub fn from_bits(bits: Vec<LogicState>) -> Self {
+        Self { bits }
+    }
+
+    /// Creates a value of the given width from an integer
+    ///
+    /// Bits above the width of the value are discarded
+    pub fn from_u128(val: u128, width: u64) -> Self {
+        Self {
+            bits: (0..width)
+                .rev()
+                .map(|idx| LogicState::from(idx < 128 && (val >> idx) & 1 == 1))
+                .collect(),
+        }
+    }
+
+    /// Number of bits in the value
+    pub fn width(&self) -> u64 {
+        self.bits.len() as u64
+    }
+
+    /// Bits of the value, most significant first
+    pub fn bits(&self) -> &[LogicState] {
+        &self.bits
+    }
+
+    /// Position in `bits` of the bit at `idx`, if it is within the value
+    fn position(&self, idx: u64) -> Option<usize> {
+        (idx < self.width()).then(|| self.bits.len() - 1 - idx as usize)
+    }
+
+    /// Returns the bit at `idx`
+    ///
+    /// Reading outside the value returns X, as in SystemVerilog
+    pub fn get_bit(&self, idx: u64) -> LogicState {
+        match self.position(idx) {
+            Some(pos) => self.bits[pos],
+            None => LogicState::X,
+        }
+    }
+
+    /// Sets the bit at `idx`
+    ///
+    /// Writing outside the value has no effect, as in SystemVerilog
+    pub fn set_bit(&mut self, idx: u64, state: LogicState) {
+        if let Some(pos) = self.position(idx) {
+            self.bits[pos] = state;
+        }
+    }
+
+    /// Returns bits `high` down to `low`, as in `val[high:low]`
+    pub fn get_range(&self, high: u64, low: u64) -> LogicValue {
+        Self {
+            bits: (low..=high).rev().map(|idx| self.get_bit(idx)).collect(),
+        }
+    }
+
+    /// Sets bits `high` down to `low`, as in `val[high:low] = new_val`
+    ///
+    /// `new_val` is aligned to `low`. It is truncated if too wide and padded
+    /// with zeros if too narrow
+    pub fn set_range(&mut self, high: u64, low: u64, new_val: &LogicValue) {
+        for idx in low..=high {
+            let state = if idx - low < new_val.width() {
+                new_val.get_bit(idx - low)
+            } else {
+                LogicState::Zero
+            };
+
+            self.set_bit(idx, state);
+        }
+    }
+
+    /// Returns the value zero-extended or truncated to `width` bits
+    ///
+    /// Truncation keeps the least significant bits
+    pub fn resize(&self, width: u64) -> LogicValue {
+        let width = width as usize;
+        let len = self.bits.len();
+
+        let bits = if width <= len {
+            self.bits[len - width..].to_vec()
+        } else {
+            let mut bits = vec![LogicState::Zero; width - len];
+            bits.extend_from_slice(&self.bits);
+            bits
+        };
+
+        Self { bits }
+    }
+
+    /// Returns the value sign-extended or truncated to `width` bits
+    ///
+    /// Extension copies the most significant bit, even if it is X or Z
+    pub fn sign_extend(&self, width: u64) -> LogicValue {
+        match self.bits.first() {
+            Some(msb) if width > self.width() => {
+                let mut bits = vec![*msb; (width - self.width()) as usize];
+                bits.extend_from_slice(&self.bits);
+                Self { bits }
+            }
+            _ => self.resize(width),
+        }
+    }
+
+    /// Applies `op` to each pair of bits, zero-extending the narrower value
+    fn zip_with(&self, rhs: &LogicValue, op: fn(LogicState, LogicState) -> LogicState) -> Self {
+        let width = self.width().max(rhs.width());
+        let lhs = self.resize(width);
+        let rhs = rhs.resize(width);
+
+        Self {
+            bits: lhs
+                .bits
+                .iter()
+                .zip(&rhs.bits)
+                .map(|(a, b)| op(*a, *b))
+                .collect(),
+        }
+    }
+
+    /// Returns `true` if every bit is Z
+    pub fn is_hi_z(&self) -> bool {
+        self.bits.iter().all(|bit| *bit == LogicState::Z)
+    }
+
+    /// Returns `true` if any bit is X or Z
+    pub fn has_unknown(&self) -> bool {
+        self.bits.iter().any(|bit| bit.is_unknown())
+    }
+
+    /// Truth of the value used as a condition
+    ///
+    /// A value is true if any bit is 1 and false if every bit is 0. Otherwise
+    /// its truth is unknown
+    pub fn truth(&self) -> LogicState {
+        if self.bits.contains(&LogicState::One) {
+            LogicState::One
+        } else if self.bits.iter().all(|bit| *bit == LogicState::Zero) {
+            LogicState::Zero
+        } else {
+            LogicState::X
+        }
+    }
+
+    /// Shifts the value left by `amount` bits, filling with zeros
+    ///
+    /// An unknown shift amount makes every bit of the result X
+    pub fn shl(&self, amount: &LogicValue) -> LogicValue {
+        let Some(amount) = self.shift_amount(amount) else {
+            return Self::new(self.width());
+        };
+
+        let mut bits = self.bits[amount..].to_vec();
+        bits.resize(self.bits.len(), LogicState::Zero);
+        Self { bits }
+    }
+
+    /// Shifts the value right by `amount` bits, filling with zeros
+    ///
+    /// An unknown shift amount makes every bit of the result X
+    pub fn shr(&self, amount: &LogicValue) -> LogicValue {
+        let Some(amount) = self.shift_amount(amount) else {
+            return Self::new(self.width());
+        };
+
+        let mut bits = vec![LogicState::Zero; amount];
+        bits.extend_from_slice(&self.bits[..self.bits.len() - amount]);
+        Self { bits }
+    }
+
+    /// Shifts the value right by `amount` bits, filling with copies of the
+    /// most significant bit, as `>>>` does for signed values
+    ///
+    /// An unknown shift amount makes every bit of the result X
+    pub fn ashr(&self, amount: &LogicValue) -> LogicValue {
+        let Some(amount) = self.shift_amount(amount) else {
+            return Self::new(self.width());
+        };
+
+        let fill = self.bits.first().copied().unwrap_or(LogicState::Zero);
+        let mut bits = vec![fill; amount];
+        bits.extend_from_slice(&self.bits[..self.bits.len() - amount]);
+        Self { bits }
+    }
+
+    /// Shift amount in bits, capped at the width of the value, or `None` if
+    /// `amount` is unknown
+    fn shift_amount(&self, amount: &LogicValue) -> Option<usize> {
+        if amount.has_unknown() {
+            return None;
+        }
+
+        let in_range = amount
+            .bits
+            .iter()
+            .rev()
+            .skip(64)
+            .all(|bit| *bit == LogicState::Zero);
+        let amount = amount.resize(amount.width().min(64)).to_u128().unwrap_or(0) as u64;
+
+        Some(match in_range {
+            true => amount.min(self.width()) as usize,
+            false => self.bits.len(),
+        })
+    }
+
+    /// Unsigned quotient, with the width of the wider operand
+    ///
+    /// Dividing by zero, or by or into a value with X or Z bits, makes every
+    /// bit of the result X. Operands wider than 128 bits are not supported
+    /// and also give X
+    pub fn div(&self, rhs: &LogicValue) -> LogicValue {
+        let width = self.width().max(rhs.width());
+
+        match (self.to_u128(), rhs.to_u128()) {
+            (Some(lhs), Some(rhs)) if rhs != 0 => Self::from_u128(lhs / rhs, width),
+            _ => Self::new(width),
+        }
+    }
+
+    /// Signed quotient of two's complement values, with the width of the
+    /// wider operand, rounding toward zero
+    ///
+    /// X results are as for `div`
+    pub fn signed_div(&self, rhs: &LogicValue) -> LogicValue {
+        let width = self.width().max(rhs.width());
+        let (lhs, rhs) = (self.signed_integer(), rhs.signed_integer());
+
+        match (lhs, rhs) {
+            (Some(lhs), Some(rhs)) if rhs != 0 => {
+                Self::from_u128(lhs.wrapping_div(rhs) as u128, width)
+            }
+            _ => Self::new(width),
+        }
+    }
+
+    /// Unsigned remainder, with the width of the wider operand
+    ///
+    /// X results are as for `div`
+    pub fn rem(&self, rhs: &LogicValue) -> LogicValue {
+        let width = self.width().max(rhs.width());
+
+        match (self.to_u128(), rhs.to_u128()) {
+            (Some(lhs), Some(rhs)) if rhs != 0 => Self::from_u128(lhs % rhs, width),
+            _ => Self::new(width),
+        }
+    }
+
+    /// Signed remainder of two's complement values, with the width of the
+    /// wider operand and the sign of the dividend
+    ///
+    /// X results are as for `div`
+    pub fn signed_rem(&self, rhs: &LogicValue) -> LogicValue {
+        let width = self.width().max(rhs.width());
+        let (lhs, rhs) = (self.signed_integer(), rhs.signed_integer());
+
+        match (lhs, rhs) {
+            (Some(lhs), Some(rhs)) if rhs != 0 => {
+                Self::from_u128(lhs.wrapping_rem(rhs) as u128, width)
+            }
+            _ => Self::new(width),
+        }
+    }
+
+    /// Two's complement value as an integer, or `None` if it contains X or Z
+    /// bits or is wider than 128 bits
+    fn signed_integer(&self) -> Option<i128> {
+        match self.width() {
+            0 => Some(0),
+            1..=128 => Some(self.sign_extend(128).to_u128()? as i128),
+            _ => None,
+        }
+    }
+
+    /// Signed comparison of two's complement values, or `None` if either
+    /// value has X or Z bits
+    pub fn signed_compare(&self, rhs: &LogicValue) -> Option<Ordering> {
+        if self.has_unknown() || rhs.has_unknown() {
+            return None;
+        }
+
+        // Values of the same sign compare as they would unsigned
+        match (self.bits.first(), rhs.bits.first()) {
+            (Some(LogicState::One), Some(LogicState::Zero)) => Some(Ordering::Less),
+            (Some(LogicState::Zero), Some(LogicState::One)) => Some(Ordering::Greater),
+            _ => {
+                let width = self.width().max(rhs.width());
+                self.sign_extend(width).compare(&rhs.sign_extend(width))
+            }
+        }
+    }
+
+    /// Unsigned comparison, or `None` if either value has X or Z bits
+    pub fn compare(&self, rhs: &LogicValue) -> Option<Ordering> {
+        if self.has_unknown() || rhs.has_unknown() {
+            return None;
+        }
+
+        let width = self.width().max(rhs.width());
+        let (lhs, rhs) = (self.resize(width), rhs.resize(width));
+
+        Some(
+            lhs.bits
+                .iter()
+                .zip(&rhs.bits)
+                .fold(Ordering::Equal, |ord, (a, b)| {
+                    ord.then((*a == LogicState::One).cmp(&(*b == LogicState::One)))
+                }),
+        )
+    }
+
+    /// Logical equality, as the `==` operator
+    ///
+    /// The result is 0 if any pair of known bits differ, X if the values
+    /// otherwise differ only in X or Z bits, and 1 if they are equal
+    pub fn logic_eq(&self, rhs: &LogicValue) -> LogicState {
+        let width = self.width().max(rhs.width());
+        let (lhs, rhs) = (self.resize(width), rhs.resize(width));
+        let mut result = LogicState::One;
+
+        for (a, b) in lhs.bits.iter().zip(&rhs.bits) {
+            if a.is_unknown() || b.is_unknown() {
+                result = LogicState::X;
+            } else if a != b {
+                return LogicState::Zero;
+            }
+        }
+
+        result
+    }
+
+    /// Case equality, as the `===` operator, comparing X and Z bits exactly
+    pub fn case_eq(&self, rhs: &LogicValue) -> bool {
+        let width = self.width().max(rhs.width());
+        self.resize(width) == rhs.resize(width)
+    }
+
+    /// Returns the value as an integer, or `None` if it contains X or Z bits
+    /// or is wider than 128 bits
+    pub fn to_u128(&self) -> Option<u128> {
+        if self.bits.len() > 128 || self.bits.iter().any(|bit| bit.is_unknown()) {
+            return None;
+        }
+
+        Some(
+            self.bits
+                .iter()
+                .fold(0, |acc, bit| (acc << 1) | (*bit == LogicState::One) as u128),
+        )
+    }
+}
+
+impl BitAnd for &LogicValue {
+    type Output = LogicValue;
+
+    fn bitand(self, rhs: Self) -> LogicValue {
+        self.zip_with(rhs, LogicState::bitand)
+    }
+}
+
+impl BitOr for &LogicValue {
+    type Output = LogicValue;
+
+    fn bitor(self, rhs: Self) -> LogicValue {
+        self.zip_with(rhs, LogicState::bitor)
+    }
+}
+
+impl BitXor for &LogicValue {
+    type Output = LogicValue;
+
+    fn bitxor(self, rhs: Self) -> LogicValue {
+        self.zip_with(rhs, LogicState::bitxor)
+    }
+}
+
+/// Unsigned addition, with the width of the wider operand
+///
+/// The sum wraps on overflow. Any X or Z bit makes every bit of the result X
+impl Add for &LogicValue {
+    type Output = LogicValue;
+
+    fn add(self, rhs: Self) -> LogicValue {
+        let width = self.width().max(rhs.width());
+        if self.has_unknown() || rhs.has_unknown() {
+            return LogicValue::new(width);
+        }
+
+        let (lhs, rhs) = (self.resize(width), rhs.resize(width));
+        let mut carry = false;
+        let mut bits: Vec<LogicState> = lhs
+            .bits
+            .iter()
+            .zip(&rhs.bits)
+            .rev()
+            .map(|(a, b)| {
+                let (a, b) = (*a == LogicState::One, *b == LogicState::One);
+                let (sum, carry_out) = full_add(a, b, carry);
+                carry = carry_out;
+                LogicState::from(sum)
+            })
+            .collect();
+
+        bits.reverse();
+        LogicValue { bits }
+    }
+}
+
+/// Adds two bits and a carry, returning the sum and carry out
+fn full_add(a: bool, b: bool, carry: bool) -> (bool, bool) {
+    (a ^ b ^ carry, (a && b) || (carry && (a ^ b)))
+}
+
+/// Unsigned subtraction, with the width of the wider operand
+///
+/// The difference wraps on underflow. Any X or Z bit makes every bit of the
+/// result X
+impl Sub for &LogicValue {
+    type Output = LogicValue;
+
+    fn sub(self, rhs: Self) -> LogicValue {
+        let width = self.width().max(rhs.width());
+        let negated = &!&rhs.resize(width) + &LogicValue::from_u128(1, width);
+
+        self + &negated
+    }
+}
+
+/// Unsigned multiplication, with the width of the wider operand
+///
+/// The product wraps on overflow. Any X or Z bit makes every bit of the
+/// result X
+impl Mul for &LogicValue {
+    type Output = LogicValue;
+
+    fn mul(self, rhs: Self) -> LogicValue {
+        let width = self.width().max(rhs.width());
+        if self.has_unknown() || rhs.has_unknown() {
+            return LogicValue::new(width);
+        }
+
+        let to_bools = |val: &LogicValue| -> Vec<bool> {
+            val.resize(width)
+                .bits
+                .iter()
+                .map(|bit| *bit == LogicState::One)
+                .collect()
+        };
+
+        LogicValue {
+            bits: wrapping_mul(&to_bools(self), &to_bools(rhs))
+                .into_iter()
+                .map(LogicState::from)
+                .collect(),
+        }
+    }
+}
+
+impl Not for &LogicValue {
+    type Output = LogicValue;
+
+    fn not(self) -> LogicValue {
+        LogicValue {
+            bits: self.bits.iter().map(|bit| !*bit).collect(),
+        }
+    }
+}
+
+impl fmt::Debug for LogicValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}'b{}", self.width(), to_binary_string(&self.bits))
+    }
+}
+
+impl fmt::Display for LogicValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", to_binary_string(&self.bits))
+    }
+}
+
+/// Width of a based literal written without one, as in `'hFF`
+const UNSIZED_LITERAL_WIDTH: u32 = 32;
+
+/// Widest value a literal or declaration may have, in bits
+///
+/// Other simulators limit widths to 2^16 bits as well. Without a limit, a
+/// literal such as `4294967295'b0` would allocate gigabytes
+pub const MAX_WIDTH: u64 = 1 << 16;
+
+/// Based numeric literal, as in `8'hFF`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizedLiteral {
+    /// Width in bits, 32 if the literal has no explicit width
+    pub width: u32,
+
+    /// Value with any X and Z bits read as 0, truncated to the width
+    pub value: u128,
+
+    /// Four-state bits of the value, most significant first
+    pub bits: Vec<LogicState>,
+
+    /// Whether the literal is signed, as in `4'sd3`
+    pub signed: bool,
+}
+
+impl From<&SizedLiteral> for LogicValue {
+    fn from(val: &SizedLiteral) -> Self {
+        LogicValue::from_bits(val.bits.clone())
+    }
+}
+
+/// Lexer callback for based literals such as `8'hFF`
+pub fn sized_literal(lex: &mut Lexer<Token>) -> Result<SizedLiteral, LexingError> {
+    parse_sized_literal(lex.slice())
+}
+
+/// Lexer callback for unbased, unsized literals such as `'1`
+pub fn unbased_literal(lex: &mut Lexer<Token>) -> LogicState {
+    match lex.slice() {
+        "'0" => LogicState::Zero,
+        "'1" => LogicState::One,
+        "'x" | "'X" => LogicState::X,
+        _ => LogicState::Z,
+    }
+}
+
+/// Parses a based literal such as `8'hFF`, `12'O7_7`, `'d10`, `4'b10xz` or
+/// `4'sd3`
+///
+/// The base and sign characters may be upper or lower case, and underscores
+/// between the digits of the width or value are ignored, as in `1_6'hFF_FF`.
+/// An `x` digit stands for unknown bits and a `z` or `?` digit for high
+/// impedance bits, as many as the digit covers in its base. Decimal literals
+/// may only use these as their sole digit.
+///
+/// Literals with fewer digits than their width are extended with zeros, or
+/// with X or Z if the leftmost digit is one. Values wider than the literal
+/// are truncated with a warning, except for unsized literals, which are at
+/// least 32 bits but widen to fit their value. Known values that do not fit
+/// in 128 bits are an error
+pub fn parse_sized_literal(slice: &str) -> Result<SizedLiteral, LexingError> {
+    let (width, rest) = slice.split_once('\'').ok_or(LexingError::UnexpectedToken)?;
+    let has_width = !width.is_empty();
+    let mut width: u32 = match width {
+        "" => UNSIZED_LITERAL_WIDTH,
+        width => width.replace('_', "").parse()?,
+    };
+
+    if width == 0 {
+        error!("literal width must be positive: '{}'", slice);
+        return Err(LexingError::InvalidInteger(slice.to_owned()));
+    }
+    if u64::from(width) > MAX_WIDTH {
+        error!("literal is wider than {} bits: '{}'", MAX_WIDTH, slice);
+        return Err(LexingError::WidthTooLarge(u64::from(width)));
+    }
+
+    let signed = rest.starts_with(['s', 'S']);
+    let mut chars = rest.chars().skip(usize::from(signed));
+    let base = chars.next().map(|c| c.to_ascii_lowercase());
+    let digits: String = chars.filter(|c| *c != '_').collect();
+
+    let mut bits = match base {
+        Some('b') => digit_bits(&digits, 1, slice)?,
+        Some('o') => digit_bits(&digits, 3, slice)?,
+        Some('h') => digit_bits(&digits, 4, slice)?,
+        Some('d') => match unknown_digit(&digits) {
+            Some(state) => vec![state],
+            None => {
+                let value = digits.parse::<u128>()?;
+                let width = u128::BITS - value.leading_zeros();
+                LogicValue::from_u128(value, u64::from(width.max(1))).bits
+            }
+        },
+        _ => {
+            error!("unknown literal base: '{}'", slice);
+            return Err(LexingError::InvalidInteger(slice.to_owned()));
+        }
+    };
+
+    let zeros = bits.iter().take_while(|bit| **bit == LogicState::Zero);
+    let needed = bits.len() - zeros.count();
+    if !has_width && needed > width as usize {
+        if needed as u64 > MAX_WIDTH {
+            error!("literal is wider than {} bits: '{}'", MAX_WIDTH, slice);
+            return Err(LexingError::WidthTooLarge(needed as u64));
+        }
+        width = needed as u32;
+    }
+
+    let width_bits = width as usize;
+    if bits.len() < width_bits {
+        let fill = match bits[0] {
+            LogicState::X | LogicState::Z => bits[0],
+            _ => LogicState::Zero,
+        };
+        bits.splice(0..0, vec![fill; width_bits - bits.len()]);
+    } else if bits.len() > width_bits {
+        let dropped: Vec<LogicState> = bits.drain(..bits.len() - width_bits).collect();
+        if dropped.iter().any(|bit| *bit != LogicState::Zero) {
+            warn!(
+                "literal '{}' does not fit in {} bits and is truncated",
+                slice, width
+            );
+        }
+    }
+
+    let high = bits.len().saturating_sub(u128::BITS as usize);
+    if bits[..high].contains(&LogicState::One) {
+        error!("literal '{}' does not fit in 128 bits", slice);
+        return Err(LexingError::InvalidInteger("overflow error".to_owned()));
+    }
+    let value = bits[high..].iter().fold(0, |acc, bit| {
+        (acc << 1) | u128::from(*bit == LogicState::One)
+    });
+
+    Ok(SizedLiteral {
+        width,
+        value,
+        bits,
+        signed,
+    })
+}
+
+/// State of an `x`, `z` or `?` literal digit
+fn unknown_digit(digit: &str) -> Option<LogicState> {
+    match digit {
+        "x" | "X" => Some(LogicState::X),
+        "z" | "Z" | "?" => Some(LogicState::Z),
+        _ => None,
+    }
+}
+
+/// Converts the digits of a binary, octal or hexadecimal literal to bits,
+/// with `digit_width` bits per digit
+fn digit_bits(digits: &str, digit_width: u32, slice: &str) -> Result<Vec<LogicState>, LexingError> {
+    let mut bits = Vec::with_capacity(digits.len() * digit_width as usize);
+
+    for (idx, digit) in digits.char_indices() {
+        if let Some(state) = unknown_digit(&digits[idx..idx + 1]) {
+            bits.extend(std::iter::repeat_n(state, digit_width as usize));
+            continue;
+        }
+
+        match digit.to_digit(1 << digit_width) {
+            Some(val) => bits.extend(
+                (0..digit_width)
+                    .rev()
+                    .map(|bit| LogicState::from((val >> bit) & 1 == 1)),
+            ),
+            None => {
+                error!("invalid digit '{}' in literal: '{}'", digit, slice);
+                return Err(LexingError::InvalidInteger(slice.to_owned()));
+            }
+        }
+    }
+
+    Ok(bits)
+}
+
+/// Formats a value as a binary string
+///
+/// Bits are given most significant first, so `[One, Zero, X, Z]`
+/// formats as `10xz`
+pub fn to_binary_string(val: &[LogicState]) -> String {
+    val.iter().map(|bit| bit.to_string()).collect()
+}
+
+/// Formats a value as a hexadecimal string
+///
+/// Bits are grouped four at a time starting from the least significant bit.
+/// A group made up entirely of X or Z bits is shown as `x` or `z`, while a
+/// group only partially made up of them is shown as `X` or `Z`
+pub fn to_hex_string(val: &[LogicState]) -> String {
+    to_radix_string(val, 4)
+}
+
+/// Formats a value as an octal string
+///
+/// Bits are grouped three at a time, with X and Z bits shown as in
+/// `to_hex_string`
+pub fn to_octal_string(val: &[LogicState]) -> String {
+    to_radix_string(val, 3)
+}
+
+/// Formats a value with one digit for every `group` bits, counting from the
+/// least significant bit
+fn to_radix_string(val: &[LogicState], group: usize) -> String {
+    let mut digits: Vec<char> = val
+        .rchunks(group)
+        .map(|group| {
+            if group.iter().all(|bit| *bit == LogicState::X) {
+                'x'
+            } else if group.iter().all(|bit| *bit == LogicState::Z) {
+                'z'
+            } else if group.contains(&LogicState::X) {
+                'X'
+            } else if group.contains(&LogicState::Z) {
+                'Z'
+            } else {
+                let digit = group
+                    .iter()
+                    .fold(0, |acc, bit| (acc << 1) | (*bit == LogicState::One) as u32);
+                char::from_digit(digit, 16).unwrap_or('0')
+            }
+        })
+        .collect();
+
+    digits.reverse();
+    digits.into_iter().collect()
+}
+
+/// Formats a value as an unsigned decimal string
+///
+/// Values of any width are supported. Returns `SimError::UnknownValue` if any
+/// bit is X or Z
+pub fn to_decimal_string(val: &[LogicState]) -> Result<String, SimError> {
+    if val.iter().any(|bit| bit.is_unknown()) {
+        return Err(SimError::UnknownValue);
+    }
+
+    // Decimal digits, least significant first
+    let mut digits: Vec<u8> = vec![0];
+
+    for bit in val {
+        let mut carry = (*bit == LogicState::One) as u8;
+
+        for digit in digits.iter_mut() {
+            let doubled = *digit * 2 + carry;
+            *digit = doubled % 10;
+            carry = doubled / 10;
+        }
+
+        if carry > 0 {
+            digits.push(carry);
+        }
+    }
+
+    Ok(digits
+        .iter()
+        .rev()
+        .map(|digit| (b'0' + digit) as char)
+        .collect())
+}
+
+/// Raises `base` to the power `exp`, as the `**` operator
+///
+/// Both operands are unsigned. The result has the width of `base` and wraps
+/// on overflow. If either operand contains an X or Z bit, every bit of the
+/// result is X
+pub fn sv_pow(base: &[LogicState], exp: &[LogicState]) -> Vec<LogicState> {
+    if base.iter().chain(exp).any(|bit| bit.is_unknown()) {
+        return vec![LogicState::X; base.len()];
+    }
+
+    let base: Vec<bool> = base.iter().map(|bit| *bit == LogicState::One).collect();
+    let mut result = vec![false; base.len()];
+
+    if let Some(last) = result.last_mut() {
+        *last = true;
+    }
+
+    // Square and multiply, working through the exponent from its top bit
+    for bit in exp {
+        result = wrapping_mul(&result, &result);
+
+        if *bit == LogicState::One {
+            result = wrapping_mul(&result, &base);
+        }
+    }
+
+    result.into_iter().map(LogicState::from).collect()
+}
+
+/// Multiplies two equal width, most significant bit first values, keeping
+/// only the low bits
+fn wrapping_mul(a: &[bool], b: &[bool]) -> Vec<bool> {
+    let width = a.len();
+    let mut product = vec![false; width];
+
+    for (shift, _) in b.iter().rev().enumerate().filter(|(_, bit)| **bit) {
+        let mut carry = false;
+
+        for idx in (0..width - shift).rev() {
+            let addend = a[idx + shift];
+            let sum = product[idx] ^ addend ^ carry;
+            carry = (product[idx] && addend) || (carry && (product[idx] ^ addend));
+            product[idx] = sum;
+        }
+    }
+
+    product
+}
+
+/// Strength with which a driver drives a net
+///
+/// Strengths are ordered from weakest to strongest
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DriveStrength {
+    /// High impedance, the driver is not driving
+    HighZ,
+
+    /// Weak drive (`weak0`/`weak1`)
+    Weak,
+
+    /// Pull drive (`pull0`/`pull1`)
+    Pull,
+
+    /// Strong drive (`strong0`/`strong1`), the default for assignments
+    #[default]
+    Strong,
+
+    /// Supply drive (`supply0`/`supply1`)
+    Supply,
+}
+
+/// Identifies a single driver of a net
+pub type DriverId = usize;
+
+/// Net driven by any number of drivers
+///
+/// Each driver's value is kept separately and combined by `resolve`, so
+/// several continuous assignments or port connections can drive the same
+/// `wire` or `tri` net
+#[derive(Default, Debug, Clone)]
+pub struct Net {
+    /// Net width in bits
+    pub width: u64,
+
+    /// Driver values, most significant bit first
+    pub drivers: Vec<(DriverId, DriveStrength, Vec<LogicState>)>,
+}
+
+impl Net {
+    /// Creates an undriven net
+    pub fn new(width: u64) -> Self {
+        Self {
+            width,
+            drivers: Vec::new(),
+        }
+    }
+
+    /// Sets the value and strength of a driver, adding it if not yet present
+    pub fn drive(&mut self, id: DriverId, strength: DriveStrength, val: Vec<LogicState>) {
+        match self.drivers.iter_mut().find(|(driver, _, _)| *driver == id) {
+            Some(driver) => *driver = (id, strength, val),
+            None => self.drivers.push((id, strength, val)),
+        }
+    }
+
+    /// Removes a driver from the net
+    pub fn release(&mut self, id: DriverId) {
+        self.drivers.retain(|(driver, _, _)| *driver != id);
+    }
+
+    /// Resolves the value of the net from all of its drivers
+    ///
+    /// Each bit is resolved independently. A `Z` bit does not drive, and the
+    /// strongest remaining drivers determine the result: if they agree their
+    /// value wins, otherwise the bit is `X`. A bit with no driver is `Z`.
+    /// Driver values narrower than the net are treated as `Z` in their
+    /// missing upper bits
+    pub fn resolve(&self) -> Vec<LogicState> {
+        (0..self.width as usize)
+            .rev()
+            .map(|bit| {
+                let mut strongest = DriveStrength::HighZ;
+                let mut resolved = LogicState::Z;
+
+                for (_, strength, val) in &self.drivers {
+                    let state = match val.len().checked_sub(bit + 1) {
+                        Some(idx) => val[idx],
+                        None => LogicState::Z,
+                    };
+
+                    if state == LogicState::Z || *strength == DriveStrength::HighZ {
+                        continue;
+                    }
+
+                    if *strength > strongest {
+                        strongest = *strength;
+                        resolved = state;
+                    } else if *strength == strongest && state != resolved {
+                        resolved = LogicState::X;
+                    }
+                }
+
+                resolved
+            })
+            .collect()
+    }
+}
@@ -1,6 +1,15 @@
-use crate::{LexingError, Token};
+use crate::eval::{calls_function, const_index, ValueLookup};
+use crate::expr::{expr_sv_string, integer_literal, parse_expr, BinOp, Expr};
+use crate::value::{LogicState, LogicValue, MAX_WIDTH};
+use crate::{
+    expect_token, next_token, parse_block_comment, parse_comment, peek_token, LexingError, Token,
+};
 use log::{error, trace};
 use logos::Lexer;
+use std::collections::HashMap;
+
+/// Most elements a memory may have
+pub const MAX_MEMORY_DEPTH: u64 = 1 << 24;
 
 #[derive(Default, Debug, Clone)]
 pub struct Input {
@@ -20,11 +29,52 @@ pub struct Inout {
     pub var: Var,
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, PartialEq)]
 pub enum VarType {
     #[default]
     Wire,
     Reg,
+    Tri,
+    Logic,
+    Byte,
+    ShortInt,
+    Int,
+    LongInt,
+    Integer,
+    Genvar,
+}
+
+impl VarType {
+    /// Width of an integer type, or `None` for types sized by a range
+    pub fn width(&self) -> Option<u64> {
+        match self {
+            VarType::Byte => Some(8),
+            VarType::ShortInt => Some(16),
+            VarType::Int | VarType::Integer | VarType::Genvar => Some(32),
+            VarType::LongInt => Some(64),
+            VarType::Wire | VarType::Reg | VarType::Tri | VarType::Logic => None,
+        }
+    }
+
+    /// Whether the type is a net, whose value is resolved from every
+    /// continuous assignment and port connection driving it
+    pub fn net(&self) -> bool {
+        matches!(self, VarType::Wire | VarType::Tri)
+    }
+
+    /// Whether values of the type are signed unless declared `unsigned`
+    pub fn signed(&self) -> bool {
+        self.width().is_some()
+    }
+
+    /// Whether the type is one of the two-state integer types, whose values
+    /// start out as zero rather than X
+    pub fn two_state(&self) -> bool {
+        matches!(
+            self,
+            VarType::Byte | VarType::ShortInt | VarType::Int | VarType::LongInt
+        )
+    }
 }
 
 impl From<&str> for VarType {
@@ -32,18 +82,153 @@ impl From<&str> for VarType {
         match val {
             "wire" => VarType::Wire,
             "reg" => VarType::Reg,
+            "tri" => VarType::Tri,
+            "logic" => VarType::Logic,
+            "byte" => VarType::Byte,
+            "shortint" => VarType::ShortInt,
+            "int" => VarType::Int,
+            "longint" => VarType::LongInt,
+            "integer" => VarType::Integer,
+            "genvar" => VarType::Genvar,
             _ => VarType::default(),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Var {
     pub name: String,
     pub width: u64,
+    /// Index of the least significant bit, as the 1 of `[8:1]`
+    pub lsb: u64,
+    pub var_type: VarType,
+    /// Whether the value is declared `signed`
+    pub signed: bool,
+    pub state: LogicValue,
+    /// Address range of a memory, as `(first, last)` for `mem [0:15]`,
+    /// or `None` for a single value
+    pub array: Option<(u64, u64)>,
+    /// Bounds of the range giving `width` while they depend on parameters,
+    /// as in `[WIDTH-1:0]`
+    pub bounds: Option<Bounds>,
+    /// Bounds of the memory address range while they depend on parameters,
+    /// as in `[0:DEPTH-1]`
+    pub array_bounds: Option<Bounds>,
+}
+
+impl Var {
+    /// Returns bit `idx` of the value, counting up from the least
+    /// significant bit, or X outside the value
+    pub fn get_bit(&self, idx: u64) -> LogicState {
+        self.state.get_bit(idx)
+    }
+
+    /// Sets bit `idx` of the value, counting up from the least significant
+    /// bit. Writing outside the value has no effect
+    pub fn set_bit(&mut self, idx: u64, state: LogicState) {
+        self.state.set_bit(idx, state)
+    }
+
+    /// Returns bits `high` down to `low` of the value, as in `data[3:0]`
+    pub fn get_range(&self, high: u64, low: u64) -> LogicValue {
+        self.state.get_range(high, low)
+    }
+
+    /// Sets bits `high` down to `low` of the value, as in
+    /// `data[3:0] = 4'b1010`, leaving the other bits as they were
+    ///
+    /// `value` is truncated if too wide and padded with zeros if too narrow
+    pub fn set_range(&mut self, high: u64, low: u64, value: &LogicValue) {
+        self.state.set_range(high, low, value)
+    }
+
+    /// Value before anything is assigned, which is zero for two-state types,
+    /// Z for nets, which float until they are driven, and X otherwise
+    pub fn initial_value(&self) -> LogicValue {
+        match (self.var_type.two_state(), self.var_type.net()) {
+            (true, _) => LogicValue::filled(self.width, LogicState::Zero),
+            (false, true) => LogicValue::filled(self.width, LogicState::Z),
+            (false, false) => LogicValue::new(self.width),
+        }
+    }
+
+    /// Addresses of a memory's elements, from the lowest up, or `None` for a
+    /// single value
+    pub fn addresses(&self) -> Option<std::ops::RangeInclusive<u64>> {
+        self.array
+            .map(|(first, last)| first.min(last)..=first.max(last))
+    }
+
+    /// Works out the width and memory address range from bounds that depend
+    /// on parameters, using `params` as their values
+    ///
+    /// Returns whether every bound is now known. A range that is negative or
+    /// too wide is an error
+    pub fn apply_bounds(&mut self, params: &impl ValueLookup) -> Result<bool, LexingError> {
+        if let Some((end, start)) = self.bounds.as_ref().and_then(|bounds| bounds.eval(params)) {
+            self.width = range_width(end, start)?;
+            self.lsb = start;
+            self.state = LogicValue::new(self.width);
+            self.bounds = None;
+        }
+
+        if let Some((first, last)) = self.array_bounds.as_ref().and_then(|bounds| bounds.eval(params)) {
+            let depth = first.abs_diff(last).saturating_add(1);
+            if depth > MAX_MEMORY_DEPTH {
+                error!("memory '{}' has more than {} elements", self.name, MAX_MEMORY_DEPTH);
+                return Err(LexingError::MemoryTooLarge(depth));
+            }
+            self.array = Some((first, last));
+            self.array_bounds = None;
+        }
+
+        Ok(self.bounds.is_none() && self.array_bounds.is_none())
+    }
+}
+
+/// Data type given to a type parameter, as the `logic [7:0]` of
+/// `localparam type T = logic [7:0]`, or to a parameter, as the `int` of
+/// `parameter int N = 4`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeExpr {
+    /// Base type
     pub var_type: VarType,
-    pub state: bool,
-    pub hi_z: bool,
+
+    /// Whether values of the type are signed
+    pub signed: bool,
+
+    /// Width, which is only known once the bounds are applied if there are
+    /// any
+    pub width: u64,
+
+    /// Bounds of the range, if they depend on parameters
+    pub bounds: Option<Bounds>,
+}
+
+impl From<VarType> for TypeExpr {
+    fn from(var_type: VarType) -> Self {
+        TypeExpr {
+            width: var_type.width().unwrap_or(1),
+            signed: var_type.signed(),
+            bounds: None,
+            var_type,
+        }
+    }
+}
+
+/// Bounds of a range, as the `WIDTH-1` and `0` of `[WIDTH-1:0]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bounds {
+    pub left: Expr,
+    pub right: Expr,
+}
+
+impl Bounds {
+    /// Values of the left and right bound, or `None` if either is not a
+    /// known constant with `params` as the values of parameters
+    pub fn eval(&self, params: &impl ValueLookup) -> Option<(u64, u64)> {
+        Some((const_index(&self.left, params)?, const_index(&self.right, params)?))
+    }
 }
 
 impl Default for Var {
@@ -51,33 +236,104 @@ impl Default for Var {
         Self {
             name: String::new(),
             width: 1,
+            lsb: 0,
             var_type: VarType::default(),
-            state: false,
-            hi_z: false,
+            signed: false,
+            state: LogicValue::new(1),
+            array: None,
+            bounds: None,
+            array_bounds: None,
+        }
+    }
+}
+
+impl From<&VarType> for &str {
+    fn from(val: &VarType) -> Self {
+        match val {
+            VarType::Wire => "wire",
+            VarType::Reg => "reg",
+            VarType::Tri => "tri",
+            VarType::Logic => "logic",
+            VarType::Byte => "byte",
+            VarType::ShortInt => "shortint",
+            VarType::Int => "int",
+            VarType::LongInt => "longint",
+            VarType::Integer => "integer",
+            VarType::Genvar => "genvar",
         }
     }
 }
 
+/// Formats a variable declaration as SystemVerilog, e.g. `reg [7:0] count`,
+/// `wire signed [7:0] data` or `int unsigned total`
+pub fn var_sv_string(var: &Var) -> String {
+    let var_type: &str = (&var.var_type).into();
+    let var_type = match (var.signed, var.var_type.signed()) {
+        (true, false) => format!("{} signed", var_type),
+        (false, true) => format!("{} unsigned", var_type),
+        _ => var_type.to_owned(),
+    };
+
+    // Integer types have a fixed width and cannot take a range
+    if var.var_type.width().is_some() {
+        return format!("{} {}", var_type, sv_identifier(&var.name));
+    }
+
+    let decl = match (&var.bounds, var.width) {
+        (Some(bounds), _) => format!(
+            "{} [{}:{}] {}",
+            var_type,
+            expr_sv_string(&bounds.left),
+            expr_sv_string(&bounds.right),
+            sv_identifier(&var.name)
+        ),
+        (None, 0 | 1) => format!("{} {}", var_type, sv_identifier(&var.name)),
+        (None, width) => format!("{} [{}:0] {}", var_type, width - 1, sv_identifier(&var.name)),
+    };
+
+    match (&var.array_bounds, var.array) {
+        (Some(bounds), _) => format!(
+            "{} [{}:{}]",
+            decl,
+            expr_sv_string(&bounds.left),
+            expr_sv_string(&bounds.right)
+        ),
+        (None, Some((first, last))) => format!("{} [{}:{}]", decl, first, last),
+        (None, None) => decl,
+    }
+}
+
+/// Formats a name as a SystemVerilog identifier
+///
+/// Names that are not simple identifiers are written in escaped form, which
+/// must be terminated by whitespace
+pub fn sv_identifier(name: &str) -> String {
+    let simple = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+
+    if simple {
+        name.to_owned()
+    } else {
+        format!("\\{} ", name)
+    }
+}
+
 pub fn parse_input<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Input, LexingError> {
     trace!("parsing input");
 
     match parse_var(lexer) {
-        Ok((var_type, name, width)) => Ok(Input {
-            name: name.to_owned(),
-            var: Var {
-                name: name.to_owned(),
-                width,
-                var_type,
-                state: false,
-                hi_z: false,
-            },
+        Ok(var) => Ok(Input {
+            name: var.name.to_owned(),
+            var,
         }),
         Err(e) => {
             error!(
                 "unexpected error occurred parsing input: '{}'",
                 lexer.slice()
             );
-            return Err(e);
+            Err(e)
         }
     }
 }
@@ -86,22 +342,16 @@ pub fn parse_output<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Output
     trace!("parsing output");
 
     match parse_var(lexer) {
-        Ok((var_type, name, width)) => Ok(Output {
-            name: name.to_owned(),
-            var: Var {
-                name: name.to_owned(),
-                width,
-                var_type,
-                state: false,
-                hi_z: false,
-            },
+        Ok(var) => Ok(Output {
+            name: var.name.to_owned(),
+            var,
         }),
         Err(e) => {
             error!(
                 "unexpected error occurred parsing output: '{}'",
                 lexer.slice()
             );
-            return Err(e);
+            Err(e)
         }
     }
 }
@@ -110,31 +360,30 @@ pub fn parse_inout<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Inout,
     trace!("parsing inout");
 
     match parse_var(lexer) {
-        Ok((var_type, name, width)) => Ok(Inout {
-            name: name.to_owned(),
-            var: Var {
-                name: name.to_owned(),
-                width,
-                var_type,
-                state: false,
-                hi_z: false,
-            },
+        Ok(var) => Ok(Inout {
+            name: var.name.to_owned(),
+            var,
         }),
         Err(e) => {
             error!(
                 "unexpected error occurred parsing input: '{}'",
                 lexer.slice()
             );
-            return Err(e);
+            Err(e)
         }
     }
 }
 
-pub fn parse_var<'source>(
-    lexer: &mut Lexer<'source, Token>,
-) -> Result<(VarType, String, u64), LexingError> {
-    let mut width: u64 = 1;
+/// Parses a variable declaration to completion, as in `wire signed [7:0] data`
+///
+/// The type defaults to `VarType::default()` when it is not given. Without
+/// a range or qualifier, the width and signedness are those of the type
+pub fn parse_var<'source>(lexer: &mut Lexer<'source, Token>) -> Result<Var, LexingError> {
+    let mut width = None;
+    let mut lsb = 0;
+    let mut bounds = None;
     let mut var_type = VarType::default();
+    let mut signed = None;
 
     trace!("parsing variable");
 
@@ -142,17 +391,46 @@ pub fn parse_var<'source>(
         match token {
             Ok(Token::Wire) => var_type = VarType::Wire,
             Ok(Token::Reg) => var_type = VarType::Reg,
-            Ok(Token::Word) => match parse_name(lexer) {
-                Ok(name) => return Ok((var_type, name, width)),
+            Ok(Token::Tri) => var_type = VarType::Tri,
+            Ok(Token::Logic) | Ok(Token::Var) => var_type = VarType::Logic,
+            Ok(Token::Byte) => var_type = VarType::Byte,
+            Ok(Token::ShortInt) => var_type = VarType::ShortInt,
+            Ok(Token::Int) => var_type = VarType::Int,
+            Ok(Token::LongInt) => var_type = VarType::LongInt,
+            Ok(Token::IntegerType) => var_type = VarType::Integer,
+            Ok(Token::Signed) => signed = Some(true),
+            Ok(Token::Unsigned) => signed = Some(false),
+            Ok(Token::Word) | Ok(Token::EscapedIdentifier(_)) => match parse_name(lexer) {
+                Ok(name) => {
+                    let width = width.or(var_type.width()).unwrap_or(1);
+
+                    return Ok(Var {
+                        name,
+                        width,
+                        lsb,
+                        signed: signed.unwrap_or(var_type.signed()),
+                        var_type,
+                        state: LogicValue::new(width),
+                        bounds,
+                        ..Default::default()
+                    });
+                }
                 Err(e) => return Err(e),
             },
-            Ok(Token::OpenBracket) => match parse_width(lexer) {
-                Ok(val) => width = val,
+            Ok(Token::OpenBracket) => match parse_range(lexer) {
+                Ok((val, val_lsb, val_bounds)) => {
+                    width = Some(val);
+                    lsb = val_lsb;
+                    bounds = val_bounds;
+                }
                 Err(e) => return Err(e),
             },
-            Ok(Token::Comment) => match crate::parse_comment(lexer) {
-                _ => (),
-            },
+            Ok(Token::LineComment) => {
+                let _ = crate::parse_comment(lexer);
+            }
+            Ok(Token::BlockCommentStart) => {
+                let _ = crate::parse_block_comment(lexer);
+            }
             Ok(Token::WhiteSpace) => (),
             Err(e) => {
                 error!(
@@ -171,17 +449,78 @@ pub fn parse_var<'source>(
     Err(LexingError::UnexpectedToken)
 }
 
+/// Parses a name to completion, starting from the current token
+///
+/// The current token must be a `Token::Word` or `Token::EscapedIdentifier`.
+/// Only escaped identifiers may start with a digit, anything else starting
+/// with one returns `LexingError::InvalidIdentifier`
 pub fn parse_name<'source>(lexer: &mut Lexer<'source, Token>) -> Result<String, LexingError> {
-    let mut name = lexer.slice().to_owned();
+    let (name, array_bounds) = parse_array_name(lexer)?;
+
+    if array_bounds.is_some() {
+        error!("'{}' cannot be declared as a memory here", name);
+        return Err(LexingError::UnexpectedToken);
+    }
+
+    Ok(name)
+}
+
+/// Parses a name to completion as `parse_name`, along with the bounds of the
+/// address range of a memory following it, as in `mem [0:15]` or `mem [16]`
+///
+/// The bounds are left for `Var::apply_bounds` to evaluate
+pub fn parse_array_name<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<(String, Option<Bounds>), LexingError> {
+    let slice = lexer.slice();
+    let mut array = None;
 
     trace!("parsing variable name");
 
+    let mut name = match slice.strip_prefix('\\') {
+        Some(escaped) => escaped.to_owned(),
+        None if slice.starts_with(|c: char| c.is_ascii_digit()) => {
+            error!("identifier '{}' cannot start with a digit unless escaped", slice);
+            return Err(LexingError::InvalidIdentifier(slice.to_owned()));
+        }
+        None => slice.to_owned(),
+    };
+
     while let Some(token) = lexer.next() {
         match token {
-            Ok(Token::Word) => name += lexer.slice(),
-            Ok(Token::Underscore) => name += "_",
+            Ok(Token::Word) if array.is_none() => name += lexer.slice(),
+            Ok(Token::Underscore) if array.is_none() => name += "_",
+            // Digits after the first character lex as numbers, as in `data_8b`
+            Ok(Token::Integer(_)) | Ok(Token::Time(_)) if array.is_none() => name += lexer.slice(),
+            // A memory of `[size]` elements is addressed from 0
+            Ok(Token::OpenBracket) => {
+                array = match parse_bounds(lexer)? {
+                    (first, Some(last)) => Some(Bounds {
+                        left: first,
+                        right: last,
+                    }),
+                    (size, None) => {
+                        if const_index(&size, &HashMap::new()) == Some(0) {
+                            error!("memory '{}' cannot have zero elements", name);
+                            return Err(LexingError::IncompleteWidth);
+                        }
+                        Some(Bounds {
+                            left: Expr::Literal(integer_literal(0), true),
+                            right: Expr::BinOp(
+                                BinOp::Sub,
+                                Box::new(size),
+                                Box::new(Expr::Literal(integer_literal(1), true)),
+                            ),
+                        })
+                    }
+                }
+            }
             Ok(Token::WhiteSpace) | Ok(Token::Newline) => (),
-            Ok(Token::Semicolon) | Ok(Token::Comma) => return Ok(name),
+            Ok(Token::LineComment) => parse_comment(lexer)?,
+            Ok(Token::BlockCommentStart) => parse_block_comment(lexer)?,
+            Ok(Token::Semicolon) | Ok(Token::Comma) | Ok(Token::CloseParen) | Ok(Token::Equals) => {
+                return Ok((name, array))
+            }
             Err(e) => {
                 error!(
                     "unexpected error occurred parsing variable name: '{}'",
@@ -193,51 +532,117 @@ pub fn parse_name<'source>(lexer: &mut Lexer<'source, Token>) -> Result<String,
         };
     }
 
-    Ok(name)
+    Ok((name, array))
 }
 
-pub fn parse_width<'source>(lexer: &mut Lexer<'source, Token>) -> Result<u64, LexingError> {
-    let mut start = 0;
-    let mut end = 0;
-    let mut end_found = false;
+/// Parses a bit width, as in `[7:0]`, to completion
+///
+/// The opening bracket has already been consumed. A single index, as in
+/// `[0]`, is one bit wide. Returns the width, and the bounds of the range if
+/// they depend on parameters, as in `[WIDTH-1:0]`, in which case the width
+/// is only known once `Var::apply_bounds` evaluates them. Several packed
+/// dimensions, as in `[3:0][7:0]`, are not supported and return an error
+pub fn parse_width<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<(u64, Option<Bounds>), LexingError> {
+    parse_range(lexer).map(|(width, _, bounds)| (width, bounds))
+}
 
+/// Parses a bit width to completion as `parse_width`, along with the index
+/// of its least significant bit, as the 1 of `[8:1]`
+///
+/// The index is 0 while the bounds depend on parameters
+pub fn parse_range<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<(u64, u64, Option<Bounds>), LexingError> {
     trace!("parsing variable width");
 
-    while let Some(token) = lexer.next() {
-        match token {
-            Ok(Token::Integer(val)) => {
-                if !end_found {
-                    end = val;
-                    end_found = true;
-                } else {
-                    start = val;
-                }
-            }
-            Ok(Token::Colon) | Ok(Token::WhiteSpace) => (),
-            Ok(Token::CloseBracket) => {
-                if end < start {
-                    error!(
-                        "cannot assign a negative width to var (start: {}, end: {})",
-                        start, end
-                    );
-                    return Err(LexingError::NegativeBitWidth);
-                }
+    let bounds = parse_bounds(lexer)?;
+    if peek_token(lexer) == Some(Ok(Token::OpenBracket)) {
+        error!("multiple packed dimensions are not supported");
+        return Err(LexingError::UnexpectedToken);
+    }
 
-                return Ok(end - start + 1);
-            }
-            Err(e) => {
-                error!(
-                    "unexpected error occurred parsing variable width: '{}'",
-                    lexer.slice()
-                );
-                return Err(e);
+    let bounds = match bounds {
+        (left, Some(right)) => Bounds { left, right },
+        // A single index such as `[0]` selects one bit
+        (_, None) => return Ok((1, 0, None)),
+    };
+
+    // Functions are only known once the module is, so calls wait until then
+    let known = match calls_function(&bounds.left) || calls_function(&bounds.right) {
+        true => None,
+        false => bounds.eval(&HashMap::new()),
+    };
+    match known {
+        Some((end, start)) => Ok((range_width(end, start)?, start, None)),
+        None => Ok((1, 0, Some(bounds))),
+    }
+}
+
+/// Width of the range `[end:start]`
+///
+/// A range whose end is below its start, or wider than `MAX_WIDTH`, is an
+/// error
+fn range_width(end: u64, start: u64) -> Result<u64, LexingError> {
+    if end < start {
+        error!(
+            "cannot assign a negative width to var (start: {}, end: {})",
+            start, end
+        );
+        return Err(LexingError::NegativeBitWidth);
+    }
+
+    let width = (end - start).checked_add(1).ok_or_else(|| {
+        error!("variable width overflowed (start: {}, end: {})", start, end);
+        LexingError::InvalidInteger("overflow error".to_owned())
+    })?;
+
+    if width > MAX_WIDTH {
+        error!("variable is wider than {} bits: [{}:{}]", MAX_WIDTH, end, start);
+        return Err(LexingError::WidthTooLarge(width));
+    }
+
+    Ok(width)
+}
+
+/// Parses the bounds of a range, as in `[7:0]` or `[WIDTH-1:0]`, or a single
+/// index, as in `[8]`, to completion
+///
+/// The opening bracket has already been consumed. Returns the left bound,
+/// and the right bound if there is one. A missing bound, as in `[7:]`, is
+/// an error
+fn parse_bounds<'source>(
+    lexer: &mut Lexer<'source, Token>,
+) -> Result<(Expr, Option<Expr>), LexingError> {
+    if peek_token(lexer) == Some(Ok(Token::CloseBracket)) {
+        error!("expected an index or range in variable width");
+        return Err(LexingError::IncompleteWidth);
+    }
+    let left = parse_expr(lexer)?;
+
+    match next_token(lexer) {
+        Some(Ok(Token::CloseBracket)) => Ok((left, None)),
+        Some(Ok(Token::Colon)) => {
+            if peek_token(lexer) == Some(Ok(Token::CloseBracket)) {
+                error!("range is missing its right bound");
+                return Err(LexingError::IncompleteWidth);
             }
-            _ => error!(
-                "unexpected value in variable width parsing, got {:?}",
-                token.unwrap()
-            ),
+            let right = parse_expr(lexer)?;
+            expect_token(lexer, Token::CloseBracket, "range")?;
+
+            Ok((left, Some(right)))
+        }
+        Some(Err(e)) => {
+            error!(
+                "unexpected error occurred parsing variable width: '{}'",
+                lexer.slice()
+            );
+            Err(e)
+        }
+        _ => {
+            error!("expected ':' or ']' in range, got '{}'", lexer.slice());
+            Err(LexingError::IncompleteWidth)
         }
     }
-
-    Err(LexingError::IncompleteWidth)
 }
@@ -0,0 +1,300 @@
+//! Continuous assignment parsing tests
+
+use std::collections::HashMap;
+use sv_sim::eval::eval_expr;
+use sv_sim::expr::{BinOp, Expr};
+use sv_sim::logic::{assign_sv_string, AssignStmt, Delay};
+use sv_sim::parse_sv_file;
+use sv_sim::value::DriveStrength;
+
+/// Parses a module containing `body` and returns its continuous assignments
+fn parse_assigns(body: &str) -> Vec<AssignStmt> {
+    let source = format!(
+        "module m (input wire a, input wire b, input wire c, output wire out);\n{body}\nendmodule\n"
+    );
+    let object = parse_sv_file(source).expect("module parses");
+
+    object.mods["m"].assigns.clone()
+}
+
+#[test]
+fn assign_round_trips() {
+    for source in [
+        "assign out = a & b;",
+        "assign out = a & (b | c);",
+        "assign out = ~(a ^ b) + 4'b0011 * c;",
+        "assign out = -4'sd3 + 7 / a;",
+        "assign out = a - (b - c);",
+        "assign out = a ** b ** c;",
+        "assign out = a ** (b ** c);",
+        "assign out = a[0] & b[3:1];",
+        "assign out = a[c * 2 +: 2] | b[c -: 2];",
+        "assign out = a ? b : c;",
+        "assign out = a ? b ? c : a : (c ? b : a) ? a : b;",
+        "assign out = (a ? b : c) & ~(a ? c : b);",
+        "assign out = a && b || c != a << 2 >>> b;",
+        "assign out = a % b * c === (a !== b);",
+        "assign out = a == '1 ? 'z : 'x ^ '0;",
+        "assign {out, c[2]} = {a, 2'b01, {4{b}}, {2{a, c}}};",
+        "assign out = $signed(a) >>> $unsigned(b + c) < $clog2(c) - $time;",
+        "assign out = parity(a, b[3:0] + 1) ^ flag();",
+        "assign out = '{a, b ? c : a, 2'b01};",
+        "assign out = '{x: a, y: b & c};",
+        "assign #5 out = a;",
+        "assign #(c * 2) out = a;",
+        "assign #2.5 out = a;",
+        "assign #1.5ns out = a;",
+        "assign #250ps out = a;",
+        "assign (weak0, pull1) #5 out = a;",
+        "assign out = pkg::WIDTH + pkg::Class::f(a) - Class::g();",
+        "assign out = h.field[3:0] ^ h.next.f(a, b).data;",
+    ] {
+        let assigns = parse_assigns(source);
+        assert_eq!(assigns.len(), 1);
+
+        let formatted = assign_sv_string(&assigns[0]);
+        assert_eq!(
+            parse_assigns(&formatted),
+            assigns,
+            "{source} -> {formatted}"
+        );
+    }
+
+    assert_eq!(
+        assign_sv_string(&parse_assigns("assign out = a & b;")[0]),
+        "assign out = a & b;"
+    );
+}
+
+#[test]
+fn power_groups_left_to_right() {
+    let assigns = parse_assigns("assign out = a ** b ** c;");
+    let Expr::BinOp(BinOp::Pow, lhs, rhs) = &assigns[0].rhs else {
+        panic!("expected power, got {:?}", assigns[0].rhs);
+    };
+    assert!(matches!(**lhs, Expr::BinOp(BinOp::Pow, ..)));
+    assert_eq!(**rhs, Expr::Ident("c".to_owned()));
+
+    let lookup: HashMap<String, _> = HashMap::new();
+    let value = |body: &str| eval_expr(&parse_assigns(body)[0].rhs, &lookup).to_u128();
+    assert_eq!(value("assign out = 2 ** 3 ** 2;"), Some(64));
+    assert_eq!(value("assign out = 2 ** (3 ** 2);"), Some(512));
+
+    assert_eq!(
+        assign_sv_string(&parse_assigns("assign out = a ** (b ** c);")[0]),
+        "assign out = a ** (b ** c);"
+    );
+}
+
+#[test]
+fn assign_list() {
+    let assigns = parse_assigns("assign out = a, out = b;");
+
+    assert_eq!(assigns.len(), 2);
+}
+
+#[test]
+fn delayed_assignments() {
+    let assigns = parse_assigns("assign #3ns out = a, c = b;");
+    assert_eq!(assigns.len(), 2);
+    for assign in &assigns {
+        assert!(
+            matches!(assign.delay, Some(Delay::Time(time)) if (time - 3e-9).abs() < 1e-15),
+            "{:?}",
+            assign.delay
+        );
+    }
+    assert_eq!(assign_sv_string(&assigns[1]), "assign #3ns c = b;");
+
+    assert_eq!(parse_assigns("assign out = a;")[0].delay, None);
+    assert_eq!(
+        assign_sv_string(&parse_assigns("assign #(c * b) out = a;")[0]),
+        "assign #(c * b) out = a;"
+    );
+}
+
+#[test]
+fn drive_strengths() {
+    let assigns = parse_assigns("assign (pull1, supply0) out = a, c = b;");
+    assert_eq!(assigns.len(), 2);
+    for assign in &assigns {
+        assert_eq!(
+            assign.strength,
+            (DriveStrength::Supply, DriveStrength::Pull)
+        );
+    }
+    assert_eq!(
+        assign_sv_string(&assigns[0]),
+        "assign (supply0, pull1) out = a;"
+    );
+    assert_eq!(
+        parse_assigns("assign out = a;")[0].strength,
+        (DriveStrength::Strong, DriveStrength::Strong)
+    );
+
+    for strength in [
+        "(weak0)",
+        "(weak0, weak0)",
+        "(highz0, highz1)",
+        "(weak2, weak1)",
+    ] {
+        let source = format!(
+            "module m (input wire a, output wire out);\nassign {strength} out = a;\nendmodule\n"
+        );
+        assert!(parse_sv_file(source).is_err(), "{strength}");
+    }
+}
+
+#[test]
+fn concat_and_replicate() {
+    let assigns = parse_assigns("assign out = {a, {4{b}}, {2{a, c}}};");
+
+    let Expr::Concat(values) = &assigns[0].rhs else {
+        panic!("expected concatenation, got {:?}", assigns[0].rhs);
+    };
+    assert_eq!(values.len(), 3);
+    assert_eq!(values[0], Expr::Ident("a".to_owned()));
+    assert!(matches!(
+        &values[1],
+        Expr::Replicate { value, .. } if **value == Expr::Ident("b".to_owned())
+    ));
+    assert!(matches!(
+        &values[2],
+        Expr::Replicate { value, .. } if matches!(**value, Expr::Concat(ref inner) if inner.len() == 2)
+    ));
+}
+
+#[test]
+fn concatenated_and_selected_targets() {
+    let assigns = parse_assigns("assign {carry, sum} = a + b + cin, bus[7:4] = upper_nibble;");
+    let Expr::Concat(targets) = &assigns[0].lhs else {
+        panic!("expected concatenation, got {:?}", assigns[0].lhs);
+    };
+    assert_eq!(
+        *targets,
+        [Expr::Ident("carry".to_owned()), Expr::Ident("sum".to_owned())]
+    );
+    assert!(matches!(assigns[1].lhs, Expr::PartSelect(..)));
+
+    let assigns = parse_assigns("assign {flags[2], {bus[c +: 2], out}} = a;");
+    assert!(matches!(
+        &assigns[0].lhs,
+        Expr::Concat(targets) if matches!(&targets[1], Expr::Concat(inner) if inner.len() == 2)
+    ));
+
+    // Only signals and selects of them can be assigned to
+    for source in [
+        "assign {a + b, c} = out;",
+        "assign {2{a}} = out;",
+        "assign {a, 1'b0} = out;",
+    ] {
+        let source = format!("module m (input wire out);\n{source}\nendmodule\n");
+        assert!(parse_sv_file(source.clone()).is_err(), "{source}");
+    }
+}
+
+#[test]
+fn array_and_struct_literals() {
+    let assigns = parse_assigns("assign out = '{a, b ? c : a};");
+    let Expr::ArrayLiteral(values) = &assigns[0].rhs else {
+        panic!("expected array literal, got {:?}", assigns[0].rhs);
+    };
+    assert_eq!(values.len(), 2);
+    assert!(matches!(values[1], Expr::Ternary { .. }));
+
+    let assigns = parse_assigns("assign out = '{lo: a, \\hi  : {b, c}};");
+    let Expr::StructLiteral(fields) = &assigns[0].rhs else {
+        panic!("expected structure literal, got {:?}", assigns[0].rhs);
+    };
+    let names: Vec<&str> = fields.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["lo", "hi"]);
+    assert!(matches!(fields[1].1, Expr::Concat(_)));
+
+    let assigns = parse_assigns("assign out = '{default: a & b};");
+    let Expr::DefaultLiteral(value) = &assigns[0].rhs else {
+        panic!("expected default literal, got {:?}", assigns[0].rhs);
+    };
+    assert!(matches!(**value, Expr::BinOp(..)));
+
+    // Named and positional values cannot be mixed, nor given with a default
+    for source in [
+        "assign out = '{x: a, b};",
+        "assign out = '{};",
+        "assign out = '{default: a, b};",
+    ] {
+        let source = format!("module m (output wire out);\n{source}\nendmodule\n");
+        assert!(parse_sv_file(source).is_err());
+    }
+}
+
+#[test]
+fn indexed_part_selects() {
+    let assigns = parse_assigns("assign out = a[c +: 2] ^ b[3 -: 2];");
+
+    let Expr::BinOp(_, lhs, rhs) = &assigns[0].rhs else {
+        panic!("expected binary operation, got {:?}", assigns[0].rhs);
+    };
+    assert!(matches!(**lhs, Expr::PartSelectUp(..)));
+    assert!(matches!(**rhs, Expr::PartSelectDown(..)));
+}
+
+#[test]
+fn ternary_binds_least_tightly() {
+    let assigns = parse_assigns("assign out = a | b ? c : a & b;");
+
+    let Expr::Ternary { cond, else_, .. } = &assigns[0].rhs else {
+        panic!("expected ternary, got {:?}", assigns[0].rhs);
+    };
+    assert!(matches!(**cond, Expr::BinOp(..)));
+    assert!(matches!(**else_, Expr::BinOp(..)));
+    assert_eq!(
+        assign_sv_string(&assigns[0]),
+        "assign out = a | b ? c : a & b;"
+    );
+}
+
+#[test]
+fn scoped_names_and_members() {
+    let assigns = parse_assigns("assign out = pkg::C;");
+    assert_eq!(
+        assigns[0].rhs,
+        Expr::Scoped("pkg".to_owned(), Box::new(Expr::Ident("C".to_owned())))
+    );
+
+    let assigns = parse_assigns("assign out = pkg::Class::f(a)[0];");
+    let Expr::BitSelect(base, _) = &assigns[0].rhs else {
+        panic!("expected bit select, got {:?}", assigns[0].rhs);
+    };
+    let Expr::Scoped(scope, item) = &**base else {
+        panic!("expected scoped name, got {base:?}");
+    };
+    assert_eq!(scope, "pkg");
+    assert!(matches!(&**item, Expr::Scoped(class, call)
+        if class == "Class" && matches!(**call, Expr::FuncCall(..))));
+
+    let assigns = parse_assigns("assign out = h.field[1] & h.f(a);");
+    let Expr::BinOp(_, lhs, rhs) = &assigns[0].rhs else {
+        panic!("expected binary operation, got {:?}", assigns[0].rhs);
+    };
+    let Expr::BitSelect(member, _) = &**lhs else {
+        panic!("expected bit select, got {lhs:?}");
+    };
+    assert_eq!(
+        **member,
+        Expr::Member(Box::new(Expr::Ident("h".to_owned())), "field".to_owned())
+    );
+    let Expr::MethodCall(handle, method, args) = &**rhs else {
+        panic!("expected method call, got {rhs:?}");
+    };
+    assert_eq!(**handle, Expr::Ident("h".to_owned()));
+    assert_eq!((method.as_str(), args.len()), ("f", 1));
+
+    for source in [
+        "assign out = pkg::;",
+        "assign out = h.;",
+        "assign out = pkg::3;",
+    ] {
+        let source = format!("module m (output wire out);\n{source}\nendmodule\n");
+        assert!(parse_sv_file(source.clone()).is_err(), "{source}");
+    }
+}
@@ -0,0 +1,385 @@
+//! Combinational evaluation tests
+
+use std::collections::HashMap;
+use sv_sim::module::Module;
+use sv_sim::parse_sv_file;
+use sv_sim::value::{LogicState, LogicValue};
+
+/// Parses a source holding a single module
+fn parse_module(source: &str) -> Module {
+    let object = parse_sv_file(source.to_owned()).expect("source parses");
+
+    object.mods.into_values().next().expect("source holds a module")
+}
+
+/// Input values from `(name, value, width)` triples
+fn inputs(values: &[(&str, u128, u64)]) -> HashMap<String, LogicValue> {
+    values
+        .iter()
+        .map(|(name, value, width)| (name.to_string(), LogicValue::from_u128(*value, *width)))
+        .collect()
+}
+
+/// Module whose logic is written out of dependency order
+const MUX: &str = "module mux (input wire [1:0] sel, input wire [3:0] a, input wire [3:0] b, output reg [3:0] out, output wire [3:0] masked);
+assign masked = out & mask;
+always_comb begin
+    case (sel)
+        2'd0: out = a;
+        2'd1: out = b;
+        default: out = a ^ b;
+    endcase
+end
+wire [3:0] mask;
+assign mask = {sel, sel};
+endmodule
+";
+
+#[test]
+fn evaluates_in_dependency_order() {
+    let module = parse_module(MUX);
+
+    let outputs = module.evaluate_comb(&inputs(&[("sel", 1, 2), ("a", 3, 4), ("b", 6, 4)]));
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(outputs["out"], LogicValue::from_u128(6, 4));
+    assert_eq!(outputs["masked"], LogicValue::from_u128(4, 4));
+
+    let outputs = module.evaluate_comb(&inputs(&[("sel", 3, 2), ("a", 3, 4), ("b", 6, 4)]));
+    assert_eq!(outputs["out"], LogicValue::from_u128(5, 4));
+    assert_eq!(outputs["masked"], LogicValue::from_u128(5, 4));
+}
+
+#[test]
+fn missing_inputs_are_unknown() {
+    let module = parse_module(MUX);
+
+    let outputs = module.evaluate_comb(&inputs(&[("sel", 0, 2), ("b", 6, 4)]));
+    assert_eq!(outputs["out"], LogicValue::new(4));
+    assert_eq!(outputs["masked"], LogicValue::from_u128(0, 4));
+}
+
+#[test]
+fn parameters_and_generate_blocks_take_default_values() {
+    let module = parse_module(
+        "module invert #(parameter WIDTH = 4) (input wire [WIDTH-1:0] a, output wire [WIDTH-1:0] y, output wire [WIDTH-1:0] sum);
+localparam STEP = 3;
+genvar i;
+generate
+    for (i = 0; i < WIDTH; i = i + 1) begin : bits
+        assign y[i] = ~a[i];
+    end
+endgenerate
+assign sum = a + STEP;
+endmodule
+",
+    );
+
+    let outputs = module.evaluate_comb(&inputs(&[("a", 0b0110, 4)]));
+    assert_eq!(outputs["y"], LogicValue::from_u128(0b1001, 4));
+    assert_eq!(outputs["sum"], LogicValue::from_u128(9, 4));
+}
+
+#[test]
+fn stable_loops_settle() {
+    let module = parse_module(
+        "module latch (input wire set, input wire clear, output wire q, output wire q_n);
+assign q = ~(clear | q_n);
+assign q_n = ~(set | q);
+endmodule
+",
+    );
+
+    let outputs = module.evaluate_comb(&inputs(&[("set", 1, 1), ("clear", 0, 1)]));
+    assert_eq!(outputs["q"], LogicValue::from_u128(1, 1));
+    assert_eq!(outputs["q_n"], LogicValue::from_u128(0, 1));
+}
+
+#[test]
+fn remainders_and_case_equality() {
+    let module = parse_module(
+        "module ops (input wire [3:0] a, input wire [3:0] b, input wire [3:0] s, input wire [3:0] d, output wire [3:0] r, output wire [3:0] sr, output wire same, output wire differ, output wire eq, output wire [3:0] sr3);
+assign r = a % b;
+assign sr3 = $signed(s) % 4'sd3;
+assign sr = $signed(s) % $signed(d);
+assign same = a === b;
+assign differ = a !== b;
+assign eq = a == b;
+endmodule
+",
+    );
+
+    let outputs = module.evaluate_comb(&inputs(&[
+        ("a", 11, 4),
+        ("b", 4, 4),
+        ("s", 0b1011, 4),
+        ("d", 3, 4),
+    ]));
+    assert_eq!(outputs["r"], LogicValue::from_u128(3, 4));
+    // -5 % 3 takes the sign of the dividend
+    assert_eq!(outputs["sr"], LogicValue::from_u128(0b1110, 4));
+    assert_eq!(outputs["sr3"], LogicValue::from_u128(0b1110, 4));
+    assert_eq!(outputs["same"], LogicValue::from_u128(0, 1));
+    assert_eq!(outputs["differ"], LogicValue::from_u128(1, 1));
+
+    // Unknown bits compare equal to each other only under case equality
+    let outputs = module.evaluate_comb(&inputs(&[("b", 0, 4)]));
+    assert_eq!(outputs["r"], LogicValue::new(4));
+    assert_eq!(outputs["same"], LogicValue::from_u128(0, 1));
+    assert_eq!(outputs["eq"], LogicValue::new(1));
+
+    let outputs = module.evaluate_comb(&HashMap::new());
+    assert_eq!(outputs["same"], LogicValue::from_u128(1, 1));
+    assert_eq!(outputs["differ"], LogicValue::from_u128(0, 1));
+    assert_eq!(outputs["eq"], LogicValue::new(1));
+}
+
+#[test]
+fn bitwise_and_reduction_operators() {
+    let module = parse_module(
+        "module gates (input wire [3:0] a, input wire [3:0] b, output wire [3:0] nand_, output wire [3:0] xnor_, output wire [5:0] reduced);
+assign nand_ = ~(a & b);
+assign xnor_ = a ~^ b;
+assign reduced = {&a, ~&a, |a, ~|a, ^a, ^~a};
+endmodule
+",
+    );
+
+    let outputs = module.evaluate_comb(&inputs(&[("a", 0b1101, 4), ("b", 0b0110, 4)]));
+    assert_eq!(outputs["nand_"], LogicValue::from_u128(0b1011, 4));
+    assert_eq!(outputs["xnor_"], LogicValue::from_u128(0b0100, 4));
+    assert_eq!(outputs["reduced"], LogicValue::from_u128(0b011010, 6));
+
+    // A known 0 decides an AND reduction and a known 1 an OR reduction,
+    // while any unknown bit makes an XOR reduction unknown
+    let mut a = LogicValue::from_u128(0b0100, 4);
+    a.set_bit(0, LogicState::X);
+    let outputs = module.evaluate_comb(&HashMap::from([("a".to_owned(), a)]));
+    assert_eq!(
+        outputs["reduced"].bits(),
+        [
+            LogicState::Zero,
+            LogicState::One,
+            LogicState::One,
+            LogicState::Zero,
+            LogicState::X,
+            LogicState::X,
+        ]
+    );
+}
+
+#[test]
+fn logical_and_shift_operators() {
+    let module = parse_module(
+        "module ops (input wire [3:0] a, input wire [3:0] b, input wire c, output wire [3:0] shifted, output wire [3:0] arith, output wire [3:0] wide, output wire [3:0] logic_);
+assign shifted = a + b << 1;
+assign arith = {$signed(a) >>> 1} | a <<< 4;
+assign wide = a >> b;
+assign logic_ = {a << 1 < b, c && a, c || !a, !c};
+endmodule
+",
+    );
+
+    let outputs = module.evaluate_comb(&inputs(&[("a", 0b1001, 4), ("b", 2, 4), ("c", 0, 1)]));
+    // Shifts bind looser than `+` and tighter than comparisons
+    assert_eq!(outputs["shifted"], LogicValue::from_u128(0b0110, 4));
+    assert_eq!(outputs["arith"], LogicValue::from_u128(0b1100, 4));
+    assert_eq!(outputs["wide"], LogicValue::from_u128(0b0010, 4));
+    assert_eq!(outputs["logic_"], LogicValue::from_u128(0b0001, 4));
+
+    let outputs = module.evaluate_comb(&inputs(&[("a", 0b0101, 4), ("b", 7, 4), ("c", 1, 1)]));
+    assert_eq!(outputs["wide"], LogicValue::from_u128(0, 4));
+    assert_eq!(outputs["logic_"], LogicValue::from_u128(0b0110, 4));
+
+    // An unknown shift amount leaves no bit known, and an unknown operand
+    // makes a comparison unknown
+    let outputs = module.evaluate_comb(&inputs(&[("a", 0, 4), ("c", 1, 1)]));
+    assert_eq!(outputs["wide"], LogicValue::new(4));
+    assert_eq!(
+        outputs["logic_"].bits(),
+        [
+            LogicState::X,
+            LogicState::Zero,
+            LogicState::One,
+            LogicState::Zero,
+        ]
+    );
+}
+
+#[test]
+fn unbased_literals_fill_their_context() {
+    let module = parse_module(
+        "module fill (input wire [7:0] a, output wire [7:0] ones, output wire [7:0] floating, output wire all_set, output wire [3:0] low);
+assign ones = '1;
+assign floating = a == '0 ? 'z : 'x;
+assign all_set = a == '1;
+assign low = a[3:0] & '1;
+endmodule
+",
+    );
+
+    let outputs = module.evaluate_comb(&inputs(&[("a", 0xFF, 8)]));
+    assert_eq!(outputs["ones"], LogicValue::from_u128(0xFF, 8));
+    assert_eq!(outputs["floating"], LogicValue::filled(8, LogicState::X));
+    assert_eq!(outputs["all_set"], LogicValue::from_u128(1, 1));
+    assert_eq!(outputs["low"], LogicValue::from_u128(0xF, 4));
+
+    let outputs = module.evaluate_comb(&inputs(&[("a", 0, 8)]));
+    assert_eq!(outputs["floating"], LogicValue::filled(8, LogicState::Z));
+    assert_eq!(outputs["all_set"], LogicValue::from_u128(0, 1));
+}
+
+#[test]
+fn concatenation_and_replication() {
+    let module = parse_module(
+        "module extend #(parameter N = 3) (input wire [3:0] a, input wire b, output wire [7:0] signed_a, output wire [7:0] zeroed, output wire [5:0] repeated, output wire [4:0] empty);
+assign signed_a = {{4{a[3]}}, a};
+assign zeroed = {{N+1{1'b0}}, a};
+assign repeated = {2{b, a[0], 1'b1}};
+assign empty = {{0{b}}, b, a};
+endmodule
+",
+    );
+
+    let outputs = module.evaluate_comb(&inputs(&[("a", 0b1010, 4), ("b", 1, 1)]));
+    assert_eq!(outputs["signed_a"], LogicValue::from_u128(0b1111_1010, 8));
+    assert_eq!(outputs["zeroed"], LogicValue::from_u128(0b1010, 8));
+    assert_eq!(outputs["repeated"], LogicValue::from_u128(0b101_101, 6));
+    assert_eq!(outputs["empty"], LogicValue::from_u128(0b1_1010, 5));
+
+    let outputs = module.evaluate_comb(&inputs(&[("a", 0b0101, 4), ("b", 0, 1)]));
+    assert_eq!(outputs["signed_a"], LogicValue::from_u128(0b0101, 8));
+    assert_eq!(outputs["repeated"], LogicValue::from_u128(0b011_011, 6));
+}
+
+#[test]
+fn bit_and_part_selects() {
+    let module = parse_module(
+        "module select (input wire [7:0] d, input wire [2:0] i, output wire bit_i, output wire [3:0] upper, output wire [2:0] up, output wire [2:0] down, output wire [1:0] past_end);
+assign bit_i = d[i];
+assign upper = d[7:4];
+assign up = d[i +: 3];
+assign down = d[i * 2 -: 3];
+assign past_end = d[8:7];
+endmodule
+",
+    );
+
+    let outputs = module.evaluate_comb(&inputs(&[("d", 0b1100_1010, 8), ("i", 2, 3)]));
+    assert_eq!(outputs["bit_i"], LogicValue::from_u128(0, 1));
+    assert_eq!(outputs["upper"], LogicValue::from_u128(0b1100, 4));
+    assert_eq!(outputs["up"], LogicValue::from_u128(0b010, 3));
+    assert_eq!(outputs["down"], LogicValue::from_u128(0b010, 3));
+    assert_eq!(
+        outputs["past_end"],
+        LogicValue::from_bits(vec![LogicState::X, LogicState::One])
+    );
+
+    // An unknown index selects nothing
+    let outputs = module.evaluate_comb(&inputs(&[("d", 0b1100_1010, 8)]));
+    assert_eq!(outputs["bit_i"], LogicValue::new(1));
+    assert_eq!(outputs["up"], LogicValue::new(3));
+    assert_eq!(outputs["upper"], LogicValue::from_u128(0b1100, 4));
+}
+
+#[test]
+fn ternaries_merge_unknown_conditions() {
+    let module = parse_module(
+        "module mux #(parameter W = 1 ? 4 : 2) (input wire [3:0] a, input wire s, output wire [3:0] y, output wire b, output wire [3:0] z, output reg [3:0] r);
+assign y = s ? a : ~a;
+assign b = a[s ? 3 : 0];
+assign z = s ? 4'b1100 : 4'b1010;
+always_comb r = s ? (a > 2 ? 4'd1 : 4'd2) : W;
+endmodule
+",
+    );
+
+    let outputs = module.evaluate_comb(&inputs(&[("a", 0b0101, 4), ("s", 1, 1)]));
+    assert_eq!(outputs["y"], LogicValue::from_u128(0b0101, 4));
+    assert_eq!(outputs["b"], LogicValue::from_u128(0, 1));
+    assert_eq!(outputs["z"], LogicValue::from_u128(0b1100, 4));
+    assert_eq!(outputs["r"], LogicValue::from_u128(1, 4));
+
+    let outputs = module.evaluate_comb(&inputs(&[("a", 0b0101, 4), ("s", 0, 1)]));
+    assert_eq!(outputs["y"], LogicValue::from_u128(0b1010, 4));
+    assert_eq!(outputs["b"], LogicValue::from_u128(1, 1));
+    assert_eq!(outputs["r"], LogicValue::from_u128(4, 4));
+
+    // Bits both sides agree on survive an unknown condition
+    let outputs = module.evaluate_comb(&inputs(&[("a", 0b0101, 4)]));
+    let x = LogicState::X;
+    let (zero, one) = (LogicState::Zero, LogicState::One);
+    assert_eq!(outputs["y"], LogicValue::new(4));
+    assert_eq!(outputs["b"], LogicValue::new(1));
+    assert_eq!(outputs["z"], LogicValue::from_bits(vec![one, x, x, zero]));
+    assert_eq!(outputs["r"], LogicValue::from_bits(vec![zero, x, zero, x]));
+}
+
+#[test]
+fn case_kinds_treat_unknown_bits_differently() {
+    let module = parse_module(
+        "module decode (input wire [3:0] op, output reg [1:0] exact, output reg [1:0] z_care, output reg [1:0] x_care);
+always_comb begin
+    case (op)
+        4'b1x0z: exact = 2'd1;
+        4'b1???: exact = 2'd2;
+        default: exact = 2'd0;
+    endcase
+    unique casez (op)
+        4'b1x0z: z_care = 2'd1;
+        4'b1???: z_care = 2'd2;
+        default: z_care = 2'd0;
+    endcase
+    priority casex (op)
+        4'b1x0z: x_care = 2'd1;
+        4'b1???: x_care = 2'd2;
+        default: x_care = 2'd0;
+    endcase
+end
+endmodule
+",
+    );
+
+    let (x, z) = (LogicState::X, LogicState::Z);
+    let (zero, one) = (LogicState::Zero, LogicState::One);
+    let decode = |op: Vec<LogicState>| {
+        let outputs = module.evaluate_comb(&HashMap::from([(
+            "op".to_owned(),
+            LogicValue::from_bits(op),
+        )]));
+        [&outputs["exact"], &outputs["z_care"], &outputs["x_care"]]
+            .map(|value| value.to_u128().expect("known decode"))
+    };
+
+    assert_eq!(decode(vec![one, x, zero, z]), [1, 1, 1]);
+    assert_eq!(decode(vec![one, one, zero, one]), [0, 2, 1]);
+    assert_eq!(decode(vec![one, zero, one, one]), [0, 2, 2]);
+    assert_eq!(decode(vec![x, one, zero, one]), [0, 0, 1]);
+}
+
+#[test]
+fn signed_declarations_extend_and_compare_as_signed() {
+    let module = parse_module(
+        "module signs (input wire signed [3:0] s, input wire [3:0] u, output wire [7:0] wide_s, output wire [7:0] wide_u, output wire neg, output wire [3:0] half, output wire mixed, output wire [7:0] based);
+wire signed [3:0] quotient;
+assign wide_s = s;
+assign wide_u = u;
+assign neg = s < 0;
+assign quotient = s / 4'sd2;
+assign half = quotient >>> 1;
+assign mixed = s < u;
+assign based = s + 4'hF;
+endmodule
+",
+    );
+
+    let outputs = module.evaluate_comb(&inputs(&[("s", 0b1010, 4), ("u", 0b1010, 4)]));
+    assert_eq!(outputs["wide_s"], LogicValue::from_u128(0b1111_1010, 8));
+    assert_eq!(outputs["wide_u"], LogicValue::from_u128(0b0000_1010, 8));
+    assert_eq!(outputs["neg"], LogicValue::from_u128(1, 1));
+    // -6 / 2 is -3, which shifts to -2
+    assert_eq!(outputs["half"], LogicValue::from_u128(0b1110, 4));
+    // An unsigned operand makes the comparison unsigned
+    assert_eq!(outputs["mixed"], LogicValue::from_u128(0, 1));
+    // So does a based literal, which zero-extends s
+    assert_eq!(outputs["based"], LogicValue::from_u128(0x19, 8));
+}
@@ -0,0 +1,66 @@
+//! Comment parsing tests
+
+use sv_sim::{parse_sv_file, LexingError, SimError};
+
+#[test]
+fn line_comment_may_contain_any_text() {
+    let source = "// $Id: $ ' \" \\\nmodule m (input wire a);\nendmodule\n";
+
+    let object = parse_sv_file(source.to_owned()).expect("file parses");
+    assert_eq!(object.mods["m"].name, "m");
+}
+
+#[test]
+fn block_comment_between_ports() {
+    let source =
+        "module m (\n    input wire a, /* first\n    port */\n    input wire b\n);\nendmodule\n";
+
+    let object = parse_sv_file(source.to_owned()).expect("file parses");
+    assert_eq!(object.mods["m"].io.inputs.len(), 2);
+}
+
+#[test]
+fn comments_after_declared_names() {
+    let source = "module m (input wire a // the a
+, input wire /* t */ [3:0] /* w */ b, output wire y /* last */);
+reg [3:0] r1, /* second */ r2 /* third */, r3; // trailing
+wire w /* w */ = a;
+assign y = w;
+endmodule
+";
+
+    let object = parse_sv_file(source.to_owned()).expect("file parses");
+    let module = &object.mods["m"];
+    let inputs: Vec<&str> = module
+        .io
+        .inputs
+        .iter()
+        .map(|input| input.var.name.as_str())
+        .collect();
+    assert_eq!(inputs, ["a", "b"]);
+    assert_eq!(module.io.inputs[1].var.width, 4);
+    assert_eq!(module.io.outputs[0].var.name, "y");
+
+    let vars: Vec<&str> = module.vars.iter().map(|var| var.name.as_str()).collect();
+    assert_eq!(vars, ["r1", "r2", "r3", "w"]);
+}
+
+#[test]
+fn comment_ends_are_only_stray_on_the_same_line() {
+    let source = "/* old /* header */
+module m (output reg a);
+initial a = 1; // see a*/b
+endmodule
+";
+    let object = parse_sv_file(source.to_owned()).expect("file parses");
+    assert_eq!(object.mods["m"].name, "m");
+
+    let source = "/* outer /* inner */ still outer */
+module m (output reg a);
+endmodule
+";
+    let Err(SimError::Lex(err)) = parse_sv_file(source.to_owned()) else {
+        panic!("comment written as if nested parses");
+    };
+    assert_eq!(err.kind(), &LexingError::ImproperCommentFormatting);
+}
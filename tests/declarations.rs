@@ -0,0 +1,484 @@
+//! Port and variable declaration parsing tests
+
+use std::collections::HashMap;
+use sv_sim::eval::eval_expr;
+use sv_sim::expr::Expr;
+use sv_sim::module::Module;
+use sv_sim::param::Param;
+use sv_sim::parse_sv_file;
+use sv_sim::value::{LogicState, LogicValue};
+use sv_sim::var_types::{var_sv_string, VarType};
+
+/// Parses a source holding a single module
+fn parse_module(source: &str) -> Module {
+    let object = parse_sv_file(source.to_owned()).expect("source parses");
+
+    object
+        .mods
+        .into_values()
+        .next()
+        .expect("source holds a module")
+}
+
+#[test]
+fn names_with_digits() {
+    let module = parse_module(
+        "module fifo_2x8 (input wire clk_32, output wire [7:0] data_out_8b);
+wire tmp_1s;
+reg [3:0] cnt_08;
+endmodule
+",
+    );
+
+    assert_eq!(module.name, "fifo_2x8");
+    assert_eq!(module.io.inputs[0].name, "clk_32");
+    assert_eq!(module.io.outputs[0].name, "data_out_8b");
+
+    let vars: Vec<&str> = module.vars.iter().map(|var| var.name.as_str()).collect();
+    assert_eq!(vars, ["tmp_1s", "cnt_08"]);
+}
+
+#[test]
+fn names_starting_with_underscores() {
+    let module = parse_module(
+        "module _fifo (input wire _clk, output wire [7:0] _8b);
+wire _1ns;
+reg [3:0] _cnt$0;
+_stage _u0 (._d(_8b));
+endmodule
+",
+    );
+
+    assert_eq!(module.name, "_fifo");
+    assert_eq!(module.io.inputs[0].name, "_clk");
+    assert_eq!(module.io.outputs[0].name, "_8b");
+
+    let vars: Vec<&str> = module.vars.iter().map(|var| var.name.as_str()).collect();
+    assert_eq!(vars, ["_1ns", "_cnt$0"]);
+
+    assert_eq!(module.instances[0].module_name, "_stage");
+    assert_eq!(module.instances[0].inst_name, "_u0");
+}
+
+#[test]
+fn single_index_widths() {
+    let module = parse_module(
+        "module m (input wire [0] a, input wire [3] b, output wire [0:0] c, output wire [3:0] d);
+reg [5] e;
+endmodule
+",
+    );
+
+    let widths: Vec<u64> = module
+        .io
+        .inputs
+        .iter()
+        .map(|input| input.var.width)
+        .chain(module.io.outputs.iter().map(|output| output.var.width))
+        .chain(module.vars.iter().map(|var| var.width))
+        .collect();
+    assert_eq!(widths, [1, 1, 1, 4, 1]);
+}
+
+#[test]
+fn empty_width_is_rejected() {
+    assert!(parse_sv_file("module m (input wire [] a);\nendmodule\n".to_owned()).is_err());
+}
+
+#[test]
+fn logic_declarations() {
+    let module = parse_module(
+        "module m (input logic clk, output logic [7:0] count);
+logic [3:0] state;
+logic done;
+always_ff @(posedge clk) count <= count + 1;
+endmodule
+",
+    );
+
+    assert!(matches!(module.io.inputs[0].var.var_type, VarType::Logic));
+    assert!(matches!(module.io.outputs[0].var.var_type, VarType::Logic));
+    assert_eq!(module.io.outputs[0].var.width, 8);
+
+    let vars: Vec<(&str, u64)> = module
+        .vars
+        .iter()
+        .map(|var| {
+            assert!(matches!(var.var_type, VarType::Logic));
+            (var.name.as_str(), var.width)
+        })
+        .collect();
+    assert_eq!(vars, [("state", 4), ("done", 1)]);
+}
+
+#[test]
+fn var_declarations() {
+    let module = parse_module(
+        "module m (input var logic en, output var [7:0] count);
+var logic [3:0] state, next;
+var int total;
+var signed [3:0] delta;
+endmodule
+",
+    );
+
+    assert_eq!(module.io.inputs[0].name, "en");
+    assert!(matches!(module.io.inputs[0].var.var_type, VarType::Logic));
+    assert_eq!(module.io.outputs[0].name, "count");
+    assert!(matches!(module.io.outputs[0].var.var_type, VarType::Logic));
+    assert_eq!(module.io.outputs[0].var.width, 8);
+
+    let vars: Vec<(&str, &str, u64)> = module
+        .vars
+        .iter()
+        .map(|var| (var.name.as_str(), (&var.var_type).into(), var.width))
+        .collect();
+    assert_eq!(
+        vars,
+        [
+            ("state", "logic", 4),
+            ("next", "logic", 4),
+            ("total", "int", 32),
+            ("delta", "logic", 4),
+        ]
+    );
+    assert!(module.vars[3].signed);
+}
+
+#[test]
+fn variable_bits_and_ranges() {
+    let mut module = parse_module(
+        "module m ();
+wire [3:0] data;
+reg [7:0] word;
+endmodule
+",
+    );
+
+    let data = &mut module.vars[0];
+    assert_eq!(data.get_range(3, 0), LogicValue::new(4));
+    data.set_range(3, 0, &LogicValue::from_u128(0b1010, 4));
+    assert_eq!(data.get_range(3, 0), LogicValue::from_u128(0b1010, 4));
+    assert_eq!(data.get_bit(1), LogicState::One);
+    assert_eq!(data.get_bit(4), LogicState::X);
+
+    let word = &mut module.vars[1];
+    word.set_range(7, 0, &LogicValue::from_u128(0, 8));
+    word.set_range(5, 2, &LogicValue::from_u128(0xf, 4));
+    word.set_bit(7, LogicState::Z);
+    word.set_bit(8, LogicState::One);
+    assert_eq!(word.state.width(), 8);
+    assert_eq!(word.get_range(6, 0), LogicValue::from_u128(0b0111100, 7));
+    assert_eq!(word.get_bit(7), LogicState::Z);
+}
+
+#[test]
+fn signed_qualifiers() {
+    let module = parse_module(
+        "module m (input signed [7:0] data, input wire unsigned [3:0] sel, output reg signed [15:0] acc);
+reg signed [15:0] sum;
+logic unsigned [7:0] count;
+wire flag;
+endmodule
+",
+    );
+
+    assert!(module.io.inputs[0].var.signed);
+    assert_eq!(module.io.inputs[0].var.width, 8);
+    assert!(!module.io.inputs[1].var.signed);
+    assert!(module.io.outputs[0].var.signed);
+
+    let vars: Vec<(&str, bool, u64)> = module
+        .vars
+        .iter()
+        .map(|var| (var.name.as_str(), var.signed, var.width))
+        .collect();
+    assert_eq!(
+        vars,
+        [("sum", true, 16), ("count", false, 8), ("flag", false, 1)]
+    );
+}
+
+#[test]
+fn integer_type_widths() {
+    let module = parse_module(
+        "module m (input int count, output byte unsigned data);
+shortint half;
+longint wide;
+integer total;
+int [7:0] narrow;
+parameter int DEPTH = 4;
+endmodule
+",
+    );
+
+    assert!(matches!(module.io.inputs[0].var.var_type, VarType::Int));
+    assert_eq!(module.io.inputs[0].var.width, 32);
+    assert!(module.io.inputs[0].var.signed);
+    assert_eq!(module.io.outputs[0].var.width, 8);
+    assert!(!module.io.outputs[0].var.signed);
+
+    let vars: Vec<(&str, u64, bool)> = module
+        .vars
+        .iter()
+        .map(|var| (var.name.as_str(), var.width, var.signed))
+        .collect();
+    assert_eq!(
+        vars,
+        [
+            ("half", 16, true),
+            ("wide", 64, true),
+            ("total", 32, true),
+            ("narrow", 8, true),
+        ]
+    );
+    assert_eq!(module.params[0].name, "DEPTH");
+}
+
+#[test]
+fn parameter_accessors() {
+    let module = parse_module(
+        "module m #(parameter WIDTH = 8, localparam MAX = WIDTH - 1) ();
+parameter DEPTH = 4;
+localparam LAST = DEPTH - 1;
+endmodule
+",
+    );
+
+    let names = |params: Vec<&Param>| -> Vec<String> {
+        params.iter().map(|param| param.name.to_owned()).collect()
+    };
+    assert_eq!(names(module.parameters()), ["WIDTH", "DEPTH"]);
+    assert_eq!(names(module.localparams()), ["MAX", "LAST"]);
+
+    assert!(module.has_parameter("DEPTH"));
+    assert!(!module.has_parameter("LAST"));
+    assert!(!module.has_parameter("missing"));
+    assert!(module.has_localparam("MAX"));
+    assert!(!module.has_localparam("WIDTH"));
+
+    let width = module.parameter_default("WIDTH").expect("WIDTH is declared");
+    assert_eq!(eval_expr(width, &HashMap::new()).to_u128(), Some(8));
+    assert!(matches!(
+        module.localparam_default("LAST"),
+        Some(Expr::BinOp(_, lhs, _)) if **lhs == Expr::Ident("DEPTH".to_owned())
+    ));
+    assert_eq!(module.parameter_default("MAX"), None);
+}
+
+#[test]
+fn typed_parameters() {
+    let module = parse_module(
+        "module m #(parameter int N = 4, parameter [N-1:0] MASK = 8'hFF) ();
+parameter logic signed [3:0] NEG = -1, PLAIN = 2'd3;
+localparam byte unsigned SMALL = 300;
+localparam [7:0] WIDE = 4'sb1000;
+endmodule
+",
+    );
+
+    let data_type = |param: &Param| param.data_type.clone().expect("parameter is typed");
+    assert_eq!(data_type(&module.io.params[0]).var_type, VarType::Int);
+    assert_eq!(data_type(&module.io.params[0]).width, 32);
+    assert!(data_type(&module.io.params[1]).bounds.is_some());
+    assert!(data_type(&module.params[0]).signed);
+    assert!(module.params[1].data_type.is_none());
+
+    let values = module.param_values(&Default::default());
+    assert_eq!(values["N"], LogicValue::from_u128(4, 32));
+    assert_eq!(values["MASK"], LogicValue::from_u128(0xF, 4));
+    assert_eq!(values["NEG"], LogicValue::from_u128(0xF, 4));
+    assert_eq!(values["PLAIN"], LogicValue::from_u128(3, 2));
+    assert_eq!(values["SMALL"], LogicValue::from_u128(44, 8));
+    assert_eq!(values["WIDE"], LogicValue::from_u128(0xF8, 8));
+
+    // Overrides are converted to the type too, with ranges sized by the
+    // overridden values they depend on
+    let overrides = HashMap::from([
+        ("N".to_owned(), LogicValue::from_u128(2, 4)),
+        ("MASK".to_owned(), LogicValue::from_u128(0x7, 8)),
+    ]);
+    let values = module.param_values(&overrides);
+    assert_eq!(values["N"], LogicValue::from_u128(2, 32));
+    assert_eq!(values["MASK"], LogicValue::from_u128(0x3, 2));
+}
+
+#[test]
+fn memory_declarations() {
+    let module = parse_module(
+        "module m ();
+reg [7:0] mem [0:15];
+logic [3:0] rom [8];
+reg flags [7:4];
+wire single;
+endmodule
+",
+    );
+
+    let arrays: Vec<_> = module
+        .vars
+        .iter()
+        .map(|var| (var.name.as_str(), var.width, var.array))
+        .collect();
+    assert_eq!(
+        arrays,
+        [
+            ("mem", 8, Some((0, 15))),
+            ("rom", 4, Some((0, 7))),
+            ("flags", 1, Some((7, 4))),
+            ("single", 1, None),
+        ]
+    );
+    assert_eq!(module.vars[2].addresses(), Some(4..=7));
+    assert_eq!(var_sv_string(&module.vars[0]), "reg [7:0] mem [0:15]");
+}
+
+#[test]
+fn comma_separated_declarations() {
+    let module = parse_module(
+        "module m ();
+wire [3:0] a, b, c;
+reg flag, mem [0:3], last;
+endmodule
+",
+    );
+
+    let vars: Vec<_> = module
+        .vars
+        .iter()
+        .map(|var| (var.name.as_str(), var.width, var.array))
+        .collect();
+    assert_eq!(
+        vars,
+        [
+            ("a", 4, None),
+            ("b", 4, None),
+            ("c", 4, None),
+            ("flag", 1, None),
+            ("mem", 1, Some((0, 3))),
+            ("last", 1, None),
+        ]
+    );
+}
+
+#[test]
+fn declaration_initializers() {
+    let object = parse_sv_file(
+        "module m ();
+wire [3:0] a = 4'd5, b;
+reg [7:0] count = 8'd3, other;
+assign b = a + 1;
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    let module = &object.mods["m"];
+
+    let vars: Vec<&str> = module.vars.iter().map(|var| var.name.as_str()).collect();
+    assert_eq!(vars, ["a", "b", "count", "other"]);
+    assert_eq!(module.assigns.len(), 2);
+    assert_eq!(module.initial_blocks.len(), 1);
+
+    let result = object.run(1e-9);
+    for (signal, expected) in [("m.a", 5), ("m.b", 6), ("m.count", 3)] {
+        assert_eq!(result.signals[signal].to_u128(), Some(expected), "{signal}");
+    }
+    assert_eq!(result.signals["m.other"].to_u128(), None);
+}
+
+#[test]
+fn parameter_ranges() {
+    let module = parse_module(
+        "module m #(parameter WIDTH = 8, parameter DEPTH = 16) (input wire [WIDTH-1:0] data);
+reg [WIDTH*2-1:0] wide, mem [0:DEPTH-1];
+logic [WIDTH-1:0] rom [DEPTH];
+endmodule
+",
+    );
+    assert_eq!(module.io.inputs[0].var.width, 1);
+    assert!(module.io.inputs[0].var.bounds.is_some());
+    assert!(module.vars[1].array_bounds.is_some());
+
+    let generated = module
+        .generate(&module.param_values(&Default::default()))
+        .expect("ranges are constant");
+    assert_eq!(generated.io.inputs[0].var.width, 8);
+    let vars: Vec<_> = generated
+        .vars
+        .iter()
+        .map(|var| (var.name.as_str(), var.width, var.array))
+        .collect();
+    assert_eq!(
+        vars,
+        [
+            ("wide", 16, None),
+            ("mem", 16, Some((0, 15))),
+            ("rom", 8, Some((0, 15))),
+        ]
+    );
+}
+
+#[test]
+fn malformed_ranges_are_rejected() {
+    for range in ["[7:]", "[:0]", "[7:0", "[7 0]", "[3:0][7:0]", "[0] [1]"] {
+        let source = format!("module m ();\nreg {range} r;\nendmodule\n");
+        assert!(parse_sv_file(source).is_err(), "{range}");
+    }
+}
+
+#[test]
+fn type_parameters() {
+    let module = parse_module(
+        "module m #(parameter WIDTH = 4) ();
+localparam type byte_t = logic [7:0];
+parameter type word_t = logic signed [WIDTH*4-1:0], count_t = int unsigned;
+localparam type alias_t = byte_t;
+byte_t data, mask [0:3];
+word_t acc = 0;
+count_t n;
+alias_t copy;
+endmodule
+",
+    );
+
+    let names: Vec<&str> = module
+        .type_params
+        .iter()
+        .map(|param| param.name.as_str())
+        .collect();
+    assert_eq!(names, ["byte_t", "word_t", "count_t", "alias_t"]);
+    assert!(module.type_parameter("word_t").is_some());
+    assert!(module.type_parameter("WIDTH").is_none());
+
+    let generated = module
+        .generate(&module.param_values(&Default::default()))
+        .expect("ranges are constant");
+    let vars: Vec<_> = generated
+        .vars
+        .iter()
+        .map(|var| (var.name.as_str(), var.width, var.signed, var.array))
+        .collect();
+    assert_eq!(
+        vars,
+        [
+            ("data", 8, false, None),
+            ("mask", 8, false, Some((0, 3))),
+            ("acc", 16, true, None),
+            ("n", 32, false, None),
+            ("copy", 8, false, None),
+        ]
+    );
+    assert!(matches!(generated.vars[0].var_type, VarType::Logic));
+    assert_eq!(generated.initial_blocks.len(), 1);
+
+    // Types must be declared before they are used
+    for source in [
+        "localparam type t = undeclared_t;",
+        "localparam type t;",
+        "localparam type = logic;",
+    ] {
+        let source = format!("module m ();\n{source}\nendmodule\n");
+        assert!(parse_sv_file(source.clone()).is_err(), "{source}");
+    }
+}
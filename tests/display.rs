@@ -0,0 +1,319 @@
+//! Output task tests
+
+use std::collections::HashMap;
+use sv_sim::expr::Expr;
+use sv_sim::parse_sv_file;
+use sv_sim::sys_task::format_display;
+use sv_sim::value::{LogicState, LogicValue};
+
+/// Simulates `source` for ten nanoseconds and returns the text it printed
+fn output(source: &str) -> String {
+    let object = parse_sv_file(source.to_owned()).expect("source parses");
+
+    object.run(10e-9).output
+}
+
+/// Formats `$display` arguments, reading `data` as an 8-bit 165 and `wide`
+/// as a 12-bit value whose low bits are unknown
+fn display(args: &[Expr]) -> String {
+    let mut wide = LogicValue::from_u128(0xA50, 12);
+    wide.set_bit(0, LogicState::X);
+    wide.set_bit(1, LogicState::Z);
+
+    let lookup = HashMap::from([
+        ("data".to_owned(), LogicValue::from_u128(165, 8)),
+        ("wide".to_owned(), wide),
+    ]);
+
+    format_display(args, 'd', "top.u_dut", &lookup)
+}
+
+/// Format string argument
+fn format(text: &str) -> Expr {
+    Expr::Str(text.to_owned())
+}
+
+/// Signal argument
+fn ident(name: &str) -> Expr {
+    Expr::Ident(name.to_owned())
+}
+
+#[test]
+fn format_specifiers() {
+    let data = ident("data");
+
+    for (spec, expected) in [
+        ("%d", "165"),
+        ("%0d", "165"),
+        ("%5d", "  165"),
+        ("%b", "10100101"),
+        ("%h", "a5"),
+        ("%H", "a5"),
+        ("%x", "a5"),
+        ("%o", "245"),
+        ("%1o", "245"),
+        ("%6h", "0000a5"),
+        ("%c", "\u{a5}"),
+    ] {
+        assert_eq!(display(&[format(spec), data.clone()]), expected, "{spec}");
+    }
+
+    let small = Expr::Literal(LogicValue::from_u128(5, 8), false);
+    assert_eq!(display(&[format("%d"), small.clone()]), "  5");
+    assert_eq!(display(&[format("%0d"), small.clone()]), "5");
+    assert_eq!(display(&[format("%b"), small.clone()]), "00000101");
+    assert_eq!(display(&[format("%0b"), small.clone()]), "101");
+    assert_eq!(display(&[format("%0h"), small]), "5");
+
+    let wide = ident("wide");
+    assert_eq!(display(&[format("%b"), wide.clone()]), "1010010100zx");
+    assert_eq!(display(&[format("%h"), wide.clone()]), "a5X");
+    assert_eq!(display(&[format("%d"), wide]), "   X");
+    assert_eq!(display(&[format("%d"), ident("missing")]), "x");
+}
+
+#[test]
+fn format_strings() {
+    assert_eq!(
+        display(&[
+            format("%m: data = %0d (%h)\\t100%%\\n"),
+            ident("data"),
+            ident("data"),
+        ]),
+        "top.u_dut: data = 165 (a5)\t100%\n"
+    );
+
+    // Arguments without a format specifier are printed in decimal
+    assert_eq!(
+        display(&[ident("data"), format(" and "), ident("data")]),
+        "165 and 165"
+    );
+
+    assert_eq!(display(&[format("%s!"), format("hi")]), "hi!");
+    assert_eq!(display(&[format("%d %d"), ident("data")]), "165 ");
+    assert_eq!(display(&[format("%q")]), "%q");
+
+    assert_eq!(
+        display(&[format("\\101\\x42\\7\\x4g \\\\ \\\"")]),
+        "AB\u{7}\u{4}g \\ \""
+    );
+}
+
+#[test]
+fn prints_during_simulation() {
+    let text = output(
+        "module tb ();
+reg [7:0] count;
+initial begin
+    count = 8'd1;
+    $write(\"count: \");
+    $display(\"%0d\", count);
+    count <= 8'd2;
+    $display(\"before: %0d\", count);
+    $strobe(\"strobe: %0d\", count);
+    #1 $display(\"later: %0d\", count);
+end
+endmodule
+",
+    );
+
+    assert_eq!(text, "count: 1\nbefore: 1\nstrobe: 2\nlater: 2\n");
+}
+
+#[test]
+fn radix_variants_print_other_arguments_in_their_radix() {
+    let text = output(
+        "module tb ();
+reg [7:0] data;
+initial begin
+    data = 8'd165;
+    $displayh(data, \" \", 4'd3);
+    $displayb(\"%0d \", data, data);
+    $writeo(data);
+    $display;
+    $strobeh(\"strobe \", data);
+    $monitorb(data);
+    data <= 8'd7;
+end
+endmodule
+",
+    );
+
+    assert_eq!(text, "a5 3\n165 10100101\n245\nstrobe 07\n00000111\n");
+}
+
+#[test]
+fn prints_signed_values_with_their_sign() {
+    let text = output(
+        "module tb ();
+reg signed [7:0] delta;
+integer total;
+reg [7:0] raw;
+initial begin
+    delta = -8'sd5;
+    total = delta * 3;
+    raw = delta;
+    $display(\"%d|%0d|%d|%0d\", delta, total, raw, $unsigned(delta));
+    $display(delta);
+end
+endmodule
+",
+    );
+
+    assert_eq!(text, "  -5|-15|251|251\n  -5\n");
+}
+
+#[test]
+fn two_state_variables_start_at_zero() {
+    let text = output(
+        "module tb ();
+int count;
+byte small;
+longint wide;
+integer total;
+reg [3:0] raw;
+initial begin
+    $display(\"%0d %0d %0d %0d %0d\", count, small, wide, total, raw);
+    count = count + 1;
+    total = total + 1;
+    $display(\"%0d %0d\", count, total);
+end
+endmodule
+",
+    );
+
+    assert_eq!(text, "0 0 0 x x\n1 x\n");
+}
+
+#[test]
+fn monitors_print_when_their_arguments_change() {
+    let text = output(
+        "module tb ();
+reg a;
+reg b;
+reg unused;
+initial begin
+    a = 0;
+    b = 0;
+    $monitor(\"a=%b b=%b\", a, b);
+    #1 a = 1;
+    a = 0;
+    #1 unused = 1;
+    #1 b = 1;
+    $monitoroff;
+    #1 a = 1;
+    #1 $monitoron;
+    #1 $monitor(\"b is %b\", b);
+end
+endmodule
+",
+    );
+
+    // Nothing changes overall at 1ns and 2ns, and the monitor is off at 3ns
+    assert_eq!(text, "a=0 b=0\na=1 b=1\nb is 1\n");
+}
+
+#[test]
+fn monitors_follow_memory_elements_and_their_indices() {
+    let text = output(
+        "module tb ();
+reg [7:0] mem [0:3];
+reg [1:0] i;
+initial begin
+    i = 0;
+    mem[0] = 1;
+    mem[1] = 2;
+    $monitor(\"mem[%0d]=%0d\", i, mem[i]);
+    #1 mem[1] = 3;
+    #1 mem[0] = 4;
+    #1 i = 1;
+    #1 mem[1] = 3;
+    mem[2] = 5;
+end
+endmodule
+",
+    );
+
+    assert_eq!(text, "mem[0]=1\nmem[0]=4\nmem[1]=3\n");
+}
+
+#[test]
+fn prints_the_simulation_time() {
+    let text = output(
+        "`timescale 1ns/1ns
+module tb ();
+reg a;
+initial begin
+    a = 0;
+    $monitor(\"%0t: a=%b\", $time, a);
+    #2 $display(\"%t %0d %0d\", $time, $stime, $realtime());
+    #1 a = 1;
+end
+endmodule
+",
+    );
+
+    // The time changing on its own does not set off the monitor
+    assert_eq!(text, "0: a=0\n                   2 2 2\n3: a=1\n");
+}
+
+#[test]
+fn counts_the_time_in_time_units() {
+    let text = output(
+        "`timescale 1ns/100ps
+module tb ();
+initial begin
+    #2 $display(\"%0d %0d %0t\", $time, $stime, $time);
+    #1.25 $display(\"%0d %0d %0t %0t\", $time, $realtime, $time, $realtime);
+    #0.2 $display(\"%0d %0t\", $time, 7);
+end
+endmodule
+",
+    );
+
+    // `%t` prints in precision steps, with `$realtime` keeping the part
+    // `$time` rounds away
+    assert_eq!(text, "2 2 20\n3 3 30 33\n4 70\n");
+}
+
+#[test]
+fn evaluates_constant_system_functions() {
+    let text = output(
+        "module tb ();
+parameter DEPTH = 12;
+localparam AW = $clog2(DEPTH);
+reg [5:0] data;
+reg [7:0] mem [4:11];
+initial begin
+    $display(\"%0d %0d %0d\", AW, $clog2(1), $clog2(17));
+    $display(\"%0d %0d %0d %0d\", $bits(data), $size(data), $high(data), $low(data));
+    $display(\"%0d %0d %0d %0d\", $bits(mem), $size(mem), $high(mem), $low(mem));
+end
+endmodule
+",
+    );
+
+    assert_eq!(text, "4 0 5\n6 6 5 0\n64 8 11 4\n");
+}
+
+#[test]
+fn sizes_types_and_earlier_declarations() {
+    let text = output(
+        "module tb ();
+parameter DEPTH = 12;
+localparam AW = $clog2(DEPTH);
+localparam TW = $bits(logic [AW-1:0]);
+reg [AW-1:0] addr;
+reg [7:0] mem [0:DEPTH-1];
+reg [$bits(addr)*2-1:0] pair;
+reg [$bits(mem)-1:0] flat;
+initial begin
+    $display(\"%0d %0d %0d %0d\", TW, $bits(int), $bits(logic signed [2:0]), $bits(byte));
+    $display(\"%0d %0d %0d\", $bits(pair), $bits(flat), $bits(missing));
+end
+endmodule
+",
+    );
+
+    assert_eq!(text, "4 32 3 8\n8 96 x\n");
+}
@@ -0,0 +1,583 @@
+//! Design hierarchy elaboration tests
+
+use sv_sim::elab::MAX_RECURSION_DEPTH;
+use sv_sim::expr::Expr;
+use sv_sim::{parse_sv_file, ElabError, SimConfig, SimError, SimObject};
+
+/// Design with a two-level hierarchy below `top`
+const DESIGN: &str =
+    "module half_adder (input wire a, input wire b, output wire sum, output wire carry);
+assign sum = a ^ b;
+assign carry = a & b;
+endmodule
+
+module full_adder (input wire a, input wire b, input wire cin, output wire sum, output wire cout);
+wire s0;
+wire c0;
+wire c1;
+half_adder u_ha0 (.a(a), .b(b), .sum(s0), .carry(c0));
+half_adder u_ha1 (.a(s0), .b(cin), .sum(sum), .carry(c1));
+assign cout = c0 | c1;
+endmodule
+
+module top (input wire a, input wire b, output wire sum, output wire cout);
+full_adder u_fa (.a(a), .b(b), .cin(1'b0), .sum(sum), .cout(cout));
+endmodule
+";
+
+#[test]
+fn builds_instance_tree() {
+    let object = parse_sv_file(DESIGN.to_owned()).expect("design parses");
+    let design = object.elaborate("top").expect("design elaborates");
+
+    assert_eq!(design.top.module_name, "top");
+    assert_eq!(design.top.inst_name, "top");
+
+    let paths: Vec<(&str, &str)> = design
+        .top
+        .iter()
+        .map(|inst| (inst.path.as_str(), inst.module_name.as_str()))
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            ("top", "top"),
+            ("top.u_fa", "full_adder"),
+            ("top.u_fa.u_ha0", "half_adder"),
+            ("top.u_fa.u_ha1", "half_adder"),
+        ]
+    );
+
+    // Any module may be used as the top
+    let design = object.elaborate("half_adder").expect("design elaborates");
+    assert!(design.top.children.is_empty());
+}
+
+#[test]
+fn undefined_modules_are_reported() {
+    let object = parse_sv_file(DESIGN.to_owned()).expect("design parses");
+    assert_eq!(
+        object.elaborate("missing"),
+        Err(ElabError::UndefinedModule("missing".to_owned()))
+    );
+
+    let object = parse_sv_file(
+        "module top (input wire a, output wire b);
+buffer u_buf (.a(a), .b(b));
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("design parses");
+    let err = object.elaborate("top").expect_err("buffer is not defined");
+    assert_eq!(err, ElabError::UndefinedModule("buffer".to_owned()));
+
+    let err = SimError::from(err);
+    assert_eq!(err.to_string(), "module 'buffer' is not defined");
+}
+
+/// Design connecting `top`'s signals to a register with an 8 bit data port
+/// through `connections`
+fn register_design(connections: &str) -> String {
+    format!(
+        "module register (input wire clk, input wire [7:0] d, output reg [7:0] q);
+always_ff @(posedge clk) q <= d;
+endmodule
+
+module top (input wire clk, input wire flag, input wire [7:0] data_in, output wire [7:0] out);
+wire [3:0] nibble;
+reg [7:0] history [0:3];
+register u_reg {connections};
+endmodule
+"
+    )
+}
+
+#[test]
+fn port_width_mismatches_are_reported() {
+    let object =
+        parse_sv_file(register_design("(.clk(clk), .d(flag), .q(out))")).expect("design parses");
+    let err = object.elaborate("top").expect_err("d is 8 bits wide");
+    assert_eq!(
+        err,
+        ElabError::PortWidthMismatch {
+            inst: "top.u_reg".to_owned(),
+            port: "d".to_owned(),
+            expected: 8,
+            got: 1,
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "port 'd' of 'top.u_reg' is 8 bits wide, but is connected to 1 bits"
+    );
+
+    // Positional connections follow the header order of the ports
+    let object = parse_sv_file(register_design("(clk, data_in, nibble)")).expect("design parses");
+    assert!(matches!(
+        object.elaborate("top"),
+        Err(ElabError::PortWidthMismatch { ref port, expected: 8, got: 4, .. }) if port == "q"
+    ));
+
+    // Memory elements take the width of an element
+    let object = parse_sv_file(register_design("(.clk(clk), .d(history[1][3:0]), .q(out))"))
+        .expect("design parses");
+    assert!(matches!(
+        object.elaborate("top"),
+        Err(ElabError::PortWidthMismatch { ref port, expected: 8, got: 4, .. }) if port == "d"
+    ));
+}
+
+#[test]
+fn matching_and_constant_connections_elaborate() {
+    for connections in [
+        "(.clk(clk), .d(data_in), .q(out))",
+        "(.clk(clk), .d({nibble, data_in[3:0]}), .q(out))",
+        "(.clk(clk), .d(0), .q(out))",
+        "(.clk(clk), .d(history[2]), .q(out))",
+        "(clk, data_in, out)",
+    ] {
+        let object = parse_sv_file(register_design(connections)).expect("design parses");
+        assert!(object.elaborate("top").is_ok(), "{connections}");
+    }
+}
+
+#[test]
+fn implicit_truncation_can_be_allowed() {
+    let object =
+        parse_sv_file(register_design("(.clk(clk), .d(flag), .q(nibble))")).expect("design parses");
+    let config = SimConfig {
+        allow_implicit_truncation: true,
+        ..Default::default()
+    };
+
+    let design = object
+        .elaborate_with_config("top", &config)
+        .expect("design elaborates");
+    assert_eq!(design.top.children[0].path, "top.u_reg");
+}
+
+#[test]
+fn port_compatibility_lists_every_mismatch() {
+    let object =
+        parse_sv_file(register_design("(.clk(clk), .d(flag), .q(nibble))")).expect("design parses");
+    let config = SimConfig {
+        allow_implicit_truncation: true,
+        ..Default::default()
+    };
+    let design = object
+        .elaborate_with_config("top", &config)
+        .expect("design elaborates");
+
+    let errors = SimObject::check_port_compatibility(
+        &design.top.children[0],
+        &design.mods["register"],
+        &design.mods["top"],
+    );
+    let mismatches: Vec<(&str, &str, u64, u64)> = errors
+        .iter()
+        .map(|err| match err {
+            SimError::PortWidthMismatch {
+                inst,
+                port,
+                port_width,
+                connection_width,
+            } => (inst.as_str(), port.as_str(), *port_width, *connection_width),
+            err => panic!("unexpected error {err}"),
+        })
+        .collect();
+    assert_eq!(
+        mismatches,
+        [("top.u_reg", "d", 8, 1), ("top.u_reg", "q", 8, 4)]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "port 'd' of 'top.u_reg' is 8 bits wide, but is connected to 1 bits"
+    );
+
+    // Port widths follow the parameters of the instance
+    let object = parse_sv_file(
+        "module register #(parameter WIDTH = 8) (input wire [WIDTH-1:0] d, output reg [WIDTH-1:0] q);
+always_comb q = d;
+endmodule
+
+module top (input wire [3:0] nibble, output wire [3:0] out);
+register #(.WIDTH(4)) u_reg (.d(nibble), .q(out));
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("design parses");
+    let design = object.elaborate("top").expect("design elaborates");
+    let errors = SimObject::check_port_compatibility(
+        &design.top.children[0],
+        &design.mods["register"],
+        &design.mods["top"],
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+}
+
+#[test]
+fn unconnected_ports_can_be_denied() {
+    let config = SimConfig {
+        deny_unconnected_ports: true,
+        ..Default::default()
+    };
+
+    for (connections, port) in [
+        ("(.clk(clk), .q(out))", "d"),
+        ("(.clk(), .d(data_in), .q(out))", "clk"),
+        ("(clk, data_in)", "q"),
+        ("(clk, , out)", "d"),
+    ] {
+        let object = parse_sv_file(register_design(connections)).expect("design parses");
+
+        // Unconnected ports only warn by default
+        assert!(object.elaborate("top").is_ok(), "{connections}");
+
+        let err = object
+            .elaborate_with_config("top", &config)
+            .expect_err("a port is unconnected");
+        assert_eq!(
+            err,
+            ElabError::UnconnectedPort {
+                inst: "top.u_reg".to_owned(),
+                port: port.to_owned(),
+            }
+        );
+    }
+
+    let object = parse_sv_file(register_design("(clk, data_in, out)")).expect("design parses");
+    assert!(object.elaborate_with_config("top", &config).is_ok());
+}
+
+#[test]
+fn wildcard_connections_use_matching_names() {
+    let config = SimConfig {
+        deny_unconnected_ports: true,
+        ..Default::default()
+    };
+
+    // `top` has a `clk` but no `d` or `q`
+    let object = parse_sv_file(register_design("(.*)")).expect("design parses");
+    assert_eq!(
+        object.elaborate_with_config("top", &config).unwrap_err(),
+        ElabError::UnconnectedPort {
+            inst: "top.u_reg".to_owned(),
+            port: "d".to_owned(),
+        }
+    );
+
+    let object =
+        parse_sv_file(register_design("(.q(out), .*, .d(data_in))")).expect("design parses");
+    let design = object
+        .elaborate_with_config("top", &config)
+        .expect("design elaborates");
+    let ident = |name: &str| Expr::Ident(name.to_owned());
+    assert_eq!(
+        design.top.children[0].connections,
+        [
+            ("q".to_owned(), ident("out")),
+            ("d".to_owned(), ident("data_in")),
+            ("clk".to_owned(), ident("clk")),
+        ]
+    );
+}
+
+#[test]
+fn circular_instantiation_is_reported() {
+    let object = parse_sv_file(
+        "module top (input wire a, output wire b);
+outer u_outer (.a(a), .b(b));
+endmodule
+
+module outer (input wire a, output wire b);
+inner u_inner (.a(a), .b(b));
+endmodule
+
+module inner (input wire a, output wire b);
+outer u_outer (.a(a), .b(b));
+endmodule
+
+module selfish (input wire a, output wire b);
+selfish u_self (.a(a), .b(b));
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("design parses");
+
+    let err = object
+        .elaborate("top")
+        .expect_err("outer instantiates itself");
+    assert_eq!(
+        err,
+        ElabError::CircularDependency(vec![
+            "outer".to_owned(),
+            "inner".to_owned(),
+            "outer".to_owned(),
+        ])
+    );
+    assert_eq!(
+        err.to_string(),
+        "circular module instantiation: outer -> inner -> outer"
+    );
+
+    assert_eq!(
+        object.elaborate("selfish"),
+        Err(ElabError::CircularDependency(vec![
+            "selfish".to_owned(),
+            "selfish".to_owned(),
+        ]))
+    );
+}
+
+#[test]
+fn modules_may_instantiate_themselves_with_other_parameters() {
+    let object = parse_sv_file(
+        "module tree #(parameter N = 4) (input wire [N-1:0] a, output wire y);
+generate
+    if (N == 1) begin : leaf
+        assign y = a[0];
+    end else begin : node
+        wire l;
+        wire r;
+        tree #(.N(N / 2)) lo (.a(a[N/2-1:0]), .y(l));
+        tree #(.N(N - N / 2)) hi (.a(a[N-1:N/2]), .y(r));
+        assign y = l | r;
+    end
+endgenerate
+endmodule
+
+module top;
+reg [3:0] a;
+wire y;
+tree t (.a(a), .y(y));
+initial begin
+    a = 4'b0100;
+    #1 $display(\"%b\", y);
+end
+endmodule
+
+module endless #(parameter N = 0) ();
+endless #(.N(N + 1)) u ();
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("design parses");
+
+    let design = object.elaborate("top").expect("design elaborates");
+    assert_eq!(object.count_instances("top").expect("design elaborates"), 8);
+    assert_eq!(design.run(10e-9).output, "1\n");
+
+    // Parameters that never end the recursion still stop it eventually
+    match object.elaborate("endless") {
+        Err(ElabError::CircularDependency(cycle)) => {
+            assert_eq!(cycle.len(), MAX_RECURSION_DEPTH + 1)
+        }
+        result => panic!("unexpected result {result:?}"),
+    }
+}
+
+#[test]
+fn modules_may_be_instantiated_more_than_once() {
+    let object = parse_sv_file(DESIGN.to_owned()).expect("design parses");
+    let design = object.elaborate("full_adder").expect("design elaborates");
+
+    // Both half adders are siblings, not a cycle
+    assert_eq!(design.top.children.len(), 2);
+}
+
+#[test]
+fn counts_instances_and_depth() {
+    let object = parse_sv_file(DESIGN.to_owned()).expect("design parses");
+
+    // Each half adder instance counts separately
+    assert_eq!(object.count_instances("top").expect("design elaborates"), 4);
+    assert_eq!(object.count_instances("half_adder").expect("design elaborates"), 1);
+    assert_eq!(object.count_unique_modules(), 3);
+
+    assert_eq!(object.deepest_hierarchy_depth("top").expect("design elaborates"), 3);
+    assert_eq!(
+        object
+            .deepest_hierarchy_depth("half_adder")
+            .expect("design elaborates"),
+        1
+    );
+
+    assert!(matches!(
+        object.count_instances("missing"),
+        Err(SimError::Elaboration(ElabError::UndefinedModule(ref name))) if name == "missing"
+    ));
+}
+
+#[test]
+fn finds_the_top_module() {
+    let object = parse_sv_file(DESIGN.to_owned()).expect("design parses");
+    assert_eq!(
+        object.find_top_module(None).map(|top| &top.name),
+        Ok(&"top".to_owned())
+    );
+    assert_eq!(
+        object
+            .find_top_module(Some("full_adder"))
+            .map(|top| &top.name),
+        Ok(&"full_adder".to_owned())
+    );
+    assert!(matches!(
+        object.find_top_module(Some("missing")),
+        Err(ElabError::UndefinedModule(_))
+    ));
+
+    let object =
+        parse_sv_file(format!("{DESIGN}module spare ();\nendmodule\n")).expect("design parses");
+    let err = object
+        .find_top_module(None)
+        .expect_err("top and spare are both candidates");
+    assert_eq!(
+        err,
+        ElabError::AmbiguousTopModule(vec!["spare".to_owned(), "top".to_owned()])
+    );
+    assert_eq!(
+        err.to_string(),
+        "several modules could be the top-level module, choose one of: spare, top"
+    );
+    assert!(object.find_top_module(Some("spare")).is_ok());
+}
+
+#[test]
+fn parameter_ranges_use_instance_parameters() {
+    let design = |overrides: &str| {
+        format!(
+            "module shifter #(parameter WIDTH = 4) (input wire [WIDTH-1:0] data, output wire [WIDTH-1:0] out);
+assign out = data << 1;
+endmodule
+
+module top ();
+wire [7:0] data;
+wire [7:0] out;
+shifter {overrides} u_shift (.data(data), .out(out));
+endmodule
+"
+        )
+    };
+    let elaborate = |overrides: &str| {
+        parse_sv_file(design(overrides))
+            .expect("design parses")
+            .elaborate("top")
+    };
+
+    assert!(elaborate("#(.WIDTH(8))").is_ok());
+    assert!(elaborate("#(8)").is_ok());
+    assert!(elaborate("#(.WIDTH(8), .DEPTH(2))").is_ok());
+    assert_eq!(
+        elaborate(""),
+        Err(ElabError::PortWidthMismatch {
+            inst: "top.u_shift".to_owned(),
+            port: "data".to_owned(),
+            expected: 4,
+            got: 8,
+        })
+    );
+
+    let object = parse_sv_file(
+        "module m #(parameter DEPTH = 4) ();
+reg [DEPTH:0] mem [0:DEPTH-1];
+reg [0:DEPTH] backwards;
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("design parses");
+    assert_eq!(
+        object.elaborate("m"),
+        Err(ElabError::InvalidWidth("backwards".to_owned()))
+    );
+}
+
+#[test]
+fn localparams_cannot_be_overridden() {
+    let object = parse_sv_file(
+        "module m #(parameter WIDTH = 4, localparam MAX = WIDTH - 1) ();
+endmodule
+
+module top ();
+m #(.WIDTH(8), .MAX(2), .DEPTH(3)) u_m ();
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("design parses");
+    let design = object.elaborate("top").expect("design elaborates");
+
+    let params = &design.top.children[0].params;
+    assert_eq!(params["WIDTH"].to_u128(), Some(8));
+    assert_eq!(params["MAX"].to_u128(), Some(7));
+    assert!(!params.contains_key("DEPTH"));
+}
+
+#[test]
+fn output_ports_share_a_net() {
+    let object = parse_sv_file(
+        "module driver (input en, input d, output y);
+assign y = en ? d : 1'bz;
+endmodule
+
+module top;
+reg en_a;
+reg en_b;
+wire bus;
+driver a (.en(en_a), .d(1'b1), .y(bus));
+driver b (.en(en_b), .d(1'b0), .y(bus));
+initial begin
+    en_a = 1;
+    en_b = 0;
+    #1 $display(\"%b\", bus);
+    en_a = 0;
+    en_b = 1;
+    #1 $display(\"%b\", bus);
+    en_a = 1;
+    #1 $display(\"%b\", bus);
+    en_a = 0;
+    en_b = 0;
+    #1 $display(\"%b\", bus);
+end
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("design parses");
+
+    let design = object.elaborate("top").expect("design elaborates");
+    assert_eq!(design.run(10e-9).output, "1\n0\nx\nz\n");
+}
+
+#[test]
+fn members_read_signals_of_instances() {
+    let object = parse_sv_file(
+        "module counter;
+reg [3:0] count;
+initial begin
+    count = 9;
+    #2 count = 3;
+end
+endmodule
+
+module top;
+counter u_counter ();
+wire [3:0] seen;
+assign seen = u_counter.count;
+initial begin
+    #1 $display(\"%0d\", seen);
+    #2 $display(\"%0d\", seen);
+end
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("design parses");
+
+    let design = object.elaborate("top").expect("design elaborates");
+    assert_eq!(design.run(10e-9).output, "9\n3\n");
+}
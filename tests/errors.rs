@@ -0,0 +1,135 @@
+//! Error reporting tests
+
+use std::error::Error;
+use std::io::ErrorKind;
+use std::num::ParseIntError;
+use sv_sim::value::parse_sized_literal;
+use sv_sim::{parse_sv_file, read_sv_file, LexingError, SimError};
+
+#[test]
+fn lexing_errors_display_messages() {
+    assert_eq!(
+        LexingError::InvalidInteger("overflow error".to_owned()).to_string(),
+        "invalid integer encountered: overflow error"
+    );
+    assert_eq!(LexingError::ExpectedSemi.to_string(), "expected ';'");
+    assert_eq!(
+        LexingError::UndeclaredPortReference("clk".to_owned()).to_string(),
+        "port 'clk' is not listed in the module header"
+    );
+    assert_eq!(
+        LexingError::DuplicateConnection("clk".to_owned()).to_string(),
+        "'clk' is connected more than once"
+    );
+}
+
+#[test]
+fn parse_errors_display_messages() {
+    let err = parse_sv_file("module m (input wire a);\nassign a = ;\nendmodule\n".to_owned())
+        .expect_err("source does not parse");
+
+    assert_eq!(
+        format!("error: {err}"),
+        "error: 2:12: unexpected token encountered"
+    );
+}
+
+#[test]
+fn parse_errors_carry_their_location() {
+    let source = "module m (input wire a);\n\nassign a = b +;\nendmodule\n";
+    let Err(SimError::Lex(err)) = parse_sv_file(source.to_owned()) else {
+        panic!("source does not parse");
+    };
+
+    assert_eq!(err.kind(), &LexingError::UnexpectedToken);
+    assert_eq!(err.location(), Some((3, 15)));
+
+    let span = err.span().expect("error has a span");
+    assert_eq!(&source[span], ";");
+}
+
+#[test]
+fn malformed_module_headers_are_errors() {
+    let object = parse_sv_file("module top;\nreg a;\nendmodule\n".to_owned())
+        .expect("module without a port list parses");
+    assert!(object.mods["top"].io.port_order.is_empty());
+
+    for source in [
+        "module top\nreg a;\nendmodule\n",
+        "module (input wire a);\nendmodule\n",
+        "module top (input wire a) reg b;\nendmodule\n",
+        "module top (input wire a",
+        "module top (input wire a);\nreg b;\n",
+    ] {
+        let Err(SimError::Lex(err)) = parse_sv_file(source.to_owned()) else {
+            panic!("'{source}' does not parse");
+        };
+        assert!(err.location().is_some(), "'{source}' gives '{err}'");
+    }
+
+    let Err(err) = parse_sv_file("module top\nreg a;\nendmodule\n".to_owned()) else {
+        panic!("header is missing its port list");
+    };
+    assert_eq!(err.to_string(), "2:1: unexpected token encountered");
+}
+
+#[test]
+fn integer_errors_chain_their_source() {
+    let err = parse_sized_literal("99999999999'd1").expect_err("width overflows");
+    assert!(matches!(err, LexingError::ParseInteger(_)));
+
+    let source = err.source().expect("parse error is the source");
+    assert!(source.downcast_ref::<ParseIntError>().is_some());
+    assert_eq!(
+        err.to_string(),
+        format!("invalid integer encountered: {source}")
+    );
+
+    assert!(LexingError::UnexpectedToken.source().is_none());
+}
+
+#[test]
+fn lexing_errors_box_as_standard_errors() {
+    fn parse(source: &str) -> Result<usize, Box<dyn Error>> {
+        Ok(parse_sv_file(source.to_owned())?.mods.len())
+    }
+
+    assert_eq!(parse("module m ();\nendmodule\n").unwrap(), 1);
+    assert!(parse("module m (input wire a);\nassign a = ;\nendmodule\n").is_err());
+}
+
+#[test]
+fn pipeline_errors_share_one_type() {
+    let missing = read_sv_file(&"sv/does_not_exist.sv".into()).expect_err("file is missing");
+    assert!(matches!(missing, SimError::Io(ref e) if e.kind() == ErrorKind::NotFound));
+    assert!(missing.source().is_some());
+    assert!(missing
+        .to_string()
+        .starts_with("failed to read sv/does_not_exist.sv: "));
+
+    let parsed = read_sv_file(&"sv/blink.sv".into()).and_then(parse_sv_file);
+    assert!(parsed.is_ok());
+}
+
+#[test]
+fn widths_are_capped() {
+    assert_eq!(
+        parse_sized_literal("4294967295'b0").expect_err("literal is too wide"),
+        LexingError::WidthTooLarge(4294967295)
+    );
+    assert!(parse_sized_literal("65536'b0").is_ok());
+
+    let Err(SimError::Lex(err)) =
+        parse_sv_file("module m ();\nreg [65536:0] wide;\nendmodule\n".to_owned())
+    else {
+        panic!("declaration is too wide");
+    };
+    assert_eq!(err.kind(), &LexingError::WidthTooLarge(65537));
+
+    let Err(SimError::Lex(err)) =
+        parse_sv_file("module m ();\nwire a = 4294967295'b0;\nendmodule\n".to_owned())
+    else {
+        panic!("literal is too wide");
+    };
+    assert_eq!(err.kind(), &LexingError::WidthTooLarge(4294967295));
+}
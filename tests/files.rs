@@ -0,0 +1,106 @@
+//! Multi-file parsing tests
+
+use std::path::PathBuf;
+use sv_sim::{parse_sv_file, parse_sv_files, ElabError, SimConfig, SimError, SimObject};
+
+/// Paths of files in the `sv` directory
+fn sv_paths(names: &[&str]) -> Vec<PathBuf> {
+    names
+        .iter()
+        .map(|name| PathBuf::from("sv").join(name))
+        .collect()
+}
+
+#[test]
+fn merges_modules_from_every_file() {
+    let object = parse_sv_files(&sv_paths(&["blink.sv", "flex_counter.sv", "cu_top.sv"]))
+        .expect("files parse");
+
+    let mut names: Vec<&str> = object.mods.keys().map(|name| name.as_str()).collect();
+    names.sort();
+    assert_eq!(names, ["blink", "flex_counter", "top"]);
+    assert_eq!(object.mods["blink"].io.outputs[0].name, "led");
+
+    // `timescale 1ns/1ps in cu_top.sv, the last file, is still in effect
+    assert_eq!(object.sim_time.d_time, 1e-12);
+}
+
+#[test]
+fn duplicate_modules_are_rejected() {
+    let err = parse_sv_files(&sv_paths(&["blink.sv", "flex_counter.sv", "blink.sv"]))
+        .expect_err("blink is defined twice");
+    assert!(matches!(err, SimError::DuplicateModule(ref name) if name == "blink"));
+    assert_eq!(err.to_string(), "module 'blink' is defined more than once");
+
+    let err = parse_sv_file("module m ();\nendmodule\nmodule m ();\nendmodule\n".to_owned())
+        .expect_err("m is defined twice");
+    assert!(matches!(err, SimError::DuplicateModule(ref name) if name == "m"));
+}
+
+#[test]
+fn duplicate_interfaces_are_rejected() {
+    let source = "interface bus;
+logic valid;
+endinterface
+module m ();
+endmodule
+interface bus;
+logic ready;
+endinterface
+";
+    let err = parse_sv_file(source.to_owned()).expect_err("bus is defined twice");
+    assert!(matches!(err, SimError::DuplicateInterface(ref name) if name == "bus"));
+    assert_eq!(err.to_string(), "interface 'bus' is defined more than once");
+}
+
+#[test]
+fn missing_files_are_reported() {
+    let err =
+        parse_sv_files(&sv_paths(&["blink.sv", "does_not_exist.sv"])).expect_err("file is missing");
+    assert!(matches!(err, SimError::Io(_)));
+}
+
+#[test]
+fn elaborates_from_files() {
+    let config = SimConfig::default();
+    let design = SimObject::elaborate_with_top(
+        &sv_paths(&["blink.sv", "flex_counter.sv", "cu_top.sv"]),
+        Some("flex_counter"),
+        &config,
+    )
+    .expect("design elaborates");
+    assert_eq!(design.top.path, "flex_counter");
+    assert!(design.mods.contains_key("flex_counter"));
+    assert_eq!(design.sim_time.d_time, 1e-12);
+
+    // Every file is read, so both problems are reported
+    let errors = SimObject::elaborate_with_top(
+        &sv_paths(&["does_not_exist.sv", "blink.sv", "blink.sv"]),
+        Some("blink"),
+        &config,
+    )
+    .expect_err("files do not parse");
+    assert_eq!(errors.len(), 2);
+    assert!(matches!(errors[0], SimError::Io(_)));
+    assert!(matches!(errors[1], SimError::DuplicateModule(ref name) if name == "blink"));
+
+    let errors = SimObject::elaborate_with_top(&sv_paths(&["blink.sv"]), Some("missing"), &config)
+        .expect_err("top module is not defined");
+    assert!(matches!(
+        errors[..],
+        [SimError::Elaboration(ElabError::UndefinedModule(ref name))] if name == "missing"
+    ));
+
+    // Without a name, the only module not instantiated by another is the top
+    let design = SimObject::elaborate_with_top(&sv_paths(&["blink.sv"]), None, &config)
+        .expect("design elaborates");
+    assert_eq!(design.top.path, "blink");
+
+    let errors =
+        SimObject::elaborate_with_top(&sv_paths(&["blink.sv", "flex_counter.sv"]), None, &config)
+            .expect_err("either module could be the top");
+    assert!(matches!(
+        errors[..],
+        [SimError::Elaboration(ElabError::AmbiguousTopModule(ref tops))] if tops == &["blink", "flex_counter"]
+    ));
+}
@@ -0,0 +1,169 @@
+//! Function definition and call tests
+
+use sv_sim::logic::LogicStmt;
+use sv_sim::parse_sv_file;
+use sv_sim::sim::SimResult;
+use sv_sim::value::LogicValue;
+
+/// Parses `source` and simulates it for one nanosecond
+fn simulate(source: &str) -> SimResult {
+    let object = parse_sv_file(source.to_owned()).expect("source parses");
+
+    object.run(1e-9)
+}
+
+#[test]
+fn parses_function_definitions() {
+    let object = parse_sv_file(
+        "module top ();
+function automatic logic [8:0] add(input [7:0] a, b, input signed [3:0] c);
+    return a + b;
+endfunction
+function [3:0] swap;
+    input [3:0] value;
+    integer i;
+    begin
+        for (i = 0; i < 4; i++) swap[i] = value[3 - i];
+    end
+endfunction : swap
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    let functions = &object.mods["top"].functions;
+
+    let add = &functions[0];
+    assert_eq!(add.name, "add");
+    assert_eq!(add.ret.width, 9);
+    let args: Vec<(&str, u64, bool)> = add
+        .args
+        .iter()
+        .map(|arg| (arg.name.as_str(), arg.width, arg.signed))
+        .collect();
+    assert_eq!(args, [("a", 8, false), ("b", 8, false), ("c", 4, true)]);
+    assert!(matches!(add.body.as_slice(), [LogicStmt::Return(Some(_))]));
+
+    let swap = &functions[1];
+    assert_eq!(swap.name, "swap");
+    assert_eq!(swap.args[0].name, "value");
+    assert_eq!(swap.vars[0].name, "i");
+    assert_eq!(swap.vars[0].width, 32);
+    assert_eq!(swap.body.len(), 1);
+}
+
+#[test]
+fn calls_run_the_function_body() {
+    let result = simulate(
+        "module top ();
+function [8:0] add(input [7:0] a, input [7:0] b);
+    return a + b;
+endfunction
+function [3:0] swap;
+    input [3:0] value;
+    integer i;
+    for (i = 0; i < 4; i++) swap[i] = value[3 - i];
+endfunction
+function [7:0] first_set(input [7:0] value);
+    for (int i = 0; i < 8; i++)
+        if (value[i]) return i;
+    return 8'hFF;
+endfunction
+reg [7:0] x;
+reg [7:0] y;
+wire [8:0] sum;
+wire [3:0] swapped;
+wire [7:0] first;
+wire [7:0] none;
+assign sum = add(x, y);
+assign swapped = swap(add(x, 2));
+assign first = first_set(y);
+assign none = first_set(0);
+initial begin
+    x = 8'd200;
+    y = 8'd100;
+end
+endmodule
+",
+    );
+
+    for (signal, expected, width) in [
+        ("sum", 300, 9),
+        ("swapped", 0b0101, 4),
+        ("first", 2, 8),
+        ("none", 0xFF, 8),
+    ] {
+        assert_eq!(
+            result.signals[&format!("top.{signal}")],
+            LogicValue::from_u128(expected, width),
+            "{signal}"
+        );
+    }
+}
+
+#[test]
+fn constant_functions_size_parameters() {
+    let result = simulate(
+        "module top ();
+function integer log2(input integer value);
+    log2 = 0;
+    while (value > 1) begin
+        value = value >> 1;
+        log2 = log2 + 1;
+    end
+endfunction
+localparam DEPTH = 64;
+localparam AW = log2(DEPTH);
+endmodule
+",
+    );
+
+    assert_eq!(result.signals["top.AW"], LogicValue::from_u128(6, 32));
+}
+
+#[test]
+fn constant_functions_give_ranges_and_generate_values() {
+    let object = parse_sv_file(
+        "module cell #(parameter W = 1) ();
+wire [W-1:0] bus;
+endmodule
+
+module top ();
+function integer double(input integer value);
+    return value * 2;
+endfunction
+reg [double(3)-1:0] wide;
+wire [3:0] ones;
+for (genvar i = 0; i < double(2); i++) begin : gen
+    assign ones[i] = 1'b1;
+end
+if (double(1) == 2) begin : twice
+    cell #(.W(double(5))) u_cell ();
+end
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    let result = object
+        .elaborate("top")
+        .expect("design elaborates")
+        .run(1e-9);
+
+    assert_eq!(result.signals["top.wide"].width(), 6);
+    assert_eq!(result.signals["top.ones"], LogicValue::from_u128(0b1111, 4));
+    assert_eq!(result.signals["top.twice.u_cell.bus"].width(), 10);
+}
+
+#[test]
+fn undefined_functions_read_as_x() {
+    let result = simulate(
+        "module top ();
+wire out;
+assign out = missing(1'b1);
+endmodule
+",
+    );
+
+    assert_eq!(result.signals["top.out"], LogicValue::new(1));
+}
@@ -0,0 +1,19 @@
+//! Regression inputs found by fuzzing `parse_sv_file`
+//!
+//! Every file in `tests/fuzz_corpus` once caused the parser to panic. Parsing
+//! may succeed or fail, but must not panic
+
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn fuzz_corpus_does_not_panic() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fuzz_corpus");
+
+    for entry in fs::read_dir(dir).expect("fuzz corpus directory exists") {
+        let path = entry.expect("fuzz corpus entry is readable").path();
+        let input = fs::read_to_string(&path).expect("fuzz corpus file is valid UTF-8");
+
+        let _ = sv_sim::parse_sv_file(input);
+    }
+}
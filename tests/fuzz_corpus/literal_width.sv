@@ -0,0 +1,4 @@
+module m ();
+wire [7:0] a;
+assign a = 4294967295'b0;
+endmodule
@@ -0,0 +1,4 @@
+module m (
+    input wire [18446744073709551615:0] a,
+);
+endmodule
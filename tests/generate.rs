@@ -0,0 +1,281 @@
+//! Generate construct tests
+
+use sv_sim::generate::GenBlock;
+use sv_sim::parse_sv_file;
+use sv_sim::value::LogicValue;
+use sv_sim::var_types::VarType;
+use sv_sim::ElabError;
+
+#[test]
+fn parses_loop_and_conditional_generates() {
+    let object = parse_sv_file(
+        "module top ();
+parameter N = 2;
+generate
+    for (genvar i = 0; i < N; i++) begin : gen
+        wire x;
+    end
+endgenerate
+if (N > 1) begin : wide
+    wire y;
+end else if (N == 1) begin
+    wire z;
+end else
+    assign a = 1;
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    let module = &object.mods["top"];
+
+    let [GenBlock::For {
+        genvar, body: gen, ..
+    }, GenBlock::If {
+        then,
+        else_if,
+        else_: Some(else_),
+        ..
+    }] = module.generates.as_slice()
+    else {
+        panic!("unexpected generates: {:?}", module.generates);
+    };
+    assert_eq!(genvar, "i");
+    assert_eq!(gen.label.as_deref(), Some("gen"));
+    assert_eq!(gen.items.vars[0].name, "x");
+    assert_eq!(then.label.as_deref(), Some("wide"));
+    assert_eq!(else_if.len(), 1);
+    assert_eq!(else_if[0].1.label, None);
+    assert_eq!(else_.items.assigns.len(), 1);
+}
+
+#[test]
+fn loops_repeat_their_items() {
+    let object = parse_sv_file(
+        "module top ();
+parameter WIDTH = 4;
+reg [3:0] in;
+wire [3:0] out;
+for (genvar i = 0; i < WIDTH; i = i + 1) begin : gen
+    wire bit_;
+    assign bit_ = in[WIDTH - 1 - i];
+    assign out[i] = bit_;
+end
+initial in = 4'b0011;
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    let result = object.run(1e-9);
+
+    assert_eq!(result.signals["top.out"], LogicValue::from_u128(0b1100, 4));
+    assert_eq!(
+        result.signals["top.gen[0].bit_"],
+        LogicValue::from_u128(0, 1)
+    );
+    assert_eq!(
+        result.signals["top.gen[3].bit_"],
+        LogicValue::from_u128(1, 1)
+    );
+    assert!(!result.signals.contains_key("top.gen[4].bit_"));
+}
+
+#[test]
+fn genvars_are_not_simulated() {
+    let object = parse_sv_file(
+        "module top ();
+genvar i;
+wire [2:0] ones;
+for (i = 0; i < 3; i++) assign ones[i] = 1'b1;
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    assert!(matches!(
+        object.mods["top"].vars[0].var_type,
+        VarType::Genvar
+    ));
+
+    let result = object.run(1e-9);
+    assert_eq!(result.signals["top.ones"], LogicValue::from_u128(0b111, 3));
+    assert!(!result.signals.contains_key("top.i"));
+}
+
+#[test]
+fn conditions_select_a_branch() {
+    let source = |mode: u32| {
+        format!(
+            "module top ();
+localparam MODE = {mode};
+wire [1:0] out;
+if (MODE == 0) begin : zero
+    assign out = 2'd1;
+end else if (MODE == 1) begin
+    localparam VALUE = 2;
+    assign out = VALUE;
+end else
+    assign out = 2'd3;
+endmodule
+"
+        )
+    };
+
+    for (mode, expected) in [(0, 1), (1, 2), (2, 3)] {
+        let object = parse_sv_file(source(mode)).expect("source parses");
+        let result = object.run(1e-9);
+
+        assert_eq!(
+            result.signals["top.out"],
+            LogicValue::from_u128(expected, 2),
+            "mode {mode}"
+        );
+    }
+
+    let object = parse_sv_file(source(1)).expect("source parses");
+    let generated = object.mods["top"]
+        .generate(&object.mods["top"].param_values(&Default::default()))
+        .expect("generates unroll");
+    assert_eq!(generated.params[1].name, "genblk1.VALUE");
+}
+
+#[test]
+fn case_generates_select_an_item() {
+    let source = |mode: u32| {
+        format!(
+            "module top ();
+localparam MODE = {mode};
+wire [1:0] out;
+case (MODE)
+    0: begin : zero
+        assign out = 2'd1;
+    end
+    1, 2: begin
+        localparam VALUE = 2;
+        assign out = VALUE;
+    end
+    default assign out = 2'd3;
+endcase
+endmodule
+"
+        )
+    };
+
+    for (mode, expected) in [(0, 1), (1, 2), (2, 2), (5, 3)] {
+        let object = parse_sv_file(source(mode)).expect("source parses");
+        let result = object.run(1e-9);
+
+        assert_eq!(
+            result.signals["top.out"],
+            LogicValue::from_u128(expected, 2),
+            "mode {mode}"
+        );
+    }
+
+    let object = parse_sv_file(source(2)).expect("source parses");
+    let generated = object.mods["top"]
+        .generate(&object.mods["top"].param_values(&Default::default()))
+        .expect("generates unroll");
+    assert_eq!(generated.params[1].name, "genblk1.VALUE");
+}
+
+#[test]
+fn elaborates_generated_instances() {
+    let object = parse_sv_file(
+        "module cell (input wire a, output wire y);
+assign y = ~a;
+endmodule
+
+module row #(parameter N = 1) (input wire [3:0] a, output wire [3:0] y);
+for (genvar i = 0; i < N; i++) begin : gen
+    cell u_cell (.a(a[i]), .y(y[i]));
+end
+endmodule
+
+module top (input wire [3:0] a, output wire [3:0] y);
+row #(3) u_row (.a(a), .y(y));
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    let design = object.elaborate("top").expect("design elaborates");
+
+    let paths: Vec<&str> = design.top.iter().map(|inst| inst.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "top",
+            "top.u_row",
+            "top.u_row.gen[0].u_cell",
+            "top.u_row.gen[1].u_cell",
+            "top.u_row.gen[2].u_cell",
+        ]
+    );
+}
+
+#[test]
+fn unknown_conditions_are_reported() {
+    let object = parse_sv_file(
+        "module top ();
+wire sel;
+if (sel) begin : maybe
+    wire x;
+end
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+
+    assert_eq!(
+        object.mods["top"]
+            .generate(&Default::default())
+            .unwrap_err(),
+        ElabError::UnknownGenerateValue("maybe".to_owned())
+    );
+}
+
+#[test]
+fn loops_step_declared_genvars() {
+    let object = parse_sv_file(
+        "module top ();
+wire [3:0] ones;
+generate
+    genvar i;
+    for (i = 0; i < 2; i = i + 1) begin : outer
+        genvar j;
+        for (j = 0; j < 2; j++) begin : inner
+            assign ones[i * 2 + j] = 1'b1;
+        end
+    end
+endgenerate
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    let result = object.run(1e-9);
+    assert_eq!(result.signals["top.ones"], LogicValue::from_u128(0b1111, 4));
+
+    let object = parse_sv_file(
+        "module top ();
+wire [1:0] ones;
+for (k = 0; k < 2; k++) begin : gen
+    assign ones[k] = 1'b1;
+end
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    let err = object.mods["top"]
+        .generate(&Default::default())
+        .unwrap_err();
+    assert_eq!(err, ElabError::UndeclaredGenvar("k".to_owned()));
+    assert_eq!(
+        err.to_string(),
+        "generate loop variable 'k' is not declared as a genvar"
+    );
+}
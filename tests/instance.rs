@@ -0,0 +1,138 @@
+//! Module instantiation parsing tests
+
+use sv_sim::expr::Expr;
+use sv_sim::module::{ModuleInst, ParamOverride, PortConn};
+use sv_sim::{parse_sv_file, LexingError, SimError};
+
+/// Parses a module containing `body` and returns its sub-module instances
+fn parse_instances(body: &str) -> Vec<ModuleInst> {
+    let source =
+        format!("module m (input wire a, input wire b, output wire out);\n{body}\nendmodule\n");
+    let object = parse_sv_file(source).expect("module parses");
+
+    object.mods["m"].instances.clone()
+}
+
+#[test]
+fn named_connections() {
+    let instances = parse_instances("adder u_add (.a(a), .b(b[0]), .sum(out), .carry());");
+
+    assert_eq!(instances.len(), 1);
+    assert_eq!(instances[0].module_name, "adder");
+    assert_eq!(instances[0].inst_name, "u_add");
+
+    let ports: Vec<(&str, bool)> = instances[0]
+        .port_connections
+        .iter()
+        .map(|conn| match conn {
+            PortConn::Named { port, signal } => (port.as_str(), signal.is_some()),
+            _ => panic!("expected named connection"),
+        })
+        .collect();
+    assert_eq!(
+        ports,
+        [("a", true), ("b", true), ("sum", true), ("carry", false)]
+    );
+}
+
+#[test]
+fn wildcard_connections() {
+    let instances = parse_instances("adder u_add (.*, .sum(out)), u_sub (.b(a), .*);");
+
+    assert_eq!(instances[0].port_connections[0], PortConn::Wildcard);
+    assert!(matches!(
+        &instances[0].port_connections[1],
+        PortConn::Named { port, .. } if port == "sum"
+    ));
+    assert_eq!(instances[1].port_connections[1], PortConn::Wildcard);
+
+    for body in ["adder u (a, .*);", "adder #(.*) u (.*);"] {
+        let source =
+            format!("module m (input wire a, input wire b, output wire out);\n{body}\nendmodule\n");
+        assert!(parse_sv_file(source).is_err(), "{body}");
+    }
+}
+
+#[test]
+fn ports_are_connected_once() {
+    for (body, name) in [
+        ("adder u (.a(a), .b(b), .a(out));", "a"),
+        ("adder u (.a, .a());", "a"),
+        ("adder u (.*, .a(a), .*);", "*"),
+        ("adder #(.W(1), .W(2)) u (.a(a));", "W"),
+    ] {
+        let source =
+            format!("module m (input wire a, input wire b, output wire out);\n{body}\nendmodule\n");
+        let Err(SimError::Lex(err)) = parse_sv_file(source) else {
+            panic!("'{body}' parses");
+        };
+        assert_eq!(
+            err.kind(),
+            &LexingError::DuplicateConnection(name.to_owned()),
+            "{body}"
+        );
+    }
+}
+
+#[test]
+fn positional_connections() {
+    let instances = parse_instances("half_adder u0 (a, b, out), u1 (a, , out);");
+
+    assert_eq!(instances.len(), 2);
+    assert_eq!(instances[0].module_name, "half_adder");
+    assert_eq!(instances[1].module_name, "half_adder");
+    assert_eq!(instances[1].inst_name, "u1");
+    assert_eq!(instances[0].port_connections.len(), 3);
+    assert_eq!(instances[1].port_connections[1], PortConn::Positional(None));
+}
+
+#[test]
+fn implicit_named_connection() {
+    let instances = parse_instances("adder u_add (.a, .b(b), .sum(out));");
+
+    assert_eq!(
+        instances[0].port_connections[0],
+        PortConn::Named {
+            port: "a".to_owned(),
+            signal: Some(Expr::Ident("a".to_owned())),
+        }
+    );
+}
+
+#[test]
+fn mixed_connections_are_rejected() {
+    for body in [
+        "adder u_add (.a(a), b, .sum(out));",
+        "adder u_add (a, .b(b), out);",
+    ] {
+        let source =
+            format!("module m (input wire a, input wire b, output wire out);\n{body}\nendmodule\n");
+        assert!(parse_sv_file(source).is_err(), "{body}");
+    }
+}
+
+#[test]
+fn parameter_overrides() {
+    let instances = parse_instances(
+        "fifo #(.DEPTH(16), .WIDTH()) u_fifo (.a(a)); counter #(4, 2) u_cnt (a); adder #8 u_add (a, b, out);",
+    );
+
+    assert_eq!(instances.len(), 3);
+    assert_eq!(instances[0].inst_name, "u_fifo");
+    assert!(matches!(
+        &instances[0].param_overrides[..],
+        [
+            ParamOverride::Named { param: depth, value: Some(_) },
+            ParamOverride::Named { param: width, value: None },
+        ] if depth == "DEPTH" && width == "WIDTH"
+    ));
+    assert!(matches!(
+        &instances[1].param_overrides[..],
+        [ParamOverride::Positional(_), ParamOverride::Positional(_)]
+    ));
+    assert!(matches!(
+        &instances[2].param_overrides[..],
+        [ParamOverride::Positional(_)]
+    ));
+    assert_eq!(instances[2].port_connections.len(), 3);
+}
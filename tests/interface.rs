@@ -0,0 +1,235 @@
+//! Interface declaration tests
+
+use sv_sim::interface::{InterfacePort, ModportPort};
+use sv_sim::module::PortDirection;
+use sv_sim::{parse_sv_file, ElabError, LexingError, SimError};
+
+/// APB bus interface shared by the tests
+const APB_IF: &str = "interface APB_if #(parameter WIDTH = 32) (input logic pclk);
+logic psel;
+logic penable;
+logic [31:0] prdata;
+localparam IDLE = 0;
+modport Master (output psel, output penable, input prdata);
+modport Slave (input psel, penable, output prdata), Monitor (input psel);
+endinterface
+";
+
+#[test]
+fn parses_interface_declarations() {
+    let object = parse_sv_file(format!(
+        "{APB_IF}
+interface empty_if;
+endinterface
+"
+    ))
+    .expect("source parses");
+
+    let apb = object.interface("APB_if").expect("APB_if is defined");
+    assert_eq!(apb.io.params[0].name, "WIDTH");
+    assert_eq!(apb.io.inputs[0].name, "pclk");
+    let vars: Vec<(&str, u64)> = apb
+        .vars
+        .iter()
+        .map(|var| (var.name.as_str(), var.width))
+        .collect();
+    assert_eq!(vars, [("psel", 1), ("penable", 1), ("prdata", 32)]);
+    assert_eq!(apb.params[0].name, "IDLE");
+
+    let modports: Vec<(&str, usize)> = apb
+        .modports
+        .iter()
+        .map(|modport| (modport.name.as_str(), modport.ports.len()))
+        .collect();
+    assert_eq!(modports, [("Master", 3), ("Slave", 3), ("Monitor", 1)]);
+    let slave: Vec<(&str, PortDirection)> = apb
+        .modport("Slave")
+        .expect("Slave is declared")
+        .ports
+        .iter()
+        .map(|port| (port.name.as_str(), port.direction))
+        .collect();
+    assert_eq!(
+        slave,
+        [
+            ("psel", PortDirection::Input),
+            ("penable", PortDirection::Input),
+            ("prdata", PortDirection::Output),
+        ]
+    );
+    assert_eq!(
+        apb.modports[2].ports,
+        [ModportPort {
+            name: "psel".to_owned(),
+            direction: PortDirection::Input,
+        }]
+    );
+
+    let empty = &object.interfaces[1];
+    assert_eq!(empty.name, "empty_if");
+    assert!(empty.vars.is_empty());
+    assert!(object.mods.is_empty());
+}
+
+#[test]
+fn module_ports_can_be_interfaces() {
+    let object = parse_sv_file(format!(
+        "{APB_IF}
+module apb_master (input wire clk, APB_if.Master bus, APB_if mon);
+endmodule
+
+module top ();
+wire clk;
+APB_if apb (clk);
+apb_master u_master (.clk(clk), .bus(apb), .mon(apb));
+endmodule
+"
+    ))
+    .expect("source parses");
+    let io = &object.mods["apb_master"].io;
+
+    assert_eq!(io.port_order, ["clk", "bus", "mon"]);
+    assert_eq!(io.inputs.len(), 1);
+    assert!(io.ports.is_empty());
+    assert_eq!(
+        io.interfaces,
+        [
+            InterfacePort {
+                name: "bus".to_owned(),
+                interface: "APB_if".to_owned(),
+                modport: Some("Master".to_owned()),
+            },
+            InterfacePort {
+                name: "mon".to_owned(),
+                interface: "APB_if".to_owned(),
+                modport: None,
+            },
+        ]
+    );
+
+    let design = object.elaborate("top").expect("design elaborates");
+    let paths: Vec<&str> = design.top.iter().map(|inst| inst.path.as_str()).collect();
+    assert_eq!(paths, ["top", "top.u_master"]);
+}
+
+#[test]
+fn end_labels_repeat_the_interface_name() {
+    let object = parse_sv_file(
+        "interface bus_if;
+logic valid;
+endinterface : bus_if
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    assert_eq!(object.interfaces[0].name, "bus_if");
+    assert_eq!(object.interfaces[0].vars[0].name, "valid");
+
+    let source = "interface bus_if;
+endinterface : other_if
+";
+    assert!(parse_sv_file(source.to_owned()).is_err());
+}
+
+#[test]
+fn modports_must_expose_declared_signals() {
+    let source = "interface bus_if;
+logic valid;
+modport Source (output valid, input ready);
+endinterface
+";
+    let Err(SimError::Lex(err)) = parse_sv_file(source.to_owned()) else {
+        panic!("source parses");
+    };
+
+    assert_eq!(
+        err.kind(),
+        &LexingError::UndeclaredPortReference("ready".to_owned())
+    );
+}
+
+#[test]
+fn modports_are_declared_once() {
+    let source = "interface bus_if;
+logic valid;
+modport Source (output valid), Sink (input valid);
+modport Source (input valid);
+endinterface
+";
+    let Err(SimError::Lex(err)) = parse_sv_file(source.to_owned()) else {
+        panic!("source parses");
+    };
+    assert_eq!(
+        err.kind(),
+        &LexingError::DuplicateModport("Source".to_owned())
+    );
+    assert_eq!(
+        err.kind().to_string(),
+        "modport 'Source' is declared more than once"
+    );
+
+    let source = "interface bus_if;
+logic valid;
+modport Source (output valid, input valid);
+endinterface
+";
+    assert!(parse_sv_file(source.to_owned()).is_err());
+}
+
+#[test]
+fn interface_connections_are_checked() {
+    let design = |port: &str, connection: &str| {
+        format!(
+            "{APB_IF}
+interface other_if;
+endinterface
+
+module apb_slave ({port} bus);
+endmodule
+
+module top ();
+wire pclk;
+wire [31:0] data;
+APB_if apb (pclk);
+other_if other ();
+apb_slave u_slave (.bus({connection}));
+endmodule
+"
+        )
+    };
+    let elaborate = |port: &str, connection: &str| {
+        parse_sv_file(design(port, connection))
+            .expect("source parses")
+            .elaborate("top")
+    };
+
+    assert!(elaborate("APB_if.Slave", "apb").is_ok());
+    assert!(elaborate("APB_if", "apb").is_ok());
+    for connection in ["other", "data", "missing"] {
+        assert_eq!(
+            elaborate("APB_if.Slave", connection),
+            Err(ElabError::InterfaceMismatch {
+                inst: "top.u_slave".to_owned(),
+                port: "bus".to_owned(),
+                interface: "APB_if".to_owned(),
+            }),
+            "{connection}"
+        );
+    }
+    assert_eq!(
+        elaborate("APB_if.Bridge", "apb"),
+        Err(ElabError::UndefinedModport {
+            interface: "APB_if".to_owned(),
+            modport: "Bridge".to_owned(),
+        })
+    );
+
+    // Interface ports must be connected, even though other ports may not be
+    assert_eq!(
+        elaborate("APB_if", ""),
+        Err(ElabError::UnconnectedPort {
+            inst: "top.u_slave".to_owned(),
+            port: "bus".to_owned(),
+        })
+    );
+}
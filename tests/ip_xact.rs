@@ -0,0 +1,185 @@
+//! IP-XACT output tests
+
+use sv_sim::ip_xact::ip_xact_string;
+use sv_sim::parse_sv_file;
+
+/// Describes module `m` of `source` as IP-XACT
+fn describe(source: &str) -> String {
+    let object = parse_sv_file(source.to_owned()).expect("source is valid");
+    ip_xact_string(&object.mods["m"], "acme", "lib", "1.0").expect("description is written")
+}
+
+#[test]
+fn describes_ports_with_declared_bounds() {
+    let xml = describe(
+        "module m (input clk, input [8:1] a, output [3:0] y);
+assign y = a[4:1];
+endmodule",
+    );
+
+    assert_eq!(
+        xml,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<ipxact:component xmlns:ipxact="http://www.accellera.org/XMLSchema/IPXACT/1685-2014" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.accellera.org/XMLSchema/IPXACT/1685-2014 http://www.accellera.org/XMLSchema/IPXACT/1685-2014/index.xsd">
+  <ipxact:vendor>acme</ipxact:vendor>
+  <ipxact:library>lib</ipxact:library>
+  <ipxact:name>m</ipxact:name>
+  <ipxact:version>1.0</ipxact:version>
+  <ipxact:model>
+    <ipxact:ports>
+      <ipxact:port>
+        <ipxact:name>clk</ipxact:name>
+        <ipxact:wire>
+          <ipxact:direction>in</ipxact:direction>
+        </ipxact:wire>
+      </ipxact:port>
+      <ipxact:port>
+        <ipxact:name>a</ipxact:name>
+        <ipxact:wire>
+          <ipxact:direction>in</ipxact:direction>
+          <ipxact:vectors>
+            <ipxact:vector>
+              <ipxact:left>8</ipxact:left>
+              <ipxact:right>1</ipxact:right>
+            </ipxact:vector>
+          </ipxact:vectors>
+        </ipxact:wire>
+      </ipxact:port>
+      <ipxact:port>
+        <ipxact:name>y</ipxact:name>
+        <ipxact:wire>
+          <ipxact:direction>out</ipxact:direction>
+          <ipxact:vectors>
+            <ipxact:vector>
+              <ipxact:left>3</ipxact:left>
+              <ipxact:right>0</ipxact:right>
+            </ipxact:vector>
+          </ipxact:vectors>
+        </ipxact:wire>
+      </ipxact:port>
+    </ipxact:ports>
+  </ipxact:model>
+</ipxact:component>"#
+    );
+}
+
+#[test]
+fn describes_parameters_and_memory_maps() {
+    let xml = describe(
+        "module m #(parameter DEPTH = 16) (input clk);
+parameter INIT = 8'h5a;
+localparam HIDDEN = 1;
+reg [7:0] mem [4:19];
+endmodule",
+    );
+
+    assert_eq!(
+        xml,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<ipxact:component xmlns:ipxact="http://www.accellera.org/XMLSchema/IPXACT/1685-2014" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.accellera.org/XMLSchema/IPXACT/1685-2014 http://www.accellera.org/XMLSchema/IPXACT/1685-2014/index.xsd">
+  <ipxact:vendor>acme</ipxact:vendor>
+  <ipxact:library>lib</ipxact:library>
+  <ipxact:name>m</ipxact:name>
+  <ipxact:version>1.0</ipxact:version>
+  <ipxact:memoryMaps>
+    <ipxact:memoryMap>
+      <ipxact:name>mem</ipxact:name>
+      <ipxact:addressBlock>
+        <ipxact:name>mem</ipxact:name>
+        <ipxact:baseAddress>4</ipxact:baseAddress>
+        <ipxact:range>16</ipxact:range>
+        <ipxact:width>8</ipxact:width>
+        <ipxact:usage>memory</ipxact:usage>
+      </ipxact:addressBlock>
+      <ipxact:addressUnitBits>8</ipxact:addressUnitBits>
+    </ipxact:memoryMap>
+  </ipxact:memoryMaps>
+  <ipxact:model>
+    <ipxact:ports>
+      <ipxact:port>
+        <ipxact:name>clk</ipxact:name>
+        <ipxact:wire>
+          <ipxact:direction>in</ipxact:direction>
+        </ipxact:wire>
+      </ipxact:port>
+    </ipxact:ports>
+  </ipxact:model>
+  <ipxact:parameters>
+    <ipxact:parameter parameterId="DEPTH" resolve="user">
+      <ipxact:name>DEPTH</ipxact:name>
+      <ipxact:value>16</ipxact:value>
+    </ipxact:parameter>
+    <ipxact:parameter parameterId="INIT" resolve="user">
+      <ipxact:name>INIT</ipxact:name>
+      <ipxact:value>90</ipxact:value>
+    </ipxact:parameter>
+  </ipxact:parameters>
+</ipxact:component>"#
+    );
+}
+
+#[test]
+fn describes_axi_ports_as_a_bus_interface() {
+    let xml = describe(
+        "module m (output axi_awvalid, input axi_awready);
+assign axi_awvalid = 1'b0;
+endmodule",
+    );
+
+    assert_eq!(
+        xml,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<ipxact:component xmlns:ipxact="http://www.accellera.org/XMLSchema/IPXACT/1685-2014" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.accellera.org/XMLSchema/IPXACT/1685-2014 http://www.accellera.org/XMLSchema/IPXACT/1685-2014/index.xsd">
+  <ipxact:vendor>acme</ipxact:vendor>
+  <ipxact:library>lib</ipxact:library>
+  <ipxact:name>m</ipxact:name>
+  <ipxact:version>1.0</ipxact:version>
+  <ipxact:busInterfaces>
+    <ipxact:busInterface>
+      <ipxact:name>axi</ipxact:name>
+      <ipxact:busType vendor="amba.com" library="AMBA4" name="AXI4" version="r0p0_0"/>
+      <ipxact:abstractionTypes>
+        <ipxact:abstractionType>
+          <ipxact:abstractionRef vendor="amba.com" library="AMBA4" name="AXI4_rtl" version="r0p0_0"/>
+          <ipxact:portMaps>
+            <ipxact:portMap>
+              <ipxact:logicalPort>
+                <ipxact:name>AWREADY</ipxact:name>
+              </ipxact:logicalPort>
+              <ipxact:physicalPort>
+                <ipxact:name>axi_awready</ipxact:name>
+              </ipxact:physicalPort>
+            </ipxact:portMap>
+            <ipxact:portMap>
+              <ipxact:logicalPort>
+                <ipxact:name>AWVALID</ipxact:name>
+              </ipxact:logicalPort>
+              <ipxact:physicalPort>
+                <ipxact:name>axi_awvalid</ipxact:name>
+              </ipxact:physicalPort>
+            </ipxact:portMap>
+          </ipxact:portMaps>
+        </ipxact:abstractionType>
+      </ipxact:abstractionTypes>
+      <ipxact:master/>
+    </ipxact:busInterface>
+  </ipxact:busInterfaces>
+  <ipxact:model>
+    <ipxact:ports>
+      <ipxact:port>
+        <ipxact:name>axi_awready</ipxact:name>
+        <ipxact:wire>
+          <ipxact:direction>in</ipxact:direction>
+        </ipxact:wire>
+      </ipxact:port>
+      <ipxact:port>
+        <ipxact:name>axi_awvalid</ipxact:name>
+        <ipxact:wire>
+          <ipxact:direction>out</ipxact:direction>
+        </ipxact:wire>
+      </ipxact:port>
+    </ipxact:ports>
+  </ipxact:model>
+</ipxact:component>"#
+    );
+}
@@ -0,0 +1,88 @@
+//! JSON output tests
+
+use std::collections::BTreeMap;
+use sv_sim::output::json::JsonWriter;
+use sv_sim::parse_sv_file;
+use sv_sim::sim::{SignalChange, SimResult};
+use sv_sim::value::LogicValue;
+
+/// Writes `result` as JSON
+fn to_json(result: &SimResult) -> String {
+    let mut writer = JsonWriter::new(Vec::new());
+    writer.write_result(result).expect("result is written");
+
+    String::from_utf8(writer.into_inner()).expect("output is UTF-8")
+}
+
+#[test]
+fn writes_signals_and_changes() {
+    let object = parse_sv_file(
+        "`timescale 1ns/1ps
+module top ();
+reg clk;
+reg [3:0] count;
+initial begin
+    clk = 0;
+    count = 4'd9;
+    #1 clk = 1;
+    count = 4'bx01z;
+end
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    let result = object.run(5e-9);
+
+    assert_eq!(
+        to_json(&result),
+        r#"{
+  "timescale": {"unit": 1e-9, "precision": 1e-12},
+  "end_time": 1e-9,
+  "signals": {
+    "top.clk": {"width": 1, "value": "1"},
+    "top.count": {"width": 4, "value": "x01z"}
+  },
+  "changes": [
+    {"time": 0e0, "signal": "top.clk", "value": "0"},
+    {"time": 0e0, "signal": "top.count", "value": "1001"},
+    {"time": 1e-9, "signal": "top.clk", "value": "1"},
+    {"time": 1e-9, "signal": "top.count", "value": "x01z"}
+  ]
+}
+"#
+    );
+}
+
+#[test]
+fn escapes_signal_names() {
+    let result = SimResult {
+        signals: BTreeMap::from([("top.a\"b\\c".to_owned(), LogicValue::from_u128(1, 1))]),
+        changes: vec![SignalChange {
+            time: 2e-9,
+            signal: "top.a\"b\\c".to_owned(),
+            value: LogicValue::from_u128(1, 1),
+        }],
+        ..Default::default()
+    };
+
+    let json = to_json(&result);
+    assert!(json.contains(r#""top.a\"b\\c": {"width": 1, "value": "1"}"#));
+    assert!(json.contains(r#"{"time": 2e-9, "signal": "top.a\"b\\c", "value": "1"}"#));
+}
+
+#[test]
+fn empty_results_are_valid() {
+    assert_eq!(
+        to_json(&SimResult::default()),
+        r#"{
+  "timescale": {"unit": 1e-9, "precision": 1e-12},
+  "end_time": 0e0,
+  "signals": {
+  },
+  "changes": [
+  ]
+}
+"#
+    );
+}
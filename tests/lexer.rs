@@ -0,0 +1,420 @@
+//! Lexer tests for literals and operators
+
+use logos::Logos;
+use sv_sim::value::{parse_sized_literal, LogicState, LogicValue, SizedLiteral};
+use sv_sim::{LexingError, Token};
+
+/// Lexes `source`, dropping whitespace
+fn lex(source: &str) -> Vec<(Token, &str)> {
+    let mut lexer = Token::lexer(source);
+    let mut tokens = Vec::new();
+
+    while let Some(token) = lexer.next() {
+        let token = token.expect("source lexes without errors");
+
+        if token != Token::WhiteSpace {
+            tokens.push((token, lexer.slice()));
+        }
+    }
+
+    tokens
+}
+
+/// Literal of the given width with no X or Z bits
+fn known_literal(width: u32, value: u128) -> SizedLiteral {
+    SizedLiteral {
+        width,
+        value,
+        bits: LogicValue::from_u128(value, u64::from(width))
+            .bits()
+            .to_vec(),
+        signed: false,
+    }
+}
+
+#[test]
+fn integer_with_underscores() {
+    assert_eq!(
+        lex("1_000_000 42 0__1"),
+        vec![
+            (Token::Integer(1_000_000), "1_000_000"),
+            (Token::Integer(42), "42"),
+            (Token::Integer(1), "0__1"),
+        ]
+    );
+}
+
+#[test]
+fn integer_underscore_is_not_leading() {
+    assert_eq!(
+        lex("_12 a_12_b _ _$1"),
+        vec![
+            (Token::Word, "_12"),
+            (Token::Word, "a_12_b"),
+            (Token::Underscore, "_"),
+            (Token::Word, "_$1"),
+        ]
+    );
+}
+
+#[test]
+fn identifiers_may_start_with_keywords() {
+    assert_eq!(
+        lex("byte_en logic_out int2 reg"),
+        vec![
+            (Token::Word, "byte_en"),
+            (Token::Word, "logic_out"),
+            (Token::Word, "int2"),
+            (Token::Reg, "reg"),
+        ]
+    );
+}
+
+#[test]
+fn binary_value_with_underscores() {
+    let cases = [
+        ("8'b1010_0101", 8, 0b1010_0101),
+        ("16'b0000_0000_1111_1111", 16, 0b1111_1111),
+        ("4'b1_0_1_0", 4, 0b1010),
+        ("16'hDEAD_BEEF", 16, 0xBEEF),
+    ];
+
+    for (source, width, value) in cases {
+        let literal = match Token::lexer(source).next() {
+            Some(Ok(Token::BinaryValue(literal) | Token::HexValue(literal))) => literal,
+            token => panic!("{source} lexed as {token:?}"),
+        };
+        assert_eq!(literal.width, width, "{source}");
+        assert_eq!(literal.value, value, "{source}");
+        assert_eq!(literal, known_literal(width, value), "{source}");
+    }
+}
+
+#[test]
+fn underscores_in_literal_widths() {
+    assert_eq!(
+        lex("1_6'hFF_FF 6_4'sd1_000 1__2'o7"),
+        vec![
+            (Token::HexValue(known_literal(16, 0xFFFF)), "1_6'hFF_FF"),
+            (
+                Token::DecimalValue(SizedLiteral {
+                    signed: true,
+                    ..known_literal(64, 1_000)
+                }),
+                "6_4'sd1_000"
+            ),
+            (Token::OctalValue(known_literal(12, 0o7)), "1__2'o7"),
+        ]
+    );
+}
+
+#[test]
+fn bitwise_operators() {
+    assert_eq!(
+        lex("a & b | ~c ^ d ~^ e ^~ f ~&g ~|h"),
+        vec![
+            (Token::Word, "a"),
+            (Token::BitAnd, "&"),
+            (Token::Word, "b"),
+            (Token::BitOr, "|"),
+            (Token::BitNot, "~"),
+            (Token::Word, "c"),
+            (Token::BitXor, "^"),
+            (Token::Word, "d"),
+            (Token::BitXnor, "~^"),
+            (Token::Word, "e"),
+            (Token::BitXnor, "^~"),
+            (Token::Word, "f"),
+            (Token::ReductionNand, "~&"),
+            (Token::Word, "g"),
+            (Token::ReductionNor, "~|"),
+            (Token::Word, "h"),
+        ]
+    );
+}
+
+#[test]
+fn adjacent_bitwise_operators() {
+    // The longest operator is taken first, so a reduction directly after a
+    // binary operator needs no space unless the two would form `&&` or `||`
+    assert_eq!(
+        lex("a&&&b|~|c^~^d"),
+        vec![
+            (Token::Word, "a"),
+            (Token::LogAnd, "&&"),
+            (Token::BitAnd, "&"),
+            (Token::Word, "b"),
+            (Token::BitOr, "|"),
+            (Token::ReductionNor, "~|"),
+            (Token::Word, "c"),
+            (Token::BitXnor, "^~"),
+            (Token::BitXor, "^"),
+            (Token::Word, "d"),
+        ]
+    );
+}
+
+#[test]
+fn logical_and_shift_operators() {
+    assert_eq!(
+        lex("a && b || !c << 1 >> 2 <<< 3 >>> 4 != 5"),
+        vec![
+            (Token::Word, "a"),
+            (Token::LogAnd, "&&"),
+            (Token::Word, "b"),
+            (Token::LogOr, "||"),
+            (Token::LogicalNot, "!"),
+            (Token::Word, "c"),
+            (Token::SHL, "<<"),
+            (Token::Integer(1), "1"),
+            (Token::SHR, ">>"),
+            (Token::Integer(2), "2"),
+            (Token::ASHL, "<<<"),
+            (Token::Integer(3), "3"),
+            (Token::ASHR, ">>>"),
+            (Token::Integer(4), "4"),
+            (Token::BNEQ, "!="),
+            (Token::Integer(5), "5"),
+        ]
+    );
+}
+
+#[test]
+fn based_literals() {
+    let lit = |width, value| known_literal(width, value);
+
+    assert_eq!(
+        lex("32'hDEAD_BEEF 8'Hff 12'o7_7 6'O17 16'd65_535 'D10 'h1"),
+        vec![
+            (Token::HexValue(lit(32, 0xDEAD_BEEF)), "32'hDEAD_BEEF"),
+            (Token::HexValue(lit(8, 0xFF)), "8'Hff"),
+            (Token::OctalValue(lit(12, 0o77)), "12'o7_7"),
+            (Token::OctalValue(lit(6, 0o17)), "6'O17"),
+            (Token::DecimalValue(lit(16, 65_535)), "16'd65_535"),
+            (Token::DecimalValue(lit(32, 10)), "'D10"),
+            (Token::HexValue(lit(32, 1)), "'h1"),
+        ]
+    );
+}
+
+#[test]
+fn signed_based_literals() {
+    let signed = |width, value| SizedLiteral {
+        signed: true,
+        ..known_literal(width, value)
+    };
+
+    assert_eq!(
+        lex("4'sd3 8'SHff 'sb1 3'So7"),
+        vec![
+            (Token::DecimalValue(signed(4, 3)), "4'sd3"),
+            (Token::HexValue(signed(8, 0xFF)), "8'SHff"),
+            (Token::BinaryValue(signed(32, 1)), "'sb1"),
+            (Token::OctalValue(signed(3, 7)), "3'So7"),
+        ]
+    );
+}
+
+#[test]
+fn based_literal_truncated_to_width() {
+    let lit = |width, value| known_literal(width, value);
+
+    assert_eq!(
+        lex("4'hFF 128'hFFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF"),
+        vec![
+            (Token::HexValue(lit(4, 0xF)), "4'hFF"),
+            (
+                Token::HexValue(lit(128, u128::MAX)),
+                "128'hFFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn unsized_literals_widen_to_fit() {
+    let lit = |width, value| known_literal(width, value);
+
+    assert_eq!(
+        lex("'hFF_FFFF_FFFF 'b0_0000_0000_0000_0000_0000_0000_0000_0000_0001 'o1"),
+        vec![
+            (Token::HexValue(lit(40, 0xFF_FFFF_FFFF)), "'hFF_FFFF_FFFF"),
+            (
+                Token::BinaryValue(lit(32, 1)),
+                "'b0_0000_0000_0000_0000_0000_0000_0000_0000_0001"
+            ),
+            (Token::OctalValue(lit(32, 1)), "'o1"),
+        ]
+    );
+
+    let literal = parse_sized_literal("'hx_0000_0000").expect("literal parses");
+    assert_eq!(literal.width, 36);
+    assert_eq!(literal.bits[..4], [LogicState::X; 4]);
+}
+
+#[test]
+fn four_state_literal_digits() {
+    use LogicState::*;
+
+    let bits = |slice| parse_sized_literal(slice).expect("literal parses").bits;
+
+    assert_eq!(bits("4'b10xz"), vec![One, Zero, X, Z]);
+    assert_eq!(bits("4'B1?_0X"), vec![One, Z, Zero, X]);
+    assert_eq!(bits("8'hzz"), vec![Z; 8]);
+    assert_eq!(bits("8'h1x"), vec![Zero, Zero, Zero, One, X, X, X, X]);
+    assert_eq!(bits("6'o7z"), vec![One, One, One, Z, Z, Z]);
+    assert_eq!(bits("4'dx"), vec![X; 4]);
+    assert_eq!(bits("4'd9"), vec![One, Zero, Zero, One]);
+}
+
+#[test]
+fn unbased_literals() {
+    assert_eq!(
+        lex("'0 '1 'x 'Z '{"),
+        vec![
+            (Token::UnbasedValue(LogicState::Zero), "'0"),
+            (Token::UnbasedValue(LogicState::One), "'1"),
+            (Token::UnbasedValue(LogicState::X), "'x"),
+            (Token::UnbasedValue(LogicState::Z), "'Z"),
+            (Token::TickBrace, "'{"),
+        ]
+    );
+}
+
+#[test]
+fn four_state_literal_extension() {
+    use LogicState::*;
+
+    let bits = |slice| parse_sized_literal(slice).expect("literal parses").bits;
+
+    assert_eq!(bits("4'bx"), vec![X; 4]);
+    assert_eq!(bits("4'bz1"), vec![Z, Z, Z, One]);
+    assert_eq!(bits("4'b1x"), vec![Zero, Zero, One, X]);
+    assert_eq!(bits("2'b10xz"), vec![X, Z]);
+}
+
+#[test]
+fn four_state_literal_value_ignores_unknown_bits() {
+    let literal = parse_sized_literal("8'b1x1z_0001").expect("literal parses");
+
+    assert_eq!(literal.width, 8);
+    assert_eq!(literal.value, 0b1010_0001);
+}
+
+#[test]
+fn time_with_underscores() {
+    use sv_sim::sim_time::TimeUnit::*;
+
+    assert_eq!(
+        lex("1_000ns 2_5ps"),
+        vec![
+            (Token::Time(1000.0 * Nanoseconds.multiplier()), "1_000ns"),
+            (Token::Time(25.0 * Picoseconds.multiplier()), "2_5ps"),
+        ]
+    );
+}
+
+#[test]
+fn fractional_times_and_reals() {
+    use sv_sim::sim_time::TimeUnit::*;
+
+    assert_eq!(
+        lex("2.5ns 0.25 1_000.5"),
+        vec![
+            (Token::Time(2.5 * Nanoseconds.multiplier()), "2.5ns"),
+            (Token::Real(0.25), "0.25"),
+            (Token::Real(1000.5), "1_000.5"),
+        ]
+    );
+}
+
+#[test]
+fn time_units() {
+    use sv_sim::sim_time::TimeUnit::*;
+
+    assert_eq!(
+        lex("1s 10ms 100us 1ns 10ps 100fs"),
+        vec![
+            (Token::Time(Seconds.multiplier()), "1s"),
+            (Token::Time(10.0 * Milliseconds.multiplier()), "10ms"),
+            (Token::Time(100.0 * Microseconds.multiplier()), "100us"),
+            (Token::Time(Nanoseconds.multiplier()), "1ns"),
+            (Token::Time(10.0 * Picoseconds.multiplier()), "10ps"),
+            (Token::Time(100.0 * Femtoseconds.multiplier()), "100fs"),
+        ]
+    );
+}
+
+#[test]
+fn timescale_in_any_units() {
+    let object = sv_sim::parse_sv_file("`timescale 1us/10fs\nmodule m ();\nendmodule\n".to_owned())
+        .expect("source parses");
+
+    assert_eq!(object.sim_time.n_time, 1e-6);
+    assert_eq!(object.sim_time.d_time, 10.0 * 1e-15);
+    assert_eq!(object.sim_time.ratio().round(), 1e8);
+
+    let source = "`timescale 1 ms / 100 us\nmodule m ();\nendmodule\n";
+    let object = sv_sim::parse_sv_file(source.to_owned()).expect("source parses");
+    assert_eq!(object.sim_time.n_time, 1e-3);
+    assert_eq!(object.sim_time.ratio().round(), 10.0);
+}
+
+#[test]
+fn malformed_timescales() {
+    // Missing precisions, precisions coarser than units, multipliers other
+    // than 1, 10 or 100 and missing or unknown units
+    for timescale in ["1ns", "1ps/1ns", "2ns/1ps", "10/1ps", "1 xs/1ps"] {
+        let source = format!("`timescale {timescale}\nmodule m ();\nendmodule\n");
+        assert!(sv_sim::parse_sv_file(source).is_err(), "{timescale}");
+    }
+}
+
+#[test]
+fn durations() {
+    use sv_sim::sim_time::parse_duration;
+
+    assert_eq!(parse_duration("1000ns"), Ok(1000.0 * 1e-9));
+    assert_eq!(parse_duration(" 2_500ps"), Ok(2500.0 * 1e-12));
+    assert_eq!(parse_duration("3us"), Ok(3.0 * 1e-6));
+    assert_eq!(parse_duration("10 ns"), Ok(10.0 * 1e-9));
+
+    for text in ["", "1000", "ns", "10ns 5ns", "10xs", "10 xs", "10 ns ns", "10 5ns"] {
+        assert!(parse_duration(text).is_err(), "{text}");
+    }
+}
+
+#[test]
+fn binary_literal_values() {
+    let mut lexer = Token::lexer("4'b10xz");
+    let Some(Ok(Token::BinaryValue(literal))) = lexer.next() else {
+        panic!("binary literal lexes");
+    };
+    assert_eq!(literal.width, 4);
+    assert_eq!(literal.value, 0b1000);
+    assert_eq!(
+        literal.bits,
+        [
+            LogicState::One,
+            LogicState::Zero,
+            LogicState::X,
+            LogicState::Z
+        ]
+    );
+
+    assert_eq!(
+        lex("'b1"),
+        vec![(Token::BinaryValue(known_literal(32, 1)), "'b1")]
+    );
+}
+
+#[test]
+fn binary_literal_overflow() {
+    // Known bits above the 128th do not fit the value
+    let source = format!("200'b{}", "1".repeat(130));
+
+    assert!(matches!(
+        Token::lexer(&source).next(),
+        Some(Err(LexingError::InvalidInteger(_)))
+    ));
+}
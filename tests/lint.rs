@@ -0,0 +1,213 @@
+//! Design check tests
+
+use sv_sim::lint::{
+    compute_fanout, detect_combinational_loops, detect_inferred_latches, partially_assigned,
+};
+use sv_sim::module::Module;
+use sv_sim::{parse_sv_file, SimError};
+
+/// Parses a source holding a single module
+fn parse_module(source: &str) -> Module {
+    let object = parse_sv_file(source.to_owned()).expect("source parses");
+
+    object.mods.into_values().next().expect("source holds a module")
+}
+
+#[test]
+fn finds_combinational_loops() {
+    let module = parse_module(
+        "module loops (input wire a, input wire sel, output wire y);
+wire b;
+wire c;
+wire d;
+reg e;
+reg f;
+assign b = a & d;
+assign c = b;
+always_comb begin
+    if (sel)
+        e = f;
+    else
+        e = 1'b0;
+end
+always_comb f = e | a;
+assign d = c ^ a;
+assign y = d;
+endmodule
+",
+    );
+
+    assert_eq!(
+        detect_combinational_loops(&module),
+        [
+            vec!["b".to_owned(), "c".to_owned(), "d".to_owned()],
+            vec!["e".to_owned(), "f".to_owned()],
+        ]
+    );
+}
+
+#[test]
+fn registers_and_unrelated_assignments_break_loops() {
+    let module = parse_module(
+        "module no_loops (input wire clk, input wire p, output reg q, output reg x, output reg y);
+wire next;
+assign next = q ^ p;
+always_ff @(posedge clk) q <= next;
+always_comb begin
+    x = p;
+    y = q;
+end
+endmodule
+",
+    );
+
+    // `x` and `y` are written by the same block, but neither reads the other
+    assert!(detect_combinational_loops(&module).is_empty());
+}
+
+#[test]
+fn reports_loops_by_instance() {
+    let object = parse_sv_file(
+        "module ring (input wire a, output wire y);
+wire b;
+assign y = b & a;
+assign b = y;
+endmodule
+
+module top (input wire a, output wire y0, output wire y1);
+ring u0 (.a(a), .y(y0));
+ring u1 (.a(a), .y(y1));
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("design parses");
+    let design = object.elaborate("top").expect("design elaborates");
+
+    let loops: Vec<String> = design
+        .combinational_loops()
+        .iter()
+        .map(|err| match err {
+            SimError::CombinationalLoop(signals) => signals.join(" "),
+            _ => panic!("unexpected error '{err}'"),
+        })
+        .collect();
+    assert_eq!(loops, ["top.u0.b top.u0.y", "top.u1.b top.u1.y"]);
+
+    assert_eq!(
+        design.combinational_loops()[0].to_string(),
+        "combinational loop through top.u0.b, top.u0.y"
+    );
+}
+
+#[test]
+fn counts_fanout_by_block() {
+    let module = parse_module(
+        "module fan (input wire clk, input wire rst, input wire [3:0] a, input wire [3:0] b, output reg [3:0] q, output wire [3:0] y);
+parameter WIDTH = 4;
+reg [3:0] sum;
+assign y = a & b | a;
+always_comb begin
+    if (a[0])
+        sum = a + b;
+    else
+        sum = b;
+end
+always_ff @(posedge clk or posedge rst) begin
+    if (rst) q <= 0;
+    else q <= sum + WIDTH;
+end
+always_ff @(posedge clk) q <= a;
+endmodule
+",
+    );
+
+    let fanout = compute_fanout(&module);
+    assert_eq!(fanout.len(), 5);
+    assert_eq!(fanout["a"], 3);
+    assert_eq!(fanout["b"], 2);
+    assert_eq!(fanout["clk"], 2);
+    assert_eq!(fanout["rst"], 1);
+    assert_eq!(fanout["sum"], 1);
+    assert!(!fanout.contains_key("q"));
+    assert!(!fanout.contains_key("WIDTH"));
+}
+
+#[test]
+fn reports_high_fanout_nets_by_instance() {
+    let object = parse_sv_file(
+        "module stage (input wire en, input wire d, output wire y, output wire z);
+assign y = en & d;
+assign z = en | d;
+endmodule
+
+module top (input wire en, input wire d, output wire [2:0] y, output wire [2:0] z);
+assign y[2] = en;
+assign z[2] = d;
+stage u0 (.en(en), .d(d), .y(y[0]), .z(z[0]));
+stage u1 (.en(en), .d(d), .y(y[1]), .z(z[1]));
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("design parses");
+    let design = object.elaborate("top").expect("design elaborates");
+
+    assert_eq!(
+        design.high_fanout_nets(1),
+        [
+            ("top.u0.d".to_owned(), 2),
+            ("top.u0.en".to_owned(), 2),
+            ("top.u1.d".to_owned(), 2),
+            ("top.u1.en".to_owned(), 2),
+        ]
+    );
+    assert_eq!(design.high_fanout_nets(0).len(), 6);
+    assert!(design.high_fanout_nets(2).is_empty());
+}
+
+#[test]
+fn finds_signals_missing_from_some_branches() {
+    let module = parse_module(
+        "module latches (input wire en, input wire [1:0] sel, input wire d, output reg q, output reg y, output reg z, output reg w);
+always_latch begin
+    if (en)
+        q = d;
+end
+always_comb begin
+    y = 1'b0;
+    if (en) begin
+        y = d;
+        z = d;
+    end else if (sel[0])
+        z = ~d;
+    else
+        z = 1'b0;
+    case (sel)
+        2'd0: w = d;
+        2'd1: begin w = en; q = d; end
+    endcase
+end
+always_comb begin
+    case (sel)
+        2'd0: y = d;
+        default: y = en;
+    endcase
+    for (int i = 0; i < 2; i++)
+        w = d;
+end
+endmodule
+",
+    );
+
+    assert_eq!(module.latch_blocks.len(), 1);
+    assert_eq!(partially_assigned(&module.latch_blocks[0].body), ["q"]);
+    assert_eq!(detect_inferred_latches(&module), ["q", "w", "w"]);
+
+    // Latches may not wait any more than combinational blocks may
+    let source = "module m (input wire en, input wire d, output reg q);
+always_latch if (en) #1 q = d;
+endmodule
+";
+    assert!(parse_sv_file(source.to_owned()).is_err());
+}
@@ -0,0 +1,229 @@
+//! Memory and `$readmem` tests
+
+use std::fs;
+use sv_sim::parse_sv_file;
+use sv_sim::sim::SimResult;
+use sv_sim::sys_task::parse_mem_file;
+use sv_sim::value::{LogicState, LogicValue};
+use sv_sim::{LexingError, SimError};
+
+/// Parses `source` and simulates it for ten nanoseconds
+fn simulate(source: &str) -> SimResult {
+    let object = parse_sv_file(source.to_owned()).expect("source parses");
+
+    object.run(10e-9)
+}
+
+/// Final value of `signal` as an integer
+fn value(result: &SimResult, signal: &str) -> Option<u128> {
+    result.signals[signal].to_u128()
+}
+
+#[test]
+fn memories_are_read_and_written() {
+    let result = simulate(
+        "module ram ();
+reg [7:0] mem [0:3];
+reg [1:0] addr;
+wire [7:0] data;
+assign data = mem[addr];
+initial begin
+    mem[0] = 8'd10;
+    mem[1] = 8'd200;
+    addr = 1;
+    #1 mem[1] = mem[0] + 8'd5;
+end
+endmodule
+",
+    );
+
+    assert_eq!(value(&result, "ram.mem[0]"), Some(10));
+    assert_eq!(value(&result, "ram.mem[1]"), Some(15));
+    assert_eq!(value(&result, "ram.mem[2]"), None);
+    assert!(!result.signals.contains_key("ram.mem"));
+
+    // The continuous assignment follows writes to the memory
+    let data: Vec<(f64, Option<u128>)> = result
+        .changes
+        .iter()
+        .filter(|change| change.signal == "ram.data")
+        .map(|change| (change.time, change.value.to_u128()))
+        .collect();
+    assert_eq!(data, [(0.0, None), (0.0, Some(200)), (1e-9, Some(15))]);
+}
+
+#[test]
+fn parses_memory_files() {
+    let text = "// header
+a5 0F /* skipped
+ff */ x_1
+@10 7
+@1_1 zz 1ff";
+    let values: Vec<(u64, String)> = parse_mem_file(text, true, 8, 2, false)
+        .expect("file parses")
+        .into_iter()
+        .map(|(address, value)| (address, value.to_string()))
+        .collect();
+    assert_eq!(
+        values,
+        [
+            (2, "10100101".to_owned()),
+            (3, "00001111".to_owned()),
+            (4, "xxxx0001".to_owned()),
+            (16, "00000111".to_owned()),
+            (17, "zzzzzzzz".to_owned()),
+            (18, "11111111".to_owned()),
+        ]
+    );
+
+    let values = parse_mem_file("1010\n0x1_1\n", false, 4, 0, false).expect("file parses");
+    let mut unknown = LogicValue::from_u128(0b0011, 4);
+    unknown.set_bit(2, LogicState::X);
+    assert_eq!(
+        values,
+        [(0, LogicValue::from_u128(0b1010, 4)), (1, unknown)]
+    );
+
+    let addresses: Vec<u64> = parse_mem_file("1 0 1\n@5 0", false, 1, 2, true)
+        .expect("file parses")
+        .into_iter()
+        .map(|(address, _)| address)
+        .collect();
+    assert_eq!(addresses, [2, 1, 0, 5]);
+
+    assert_eq!(
+        parse_mem_file("10 12", false, 4, 0, false),
+        Err(LexingError::InvalidInteger("12".to_owned()))
+    );
+    assert_eq!(
+        parse_mem_file("@g 1", true, 4, 0, false),
+        Err(LexingError::InvalidInteger("@g".to_owned()))
+    );
+}
+
+#[test]
+fn readmem_loads_memories() {
+    let dir = std::env::temp_dir().join(format!("sv_sim_readmem_{}", std::process::id()));
+    fs::create_dir_all(&dir).expect("scratch directory is created");
+    let hex = dir.join("rom.hex");
+    let bin = dir.join("rom.bin");
+    fs::write(&hex, "01 02\n@6 ab cd ef\n").expect("hex file is written");
+    fs::write(&bin, "1111 0000 1010\n").expect("binary file is written");
+
+    let result = simulate(&format!(
+        "module rom ();
+reg [7:0] hex [15:4];
+reg [3:0] bin [0:7];
+reg [3:0] rev [0:7];
+initial begin
+    $readmemh(\"{}\", hex);
+    $readmemb(\"{}\", bin, 2, 3);
+    $readmemb(\"{}\", rev, 6, 5);
+end
+endmodule
+",
+        hex.display(),
+        bin.display(),
+        bin.display()
+    ));
+    fs::remove_dir_all(dir).expect("scratch directory is removed");
+
+    let loaded = |memory: &str, addresses: std::ops::Range<u64>| -> Vec<Option<u128>> {
+        addresses
+            .map(|address| value(&result, &format!("rom.{memory}[{address}]")))
+            .collect()
+    };
+    assert_eq!(
+        loaded("hex", 4..10),
+        [
+            Some(0x01),
+            Some(0x02),
+            Some(0xab),
+            Some(0xcd),
+            Some(0xef),
+            None
+        ]
+    );
+    assert_eq!(loaded("bin", 1..5), [None, Some(0b1111), Some(0), None]);
+    assert_eq!(loaded("rev", 4..8), [None, Some(0), Some(0b1111), None]);
+}
+
+#[test]
+fn memory_contents_are_read_back() {
+    let dir = std::env::temp_dir().join(format!("sv_sim_get_memory_{}", std::process::id()));
+    fs::create_dir_all(&dir).expect("scratch directory is created");
+    let hex = dir.join("regs.hex");
+    fs::write(&hex, "a 5\n@3 f\n").expect("hex file is written");
+
+    let result = simulate(&format!(
+        "module regfile ();
+reg [3:0] regs [0:3];
+reg [3:0] flag;
+initial $readmemh(\"{}\", regs);
+endmodule
+",
+        hex.display()
+    ));
+    fs::remove_dir_all(dir).expect("scratch directory is removed");
+
+    let word = |value: u128| LogicValue::from_u128(value, 4).bits().to_vec();
+    let unset = vec![LogicState::X; 4];
+    assert_eq!(
+        result.get_memory("regfile.regs").expect("memory exists"),
+        [word(0xa), word(0x5), unset, word(0xf)]
+    );
+    assert_eq!(
+        result
+            .get_memory_word("regfile.regs", 1)
+            .expect("address is in range"),
+        word(0x5)
+    );
+
+    assert!(matches!(
+        result.get_memory_word("regfile.regs", 4),
+        Err(SimError::AddressOutOfRange { addr: 4, .. })
+    ));
+    assert!(matches!(
+        result.get_memory("regfile.flag"),
+        Err(SimError::UndefinedSignal(ref name)) if name == "regfile.flag"
+    ));
+}
+
+#[test]
+fn array_literals_fill_memories() {
+    let result = simulate(
+        "module lut ();
+reg [7:0] table [0:3] = '{8'd1, 8'd2, 4, 8'hff};
+reg [3:0] small [2:1];
+reg [3:0] up [1:2];
+reg [7:0] filled [0:1] = '{default: 8'h5a};
+reg [11:0] packed;
+reg [3:0] ones;
+initial begin
+    #1 small = '{5, 6};
+    up = '{5, 6};
+    packed = '{hi: 4'ha, mid: 4'h0, lo: 4'h5};
+    ones = '{default: 1};
+end
+endmodule
+",
+    );
+
+    let table: Vec<Option<u128>> = (0..4)
+        .map(|address| value(&result, &format!("lut.table[{address}]")))
+        .collect();
+    assert_eq!(table, [Some(1), Some(2), Some(4), Some(0xff)]);
+
+    // The first value goes to the left bound of the address range
+    assert_eq!(value(&result, "lut.small[2]"), Some(5));
+    assert_eq!(value(&result, "lut.small[1]"), Some(6));
+    assert_eq!(value(&result, "lut.up[1]"), Some(5));
+    assert_eq!(value(&result, "lut.up[2]"), Some(6));
+
+    assert_eq!(value(&result, "lut.filled[0]"), Some(0x5a));
+    assert_eq!(value(&result, "lut.filled[1]"), Some(0x5a));
+    assert_eq!(value(&result, "lut.ones"), Some(0xf));
+
+    // Structure fields are packed in order, the first most significant
+    assert_eq!(value(&result, "lut.packed"), Some(0xa05));
+}
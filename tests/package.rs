@@ -0,0 +1,198 @@
+//! Package declaration and import tests
+
+use sv_sim::{parse_sv_file, ElabError, SimError};
+
+/// Package shared by the tests
+const PKG: &str = "package cfg_pkg;
+parameter WIDTH = 4;
+localparam MAX = (1 << WIDTH) - 1;
+function automatic integer twice(input integer x);
+    twice = 2 * x;
+endfunction
+endpackage : cfg_pkg
+";
+
+#[test]
+fn parses_package_declarations() {
+    let object = parse_sv_file(PKG.to_owned()).expect("source parses");
+    let package = object.package("cfg_pkg").expect("package is declared");
+
+    let params: Vec<&str> = package.params.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(params, ["WIDTH", "MAX"]);
+    assert_eq!(package.functions[0].name, "twice");
+    assert_eq!(package.param_values()["MAX"].to_u128(), Some(15));
+    assert!(package.declares("twice"));
+    assert!(!package.declares("DEPTH"));
+
+    let source = "package cfg_pkg;
+endpackage : other_pkg
+";
+    assert!(parse_sv_file(source.to_owned()).is_err());
+
+    let source = format!("{PKG}{PKG}");
+    let Err(SimError::DuplicatePackage(name)) = parse_sv_file(source) else {
+        panic!("package is declared twice");
+    };
+    assert_eq!(name, "cfg_pkg");
+}
+
+#[test]
+fn wildcard_imports_add_every_item() {
+    let source = format!(
+        "{PKG}
+module top;
+import cfg_pkg::*;
+logic [WIDTH-1:0] count;
+initial begin
+    count = MAX;
+    $display(\"%0d %0d %0d\", $bits(count), count, twice(WIDTH));
+end
+endmodule
+"
+    );
+    let object = parse_sv_file(source).expect("design parses");
+    let design = object.elaborate("top").expect("design elaborates");
+    assert_eq!(design.run(1e-9).output, "4 15 8\n");
+}
+
+#[test]
+fn local_declarations_hide_wildcard_imports() {
+    let source = format!(
+        "{PKG}
+module top;
+import cfg_pkg::*;
+localparam WIDTH = 2;
+initial $display(\"%0d %0d\", WIDTH, MAX);
+endmodule
+"
+    );
+    let object = parse_sv_file(source).expect("design parses");
+    let design = object.elaborate("top").expect("design elaborates");
+    assert_eq!(design.run(1e-9).output, "2 15\n");
+}
+
+#[test]
+fn single_item_imports_add_only_that_item() {
+    let source = format!(
+        "{PKG}
+module top;
+import cfg_pkg::WIDTH;
+initial $display(\"%0d\", WIDTH);
+endmodule
+
+module other;
+import cfg_pkg::WIDTH;
+endmodule
+"
+    );
+    let object = parse_sv_file(source).expect("design parses");
+    let design = object.elaborate("top").expect("design elaborates");
+    assert_eq!(design.run(1e-9).output, "4\n");
+
+    let module = object
+        .resolve_imports(&object.mods["other"])
+        .expect("imports resolve");
+    let params: Vec<&str> = module.io.params.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(params, ["WIDTH"]);
+    assert!(module.functions.is_empty());
+}
+
+#[test]
+fn imports_must_resolve() {
+    let source = format!(
+        "{PKG}
+module missing_package;
+import bus_pkg::*;
+endmodule
+
+module missing_item;
+import cfg_pkg::DEPTH;
+endmodule
+"
+    );
+    let object = parse_sv_file(source).expect("design parses");
+
+    assert_eq!(
+        object.elaborate("missing_package").err(),
+        Some(ElabError::UndefinedPackage("bus_pkg".to_owned()))
+    );
+    assert_eq!(
+        object.elaborate("missing_item").err(),
+        Some(ElabError::UndefinedImport {
+            package: "cfg_pkg".to_owned(),
+            item: "DEPTH".to_owned(),
+        })
+    );
+}
+
+#[test]
+fn imports_conflict_with_local_declarations() {
+    let source = format!(
+        "{PKG}
+module top;
+import cfg_pkg::WIDTH;
+logic [3:0] WIDTH;
+endmodule
+"
+    );
+    let object = parse_sv_file(source).expect("design parses");
+
+    let err = object.elaborate("top").expect_err("import conflicts");
+    assert_eq!(
+        err,
+        ElabError::ImportConflict("WIDTH".to_owned(), "cfg_pkg".to_owned())
+    );
+    let SimError::ImportConflict(name, package) = SimError::from(err) else {
+        panic!("conflicts are reported as import conflicts");
+    };
+    assert_eq!((name.as_str(), package.as_str()), ("WIDTH", "cfg_pkg"));
+}
+
+#[test]
+fn scoped_names_read_items_of_their_package() {
+    let source = format!(
+        "{PKG}
+package bus_pkg;
+parameter WIDTH = 16;
+endpackage
+
+module top (input wire [bus_pkg::WIDTH-1:0] data);
+import cfg_pkg::*;
+wire [7:0] y;
+assign y = cfg_pkg::MAX;
+initial #1 $display(\"%0d %0d %0d %0d\", y, cfg_pkg::twice(3), bus_pkg::WIDTH, $bits(data));
+endmodule
+"
+    );
+    let object = parse_sv_file(source).expect("design parses");
+    let design = object.elaborate("top").expect("design elaborates");
+    assert_eq!(design.run(2e-9).output, "15 6 16 16\n");
+}
+
+#[test]
+fn scoped_names_must_resolve() {
+    let source = format!(
+        "{PKG}
+module missing_package;
+initial $display(\"%0d\", bus_pkg::WIDTH);
+endmodule
+
+module missing_item;
+wire [cfg_pkg::DEPTH-1:0] data;
+endmodule
+"
+    );
+    let object = parse_sv_file(source).expect("design parses");
+
+    assert_eq!(
+        object.elaborate("missing_package").err(),
+        Some(ElabError::UndefinedPackage("bus_pkg".to_owned()))
+    );
+    assert_eq!(
+        object.elaborate("missing_item").err(),
+        Some(ElabError::UndefinedImport {
+            package: "cfg_pkg".to_owned(),
+            item: "DEPTH".to_owned(),
+        })
+    );
+}
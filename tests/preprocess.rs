@@ -0,0 +1,576 @@
+//! Preprocessor tests
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use sv_sim::preprocess::{Preprocessor, MAX_INCLUDE_DEPTH};
+use sv_sim::{parse_sv_file, parse_sv_files_with, IncludeError, PreprocessError, SimError};
+
+/// Preprocessor with macros defined from `NAME=VALUE` arguments
+fn preprocessor(defines: &[&str]) -> Preprocessor {
+    let mut preprocessor = Preprocessor::new();
+    for define in defines {
+        preprocessor
+            .define_arg(define)
+            .expect("definition is valid");
+    }
+
+    preprocessor
+}
+
+/// Creates an empty scratch directory unique to `test`
+fn scratch_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sv_sim_{}_{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("scratch directory is created");
+
+    dir
+}
+
+/// Writes `contents` to `name` within `dir`, creating any parent directories
+fn write_file(dir: &Path, name: &str, contents: &str) {
+    let path = dir.join(name);
+    fs::create_dir_all(path.parent().expect("path has a parent")).expect("directory is created");
+    fs::write(path, contents).expect("file is written");
+}
+
+#[test]
+fn command_line_definitions() {
+    let preprocessor = preprocessor(&["SIMULATION", "WIDTH=8", "EMPTY=", "EXPR=a + b"]);
+
+    let body = |name: &str| preprocessor.defines[name].body.as_str();
+    assert_eq!(body("SIMULATION"), "1");
+    assert_eq!(body("WIDTH"), "8");
+    assert_eq!(body("EMPTY"), "");
+    assert_eq!(body("EXPR"), "a + b");
+
+    // Macros with arguments may be given too
+    let mut functions = self::preprocessor(&["MAX(a, b)=((a) > (b) ? (a) : (b))", "NONE()=0"]);
+    assert_eq!(
+        functions.preprocess("assign y = `MAX(x, `NONE());\n"),
+        Ok("assign y = ((x) > (0) ? (x) : (0));\n".to_owned())
+    );
+
+    for arg in [
+        "", "=1", "2FAST", "A B=1", "A-B", "F(1)=x", "F(a=1", "F (a)=a", "F(a)b=1",
+    ] {
+        assert_eq!(
+            Preprocessor::new().define_arg(arg),
+            Err(PreprocessError::InvalidDefine(arg.to_owned())),
+            "{arg}"
+        );
+    }
+}
+
+#[test]
+fn expands_defined_macros() {
+    let mut preprocessor = preprocessor(&["WIDTH=8", "RESET"]);
+
+    assert_eq!(
+        preprocessor.preprocess("`timescale 1ns/1ps\nwire [`WIDTH-1:0] a = `RESET;\n"),
+        Ok("`timescale 1ns/1ps\nwire [8-1:0] a = 1;\n".to_owned())
+    );
+}
+
+#[test]
+fn comments_and_strings_are_left_alone() {
+    let mut preprocessor = preprocessor(&["A=1"]);
+
+    let source = "// `UNDEFINED\n/* `UNDEFINED\n*/ $display(\"`UNDEFINED \\\" `A\"); `A / 2";
+    assert_eq!(
+        preprocessor.preprocess(source),
+        Ok("// `UNDEFINED\n/* `UNDEFINED\n*/ $display(\"`UNDEFINED \\\" `A\"); 1 / 2".to_owned())
+    );
+}
+
+#[test]
+fn undefined_macros_are_reported() {
+    assert_eq!(
+        Preprocessor::new().preprocess("assign a = `MISSING;"),
+        Err(PreprocessError::UndefinedMacro("MISSING".to_owned()))
+    );
+
+    let err = parse_sv_file("module m ();\nwire [`W:0] a;\nendmodule\n".to_owned())
+        .expect_err("W is not defined");
+    assert_eq!(err.to_string(), "macro 'W' is not defined");
+}
+
+#[test]
+fn include_directories_are_searched_in_order() {
+    let dir = scratch_dir("include_order");
+    write_file(&dir, "first/defs.svh", "");
+    write_file(&dir, "second/defs.svh", "");
+    write_file(&dir, "second/sub/types.svh", "");
+
+    let mut preprocessor = Preprocessor::new();
+    preprocessor.add_include_dir(&dir.join("missing"));
+    preprocessor.add_include_dir(&dir.join("first"));
+    preprocessor.add_include_dir(&dir.join("second"));
+
+    assert_eq!(
+        preprocessor.find_include("defs.svh"),
+        Ok(dir.join("first").join("defs.svh"))
+    );
+    assert_eq!(
+        preprocessor.find_include("sub/types.svh"),
+        Ok(dir.join("second").join("sub/types.svh"))
+    );
+    assert_eq!(
+        preprocessor.find_include("other.svh"),
+        Err(IncludeError::NotFound("other.svh".to_owned()))
+    );
+
+    // Directories are not files
+    assert!(preprocessor.find_include("sub").is_err());
+
+    fs::remove_dir_all(dir).expect("scratch directory is removed");
+}
+
+#[test]
+fn source_definitions() {
+    let mut preprocessor = Preprocessor::new();
+
+    let source = "`define WIDTH 8 // bus width
+`define TWICE(x) (2 * (x))
+`define MAX(a, b) ((a) > (b) ? \\
+    (a) : (b))
+wire [`WIDTH-1:0] a = `MAX(`TWICE(b), f(c, d));
+`undef WIDTH
+`define WIDTH 16
+wire [`WIDTH-1:0] e = `TWICE( \"a,b\" );
+";
+    assert_eq!(
+        preprocessor.preprocess(source),
+        Ok("
+
+
+
+wire [8-1:0] a = (((2 * (b))) > (f(c, d)) ? \n    ((2 * (b))) : (f(c, d)));
+
+
+wire [16-1:0] e = (2 * (\"a,b\"));
+"
+        .to_owned())
+    );
+
+    let mac = &preprocessor.defines["TWICE"];
+    assert_eq!(mac.params, Some(vec!["x".to_owned()]));
+    assert_eq!(mac.body, "(2 * (x))");
+}
+
+#[test]
+fn macros_used_in_macros() {
+    let mut preprocessor = Preprocessor::new();
+
+    let source = "`define INNER(v) v + 1
+`define OUTER(v) `INNER(v) * 2
+`define NONE() 0
+assign a = `OUTER(x) + `NONE();
+";
+    assert_eq!(
+        preprocessor.preprocess(source),
+        Ok("\n\n\nassign a = x + 1 * 2 + 0;\n".to_owned())
+    );
+
+    let err = Preprocessor::new()
+        .preprocess("`define PAIR(a, b) a, b\n`PAIR(1)\n")
+        .expect_err("PAIR takes two arguments");
+    assert_eq!(
+        err,
+        PreprocessError::MacroArguments {
+            name: "PAIR".to_owned(),
+            expected: 2,
+            got: 1,
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "macro 'PAIR' takes 2 arguments, but is given 1"
+    );
+
+    assert_eq!(
+        Preprocessor::new().preprocess("`define 2BAD 1\n"),
+        Err(PreprocessError::InvalidDefine("2BAD 1".to_owned()))
+    );
+}
+
+#[test]
+fn macro_arguments_may_have_defaults() {
+    let mut preprocessor = preprocessor(&["INC(x, by=1)=x + by"]);
+
+    let source = "`define ADD(a, b=1, c=f(2, 3)) a + b + c
+`define EMPTY(a=) [a]
+assign x = `ADD(y) - `ADD(y, 4) - `ADD(y, , z) - `INC(w);
+assign z = `EMPTY() + `EMPTY(1);
+";
+    assert_eq!(
+        preprocessor.preprocess(source),
+        Ok("
+
+assign x = y + 1 + f(2, 3) - y + 4 + f(2, 3) - y + 1 + z - w + 1;
+assign z = [] + [1];
+"
+        .to_owned())
+    );
+    assert_eq!(
+        preprocessor.defines["ADD"].defaults,
+        [None, Some("1".to_owned()), Some("f(2, 3)".to_owned())]
+    );
+
+    // Arguments without defaults must still be given, if only as empty text
+    assert_eq!(
+        preprocessor.preprocess("`ADD()\n"),
+        Ok(" + 1 + f(2, 3)\n".to_owned())
+    );
+    assert_eq!(
+        preprocessor.preprocess("`define FIRST(a=1, b) a b\n`FIRST(2)\n"),
+        Err(PreprocessError::MacroArguments {
+            name: "FIRST".to_owned(),
+            expected: 2,
+            got: 1,
+        })
+    );
+}
+
+#[test]
+fn macros_paste_and_quote_text() {
+    let mut preprocessor = Preprocessor::new();
+
+    let source = "`define REG(name) reg_``name
+`define STR(x) `\"x`\"
+`define SAY(x) `\"say `\\`\"x`\\`\"`\"
+`define JOINED a``b
+wire `REG(valid) = `JOINED;
+initial $display(`STR(hello), `SAY(hi), \"`x\");
+";
+    assert_eq!(
+        preprocessor.preprocess(source),
+        Ok("
+
+
+
+wire reg_valid = ab;
+initial $display(\"hello\", \"say \\\"hi\\\"\", \"`x\");
+"
+        .to_owned())
+    );
+}
+
+#[test]
+fn recursive_macros_are_reported() {
+    assert_eq!(
+        Preprocessor::new().preprocess("`define LOOP `LOOP\nwire a = `LOOP;\n"),
+        Err(PreprocessError::RecursiveMacro(vec![
+            "LOOP".to_owned(),
+            "LOOP".to_owned()
+        ]))
+    );
+
+    let err = Preprocessor::new()
+        .preprocess("`define A(x) `B(x)\n`define B(x) `C + x\n`define C `A(1)\n`A(2)\n")
+        .expect_err("A uses itself through B and C");
+    assert_eq!(
+        err.to_string(),
+        "recursive macro expansion: A -> B -> C -> A"
+    );
+
+    // Using a macro twice within another is not recursion
+    let mut preprocessor = preprocessor(&["ONE=1"]);
+    assert_eq!(
+        preprocessor.preprocess("`define TWO `ONE + `ONE\n`TWO\n"),
+        Ok("\n1 + 1\n".to_owned())
+    );
+}
+
+#[test]
+fn parses_sources_using_macros() {
+    let object = parse_sv_file(
+        "`define BITS(msb) [msb:0]
+module m ();
+reg `BITS(3) count;
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+
+    assert_eq!(object.mods["m"].vars[0].width, 4);
+}
+
+#[test]
+fn conditional_blocks() {
+    let source = "`ifdef SYNTHESIS
+wire a;
+`elsif SIMULATION
+wire b;
+`ifndef FAST
+wire c;
+`else
+wire d;
+`endif
+`else
+wire e;
+`endif
+";
+    let kept = |defines: &[&str]| -> Vec<String> {
+        let out = preprocessor(defines)
+            .preprocess(source)
+            .expect("source preprocesses");
+        assert_eq!(out.lines().count(), source.lines().count());
+
+        out.lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_owned)
+            .collect()
+    };
+
+    assert_eq!(kept(&["SYNTHESIS", "SIMULATION"]), ["wire a;"]);
+    assert_eq!(kept(&["SIMULATION"]), ["wire b;", "wire c;"]);
+    assert_eq!(kept(&["SIMULATION", "FAST"]), ["wire b;", "wire d;"]);
+    assert_eq!(kept(&["FAST"]), ["wire e;"]);
+}
+
+#[test]
+fn conditions_may_be_macro_expressions() {
+    let source = "`ifdef (A && !B)
+wire a;
+`elsif ( (B || C) && !(A -> C) )
+wire b;
+`endif
+`ifndef (A <-> B) wire c; `endif
+`ifdef (A -> B -> C) wire d; `endif
+";
+    let kept = |defines: &[&str]| -> String {
+        let out = preprocessor(defines)
+            .preprocess(source)
+            .expect("source preprocesses");
+
+        out.split_whitespace().collect::<Vec<_>>().join(" ")
+    };
+
+    assert_eq!(kept(&[]), "wire d;");
+    assert_eq!(kept(&["A"]), "wire a; wire c; wire d;");
+    assert_eq!(kept(&["A", "B"]), "wire b;");
+    assert_eq!(kept(&["A", "B", "C"]), "wire d;");
+    assert_eq!(kept(&["B"]), "wire c; wire d;");
+
+    for expr in ["(A &&)", "(A B)", "(A || (B)", "()", "(!)"] {
+        assert_eq!(
+            Preprocessor::new().preprocess(&format!("`ifdef {expr}\n`endif\n")),
+            Err(PreprocessError::InvalidMacroExpression(expr.to_owned())),
+            "{expr}"
+        );
+    }
+}
+
+#[test]
+fn skipped_blocks_are_not_preprocessed() {
+    let mut preprocessor = Preprocessor::new();
+
+    let source = "`define A
+`ifndef A
+`define B
+assign x = `UNDEFINED;
+`endif
+`ifdef B `UNDEFINED `else b `endif
+// `endif
+`ifdef A a `endif
+";
+    assert_eq!(
+        preprocessor.preprocess(source),
+        Ok("\n\n\n\n\n b \n// `endif\n a \n".to_owned())
+    );
+    assert!(!preprocessor.defines.contains_key("B"));
+}
+
+#[test]
+fn mismatched_conditionals_are_reported() {
+    for (source, err) in [
+        (
+            "`endif\n",
+            PreprocessError::UnmatchedConditional("endif".to_owned()),
+        ),
+        (
+            "`ifdef A\n`else\n`elsif B\n`endif\n",
+            PreprocessError::UnmatchedConditional("elsif".to_owned()),
+        ),
+        (
+            "`ifdef A\n`ifdef B\n`endif\n",
+            PreprocessError::UnterminatedConditional,
+        ),
+        (
+            "`ifndef\n`endif\n",
+            PreprocessError::MissingMacroName("ifndef".to_owned()),
+        ),
+    ] {
+        assert_eq!(Preprocessor::new().preprocess(source), Err(err), "{source}");
+    }
+
+    let err = Preprocessor::new()
+        .preprocess("`else\n")
+        .expect_err("there is no open block");
+    assert_eq!(err.to_string(), "'`else' without a matching '`ifdef'");
+}
+
+#[test]
+fn includes_files() {
+    let dir = scratch_dir("includes");
+    write_file(
+        &dir,
+        "rtl/top.sv",
+        "`include \"defs.svh\"\nwire [`WIDTH-1:0] a;\n`include \"common.svh\" // shared\n",
+    );
+    write_file(&dir, "rtl/defs.svh", "`define WIDTH 8\n");
+    write_file(
+        &dir,
+        "include/common.svh",
+        "`ifdef WIDTH\nwire [`WIDTH:0] b;\n`endif\n",
+    );
+
+    let mut preprocessor = Preprocessor::new();
+    preprocessor.add_include_dir(&dir.join("include"));
+
+    let path = dir.join("rtl/top.sv");
+    let source = fs::read_to_string(&path).expect("top is read");
+    assert_eq!(
+        preprocessor.preprocess_file(&path, &source),
+        Ok("\n\nwire [8-1:0] a;\n\nwire [8:0] b;\n\n // shared\n".to_owned())
+    );
+
+    // Files are only searched for next to the file including them
+    assert_eq!(
+        preprocessor.preprocess("`include \"defs.svh\"\n"),
+        Err(PreprocessError::Include(IncludeError::NotFound(
+            "defs.svh".to_owned()
+        )))
+    );
+    for text in ["`include defs.svh\n", "`include <defs.svh\n>\n"] {
+        assert_eq!(
+            preprocessor.preprocess(text),
+            Err(PreprocessError::Include(IncludeError::MissingFileName)),
+            "{text}"
+        );
+    }
+
+    // Names in angle brackets are only searched for in include directories
+    let source = "`include <common.svh>\n`include <defs.svh>\n";
+    assert_eq!(
+        preprocessor.preprocess_file(&path, source),
+        Err(PreprocessError::Include(IncludeError::NotFound(
+            "defs.svh".to_owned()
+        )))
+    );
+    assert_eq!(
+        preprocessor.preprocess_file(&path, "`include <common.svh>\n"),
+        Ok("\nwire [8:0] b;\n\n\n".to_owned())
+    );
+
+    // File names may be given by macros
+    let source = "`define COMMON <common.svh>
+`define SVH(name) `\"name.svh`\"
+`include `COMMON
+`include `SVH(defs) // defs
+";
+    assert_eq!(
+        preprocessor.preprocess_file(&path, source),
+        Ok("\n\n\nwire [8:0] b;\n\n\n\n // defs\n".to_owned())
+    );
+    for text in [
+        "`define NAME defs.svh\n`include `NAME\n",
+        "`define NAME \"defs.svh\" x\n`include `NAME\n",
+    ] {
+        assert_eq!(
+            preprocessor.preprocess_file(&path, text),
+            Err(PreprocessError::Include(IncludeError::MissingFileName)),
+            "{text}"
+        );
+    }
+
+    fs::remove_dir_all(dir).expect("scratch directory is removed");
+}
+
+#[test]
+fn circular_and_deep_includes_are_reported() {
+    let dir = scratch_dir("circular_includes");
+    write_file(&dir, "a.svh", "`include \"b.svh\"\n");
+    write_file(&dir, "b.svh", "`include \"a.svh\"\n");
+    for level in 0..=MAX_INCLUDE_DEPTH {
+        let next = format!("`include \"deep_{}.svh\"\n", level + 1);
+        write_file(&dir, &format!("deep_{level}.svh"), &next);
+    }
+
+    let mut preprocessor = Preprocessor::new();
+    preprocessor.add_include_dir(&dir);
+
+    let err = preprocessor
+        .preprocess("`include \"a.svh\"\n")
+        .expect_err("a and b include each other");
+    let a = dir.join("a.svh").display().to_string();
+    let b = dir.join("b.svh").display().to_string();
+    assert_eq!(
+        err,
+        PreprocessError::Include(IncludeError::Circular(vec![
+            a.clone(),
+            b.clone(),
+            a.clone()
+        ]))
+    );
+    assert_eq!(
+        err.to_string(),
+        format!("circular include: {a} -> {b} -> {a}")
+    );
+
+    let err = preprocessor
+        .preprocess("`include \"deep_0.svh\"\n")
+        .expect_err("the chain is too deep");
+    assert_eq!(
+        err,
+        PreprocessError::Include(IncludeError::TooDeep(format!(
+            "deep_{MAX_INCLUDE_DEPTH}.svh"
+        )))
+    );
+
+    fs::remove_dir_all(dir).expect("scratch directory is removed");
+}
+
+#[test]
+fn parse_errors_are_located_in_the_original_files() {
+    let dir = scratch_dir("error_locations");
+    write_file(&dir, "ports.svh", "wire q;\n\nwire r = +;\n");
+    write_file(&dir, "empty.svh", "// nothing\n\n\n");
+    write_file(
+        &dir,
+        "top.sv",
+        "`define DECL wire b; \\\n  wire c;\nmodule top (input wire a);\n`include \"empty.svh\"\n`DECL\n\nwire e = +;\nendmodule\n",
+    );
+    write_file(
+        &dir,
+        "inc.sv",
+        "module inc (input wire a);\n`include \"ports.svh\"\nendmodule\n",
+    );
+
+    let mut preprocessor = Preprocessor::new();
+    let Err(SimError::Lex(err)) = parse_sv_files_with(&[dir.join("top.sv")], &mut preprocessor)
+    else {
+        panic!("top.sv does not parse");
+    };
+    assert_eq!(err.location(), Some((7, 11)));
+    assert_eq!(err.file(), None);
+    assert_eq!(
+        preprocessor.source_line(4),
+        (Some(dir.join("empty.svh").as_path()), 1)
+    );
+
+    let Err(SimError::Lex(err)) = parse_sv_files_with(&[dir.join("inc.sv")], &mut preprocessor)
+    else {
+        panic!("ports.svh does not parse");
+    };
+    assert_eq!(err.location(), Some((3, 11)));
+    let ports = dir.join("ports.svh");
+    assert_eq!(err.file(), Some(ports.as_path()));
+    assert_eq!(
+        err.to_string(),
+        format!("{}:3:11: unexpected token encountered", ports.display())
+    );
+
+    fs::remove_dir_all(dir).expect("scratch directory is removed");
+}
@@ -0,0 +1,401 @@
+//! Procedural statement parsing tests
+
+use sv_sim::expr::{integer_literal, BinOp, Expr};
+use sv_sim::logic::{
+    CaseKind, Delay, DisplayTask, Edge, EdgeEvent, EventTrigger, ForLoop, IfStmt, JoinKind,
+    LogicStmt, LoopStmt,
+};
+use sv_sim::parse_sv_file;
+
+/// Parses a module whose only `initial` block holds `body` and returns the
+/// block's statements
+fn parse_initial(body: &str) -> Vec<LogicStmt> {
+    let source = format!(
+        "module m (input wire a, input wire b, output reg out);\ninitial begin\n{body}\nend\nendmodule\n"
+    );
+    let object = parse_sv_file(source).expect("module parses");
+
+    object.mods["m"].initial_blocks[0].body.clone()
+}
+
+/// Parses a module whose only `always_comb` block holds `body` and returns
+/// the block's statements
+fn parse_comb(body: &str) -> Vec<LogicStmt> {
+    let source = format!(
+        "module m (input wire a, input wire b, output reg out);\nalways_comb begin\n{body}\nend\nendmodule\n"
+    );
+    let object = parse_sv_file(source).expect("module parses");
+
+    object.mods["m"].comb_blocks[0].body.clone()
+}
+
+#[test]
+fn if_else_if_chain() {
+    let body = parse_comb(
+        "if (a) out = 1; else if (b) out = 2; else if (a & b) begin out = 3; end else out = 4;",
+    );
+
+    let [LogicStmt::If(IfStmt {
+        cond,
+        then,
+        else_if,
+        else_,
+    })] = &body[..]
+    else {
+        panic!("expected a single if statement, got {body:?}");
+    };
+    assert_eq!(*cond, Expr::Ident("a".to_owned()));
+    assert_eq!(then.len(), 1);
+    assert_eq!(else_if.len(), 2);
+    assert_eq!(else_if[0].0, Expr::Ident("b".to_owned()));
+    assert!(matches!(else_if[1].0, Expr::BinOp(..)));
+    assert_eq!(else_.as_ref().map(Vec::len), Some(1));
+}
+
+#[test]
+fn if_without_else() {
+    let body = parse_comb("if (a) out = 1; out = 2;");
+
+    assert_eq!(body.len(), 2);
+    assert!(matches!(
+        &body[0],
+        LogicStmt::If(IfStmt { else_if, else_: None, .. }) if else_if.is_empty()
+    ));
+}
+
+#[test]
+fn qualified_if_statements() {
+    for qualifier in ["unique", "unique0", "priority"] {
+        let body = parse_comb(&format!(
+            "{qualifier} if (a) out = 1; else if (b) out = 2; else out = 3;"
+        ));
+
+        assert!(
+            matches!(
+                &body[..],
+                [LogicStmt::If(IfStmt { else_if, else_: Some(_), .. })] if else_if.len() == 1
+            ),
+            "{qualifier}: {body:?}"
+        );
+    }
+
+    let source =
+        "module m (input wire a, output reg out);\nalways_comb unique out = a;\nendmodule\n";
+    assert!(parse_sv_file(source.to_owned()).is_err());
+}
+
+#[test]
+fn case_kinds() {
+    for (keyword, expected) in [
+        ("case", CaseKind::Case),
+        ("casez", CaseKind::Casez),
+        ("casex", CaseKind::Casex),
+    ] {
+        let body = parse_comb(&format!(
+            "{keyword} ({{a, b}}) 2'b1?: out = 1; 2'b00, 2'b01: out = 2; default out = 0; endcase"
+        ));
+
+        let [LogicStmt::Case {
+            kind,
+            items,
+            default,
+            ..
+        }] = &body[..]
+        else {
+            panic!("expected a single case statement, got {body:?}");
+        };
+        assert_eq!(*kind, expected);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].labels.len(), 2);
+        assert!(default.is_some());
+    }
+}
+
+#[test]
+fn qualified_case_statements() {
+    for (keyword, expected) in [
+        ("unique case", CaseKind::Case),
+        ("unique0 casez", CaseKind::Casez),
+        ("priority casex", CaseKind::Casex),
+    ] {
+        let body = parse_comb(&format!(
+            "{keyword} (a) 1'b0: out = 0; 1'b1: out = 1; endcase"
+        ));
+
+        assert!(
+            matches!(
+                &body[..],
+                [LogicStmt::Case { kind, items, default: None, .. }]
+                    if *kind == expected && items.len() == 2
+            ),
+            "{keyword}: {body:?}"
+        );
+    }
+}
+
+#[test]
+fn for_loops() {
+    let body = parse_comb(
+        "for (int i = 0; i < 4; i++) out = i; for (i = 3; i >= 0; i -= 1) begin out = i; out = a; end",
+    );
+
+    let [LogicStmt::For(up), LogicStmt::For(down)] = &body[..] else {
+        panic!("expected two for loops, got {body:?}");
+    };
+    assert_eq!(
+        *up.init,
+        LogicStmt::BlockingAssign {
+            lhs: Expr::Ident("i".to_owned()),
+            rhs: Expr::Literal(integer_literal(0), true),
+        }
+    );
+    assert_eq!(
+        *up.step,
+        LogicStmt::BlockingAssign {
+            lhs: Expr::Ident("i".to_owned()),
+            rhs: Expr::BinOp(
+                BinOp::Add,
+                Box::new(Expr::Ident("i".to_owned())),
+                Box::new(Expr::Literal(integer_literal(1), true)),
+            ),
+        }
+    );
+    assert_eq!(up.body.len(), 1);
+    assert!(matches!(
+        down,
+        ForLoop { step, body, .. }
+            if body.len() == 2
+                && matches!(**step, LogicStmt::BlockingAssign { rhs: Expr::BinOp(BinOp::Sub, ..), .. })
+    ));
+
+    let var = up.var.as_ref().expect("loop declares i");
+    assert_eq!((var.name.as_str(), var.width), ("i", 32));
+    assert!(down.var.is_none());
+}
+
+#[test]
+fn assignment_operators() {
+    let body = parse_initial("out++; out -= a; out <<= 1; out |= b; out <= 1;");
+
+    let ops: Vec<Option<BinOp>> = body
+        .iter()
+        .map(|stmt| match stmt {
+            LogicStmt::BlockingAssign {
+                lhs: Expr::Ident(name),
+                rhs: Expr::BinOp(op, lhs, _),
+            } if name == "out" && **lhs == Expr::Ident("out".to_owned()) => Some(*op),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        ops,
+        [
+            Some(BinOp::Add),
+            Some(BinOp::Sub),
+            Some(BinOp::Shl),
+            Some(BinOp::BitOr),
+            None
+        ]
+    );
+    assert!(matches!(body[4], LogicStmt::NonBlockingAssign { .. }));
+
+    let source = "module m (output reg out);\ninitial out + 1;\nendmodule\n";
+    assert!(parse_sv_file(source.to_owned()).is_err());
+}
+
+#[test]
+fn loop_variable_types() {
+    for (header, width) in [
+        ("logic [2:0] k = 0", Some(3)),
+        ("byte unsigned k = 0", Some(8)),
+        ("k = 0", None),
+        ("word_t k = 0", None),
+    ] {
+        let body = parse_comb(&format!("for ({header}; k < 3; k++) out = k;"));
+
+        let [LogicStmt::For(stmt)] = &body[..] else {
+            panic!("expected a for loop, got {body:?}");
+        };
+        assert_eq!(stmt.var.as_ref().map(|var| var.width), width, "{header}");
+        assert_eq!(
+            *stmt.init,
+            LogicStmt::BlockingAssign {
+                lhs: Expr::Ident("k".to_owned()),
+                rhs: Expr::Literal(integer_literal(0), true),
+            },
+            "{header}"
+        );
+    }
+}
+
+#[test]
+fn loops() {
+    let body = parse_initial(
+        "while (a) out = ~out; do out = a; while (b); repeat (4) begin #5 out = 1; end forever begin out = ~out; #5; end",
+    );
+
+    assert!(matches!(
+        &body[..],
+        [
+            LogicStmt::Loop(LoopStmt::While { .. }),
+            LogicStmt::Loop(LoopStmt::DoWhile { .. }),
+            LogicStmt::Loop(LoopStmt::Repeat { count: Expr::Literal(..), .. }),
+            LogicStmt::Loop(LoopStmt::Forever { body }),
+        ] if body.len() == 2
+    ));
+}
+
+#[test]
+fn break_and_continue() {
+    let body = parse_initial("while (a) begin if (b) continue; break; end");
+
+    let [LogicStmt::Loop(LoopStmt::While { body, .. })] = &body[..] else {
+        panic!("expected a while loop, got {body:?}");
+    };
+    assert!(matches!(
+        &body[..],
+        [LogicStmt::If(IfStmt { then, .. }), LogicStmt::Break] if then[..] == [LogicStmt::Continue]
+    ));
+
+    let source = "module m ();\ninitial forever break\nendmodule\n";
+    assert!(parse_sv_file(source.to_owned()).is_err());
+}
+
+#[test]
+fn fork_join_and_event_control() {
+    let body = parse_initial(
+        "fork out = 1; begin #5 out = a; out = b; end join_any @(posedge a or b) out = 0; @*; @a;",
+    );
+
+    let [LogicStmt::ForkJoin { branches, join }, LogicStmt::EventControl {
+        events,
+        body: event_body,
+    }, LogicStmt::EventControl {
+        events: None,
+        body: star_body,
+    }, LogicStmt::EventControl {
+        events: Some(single),
+        ..
+    }] = &body[..]
+    else {
+        panic!("unexpected statements {body:?}");
+    };
+    assert_eq!(*join, JoinKind::JoinAny);
+    assert_eq!(branches.len(), 2);
+    assert_eq!(branches[1].len(), 2);
+    assert_eq!(
+        events.as_deref(),
+        Some(
+            &[
+                EventTrigger::Edge(EdgeEvent {
+                    edge: Edge::Posedge,
+                    signal: "a".to_owned(),
+                }),
+                EventTrigger::Change("b".to_owned()),
+            ][..]
+        )
+    );
+    assert_eq!(event_body.len(), 1);
+    assert!(star_body.is_empty());
+    assert_eq!(single, &[EventTrigger::Change("a".to_owned())]);
+}
+
+#[test]
+fn wait_statements() {
+    let body = parse_initial("wait (a && !b) out = 1; wait (b);");
+
+    assert!(matches!(
+        &body[..],
+        [
+            LogicStmt::Wait { cond: Expr::BinOp(BinOp::LogAnd, ..), body },
+            LogicStmt::Wait { cond: Expr::Ident(name), body: empty },
+        ] if body.len() == 1 && name == "b" && empty.is_empty()
+    ));
+}
+
+#[test]
+fn intra_assignment_delays() {
+    let body = parse_initial("out = #2 a; out <= #(1 + 2) b; out <= #2ns a;");
+
+    let delays: Vec<(bool, &Delay)> = body
+        .iter()
+        .filter_map(|stmt| match stmt {
+            LogicStmt::DelayedAssign {
+                delay, blocking, ..
+            } => Some((*blocking, delay)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        delays,
+        [
+            (true, &Delay::Units(Expr::Literal(integer_literal(2), true))),
+            (
+                false,
+                &Delay::Units(Expr::BinOp(
+                    BinOp::Add,
+                    Box::new(Expr::Literal(integer_literal(1), true)),
+                    Box::new(Expr::Literal(integer_literal(2), true)),
+                ))
+            ),
+            (false, &Delay::Time(2e-9)),
+        ]
+    );
+    assert!(matches!(
+        &body[0],
+        LogicStmt::DelayedAssign { lhs: Expr::Ident(lhs), rhs: Expr::Ident(rhs), .. }
+            if lhs == "out" && rhs == "a"
+    ));
+}
+
+#[test]
+fn display_tasks() {
+    let body =
+        parse_initial("$display(\"a = %0d\", a); $write(\"b\"); $strobe(\"%b\", out); $finish;");
+
+    let tasks: Vec<Option<DisplayTask>> = body
+        .iter()
+        .map(|stmt| match stmt {
+            LogicStmt::Display { task, .. } => Some(*task),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        tasks,
+        [
+            Some(DisplayTask::Display),
+            Some(DisplayTask::Write),
+            Some(DisplayTask::Strobe),
+            None,
+        ]
+    );
+    assert!(matches!(&body[0], LogicStmt::Display { args, .. } if args.len() == 2));
+    assert!(matches!(&body[3], LogicStmt::SysTaskCall { name, .. } if name == "finish"));
+}
+
+#[test]
+fn combinational_blocks_may_not_wait() {
+    let body = parse_comb(
+        "case (a) 1'b0: out = b; default: out = ~b; endcase\nfor (int i = 0; i < 2; i++) out = out ^ a;",
+    );
+    assert!(matches!(
+        body[..],
+        [LogicStmt::Case { .. }, LogicStmt::For(_)]
+    ));
+
+    for body in [
+        "#5 out = a;",
+        "@(posedge a) out = b;",
+        "if (a) out = b; else begin @(b); out = 0; end",
+        "case (a) 1'b1: #1; endcase",
+        "repeat (2) #1 out = ~out;",
+        "fork out = a; join",
+        "out = #1 a;",
+        "wait (a) out = b;",
+    ] {
+        let source = format!(
+            "module m (input wire a, input wire b, output reg out);\nalways_comb begin\n{body}\nend\nendmodule\n"
+        );
+        assert!(parse_sv_file(source).is_err(), "{body}");
+    }
+}
@@ -0,0 +1,153 @@
+//! Clock edge evaluation tests
+
+use std::collections::BTreeSet;
+use sv_sim::logic::{Edge, EdgeEvent, LogicStmt};
+use sv_sim::module::Module;
+use sv_sim::parse_sv_file;
+use sv_sim::sim::ModuleState;
+use sv_sim::value::LogicValue;
+
+/// Parses a source holding a single module
+fn parse_module(source: &str) -> Module {
+    let object = parse_sv_file(source.to_owned()).expect("source parses");
+
+    object.mods.into_values().next().expect("source holds a module")
+}
+
+/// Set of signal names
+fn names(names: &[&str]) -> BTreeSet<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn non_blocking_assignments_update_together() {
+    let module = parse_module(
+        "module swap (input wire clk, output reg [7:0] a, output reg [7:0] b);
+always_ff @(posedge clk) a <= b;
+always_ff @(posedge clk) b <= a;
+endmodule
+",
+    );
+    let mut state = ModuleState::new(&module);
+    state.set("clk", &LogicValue::from_u128(0, 1));
+    state.set("a", &LogicValue::from_u128(1, 8));
+    state.set("b", &LogicValue::from_u128(2, 8));
+
+    let changed = module.clock_edge("clk", Edge::Posedge, &mut state);
+    assert_eq!(changed, names(&["a", "b", "clk"]));
+    assert_eq!(state.get("a"), Some(&LogicValue::from_u128(2, 8)));
+    assert_eq!(state.get("b"), Some(&LogicValue::from_u128(1, 8)));
+
+    // Only rising edges trigger the blocks
+    let changed = module.clock_edge("clk", Edge::Negedge, &mut state);
+    assert_eq!(changed, names(&["clk"]));
+}
+
+#[test]
+fn counter_with_asynchronous_reset() {
+    let module = parse_module(
+        "module counter (input wire clk, input wire rst, output reg [3:0] count, output wire wrap);
+always_ff @(posedge clk or posedge rst) begin
+    if (rst) count <= 0;
+    else count <= count + 1;
+end
+assign wrap = count == 4'hF;
+endmodule
+",
+    );
+    let mut state = ModuleState::new(&module);
+    state.set("rst", &LogicValue::from_u128(0, 1));
+
+    let changed = module.clock_edge("rst", Edge::Posedge, &mut state);
+    assert_eq!(changed, names(&["count", "rst", "wrap"]));
+    assert_eq!(state.get("count"), Some(&LogicValue::from_u128(0, 4)));
+
+    state.set("rst", &LogicValue::from_u128(0, 1));
+    for _ in 0..15 {
+        module.clock_edge("clk", Edge::Posedge, &mut state);
+        module.clock_edge("clk", Edge::Negedge, &mut state);
+    }
+    assert_eq!(state.get("count"), Some(&LogicValue::from_u128(15, 4)));
+    assert_eq!(state.get("wrap"), Some(&LogicValue::from_u128(1, 1)));
+
+    let changed = module.clock_edge("clk", Edge::Posedge, &mut state);
+    assert_eq!(changed, names(&["clk", "count", "wrap"]));
+    assert_eq!(state.get("count"), Some(&LogicValue::from_u128(0, 4)));
+}
+
+#[test]
+fn parameterized_registers_take_default_widths() {
+    let module = parse_module(
+        "module shift #(parameter WIDTH = 6) (input wire clk, output reg [WIDTH-1:0] q, output wire [WIDTH-1:0] reversed);
+genvar i;
+generate
+    for (i = 0; i < WIDTH; i = i + 1) begin : bits
+        assign reversed[i] = q[WIDTH-1-i];
+    end
+endgenerate
+always_ff @(posedge clk) q <= {q[WIDTH-2:0], 1'b1};
+endmodule
+",
+    );
+    let mut state = ModuleState::new(&module);
+    state.set("q", &LogicValue::from_u128(0, 6));
+
+    module.clock_edge("clk", Edge::Posedge, &mut state);
+    let changed = module.clock_edge("clk", Edge::Posedge, &mut state);
+    assert_eq!(changed, names(&["q", "reversed"]));
+    assert_eq!(state.get("q"), Some(&LogicValue::from_u128(0b000011, 6)));
+    assert_eq!(
+        state.get("reversed"),
+        Some(&LogicValue::from_u128(0b110000, 6))
+    );
+}
+
+#[test]
+fn always_ff_records_clock_and_reset_edges() {
+    let module = parse_module(
+        "module regs (input wire clk, input wire rst_n, input wire d, output reg q, output reg r);
+always_ff @(posedge clk or negedge rst_n) begin
+    if (!rst_n)
+        q <= 1'b0;
+    else
+        q <= d;
+end
+always_ff @(negedge clk) r <= q;
+always @(posedge clk, negedge rst_n) r <= d;
+endmodule
+",
+    );
+
+    let edge = |edge: Edge, signal: &str| EdgeEvent {
+        edge,
+        signal: signal.to_owned(),
+    };
+    let edges: Vec<(EdgeEvent, Option<EdgeEvent>)> = module
+        .ff_blocks
+        .iter()
+        .map(|block| (block.clock.clone(), block.reset.clone()))
+        .collect();
+    assert_eq!(
+        edges,
+        [
+            (edge(Edge::Posedge, "clk"), Some(edge(Edge::Negedge, "rst_n"))),
+            (edge(Edge::Negedge, "clk"), None),
+            (edge(Edge::Posedge, "clk"), Some(edge(Edge::Negedge, "rst_n"))),
+        ]
+    );
+    assert!(matches!(module.ff_blocks[0].body[..], [LogicStmt::If(_)]));
+    assert!(matches!(
+        module.ff_blocks[1].body[..],
+        [LogicStmt::NonBlockingAssign { .. }]
+    ));
+
+    // Sequential blocks are triggered by a clock and at most one reset edge
+    for events in [
+        "posedge clk or negedge rst_n or posedge set",
+        "posedge clk or d",
+        "*",
+    ] {
+        let source = format!("module m ();\nalways_ff @({events}) q <= d;\nendmodule\n");
+        assert!(parse_sv_file(source.clone()).is_err(), "{source}");
+    }
+}
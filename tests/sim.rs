@@ -0,0 +1,1043 @@
+//! Event-driven simulation tests
+
+use std::collections::HashMap;
+use sv_sim::expr::Expr;
+use sv_sim::sim::{SimResult, SimStatus};
+use sv_sim::sys_task::{assert_action, AssertAction};
+use sv_sim::value::LogicValue;
+use sv_sim::{parse_sv_file, SimError};
+
+/// One nanosecond, the default time unit
+const NS: f64 = 1e-9;
+
+/// Parses `source` and simulates it for `end_time` seconds
+fn simulate(source: &str, end_time: f64) -> SimResult {
+    let object = parse_sv_file(source.to_owned()).expect("source parses");
+
+    object.run(end_time)
+}
+
+/// Final value of `signal`
+fn value(result: &SimResult, signal: &str) -> LogicValue {
+    result.signals[signal].clone()
+}
+
+#[test]
+fn counter_counts_clock_edges() {
+    let result = simulate(
+        "module counter ();
+reg clk;
+reg rst;
+reg [3:0] count;
+always_ff @(posedge clk or posedge rst) begin
+    if (rst) count <= 0;
+    else count <= count + 1;
+end
+initial begin
+    clk = 0;
+    rst = 1;
+    #2 rst = 0;
+end
+initial forever #5 clk = ~clk;
+endmodule
+",
+        100e-9,
+    );
+
+    // Rising edges at 5ns, 15ns and so on up to 95ns
+    assert_eq!(
+        value(&result, "counter.count"),
+        LogicValue::from_u128(10, 4)
+    );
+    assert_eq!(result.end_time, 100e-9);
+
+    let clk_changes = result
+        .changes
+        .iter()
+        .filter(|change| change.signal == "counter.clk")
+        .count();
+    assert_eq!(clk_changes, 1 + 100 / 5);
+}
+
+#[test]
+fn flip_flops_sample_before_updating() {
+    let result = simulate(
+        "module swap ();
+reg clk;
+reg [7:0] a;
+reg [7:0] b;
+initial begin
+    a = 8'd1;
+    b = 8'd2;
+    clk = 0;
+    #1 clk = 1;
+end
+always @(posedge clk) begin
+    a <= b;
+    b <= a;
+end
+endmodule
+",
+        10.0 * NS,
+    );
+
+    assert_eq!(value(&result, "swap.a"), LogicValue::from_u128(2, 8));
+    assert_eq!(value(&result, "swap.b"), LogicValue::from_u128(1, 8));
+}
+
+#[test]
+fn continuous_assignments_follow_inputs() {
+    let result = simulate(
+        "module adder ();
+reg [3:0] a;
+reg [3:0] b;
+wire [4:0] sum;
+wire [3:0] doubled;
+assign sum = a + b;
+assign doubled = {sum[2:0], 1'b0};
+initial begin
+    a = 4'd9;
+    b = 4'd8;
+    #1 b = 4'd1;
+end
+endmodule
+",
+        10.0 * NS,
+    );
+
+    assert_eq!(value(&result, "adder.sum"), LogicValue::from_u128(10, 5));
+    assert_eq!(value(&result, "adder.doubled"), LogicValue::from_u128(4, 4));
+
+    let sums: Vec<(f64, u128)> = result
+        .changes
+        .iter()
+        .filter(|change| change.signal == "adder.sum")
+        .map(|change| (change.time, change.value.to_u128().unwrap()))
+        .collect();
+    assert_eq!(sums, [(0.0, 17), (NS, 10)]);
+}
+
+#[test]
+fn nets_resolve_every_driver() {
+    let result = simulate(
+        "module nets ();
+tri floating;
+tri conflict;
+wire weak;
+wire [3:0] partial;
+wire undriven;
+reg en;
+wire bus;
+assign floating = 1'b1;
+assign floating = 1'bz;
+assign conflict = 1'b1;
+assign conflict = 1'b0;
+assign (weak0, weak1) weak = 1'b0;
+assign weak = 1'b1;
+assign partial[2] = 1'b1;
+assign bus = en ? 1'b0 : 1'bz;
+assign (pull0, pull1) bus = 1'b1;
+initial begin
+    en = 1;
+    #1 en = 0;
+end
+endmodule
+",
+        10.0 * NS,
+    );
+
+    assert_eq!(value(&result, "nets.floating"), LogicValue::from_u128(1, 1));
+    assert_eq!(value(&result, "nets.conflict").to_string(), "x");
+    assert_eq!(value(&result, "nets.weak"), LogicValue::from_u128(1, 1));
+    assert_eq!(value(&result, "nets.partial").to_string(), "z1zz");
+    assert_eq!(value(&result, "nets.undriven").to_string(), "z");
+
+    // The pull-up only wins once the strong driver lets go of the bus
+    let bus: Vec<(f64, String)> = result
+        .changes
+        .iter()
+        .filter(|change| change.signal == "nets.bus")
+        .map(|change| (change.time, change.value.to_string()))
+        .collect();
+    assert_eq!(bus, [(0.0, "0".to_owned()), (NS, "1".to_owned())]);
+}
+
+#[test]
+fn loops_and_fork_join() {
+    let result = simulate(
+        "module loops ();
+reg [7:0] total;
+reg [7:0] count;
+reg done;
+initial begin
+    total = 0;
+    for (int i = 0; i < 4; i++) total = total + i;
+    count = 0;
+    repeat (3) count = count + 2;
+    fork
+        #3 count = count + 1;
+        #5 total = total + 10;
+    join
+    done = 1;
+end
+endmodule
+",
+        10.0 * NS,
+    );
+
+    assert_eq!(value(&result, "loops.total"), LogicValue::from_u128(16, 8));
+    assert_eq!(value(&result, "loops.count"), LogicValue::from_u128(7, 8));
+
+    let done = result
+        .changes
+        .iter()
+        .find(|change| change.signal == "loops.done")
+        .expect("done is set");
+    assert_eq!(done.time, 5.0 * NS);
+}
+
+#[test]
+fn stops_at_end_time() {
+    let result = simulate(
+        "module clock ();
+reg clk;
+initial begin
+    clk = 0;
+    forever #1 clk = ~clk;
+end
+endmodule
+",
+        4.5e-9,
+    );
+
+    assert_eq!(result.end_time, 4.5e-9);
+    // The clock is set at 0ns, then toggles at 1ns through 4ns
+    assert_eq!(result.changes.len(), 5);
+}
+
+#[test]
+fn always_blocks_without_sensitivity_lists_repeat() {
+    let result = simulate(
+        "module clk_gen ();
+reg clk;
+reg [3:0] rise;
+reg [3:0] fall;
+initial begin
+    clk = 0;
+    rise = 0;
+    fall = 0;
+end
+always #5 clk = ~clk;
+always @(posedge clk) rise <= rise + 1;
+always begin
+    @(negedge clk);
+    fall = fall + 1;
+end
+endmodule
+",
+        42.0 * NS,
+    );
+
+    // Rising edges at 5ns, 15ns, 25ns and 35ns, falling edges at 10ns, 20ns,
+    // 30ns and 40ns
+    assert_eq!(value(&result, "clk_gen.rise"), LogicValue::from_u128(4, 4));
+    assert_eq!(value(&result, "clk_gen.fall"), LogicValue::from_u128(4, 4));
+
+    // Without anything to wait on, the block would never let time advance
+    let source = "module m ();\nreg q;\nalways q = ~q;\nendmodule\n";
+    assert!(parse_sv_file(source.to_owned()).is_err());
+}
+
+#[test]
+fn wait_statements_block_until_their_condition_holds() {
+    let result = simulate(
+        "module waits ();
+reg [3:0] count;
+reg done;
+reg [1:0] woken;
+initial begin
+    count = 0;
+    done = 0;
+    repeat (5) #2 count = count + 1;
+    done = 1;
+end
+initial begin
+    woken = 0;
+    wait (count == 3) woken = 1;
+    wait (done);
+    woken = 2;
+    wait (done) woken = 3;
+end
+endmodule
+",
+        20.0 * NS,
+    );
+
+    // A condition that already holds does not wait, so the last two wakes
+    // happen together
+    let wakes: Vec<(f64, LogicValue)> = result
+        .changes
+        .iter()
+        .filter(|change| change.signal == "waits.woken")
+        .map(|change| (change.time, change.value.clone()))
+        .collect();
+    assert_eq!(
+        wakes,
+        [
+            (0.0, LogicValue::from_u128(0, 2)),
+            (6e-9, LogicValue::from_u128(1, 2)),
+            (10e-9, LogicValue::from_u128(2, 2)),
+            (10e-9, LogicValue::from_u128(3, 2)),
+        ]
+    );
+}
+
+#[test]
+fn intra_assignment_delays_sample_before_waiting() {
+    let result = simulate(
+        "module delays ();
+reg [7:0] a;
+reg [7:0] block;
+reg [7:0] late;
+reg [7:0] early;
+initial begin
+    a = 1;
+    late <= #3 a;
+    block = #2 a;
+    a = 5;
+    early <= #1 a;
+end
+endmodule
+",
+        10.0 * NS,
+    );
+
+    assert_eq!(value(&result, "delays.block"), LogicValue::from_u128(1, 8));
+    assert_eq!(value(&result, "delays.late"), LogicValue::from_u128(1, 8));
+    assert_eq!(value(&result, "delays.early"), LogicValue::from_u128(5, 8));
+
+    // Each write lands once its delay has passed, with the non-blocking
+    // ones not holding up the rest of the block
+    let writes: Vec<(&str, f64)> = result
+        .changes
+        .iter()
+        .filter(|change| change.signal != "delays.a")
+        .map(|change| (change.signal.as_str(), change.time))
+        .collect();
+    assert_eq!(
+        writes,
+        [
+            ("delays.block", 2e-9),
+            ("delays.late", 3e-9),
+            ("delays.early", 3e-9),
+        ]
+    );
+}
+
+#[test]
+fn delayed_clocks_see_values_from_before_the_edge() {
+    let result = simulate(
+        "module pipeline ();
+reg clk;
+wire clk_buf;
+reg [7:0] a;
+reg [7:0] b;
+assign clk_buf = clk;
+always @(posedge clk) a <= b;
+always @(posedge clk_buf) b <= a;
+initial begin
+    a = 8'd1;
+    b = 8'd2;
+    clk = 0;
+    #1 clk = 1;
+end
+endmodule
+",
+        10.0 * NS,
+    );
+
+    assert_eq!(value(&result, "pipeline.a"), LogicValue::from_u128(2, 8));
+    assert_eq!(value(&result, "pipeline.b"), LogicValue::from_u128(1, 8));
+}
+
+#[test]
+fn registered_outputs_settle_within_the_time_step() {
+    let result = simulate(
+        "module chain ();
+reg clk;
+reg [3:0] q;
+wire [3:0] next;
+wire [3:0] doubled;
+assign next = q + 1;
+assign doubled = {q[2:0], 1'b0};
+always @(posedge clk) q <= next;
+initial begin
+    q = 0;
+    clk = 0;
+    #1 clk = 1;
+end
+endmodule
+",
+        10.0 * NS,
+    );
+
+    let at_edge: Vec<(&str, u128)> = result
+        .changes
+        .iter()
+        .filter(|change| change.time == NS)
+        .map(|change| (change.signal.as_str(), change.value.to_u128().unwrap()))
+        .collect();
+    assert_eq!(
+        at_edge,
+        [
+            ("chain.clk", 1),
+            ("chain.q", 1),
+            ("chain.next", 2),
+            ("chain.doubled", 2),
+        ]
+    );
+}
+
+#[test]
+fn oscillating_feedback_stops_the_run() {
+    let result = simulate(
+        "module ring ();
+reg en;
+wire a;
+wire b;
+assign a = en & ~b;
+assign b = a;
+initial begin
+    en = 0;
+    #1 en = 1;
+    #1 en = 0;
+end
+endmodule
+",
+        10.0 * NS,
+    );
+
+    assert_eq!(result.end_time, NS);
+    assert_eq!(result.status, SimStatus::Unsettled);
+    assert_eq!(value(&result, "ring.en"), LogicValue::from_u128(1, 1));
+}
+
+#[test]
+fn runs_a_single_top_module() {
+    let object = parse_sv_file(
+        "module clock ();
+reg clk;
+initial begin
+    clk = 0;
+    forever #5 clk = ~clk;
+end
+endmodule
+
+module idle ();
+reg unused;
+initial unused = 1;
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+
+    let top = object
+        .find_top_module(Some("clock"))
+        .expect("clock is defined");
+    let result = object.run_module(top, 20.0 * NS);
+
+    assert_eq!(result.end_time, 20.0 * NS);
+    assert!(result.signals.contains_key("clock.clk"));
+    assert!(!result.signals.contains_key("idle.unused"));
+}
+
+#[test]
+fn finish_and_stop_end_the_run() {
+    let source = |task: &str| {
+        format!(
+            "module tb ();
+reg clk;
+reg [3:0] count;
+initial begin
+    clk = 0;
+    count = 0;
+    forever #1 clk = ~clk;
+end
+always @(posedge clk) begin
+    count <= count + 1;
+    if (count == 4'd2) ${task};
+end
+endmodule
+"
+        )
+    };
+
+    // Rising edges at 1ns, 3ns and 5ns, with the third ending the run
+    // before its count is updated
+    let result = simulate(&source("finish"), 100.0 * NS);
+    assert_eq!(result.status, SimStatus::Finished);
+    assert_eq!(result.end_time, 5.0 * NS);
+    assert_eq!(value(&result, "tb.count"), LogicValue::from_u128(2, 4));
+
+    let result = simulate(&source("stop"), 100.0 * NS);
+    assert_eq!(result.status, SimStatus::Stopped);
+    assert_eq!(result.end_time, 5.0 * NS);
+
+    let result = simulate(&source("finish(0)").replace("4'd2", "4'd9"), 10.0 * NS);
+    assert_eq!(result.status, SimStatus::EndTime);
+
+    let result = simulate("module idle ();\nreg a;\ninitial a = 1;\nendmodule\n", NS);
+    assert_eq!(result.status, SimStatus::Completed);
+}
+
+#[test]
+fn final_blocks_run_when_the_run_ends() {
+    let source = |task: &str| {
+        format!(
+            "module tb ();
+reg [3:0] count;
+reg [3:0] seen;
+initial begin
+    count = 0;
+    #2 count = 1;
+    #2 ${task};
+end
+initial #4 count <= 4'd9;
+always @(count) seen = count;
+final $display(\"count=%0d seen=%0d\", count, seen);
+final begin
+    count = 4'd5;
+    $write(\"then %0d\\n\", count);
+end
+endmodule
+"
+        )
+    };
+
+    // Pending updates are dropped, and the changes final blocks make wake
+    // nothing
+    let result = simulate(&source("finish"), 10.0 * NS);
+    assert_eq!(result.status, SimStatus::Finished);
+    assert_eq!(result.output, "count=1 seen=1\nthen 5\n");
+    assert_eq!(value(&result, "tb.count"), LogicValue::from_u128(5, 4));
+    assert_eq!(value(&result, "tb.seen"), LogicValue::from_u128(1, 4));
+
+    let result = simulate(&source("display"), 3.0 * NS);
+    assert_eq!(result.status, SimStatus::EndTime);
+    assert_eq!(result.output, "count=1 seen=1\nthen 5\n");
+
+    let result = simulate(&source("display"), 10.0 * NS);
+    assert_eq!(result.status, SimStatus::Completed);
+    assert_eq!(result.output, "\ncount=9 seen=9\nthen 5\n");
+
+    let result = simulate(&source("stop"), 10.0 * NS);
+    assert_eq!(result.output, "");
+
+    // Final blocks may not wait
+    let source = "module tb ();\nfinal #1 $display(\"late\");\nendmodule\n";
+    assert!(parse_sv_file(source.to_owned()).is_err());
+}
+
+#[test]
+fn signed_casts_change_arithmetic() {
+    let result = simulate(
+        "module casts ();
+reg [7:0] a;
+reg [3:0] b;
+wire [15:0] extended;
+wire [15:0] zeroed;
+wire [15:0] cast_extended;
+wire [15:0] cast_zeroed;
+wire [15:0] product;
+wire [15:0] mixed;
+wire [7:0] shifted;
+wire [7:0] quotient;
+wire less;
+wire less_unsigned;
+wire negative;
+assign extended = $signed(a);
+assign zeroed = $unsigned($signed(a));
+assign cast_extended = signed'(a);
+assign cast_zeroed = unsigned'(signed'(a));
+assign product = $signed(a) * $signed(b);
+assign mixed = $signed(a) + b;
+assign shifted = $signed(a) >>> 1;
+assign quotient = $signed(a) / $signed(b);
+assign less = $signed(a) < $signed(b);
+assign less_unsigned = a < b;
+assign negative = $signed(a) < 0;
+initial begin
+    a = -8'd10;
+    b = 4'd3;
+end
+endmodule
+",
+        NS,
+    );
+
+    for (signal, expected, width) in [
+        ("extended", 0xFFF6, 16),
+        ("zeroed", 0x00F6, 16),
+        ("cast_extended", 0xFFF6, 16),
+        ("cast_zeroed", 0x00F6, 16),
+        ("product", 0xFFE2, 16),
+        ("mixed", 0x00F9, 16),
+        ("shifted", 0xFB, 8),
+        ("quotient", 0xFD, 8),
+        ("less", 1, 1),
+        ("less_unsigned", 0, 1),
+        ("negative", 1, 1),
+    ] {
+        assert_eq!(
+            value(&result, &format!("casts.{signal}")),
+            LogicValue::from_u128(expected, width),
+            "{signal}"
+        );
+    }
+}
+
+#[test]
+fn decimal_literals_are_signed() {
+    let result = simulate(
+        "module literals ();
+initial $display(\"%0d %0d %0d %0d %0d\", -7 / 2, -3 % 2, -8 >>> 1, 8'hF8 >>> 1, 4'sd3 + -4'sd4);
+endmodule
+",
+        NS,
+    );
+
+    // Based literals are unsigned unless written with s
+    assert_eq!(result.output, "-3 -1 -4 124 -1\n");
+}
+
+#[test]
+fn simulates_the_elaborated_hierarchy() {
+    let object = parse_sv_file(
+        "module counter #(parameter WIDTH = 2) (input wire clk, input wire en, output reg [WIDTH-1:0] count, output wire wrapped);
+initial count = 0;
+always_ff @(posedge clk) if (en) count <= count + 1;
+assign wrapped = &count;
+endmodule
+
+module top ();
+reg clk;
+wire [3:0] count;
+wire [1:0] small;
+wire wrapped;
+wire float;
+counter #(.WIDTH(4)) u_wide (.clk(clk), .en(1'b1), .count(count), .wrapped(wrapped));
+counter u_small (.clk(clk), .en(), .count(small), .wrapped(float));
+initial begin
+    clk = 0;
+    forever #5 clk = ~clk;
+end
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    let design = object.elaborate("top").expect("design elaborates");
+    assert_eq!(design.top.children[0].params["WIDTH"].to_u128(), Some(4));
+
+    // Rising edges at 5ns, 15ns and so on up to 155ns
+    let result = design.run(160.0 * NS);
+
+    assert_eq!(
+        value(&result, "top.u_wide.count"),
+        LogicValue::from_u128(0, 4)
+    );
+    assert_eq!(value(&result, "top.count"), LogicValue::from_u128(0, 4));
+    let wrapped: Vec<f64> = result
+        .changes
+        .iter()
+        .filter(|change| change.signal == "top.wrapped" && change.value.to_u128() == Some(1))
+        .map(|change| change.time)
+        .collect();
+    assert_eq!(wrapped, [145e-9]);
+
+    // The enable of `u_small` is left unconnected, so it floats and the
+    // counter never starts
+    assert_eq!(value(&result, "top.u_small.en").to_string(), "z");
+    assert_eq!(value(&result, "top.small"), LogicValue::from_u128(0, 2));
+}
+
+#[test]
+fn continuous_assignments_drive_parts_of_signals() {
+    let result = simulate(
+        "module adder ();
+reg [3:0] a;
+reg [3:0] b;
+reg cin;
+reg [3:0] upper_nibble;
+wire [3:0] sum;
+wire carry;
+wire [7:0] bus;
+wire [2:0] flags;
+assign {carry, sum} = a + b + cin;
+assign bus[7:4] = upper_nibble;
+assign bus[3:0] = sum;
+assign flags[0] = carry;
+assign {flags[2], flags[1]} = {sum[3], ~carry};
+initial begin
+    a = 4'd9;
+    b = 4'd8;
+    cin = 1;
+    upper_nibble = 4'ha;
+    #5 a = 4'd2;
+end
+endmodule
+",
+        10e-9,
+    );
+
+    assert_eq!(value(&result, "adder.sum"), LogicValue::from_u128(11, 4));
+    assert_eq!(value(&result, "adder.carry"), LogicValue::from_u128(0, 1));
+    assert_eq!(value(&result, "adder.bus"), LogicValue::from_u128(0xab, 8));
+    assert_eq!(value(&result, "adder.flags"), LogicValue::from_u128(0b110, 3));
+
+    // 9 + 8 + 1 carries out of the four-bit sum
+    let carries: Vec<f64> = result
+        .changes
+        .iter()
+        .filter(|change| change.signal == "adder.carry")
+        .map(|change| change.time)
+        .collect();
+    assert_eq!(carries, [0.0, 5.0 * NS]);
+}
+
+#[test]
+fn break_and_continue_leave_the_innermost_loop() {
+    let result = simulate(
+        "module loops ();
+integer i;
+integer j;
+reg [7:0] s;
+reg [7:0] r;
+reg [7:0] nested;
+reg [7:0] odd;
+initial begin
+    s = 0;
+    for (i = 0; i < 10; i++) begin
+        if (i == 2) continue;
+        if (i == 5) break;
+        s = s + 1;
+    end
+    r = 0;
+    repeat (3) begin
+        repeat (10) begin
+            r = r + 1;
+            if (r % 4 == 0) break;
+        end
+    end
+    nested = 0;
+    for (j = 0; j < 3; j++)
+        while (1) begin
+            nested = nested + 1;
+            break;
+        end
+    odd = 0;
+    j = 0;
+    do begin
+        j = j + 1;
+        if (j % 2 == 0) continue;
+        odd = odd + 1;
+    end while (j < 6);
+    j = 0;
+    forever begin
+        #1;
+        j = j + 1;
+        if (j == 3) break;
+    end
+    s = s + 10;
+end
+endmodule
+",
+        10e-9,
+    );
+
+    assert_eq!(value(&result, "loops.s"), LogicValue::from_u128(14, 8));
+    assert_eq!(value(&result, "loops.i"), LogicValue::from_u128(5, 32));
+    // Each inner loop is left once the count reaches a multiple of four,
+    // leaving the outer loop to run all three times
+    assert_eq!(value(&result, "loops.r"), LogicValue::from_u128(12, 8));
+    assert_eq!(value(&result, "loops.nested"), LogicValue::from_u128(3, 8));
+    assert_eq!(value(&result, "loops.odd"), LogicValue::from_u128(3, 8));
+    assert_eq!(result.status, SimStatus::Completed);
+}
+
+#[test]
+fn loop_variables_take_their_declared_width() {
+    let result = simulate(
+        "module loops ();
+reg [7:0] count;
+reg [7:0] k;
+initial begin
+    count = 0;
+    k = 8'hff;
+    for (byte unsigned j = 250; j != 4; j++) count = count + 1;
+    for (logic [1:0] k = 0; k != 3; k++) count = count + 1;
+end
+endmodule
+",
+        1e-9,
+    );
+
+    // `j` wraps from 255 to 0
+    assert_eq!(value(&result, "loops.count"), LogicValue::from_u128(13, 8));
+    assert_eq!(value(&result, "loops.j"), LogicValue::from_u128(4, 8));
+    // A variable the module declares keeps its own width
+    assert_eq!(value(&result, "loops.k"), LogicValue::from_u128(3, 8));
+}
+
+#[test]
+fn procedural_assignments_to_selects() {
+    let result = simulate(
+        "module select ();
+reg [7:0] d;
+reg [7:0] x;
+integer i;
+initial begin
+    d = 8'h00;
+    x = 8'h00;
+    i = 2;
+    d[i] = 1'b1;
+    d[i * 2 +: 3] = 3'b111;
+    d[7 -: 2] = 2'b10;
+    x[3:0] = 4'hf;
+    x[i + 8] = 1'b1;
+    i = 'x;
+    x[i] = 1'b1;
+    x[i +: 2] = 2'b11;
+end
+endmodule
+",
+        1e-9,
+    );
+
+    assert_eq!(
+        value(&result, "select.d"),
+        LogicValue::from_u128(0b1011_0100, 8)
+    );
+    // Writes past the end, or at an unknown index, change nothing
+    assert_eq!(value(&result, "select.x"), LogicValue::from_u128(0x0f, 8));
+}
+
+#[test]
+fn assertion_control_tasks_change_instance_state() {
+    let lookup = HashMap::from([("action".to_owned(), LogicValue::from_u128(5, 32))]);
+    let action = |task: &str, args: &[Expr]| assert_action(task, args, &lookup);
+    let number = |value: u128| Expr::Literal(LogicValue::from_u128(value, 32), false);
+
+    assert_eq!(
+        action("assertcontrol", &[number(4), number(1), number(7)]),
+        Some(AssertAction::Off)
+    );
+    assert_eq!(
+        action("assertcontrol", &[Expr::Ident("action".to_owned())]),
+        Some(AssertAction::Kill)
+    );
+    assert_eq!(action("asserton", &[]), Some(AssertAction::On));
+    assert_eq!(
+        action("assertvacuousoff", &[number(0)]),
+        Some(AssertAction::VacuousOff)
+    );
+    assert_eq!(action("assertcontrol", &[number(12)]), None);
+    assert_eq!(action("assertcontrol", &[]), None);
+
+    // Actions apply to every instance without scopes, and are logged and
+    // skipped for an unknown control type. Every task standing for an
+    // action is run
+    let result = simulate(
+        "module tb ();
+reg [3:0] count;
+initial begin
+    count = 0;
+    $assertcontrol(4, 15, 7, 0);
+    #1 count = count + 1;
+    $assertkill;
+    $assertcontrol(13);
+    #1 count = count + 1;
+    $assertcontrol(9);
+    $assertpassoff;
+    $assertvacuousoff;
+end
+endmodule
+",
+        10e-9,
+    );
+    assert_eq!(value(&result, "tb.count"), LogicValue::from_u128(2, 4));
+    assert_eq!(result.status, SimStatus::Completed);
+    let state = result.assertions["tb"];
+    assert!(!state.enabled && !state.fail_actions);
+    assert!(!state.pass_actions && !state.vacuous_pass_actions);
+
+    // Levels and scopes limit the instances an action applies to, and a
+    // locked instance keeps its state
+    let object = parse_sv_file(
+        "module leaf ();
+endmodule
+
+module mid ();
+leaf u_leaf ();
+endmodule
+
+module tb ();
+mid u_a ();
+mid u_b ();
+initial begin
+    $assertoff;
+    #1 $asserton(1, u_a);
+    #1 $assertcontrol(1, 15, 7, 0, tb.u_b);
+    $assertcontrol(3);
+    $assertkill(0, u_a.u_leaf);
+    $asserton(2, u_b);
+end
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("design parses");
+    let result = object
+        .elaborate("tb")
+        .expect("design elaborates")
+        .run(10e-9);
+
+    let states: Vec<(&str, bool, bool)> = result
+        .assertions
+        .iter()
+        .map(|(inst, state)| (inst.as_str(), state.enabled, state.locked))
+        .collect();
+    assert_eq!(
+        states,
+        [
+            ("tb", true, false),
+            ("tb.u_a", true, false),
+            ("tb.u_a.u_leaf", false, false),
+            ("tb.u_b", false, true),
+            ("tb.u_b.u_leaf", false, true),
+        ]
+    );
+}
+
+#[test]
+fn fractional_delays_in_stimulus() {
+    let result = simulate(
+        "`timescale 1ns/100ps
+module tb ();
+reg [1:0] a;
+initial begin
+    a = 0;
+    #2.5 a = 1;
+    #0.5ns a = 2;
+    #1_000.25ps;
+    if (a == 2)
+        $display(\"passed\");
+    else
+        $display(\"failed\");
+    $finish;
+end
+endmodule
+",
+        100e-9,
+    );
+
+    let changes: Vec<(f64, LogicValue)> = result
+        .changes
+        .iter()
+        .filter(|change| change.signal == "tb.a")
+        .map(|change| (change.time, change.value.clone()))
+        .collect();
+    assert_eq!(
+        changes,
+        [
+            (0.0, LogicValue::from_u128(0, 2)),
+            (2.5e-9, LogicValue::from_u128(1, 2)),
+            (3e-9, LogicValue::from_u128(2, 2)),
+        ]
+    );
+
+    // The last delay is rounded to the 100ps precision
+    assert_eq!(result.output, "passed\n");
+    assert_eq!(result.status, SimStatus::Finished);
+    assert_eq!(result.end_time, 4e-9);
+}
+
+#[test]
+fn delayed_continuous_assignments() {
+    let result = simulate(
+        "`timescale 1ns/1ns
+module tb ();
+reg a;
+wire y;
+assign #3 y = a;
+initial begin
+    a = 0;
+    #5 a = 1;
+    #1 a = 0;
+    #1 a = 1;
+    #10 a = 0;
+end
+endmodule
+",
+        100e-9,
+    );
+
+    let changes: Vec<(u64, LogicValue)> = result
+        .changes
+        .iter()
+        .filter(|change| change.signal == "tb.y")
+        .map(|change| ((change.time * 1e9).round() as u64, change.value.clone()))
+        .collect();
+
+    // Times in nanoseconds. The one nanosecond pulse at 6ns ends before the
+    // delay does, so never reaches `y`
+    assert_eq!(
+        changes,
+        [
+            (3, LogicValue::from_u128(0, 1)),
+            (8, LogicValue::from_u128(1, 1)),
+            (20, LogicValue::from_u128(0, 1)),
+        ]
+    );
+}
+
+#[test]
+fn system_calls_run_when_allowed() {
+    let object = parse_sv_file(
+        "module top;
+integer code;
+initial begin
+    $system(\"exit 0\");
+    code = $system(\"exit 3\");
+    $display(\"%0d %0d\", code, $system_time > 0);
+end
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("design parses");
+
+    let config = sv_sim::SimConfig {
+        allow_system_calls: true,
+        ..Default::default()
+    };
+    let design = object
+        .elaborate_with_config("top", &config)
+        .expect("design elaborates");
+    let result = design.run(NS);
+    assert_eq!(result.output, "3 1\n");
+    assert!(result.forbidden_calls.is_empty());
+    assert!(result.check_system_calls().is_ok());
+
+    // Calls are forbidden by default, so the exit code is unknown and the
+    // run fails
+    let design = object.elaborate("top").expect("design elaborates");
+    let result = design.run(NS);
+    assert_eq!(result.output, "x 1\n");
+    assert_eq!(result.forbidden_calls, ["exit 0", "exit 3"]);
+    let Err(SimError::SystemCallForbidden(cmd)) = result.check_system_calls() else {
+        panic!("forbidden calls fail the run");
+    };
+    assert_eq!(cmd, "exit 0");
+}
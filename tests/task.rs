@@ -0,0 +1,166 @@
+//! Task definition and call tests
+
+use sv_sim::logic::LogicStmt;
+use sv_sim::module::PortDirection;
+use sv_sim::parse_sv_file;
+use sv_sim::value::LogicValue;
+
+/// One nanosecond, the default time unit
+const NS: f64 = 1e-9;
+
+#[test]
+fn parses_task_definitions() {
+    let object = parse_sv_file(
+        "module top ();
+task automatic send(input [7:0] data, output ack, inout [3:0] count);
+    #1 count = count + 1;
+    ack = data[0];
+endtask
+task wait_cycles;
+    input integer n;
+    reg [7:0] i;
+    for (i = 0; i < n; i++) @(posedge clk);
+endtask : wait_cycles
+initial begin
+    send(8'hA5, done, total);
+    wait_cycles(2);
+    reset;
+end
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    let module = &object.mods["top"];
+
+    let send = &module.tasks[0];
+    assert_eq!(send.name, "send");
+    let args: Vec<(&str, PortDirection, u64)> = send
+        .args
+        .iter()
+        .map(|arg| (arg.var.name.as_str(), arg.direction, arg.var.width))
+        .collect();
+    assert_eq!(
+        args,
+        [
+            ("data", PortDirection::Input, 8),
+            ("ack", PortDirection::Output, 1),
+            ("count", PortDirection::Inout, 4),
+        ]
+    );
+    assert_eq!(send.body.len(), 2);
+
+    let wait_cycles = &module.tasks[1];
+    assert_eq!(wait_cycles.args[0].var.width, 32);
+    assert_eq!(wait_cycles.vars[0].name, "i");
+
+    let calls: Vec<(&str, usize)> = module.initial_blocks[0]
+        .body
+        .iter()
+        .map(|stmt| match stmt {
+            LogicStmt::TaskCall { name, args } => (name.as_str(), args.len()),
+            _ => panic!("unexpected statement: {:?}", stmt),
+        })
+        .collect();
+    assert_eq!(calls, [("send", 3), ("wait_cycles", 1), ("reset", 0)]);
+}
+
+#[test]
+fn calls_run_the_task_body() {
+    let object = parse_sv_file(
+        "module top ();
+reg [7:0] data;
+reg [7:0] sum;
+reg [3:0] count;
+reg odd;
+reg done;
+task add(input [7:0] a, input [7:0] b, output [7:0] result, inout [3:0] calls);
+    #2 result = a + b;
+    calls = calls + 1;
+endtask
+task check(input [7:0] value, output flag);
+    flag = 1'b0;
+    if (!value[0]) return;
+    flag = 1'b1;
+endtask
+initial begin
+    count = 0;
+    data = 8'd3;
+    add(data, 8'd4, sum, count);
+    check(sum, odd);
+    add(sum, sum, sum, count);
+    check(sum, done);
+end
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    let result = object.run(10.0 * NS);
+
+    for (signal, expected, width) in [
+        ("sum", 14, 8),
+        ("count", 2, 4),
+        ("odd", 1, 1),
+        ("done", 0, 1),
+    ] {
+        assert_eq!(
+            result.signals[&format!("top.{signal}")],
+            LogicValue::from_u128(expected, width),
+            "{signal}"
+        );
+    }
+
+    let sums: Vec<(f64, u128)> = result
+        .changes
+        .iter()
+        .filter(|change| change.signal == "top.sum")
+        .map(|change| (change.time, change.value.to_u128().unwrap()))
+        .collect();
+    assert_eq!(sums, [(2.0 * NS, 7), (4.0 * NS, 14)]);
+}
+
+#[test]
+fn arguments_left_out_take_their_defaults() {
+    let object = parse_sv_file(
+        "module top ();
+reg [7:0] base;
+reg [7:0] first;
+reg [7:0] second;
+reg [7:0] third;
+task add(output [7:0] result, input [7:0] a, input [7:0] b = 8'd10, c = base);
+    result = a + b + c;
+endtask
+initial begin
+    base = 8'd100;
+    add(first, 8'd1);
+    add(second, 8'd1, 8'd2);
+    add(third, 8'd1, 8'd2, 8'd3);
+end
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    let task = &object.mods["top"].tasks[0];
+    let defaults: Vec<bool> = task.args.iter().map(|arg| arg.default.is_some()).collect();
+    assert_eq!(defaults, [false, false, true, true]);
+
+    let result = object.run(NS);
+    for (signal, expected) in [("first", 111), ("second", 103), ("third", 6)] {
+        assert_eq!(
+            result.signals[&format!("top.{signal}")],
+            LogicValue::from_u128(expected, 8),
+            "{signal}"
+        );
+    }
+
+    // Only task arguments may have defaults
+    let source = "module top ();
+function [7:0] inc(input [7:0] a, input [7:0] by = 1);
+    return a + by;
+endfunction
+endmodule
+";
+    assert!(parse_sv_file(source.to_owned()).is_err());
+}
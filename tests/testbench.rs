@@ -0,0 +1,162 @@
+//! Annotated testbench tests
+
+use sv_sim::output::junit::junit_string;
+use sv_sim::sim::SimStatus;
+use sv_sim::{parse_sv_file, SimConfig, SimError};
+
+/// Counter with a testbench module holding several annotated tests
+const DESIGN: &str = "`timescale 1ns/1ns
+module counter (input wire clk, input wire rst, output reg [3:0] count);
+always_ff @(posedge clk) begin
+    if (rst)
+        count <= 0;
+    else
+        count <= count + 1;
+end
+endmodule
+
+module tb ();
+reg clk;
+reg rst;
+wire [3:0] count;
+counter u_counter (.clk(clk), .rst(rst), .count(count));
+initial begin
+    clk = 0;
+    forever #5 clk = ~clk;
+end
+initial begin // @testbench counts
+    rst = 1;
+    #10 rst = 0;
+    #40 if (count == 4) $finish;
+end
+initial begin : overflow
+    /* @testbench */
+    rst = 1;
+    #10 rst = 0;
+    #40 if (count == 5) $finish;
+end
+initial
+    // @testbench stops
+    #1 $stop;
+initial begin
+    // Not a test
+    $display(\"setup\");
+end
+endmodule
+";
+
+#[test]
+fn runs_annotated_initial_blocks() {
+    let object = parse_sv_file(DESIGN.to_owned()).expect("design parses");
+    let design = object.elaborate("tb").expect("design elaborates");
+
+    let tests: Vec<Option<&str>> = design.mods["tb"]
+        .initial_blocks
+        .iter()
+        .map(|block| block.testbench.as_deref())
+        .collect();
+    assert_eq!(tests, [None, Some("counts"), Some(""), Some("stops"), None]);
+
+    let config = SimConfig {
+        test_timeout: Some(200e-9),
+        ..Default::default()
+    };
+    let results = design.run_testbench(&config);
+
+    let outcomes: Vec<(&str, bool)> = results
+        .iter()
+        .map(|result| (result.name.as_str(), result.passed))
+        .collect();
+    assert_eq!(
+        outcomes,
+        [("tb.counts", true), ("tb.test2", false), ("tb.stops", false)]
+    );
+    assert!(results[0].failure.is_none());
+    assert!(matches!(
+        results[1].failure,
+        Some(SimError::Unfinished(SimStatus::EndTime))
+    ));
+    assert!(matches!(
+        results[2].failure,
+        Some(SimError::Unfinished(SimStatus::Stopped))
+    ));
+
+    let report = junit_string(&results, "tb").expect("report is produced");
+    assert!(report.contains("<testsuite name=\"tb\" tests=\"3\" failures=\"2\""));
+    assert!(report.contains("<testcase name=\"tb.counts\" classname=\"tb\""));
+    assert!(report.contains(
+        "<failure message=\"test ended because $stop was called rather than by $finish\"/>"
+    ));
+}
+
+#[test]
+fn runs_tests_once_per_instance() {
+    let object = parse_sv_file(
+        "`timescale 1ns/1ns
+module checker #(parameter VALUE = 0) ();
+initial begin // @testbench value
+    #1 if (VALUE == 1) $finish;
+end
+endmodule
+
+module tb ();
+reg clk;
+checker #(.VALUE(1)) u_pass ();
+checker #(.VALUE(2)) u_fail ();
+initial begin
+    clk = 0;
+    forever #500 clk = ~clk;
+end
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("design parses");
+    let design = object.elaborate("tb").expect("design elaborates");
+
+    // Without a timeout the clock keeps the failing test running until
+    // the default one
+    let results = design.run_testbench(&SimConfig::default());
+
+    let outcomes: Vec<(&str, bool)> = results
+        .iter()
+        .map(|result| (result.name.as_str(), result.passed))
+        .collect();
+    assert_eq!(
+        outcomes,
+        [("tb.u_pass.value", true), ("tb.u_fail.value", false)]
+    );
+    assert!(matches!(
+        results[1].failure,
+        Some(SimError::Unfinished(SimStatus::EndTime))
+    ));
+}
+
+#[test]
+fn forbidden_system_calls_fail_tests() {
+    let object = parse_sv_file(
+        "module tb ();
+initial begin // @testbench model
+    $system(\"exit 0\");
+    $finish;
+end
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("design parses");
+    let design = object.elaborate("tb").expect("design elaborates");
+
+    let results = design.run_testbench(&SimConfig::default());
+    assert!(!results[0].passed);
+    let Some(SimError::SystemCallForbidden(cmd)) = &results[0].failure else {
+        panic!("test fails with a forbidden call");
+    };
+    assert_eq!(cmd, "exit 0");
+
+    let config = SimConfig {
+        allow_system_calls: true,
+        ..Default::default()
+    };
+    assert!(design.run_testbench(&config)[0].passed);
+}
@@ -0,0 +1,79 @@
+//! Four-state value tests
+
+use std::collections::HashMap;
+use sv_sim::parse_sv_file;
+use sv_sim::value::{LogicState, LogicValue};
+
+const STATES: [LogicState; 4] = [
+    LogicState::Zero,
+    LogicState::One,
+    LogicState::X,
+    LogicState::Z,
+];
+
+/// Applies `op` to every pair of states, in the order of `STATES`
+fn truth_table(op: fn(LogicState, LogicState) -> LogicState) -> Vec<Vec<LogicState>> {
+    STATES
+        .iter()
+        .map(|&lhs| STATES.iter().map(|&rhs| op(lhs, rhs)).collect())
+        .collect()
+}
+
+/// Row of states from a string such as `"01xx"`
+fn row(states: &str) -> Vec<LogicState> {
+    states
+        .chars()
+        .map(|c| match c {
+            '0' => LogicState::Zero,
+            '1' => LogicState::One,
+            'x' => LogicState::X,
+            _ => LogicState::Z,
+        })
+        .collect()
+}
+
+#[test]
+fn four_state_truth_tables() {
+    assert_eq!(
+        truth_table(|a, b| a & b),
+        [row("0000"), row("01xx"), row("0xxx"), row("0xxx")]
+    );
+    assert_eq!(
+        truth_table(|a, b| a | b),
+        [row("01xx"), row("1111"), row("x1xx"), row("x1xx")]
+    );
+    assert_eq!(
+        truth_table(|a, b| a ^ b),
+        [row("01xx"), row("10xx"), row("xxxx"), row("xxxx")]
+    );
+
+    let inverted: Vec<LogicState> = STATES.iter().map(|&state| !state).collect();
+    assert_eq!(inverted, row("10xx"));
+}
+
+#[test]
+fn unknowns_propagate_through_combinational_logic() {
+    let object = parse_sv_file(
+        "module gates (input wire [3:0] a, input wire [3:0] b, output wire [3:0] and_out, output wire [3:0] or_out, output wire [3:0] xor_out);
+assign and_out = a & b;
+assign or_out = a | b;
+assign xor_out = a ^ b;
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    let module = object.mods.into_values().next().expect("source holds a module");
+
+    // `b` is 0, 1, X and Z from its most significant bit down
+    let inputs = HashMap::from([
+        ("a".to_owned(), LogicValue::from_u128(0b1010, 4)),
+        ("b".to_owned(), LogicValue::from_bits(row("01xz"))),
+    ]);
+    let outputs = module.evaluate_comb(&inputs);
+
+    assert_eq!(outputs["and_out"].bits(), row("00x0"));
+    assert_eq!(outputs["or_out"].bits(), row("111x"));
+    assert_eq!(outputs["xor_out"].bits(), row("11xx"));
+    assert!(LogicValue::from_bits(row("zzzz")).is_hi_z());
+}
@@ -0,0 +1,272 @@
+//! VCD output tests
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use sv_sim::output::vcd::{write_dump, VcdWriter};
+use sv_sim::parse_sv_file;
+use sv_sim::sim::{Dump, SignalChange};
+use sv_sim::sim_time::SimTime;
+use sv_sim::value::LogicValue;
+
+/// Simulates `source` for `end_time` seconds and returns the VCD dump
+fn dump(source: &str, end_time: f64) -> String {
+    let object = parse_sv_file(source.to_owned()).expect("source parses");
+    let result = object.run(end_time);
+
+    let mut writer = VcdWriter::new(Vec::new(), &result.sim_time);
+    writer.write_result(&result).expect("dump is written");
+
+    String::from_utf8(writer.into_inner()).expect("dump is UTF-8")
+}
+
+#[test]
+fn dumps_declarations_and_changes() {
+    let vcd = dump(
+        "`timescale 1ns/100ps
+module top ();
+parameter WIDTH = 4;
+reg clk;
+reg [3:0] count;
+initial begin
+    clk = 0;
+    count = 4'd9;
+    #1 clk = 1;
+    count = 4'd10;
+    #2 clk = 0;
+end
+endmodule
+",
+        5e-9,
+    );
+
+    let expected = format!(
+        "$version sv-sim {} $end
+$timescale 100ps $end
+$scope module top $end
+$var wire 32 ! WIDTH $end
+$var wire 1 \" clk $end
+$var wire 4 # count $end
+$upscope $end
+$enddefinitions $end
+#0
+$dumpvars
+b00000000000000000000000000000100 !
+x\"
+bxxxx #
+$end
+0\"
+b1001 #
+#10
+1\"
+b1010 #
+#30
+0\"
+",
+        env!("CARGO_PKG_VERSION")
+    );
+    assert_eq!(vcd, expected);
+}
+
+#[test]
+fn dumps_the_value_each_time_step_settles_on() {
+    let vcd = dump(
+        "module top ();
+reg a;
+reg b;
+wire c;
+assign c = a ^ b;
+initial begin
+    a = 0;
+    b = 0;
+    #1 a = 1;
+    a = 0;
+    #1 a = 1;
+    b = 1;
+end
+endmodule
+",
+        3e-9,
+    );
+
+    // The pulse on `a` at 1ns and the one `c` would see at 2ns both begin
+    // and end within their time step
+    let body: Vec<&str> = vcd.lines().skip(8).collect();
+    assert_eq!(
+        body,
+        [
+            "#0",
+            "$dumpvars",
+            "x!",
+            "x\"",
+            "x#",
+            "$end",
+            "0!",
+            "0\"",
+            "0#",
+            "#2000",
+            "1!",
+            "1\"",
+        ]
+    );
+}
+
+#[test]
+fn nests_hierarchical_scopes() {
+    let signals: BTreeMap<String, LogicValue> = ["top.u_add.sum", "top.clk", "top.u_add.a"]
+        .into_iter()
+        .map(|name| (name.to_owned(), LogicValue::from_u128(0, 1)))
+        .collect();
+
+    let mut writer = VcdWriter::new(Vec::new(), &SimTime::default());
+    writer.write_header(&signals).expect("header is written");
+    writer
+        .write_change(&SignalChange {
+            time: 2e-9,
+            signal: "top.u_add.sum".to_owned(),
+            value: LogicValue::from_u128(1, 1),
+        })
+        .expect("change is written");
+    let vcd = String::from_utf8(writer.into_inner()).expect("dump is UTF-8");
+
+    let body: Vec<&str> = vcd.lines().skip(2).collect();
+    assert_eq!(
+        body,
+        [
+            "$scope module top $end",
+            "$var wire 1 ! clk $end",
+            "$scope module u_add $end",
+            "$var wire 1 \" a $end",
+            "$var wire 1 # sum $end",
+            "$upscope $end",
+            "$upscope $end",
+            "$enddefinitions $end",
+            "#0",
+            "$dumpvars",
+            "0!",
+            "0\"",
+            "0#",
+            "$end",
+            "#2000",
+            "1#",
+        ]
+    );
+}
+
+#[test]
+fn dumps_requested_scopes() {
+    let object = parse_sv_file(
+        "module top ();
+reg clk;
+reg [1:0] count;
+initial begin
+    clk = 0;
+    count = 0;
+    #2 count = 1;
+    $dumpfile(\"waves.vcd\");
+    $dumpvars(1, top);
+    #1 clk = 1;
+    count = 2;
+end
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    let result = object.run(10e-9);
+
+    let dump = result.dump.clone().expect("a dump was requested");
+    assert_eq!(dump.file, PathBuf::from("waves.vcd"));
+    assert_eq!(dump.scopes, [("top".to_owned(), 1)]);
+    assert_eq!(dump.start, 2e-9);
+    assert!(dump.includes("top.clk"));
+    assert!(!dump.includes("top.u_sub.clk"));
+    assert!(!dump.includes("topper.clk"));
+
+    let dir = std::env::temp_dir().join(format!("sv_sim_dump_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("scratch directory is created");
+    let dump = Dump {
+        file: dir.join("waves.vcd"),
+        ..dump
+    };
+    write_dump(&result, &dump).expect("dump is written");
+    let vcd = std::fs::read_to_string(&dump.file).expect("dump is read");
+    std::fs::remove_dir_all(dir).expect("scratch directory is removed");
+
+    let body: Vec<&str> = vcd.lines().skip(7).collect();
+    assert_eq!(
+        body,
+        [
+            "#0",
+            "$dumpvars",
+            "x!",
+            "bxx \"",
+            "$end",
+            "#2000",
+            "0!",
+            "b01 \"",
+            "#3000",
+            "1!",
+            "b10 \"",
+        ]
+    );
+}
+
+#[test]
+fn dumpoff_pauses_the_dump() {
+    let object = parse_sv_file(
+        "module top ();
+reg [1:0] count;
+initial begin
+    count = 0;
+    $dumpoff;
+    $dumpvars;
+    #1 count = 1;
+    #1 $dumpoff;
+    count = 2;
+    $dumpoff;
+    #1 count = 3;
+    #1 $dumpon;
+    #1 count = 0;
+end
+endmodule
+"
+        .to_owned(),
+    )
+    .expect("source parses");
+    let result = object.run(10e-9);
+
+    let dump = result.dump.clone().expect("a dump was requested");
+    assert_eq!(dump.pauses, [(2e-9, 4e-9)]);
+
+    let dir = std::env::temp_dir().join(format!("sv_sim_dumpoff_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("scratch directory is created");
+    let dump = Dump {
+        file: dir.join("waves.vcd"),
+        ..dump
+    };
+    write_dump(&result, &dump).expect("dump is written");
+    let vcd = std::fs::read_to_string(&dump.file).expect("dump is read");
+    std::fs::remove_dir_all(dir).expect("scratch directory is removed");
+
+    // Count is X while the dump is off, then back to the value it changed
+    // to in the meantime
+    let body: Vec<&str> = vcd.lines().skip(6).collect();
+    assert_eq!(
+        body,
+        [
+            "#0",
+            "$dumpvars",
+            "bxx !",
+            "$end",
+            "b00 !",
+            "#1000",
+            "b01 !",
+            "#2000",
+            "bxx !",
+            "#4000",
+            "b11 !",
+            "#5000",
+            "b00 !",
+        ]
+    );
+}